@@ -49,6 +49,20 @@ pub fn draw_elements(draw_mode: types::GLenum, index_cnt: usize, draw_type: type
     }
 }
 
+pub fn draw_elements_instanced(draw_mode: types::GLenum, index_cnt: usize, draw_type: types::GLenum, instancecount: usize) {
+    unsafe {
+        let offset = ptr::null() as *const _;
+        DrawElementsInstanced(draw_mode, index_cnt as i32, draw_type, offset, instancecount as i32);
+    }
+}
+
+// offset is in bytes into the bound element array buffer
+pub fn draw_elements_with_offset(draw_mode: types::GLenum, index_cnt: usize, draw_type: types::GLenum, offset: usize) {
+    unsafe {
+        DrawElements(draw_mode, index_cnt as i32, draw_type, offset as *const _);
+    }
+}
+
 pub fn enable(capability: types::GLenum) {
     unsafe {
         Enable(capability);
@@ -73,6 +87,12 @@ pub fn blend_func(sfactor: types::GLenum, dfactor: types::GLenum) {
     }
 }
 
+pub fn depth_func(func: types::GLenum) {
+    unsafe {
+        DepthFunc(func);
+    }
+}
+
 ///////////
 // gl 1.3
 ///////////
@@ -260,6 +280,13 @@ pub fn vertex_attrib_divisor(attrib_index: u32, divisor: u32) {
     }
 }
 
+// the value a shader reads for the attribute while its array is disabled
+pub fn vertex_attrib_4f(attrib_index: u32, x: f32, y: f32, z: f32, w: f32) {
+    unsafe {
+        VertexAttrib4f(attrib_index, x, y, z, w);
+    }
+}
+
 pub fn enable_vertex_attrib_array(attribute_id: u32) {
     unsafe {
         EnableVertexAttribArray(attribute_id);
@@ -421,12 +448,26 @@ pub fn bind_texture(type_: types::GLenum, texture_id: u32) {
     }
 }
 
+// attach the data store of a buffer object to the bound buffer texture
+pub fn tex_buffer(internal_format: types::GLenum, buffer_id: u32) {
+    unsafe {
+        TexBuffer(TEXTURE_BUFFER, internal_format, buffer_id);
+    }
+}
+
 pub fn tex_image_2d<T>(type_: types::GLenum, level_of_detail: i32, format: types::GLenum, width: usize, height: usize, pixel_format: types::GLenum, data: &[T]) {
     unsafe {
         TexImage2D(type_, level_of_detail, format as i32, width as i32, height as i32, 0, format, pixel_format, data.as_ptr() as *const _);
     }
 }
 
+// same as tex_image_2d but lets the storage format differ from the format of the data we pass in (for example srgb or half float storage)
+pub fn tex_image_2d_with_internal_format<T>(type_: types::GLenum, level_of_detail: i32, format: types::GLenum, internal_format: types::GLenum, width: usize, height: usize, pixel_format: types::GLenum, data: &[T]) {
+    unsafe {
+        TexImage2D(type_, level_of_detail, internal_format as i32, width as i32, height as i32, 0, format, pixel_format, data.as_ptr() as *const _);
+    }
+}
+
 // use this to allocate memory of width * height that you can later initialize with a subtexture such as from a frame buffer attachment
 pub fn tex_image_2d_uninitialized(type_: types::GLenum, level_of_detail: i32, format: types::GLenum, internal_format: types::GLenum, width: usize, height: usize, pixel_format: types::GLenum) {
     unsafe {
@@ -434,6 +475,27 @@ pub fn tex_image_2d_uninitialized(type_: types::GLenum, level_of_detail: i32, fo
     }
 }
 
+// a 3d texture filled in one go, like the lookup tables of the color grading
+pub fn tex_image_3d<T>(type_: types::GLenum, level_of_detail: i32, format: types::GLenum, internal_format: types::GLenum, width: usize, height: usize, depth: usize, pixel_format: types::GLenum, data: &[T]) {
+    unsafe {
+        TexImage3D(type_, level_of_detail, internal_format as i32, width as i32, height as i32, depth as i32, 0, format, pixel_format, data.as_ptr() as *const _);
+    }
+}
+
+// immutable storage for all the layers of a texture array, needed if we want to make texture views of it later
+pub fn tex_storage_3d(type_: types::GLenum, levels: i32, internal_format: types::GLenum, width: usize, height: usize, depth: usize) {
+    unsafe {
+        TexStorage3D(type_, levels, internal_format, width as i32, height as i32, depth as i32);
+    }
+}
+
+// immutable storage with all the mip levels of a 2d texture or of every face of a cube map
+pub fn tex_storage_2d(type_: types::GLenum, levels: i32, internal_format: types::GLenum, width: usize, height: usize) {
+    unsafe {
+        TexStorage2D(type_, levels, internal_format, width as i32, height as i32);
+    }
+}
+
 pub fn tex_parameter_iv(target: types::GLenum, pname: types::GLenum, value: u32) {
     unsafe {        
         TexParameteriv(target, pname, &(value as i32) as *const i32);
@@ -606,6 +668,13 @@ pub fn delete_renderbuffer(render_buffer_id: u32) {
     }
 }
 
+// attach one mip level of a 2d texture or of a single cube map face (tex_target is then the face)
+pub fn framebuffer_texture_2d(target: types::GLenum, attachment: types::GLenum, tex_target: types::GLenum, texture: u32, level: i32) {
+    unsafe {
+        FramebufferTexture2D(target, attachment, tex_target, texture, level);
+    }
+}
+
 ///////////
 // gl 3.2
 ///////////
@@ -615,6 +684,23 @@ pub fn framebuffer_texture(target: types::GLenum, attachment: types::GLenum, tex
     }
 }
 
+// attach a single layer of a texture array or 3d texture
+pub fn framebuffer_texture_layer(target: types::GLenum, attachment: types::GLenum, texture: u32, level: i32, layer: usize) {
+    unsafe {
+        FramebufferTextureLayer(target, attachment, texture, level, layer as i32);
+    }
+}
+
+///////////
+// gl 4.3
+///////////
+// makes view_id share the storage of orig_texture, for example to look at one layer of a texture array as a plain 2d texture
+pub fn texture_view(view_id: u32, target: types::GLenum, orig_texture: u32, internal_format: types::GLenum, min_level: u32, num_levels: u32, min_layer: u32, num_layers: u32) {
+    unsafe {
+        TextureView(view_id, target, orig_texture, internal_format, min_level, num_levels, min_layer, num_layers);
+    }
+}
+
 pub mod helper {
     use super::*;
 
@@ -5,16 +5,39 @@ const vec2 light_bias = vec2(0.7, 0.6);
 
 in vec2 pass_tex_coords;
 in vec3 pass_normal;
+in vec3 pass_world_position;
+in vec3 pass_eye_position;
 
 out vec4 out_color;
 
 uniform sampler2D diffuse_map;
-uniform vec3 light_direction;
+
+#include "cascadedShadows.glsl"
+#include "lightData.glsl"
+#include "lightClusters.glsl"
+#include "pointShadows.glsl"
 
 void main(void) {
     vec4 color = texture(diffuse_map, pass_tex_coords);
     vec3 normal = normalize(pass_normal);
 
-    float brightness = max(0.0, dot(-light_direction, normal)) * light_bias.x + light_bias.y;
-    out_color = color * brightness;
+    // same bias as the entities, the animated models are just as prone to acne
+    float light_factor = 1.0 - shadow_amount(pass_world_position, -pass_eye_position.z, 0.01);
+
+    vec3 total_diffuse = vec3(0.0);
+    uvec2 cluster = find_cluster(pass_eye_position);
+    for (uint i=0u; i<cluster.y; i++) {
+        int light = cluster_light(cluster, i);
+        vec3 light_color;
+        vec3 to_light = fetch_light(light, pass_world_position, light_color);
+        if (light == shadow_light) {
+            light_color *= light_factor;
+        } else {
+            light_color *= 1.0 - point_shadow(light, to_light);
+        }
+        total_diffuse += max(0.0, dot(normalize(to_light), normal)) * light_color;
+    }
+
+    vec3 brightness = total_diffuse * light_bias.x + light_bias.y;
+    out_color = color * vec4(brightness, 1.0);
 }
\ No newline at end of file
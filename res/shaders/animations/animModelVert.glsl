@@ -11,9 +11,13 @@ in vec4 in_joint_weights;
 
 out vec2 pass_tex_coords;
 out vec3 pass_normal;
+out vec3 pass_world_position;
+out vec3 pass_eye_position;
 
 uniform mat4 joint_transforms[MAX_JOINTS];
 uniform mat4 projection_view_model;
+uniform mat4 transform;
+uniform mat4 view_matrix;
 
 void main(void) {
     vec4 total_pos = vec4(0);
@@ -29,6 +33,10 @@ void main(void) {
         total_normal += norm_c * in_joint_weights[i];
     }    
     gl_Position = projection_view_model * total_pos;
-    pass_normal = total_normal.xyz;
+    // the lights are in world space
+    vec4 world_position = transform * total_pos;
+    pass_world_position = world_position.xyz;
+    pass_eye_position = (view_matrix * world_position).xyz;
+    pass_normal = (transform * vec4(total_normal.xyz, 0.0)).xyz;
     pass_tex_coords = in_tex_coords;
 }
\ No newline at end of file
@@ -0,0 +1,81 @@
+#version 400 core
+
+// rgba
+layout(location = 0) out vec4 out_Color;
+layout(location = 1) out vec4 out_brightness_Color;
+
+// the g-buffer, see renderers/deferred_renderer.rs for what is in every attachment
+uniform sampler2D albedo_map;
+uniform sampler2D normal_map;
+uniform sampler2D specular_map;
+uniform sampler2D emissive_map;
+uniform sampler2D depth_map;
+
+uniform mat4 inverse_view_projection;
+uniform mat4 view_matrix;
+uniform vec3 camera_position;
+
+// turns the depth of the pixel back into the world position it was written from
+vec3 world_position_at(ivec2 pixel, float depth) {
+    vec2 ndc = (vec2(pixel) + 0.5) / vec2(textureSize(depth_map, 0)) * 2.0 - 1.0;
+    vec4 world_position = inverse_view_projection * vec4(ndc, depth * 2.0 - 1.0, 1.0);
+    return world_position.xyz / world_position.w;
+}
+
+// the lights that reach everything (the sun) are done here for every pixel, the others get a light volume each
+const int MAX_GLOBAL_LIGHTS = 8;
+uniform int global_lights[MAX_GLOBAL_LIGHTS];
+uniform int global_light_count;
+
+#include "cascadedShadows.glsl"
+#include "lightData.glsl"
+
+// index of the light the shadow map belongs to, -1 if there is none
+uniform int shadow_light;
+
+void main(void) {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(depth_map, pixel, 0).r;
+    if (depth == 1.0) {
+        discard; // nothing was drawn here, the skybox fills it in afterwards
+    }
+    // the forward passes after us (skybox, water, particles) depth test against the g-buffer geometry
+    gl_FragDepth = depth;
+
+    vec3 albedo = texelFetch(albedo_map, pixel, 0).rgb;
+    // reflectivity, shine damper, glow flag, shadow bias
+    vec4 specular = texelFetch(specular_map, pixel, 0);
+
+    vec3 world_position = world_position_at(pixel, depth);
+    vec3 unit_normal = normalize(texelFetch(normal_map, pixel, 0).xyz);
+    vec3 unit_camera = normalize(camera_position - world_position);
+    vec3 emissive = texelFetch(emissive_map, pixel, 0).rgb;
+    vec3 eye_position = (view_matrix * vec4(world_position, 1.0)).xyz;
+    float light_factor = 1.0 - shadow_amount(world_position, -eye_position.z, specular.w);
+
+    vec3 total_diffuse = vec3(0.0);
+    vec3 total_specular = vec3(0.0);
+    for (int i = 0; i < global_light_count; i++) {
+        int light = global_lights[i];
+        vec3 light_color;
+        vec3 light_direction = fetch_light(light, world_position, light_color);
+        if (light == shadow_light) {
+            light_color *= light_factor;
+        }
+
+        float brightness = max(dot(unit_normal, normalize(light_direction)), 0.0);
+        vec3 unit_specular_reflection = normalize(reflect(-light_direction, unit_normal));
+        float spec_brightness = max(dot(unit_camera, unit_specular_reflection), 0.0);
+
+        total_diffuse += brightness * light_color;
+        total_specular += pow(spec_brightness, specular.y) * specular.x * light_color;
+    }
+    // the ambient floor of the forward shaders, unlike there the light volumes add on top of it
+    total_diffuse = max(total_diffuse, 0.2);
+
+    // glowing pixels get their full color from the emissive attachment instead of the lights
+    vec3 light_based_out_color = albedo * total_diffuse * (1.0 - specular.z) + emissive + total_specular;
+    out_Color = vec4(light_based_out_color, 1.0);
+    // make pixels brightness black unless they glow
+    out_brightness_Color = specular.z * vec4(light_based_out_color, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,47 @@
+#version 400 core
+
+in vec2 pass_tex_coord;
+in vec3 surface_normal;
+
+// the g-buffer, see renderers/deferred_renderer.rs for what goes where
+layout(location = 0) out vec4 out_albedo;
+layout(location = 1) out vec4 out_normal;
+layout(location = 2) out vec4 out_specular;
+layout(location = 3) out vec4 out_emissive;
+
+uniform sampler2D texture_sampler;
+uniform sampler2D extra_info_map;
+
+// specular lighting
+uniform float shine_damper;
+uniform float reflectivity;
+// for turning off/on extra info
+uniform float has_extra_info;
+
+#include "weather.glsl"
+
+// same aggressive shadow bias as the forward entity shader
+const float shadow_bias = 0.01;
+
+void main(void) {
+    vec4 texture_color = texture(texture_sampler, pass_tex_coord);
+    if (texture_color.a < 0.5) {
+        discard; // do not render transparency (hack)
+    }
+    // wet surfaces are darker and shinier
+    float surface_reflectivity = reflectivity;
+    float surface_shine_damper = shine_damper;
+    apply_wetness(texture_color.rgb, surface_reflectivity, surface_shine_damper);
+    float shininess_fac = 1.0;
+    float glow_fac = 0.0;
+    if (has_extra_info > 0.5) {
+        vec4 extra_info = texture(extra_info_map, pass_tex_coord);
+        shininess_fac = extra_info.r;
+        glow_fac = step(0.5, extra_info.g);
+    }
+
+    out_albedo = vec4(texture_color.rgb, 1.0);
+    out_normal = vec4(normalize(surface_normal), 1.0);
+    out_specular = vec4(surface_reflectivity * shininess_fac, surface_shine_damper, glow_fac, shadow_bias);
+    out_emissive = vec4(glow_fac * texture_color.rgb, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+#version 400 core
+
+// one triangle that covers the whole screen, made from gl_VertexID so there is no vertex buffer
+void main(void) {
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,59 @@
+#version 400 core
+
+// rgba
+layout(location = 0) out vec4 out_Color;
+layout(location = 1) out vec4 out_brightness_Color;
+
+// the g-buffer, see renderers/deferred_renderer.rs for what is in every attachment
+uniform sampler2D albedo_map;
+uniform sampler2D normal_map;
+uniform sampler2D specular_map;
+uniform sampler2D emissive_map;
+uniform sampler2D depth_map;
+
+uniform mat4 inverse_view_projection;
+uniform mat4 view_matrix;
+uniform vec3 camera_position;
+
+// turns the depth of the pixel back into the world position it was written from
+vec3 world_position_at(ivec2 pixel, float depth) {
+    vec2 ndc = (vec2(pixel) + 0.5) / vec2(textureSize(depth_map, 0)) * 2.0 - 1.0;
+    vec4 world_position = inverse_view_projection * vec4(ndc, depth * 2.0 - 1.0, 1.0);
+    return world_position.xyz / world_position.w;
+}
+
+#include "lightData.glsl"
+#include "pointShadows.glsl"
+
+// the light whose volume we are drawing
+uniform int volume_light;
+
+void main(void) {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(depth_map, pixel, 0).r;
+    if (depth == 1.0) {
+        discard; // nothing was drawn here, the skybox fills it in afterwards
+    }
+
+    vec3 albedo = texelFetch(albedo_map, pixel, 0).rgb;
+    // reflectivity, shine damper, glow flag, shadow bias
+    vec4 specular = texelFetch(specular_map, pixel, 0);
+
+    vec3 world_position = world_position_at(pixel, depth);
+    vec3 unit_normal = normalize(texelFetch(normal_map, pixel, 0).xyz);
+    vec3 unit_camera = normalize(camera_position - world_position);
+
+    vec3 light_color;
+    vec3 light_direction = fetch_light(volume_light, world_position, light_color);
+    light_color *= 1.0 - point_shadow(volume_light, light_direction);
+
+    float brightness = max(dot(unit_normal, normalize(light_direction)), 0.0);
+    vec3 unit_specular_reflection = normalize(reflect(-light_direction, unit_normal));
+    float spec_brightness = max(dot(unit_camera, unit_specular_reflection), 0.0);
+
+    // added on top of the full screen pass which already did the ambient and the glow
+    vec3 diffuse = albedo * brightness * light_color * (1.0 - specular.z);
+    vec3 specular_color = pow(spec_brightness, specular.y) * specular.x * light_color;
+    out_Color = vec4(diffuse + specular_color, 0.0);
+    out_brightness_Color = vec4(0.0);
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 400 core
+
+in vec3 pos;
+
+uniform mat4 view_projection;
+
+// the sphere around the origin gets scaled by the range of the light and moved to its position
+const int LIGHT_TEXELS = 4;
+uniform samplerBuffer light_data;
+uniform int volume_light;
+
+void main(void) {
+    vec3 light_position = texelFetch(light_data, LIGHT_TEXELS * volume_light).xyz;
+    float range = texelFetch(light_data, LIGHT_TEXELS * volume_light + 1).w;
+    gl_Position = view_projection * vec4(light_position + pos * range, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,42 @@
+#version 400 core
+
+in vec2 pass_tex_coord;
+in mat3 eye_to_tangent_space;
+
+// the g-buffer, see renderers/deferred_renderer.rs for what goes where
+layout(location = 0) out vec4 out_albedo;
+layout(location = 1) out vec4 out_normal;
+layout(location = 2) out vec4 out_specular;
+layout(location = 3) out vec4 out_emissive;
+
+uniform sampler2D texture_sampler;
+uniform sampler2D normal_map_sampler;
+uniform mat4 view_matrix;
+// specular lighting
+uniform float shine_damper;
+uniform float reflectivity;
+
+#include "weather.glsl"
+
+// same bias as the entity shader since these are the same kind of detailed models
+const float shadow_bias = 0.01;
+
+void main(void) {
+    vec4 texture_color = texture(texture_sampler, pass_tex_coord);
+    if (texture_color.a < 0.5) {
+        discard; // do not render transparency (hack)
+    }
+    // wet surfaces are darker and shinier
+    float surface_reflectivity = reflectivity;
+    float surface_shine_damper = shine_damper;
+    apply_wetness(texture_color.rgb, surface_reflectivity, surface_shine_damper);
+
+    vec3 normal_tgs = normalize((2.0 * texture(normal_map_sampler, pass_tex_coord) - 1.0).xyz);
+    // both matrices are orthonormal so their transposes take the normal back to eye space and from there to world space
+    vec3 normal_world = transpose(mat3(view_matrix)) * (transpose(eye_to_tangent_space) * normal_tgs);
+
+    out_albedo = vec4(texture_color.rgb, 1.0);
+    out_normal = vec4(normalize(normal_world), 1.0);
+    out_specular = vec4(surface_reflectivity, surface_shine_damper, 0.0, shadow_bias);
+    out_emissive = vec4(0.0);
+}
\ No newline at end of file
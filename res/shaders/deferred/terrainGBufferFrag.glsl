@@ -0,0 +1,49 @@
+#version 400 core
+
+in vec2 pass_tex_coord;
+in vec3 surface_normal;
+
+// the g-buffer, see renderers/deferred_renderer.rs for what goes where
+layout(location = 0) out vec4 out_albedo;
+layout(location = 1) out vec4 out_normal;
+layout(location = 2) out vec4 out_specular;
+layout(location = 3) out vec4 out_emissive;
+
+uniform sampler2D background_sampler;
+uniform sampler2D r_sampler;
+uniform sampler2D g_sampler;
+uniform sampler2D b_sampler;
+uniform sampler2D blend_map_sampler;
+
+uniform float shine_damper;
+uniform float reflectivity;
+
+#include "weather.glsl"
+
+// the terrain has no fine detail that shadows itself so it gets away with the smaller bias
+const float shadow_bias = 0.005;
+
+void main(void) {
+    // same blending as the forward terrain shader
+    vec4 blend_map_col = texture(blend_map_sampler, pass_tex_coord);
+    float background_coef = 1.0 - (blend_map_col.r + blend_map_col.g + blend_map_col.b);
+    vec2 tiled_coords = pass_tex_coord * 40.0;
+    vec4 background_col = texture(background_sampler, tiled_coords) * background_coef;
+    vec4 r_col = texture(r_sampler, tiled_coords) * blend_map_col.r;
+    vec4 g_col = texture(g_sampler, tiled_coords) * blend_map_col.g;
+    vec4 b_col = texture(b_sampler, tiled_coords) * blend_map_col.b;
+    vec4 blended_texture_color = background_col + r_col + g_col + b_col;
+
+    // wet ground is darker and shinier, the snow settles on top of it
+    float surface_reflectivity = reflectivity;
+    float surface_shine_damper = shine_damper;
+    apply_wetness(blended_texture_color.rgb, surface_reflectivity, surface_shine_damper);
+    float snow = snow_amount(surface_normal);
+    blended_texture_color.rgb = mix(blended_texture_color.rgb, snow_color, snow);
+    surface_reflectivity = mix(surface_reflectivity, snow_reflectivity, snow);
+
+    out_albedo = vec4(blended_texture_color.rgb, 1.0);
+    out_normal = vec4(normalize(surface_normal), 1.0);
+    out_specular = vec4(surface_reflectivity, surface_shine_damper, 0.0, shadow_bias);
+    out_emissive = vec4(0.0);
+}
\ No newline at end of file
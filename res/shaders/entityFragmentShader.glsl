@@ -1,43 +1,33 @@
 #version 400 core
 
-const int NUM_LIGHTS = 4;
-
 in vec2 pass_tex_coord;
 in vec3 surface_normal;
-in vec3 light_direction[NUM_LIGHTS];
+in vec3 pass_world_position;
+in vec3 pass_eye_position;
 // specular lighting stuff
 in vec3 to_camera_dir;
-// fog stuff
-in float visibility;
-in vec4 shadow_coords;
 
 // rgba
 layout(location = 0) out vec4 out_Color;
 layout(location = 1) out vec4 out_brightness_Color;
 
 uniform sampler2D texture_sampler;
-uniform sampler2D shadow_map;
 uniform sampler2D extra_info_map;
 
-uniform vec3 light_color[NUM_LIGHTS];
 // specular lighting
 uniform float shine_damper;
 uniform float reflectivity;
-// fog
-uniform vec3 sky_color;
-// point light attenuation
-uniform vec3 attenuation[NUM_LIGHTS];
 // for turning off/on extra info
 uniform float has_extra_info;
 
 const bool uses_cell_shading = false;
 const float brightness_levels = 3.0;
 
-// how many pixels to sample on each side of center pixel (so 2 means 3x3 box) 
-const int pcf_count = 2;
-// texture pixels we will be sampling
-const float texel_count = (pcf_count*2.0 + 1.0)*(pcf_count*2.0 + 1.0);
-uniform float shadow_map_size;
+#include "cascadedShadows.glsl"
+#include "lightData.glsl"
+#include "lightClusters.glsl"
+#include "pointShadows.glsl"
+#include "weather.glsl"
 
 void adjust_brightness(inout float diffuse_brightness, inout float specular_brightness) {
     if (!uses_cell_shading) {
@@ -50,28 +40,20 @@ void adjust_brightness(inout float diffuse_brightness, inout float specular_brig
 }
 
 void main(void) {
-    // size of a pixel in texture coords space
-    float texel_size = 1.0 / shadow_map_size;
-    float total_in_shadow = 0.0;
-
-    for (int x=-pcf_count; x<=pcf_count; x++) {
-        for (int y=-pcf_count; y <= pcf_count; y++) {
-            // compare depth with shadowmap depth to figure out if this piece of terrain is in shadow or not (absence of light due to something blocking it)
-            float obj_depth_nearest_light = texture(shadow_map, shadow_coords.xy + vec2(x, y) * texel_size).r;
-            // add slight offset to prevent shadow acne
-            // note that unlike in terrain shader here we use a very aggressive bias
-            // this due to self-shadow casting of complex objects causes a lot of acne
-            // a better approach would be to calculate more precise near/far planes like described in the msdn shadows article
-            total_in_shadow += step(obj_depth_nearest_light + 0.01, shadow_coords.z);
-        }
-    }
-    total_in_shadow /= texel_count;     
-    float light_factor = 1.0 - total_in_shadow*shadow_coords.w;    
+    // add slight offset to prevent shadow acne
+    // note that unlike in terrain shader here we use a very aggressive bias
+    // this due to self-shadow casting of complex objects causes a lot of acne
+    // a better approach would be to calculate more precise near/far planes like described in the msdn shadows article
+    float light_factor = 1.0 - shadow_amount(pass_world_position, -pass_eye_position.z, 0.01);
     
     vec4 texture_color = texture(texture_sampler, pass_tex_coord);
     if (texture_color.a < 0.5) {
         discard; // do not render transparency (hack)
     }    
+    // wet surfaces are darker and shinier
+    float surface_reflectivity = reflectivity;
+    float surface_shine_damper = shine_damper;
+    apply_wetness(texture_color.rgb, surface_reflectivity, surface_shine_damper);
     float shininess_fac = 1.0;
     float glow_fac = 0.0;
     if (has_extra_info > 0.5) {
@@ -87,15 +69,22 @@ void main(void) {
     vec3 total_diffuse = vec3(0.0);
     vec3 total_specular = vec3(0.0);
 
-    for (int i=0; i<NUM_LIGHTS; i++) {
-        float distance_to_light_point = length(light_direction[i]);
-        float attenuation_factor = attenuation[i].x + attenuation[i].y * distance_to_light_point + attenuation[i].z * distance_to_light_point * distance_to_light_point;
+    uvec2 cluster = find_cluster(pass_eye_position);
+    for (uint i=0u; i<cluster.y; i++) {
+        int light = cluster_light(cluster, i);
+        vec3 light_color;
+        vec3 light_direction = fetch_light(light, pass_world_position, light_color);
+        if (light == shadow_light) {
+            light_color *= light_factor;
+        } else {
+            light_color *= 1.0 - point_shadow(light, light_direction);
+        }
         
-        vec3 unit_light = normalize(light_direction[i]);    
+        vec3 unit_light = normalize(light_direction);    
         float dotNormToLight = dot(unit_normal, unit_light);
         float brightness = max(dotNormToLight, 0.0);
 
-        vec3 specular_reflection_dir = reflect(-light_direction[i], unit_normal);
+        vec3 specular_reflection_dir = reflect(-light_direction, unit_normal);
         vec3 unit_specular_reflection = normalize(specular_reflection_dir);
 
         float dotSpecToCamera = dot(unit_camera, unit_specular_reflection);
@@ -103,10 +92,10 @@ void main(void) {
 
         adjust_brightness(brightness, spec_brightness);
 
-        total_diffuse += (brightness * light_color[i]) / attenuation_factor;
-        total_specular = (pow(spec_brightness, shine_damper) * reflectivity * light_color[i]) / attenuation_factor;
+        total_diffuse += brightness * light_color;
+        total_specular += pow(spec_brightness, surface_shine_damper) * surface_reflectivity * light_color;
     }
-    total_diffuse = max(total_diffuse * light_factor, 0.2); // clamp to 0.2 so nothing totally dark -> ambient light
+    total_diffuse = max(total_diffuse, 0.2); // clamp to 0.2 so nothing totally dark -> ambient light
 
     // apply extra info factors
     // adjust shininess (lowering it) based on specular map
@@ -115,7 +104,7 @@ void main(void) {
     total_diffuse += step(0.5, glow_fac) * (vec3(1) - total_diffuse);
     
     vec4 light_based_out_color = vec4(total_diffuse, 1.0) * texture_color + vec4(total_specular, 1.0);
-    out_Color = mix(vec4(sky_color, 1.0), light_based_out_color, visibility);
+    out_Color = light_based_out_color;
     // make pixels brightness black unless in glow map
     out_brightness_Color = step(0.5, glow_fac) * (light_based_out_color);
 }
\ No newline at end of file
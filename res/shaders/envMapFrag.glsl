@@ -4,21 +4,59 @@ in vec2 pass_tex_coord;
 in vec3 pass_normal;
 in vec3 reflected_camera_pos;
 in vec3 refracted_camera_pos;
+in vec3 pass_world_position;
+in float pass_view_depth;
 
 out vec4 out_color;
 
 uniform sampler2D in_texture;
 uniform samplerCube env_map;
 
-const vec3 light_direction = normalize(vec3(0.3, -1.0, 0.7));
+// the cube maps the reflection probes captured of the scene, see entities/reflection_probe.rs
+uniform samplerCubeArray probe_maps;
+// cube of the probe closest to the entity, -1 when no probe reaches it and we reflect the sky
+uniform int probe_layer;
+uniform vec3 probe_position;
+// 1 when the probe has a box around the area it sees
+uniform float has_parallax_box;
+uniform vec3 parallax_box_min;
+uniform vec3 parallax_box_max;
+
+// direction of the light that casts the sun shadows (or a default one when there is none)
+uniform vec3 light_direction;
+// 1 when there is a shadow map for light_direction
+uniform float has_shadow_map;
 const float ambient = 0.3;
 
+#include "cascadedShadows.glsl"
+
+// the sky is infinitely far away but the probe saw walls, so we find where the ray hits the box and look at that from the probe
+vec3 parallax_corrected(vec3 direction) {
+    if (has_parallax_box < 0.5) {
+        return direction;
+    }
+    vec3 to_max = (parallax_box_max - pass_world_position) / direction;
+    vec3 to_min = (parallax_box_min - pass_world_position) / direction;
+    vec3 furthest = max(to_max, to_min);
+    float distance = min(min(furthest.x, furthest.y), furthest.z);
+    vec3 hit = pass_world_position + direction * distance;
+    return hit - probe_position;
+}
+
+vec4 environment(vec3 direction) {
+    if (probe_layer < 0) {
+        return texture(env_map, direction);
+    }
+    return texture(probe_maps, vec4(parallax_corrected(direction), probe_layer));
+}
+
 void main(void) {
-    float brightness = max(0.0, dot(-light_direction, normalize(pass_normal))) + ambient;
+    float light_factor = 1.0 - has_shadow_map * shadow_amount(pass_world_position, pass_view_depth, 0.01);
+    float brightness = max(0.0, dot(-light_direction, normalize(pass_normal))) * light_factor + ambient;
 
     vec4 obj_color = texture(in_texture, pass_tex_coord) * brightness;
-    vec4 reflected_color = texture(env_map, reflected_camera_pos);
-    vec4 refracted_color = texture(env_map, refracted_camera_pos);
+    vec4 reflected_color = environment(reflected_camera_pos);
+    vec4 refracted_color = environment(refracted_camera_pos);
     vec4 env_color = mix(reflected_color, refracted_color, 0.5);
 
     out_color = mix(obj_color, env_color, 0.9);
@@ -8,14 +8,20 @@ out vec2 pass_tex_coord;
 out vec3 pass_normal;
 out vec3 reflected_camera_pos;
 out vec3 refracted_camera_pos;
+out vec3 pass_world_position;
+out float pass_view_depth;
 
 uniform mat4 model_matrix;
 uniform mat4 vp_matrix;
+uniform mat4 view_matrix;
 uniform vec3 camera_position;
 
 void main(void) {
     vec4 world_pos = model_matrix * vec4(position, 1.0);
     gl_Position = vp_matrix * world_pos;
+    // the shadow cascades are picked by the view space depth
+    pass_world_position = world_pos.xyz;
+    pass_view_depth = -(view_matrix * world_pos).z;
 
     vec3 nnormal = normalize(normal);
     pass_normal = nnormal;
@@ -6,9 +6,11 @@ in vec2 tex_coord;
 out vec2 pass_tex_coord;
 
 uniform vec2 transform;
+// keeps the glyphs from stretching when the window aspect ratio differs from the one the layout was made for
+uniform vec2 scale;
 
 void main(void) {
-    gl_Position = vec4(position + transform, 0.0, 1.0);
+    gl_Position = vec4(position * scale + transform, 0.0, 1.0);
 
     pass_tex_coord = tex_coord;
 }
\ No newline at end of file
@@ -0,0 +1,11 @@
+#version 400 core
+
+out vec2 pass_position;
+
+// one triangle that covers the whole target, made from gl_VertexID so there is no vertex buffer
+// pass_position is the position on the face in [-1, 1] which the fragment shaders turn into a direction
+void main(void) {
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(position, 0.0, 1.0);
+    pass_position = position;
+}
\ No newline at end of file
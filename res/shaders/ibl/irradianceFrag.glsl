@@ -0,0 +1,50 @@
+#version 400 core
+
+in vec2 pass_position;
+
+out vec4 out_Color;
+
+uniform samplerCube environment_map;
+// which cube face we are rendering, in the order gl expects them (+x, -x, +y, -y, +z, -z)
+uniform int face;
+
+const float PI = 3.14159265359;
+// step in radians between the samples, both around the normal and away from it
+const float sample_delta = 0.05;
+// the result is tiny and blurry so a small mip of the source is plenty and doesn't alias
+const float source_size = 64.0;
+
+// the direction that a texel of the face looks at, the texture coordinates of the faces go the way the gl spec defines them
+vec3 face_direction(int face, vec2 position) {
+    float s = position.x;
+    float t = position.y;
+    if (face == 0) return vec3(1.0, -t, -s);
+    if (face == 1) return vec3(-1.0, -t, s);
+    if (face == 2) return vec3(s, 1.0, t);
+    if (face == 3) return vec3(s, -1.0, -t);
+    if (face == 4) return vec3(s, -t, 1.0);
+    return vec3(-s, -t, -1.0);
+}
+
+// cosine weighted sum of the light coming from the hemisphere around the normal, that is the diffuse light a surface with that normal gets
+void main(void) {
+    vec3 normal = normalize(face_direction(face, pass_position));
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    float source_lod = max(log2(float(textureSize(environment_map, 0).x) / source_size), 0.0);
+
+    vec3 irradiance = vec3(0.0);
+    float sample_count = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += sample_delta) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += sample_delta) {
+            vec3 tangent_sample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 sample_direction = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * normal;
+            // cos for the angle to the normal and sin because the rings get smaller towards the pole
+            irradiance += textureLod(environment_map, sample_direction, source_lod).rgb * cos(theta) * sin(theta);
+            sample_count++;
+        }
+    }
+    out_Color = vec4(PI * irradiance / sample_count, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,84 @@
+#version 400 core
+
+in vec2 pass_position;
+
+out vec4 out_Color;
+
+uniform samplerCube environment_map;
+uniform int face;
+// roughness of the mip level we are rendering
+uniform float roughness;
+
+const float PI = 3.14159265359;
+const uint SAMPLE_COUNT = 512u;
+
+vec3 face_direction(int face, vec2 position) {
+    float s = position.x;
+    float t = position.y;
+    if (face == 0) return vec3(1.0, -t, -s);
+    if (face == 1) return vec3(-1.0, -t, s);
+    if (face == 2) return vec3(s, 1.0, t);
+    if (face == 3) return vec3(s, -1.0, -t);
+    if (face == 4) return vec3(s, -t, 1.0);
+    return vec3(-s, -t, -1.0);
+}
+
+// low discrepancy points in [0, 1)^2 so few samples still cover the lobe evenly
+vec2 hammersley(uint i, uint n) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(n), float(bits) * 2.3283064365386963e-10);
+}
+
+// half vector around the normal distributed like the ggx lobe of the roughness
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 half_tangent = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * half_tangent.x + bitangent * half_tangent.y + normal * half_tangent.z);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+// the split sum approximation: we assume that the view direction is the normal and convolve the environment with the ggx lobe
+// every mip level of the result is one roughness
+void main(void) {
+    vec3 normal = normalize(face_direction(face, pass_position));
+    vec3 view = normal;
+    float face_size = float(textureSize(environment_map, 0).x);
+    // solid angle of one texel of the source
+    float texel_solid_angle = 4.0 * PI / (6.0 * face_size * face_size);
+
+    vec3 prefiltered = vec3(0.0);
+    float total_weight = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 half_vector = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+        vec3 light = normalize(2.0 * dot(view, half_vector) * half_vector - view);
+        float n_dot_l = dot(normal, light);
+        if (n_dot_l > 0.0) {
+            // samples with a low probability stand for a big part of the sphere so we read them from a blurrier mip, otherwise the bright spots of the sky turn into dots
+            float n_dot_h = max(dot(normal, half_vector), 0.0);
+            float pdf = distribution_ggx(n_dot_h, roughness) * 0.25 + 0.0001;
+            float sample_solid_angle = 1.0 / (float(SAMPLE_COUNT) * pdf + 0.0001);
+            float lod = roughness == 0.0 ? 0.0 : 0.5 * log2(sample_solid_angle / texel_solid_angle);
+            prefiltered += textureLod(environment_map, light, lod).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+    out_Color = vec4(prefiltered / total_weight, 1.0);
+}
\ No newline at end of file
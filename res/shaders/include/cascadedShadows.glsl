@@ -0,0 +1,55 @@
+// how many pixels to sample on each side of center pixel (so 2 means 3x3 box)
+// a shader can #define PCF_COUNT before it includes this for a different amount
+#ifndef PCF_COUNT
+#define PCF_COUNT 2
+#endif
+const int pcf_count = PCF_COUNT;
+// texture pixels we will be sampling
+const float texel_count = (pcf_count*2.0 + 1.0)*(pcf_count*2.0 + 1.0);
+uniform float shadow_map_size;
+
+// cascaded shadow map, see shadows/shadow_box.rs. MAX_CASCADES has to match the one there
+const int MAX_CASCADES = 4;
+uniform sampler2DArray shadow_map;
+uniform mat4 to_shadowmap_space[MAX_CASCADES];
+// distance from the camera where every cascade ends
+uniform float cascade_splits[MAX_CASCADES];
+uniform int cascade_count;
+// part of every cascade at its far end where we blend into the next cascade (or out to no shadow for the last one)
+const float cascade_blend = 0.1;
+
+// fraction of the pcf samples of the cascade's layer that are in shadow
+float cascade_shadow(int cascade, vec3 world_position, float bias) {
+    vec4 shadow_coords = to_shadowmap_space[cascade] * vec4(world_position, 1.0);
+    // size of a pixel in texture coords space
+    float texel_size = 1.0 / shadow_map_size;
+    float total_in_shadow = 0.0;
+    for (int x=-pcf_count; x<=pcf_count; x++) {
+        for (int y=-pcf_count; y <= pcf_count; y++) {
+            // compare depth with shadowmap depth to figure out if this fragment is in shadow or not (absence of light due to something blocking it)
+            float obj_depth_nearest_light = texture(shadow_map, vec3(shadow_coords.xy + vec2(x, y) * texel_size, cascade)).r;
+            total_in_shadow += step(obj_depth_nearest_light + bias, shadow_coords.z);
+        }
+    }
+    return total_in_shadow / texel_count;
+}
+
+// picks the cascade by the distance from the camera and blends with the next one close to the split
+float shadow_amount(vec3 world_position, float view_depth, float bias) {
+    int cascade = 0;
+    while (cascade < cascade_count - 1 && view_depth > cascade_splits[cascade]) {
+        cascade++;
+    }
+    if (cascade_count == 0 || view_depth > cascade_splits[cascade]) {
+        return 0.0;
+    }
+    float cascade_start = cascade == 0 ? 0.0 : cascade_splits[cascade - 1];
+    float blend_start = mix(cascade_splits[cascade], cascade_start, cascade_blend);
+    float blend = clamp((view_depth - blend_start) / (cascade_splits[cascade] - blend_start), 0.0, 1.0);
+    float in_shadow = cascade_shadow(cascade, world_position, bias);
+    if (blend > 0.0) {
+        float next_in_shadow = cascade + 1 < cascade_count ? cascade_shadow(cascade + 1, world_position, bias) : 0.0;
+        in_shadow = mix(in_shadow, next_in_shadow, blend);
+    }
+    return in_shadow;
+}
\ No newline at end of file
@@ -0,0 +1,30 @@
+// clustered lights, see renderers/light_clusters.rs. the cluster counts have to match the ones there
+const int CLUSTER_X = 16;
+const int CLUSTER_Y = 9;
+const int CLUSTER_Z = 24;
+// offset and count into light_indices for every cluster
+uniform usamplerBuffer cluster_ranges;
+uniform usamplerBuffer light_indices;
+// projection_matrix[0][0] and [1][1]
+uniform vec2 cluster_projection;
+// depth where the second slice starts and log(far / that depth)
+uniform vec2 cluster_depth_params;
+// index of the light the shadow map belongs to, -1 if there is none
+uniform int shadow_light;
+
+// offset and count of the lights of the cluster that contains the eye space position
+uvec2 find_cluster(vec3 eye_position) {
+    float depth = max(-eye_position.z, 0.0001);
+    vec2 ndc = cluster_projection * eye_position.xy / depth;
+    ivec2 tile = clamp(ivec2(floor((ndc * 0.5 + 0.5) * vec2(CLUSTER_X, CLUSTER_Y))), ivec2(0), ivec2(CLUSTER_X - 1, CLUSTER_Y - 1));
+    int slice = 0;
+    if (depth >= cluster_depth_params.x) {
+        slice = min(int(log(depth / cluster_depth_params.x) / cluster_depth_params.y * (CLUSTER_Z - 1)) + 1, CLUSTER_Z - 1);
+    }
+    return texelFetch(cluster_ranges, (slice * CLUSTER_Y + tile.y) * CLUSTER_X + tile.x).rg;
+}
+
+// index of the i-th light of the cluster
+int cluster_light(uvec2 cluster, uint i) {
+    return int(texelFetch(light_indices, int(cluster.x + i)).r);
+}
\ No newline at end of file
@@ -0,0 +1,40 @@
+// light data of the light clusters, see renderers/light_clusters.rs
+// 4 texels per light: position + kind (0 directional, 1 point, 2 spot), color + range (0 means no falloff),
+// attenuation + cos of the outer spot angle, direction + cos of the inner spot angle
+const int LIGHT_TEXELS = 4;
+uniform samplerBuffer light_data;
+
+// fade the light out towards its range so it doesn't pop when the culling drops it
+float range_falloff(float dist, float range) {
+    if (range <= 0.0) {
+        return 1.0;
+    }
+    float ratio = dist / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window;
+}
+
+// returns the vector from world_position to the light (the negated direction for directional lights)
+// and sets the color that arrives there after the attenuation and the spot cone
+vec3 fetch_light(int light, vec3 world_position, out vec3 light_color) {
+    int texel = LIGHT_TEXELS * light;
+    vec4 position_kind = texelFetch(light_data, texel);
+    vec4 color_range = texelFetch(light_data, texel + 1);
+    vec4 attenuation_cos_outer = texelFetch(light_data, texel + 2);
+    vec4 direction_cos_inner = texelFetch(light_data, texel + 3);
+    if (position_kind.w < 0.5) {
+        light_color = color_range.rgb;
+        return -direction_cos_inner.xyz;
+    }
+    vec3 to_light = position_kind.xyz - world_position;
+    float dist = length(to_light);
+    vec3 attenuation = attenuation_cos_outer.xyz;
+    float attenuation_factor = attenuation.x + attenuation.y * dist + attenuation.z * dist * dist;
+    float strength = range_falloff(dist, color_range.w) / attenuation_factor;
+    if (position_kind.w > 1.5) {
+        float cos_angle = dot(-to_light / dist, direction_cos_inner.xyz);
+        strength *= smoothstep(attenuation_cos_outer.w, direction_cos_inner.w, cos_angle);
+    }
+    light_color = color_range.rgb * strength;
+    return to_light;
+}
\ No newline at end of file
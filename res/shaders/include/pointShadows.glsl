@@ -0,0 +1,44 @@
+#include "lightData.glsl"
+
+// cube shadow maps of the point lights, see shadows/point_shadows.rs. MAX_POINT_SHADOWS has to match the one there
+const int MAX_POINT_SHADOWS = 4;
+uniform samplerCubeArray point_shadow_maps;
+// index of the light every cube belongs to, -1 if the cube isn't used
+uniform int point_shadow_lights[MAX_POINT_SHADOWS];
+uniform float point_shadow_map_size;
+// the cube maps store the distance to the light divided by its range
+const float point_shadow_bias = 0.005;
+// directions around the one to the fragment that we sample for pcf
+const vec3 point_pcf_offsets[20] = vec3[](
+    vec3(1, 1, 1), vec3(1, -1, 1), vec3(-1, -1, 1), vec3(-1, 1, 1),
+    vec3(1, 1, -1), vec3(1, -1, -1), vec3(-1, -1, -1), vec3(-1, 1, -1),
+    vec3(1, 1, 0), vec3(1, -1, 0), vec3(-1, -1, 0), vec3(-1, 1, 0),
+    vec3(1, 0, 1), vec3(-1, 0, 1), vec3(1, 0, -1), vec3(-1, 0, -1),
+    vec3(0, 1, 1), vec3(0, -1, 1), vec3(0, -1, -1), vec3(0, 1, -1)
+);
+
+// fraction of the light that is blocked on its way to the fragment, 0 for lights without a cube shadow map
+// to_light is the vector from the fragment to the light that fetch_light returns
+float point_shadow(int light, vec3 to_light) {
+    int cube = -1;
+    for (int i = 0; i < MAX_POINT_SHADOWS; i++) {
+        if (point_shadow_lights[i] == light) {
+            cube = i;
+        }
+    }
+    if (cube < 0) {
+        return 0.0;
+    }
+    float range = texelFetch(light_data, LIGHT_TEXELS * light + 1).w;
+    float dist = length(to_light);
+    float depth = dist / range;
+    // a cube face covers 90 degrees so a texel at this distance is about 2 * dist / size wide
+    float offset_size = 2.0 * dist / point_shadow_map_size;
+    float total_in_shadow = 0.0;
+    for (int i = 0; i < 20; i++) {
+        vec3 direction = -to_light + point_pcf_offsets[i] * offset_size;
+        float obj_depth_nearest_light = texture(point_shadow_maps, vec4(direction, cube)).r;
+        total_in_shadow += step(obj_depth_nearest_light + point_shadow_bias, depth);
+    }
+    return total_in_shadow / 20.0;
+}
\ No newline at end of file
@@ -0,0 +1,31 @@
+// the weather the surfaces are in, see entities/weather.rs
+// 0 is dry and 1 soaked from the rain
+uniform float wetness;
+// how much snow lies on the ground, it only stays on the flatter parts
+uniform float snow_cover;
+
+// water fills the pores of a wet surface so it gets darker and shinier
+const float wet_darkening = 0.6;
+const float wet_reflectivity = 0.5;
+const float wet_shine_damper = 40.0;
+const float wet_roughness = 0.15;
+const vec3 snow_color = vec3(0.9, 0.92, 0.95);
+const float snow_reflectivity = 0.1;
+
+// for the phong materials, a stronger and tighter highlight
+void apply_wetness(inout vec3 color, inout float reflectivity, inout float shine_damper) {
+    color *= mix(1.0, wet_darkening, wetness);
+    reflectivity += wetness * wet_reflectivity;
+    shine_damper = mix(shine_damper, wet_shine_damper, wetness);
+}
+
+// for the metallic/roughness materials, metals have no pores and barely change
+void apply_wetness_pbr(inout vec3 albedo, inout float roughness, float metallic) {
+    albedo *= mix(1.0, wet_darkening, wetness * (1.0 - metallic));
+    roughness = mix(roughness, min(roughness, wet_roughness), wetness);
+}
+
+// how much of a surface with this world space normal the snow covers
+float snow_amount(vec3 normal) {
+    return snow_cover * smoothstep(0.6, 0.9, normalize(normal).y);
+}
\ No newline at end of file
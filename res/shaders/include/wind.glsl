@@ -0,0 +1,16 @@
+// plants lean with the wind, the higher up a vertex is in the model the further it moves
+// the shadow passes sway them the same way so the shadows move with the plants
+uniform float sways_in_wind;
+uniform vec3 wind;
+uniform float wind_time;
+const float sway_strength = 0.04;
+
+vec4 sway_in_wind(vec4 world_position, vec3 model_position, mat4 transform) {
+    if (sways_in_wind > 0.5) {
+        // the phase comes from where the plant stands so neighbours don't all move together
+        float phase = dot(transform[3].xz, vec2(0.37, 0.61));
+        float sway = 1.0 + 0.3 * sin(wind_time * 2.0 + phase) + 0.1 * sin(wind_time * 5.3 + phase * 1.7);
+        world_position.xz += wind.xz * max(model_position.y, 0.0) * sway_strength * sway;
+    }
+    return world_position;
+}
\ No newline at end of file
@@ -1,14 +1,12 @@
 #version 400 core
 
-const int NUM_LIGHTS = 4;
-
 in vec2 pass_tex_coord;
 in vec3 surface_normal;
-in vec3 light_direction_tgs[NUM_LIGHTS];
+in vec3 pass_world_position;
+in vec3 pass_eye_position;
+in mat3 eye_to_tangent_space;
 // specular lighting stuff
 in vec3 to_camera_dir_tgs;
-// fog stuff
-in float visibility;
 
 // rgba
 layout(location = 0) out vec4 out_Color;
@@ -16,14 +14,16 @@ layout(location = 1) out vec4 out_brightness_Color;
 
 uniform sampler2D texture_sampler;
 uniform sampler2D normal_map_sampler;
-uniform vec3 light_color[NUM_LIGHTS];
+uniform mat4 view_matrix;
 // specular lighting
 uniform float shine_damper;
 uniform float reflectivity;
-// fog
-uniform vec3 sky_color;
-// point light attenuation
-uniform vec3 attenuation[NUM_LIGHTS];
+
+#include "cascadedShadows.glsl"
+#include "lightData.glsl"
+#include "lightClusters.glsl"
+#include "pointShadows.glsl"
+#include "weather.glsl"
 
 const bool uses_cell_shading = false;
 const float brightness_levels = 3.0;
@@ -39,11 +39,17 @@ void adjust_brightness(inout float diffuse_brightness, inout float specular_brig
 }
 
 void main(void) {
-    
+    // same bias as the entity shader since these are the same kind of detailed models
+    float light_factor = 1.0 - shadow_amount(pass_world_position, -pass_eye_position.z, 0.01);
+
     vec4 texture_color = texture(texture_sampler, pass_tex_coord);
     if (texture_color.a < 0.5) {
         discard; // do not render transparency (hack)
     }
+    // wet surfaces are darker and shinier
+    float surface_reflectivity = reflectivity;
+    float surface_shine_damper = shine_damper;
+    apply_wetness(texture_color.rgb, surface_reflectivity, surface_shine_damper);
 
     vec3 normal_from_map = (2.0 * texture(normal_map_sampler, pass_tex_coord) - 1.0).xyz;
 
@@ -55,15 +61,23 @@ void main(void) {
     vec3 total_diffuse = vec3(0.0);
     vec3 total_specular = vec3(0.0);
 
-    for (int i=0; i<NUM_LIGHTS; i++) {
-        float distance_to_light_point = length(light_direction_tgs[i]);
-        float attenuation_factor = attenuation[i].x + attenuation[i].y * distance_to_light_point + attenuation[i].z * distance_to_light_point * distance_to_light_point;
+    uvec2 cluster = find_cluster(pass_eye_position);
+    for (uint i=0u; i<cluster.y; i++) {
+        int light = cluster_light(cluster, i);
+        vec3 light_color;
+        vec3 light_direction = fetch_light(light, pass_world_position, light_color);
+        if (light == shadow_light) {
+            light_color *= light_factor;
+        } else {
+            light_color *= 1.0 - point_shadow(light, light_direction);
+        }
+        vec3 light_direction_tgs = eye_to_tangent_space * (mat3(view_matrix) * light_direction);
         
-        vec3 unit_light = normalize(light_direction_tgs[i]);    
+        vec3 unit_light = normalize(light_direction_tgs);    
         float dotNormToLight = dot(unit_normal, unit_light);
         float brightness = max(dotNormToLight, 0.0);
 
-        vec3 specular_reflection_dir = reflect(-light_direction_tgs[i], unit_normal);
+        vec3 specular_reflection_dir = reflect(-light_direction_tgs, unit_normal);
         vec3 unit_specular_reflection = normalize(specular_reflection_dir);
 
         float dotSpecToCamera = dot(unit_camera, unit_specular_reflection);
@@ -71,12 +85,12 @@ void main(void) {
 
         adjust_brightness(brightness, spec_brightness);
 
-        total_diffuse += (brightness * light_color[i]) / attenuation_factor;
-        total_specular = (pow(spec_brightness, shine_damper) * reflectivity * light_color[i]) / attenuation_factor;
+        total_diffuse += brightness * light_color;
+        total_specular += pow(spec_brightness, surface_shine_damper) * surface_reflectivity * light_color;
     }
     total_diffuse = max(total_diffuse, 0.2); // clamp to 0.2 so nothing totally dark -> ambient light
 
     vec4 light_based_out_color = vec4(total_diffuse, 1.0) * texture_color + vec4(total_specular, 1.0);
-    out_Color = mix(vec4(sky_color, 1.0), light_based_out_color, visibility); 
+    out_Color = light_based_out_color;
     out_brightness_Color = vec4(0.0);
 }
\ No newline at end of file
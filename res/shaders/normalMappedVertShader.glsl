@@ -1,31 +1,26 @@
 #version 400 core
 
-const int NUM_LIGHTS = 4;
-
 in vec3 pos;
 in vec2 tex_coord;
 in vec3 normal;
 in vec4 tangents;
+// per instance
+in mat4 transform;
+in vec2 texture_offset;
 
 out vec2 pass_tex_coord;
-out vec3 light_direction_tgs[NUM_LIGHTS];
+out vec3 pass_world_position;
+out vec3 pass_eye_position;
+out mat3 eye_to_tangent_space;
 out vec3 to_camera_dir_tgs;
-out float visibility;
 
-uniform mat4 transform;
 uniform mat4 projection_matrix;
 uniform mat4 view_matrix;
 
-uniform vec3 light_pos[NUM_LIGHTS];
 uniform float uses_fake_lighting;
 
 // atlas scaling stuff
 uniform float number_of_rows;
-uniform vec2 texture_offset;
-
-// fog stuff
-const float fog_density = 0.007;
-const float fog_gradient = 1.5;
 
 // clipping plane for water rendering
 uniform vec4 clip_plane;
@@ -69,19 +64,14 @@ void main(void) {
 
     // NOTE!!! glsl shader mat3 constructor takes column vectors!!
     // so this is the matrix transposed (and since it is orthonormal that means inverted)
-    mat3 eye_to_tangent_space = mat3(
+    eye_to_tangent_space = mat3(
         tang_eye.x, bitang_eye.x, surface_normal_eye.x,
         tang_eye.y, bitang_eye.y, surface_normal_eye.y,
         tang_eye.z, bitang_eye.z, surface_normal_eye.z
     );
 
-    for (int i=0; i<NUM_LIGHTS; i++) {
-        light_direction_tgs[i] = eye_to_tangent_space * (view_matrix * (vec4(light_pos[i], 1.0) - world_position)).xyz;  
-    }    
+    // the lights are looked up per fragment from the light clusters and moved to tangent space there
+    pass_world_position = world_position.xyz;
+    pass_eye_position = eye_space_position.xyz;
     to_camera_dir_tgs = eye_to_tangent_space * (-eye_space_position.xyz);
-        
-    // compute visibility
-    float distance_to_eye = length(eye_space_position.xyz);
-    float fog_vis_coef = exp(-pow(distance_to_eye * fog_density, fog_gradient));
-    visibility = clamp(fog_vis_coef, 0.0, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 400 core
+
+in vec2 pass_position;
+in float pass_fade;
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) out vec4 out_brightness_Color;
+
+uniform vec4 color;
+
+void main(void) {
+    // round drops that are the most opaque in the middle
+    float shape = 1.0 - smoothstep(0.5, 1.0, length(pass_position));
+    out_color = vec4(color.rgb, color.a * shape * pass_fade);
+    out_brightness_Color = vec4(0.0);
+}
\ No newline at end of file
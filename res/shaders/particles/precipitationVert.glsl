@@ -0,0 +1,62 @@
+#version 400 core
+
+// the quad from -1 to 1, every instance of it is one drop
+in vec2 position;
+
+out vec2 pass_position;
+out float pass_fade;
+
+uniform mat4 projection_matrix;
+uniform mat4 view_matrix;
+uniform vec3 camera_position;
+
+// wraps around every period, by then every drop has fallen through the box a whole number of times
+uniform float time;
+uniform float period;
+uniform float fall_speed;
+uniform vec3 wind;
+// how far the wind has blown the drops, kept inside the box on the cpu
+uniform vec3 wind_drift;
+// size of the box around the camera the drops fall through
+uniform vec3 area;
+uniform vec2 drop_size;
+uniform float flutter;
+
+// keep in sync with PrecipitationRenderer::SPEED_STEPS
+const float speed_steps = 10.0;
+const float two_pi = 6.2832;
+
+float hash(float n) {
+    return fract(sin(n) * 43758.5453);
+}
+
+void main(void) {
+    float id = float(gl_InstanceID);
+    vec3 seed = vec3(hash(id * 1.37), hash(id * 2.71 + 0.5), hash(id * 4.13 + 0.25));
+    // some drops fall faster than others, in steps so they all fall whole box heights in a period
+    float fall = fall_speed * floor(mix(0.8, 1.2, seed.x) * speed_steps) / speed_steps;
+    vec3 velocity = vec3(wind.x, -fall, wind.z);
+    // and the flakes swing a whole number of times in it
+    vec2 flutter_frequency = round((1.0 + seed.yz) * period / two_pi) * two_pi / period;
+
+    // every drop moves through the world and wraps around in the box that follows the camera
+    vec3 world_position = seed * area + wind_drift + vec3(0.0, -fall * time, 0.0);
+    world_position.xz += flutter * sin(time * flutter_frequency + seed.zx * two_pi);
+    vec3 in_area = mod(world_position - camera_position + 0.5 * area, area) - 0.5 * area;
+    vec3 center = camera_position + in_area;
+
+    // rain streaks stretch along where they fall, flakes face the camera
+    vec3 to_camera = normalize(camera_position - center);
+    vec3 axis = flutter > 0.0 ? vec3(0.0, 1.0, 0.0) : normalize(velocity);
+    vec3 side = normalize(cross(axis, to_camera));
+    if (flutter > 0.0) {
+        axis = cross(to_camera, side);
+    }
+    vec3 vertex = center + side * position.x * drop_size.x + axis * position.y * drop_size.y;
+    gl_Position = projection_matrix * view_matrix * vec4(vertex, 1.0);
+
+    pass_position = position;
+    // fade out towards the sides of the box so the wrapping doesn't pop
+    vec3 edge = abs(in_area) / (0.5 * area);
+    pass_fade = 1.0 - smoothstep(0.7, 1.0, max(edge.x, max(edge.y, edge.z)));
+}
\ No newline at end of file
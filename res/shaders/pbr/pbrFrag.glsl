@@ -0,0 +1,166 @@
+#version 400 core
+
+in vec2 pass_tex_coord;
+in vec3 pass_world_position;
+in vec3 pass_eye_position;
+in vec3 pass_world_normal;
+in vec3 pass_world_tangent;
+
+layout(location = 0) out vec4 out_Color;
+layout(location = 1) out vec4 out_brightness_Color;
+
+// metallic/roughness material, the maps use the gltf channels (occlusion r, roughness g, metallic b)
+// the factors multiply the maps and are used on their own for the maps the material doesn't have
+uniform sampler2D base_color_map;
+uniform sampler2D normal_map;
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D occlusion_map;
+uniform sampler2D emissive_map;
+uniform float has_normal_map;
+uniform float has_metallic_roughness_map;
+uniform float has_occlusion_map;
+uniform float has_emissive_map;
+uniform float metallic_factor;
+uniform float roughness_factor;
+uniform float emissive_strength;
+
+uniform vec3 camera_position;
+
+// image based lighting baked from the skybox, see renderers/ibl_renderer.rs
+// like the skybox we blend between two skies over the day and rotate with it
+uniform float has_ibl;
+uniform samplerCube irradiance_map;
+uniform samplerCube prefiltered_map;
+uniform samplerCube next_irradiance_map;
+uniform samplerCube next_prefiltered_map;
+uniform sampler2D brdf_lut;
+uniform float sky_blend_factor;
+uniform mat4 sky_rotation;
+uniform float prefiltered_max_level;
+
+const float PI = 3.14159265359;
+// reflectance at normal incidence of everything that isn't a metal
+const vec3 dielectric_f0 = vec3(0.04);
+// our light colors are set up for the phong shaders where a light of 1 lights a white surface to 1, lambert divides that by pi
+const float light_intensity_scale = PI;
+// same floor as the phong shaders for when there is no sky to light with
+const float ambient_light = 0.2;
+
+#include "cascadedShadows.glsl"
+#include "lightData.glsl"
+#include "lightClusters.glsl"
+#include "pointShadows.glsl"
+#include "weather.glsl"
+
+// the normal distribution, how many microfacets face the half vector
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+// smith with the schlick approximation for one direction, k is the one for direct lights
+float geometry_schlick_ggx(float n_dot_x, float roughness) {
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// rough surfaces reflect less at grazing angles, used for the environment where there is no half vector
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// cook-torrance specular + lambert diffuse for the light arriving from light_direction
+vec3 direct_light(vec3 normal, vec3 view, vec3 light_direction, vec3 light_color, vec3 albedo, float metallic, float roughness, vec3 f0) {
+    vec3 unit_light = normalize(light_direction);
+    vec3 half_vector = normalize(view + unit_light);
+    float n_dot_l = max(dot(normal, unit_light), 0.0);
+    float n_dot_v = max(dot(normal, view), 0.0001);
+    float n_dot_h = max(dot(normal, half_vector), 0.0);
+
+    float distribution = distribution_ggx(n_dot_h, roughness);
+    float geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+    vec3 fresnel = fresnel_schlick(max(dot(half_vector, view), 0.0), f0);
+    vec3 specular = distribution * geometry * fresnel / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    // whatever the surface reflects doesn't get into it and metals have no diffuse at all
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+    return (diffuse + specular) * light_color * light_intensity_scale * n_dot_l;
+}
+
+// diffuse and specular light from the sky with the split sum approximation
+vec3 environment_light(vec3 normal, vec3 view, vec3 albedo, float metallic, float roughness, vec3 f0) {
+    if (has_ibl < 0.5) {
+        return ambient_light * albedo;
+    }
+    float n_dot_v = max(dot(normal, view), 0.0);
+    vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    vec3 diffuse_factor = (1.0 - fresnel) * (1.0 - metallic);
+
+    vec3 sky_normal = mat3(sky_rotation) * normal;
+    vec3 sky_reflection = mat3(sky_rotation) * reflect(-view, normal);
+    float level = roughness * prefiltered_max_level;
+    vec3 irradiance = mix(texture(irradiance_map, sky_normal).rgb, texture(next_irradiance_map, sky_normal).rgb, sky_blend_factor);
+    vec3 prefiltered = mix(textureLod(prefiltered_map, sky_reflection, level).rgb, textureLod(next_prefiltered_map, sky_reflection, level).rgb, sky_blend_factor);
+    vec2 brdf = texture(brdf_lut, vec2(n_dot_v, roughness)).rg;
+
+    return diffuse_factor * irradiance * albedo + prefiltered * (fresnel * brdf.x + brdf.y);
+}
+
+void main(void) {
+    vec4 base_color = texture(base_color_map, pass_tex_coord);
+    if (base_color.a < 0.5) {
+        discard;
+    }
+    vec3 albedo = base_color.rgb;
+
+    float metallic = metallic_factor;
+    float roughness = roughness_factor;
+    if (has_metallic_roughness_map > 0.5) {
+        vec4 metallic_roughness = texture(metallic_roughness_map, pass_tex_coord);
+        roughness *= metallic_roughness.g;
+        metallic *= metallic_roughness.b;
+    }
+    apply_wetness_pbr(albedo, roughness, metallic);
+    // a perfect mirror makes the specular highlight of the lights infinitely small
+    roughness = clamp(roughness, 0.04, 1.0);
+    float occlusion = has_occlusion_map > 0.5 ? texture(occlusion_map, pass_tex_coord).r : 1.0;
+    vec3 emissive = has_emissive_map > 0.5 ? texture(emissive_map, pass_tex_coord).rgb * emissive_strength : vec3(emissive_strength);
+
+    vec3 normal = normalize(pass_world_normal);
+    if (has_normal_map > 0.5) {
+        vec3 tangent = normalize(pass_world_tangent - dot(pass_world_tangent, normal) * normal);
+        vec3 bitangent = cross(normal, tangent);
+        vec3 normal_from_map = 2.0 * texture(normal_map, pass_tex_coord).rgb - 1.0;
+        normal = normalize(mat3(tangent, bitangent, normal) * normal_from_map);
+    }
+    vec3 view = normalize(camera_position - pass_world_position);
+    vec3 f0 = mix(dielectric_f0, albedo, metallic);
+
+    // same bias as the normal mapped entities
+    float light_factor = 1.0 - shadow_amount(pass_world_position, -pass_eye_position.z, 0.01);
+
+    vec3 total_light = vec3(0.0);
+    uvec2 cluster = find_cluster(pass_eye_position);
+    for (uint i=0u; i<cluster.y; i++) {
+        int light = cluster_light(cluster, i);
+        vec3 light_color;
+        vec3 light_direction = fetch_light(light, pass_world_position, light_color);
+        if (light == shadow_light) {
+            light_color *= light_factor;
+        } else {
+            light_color *= 1.0 - point_shadow(light, light_direction);
+        }
+        total_light += direct_light(normal, view, light_direction, light_color, albedo, metallic, roughness, f0);
+    }
+    total_light += occlusion * environment_light(normal, view, albedo, metallic, roughness, f0);
+
+    out_Color = vec4(total_light + emissive, 1.0);
+    // emissive parts glow like the glow channel of the extra info maps
+    out_brightness_Color = vec4(emissive, 1.0);
+}
\ No newline at end of file
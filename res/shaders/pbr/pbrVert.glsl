@@ -0,0 +1,40 @@
+#version 400 core
+
+in vec3 pos;
+in vec2 tex_coord;
+in vec3 normal;
+// only there for models with a normal map, otherwise the attribute is disabled and reads as (0, 0, 0, 1)
+in vec4 tangents;
+
+out vec2 pass_tex_coord;
+out vec3 pass_world_position;
+out vec3 pass_eye_position;
+out vec3 pass_world_normal;
+out vec3 pass_world_tangent;
+
+uniform mat4 transform;
+uniform mat4 projection_matrix;
+uniform mat4 view_matrix;
+
+// atlas scaling stuff
+uniform float number_of_rows;
+uniform vec2 texture_offset;
+
+// clipping plane for water rendering
+uniform vec4 clip_plane;
+
+void main(void) {
+    vec4 world_position = transform * vec4(pos, 1.0);
+    gl_ClipDistance[0] = dot(world_position, clip_plane);
+
+    vec4 eye_space_position = view_matrix * world_position;
+    gl_Position = projection_matrix * eye_space_position;
+    pass_tex_coord = (tex_coord / number_of_rows) + texture_offset;
+
+    // the lighting and the environment lookups happen in world space so we keep the tbn basis there
+    // our transforms are rotation + uniform scale so the transform works for the normal too
+    pass_world_position = world_position.xyz;
+    pass_eye_position = eye_space_position.xyz;
+    pass_world_normal = (transform * vec4(normal, 0.0)).xyz;
+    pass_world_tangent = (transform * vec4(tangents.xyz, 0.0)).xyz;
+}
\ No newline at end of file
@@ -0,0 +1,63 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D scene_texture;
+uniform sampler2D depth_texture;
+uniform sampler2D light_shafts_texture;
+uniform mat4 inverse_view_projection;
+uniform vec3 camera_position;
+
+// exponential height fog, see entities/atmosphere.rs
+uniform float has_fog;
+uniform float fog_density;
+uniform float fog_falloff;
+uniform float fog_base_height;
+// follows the time of day
+uniform vec3 fog_color;
+
+// towards the sun, the color is black without one
+uniform vec3 sun_direction;
+uniform vec3 sun_color;
+uniform float sun_scattering;
+
+uniform float has_light_shafts;
+uniform float light_shafts_strength;
+
+// the sky has the depth of the far plane so it gets the fog of the far plane
+vec3 world_position_at(vec2 uv) {
+    float depth = texture(depth_texture, uv).r;
+    vec4 world_position = inverse_view_projection * vec4(uv * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+    return world_position.xyz / world_position.w;
+}
+
+// the density integrated along the ray from the camera, same as HeightFog::optical_depth
+float optical_depth(vec3 from, vec3 to) {
+    float start_density = fog_density * exp(-fog_falloff * (from.y - fog_base_height));
+    float height_change = fog_falloff * (to.y - from.y);
+    // for a flat ray the density doesn't change along it
+    float height_factor = abs(height_change) > 1e-4 ? (1.0 - exp(-height_change)) / height_change : 1.0;
+    return start_density * height_factor * length(to - from);
+}
+
+void main(void) {
+    vec3 color = texture(scene_texture, texture_coords).rgb;
+
+    if (has_fog > 0.5) {
+        vec3 world_position = world_position_at(texture_coords);
+        vec3 view_direction = normalize(world_position - camera_position);
+        float transmittance = exp(-optical_depth(camera_position, world_position));
+        // looking towards the sun the fog lights up in its color
+        float sun_amount = pow(max(dot(view_direction, sun_direction), 0.0), 8.0) * sun_scattering;
+        vec3 inscattered = mix(fog_color, sun_color, sun_amount);
+        color = mix(inscattered, color, transmittance);
+    }
+
+    if (has_light_shafts > 0.5) {
+        color += texture(light_shafts_texture, texture_coords).r * light_shafts_strength * sun_color;
+    }
+
+    out_color = vec4(color, 1.0);
+}
\ No newline at end of file
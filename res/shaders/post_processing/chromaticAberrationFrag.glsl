@@ -0,0 +1,18 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D color_texture;
+uniform float strength;
+
+void main(void) {
+    vec2 from_center = texture_coords - 0.5;
+    // grows with the square of the distance to the center and reaches strength in the corners
+    vec2 offset = from_center * length(from_center) * 2.0 * strength;
+    float red = texture(color_texture, texture_coords + offset).r;
+    float green = texture(color_texture, texture_coords).g;
+    float blue = texture(color_texture, texture_coords - offset).b;
+    out_color = vec4(red, green, blue, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,18 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D color_texture;
+uniform sampler3D lut;
+uniform float strength;
+
+void main(void) {
+    vec3 color = clamp(texture(color_texture, texture_coords).rgb, 0.0, 1.0);
+    // the first and last entries of the lut sit in the centers of the edge texels
+    float lut_size = float(textureSize(lut, 0).x);
+    vec3 lut_coords = color * (lut_size - 1.0) / lut_size + 0.5 / lut_size;
+    vec3 graded = texture(lut, lut_coords).rgb;
+    out_color = vec4(mix(color, graded, strength), 1.0);
+}
\ No newline at end of file
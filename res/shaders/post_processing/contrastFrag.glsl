@@ -5,12 +5,12 @@ in vec2 texture_coords;
 out vec4 gl_Color;
 
 uniform sampler2D color_texture;
-
-const float contrast = 0.3;
+uniform float contrast;
 
 void main(void) {
     vec4 color = texture(color_texture, texture_coords);
     // scale to [-0.5, 0.5] then increase the contrast .. then translate back
-    color.rgb = (color.rgb - 0.5) * (1.0 + contrast) + 0.5;
+    // colors are in linear hdr now so make sure we don't go negative for the dark parts
+    color.rgb = max((color.rgb - 0.5) * (1.0 + contrast) + 0.5, 0.0);
     gl_Color = color;
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D color_texture;
+uniform sampler2D depth_texture;
+// positive distances to the planes of the camera projection
+uniform float near;
+uniform float far;
+uniform float focus_distance;
+uniform float focus_range;
+uniform float max_blur;
+
+const int SAMPLES = 32;
+const float GOLDEN_ANGLE = 2.39996323;
+
+float linear_depth(vec2 uv) {
+    float ndc_depth = texture(depth_texture, uv).r * 2.0 - 1.0;
+    return 2.0 * near * far / (far + near - ndc_depth * (far - near));
+}
+
+// in pixels, zero at the focus distance
+float blur_radius(float depth) {
+    return clamp(abs(depth - focus_distance) / focus_range, 0.0, 1.0) * max_blur;
+}
+
+void main(void) {
+    vec2 texel_size = 1.0 / vec2(textureSize(color_texture, 0));
+    float radius = blur_radius(linear_depth(texture_coords));
+    vec3 color = texture(color_texture, texture_coords).rgb;
+    float total_weight = 1.0;
+    // samples on a spiral which fills the disk of the blur radius evenly
+    for (int i = 1; i < SAMPLES; i++) {
+        float sample_dist = radius * sqrt(float(i) / float(SAMPLES));
+        float angle = float(i) * GOLDEN_ANGLE;
+        vec2 uv = texture_coords + vec2(cos(angle), sin(angle)) * sample_dist * texel_size;
+        // a neighbour only spreads over us if it is blurry enough to reach this far, keeps the sharp parts from leaking into the blurred ones
+        float weight = clamp(blur_radius(linear_depth(uv)) - sample_dist + 1.0, 0.0, 1.0);
+        color += texture(color_texture, uv).rgb * weight;
+        total_weight += weight;
+    }
+    out_color = vec4(color / total_weight, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D color_texture;
+uniform float strength;
+// seconds since the start, moves the noise every frame
+uniform float time;
+
+float hash(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main(void) {
+    vec3 color = texture(color_texture, texture_coords).rgb;
+    float noise = hash(texture_coords * 1000.0 + vec2(fract(time * 7.0), fract(time * 13.0)) * 100.0) - 0.5;
+    out_color = vec4(clamp(color + noise * strength, 0.0, 1.0), 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,58 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D color_texture;
+
+// contrast below which we don't bother, absolute and relative to the brightest neighbour
+const float EDGE_THRESHOLD_MIN = 1.0 / 32.0;
+const float EDGE_THRESHOLD_MAX = 1.0 / 8.0;
+// how far along the edge we blur in pixels
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+vec3 color_at(vec2 uv) {
+    return texture(color_texture, uv).rgb;
+}
+
+// the cheap version of fxaa: estimate the edge direction from the diagonal neighbours and blur along it
+void main(void) {
+    vec2 texel_size = 1.0 / vec2(textureSize(color_texture, 0));
+    vec3 color = color_at(texture_coords);
+    float luma_center = luma(color);
+    float luma_ll = luma(color_at(texture_coords + vec2(-1.0, -1.0) * texel_size));
+    float luma_lr = luma(color_at(texture_coords + vec2(1.0, -1.0) * texel_size));
+    float luma_ul = luma(color_at(texture_coords + vec2(-1.0, 1.0) * texel_size));
+    float luma_ur = luma(color_at(texture_coords + vec2(1.0, 1.0) * texel_size));
+    float luma_min = min(luma_center, min(min(luma_ll, luma_lr), min(luma_ul, luma_ur)));
+    float luma_max = max(luma_center, max(max(luma_ll, luma_lr), max(luma_ul, luma_ur)));
+    if (luma_max - luma_min < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD_MAX)) {
+        out_color = vec4(color, 1.0);
+        return;
+    }
+
+    // perpendicular to the luma gradient
+    vec2 dir;
+    dir.x = (luma_ul + luma_ur) - (luma_ll + luma_lr);
+    dir.y = (luma_ll + luma_ul) - (luma_lr + luma_ur);
+    float dir_reduce = max((luma_ll + luma_lr + luma_ul + luma_ur) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float inverse_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * inverse_dir_min, -SPAN_MAX, SPAN_MAX) * texel_size;
+
+    vec3 near_blur = 0.5 * (color_at(texture_coords + dir * (1.0 / 3.0 - 0.5)) + color_at(texture_coords + dir * (2.0 / 3.0 - 0.5)));
+    vec3 far_blur = near_blur * 0.5 + 0.25 * (color_at(texture_coords - dir * 0.5) + color_at(texture_coords + dir * 0.5));
+    // the far samples crossed another edge if they leave the range of the neighbourhood
+    float luma_far = luma(far_blur);
+    if (luma_far < luma_min || luma_far > luma_max) {
+        out_color = vec4(near_blur, 1.0);
+    } else {
+        out_color = vec4(far_blur, 1.0);
+    }
+}
\ No newline at end of file
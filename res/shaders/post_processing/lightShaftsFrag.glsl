@@ -0,0 +1,50 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D depth_texture;
+uniform mat4 inverse_projection;
+// how much every step further away from the pixel counts less
+uniform float decay;
+// fraction of the way to the sun the steps cover
+uniform float density;
+// in uv coordinates, can be a bit off the screen
+uniform vec2 sun_position;
+// fades the shafts out as the sun leaves the screen
+uniform float sun_visibility;
+
+const int SAMPLES = 64;
+// the skybox is a cube of 500 around the camera, anything at least this far away is sky
+const float SKY_DISTANCE = 490.0;
+// radius of the glow around the sun the rays start from, in uv units of the screen height
+const float SUN_RADIUS = 0.3;
+
+// the sky lets the sun through, the geometry in front of it blocks it
+float sky_mask(vec2 uv) {
+    float depth = texture(depth_texture, uv).r;
+    vec4 view_position = inverse_projection * vec4(uv * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+    return length(view_position.xyz / view_position.w) >= SKY_DISTANCE ? 1.0 : 0.0;
+}
+
+void main(void) {
+    if (sun_visibility <= 0.0) {
+        out_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec2 step_to_sun = (sun_position - texture_coords) * density / float(SAMPLES);
+    vec2 size = vec2(textureSize(depth_texture, 0));
+    vec2 aspect = vec2(size.x / size.y, 1.0);
+    vec2 uv = texture_coords;
+    float weight = 1.0;
+    float shafts = 0.0;
+    for (int i = 0; i < SAMPLES; i++) {
+        uv += step_to_sun;
+        // only the sky close to the sun makes rays, whatever is in front of it leaves a shadow in them
+        float glow = smoothstep(SUN_RADIUS, 0.0, length((uv - sun_position) * aspect));
+        shafts += sky_mask(uv) * glow * weight;
+        weight *= decay;
+    }
+    out_color = vec4(vec3(shafts / float(SAMPLES) * sun_visibility), 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,15 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D scene_texture;
+uniform sampler2D ambient_occlusion;
+uniform float strength;
+
+void main(void) {
+    vec4 scene_color = texture(scene_texture, texture_coords);
+    float visibility = texture(ambient_occlusion, texture_coords).r;
+    out_color = vec4(scene_color.rgb * mix(1.0, visibility, strength), scene_color.a);
+}
\ No newline at end of file
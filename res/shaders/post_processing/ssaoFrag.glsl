@@ -0,0 +1,52 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+// has to match SsaoKernel::KERNEL_SIZE
+const int KERNEL_SIZE = 32;
+
+uniform sampler2D depth_texture;
+uniform sampler2D noise_texture;
+uniform mat4 projection_matrix;
+uniform vec3 kernel[KERNEL_SIZE];
+uniform float radius;
+uniform float bias;
+
+// undo the perspective projection of the depth, x and y only get scaled by it
+vec3 view_position(vec2 uv) {
+    float ndc_depth = texture(depth_texture, uv).r * 2.0 - 1.0;
+    float view_z = -projection_matrix[3][2] / (ndc_depth + projection_matrix[2][2]);
+    vec2 ndc_xy = uv * 2.0 - 1.0;
+    return vec3(ndc_xy.x * -view_z / projection_matrix[0][0], ndc_xy.y * -view_z / projection_matrix[1][1], view_z);
+}
+
+void main(void) {
+    // nothing to occlude on the sky
+    if (texture(depth_texture, texture_coords).r >= 1.0) {
+        out_color = vec4(1.0);
+        return;
+    }
+    vec3 position = view_position(texture_coords);
+    // the surface normal from how the position changes towards the neighbouring pixels
+    vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+
+    // turn the kernel by the random rotation of this pixel
+    vec3 rotation = texelFetch(noise_texture, ivec2(gl_FragCoord.xy) % textureSize(noise_texture, 0), 0).xyz;
+    vec3 tangent = normalize(rotation - normal * dot(rotation, normal));
+    vec3 bitangent = cross(normal, tangent);
+    mat3 tbn = mat3(tangent, bitangent, normal);
+
+    float occlusion = 0.0;
+    for (int i = 0; i < KERNEL_SIZE; i++) {
+        vec3 sample_position = position + tbn * kernel[i] * radius;
+        vec4 sample_clip = projection_matrix * vec4(sample_position, 1.0);
+        vec2 sample_uv = sample_clip.xy / sample_clip.w * 0.5 + 0.5;
+        float scene_z = view_position(sample_uv).z;
+        // geometry far in front of the pixel doesn't shadow it
+        float range_check = smoothstep(0.0, 1.0, radius / abs(position.z - scene_z));
+        occlusion += (scene_z >= sample_position.z + bias ? 1.0 : 0.0) * range_check;
+    }
+    out_color = vec4(vec3(1.0 - occlusion / float(KERNEL_SIZE)), 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,37 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D hdr_texture;
+uniform int tonemap_operator;
+uniform float exposure;
+uniform float gamma;
+
+const int REINHARD = 0;
+const int ACES = 1;
+
+// the fit of the aces filmic curve by krzysztof narkowicz
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+void main(void) {
+    vec3 hdr_color = texture(hdr_texture, texture_coords).rgb * exposure;
+    // bring the hdr values into [0, 1]
+    vec3 mapped;
+    if (tonemap_operator == ACES) {
+        mapped = aces(hdr_color);
+    } else {
+        mapped = hdr_color / (hdr_color + vec3(1.0));
+    }
+    // everything up to here was done in linear space so convert to the srgb the screen expects
+    mapped = pow(mapped, vec3(1.0 / gamma));
+    out_color = vec4(mapped, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+out vec4 out_color;
+
+uniform sampler2D color_texture;
+uniform float radius;
+uniform float softness;
+uniform float strength;
+
+void main(void) {
+    vec3 color = texture(color_texture, texture_coords).rgb;
+    float from_center = length(texture_coords - 0.5);
+    float vignette = smoothstep(radius, radius + softness, from_center);
+    out_color = vec4(color * (1.0 - vignette * strength), 1.0);
+}
\ No newline at end of file
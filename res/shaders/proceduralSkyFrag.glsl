@@ -0,0 +1,94 @@
+#version 400 core
+
+in vec3 pass_tex_coords;
+out vec4 out_Color;
+
+// perez function of the preetham sky per channel of Yxy, see entities/procedural_sky.rs
+uniform vec3 perez_a;
+uniform vec3 perez_b;
+uniform vec3 perez_c;
+uniform vec3 perez_d;
+uniform vec3 perez_e;
+// already divided by the perez function at the zenith
+uniform vec3 zenith;
+uniform float sky_exposure;
+
+uniform vec3 to_sun;
+uniform vec3 sun_color;
+uniform float daylight;
+uniform vec3 night_color;
+uniform vec3 moon_color;
+// the stars turn around the pole by the hour angle
+uniform vec3 celestial_pole;
+uniform float hour_angle;
+
+// the discs are bigger than the real ones so they are more than a couple of pixels
+const float SUN_COS_RADIUS = 0.9995;
+const float MOON_COS_RADIUS = 0.9997;
+// bright enough to bloom
+const float SUN_INTENSITY = 20.0;
+const float MOON_INTENSITY = 4.0;
+const float STAR_GRID = 150.0;
+const float STAR_CHANCE = 0.004;
+
+vec3 perez(float cos_theta, float gamma) {
+    float cos_gamma = cos(gamma);
+    return (1.0 + perez_a * exp(perez_b / cos_theta)) * (1.0 + perez_c * exp(perez_d * gamma) + perez_e * cos_gamma * cos_gamma);
+}
+
+vec3 yxy_to_rgb(vec3 Yxy) {
+    float y = max(Yxy.z, 0.0001);
+    vec3 XYZ = vec3(Yxy.y / y * Yxy.x, Yxy.x, (1.0 - Yxy.y - Yxy.z) / y * Yxy.x);
+    mat3 xyz_to_rgb = mat3(
+        3.2406, -0.9689, 0.0557,
+        -1.5372, 1.8758, -0.2040,
+        -0.4986, 0.0415, 1.0570
+    );
+    return max(xyz_to_rgb * XYZ, vec3(0.0));
+}
+
+float hash(vec3 cell) {
+    return fract(sin(dot(cell, vec3(12.9898, 78.233, 45.164))) * 43758.5453);
+}
+
+// rodrigues rotation around the celestial pole
+vec3 to_star_space(vec3 direction) {
+    float c = cos(hour_angle);
+    float s = sin(hour_angle);
+    return direction * c + cross(celestial_pole, direction) * s + celestial_pole * dot(celestial_pole, direction) * (1.0 - c);
+}
+
+float stars(vec3 direction) {
+    vec3 position = to_star_space(direction) * STAR_GRID;
+    vec3 cell = floor(position);
+    float chance = hash(cell);
+    if (chance > STAR_CHANCE) {
+        return 0.0;
+    }
+    // somewhere inside the cell and of different brightness
+    vec3 center = cell + 0.5 + 0.3 * (vec3(hash(cell + 1.0), hash(cell + 2.0), hash(cell + 3.0)) - 0.5);
+    float brightness = 0.3 + 0.7 * chance / STAR_CHANCE;
+    return brightness * smoothstep(0.25, 0.0, length(position - center));
+}
+
+void main(void) {
+    vec3 direction = normalize(pass_tex_coords);
+    // below the horizon we look at the horizon
+    float cos_theta = max(direction.y, 0.01);
+    float cos_gamma = clamp(dot(direction, to_sun), -1.0, 1.0);
+
+    vec3 Yxy = zenith * perez(cos_theta, acos(cos_gamma));
+    Yxy.x *= sky_exposure;
+    vec3 day_sky = yxy_to_rgb(Yxy);
+
+    vec3 night_sky = night_color;
+    float above_horizon = smoothstep(-0.02, 0.02, direction.y);
+    night_sky += vec3(stars(direction)) * above_horizon;
+    float cos_moon = -cos_gamma;
+    night_sky += moon_color * MOON_INTENSITY * smoothstep(MOON_COS_RADIUS - 0.0001, MOON_COS_RADIUS, cos_moon) * above_horizon;
+
+    vec3 color = mix(night_sky, day_sky, daylight);
+    color += sun_color * SUN_INTENSITY * smoothstep(SUN_COS_RADIUS - 0.0002, SUN_COS_RADIUS, cos_gamma) * above_horizon;
+    // the fog towards the horizon comes from the atmosphere pass, see post_processing/post_processing.rs
+    out_Color = vec4(color, 1.0);
+}
\ No newline at end of file
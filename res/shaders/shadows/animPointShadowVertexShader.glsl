@@ -0,0 +1,26 @@
+#version 400 core
+
+const int MAX_JOINTS = 50;
+const int MAX_WEIGHTS = 4;
+
+in vec3 pos;
+in vec2 in_texture_coords;
+in ivec4 in_joint_indicies;
+in vec4 in_joint_weights;
+
+out vec2 geo_texture_coords;
+
+uniform mat4 joint_transforms[MAX_JOINTS];
+uniform mat4 transform;
+
+void main(void) {
+    // same skinning as animModelVert.glsl so the shadow has the pose that is drawn
+    vec4 pos4 = vec4(pos, 1.0);
+    vec4 total_pos = vec4(0);
+    for (int i=0; i<MAX_WEIGHTS; i++) {
+        total_pos += joint_transforms[in_joint_indicies[i]] * pos4 * in_joint_weights[i];
+    }
+    geo_texture_coords = in_texture_coords;
+    // the geometry shader projects the world position onto every face of the cube
+    gl_Position = transform * total_pos;
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+#version 400 core
+
+in vec2 texture_coords;
+in vec3 world_position;
+
+uniform sampler2D model_texture;
+uniform vec3 light_position;
+uniform float far_plane;
+
+void main(void) {
+
+    float alpha = texture(model_texture, texture_coords).a;
+    if (alpha < 0.5) {
+        discard;
+    }
+
+    // store the linear distance to the light in [0,1] so the lit shaders can compare it against their own distance
+    gl_FragDepth = length(world_position - light_position) / far_plane;
+}
\ No newline at end of file
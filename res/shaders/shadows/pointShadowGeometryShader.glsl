@@ -0,0 +1,28 @@
+#version 400 core
+
+layout (triangles) in;
+// the triangle once for every face of the cube
+layout (triangle_strip, max_vertices = 18) out;
+
+in vec2 geo_texture_coords[];
+
+out vec2 texture_coords;
+out vec3 world_position;
+
+// projection * view of every face in the order of the cube map layers (+x, -x, +y, -y, +z, -z)
+uniform mat4 face_matrices[6];
+// first layer of the light's cube in the cube map array
+uniform int layer_offset;
+
+void main(void) {
+    for (int face = 0; face < 6; face++) {
+        gl_Layer = layer_offset + face;
+        for (int i = 0; i < 3; i++) {
+            world_position = gl_in[i].gl_Position.xyz;
+            texture_coords = geo_texture_coords[i];
+            gl_Position = face_matrices[face] * gl_in[i].gl_Position;
+            EmitVertex();
+        }
+        EndPrimitive();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 400 core
+
+in vec3 pos;
+in vec2 in_texture_coords;
+// per instance
+in mat4 transform;
+
+out vec2 geo_texture_coords;
+
+#include "wind.glsl"
+
+void main(void) {
+    geo_texture_coords = in_texture_coords;
+    // the geometry shader projects the world position onto every face of the cube
+    gl_Position = sway_in_wind(transform * vec4(pos, 1.0), pos, transform);
+}
\ No newline at end of file
@@ -2,12 +2,16 @@
 
 in vec3 pos;
 in vec2 in_texture_coords;
+// per instance
+in mat4 transform;
 
 out vec2 texture_coords;
 
-uniform mat4 mvp_matrix;
+uniform mat4 vp_matrix;
+
+#include "wind.glsl"
 
 void main(void) {
     texture_coords = in_texture_coords;
-    gl_Position = mvp_matrix * vec4(pos, 1.0);
+    gl_Position = vp_matrix * sway_in_wind(transform * vec4(pos, 1.0), pos, transform);
 }
\ No newline at end of file
@@ -0,0 +1,15 @@
+#version 400 core
+
+in vec2 texture_coords;
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) out vec4 out_brightness_color;
+
+// the reflections of the glossy entities, alpha says how much of the lit color they replace
+uniform sampler2D ssr_tex;
+
+void main(void) {
+    out_color = texture(ssr_tex, texture_coords);
+    // zero alpha leaves the brightness of the bloom as it is
+    out_brightness_color = vec4(0.0);
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+#version 400 core
+
+in vec3 position;
+in vec2 tex_coord;
+in vec3 normal;
+
+out vec3 pass_world_position;
+out vec3 pass_surface_normal;
+
+uniform mat4 projection_matrix;
+uniform mat4 view_matrix;
+uniform mat4 transform_matrix;
+
+void main(void) {
+    vec4 world_position = transform_matrix * vec4(position, 1.0);
+    gl_Position = projection_matrix * view_matrix * world_position;
+    pass_world_position = world_position.xyz;
+    pass_surface_normal = (transform_matrix * vec4(normal, 0.0)).xyz;
+}
\ No newline at end of file
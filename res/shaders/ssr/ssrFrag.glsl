@@ -0,0 +1,118 @@
+#version 400 core
+
+in vec3 pass_world_position;
+in vec3 pass_surface_normal;
+
+out vec4 out_color;
+
+// the opaque scene of the main camera, resolved before the reflections are drawn
+uniform sampler2D scene_color;
+uniform sampler2D scene_depth;
+// the sky the rays see when they leave the screen or hit nothing
+uniform samplerCube sky_day_map;
+uniform samplerCube sky_night_map;
+uniform float sky_blend_factor;
+// turns a world direction into the direction of the rotated skybox
+uniform mat4 sky_rotation;
+
+uniform mat4 projection_matrix;
+uniform mat4 view_matrix;
+uniform vec3 camera_world_pos;
+// the coefficients of the perspective transform, same as in the water shader
+uniform float depth_calc_A;
+uniform float depth_calc_B;
+// scales the alpha so rough surfaces only take a bit of the reflection
+uniform float reflection_weight;
+// 1 blends the missed rays to the sky, 0 leaves them out so the water can fall back to its planar reflection
+uniform float sky_fallback;
+
+const int MAX_STEPS = 48;
+const int REFINE_STEPS = 6;
+const float MAX_DISTANCE = 250.0;
+// how far behind the depth buffer a ray still counts as hitting it, the steps get longer with the distance so it grows with them
+const float THICKNESS = 0.5;
+// fades the hits out towards the borders of the screen where the rays run out of information
+const float EDGE_FADE = 0.1;
+
+// positive distance to the camera of what the depth buffer has at uv
+float scene_distance(vec2 uv) {
+    float depth = texture(scene_depth, uv).x;
+    return depth_calc_B / (depth_calc_A + 2.0 * depth - 1.0);
+}
+
+vec2 to_screen(vec3 eye_position) {
+    vec4 clip = projection_matrix * vec4(eye_position, 1.0);
+    return clip.xy / clip.w * 0.5 + 0.5;
+}
+
+bool on_screen(vec2 uv) {
+    return all(greaterThanEqual(uv, vec2(0.0))) && all(lessThanEqual(uv, vec2(1.0)));
+}
+
+vec3 sky_color(vec3 world_direction) {
+    vec3 direction = mat3(sky_rotation) * world_direction;
+    return mix(texture(sky_day_map, direction).rgb, texture(sky_night_map, direction).rgb, sky_blend_factor);
+}
+
+void main(void) {
+    // the surface is drawn after the opaque scene so something in front of it can hide it
+    float surface_depth = depth_calc_B / (depth_calc_A + 2.0 * gl_FragCoord.z - 1.0);
+    float front_depth = depth_calc_B / (depth_calc_A + 2.0 * texelFetch(scene_depth, ivec2(gl_FragCoord.xy), 0).x - 1.0);
+    if (surface_depth > front_depth * 1.001 + 0.01) {
+        discard;
+    }
+
+    vec3 normal = normalize(pass_surface_normal);
+    vec3 world_ray = reflect(normalize(pass_world_position - camera_world_pos), normal);
+    vec3 sky = sky_color(world_ray);
+
+    vec3 eye_position = (view_matrix * vec4(pass_world_position, 1.0)).xyz;
+    vec3 eye_ray = normalize((view_matrix * vec4(world_ray, 0.0)).xyz);
+
+    vec3 hit_color = vec3(0.0);
+    float confidence = 0.0;
+    float last_t = 0.0;
+    for (int i = 1; i <= MAX_STEPS; ++i) {
+        // quadratic steps, short ones close to the surface where the contact details are
+        float progress = float(i) / float(MAX_STEPS);
+        float t = MAX_DISTANCE * progress * progress;
+        vec3 ray = eye_position + eye_ray * t;
+        if (ray.z > -0.1) {
+            // went behind the camera
+            break;
+        }
+        vec2 uv = to_screen(ray);
+        if (!on_screen(uv)) {
+            break;
+        }
+        float behind = -ray.z - scene_distance(uv);
+        if (behind > 0.0) {
+            if (behind > THICKNESS + (t - last_t)) {
+                // passed behind something thick, what is there is hidden from the camera
+                break;
+            }
+            // binary search between the last step in front and this one
+            float near_t = last_t;
+            float far_t = t;
+            for (int j = 0; j < REFINE_STEPS; ++j) {
+                float mid_t = 0.5 * (near_t + far_t);
+                vec3 mid = eye_position + eye_ray * mid_t;
+                if (-mid.z > scene_distance(to_screen(mid))) {
+                    far_t = mid_t;
+                } else {
+                    near_t = mid_t;
+                }
+            }
+            vec2 hit_uv = to_screen(eye_position + eye_ray * far_t);
+            hit_color = texture(scene_color, hit_uv).rgb;
+            vec2 edge = smoothstep(vec2(0.0), vec2(EDGE_FADE), hit_uv) * (1.0 - smoothstep(vec2(1.0 - EDGE_FADE), vec2(1.0), hit_uv));
+            // the far hits fade out so the reflection doesn't end at a hard line
+            confidence = edge.x * edge.y * (1.0 - progress * progress);
+            break;
+        }
+        last_t = t;
+    }
+
+    // keep in sync with ScreenSpaceReflectionRenderer::reflection_alpha
+    out_color = vec4(mix(sky, hit_color, confidence), mix(confidence, 1.0, sky_fallback) * reflection_weight);
+}
\ No newline at end of file
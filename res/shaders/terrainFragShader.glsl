@@ -1,14 +1,10 @@
 #version 400 core
 
-const int NUM_LIGHTS = 4;
-
 in vec2 pass_tex_coord;
 in vec3 surface_normal;
-in vec3 light_direction[NUM_LIGHTS];
+in vec3 pass_world_position;
+in vec3 pass_eye_position;
 in vec3 to_camera_dir;
-// fog stuff
-in float visibility;
-in vec4 shadow_coords;
 
 // rgba
 layout(location = 0) out vec4 out_Color;
@@ -19,25 +15,20 @@ uniform sampler2D r_sampler;
 uniform sampler2D g_sampler;
 uniform sampler2D b_sampler;
 uniform sampler2D blend_map_sampler;
-uniform sampler2D shadow_map;
-
-uniform vec3 light_color[NUM_LIGHTS];
 
 uniform float shine_damper;
 uniform float reflectivity;
-// fog
-uniform vec3 sky_color;
-// point light attenuation
-uniform vec3 attenuation[NUM_LIGHTS];
 
 const bool uses_cell_shading = false;
 const float brightness_levels = 2.0;
 
-// how many pixels to sample on each side of center pixel (so 2 means 3x3 box) 
-const int pcf_count = 1;
-// texture pixels we will be sampling
-const float texel_count = (pcf_count*2.0 + 1.0)*(pcf_count*2.0 + 1.0);
-uniform float shadow_map_size;
+// the terrain doesn't shadow itself much so it gets away with fewer samples
+#define PCF_COUNT 1
+#include "cascadedShadows.glsl"
+#include "lightData.glsl"
+#include "lightClusters.glsl"
+#include "pointShadows.glsl"
+#include "weather.glsl"
 
 void adjust_brightness(inout float diffuse_brightness, inout float specular_brightness) {
     if (!uses_cell_shading) {
@@ -50,20 +41,8 @@ void adjust_brightness(inout float diffuse_brightness, inout float specular_brig
 }
 
 void main(void) {
-    // size of a pixel in texture coords space
-    float texel_size = 1.0 / shadow_map_size;
-    float total_in_shadow = 0.0;
-
-    for (int x=-pcf_count; x<=pcf_count; x++) {
-        for (int y=-pcf_count; y <= pcf_count; y++) {
-            // compare depth with shadowmap depth to figure out if this piece of terrain is in shadow or not (absence of light due to something blocking it)
-            float obj_depth_nearest_light = texture(shadow_map, shadow_coords.xy + vec2(x, y) * texel_size).r;
-            // add slight offset to prevent shadow acne
-            total_in_shadow += step(obj_depth_nearest_light + 0.005, shadow_coords.z);
-        }
-    }
-    total_in_shadow /= texel_count;     
-    float light_factor = 1.0 - total_in_shadow*shadow_coords.w;
+    // add slight offset to prevent shadow acne
+    float light_factor = 1.0 - shadow_amount(pass_world_position, -pass_eye_position.z, 0.005);
 
     // sample untiled (by untiled i mean before coordinates are scaled by 40.0 which exploits REPEAT to tile the texture onto the object)
     vec4 blend_map_col = texture(blend_map_sampler, pass_tex_coord);
@@ -79,34 +58,49 @@ void main(void) {
     vec4 b_col = texture(b_sampler, tiled_coords) * blend_map_col.b;
     vec4 blended_texture_color = background_col + r_col + g_col + b_col;
 
+    // wet ground is darker and shinier, the snow settles on top of it
+    float surface_reflectivity = reflectivity;
+    float surface_shine_damper = shine_damper;
+    apply_wetness(blended_texture_color.rgb, surface_reflectivity, surface_shine_damper);
+    float snow = snow_amount(surface_normal);
+    blended_texture_color.rgb = mix(blended_texture_color.rgb, snow_color, snow);
+    surface_reflectivity = mix(surface_reflectivity, snow_reflectivity, snow);
+
     // we have to normalize after interpolation
     vec3 unit_normal = normalize(surface_normal);
     vec3 unit_camera = normalize(to_camera_dir);
 
     vec3 total_diffuse = vec3(0.0);
     vec3 total_specular = vec3(0.0);
-    for (int i=0; i<NUM_LIGHTS; i++) {
-        float dist = length(light_direction[i]);
-        float attenuation_factor = attenuation[i].x + attenuation[i].y * dist + attenuation[i].z * dist * dist;
+    uvec2 cluster = find_cluster(pass_eye_position);
+    for (uint i=0u; i<cluster.y; i++) {
+        int light = cluster_light(cluster, i);
+        vec3 light_color;
+        vec3 light_direction = fetch_light(light, pass_world_position, light_color);
+        if (light == shadow_light) {
+            light_color *= light_factor;
+        } else {
+            light_color *= 1.0 - point_shadow(light, light_direction);
+        }
 
-        vec3 unit_light = normalize(light_direction[i]);    
+        vec3 unit_light = normalize(light_direction);    
         float dotNormToLight = dot(unit_normal, unit_light);
         float brightness = max(dotNormToLight, 0.0);
                         
-        vec3 specular_reflection_dir = reflect(-light_direction[i], unit_normal);
+        vec3 specular_reflection_dir = reflect(-light_direction, unit_normal);
         vec3 unit_specular_reflection = normalize(specular_reflection_dir);
         float dotSpecToCamera = dot(unit_camera, unit_specular_reflection);
         float spec_brightness = max(dotSpecToCamera, 0.0);
 
         adjust_brightness(brightness, spec_brightness);
 
-        total_diffuse += (brightness * light_color[i]) / attenuation_factor; // add alpha of 1
-        total_specular += (pow(spec_brightness, shine_damper) * reflectivity * light_color[i]) / attenuation_factor;
+        total_diffuse += brightness * light_color;
+        total_specular += pow(spec_brightness, surface_shine_damper) * surface_reflectivity * light_color;
     }
-    total_diffuse = max(total_diffuse * light_factor, 0.2); // clamp to [0.2, 1], the 0.2 means everything is given a little bit of color -> ambient
+    total_diffuse = max(total_diffuse, 0.2); // clamp to [0.2, 1], the 0.2 means everything is given a little bit of color -> ambient
     
     vec4 light_based_out_color = vec4(total_diffuse, 1.0) * blended_texture_color + vec4(total_specular, 1.0);
-    out_Color = mix(vec4(sky_color, 1.0), light_based_out_color, visibility);
+    out_Color = light_based_out_color;
 
     out_brightness_Color = vec4(0.0);
 }
\ No newline at end of file
@@ -1,11 +1,11 @@
 #version 400 core
 
-const int LIGHT_NUM = 4;
-
 in vec4 clip_coords;
 in vec2 tex_coords;
 in vec3 to_camera_vec;
-in vec3 from_light[LIGHT_NUM];
+in vec3 pass_world_position;
+in vec3 pass_eye_position;
+in vec3 pass_surface_normal;
 
 layout(location = 0) out vec4 final_color;
 layout(location = 1) out vec4 out_brightness_Color;
@@ -15,10 +15,23 @@ uniform sampler2D refraction_tex;
 uniform sampler2D dudv_map;
 uniform sampler2D normal_map;
 uniform sampler2D depth_map;
+// the reflection of the screen space reflection pass, alpha is how sure its ray was to hit something
+uniform sampler2D ssr_tex;
+// 0 planar, 1 screen space, 2 hybrid, see WaterReflection
+uniform int reflection_mode;
 
 uniform float wave_factor;
-uniform vec3 light_color[LIGHT_NUM];
-uniform vec3 attenuation[LIGHT_NUM];
+
+// the material of the water body the tile belongs to
+uniform float wave_strength;
+uniform float water_reflectivity;
+uniform vec3 tint;
+uniform float tint_strength;
+uniform float murkiness;
+
+// raindrops hitting the surface make rings that spread out, see entities/weather.rs
+uniform float rain;
+uniform float wind_time;
 
 // these are the coefficients from the perspective transform matrix
 // we use them to get the real depth (real z) from the ndc coord z [-1,1] range
@@ -26,17 +39,38 @@ uniform vec3 attenuation[LIGHT_NUM];
 uniform float depth_calc_A;
 uniform float depth_calc_B;
 
-// fog
-uniform vec3 sky_color;
-
-const float wave_strength = 0.04;
-const float water_reflectivity = 1.5;
+#include "lightData.glsl"
+#include "lightClusters.glsl"
 
 const float shine_damper = 20.0;
 const float shine_reflectivity = 0.5;
 
-const float fog_density = 0.007;
-const float fog_gradient = 1.5;
+vec2 hash2(vec2 p) {
+    return fract(sin(vec2(dot(p, vec2(127.1, 311.7)), dot(p, vec2(269.5, 183.3)))) * 43758.5453);
+}
+
+// how far the rings of the raindrops tilt the normal in x and z
+// every cell of a grid over the water gets one drop at a random spot which lands again once its ring faded
+vec2 rain_ripples(vec2 position, float time) {
+    const float ring_size = 0.6;
+    const float ring_width = 0.08;
+    vec2 cell = floor(position);
+    vec2 tilt = vec2(0.0);
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 neighbour = cell + vec2(x, y);
+            vec2 random = hash2(neighbour);
+            vec2 to_drop = position - (neighbour + random);
+            float dist = length(to_drop);
+            // 0 when the drop lands and 1 when its ring is gone
+            float age = fract(time * (0.8 + 0.4 * random.y) + random.x);
+            float from_ring = (dist - age * ring_size) / ring_width;
+            float ring = sin(clamp(from_ring, -1.0, 1.0) * 3.1416) * (1.0 - age) * step(abs(from_ring), 1.0);
+            tilt += ring * to_drop / max(dist, 0.001);
+        }
+    }
+    return tilt;
+}
 
 void main() {
     vec2 ndc_coords = clip_coords.xy / clip_coords.w;
@@ -69,7 +103,16 @@ void main() {
     refract_coords = clamp(refract_coords, 0.001, 0.999);
 
     vec4 reflection_color = texture(reflection_tex, reflect_coords);
+    if (reflection_mode != 0) {
+        // the ssr pass rendered the reflection at the undistorted position of the surface
+        vec4 ssr_color = texture(ssr_tex, clamp(texture_coords + total_distortion, 0.001, 0.999));
+        float ssr_factor = reflection_mode == 1 ? 1.0 : ssr_color.a;
+        reflection_color = mix(reflection_color, vec4(ssr_color.rgb, 1.0), ssr_factor);
+    }
     vec4 refraction_color = texture(refraction_tex, refract_coords);
+    // the deeper the bottom the more it disappears in the murk
+    float murk = clamp(water_depth * murkiness, 0.0, 1.0);
+    refraction_color = mix(refraction_color, vec4(tint, 1.0), murk);
     
     vec4 normal_color = texture(normal_map, distorted_tex_coords);
     // we want negative values in x and y in the normals
@@ -77,6 +120,13 @@ void main() {
     const float water_flattness = 3.0;
     vec3 normal = vec3(normal_color.r * 2.0 - 1.0, normal_color.b * water_flattness, normal_color.g * 2.0 - 1.0);
     normal = normalize(normal);
+    // bend the ripples of the normal map along the waves, without waves the wave normal is straight up and this changes nothing
+    normal = normalize(normalize(pass_surface_normal) * normal.y + vec3(normal.x, 0.0, normal.z));
+    if (rain > 0.0) {
+        // more rain makes denser rings
+        vec2 ripples = rain_ripples(pass_world_position.xz * (1.0 + rain), wind_time);
+        normal = normalize(normal + 0.3 * rain * vec3(ripples.x, 0.0, ripples.y));
+    }
 
     vec3 normalize_to_cam = normalize(to_camera_vec);    
     // 1 if to camera in same direction as water normal, 0 if perpendicular
@@ -85,24 +135,20 @@ void main() {
     refraction_factor = clamp(refraction_factor, 0.0, 1.0);
 
     vec3 total_specular = vec3(0.0);
-    for (int i = 0; i < LIGHT_NUM; ++i) {
-        float dist = length(from_light[i]);
-        float attenuation_factor = attenuation[i].x + attenuation[i].y * dist + attenuation[i].z * dist * dist;
+    uvec2 cluster = find_cluster(pass_eye_position);
+    for (uint i = 0u; i < cluster.y; ++i) {
+        vec3 light_color;
+        vec3 from_light = -fetch_light(cluster_light(cluster, i), pass_world_position, light_color);
 
-        vec3 reflected = reflect(from_light[i], normal);
+        vec3 reflected = reflect(from_light, normal);
         reflected = normalize(reflected);
         float spec_factor = max(dot(reflected, normalize_to_cam), 0.0);
-        total_specular += (pow(spec_factor, shine_damper) * shine_reflectivity * light_color[i]) / attenuation_factor;
+        total_specular += pow(spec_factor, shine_damper) * shine_reflectivity * light_color;
     }
         
-    // compute visibility    
-    float fog_vis_coef = exp(-pow(water_surface_depth_real_z * fog_density, fog_gradient));
-    float visibility = clamp(fog_vis_coef, 0.0, 1.0);
-
     final_color = mix(reflection_color, refraction_color, refraction_factor);
-    // mix with a bit of blue/gree
-    final_color = mix(final_color, vec4(0.0, 0.3, 0.5, 1.0), 0.2) + vec4(total_specular * water_blend_factor, 0.0);;    
-    final_color = mix(vec4(sky_color, 1.0), final_color, visibility);
+    // mix with a bit of the tint of the water
+    final_color = mix(final_color, vec4(tint, 1.0), tint_strength) + vec4(total_specular * water_blend_factor, 0.0);
     final_color.a = water_blend_factor;
 
     out_brightness_Color = vec4(0.0);
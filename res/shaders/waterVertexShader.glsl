@@ -1,24 +1,59 @@
 #version 400 core
 
-const int LIGHT_NUM = 4;
-
 in vec3 position;
 
 out vec4 clip_coords;
 out vec2 tex_coords;
 out vec3 to_camera_vec;
-out vec3 from_light[LIGHT_NUM];
+out vec3 pass_world_position;
+out vec3 pass_eye_position;
+out vec3 pass_surface_normal;
 
 uniform mat4 projection_matrix;
 uniform mat4 view_matrix;
 uniform mat4 transform_matrix;
 uniform vec3 camera_world_pos;
-uniform vec3 light_pos[LIGHT_NUM];
+// how often the dudv and normal maps repeat over the tile, bigger tiles repeat them more
+uniform vec2 tiling;
+
+// gerstner waves, the same math as WaterWaves in entities/water_waves.rs so the cpu knows where the surface is
+const int MAX_WAVES = 4;
+const float GRAVITY = 9.81;
+const float PI = 3.14159265;
+uniform int wave_count;
+uniform float wave_time;
+// direction xz, amplitude, wavelength
+uniform vec4 waves[MAX_WAVES];
+uniform float wave_steepness[MAX_WAVES];
 
-const float tiling = 4.0;
+// moves the flat point and returns the analytic normal of the surface there
+vec3 apply_waves(inout vec3 point) {
+    vec2 flat_xz = point.xz;
+    vec3 normal = vec3(0.0, 1.0, 0.0);
+    for (int i = 0; i < wave_count; ++i) {
+        vec2 direction = waves[i].xy;
+        float amplitude = waves[i].z;
+        float k = 2.0 * PI / waves[i].w;
+        float speed = sqrt(GRAVITY / k);
+        float q = amplitude > 0.0 ? wave_steepness[i] / (k * amplitude * float(wave_count)) : 0.0;
+        float phase = k * (dot(direction, flat_xz) - speed * wave_time);
+        float s = sin(phase);
+        float c = cos(phase);
+        point.x += q * amplitude * direction.x * c;
+        point.y += amplitude * s;
+        point.z += q * amplitude * direction.y * c;
+        float ka = k * amplitude;
+        normal.x -= direction.x * ka * c;
+        normal.y -= q * ka * s;
+        normal.z -= direction.y * ka * c;
+    }
+    return normalize(normal);
+}
 
 void main() {
-    vec4 world_pos = transform_matrix * vec4(position, 1.0);
+    vec3 wave_pos = (transform_matrix * vec4(position, 1.0)).xyz;
+    pass_surface_normal = apply_waves(wave_pos);
+    vec4 world_pos = vec4(wave_pos, 1.0);
     vec4 eye_pos = view_matrix * world_pos;
     clip_coords = projection_matrix * eye_pos;
     gl_Position = clip_coords;
@@ -26,7 +61,6 @@ void main() {
     tex_coords = (position.xz / 2.0 + 0.5) * tiling;
 
     to_camera_vec = camera_world_pos - world_pos.xyz;
-    for (int i=0; i < LIGHT_NUM; ++i) {
-        from_light[i] = world_pos.xyz - light_pos[i];
-    }
+    pass_world_position = world_pos.xyz;
+    pass_eye_position = eye_pos.xyz;
 }
\ No newline at end of file
@@ -5,14 +5,16 @@ use copper::entities::player::{
     Player,
     PlayerEntityType,
 };
-use copper::display::{
-    Display,
-    framebuffers::FboMap,
+use copper::entities::{
+    Light,
+    WaterTile,
 };
+use copper::display::Display;
 use copper::renderers::{
     master_renderer::MasterRenderer,
-    master_renderer::RenderGroup,
     gui_renderer::GuiRenderer,
+    culling::CullingStats,
+    render_graph::RenderGraph,
 };
 use copper::models::{
     ResourceManager,
@@ -20,7 +22,10 @@ use copper::models::{
 use copper::particles::{
     ParticleMaster,
 };
-use copper::post_processing::post_processing::PostProcessing;
+use copper::post_processing::{
+    post_effect::PostEffect,
+    post_processing::PostProcessing,
+};
 use copper::mouse_picker::MousePicker;
 use copper::scenes::{
     scene::Scene,
@@ -29,16 +34,18 @@ use copper::scenes::{
     //environment_map_scene::*,
     load_screen::*,
 };
-use copper::gl;
 
 use std::thread;
 use std::time::Duration;
 
 fn main() {
     let mut display = Display::create();
-    let mut framebuffers = FboMap::new_rendering_fbos(&display);
+    let mut render_graph = RenderGraph::new_frame_graph();
+    // compiled right away so the scenes can show the shadow maps in the gui
+    render_graph.compile(&display);
     let mut resource_manager = ResourceManager::default();
     let mut gui_renderer = GuiRenderer::new();
+    gui_renderer.update_aspect_ratio(display.get_aspect_ratio());
     
     init_resourced_for_load_screen(&mut resource_manager);
     while resource_manager.are_textures_loading() && !display.is_close_requested() {
@@ -53,6 +60,9 @@ fn main() {
     while (!resource_init_started || resource_manager.are_textures_loading()) && !display.is_close_requested() {        
         gui_renderer.render(&load_screen.guis, &load_screen.gui_model.raw_model, &load_screen.texts);
         display.update_display();
+        if display.was_resized() {
+            gui_renderer.update_aspect_ratio(display.get_aspect_ratio());
+        }
         if !resource_init_started {
             init_scene_resources(&mut resource_manager);
             resource_init_started = true;
@@ -62,9 +72,12 @@ fn main() {
         return;
     }
 
-    let mut scene = create_scene(&mut resource_manager, &framebuffers);
+    let mut scene = create_scene(&mut resource_manager, &render_graph);
+    for pass in scene.disabled_passes.iter() {
+        render_graph.set_enabled(*pass, false);
+    }
     
-    let mut master_renderer = MasterRenderer::new(&display.projection_matrix, display.get_aspect_ratio());    
+    let mut master_renderer = MasterRenderer::new(&display.projection_matrix, display.get_aspect_ratio(), &scene.shadow_cascades);    
     
     let mut mouse_picker = MousePicker::new();
 
@@ -73,6 +86,12 @@ fn main() {
     // particle effects master
     let mut particle_master = ParticleMaster::new(&display.projection_matrix);
     let mut post_processing = PostProcessing::new(scene.quad_model.clone(), &display);
+    if let Err(error) = post_processing.configure(&scene.post_effects, &mut render_graph) {
+        println!("Invalid post processing stack of the scene, using the default one: {}", error);
+        post_processing.configure(&PostEffect::default_stack(), &mut render_graph).expect("The default post processing stack must be valid");
+    }
+    let mut last_culling_stats = CullingStats::default();
+    let mut last_dumped_graph = None;
         
     while !display.is_close_requested() {
 
@@ -86,21 +105,41 @@ fn main() {
         
         particle_master.emit_particles(&scene.particle_systems, &display);
         
-        particle_master.update(&display, &scene.camera);
+        scene.weather.update(&display);
 
-        scene.player.move_player(&display, &scene.ground);
+        particle_master.update(&display, &scene.camera, &scene.weather.wind, &scene.water);
+
+        scene.ground.update(scene.player.position(), &mut resource_manager);
+
+        scene.player.move_player(&display, &scene.ground, &scene.water);
+
+        float_entities_on_water(&mut scene, &display);
 
         scene.skybox.increase_rotation(&display);
 
-        master_renderer.render(&scene.lights, &mut scene.camera, &scene.entities, &scene.normal_mapped_entities, &scene.ground.terrains, 
-            &scene.player, &scene.water, &scene.skybox, &display, &mut framebuffers, &mut particle_master, &mut scene.entities_with_env_map, &mut scene.debug_entity);
+        move_sun_with_sky(&mut scene, &display);
+
+        master_renderer.render(&mut render_graph, &mut scene, &display, &mut particle_master, &mut post_processing, &mut gui_renderer);
 
-        do_post_processing(scene.uses_post_processing, &mut post_processing, &mut framebuffers, &display);
+        print_culling_stats(&master_renderer, &display, &mut last_culling_stats);
 
-        gui_renderer.render(&scene.guis, &scene.quad_model.raw_model, &scene.texts);
+        print_render_graph(&render_graph, &display, &mut last_dumped_graph);
 
         display.update_display();
+
+        handle_resize(&display, &mut master_renderer, &mut particle_master, &mut post_processing, &mut gui_renderer);
+    }
+}
+
+// the render graph notices the new render size by itself when it compiles, everything else has to be told
+fn handle_resize(display: &Display, master_renderer: &mut MasterRenderer, particle_master: &mut ParticleMaster, post_processing: &mut PostProcessing, gui_renderer: &mut GuiRenderer) {
+    if !display.was_resized() {
+        return;
     }
+    master_renderer.update_projection_matrix(&display.projection_matrix, display.get_aspect_ratio());
+    particle_master.update_projection_matrix(&display.projection_matrix);
+    post_processing.resize(display);
+    gui_renderer.update_aspect_ratio(display.get_aspect_ratio());
 }
 
 fn update_animations(animator: &Animator, player: &mut Player, display: &Display) {
@@ -117,42 +156,55 @@ fn update_animations(animator: &Animator, player: &mut Player, display: &Display
     }
 }
 
-fn do_post_processing(uses_post_processing: bool, post_processing: &mut PostProcessing, framebuffers: &mut FboMap, display: &Display) {    
-    
-    gl::helper::push_debug_group(RenderGroup::POST_PROCESSING.id, RenderGroup::POST_PROCESSING.name);
-
-    if uses_post_processing {
-        do_anti_aliasing_for_fbo(post_processing, framebuffers, display);
-        post_processing.do_post_processing(display);
-    } else {
-        do_anti_aliasing_to_screen(framebuffers, display);
+fn print_culling_stats(master_renderer: &MasterRenderer, display: &Display, last_culling_stats: &mut CullingStats) {
+    if !display.is_culling_stats_active() {
+        return;
+    }
+    let culling_stats = master_renderer.culling_stats();
+    if *culling_stats != *last_culling_stats {
+        println!("{}", culling_stats);
+        *last_culling_stats = culling_stats.clone();
     }
-
-    gl::helper::pop_debug_group();
-}
-
-fn do_anti_aliasing_for_fbo(post_processing: &mut PostProcessing, framebuffers: &mut FboMap, display: &Display) {
-    let camera_multisampled_fbo = framebuffers.fbos.get_mut(FboMap::CAMERA_TEXTURE_FBO_MULTI).expect("A multisampled fbo must be present MSAA processing of camera output");
-
-    // create the target fbo that will later be read from in post processing shaders
-    let mut camera_texture_fbo = post_processing.post_processing_fbos.fbos.get_mut(FboMap::CAMERA_TEXTURE_FBO).expect("A camera texture fbo is needed to write the resolved MSAA camera output to");
-    camera_multisampled_fbo.resolve_to_fbo(gl::COLOR_ATTACHMENT0, &mut camera_texture_fbo, display);
-
-    let mut camera_brightness_fbo = post_processing.post_processing_fbos.fbos.get_mut(FboMap::CAMERA_BRIGHTNESS_FBO).expect("A post processing brightness fbo is needed to write the glow brightness into");
-    camera_multisampled_fbo.resolve_to_fbo(gl::COLOR_ATTACHMENT1, &mut camera_brightness_fbo, display);
 }
 
-fn do_anti_aliasing_to_screen(framebuffers: &mut FboMap, display: &Display) {
-    let camera_multisampled_fbo = framebuffers.fbos.get_mut(FboMap::CAMERA_TEXTURE_FBO_MULTI).expect("A multisampled fbo must be present MSAA processing of camera output");
-    camera_multisampled_fbo.resolve_to_screen(&display);
+// prints the graph when the dump gets turned on and every time it is compiled again
+fn print_render_graph(render_graph: &RenderGraph, display: &Display, last_dumped_graph: &mut Option<usize>) {
+    if !display.is_render_graph_dump_active() {
+        *last_dumped_graph = None;
+        return;
+    }
+    if *last_dumped_graph != Some(render_graph.generation()) {
+        println!("{}", render_graph);
+        *last_dumped_graph = Some(render_graph.generation());
+    }
 }
 
 fn update_mouse_picker_and_move_lamp(mouse_picker: &mut MousePicker, display: &Display, scene: &mut Scene) {
     if let Some(selected_pos) = mouse_picker.update(&display, &display.projection_matrix, &scene.camera, &scene.ground) {            
         let last_pos = scene.entities.len()-1;
         scene.entities[last_pos].set_position(&selected_pos);
-        scene.lights[3].position = selected_pos;
-        scene.lights[3].position.y += 14.0; 
+        let mut lamp_light_pos = selected_pos;
+        lamp_light_pos.y += 14.0;
+        scene.lights[3].set_position(&lamp_light_pos);
+    }
+}
+
+fn float_entities_on_water(scene: &mut Scene, display: &Display) {
+    // how deep the floating things sit in the water
+    const DRAFT: f32 = 0.8;
+    for &idx in scene.floating_entities.iter() {
+        let entity = &mut scene.entities[idx];
+        let (x, z) = (entity.position.x, entity.position.z);
+        if let Some(water_height) = WaterTile::surface_height_at(&scene.water, x, z, display.wall_clock.elapsed) {
+            entity.position.y = (water_height - DRAFT).max(scene.ground.height_at_xz(x, z));
+        }
+    }
+}
+
+// a procedural sky moves the shadow casting sun over the day and turns it into the moon at night
+fn move_sun_with_sky(scene: &mut Scene, display: &Display) {
+    if let (Some(sky), Some(sun)) = (&scene.skybox.procedural, Light::shadow_caster(&scene.lights)) {
+        sky.update_light(&mut scene.lights[sun], &display.wall_clock);
     }
 }
 
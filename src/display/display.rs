@@ -5,6 +5,7 @@ use gl::glfw::{
     flush_messages,
     Glfw,
     init,
+    Monitor,
     MouseButtonLeft,
     MouseButtonMiddle,    
     MouseButtonRight,
@@ -86,12 +87,20 @@ impl fmt::Display for MousePosData {
 #[derive(Default)]
 pub struct WallClock {
     pub time_of_day: f32,
+    // seconds since the start, wraps much less often than the day so the water waves don't jump at midnight
+    pub elapsed: f32,
 }
 
 impl WallClock {
     pub const DAY_LENGTH: f32 = 240.0;
+    // a whole number of days, any longer and the f32 gets too coarse for the animations after a few hours
+    pub const ELAPSED_PERIOD: f32 = 15.0 * WallClock::DAY_LENGTH;
 
     pub fn update(&mut self, frame_time_sec: f32) {
+        self.elapsed += frame_time_sec;
+        if self.elapsed >= WallClock::ELAPSED_PERIOD {
+            self.elapsed %= WallClock::ELAPSED_PERIOD;
+        }
         self.time_of_day += frame_time_sec;
         if self.time_of_day >= WallClock::DAY_LENGTH {
             self.time_of_day %= WallClock::DAY_LENGTH;
@@ -109,6 +118,21 @@ pub struct Display {
     events: Receiver<(f64, WindowEvent)>,
     last_frame_sys_time: SystemTime,
     mouse_select_active: bool,
+    // lets us compare the deferred and the forward path at runtime
+    deferred_rendering_active: bool,
+    // prints the frustum culling counters whenever they change
+    culling_stats_active: bool,
+    // prints the pass order and fbos of the render graph whenever it gets compiled
+    render_graph_dump_active: bool,
+    fullscreen: bool,
+    fullscreen_toggle_requested: bool,
+    // where the window was before going fullscreen: x, y, width, height
+    windowed_placement: (i32, i32, u32, u32),
+    // the scene is rendered at this fraction of the window size and scaled up in the tonemapping
+    resolution_scale: f32,
+    size_change_pending: bool,
+    // true for the frame after the window or the render size changed
+    resized: bool,
 }
 
 impl Keyboard for Display {
@@ -129,6 +153,8 @@ impl Display {
     // here using actual world coords which are RHS coord sys with z axis going into screen (so more negative means further)
     pub const NEAR: f32 = -0.1;
     pub const FAR: f32 = -1000.0;
+    pub const MIN_RESOLUTION_SCALE: f32 = 0.25;
+    const RESOLUTION_SCALE_STEP: f32 = 0.25;
 
     pub fn create() -> Display {        
         let mut glfw = init(FAIL_ON_ERRORS).unwrap();
@@ -148,6 +174,8 @@ impl Display {
         window.set_mouse_button_polling(true);
         window.set_scroll_polling(true);
         window.set_key_polling(true);
+        window.set_framebuffer_size_polling(true);
+        let (window_x, window_y) = window.get_pos();
 
         Display::print_opengl_info(&window);
 
@@ -169,6 +197,15 @@ impl Display {
             mouse_pos: MousePosData::default(),
             wall_clock: WallClock::default(),
             mouse_select_active: false,
+            deferred_rendering_active: false,
+            culling_stats_active: false,
+            render_graph_dump_active: false,
+            fullscreen: false,
+            fullscreen_toggle_requested: false,
+            windowed_placement: (window_x, window_y, WIDTH, HEIGHT),
+            resolution_scale: 1.0,
+            size_change_pending: false,
+            resized: false,
             projection_matrix,
         }
     }
@@ -183,6 +220,19 @@ impl Display {
         }
     }
 
+    // what the scene gets rendered at, the framebuffer size of the window times the resolution scale
+    pub fn get_render_size(&self) -> WindowSize {
+        let (w, h) = self.window.get_framebuffer_size();
+        let width = ((w as f32 * self.resolution_scale) as usize).max(1);
+        let height = ((h as f32 * self.resolution_scale) as usize).max(1);
+        WindowSize {
+            width_f32: width as f32,
+            height_f32: height as f32,
+            width,
+            height,
+        }
+    }
+
     pub fn get_aspect_ratio(&self) -> f32 {
         Display::get_aspect_ratio_internal(&self.window)
     }
@@ -215,6 +265,7 @@ impl Display {
     }
 
     pub fn update_display(&mut self) {        
+        self.resized = false;
         self.update_viewport();
 
         self.window.swap_buffers();
@@ -226,9 +277,82 @@ impl Display {
 
         self.mouse_pos.set_prev_to_cur();
 
-        for (_, event) in flush_messages(&self.events) {
-            Display::handle_window_event(&mut self.mouse_pos, &mut self.mouse_select_active, event);
-        }    
+        let events: Vec<WindowEvent> = flush_messages(&self.events).map(|(_, event)| event).collect();
+        for event in events {
+            self.handle_window_event(event);
+        }
+
+        if self.fullscreen_toggle_requested {
+            self.fullscreen_toggle_requested = false;
+            self.toggle_fullscreen();
+        }
+        self.apply_size_change();
+    }
+
+    // a minimized window has a zero size, we keep everything as it was until it comes back
+    fn apply_size_change(&mut self) {
+        let (width, height) = self.window.get_framebuffer_size();
+        if !self.size_change_pending || width <= 0 || height <= 0 {
+            return;
+        }
+        self.size_change_pending = false;
+        self.projection_matrix = Matrix4f::create_projection_matrix(Display::NEAR, Display::FAR, Display::FOV_HORIZONTAL, Display::get_aspect_ratio_internal(&self.window));
+        self.update_viewport();
+        self.resized = true;
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        if self.fullscreen {
+            let (x, y, width, height) = self.windowed_placement;
+            self.window.set_monitor(WindowMode::Windowed, x, y, width, height, None);
+            self.fullscreen = false;
+        } else {
+            let monitor = Monitor::from_primary();
+            let video_mode = match monitor.get_video_mode() {
+                Some(video_mode) => video_mode,
+                None => {
+                    println!("Unable to go fullscreen, the primary monitor has no video mode");
+                    return;
+                },
+            };
+            let (x, y) = self.window.get_pos();
+            let (width, height) = self.window.get_size();
+            self.windowed_placement = (x, y, width as u32, height as u32);
+            self.window.set_monitor(WindowMode::FullScreen(&monitor), 0, 0, video_mode.width, video_mode.height, Some(video_mode.refresh_rate));
+            self.fullscreen = true;
+        }
+        // the framebuffer size event may not come when the new size is the same as before
+        self.size_change_pending = true;
+    }
+
+    // clamped to [MIN_RESOLUTION_SCALE, 1]
+    pub fn set_resolution_scale(&mut self, resolution_scale: f32) {
+        let resolution_scale = resolution_scale.max(Display::MIN_RESOLUTION_SCALE).min(1.0);
+        if resolution_scale != self.resolution_scale {
+            self.resolution_scale = resolution_scale;
+            self.size_change_pending = true;
+        }
+    }
+
+    pub fn resolution_scale(&self) -> f32 {
+        self.resolution_scale
+    }
+
+    // the fbos, the projection and the gui have to follow when this is true
+    pub fn was_resized(&self) -> bool {
+        self.resized
+    }
+
+    pub fn is_deferred_rendering_active(&self) -> bool {
+        self.deferred_rendering_active
+    }
+
+    pub fn is_culling_stats_active(&self) -> bool {
+        self.culling_stats_active
+    }
+
+    pub fn is_render_graph_dump_active(&self) -> bool {
+        self.render_graph_dump_active
     }
 
     pub fn is_close_requested(&self) -> bool {  
@@ -245,36 +369,80 @@ impl Display {
         self.last_frame_sys_time = current_time;
     }
 
-    fn handle_window_event(mouse_pos: &mut MousePosData, mouse_select_active: &mut bool, event: WindowEvent) {
+    fn handle_window_event(&mut self, event: WindowEvent) {
         match event {
             WindowEvent::CursorPos(x, y) => {
-                mouse_pos.prev_x = mouse_pos.cur_x;
-                mouse_pos.prev_y = mouse_pos.cur_y;
-                mouse_pos.cur_x = x;
-                mouse_pos.cur_y = y;
+                self.mouse_pos.prev_x = self.mouse_pos.cur_x;
+                self.mouse_pos.prev_y = self.mouse_pos.cur_y;
+                self.mouse_pos.cur_x = x;
+                self.mouse_pos.cur_y = y;
             },
             WindowEvent::MouseButton(button, action, _) => {
                 match (button, action) {
-                    (MouseButtonLeft, Action::Press) => { mouse_pos.is_left_pressed = true; },
-                    (MouseButtonLeft, Action::Release) => { mouse_pos.is_left_pressed = false; },
-                    (MouseButtonRight, Action::Press) => { mouse_pos.is_right_pressed = true; },
-                    (MouseButtonRight, Action::Release) => { mouse_pos.is_right_pressed = false; },
-                    (MouseButtonMiddle, Action::Press) => { mouse_pos.is_middle_pressed = true; },
-                    (MouseButtonMiddle, Action::Release) => { mouse_pos.is_middle_pressed = false; },
+                    (MouseButtonLeft, Action::Press) => { self.mouse_pos.is_left_pressed = true; },
+                    (MouseButtonLeft, Action::Release) => { self.mouse_pos.is_left_pressed = false; },
+                    (MouseButtonRight, Action::Press) => { self.mouse_pos.is_right_pressed = true; },
+                    (MouseButtonRight, Action::Release) => { self.mouse_pos.is_right_pressed = false; },
+                    (MouseButtonMiddle, Action::Press) => { self.mouse_pos.is_middle_pressed = true; },
+                    (MouseButtonMiddle, Action::Release) => { self.mouse_pos.is_middle_pressed = false; },
                     _ => {}
                 }
             },
             WindowEvent::Scroll(_x_scroll, y_scroll) => {                
-                mouse_pos.cur_scroll = y_scroll;
+                self.mouse_pos.cur_scroll = y_scroll;
             },
             WindowEvent::Key(key, _, action, _) => {
                 if key == Key::M && action == Action::Press {
-                    *mouse_select_active = !*mouse_select_active;
-                    println!("Toggled mouse select: {}", mouse_select_active);
+                    self.mouse_select_active = !self.mouse_select_active;
+                    println!("Toggled mouse select: {}", self.mouse_select_active);
+                }
+                if key == Key::G && action == Action::Press {
+                    self.deferred_rendering_active = !self.deferred_rendering_active;
+                    println!("Toggled deferred rendering: {}", self.deferred_rendering_active);
+                }
+                if key == Key::C && action == Action::Press {
+                    self.culling_stats_active = !self.culling_stats_active;
+                    println!("Toggled culling stats: {}", self.culling_stats_active);
+                }
+                if key == Key::R && action == Action::Press {
+                    self.render_graph_dump_active = !self.render_graph_dump_active;
+                    println!("Toggled render graph dump: {}", self.render_graph_dump_active);
+                }
+                if key == Key::F11 && action == Action::Press {
+                    self.fullscreen_toggle_requested = true;
+                }
+                if (key == Key::PageUp || key == Key::PageDown) && action == Action::Press {
+                    let step = if key == Key::PageUp { Display::RESOLUTION_SCALE_STEP } else { -Display::RESOLUTION_SCALE_STEP };
+                    self.set_resolution_scale(self.resolution_scale + step);
+                    println!("Resolution scale: {}", self.resolution_scale);
                 }
             },
+            WindowEvent::FramebufferSize(_, _) => {
+                self.size_change_pending = true;
+            },
             _ => {}
         }
     }
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wall_clock_wraps() {
+        let mut wall_clock = WallClock::default();
+        let frame_time_sec = 0.25;
+        let frames = (3.0 * WallClock::ELAPSED_PERIOD / frame_time_sec) as usize + 2;
+        for _ in 0..frames {
+            wall_clock.update(frame_time_sec);
+            assert!(wall_clock.elapsed < WallClock::ELAPSED_PERIOD);
+            assert!(wall_clock.time_of_day < WallClock::DAY_LENGTH);
+        }
+        assert_eq!(wall_clock.elapsed, 2.0 * frame_time_sec);
+        // the elapsed time wraps at midnight too so the clock stays on the same time of day
+        assert_eq!(wall_clock.elapsed % WallClock::DAY_LENGTH, wall_clock.time_of_day);
+    }
+}
@@ -9,6 +9,8 @@ bitflags! {
         const DEPTH_RENDERBUF   = 0b0001000;
         const SHADOW_DEPTH      = 0b0010000;
         const MULTISAMPLED      = 0b0100000;
+        // half float color attachments so we can store hdr values above 1.0
+        const FLOAT_COLOR       = 0b1000000;
     }
 }
 
@@ -18,6 +20,8 @@ pub struct FramebufferObject {
     pub viewport_height: usize,
     color_textures: Option<Vec<u32>>,
     pub depth_texture: Option<u32>,
+    // one 2d view per layer when the depth texture is a texture array so we can show the layers in the gui
+    pub depth_layer_views: Option<Vec<u32>>,
     pub depth_renderbuffer_id: Option<u32>,
     color_renderbuffer_ids: Option<Vec<u32>>,
 }
@@ -25,7 +29,7 @@ pub struct FramebufferObject {
 impl FramebufferObject {
     // number of samples used for multisampled anti aliasing MSAA
     const SAMPLE_NUM: usize = 4;
-    const ATTACHMENT_IDS: [gl::types::GLenum; 3] = [gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1, gl::COLOR_ATTACHMENT2];
+    const ATTACHMENT_IDS: [gl::types::GLenum; 4] = [gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1, gl::COLOR_ATTACHMENT2, gl::COLOR_ATTACHMENT3];
 
     pub fn new(viewport_width: usize, viewport_height: usize, flags: FboFlags, num_color_attachments: usize) -> Self {
         assert!(num_color_attachments <= Self::ATTACHMENT_IDS.len());
@@ -33,7 +37,7 @@ impl FramebufferObject {
         let color_textures = if flags.contains(FboFlags::COLOR_TEX) {
             let mut color_attachs = Vec::new();
             for i in 0..num_color_attachments {
-                color_attachs.push(Self::create_color_texture_attachment(Self::ATTACHMENT_IDS[i], viewport_width, viewport_height))
+                color_attachs.push(Self::create_color_texture_attachment(Self::ATTACHMENT_IDS[i], viewport_width, viewport_height, flags.contains(FboFlags::FLOAT_COLOR)))
             }
             Some(color_attachs)
         } else {
@@ -55,7 +59,7 @@ impl FramebufferObject {
         let color_renderbuffer_ids = if flags.contains(FboFlags::COLOR_RENDERBUF) {
             let mut color_attachs = Vec::new();
             for i in 0..num_color_attachments {
-                color_attachs.push(Self::create_color_renderbuffer_attachment(Self::ATTACHMENT_IDS[i], viewport_width, viewport_height, flags.contains(FboFlags::MULTISAMPLED)))
+                color_attachs.push(Self::create_color_renderbuffer_attachment(Self::ATTACHMENT_IDS[i], viewport_width, viewport_height, flags.contains(FboFlags::MULTISAMPLED), flags.contains(FboFlags::FLOAT_COLOR)))
             }
             Some(color_attachs)
         } else {
@@ -68,11 +72,72 @@ impl FramebufferObject {
             viewport_height,
             color_textures,
             depth_texture,
+            depth_layer_views: None,
             depth_renderbuffer_id,
             color_renderbuffer_ids,
         }
     }
 
+    // depth only fbo backed by a texture array, bind_depth_layer picks which layer we render into
+    // used for the shadow cascades which all get the same size map
+    pub fn new_layered_shadow_depth(viewport_width: usize, viewport_height: usize, layers: usize) -> Self {
+        let fbo_id = Self::create_frame_buffer(FboFlags::SHADOW_DEPTH, 0);
+        let depth_texture = Self::create_depth_texture_array_for_shadows(viewport_width, viewport_height, layers);
+        let depth_layer_views = (0..layers).map(|layer| {
+            let view_id = gl::gen_texture();
+            gl::texture_view(view_id, gl::TEXTURE_2D, depth_texture, gl::DEPTH_COMPONENT32F, 0, 1, layer as u32, 1);
+            view_id
+        }).collect();
+        Self::check_framebuffer();
+        FramebufferObject {
+            fbo_id,
+            viewport_width,
+            viewport_height,
+            color_textures: None,
+            depth_texture: Some(depth_texture),
+            depth_layer_views: Some(depth_layer_views),
+            depth_renderbuffer_id: None,
+            color_renderbuffer_ids: None,
+        }
+    }
+
+    // depth only fbo backed by a cube map array with all the layers attached at once
+    // a geometry shader picks the face and cube through gl_Layer, used for the point light shadows
+    pub fn new_cube_shadow_depth(size: usize, cubes: usize) -> Self {
+        let fbo_id = Self::create_frame_buffer(FboFlags::SHADOW_DEPTH, 0);
+        let depth_texture = Self::create_depth_cube_array_for_shadows(size, cubes);
+        Self::check_framebuffer();
+        FramebufferObject {
+            fbo_id,
+            viewport_width: size,
+            viewport_height: size,
+            color_textures: None,
+            depth_texture: Some(depth_texture),
+            depth_layer_views: None,
+            depth_renderbuffer_id: None,
+            color_renderbuffer_ids: None,
+        }
+    }
+
+    // color cube map array with a depth renderbuffer for one face, bind_color_layer picks the face we render into
+    // used for the reflection probes which render their faces one at a time like a normal camera
+    pub fn new_cube_color(size: usize, cubes: usize) -> Self {
+        let fbo_id = Self::create_frame_buffer(FboFlags::COLOR_TEX, 1);
+        let color_texture = Self::create_color_cube_array(size, cubes);
+        let depth_renderbuffer_id = Self::create_depth_renderbuffer_attachment(size, size, false);
+        Self::check_framebuffer();
+        FramebufferObject {
+            fbo_id,
+            viewport_width: size,
+            viewport_height: size,
+            color_textures: Some(vec![color_texture]),
+            depth_texture: None,
+            depth_layer_views: None,
+            depth_renderbuffer_id: Some(depth_renderbuffer_id),
+            color_renderbuffer_ids: None,
+        }
+    }
+
     pub fn create_frame_buffer(flags: FboFlags, num_color_attachments: usize) -> u32 {
         let fbo_id = gl::gen_framebuffer();
         gl::bind_framebuffer(gl::FRAMEBUFFER, fbo_id);
@@ -86,10 +151,14 @@ impl FramebufferObject {
         fbo_id
     }
 
-    pub fn create_color_texture_attachment(color_attach_id: gl::types::GLenum, width: usize, height: usize) -> u32 {
+    pub fn create_color_texture_attachment(color_attach_id: gl::types::GLenum, width: usize, height: usize, float_color: bool) -> u32 {
         let tex_id = gl::gen_texture();
         gl::bind_texture(gl::TEXTURE_2D, tex_id);
-        gl::tex_image_2d_uninitialized(gl::TEXTURE_2D, 0, gl::RGB, gl::RGB, width, height, gl::UNSIGNED_BYTE);
+        if float_color {
+            gl::tex_image_2d_uninitialized(gl::TEXTURE_2D, 0, gl::RGBA, gl::RGBA16F, width, height, gl::FLOAT);
+        } else {
+            gl::tex_image_2d_uninitialized(gl::TEXTURE_2D, 0, gl::RGB, gl::RGB, width, height, gl::UNSIGNED_BYTE);
+        }
         gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR);
         gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR);
         // attach mipmap level 0 of texture (tex_id -> unitialized) to the color attach0 of current framebuffer 
@@ -121,6 +190,50 @@ impl FramebufferObject {
         tex_id
     }
 
+    pub fn create_depth_texture_array_for_shadows(width: usize, height: usize, layers: usize) -> u32 {
+        let tex_id = gl::gen_texture();
+        gl::bind_texture(gl::TEXTURE_2D_ARRAY, tex_id);
+        // immutable storage so we can make views of the layers
+        gl::tex_storage_3d(gl::TEXTURE_2D_ARRAY, 1, gl::DEPTH_COMPONENT32F, width, height, layers);
+        gl::tex_parameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::NEAREST);
+        gl::tex_parameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::NEAREST);
+        gl::tex_parameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        gl::tex_parameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        // attach the first layer so the framebuffer is complete, the shadow pass switches layers with bind_depth_layer
+        gl::framebuffer_texture_layer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, tex_id, 0, 0);
+        tex_id
+    }
+
+    pub fn create_depth_cube_array_for_shadows(size: usize, cubes: usize) -> u32 {
+        let tex_id = gl::gen_texture();
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP_ARRAY, tex_id);
+        // 6 layers per cube, one for every face
+        gl::tex_storage_3d(gl::TEXTURE_CUBE_MAP_ARRAY, 1, gl::DEPTH_COMPONENT32F, size, size, 6 * cubes);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_MIN_FILTER, gl::NEAREST);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_MAG_FILTER, gl::NEAREST);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE);
+        // attaching the whole texture makes the fbo layered
+        gl::framebuffer_texture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, tex_id, 0);
+        tex_id
+    }
+
+    pub fn create_color_cube_array(size: usize, cubes: usize) -> u32 {
+        let tex_id = gl::gen_texture();
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP_ARRAY, tex_id);
+        // 6 layers per cube in gl's face order, half floats like the rest of the scene
+        gl::tex_storage_3d(gl::TEXTURE_CUBE_MAP_ARRAY, 1, gl::RGBA16F, size, size, 6 * cubes);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        gl::tex_parameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE);
+        // attach the first face so the framebuffer is complete
+        gl::framebuffer_texture_layer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, tex_id, 0, 0);
+        tex_id
+    }
+
     pub fn create_depth_renderbuffer_attachment(width: usize, height: usize, multisampled: bool) -> u32 {
         let render_buffer_id = gl::gen_renderbuffer();
         gl::bind_renderbuffer(gl::RENDERBUFFER, render_buffer_id);
@@ -133,13 +246,14 @@ impl FramebufferObject {
         render_buffer_id
     }
 
-    pub fn create_color_renderbuffer_attachment(color_attach_id: gl::types::GLenum, width: usize, height: usize, multisampled: bool) -> u32 {
+    pub fn create_color_renderbuffer_attachment(color_attach_id: gl::types::GLenum, width: usize, height: usize, multisampled: bool, float_color: bool) -> u32 {
         let render_buffer_id = gl::gen_renderbuffer();
         gl::bind_renderbuffer(gl::RENDERBUFFER, render_buffer_id);
+        let internal_format = if float_color { gl::RGBA16F } else { gl::RGBA8 };
         if multisampled {
-            gl::renderbuffer_storage_multisampled(gl::RENDERBUFFER, gl::RGBA8, width, height, Self::SAMPLE_NUM);
+            gl::renderbuffer_storage_multisampled(gl::RENDERBUFFER, internal_format, width, height, Self::SAMPLE_NUM);
         } else {
-            gl::renderbuffer_storage(gl::RENDERBUFFER, gl::RGBA8, width, height);
+            gl::renderbuffer_storage(gl::RENDERBUFFER, internal_format, width, height);
         }
         gl::framebuffer_renderbuffer(gl::FRAMEBUFFER, color_attach_id, gl::RENDERBUFFER, render_buffer_id);
         render_buffer_id
@@ -178,7 +292,19 @@ impl FramebufferObject {
         Self::bind_framebuffer(self.fbo_id, self.viewport_width, self.viewport_height);
     }
 
-    pub fn resolve_to_fbo(&mut self, attachment_id: gl::types::GLenum, target_fbo: &mut FramebufferObject, display: &Display) {
+    // render depth into the given layer of the depth texture array, the fbo has to be bound
+    pub fn bind_depth_layer(&mut self, layer: usize) {
+        let depth_texture = self.depth_texture.expect("Must have a depth texture to bind one of its layers");
+        gl::framebuffer_texture_layer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, depth_texture, 0, layer);
+    }
+
+    // render color into the given layer of the color texture array, the fbo has to be bound
+    pub fn bind_color_layer(&mut self, layer: usize) {
+        let color_texture = self.color_texture(0).expect("Must have a color texture to bind one of its layers");
+        gl::framebuffer_texture_layer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, color_texture, 0, layer);
+    }
+
+    pub fn resolve_to_fbo(&self, attachment_id: gl::types::GLenum, target_fbo: &FramebufferObject, display: &Display) {
         // draw to target
         gl::bind_framebuffer(gl::DRAW_FRAMEBUFFER, target_fbo.fbo_id);
         // read from us
@@ -189,6 +315,7 @@ impl FramebufferObject {
         display.restore_default_framebuffer();
     }
 
+    // stretched to the window size, filtered so a lowered resolution scale doesn't look blocky
     pub fn resolve_to_screen(&mut self, display: &Display) {
         // draw to default fbo
         gl::bind_framebuffer(gl::DRAW_FRAMEBUFFER, 0);
@@ -197,7 +324,7 @@ impl FramebufferObject {
         let size = display.get_size();
         let width = size.width;
         let height = size.height;
-        gl::blit_framebuffer(0, 0, self.viewport_width, self.viewport_height, 0, 0, width, height, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+        gl::blit_framebuffer(0, 0, self.viewport_width, self.viewport_height, 0, 0, width, height, gl::COLOR_BUFFER_BIT, gl::LINEAR);
         display.restore_default_framebuffer();
     }
 }
@@ -210,6 +337,9 @@ impl Drop for FramebufferObject {
                 gl::delete_texture(*color_tex);
             }
         }
+        if let Some(depth_layer_views) = &self.depth_layer_views {
+            gl::delete_textures(depth_layer_views);
+        }
         if let Some(depth_tex) = self.depth_texture {
             gl::delete_texture(depth_tex);
         }
@@ -1,6 +1,4 @@
-pub mod fbo_map;
 pub mod framebuffer_object;
 
-pub use fbo_map::FboMap;
 pub use framebuffer_object::FramebufferObject;
 pub use framebuffer_object::FboFlags;
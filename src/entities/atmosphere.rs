@@ -0,0 +1,218 @@
+use crate::display::WallClock;
+use crate::math::{
+    Matrix4f,
+    Vector2f,
+    Vector3f,
+    Vector4f,
+};
+
+const DAY_SEGMENTS: f32 = WallClock::DAY_LENGTH / 4.0;
+
+// exponential fog that is densest at base_height and thins out the higher up it gets
+// applied to the whole resolved scene in one pass so every kind of geometry and the sky get the same fog
+#[derive(Debug, Clone)]
+pub struct HeightFog {
+    // extinction per world unit at base_height
+    pub density: f32,
+    // how fast the density drops per unit of height above base_height
+    pub falloff: f32,
+    pub base_height: f32,
+    pub day_color: Vector3f,
+    pub night_color: Vector3f,
+}
+
+impl HeightFog {
+    pub const DENSITY: f32 = 0.006;
+    pub const FALLOFF: f32 = 0.02;
+    // how much of the sun color bleeds into the fog when looking towards it
+    pub const SUN_SCATTERING: f32 = 0.5;
+
+    pub fn new(density: f32, falloff: f32, base_height: f32) -> HeightFog {
+        HeightFog {
+            density,
+            falloff,
+            base_height,
+            day_color: Vector3f::new(0.5444, 0.62, 0.69),
+            night_color: Vector3f::new(0.05, 0.06, 0.1),
+        }
+    }
+
+    pub fn with_colors(mut self, day_color: Vector3f, night_color: Vector3f) -> HeightFog {
+        self.day_color = day_color;
+        self.night_color = night_color;
+        self
+    }
+
+    // fades between the night and the day color the same way the skybox fades between its textures
+    pub fn color(&self, wall_clock: &WallClock) -> Vector3f {
+        // Vector3f::lerp weights the first vector by t
+        Vector3f::lerp(&self.day_color, &self.night_color, Atmosphere::daylight(wall_clock))
+    }
+
+    // the density integrated along the straight line between the two points
+    // the atmosphere shader does the same math per pixel between the camera and what the pixel shows
+    pub fn optical_depth(&self, from: &Vector3f, to: &Vector3f) -> f32 {
+        let distance = (to - from).length();
+        let start_density = self.density * (-self.falloff * (from.y - self.base_height)).exp();
+        let height_change = self.falloff * (to.y - from.y);
+        // for a flat ray the density doesn't change along it
+        let height_factor = if height_change.abs() > 1e-4 {
+            (1.0 - (-height_change).exp()) / height_change
+        } else {
+            1.0
+        };
+        start_density * height_factor * distance
+    }
+
+    // how much of the light from `to` makes it to `from`, the rest gets replaced by the fog color
+    pub fn transmittance(&self, from: &Vector3f, to: &Vector3f) -> f32 {
+        (-self.optical_depth(from, to)).exp()
+    }
+}
+
+impl Default for HeightFog {
+    fn default() -> HeightFog {
+        HeightFog::new(HeightFog::DENSITY, HeightFog::FALLOFF, 0.0)
+    }
+}
+
+// god rays of the directional light, the parts of the screen that show the sky get blurred radially away from the sun
+#[derive(Debug, Clone)]
+pub struct LightShafts {
+    // brightness of the shafts added on top of the scene
+    pub strength: f32,
+    // how much every sample further from the pixel counts less
+    pub decay: f32,
+    // fraction of the way to the sun the samples cover, shorter makes shorter rays
+    pub density: f32,
+}
+
+impl LightShafts {
+    // the shafts are blurred at a fraction of the screen size
+    pub const DOWNSCALE: usize = 2;
+
+    pub fn new(strength: f32) -> LightShafts {
+        LightShafts {
+            strength,
+            decay: 0.97,
+            density: 0.9,
+        }
+    }
+
+    // where the sun is on the screen in uv coordinates and how much its shafts count there
+    // they fade out as the sun leaves the screen and there are none with the sun behind the camera
+    pub fn sun_on_screen(view_projection: &Matrix4f, to_sun: &Vector3f) -> Option<(Vector2f, f32)> {
+        // a direction so it is infinitely far away and the camera position doesn't matter
+        let clip = view_projection.transform(&Vector4f::vector(to_sun));
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let (x, y) = (clip.x / clip.w, clip.y / clip.w);
+        let visibility = (2.0 - x.abs().max(y.abs())).clamp(0.0, 1.0);
+        if visibility == 0.0 {
+            return None;
+        }
+        Some((Vector2f::new(0.5 * x + 0.5, 0.5 * y + 0.5), visibility))
+    }
+}
+
+// the fog and the light shafts of a scene, both are optional
+#[derive(Debug, Clone, Default)]
+pub struct Atmosphere {
+    pub fog: Option<HeightFog>,
+    pub light_shafts: Option<LightShafts>,
+}
+
+impl Atmosphere {
+    pub fn new() -> Atmosphere {
+        Atmosphere::default()
+    }
+
+    pub fn with_fog(mut self, fog: HeightFog) -> Atmosphere {
+        self.fog = Some(fog);
+        self
+    }
+
+    pub fn with_light_shafts(mut self, light_shafts: LightShafts) -> Atmosphere {
+        self.light_shafts = Some(light_shafts);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.fog.is_some() || self.light_shafts.is_some()
+    }
+
+    // 0 at night 1 during the day, follows the segments of the skybox day night cycle
+    pub fn daylight(wall_clock: &WallClock) -> f32 {
+        let time = wall_clock.time_of_day;
+        if time < DAY_SEGMENTS {
+            time / DAY_SEGMENTS
+        } else if time < 2.0 * DAY_SEGMENTS {
+            1.0
+        } else if time < 3.0 * DAY_SEGMENTS {
+            1.0 - (time - 2.0 * DAY_SEGMENTS) / DAY_SEGMENTS
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(time_of_day: f32) -> WallClock {
+        WallClock { time_of_day, elapsed: time_of_day }
+    }
+
+    #[test]
+    fn test_daylight_follows_clock() {
+        assert_eq!(Atmosphere::daylight(&clock(0.0)), 0.0);
+        assert!((Atmosphere::daylight(&clock(DAY_SEGMENTS / 2.0)) - 0.5).abs() < 1e-5);
+        assert_eq!(Atmosphere::daylight(&clock(1.5 * DAY_SEGMENTS)), 1.0);
+        assert!((Atmosphere::daylight(&clock(2.25 * DAY_SEGMENTS)) - 0.75).abs() < 1e-5);
+        assert_eq!(Atmosphere::daylight(&clock(3.5 * DAY_SEGMENTS)), 0.0);
+
+        let fog = HeightFog::default();
+        let noon = fog.color(&clock(1.5 * DAY_SEGMENTS));
+        assert!((noon.x - fog.day_color.x).abs() < 1e-5);
+        let midnight = fog.color(&clock(3.5 * DAY_SEGMENTS));
+        assert!((midnight.z - fog.night_color.z).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_sun_on_screen() {
+        // the camera at the origin looks down -z
+        let projection = Matrix4f::create_projection_matrix(-0.1, -1000.0, 90.0, 1.0);
+        let (uv, visibility) = LightShafts::sun_on_screen(&projection, &Vector3f::new(0.0, 0.0, -1.0)).expect("The sun is right in front");
+        assert!((uv.x - 0.5).abs() < 1e-5 && (uv.y - 0.5).abs() < 1e-5);
+        assert_eq!(visibility, 1.0);
+        // up and to the right ends up in the top right quarter
+        let (uv, _) = LightShafts::sun_on_screen(&projection, &Vector3f::new(0.3, 0.3, -1.0)).expect("The sun is on the screen");
+        assert!(uv.x > 0.5 && uv.y > 0.5);
+        // just off the screen the shafts fade
+        let (_, visibility) = LightShafts::sun_on_screen(&projection, &Vector3f::new(1.5, 0.0, -1.0)).expect("The sun is close to the screen");
+        assert!(visibility > 0.0 && visibility < 1.0);
+        assert!(LightShafts::sun_on_screen(&projection, &Vector3f::new(0.0, 0.0, 1.0)).is_none());
+        assert!(LightShafts::sun_on_screen(&projection, &Vector3f::new(5.0, 0.0, -1.0)).is_none());
+    }
+
+    #[test]
+    fn test_height_fog_transmittance() {
+        let fog = HeightFog::new(0.01, 0.1, 0.0);
+        let camera = Vector3f::new(0.0, 0.0, 0.0);
+        // a flat ray at the base height is plain exponential fog
+        let flat = fog.transmittance(&camera, &Vector3f::new(100.0, 0.0, 0.0));
+        assert!((flat - (-1.0f32).exp()).abs() < 1e-5);
+        // the same distance going up passes through thinner fog
+        let up = fog.transmittance(&camera, &Vector3f::new(0.0, 100.0, 0.0));
+        assert!(up > flat);
+        // and it is the same in both directions
+        let down = fog.transmittance(&Vector3f::new(0.0, 100.0, 0.0), &camera);
+        assert!((up - down).abs() < 1e-5);
+        // nearly flat rays don't blow up
+        let almost_flat = fog.transmittance(&camera, &Vector3f::new(100.0, 0.0001, 0.0));
+        assert!((almost_flat - flat).abs() < 1e-3);
+        assert_eq!(fog.transmittance(&camera, &camera), 1.0);
+    }
+}
\ No newline at end of file
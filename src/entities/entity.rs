@@ -1,4 +1,8 @@
 use crate::math::{
+    Aabb,
+    BoundingSphere,
+    Frustum,
+    Matrix4f,
     Vector2f,
     Vector3f,
 };
@@ -51,6 +55,21 @@ impl Entity {
         self.rotation_deg.z += drz;
     }
 
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.model.bounds.world_sphere(&self.position, self.scale)
+    }
+
+    pub fn world_aabb(&self) -> Aabb {
+        let transform_mat = Matrix4f::create_transform_matrix(&self.position, &self.rotation_deg, self.scale);
+        self.model.bounds.world_aabb(&transform_mat)
+    }
+
+    // the sphere is cheap so it goes first, the box only decides for what the sphere lets through
+    // the box is tighter for long thin models like the fern or the lamp where the sphere around the origin is mostly empty
+    pub fn is_in_frustum(&self, frustum: &Frustum) -> bool {
+        frustum.intersects_bounding_sphere(&self.bounding_sphere()) && frustum.intersects_bounding_box(&self.world_aabb())
+    }
+
     pub fn get_atlas_offset(&self) -> Vector2f {
         let num_rows = self.model.texture.number_of_rows_in_atlas;
         let row = self.atlas_index / num_rows;
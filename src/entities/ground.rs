@@ -1,17 +1,132 @@
-use crate::entities::Terrain;
+use crate::entities::{
+    Terrain,
+    terrain::TerrainMeshData,
+    terrain_streamer::TerrainStreamer,
+};
 use crate::math::{
     Vector3f,
     Vector2f,
     BarycentricCoords,
 };
+use crate::models::{
+    NoiseTerrainGenerator,
+    ErosionParams,
+    ResourceManager,
+};
+use std::collections::HashMap;
 use std::f32;
 use std::cmp;
 
 pub struct Ground {
     pub terrains: Vec<Terrain>,
+    // spatial index from grid coords to the position of the cell in terrains
+    cell_index: HashMap<(i32, i32), usize>,
+    streamer: Option<TerrainStreamer>,
 }
 
 impl Ground {
+    pub fn new(terrains: Vec<Terrain>) -> Self {
+        let mut ground = Ground {
+            terrains: Vec::new(),
+            cell_index: HashMap::new(),
+            streamer: None,
+        };
+        for terrain in terrains {
+            ground.add_terrain(terrain);
+        }
+        ground
+    }
+
+    // infinite terrain which is generated around the center and then kept up to date by calling update
+    pub fn new_streamed(resource_manager: &mut ResourceManager, generator: NoiseTerrainGenerator, erosion: Option<ErosionParams>, load_radius: i32, center: &Vector3f) -> Self {
+        let streamer = TerrainStreamer::new(generator, resource_manager.terrain_pack(), resource_manager.blend_texture(), load_radius, erosion);
+        let mut ground = Ground {
+            terrains: Vec::new(),
+            cell_index: HashMap::new(),
+            streamer: Some(streamer),
+        };
+        ground.load_around_blocking(center, resource_manager);
+        ground
+    }
+
+    fn add_terrain(&mut self, terrain: Terrain) {
+        self.cell_index.insert(terrain.grid_coords(), self.terrains.len());
+        self.terrains.push(terrain);
+    }
+
+    fn remove_terrain(&mut self, idx: usize) -> Terrain {
+        let removed = self.terrains.swap_remove(idx);
+        self.cell_index.remove(&removed.grid_coords());
+        // the last terrain got moved into idx
+        if idx < self.terrains.len() {
+            self.cell_index.insert(self.terrains[idx].grid_coords(), idx);
+        }
+        removed
+    }
+
+    fn terrain_at(&self, x: f32, z: f32) -> Option<&Terrain> {
+        self.cell_index.get(&Terrain::grid_coords_at(x, z)).map(|idx| &self.terrains[*idx])
+    }
+
+    // generate the whole ring around the center and wait for it. used when creating the scene so we can place things on the ground
+    pub fn load_around_blocking(&mut self, center: &Vector3f, resource_manager: &mut ResourceManager) {
+        let center_cell = Terrain::grid_coords_at(center.x, center.z);
+        let cell_index = &self.cell_index;
+        let streamer = self.streamer.as_mut().expect("Can only stream terrain for a ground created with new_streamed");
+        streamer.request_cells_around(center_cell, |cell| cell_index.contains_key(&cell));
+        let mut meshes = Vec::new();
+        while streamer.has_pending() {
+            meshes.push(streamer.recv_mesh());
+        }
+        for (cell, mesh) in meshes {
+            self.upload_cell(cell, mesh, resource_manager);
+        }
+    }
+
+    // request the missing cells around center, upload the ones that finished generating and evict the far away ones
+    pub fn update(&mut self, center: &Vector3f, resource_manager: &mut ResourceManager) {
+        if self.streamer.is_none() {
+            return;
+        }
+        let center_cell = Terrain::grid_coords_at(center.x, center.z);
+        let (load_radius, evict_radius) = {
+            let cell_index = &self.cell_index;
+            let streamer = self.streamer.as_mut().expect("Checked above");
+            streamer.request_cells_around(center_cell, |cell| cell_index.contains_key(&cell));
+            (streamer.load_radius, streamer.evict_radius)
+        };
+
+        for _ in 0..TerrainStreamer::MAX_UPLOADS_PER_FRAME {
+            let finished = self.streamer.as_mut().expect("Checked above").try_recv_mesh();
+            match finished {
+                // the player may have moved on while the cell was generating
+                Some((cell, mesh)) if TerrainStreamer::is_within_radius(center_cell, cell, load_radius) => self.upload_cell(cell, mesh, resource_manager),
+                Some(_) => continue,
+                None => break,
+            }
+        }
+
+        let mut idx = 0;
+        while idx < self.terrains.len() {
+            if TerrainStreamer::is_within_radius(center_cell, self.terrains[idx].grid_coords(), evict_radius) {
+                idx += 1;
+            } else {
+                let evicted = self.remove_terrain(idx);
+                resource_manager.unload_terrain_model(&evicted.model);
+            }
+        }
+    }
+
+    fn upload_cell(&mut self, cell: (i32, i32), mesh: TerrainMeshData, resource_manager: &mut ResourceManager) {
+        if self.cell_index.contains_key(&cell) {
+            return;
+        }
+        let streamer = self.streamer.as_ref().expect("Can only stream terrain for a ground created with new_streamed");
+        let terrain_model = resource_manager.load_terrain_mesh(mesh);
+        let blend_texture = terrain_model.blend_map.clone().unwrap_or_else(|| streamer.blend_texture.clone());
+        let terrain = Terrain::new(cell.0, cell.1, streamer.texture_pack.clone(), blend_texture, terrain_model);
+        self.add_terrain(terrain);
+    }
     
     pub fn create_pos_above_terrain(&self, x: f32, y: f32, z: f32) -> Vector3f {
         let height = self.height_at_xz(x, z);
@@ -24,39 +139,110 @@ impl Ground {
 	}
 
     pub fn height_at_xz(&self, x: f32, z: f32) -> f32 {
-        for terrain_cell in self.terrains.iter() {
-            if terrain_cell.is_xz_within_terrain_cell(x, z) {
-                let mx = x - terrain_cell.x;
-                let mz = z - terrain_cell.z;
-                let grid_cell_count = terrain_cell.model.height_map.len();
-                let grid_width = Terrain::SIZE / ((grid_cell_count - 1) as f32);
-                let grid_x = (mx / grid_width).floor() as usize;
-                let grid_z = (mz / grid_width).floor() as usize;
-                // clamp due to floating point imprecision?
-                let grid_x = cmp::max(0, cmp::min(grid_x, grid_cell_count - 2));
-                let grid_z = cmp::max(0, cmp::min(grid_z, grid_cell_count - 2));
-
-                // now find the coords in the rectangle as fraction in [0,1]
-                let r_x = (mx % grid_width) / grid_width;
-                let r_z = (mz % grid_width) / grid_width;
-                // now find which of the two inner triangles we are in
-                let (t_a, t_b, t_c) = if r_x + r_z <= 1.0 {
-                    let t_a = Vector3f::new(0.0, 0.0, terrain_cell.model.height_map[grid_x][grid_z]);
-                    let t_b = Vector3f::new(1.0, 0.0, terrain_cell.model.height_map[grid_x + 1][grid_z]);
-                    let t_c = Vector3f::new(0.0, 1.0, terrain_cell.model.height_map[grid_x][grid_z + 1]);
-                    (t_a, t_b, t_c)
-                } else {
-                    let t_a = Vector3f::new(1.0, 0.0, terrain_cell.model.height_map[grid_x + 1][grid_z]);
-                    let t_b = Vector3f::new(0.0, 1.0, terrain_cell.model.height_map[grid_x][grid_z + 1]);
-                    let t_c = Vector3f::new(1.0, 1.0, terrain_cell.model.height_map[grid_x + 1][grid_z + 1]);
-                    (t_a, t_b, t_c)
-                };
-                let point = Vector2f::new(r_x, r_z);
-                let bary_coords = BarycentricCoords::to_barycentric_coords(&point, &t_a, &t_b, &t_c);
-                let point_in_3d = BarycentricCoords::from_barycentric_coords(&bary_coords, &t_a, &t_b, &t_c);                             
-                return point_in_3d.z;
-            }
+        let terrain_cell = match self.terrain_at(x, z) {
+            Some(terrain_cell) => terrain_cell,
+            None => return 0.0,
+        };
+        let mx = x - terrain_cell.x;
+        let mz = z - terrain_cell.z;
+        let grid_cell_count = terrain_cell.model.height_map.len();
+        let grid_width = Terrain::SIZE / ((grid_cell_count - 1) as f32);
+        let grid_x = (mx / grid_width).floor() as usize;
+        let grid_z = (mz / grid_width).floor() as usize;
+        // clamp due to floating point imprecision?
+        let grid_x = cmp::max(0, cmp::min(grid_x, grid_cell_count - 2));
+        let grid_z = cmp::max(0, cmp::min(grid_z, grid_cell_count - 2));
+
+        // now find the coords in the rectangle as fraction in [0,1]
+        let r_x = (mx % grid_width) / grid_width;
+        let r_z = (mz % grid_width) / grid_width;
+        // now find which of the two inner triangles we are in
+        let (t_a, t_b, t_c) = if r_x + r_z <= 1.0 {
+            let t_a = Vector3f::new(0.0, 0.0, terrain_cell.model.height_map[grid_x][grid_z]);
+            let t_b = Vector3f::new(1.0, 0.0, terrain_cell.model.height_map[grid_x + 1][grid_z]);
+            let t_c = Vector3f::new(0.0, 1.0, terrain_cell.model.height_map[grid_x][grid_z + 1]);
+            (t_a, t_b, t_c)
+        } else {
+            let t_a = Vector3f::new(1.0, 0.0, terrain_cell.model.height_map[grid_x + 1][grid_z]);
+            let t_b = Vector3f::new(0.0, 1.0, terrain_cell.model.height_map[grid_x][grid_z + 1]);
+            let t_c = Vector3f::new(1.0, 1.0, terrain_cell.model.height_map[grid_x + 1][grid_z + 1]);
+            (t_a, t_b, t_c)
+        };
+        let point = Vector2f::new(r_x, r_z);
+        let bary_coords = BarycentricCoords::to_barycentric_coords(&point, &t_a, &t_b, &t_c);
+        let point_in_3d = BarycentricCoords::from_barycentric_coords(&bary_coords, &t_a, &t_b, &t_c);                             
+        point_in_3d.z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        RawModel,
+        TerrainModel,
+        TerrainTexture,
+        TerrainTexturePack,
+        TextureId,
+    };
+    use crate::utils::test_utils::*;
+    use std::rc::Rc;
+
+    // a flat cell at the given height so the height lookups tell which cell was found
+    fn flat_terrain(grid_x: i32, grid_z: i32, height: f32) -> Terrain {
+        let texture = TerrainTexture { tex_id: TextureId::Loaded(1) };
+        let texture_pack = TerrainTexturePack {
+            background_texture: texture.clone(),
+            r_texture: texture.clone(),
+            g_texture: texture.clone(),
+            b_texture: texture.clone(),
+        };
+        let model = TerrainModel {
+            raw_model: RawModel::default(),
+            height_map: Rc::new(vec![vec![height; 3]; 3]),
+            chunks: Rc::new(Vec::new()),
+            blend_map: None,
+        };
+        Terrain::new(grid_x, grid_z, texture_pack, texture, model)
+    }
+
+    fn assert_index_matches(ground: &Ground) {
+        assert_eq!(ground.cell_index.len(), ground.terrains.len());
+        for (cell, idx) in ground.cell_index.iter() {
+            assert_eq!(ground.terrains[*idx].grid_coords(), *cell);
         }
-        return 0.0
+    }
+
+    #[test]
+    fn test_cells_are_found_by_world_position() {
+        let ground = Ground::new(vec![flat_terrain(0, 0, 1.0), flat_terrain(1, 0, 2.0), flat_terrain(-1, 2, 3.0)]);
+        assert_index_matches(&ground);
+        assert_f32_eq!(ground.height_at_xz(10.0, 10.0), 1.0, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(ground.height_at_xz(Terrain::SIZE + 5.0, Terrain::SIZE - 1.0), 2.0, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(ground.height_at_xz(-1.0, 2.0 * Terrain::SIZE + 1.0), 3.0, test_constants::EPS_MEDIUM);
+        assert_eq!(ground.terrain_at(-1.0, 2.0 * Terrain::SIZE + 1.0).map(|terrain| terrain.grid_coords()), Some((-1, 2)));
+        // nothing is loaded there
+        assert!(ground.terrain_at(-1.0, -1.0).is_none());
+        assert_eq!(ground.height_at_xz(-1.0, -1.0), 0.0);
+    }
+
+    #[test]
+    fn test_evicting_a_middle_cell() {
+        let mut ground = Ground::new((0..4).map(|i| flat_terrain(i, 0, i as f32)).collect());
+        let evicted = ground.remove_terrain(1);
+        assert_eq!(evicted.grid_coords(), (1, 0));
+        assert_eq!(ground.terrains.len(), 3);
+        assert!(ground.terrain_at(Terrain::SIZE + 1.0, 1.0).is_none());
+        // the last cell took the place of the evicted one and its index moved with it
+        assert_eq!(ground.terrains[1].grid_coords(), (3, 0));
+        assert_eq!(ground.cell_index[&(3, 0)], 1);
+        assert_index_matches(&ground);
+        assert_f32_eq!(ground.height_at_xz(3.0 * Terrain::SIZE + 1.0, 1.0), 3.0, test_constants::EPS_MEDIUM);
+
+        // evicting the last cell doesn't move anything
+        let evicted = ground.remove_terrain(2);
+        assert_eq!(evicted.grid_coords(), (2, 0));
+        assert_index_matches(&ground);
+        assert_eq!(ground.cell_index[&(3, 0)], 1);
     }
 }
\ No newline at end of file
@@ -1,25 +1,123 @@
 use super::super::math::Vector3f;
 
+#[derive(Debug, Clone)]
+pub enum LightKind {
+    // light from infinitely far away like the sun, direction is where the light travels to
+    Directional { direction: Vector3f },
+    Point { position: Vector3f },
+    // point light limited to a cone around direction, full strength inside the inner cone fading out to nothing at the outer cone
+    // the angles are in degrees from the cone axis
+    Spot { position: Vector3f, direction: Vector3f, inner_angle: f32, outer_angle: f32 },
+}
+
+#[derive(Debug, Clone)]
 pub struct Light {
-    pub position: Vector3f,
+    pub kind: LightKind,
     pub color: Vector3f,
+    // constant, linear and quadratic falloff over distance. not used by directional lights
     pub attenuation: Vector3f,
+    pub casts_shadows: bool,
 }
 
 impl Light {
-    pub fn new_infinite(position: Vector3f, color: Vector3f) -> Light {
+    pub const MIN_BRIGHTNESS: f32 = 0.02;
+
+    pub fn new_directional(direction: Vector3f, color: Vector3f) -> Light {
+        let mut direction = direction;
+        direction.normalize();
         Light {
-            position,
+            kind: LightKind::Directional { direction },
             color,
-            attenuation: Vector3f::new(1.0, 0.0, 0.0),       
+            attenuation: Vector3f::new(1.0, 0.0, 0.0),
+            casts_shadows: false,
         }
     }
 
     pub fn new_point(position: Vector3f, color: Vector3f, attenuation: Vector3f) -> Light {
         Light {
-            position,
+            kind: LightKind::Point { position },
+            color,
+            attenuation,
+            casts_shadows: false,
+        }
+    }
+
+    pub fn new_spot(position: Vector3f, direction: Vector3f, color: Vector3f, attenuation: Vector3f, inner_angle: f32, outer_angle: f32) -> Light {
+        let mut direction = direction;
+        direction.normalize();
+        Light {
+            // the shaders fade between the cones so they can't be the same
+            kind: LightKind::Spot { position, direction, inner_angle, outer_angle: outer_angle.max(inner_angle + 0.5) },
             color,
-            attenuation,       
+            attenuation,
+            casts_shadows: false,
         }
     }
-}
\ No newline at end of file
+
+    pub fn with_shadows(mut self) -> Light {
+        self.casts_shadows = true;
+        self
+    }
+
+    pub fn position(&self) -> Option<&Vector3f> {
+        match &self.kind {
+            LightKind::Directional { .. } => None,
+            LightKind::Point { position } => Some(position),
+            LightKind::Spot { position, .. } => Some(position),
+        }
+    }
+
+    // directional lights don't have a position so this does nothing for them
+    pub fn set_position(&mut self, new_position: &Vector3f) {
+        match &mut self.kind {
+            LightKind::Directional { .. } => (),
+            LightKind::Point { position } => *position = new_position.clone(),
+            LightKind::Spot { position, .. } => *position = new_position.clone(),
+        }
+    }
+
+    pub fn direction(&self) -> Option<&Vector3f> {
+        match &self.kind {
+            LightKind::Directional { direction } => Some(direction),
+            LightKind::Point { .. } => None,
+            LightKind::Spot { direction, .. } => Some(direction),
+        }
+    }
+
+    // point lights don't have a direction so this does nothing for them
+    pub fn set_direction(&mut self, new_direction: &Vector3f) {
+        let mut new_direction = new_direction.clone();
+        new_direction.normalize();
+        match &mut self.kind {
+            LightKind::Directional { direction } => *direction = new_direction,
+            LightKind::Point { .. } => (),
+            LightKind::Spot { direction, .. } => *direction = new_direction,
+        }
+    }
+
+    // distance past which the light is dimmer than MIN_BRIGHTNESS, infinite if the light doesn't fall off
+    // the light culling uses this as the radius of the light and the shaders fade the light out towards it
+    pub fn range(&self) -> f32 {
+        if let LightKind::Directional { .. } = self.kind {
+            return f32::INFINITY;
+        }
+        let max_color = self.color.x.max(self.color.y).max(self.color.z);
+        // solve attenuation(d) = max_color / MIN_BRIGHTNESS for d
+        let target = max_color / Light::MIN_BRIGHTNESS;
+        let Vector3f { x: constant, y: linear, z: quadratic } = self.attenuation;
+        if target <= constant {
+            0.0
+        } else if quadratic > 0.0 {
+            (-linear + (linear * linear - 4.0 * quadratic * (constant - target)).sqrt()) / (2.0 * quadratic)
+        } else if linear > 0.0 {
+            (target - constant) / linear
+        } else {
+            f32::INFINITY
+        }
+    }
+
+    // index of the light that gets the sun shadow map, which only works for directional lights
+    pub fn shadow_caster(lights: &[Light]) -> Option<usize> {
+        lights.iter().position(|light| light.casts_shadows && matches!(light.kind, LightKind::Directional { .. }))
+    }
+}
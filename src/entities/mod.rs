@@ -4,19 +4,54 @@ pub mod light;
 pub mod terrain;
 pub mod player;
 pub mod ground;
+pub mod terrain_streamer;
 pub mod skybox;
 pub mod entity_traits;
 pub mod water_tile;
+pub mod water_waves;
 pub mod debug_entity;
 pub mod animated_entity;
+pub mod reflection_probe;
+pub mod atmosphere;
+pub mod procedural_sky;
+pub mod weather;
 
 pub use self::entity::Entity;
 pub use self::debug_entity::DebugEntity;
 pub use self::camera::Camera;
 pub use self::light::Light;
+pub use self::light::LightKind;
 pub use self::terrain::Terrain;
 pub use self::player::Player;
 pub use self::ground::Ground;
+pub use self::terrain_streamer::TerrainStreamer;
 pub use self::skybox::Skybox;
-pub use self::water_tile::WaterTile;
-pub use self::animated_entity::AnimatedEntity;
\ No newline at end of file
+pub use self::water_tile::{
+    WaterTile,
+    WaterMaterial,
+    WaterReflection,
+    WaterPlanes,
+};
+pub use self::water_waves::{
+    GerstnerWave,
+    WaterWaves,
+};
+pub use self::animated_entity::AnimatedEntity;
+pub use self::reflection_probe::{
+    ReflectionProbe,
+    ProbeUpdate,
+};
+pub use self::atmosphere::{
+    Atmosphere,
+    HeightFog,
+    LightShafts,
+};
+pub use self::procedural_sky::{
+    ProceduralSky,
+    SkyCoefficients,
+};
+pub use self::weather::{
+    Precipitation,
+    Weather,
+    WeatherState,
+};
\ No newline at end of file
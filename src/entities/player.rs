@@ -8,6 +8,7 @@ use crate::entities::{
     AnimatedEntity,
     Entity,
     Ground,
+    WaterTile,
 };
 use crate::math::{
     Vector3f,
@@ -32,6 +33,8 @@ impl Player {
     const RUN_SPEED: f32 = 20.0;
     const TURN_SPEED: f32 = 160.0;
     const JUMP_POWER: f32 = 30.0;
+    // how far below the water surface the feet are when swimming
+    const SWIM_DEPTH: f32 = 1.5;
 
     pub fn new_animated(animated_entity: AnimatedEntity) -> Player {
         Player {
@@ -90,7 +93,7 @@ impl Player {
         }
     }
 
-    pub fn move_player(&mut self, display: &Display, ground: &Ground) {
+    pub fn move_player(&mut self, display: &Display, ground: &Ground, water_tiles: &[WaterTile]) {
         if self.is_invisible_immovable {
             return;
         }
@@ -104,9 +107,15 @@ impl Player {
         let upwards_dist = self.upwards_speed * display.frame_time_sec;
         self.increase_position(dx, upwards_dist, dz);
 
-        let terrain_height_at_xz = ground.height_at_xz(self.position().x, self.position().z);
-        if self.position().y <= terrain_height_at_xz {
-            self.position_mut().y = terrain_height_at_xz;
+        let (x, z) = (self.position().x, self.position().z);
+        let terrain_height_at_xz = ground.height_at_xz(x, z);
+        // in deep water we swim with the waves instead of walking on the bottom
+        let floor_height = match WaterTile::surface_height_at(water_tiles, x, z, display.wall_clock.elapsed) {
+            Some(water_height) => terrain_height_at_xz.max(water_height - Player::SWIM_DEPTH),
+            None => terrain_height_at_xz,
+        };
+        if self.position().y <= floor_height {
+            self.position_mut().y = floor_height;
             self.is_in_air = false;
             self.upwards_speed = 0.0;
         }
@@ -0,0 +1,282 @@
+use std::f32::consts::PI;
+
+use crate::display::WallClock;
+use crate::entities::Light;
+use crate::math::Vector3f;
+
+// the per channel inputs of the perez function of the preetham sky, the channels are luminance Y and the chromaticities x and y
+// the sky shader evaluates the same function per pixel so these get computed once per frame
+#[derive(Debug, Clone)]
+pub struct SkyCoefficients {
+    pub a: Vector3f,
+    pub b: Vector3f,
+    pub c: Vector3f,
+    pub d: Vector3f,
+    pub e: Vector3f,
+    // Yxy of the zenith already divided by the perez function at the zenith
+    pub zenith: Vector3f,
+}
+
+impl SkyCoefficients {
+    fn perez(&self, cos_theta: f32, gamma: f32) -> Vector3f {
+        let cos_gamma = gamma.cos();
+        let f = |a: f32, b: f32, c: f32, d: f32, e: f32| {
+            (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+        };
+        Vector3f::new(
+            f(self.a.x, self.b.x, self.c.x, self.d.x, self.e.x),
+            f(self.a.y, self.b.y, self.c.y, self.d.y, self.e.y),
+            f(self.a.z, self.b.z, self.c.z, self.d.z, self.e.z),
+        )
+    }
+}
+
+// analytic daylight sky after preetham et al. with a sun that moves over the sky with the wall clock
+// the sun rises and sets in the middle of the ramps of the skybox day night cycle so both agree on when it is day
+#[derive(Debug, Clone)]
+pub struct ProceduralSky {
+    // of the observer, the sun stands lower in the sky the further away from the equator
+    pub latitude_deg: f32,
+    // how hazy the air is, 2 is very clear and 10 is hazy
+    pub turbidity: f32,
+    // of the sun, 0 at the equinoxes and 23.44 in the northern summer
+    pub declination_deg: f32,
+    // of the sun light at noon before the atmosphere reddens it
+    pub sun_color: Vector3f,
+}
+
+impl ProceduralSky {
+    pub const LATITUDE: f32 = 45.0;
+    pub const TURBIDITY: f32 = 2.5;
+    // scales the luminance of the preetham model which is in kcd/m^2 to what the rest of the hdr scene uses
+    pub const SKY_EXPOSURE: f32 = 0.06;
+    pub const NIGHT_COLOR: Vector3f = Vector3f { x: 0.02, y: 0.025, z: 0.045 };
+    pub const MOON_COLOR: Vector3f = Vector3f { x: 0.1, y: 0.12, z: 0.18 };
+    // the sun is a light once it stands this far above the horizon, the same below the horizon and the moon takes over
+    const TWILIGHT: f32 = 0.1;
+    // extinction of red, green and blue per air mass at the default turbidity
+    const EXTINCTION: Vector3f = Vector3f { x: 0.0425, y: 0.1, z: 0.24 };
+    const HORIZON_SAMPLES: usize = 8;
+
+    pub fn new(latitude_deg: f32) -> ProceduralSky {
+        ProceduralSky {
+            latitude_deg,
+            turbidity: ProceduralSky::TURBIDITY,
+            declination_deg: 0.0,
+            sun_color: Vector3f::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn with_turbidity(mut self, turbidity: f32) -> ProceduralSky {
+        // the fit of the model doesn't hold outside of this
+        self.turbidity = turbidity.clamp(1.7, 10.0);
+        self
+    }
+
+    pub fn with_declination(mut self, declination_deg: f32) -> ProceduralSky {
+        self.declination_deg = declination_deg;
+        self
+    }
+
+    pub fn with_sun_color(mut self, sun_color: Vector3f) -> ProceduralSky {
+        self.sun_color = sun_color;
+        self
+    }
+
+    // the sun is in the south at noon which is 3/8 into the day, the moon is always on the opposite side
+    pub fn hour_angle(wall_clock: &WallClock) -> f32 {
+        2.0 * PI * (wall_clock.time_of_day / WallClock::DAY_LENGTH - 0.375)
+    }
+
+    // the sky turns around this axis, it points north and is raised by the latitude
+    pub fn celestial_pole(&self) -> Vector3f {
+        let latitude = self.latitude_deg.to_radians();
+        Vector3f::new(0.0, latitude.sin(), -latitude.cos())
+    }
+
+    // unit vector towards the sun, east is +x, north is -z and up is +y
+    pub fn to_sun(&self, wall_clock: &WallClock) -> Vector3f {
+        let hour_angle = ProceduralSky::hour_angle(wall_clock);
+        let (latitude, declination) = (self.latitude_deg.to_radians(), self.declination_deg.to_radians());
+        let east = -declination.cos() * hour_angle.sin();
+        let north = declination.sin() * latitude.cos() - declination.cos() * hour_angle.cos() * latitude.sin();
+        let up = declination.sin() * latitude.sin() + declination.cos() * hour_angle.cos() * latitude.cos();
+        Vector3f::new(east, up, -north)
+    }
+
+    // 0 at night 1 during the day, goes by how high the sun stands
+    pub fn daylight(&self, wall_clock: &WallClock) -> f32 {
+        let t = (self.to_sun(wall_clock).y / ProceduralSky::TWILIGHT * 0.5 + 0.5).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    pub fn coefficients(&self, to_sun: &Vector3f) -> SkyCoefficients {
+        let t = self.turbidity;
+        let a = Vector3f::new(0.1787 * t - 1.4630, -0.0193 * t - 0.2592, -0.0167 * t - 0.2608);
+        let b = Vector3f::new(-0.3554 * t + 0.4275, -0.0665 * t + 0.0008, -0.0950 * t + 0.0092);
+        let c = Vector3f::new(-0.0227 * t + 5.3251, -0.0004 * t + 0.2125, -0.0079 * t + 0.2102);
+        let d = Vector3f::new(0.1206 * t - 2.5771, -0.0641 * t - 0.8989, -0.0441 * t - 1.6537);
+        let e = Vector3f::new(-0.0670 * t + 0.3703, -0.0033 * t + 0.0452, -0.0109 * t + 0.0529);
+
+        // the model only covers a sun above the horizon, below it the sky keeps the colors of sunset and fades with daylight
+        let theta_sun = to_sun.y.clamp(0.0, 1.0).acos();
+        let (theta2, theta3) = (theta_sun * theta_sun, theta_sun * theta_sun * theta_sun);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta_sun)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta_sun + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta_sun + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta_sun)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta_sun + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta_sun + 0.26688);
+
+        let mut coefficients = SkyCoefficients { a, b, c, d, e, zenith: Vector3f::zero() };
+        let at_zenith = coefficients.perez(1.0, theta_sun);
+        coefficients.zenith = Vector3f::new(zenith_luminance / at_zenith.x, zenith_x / at_zenith.y, zenith_y / at_zenith.z);
+        coefficients
+    }
+
+    // linear rgb of the daylight sky in the given direction, without the night sky and the sun disc
+    pub fn sky_color(&self, direction: &Vector3f, to_sun: &Vector3f) -> Vector3f {
+        let coefficients = self.coefficients(to_sun);
+        let mut direction = direction.clone();
+        direction.normalize();
+        // below the horizon we look at the horizon
+        let cos_theta = direction.y.max(0.01);
+        let gamma = direction.dot_product(to_sun).clamp(-1.0, 1.0).acos();
+        let perez = coefficients.perez(cos_theta, gamma);
+        let (luminance, x, y) = (coefficients.zenith.x * perez.x, coefficients.zenith.y * perez.y, coefficients.zenith.z * perez.z);
+        yxy_to_rgb(luminance * ProceduralSky::SKY_EXPOSURE, x, y)
+    }
+
+    // the color everything fades into far away, used for the fog and to clear the screen
+    pub fn horizon_color(&self, wall_clock: &WallClock) -> Vector3f {
+        let to_sun = self.to_sun(wall_clock);
+        let mut sum = Vector3f::zero();
+        for i in 0..ProceduralSky::HORIZON_SAMPLES {
+            let azimuth = 2.0 * PI * i as f32 / ProceduralSky::HORIZON_SAMPLES as f32;
+            sum += &self.sky_color(&Vector3f::new(azimuth.cos(), 0.05, azimuth.sin()), &to_sun);
+        }
+        let day_color = sum * (1.0 / ProceduralSky::HORIZON_SAMPLES as f32);
+        Vector3f::lerp(&day_color, &ProceduralSky::NIGHT_COLOR, self.daylight(wall_clock))
+    }
+
+    // the light of the sun after it passed through the atmosphere, red at sunrise and sunset and black below the horizon
+    pub fn sun_light_color(&self, to_sun: &Vector3f) -> Vector3f {
+        if to_sun.y <= 0.0 {
+            return Vector3f::zero();
+        }
+        // kasten and young air mass, 1 with the sun right above and about 38 at the horizon
+        let zenith_deg = to_sun.y.min(1.0).acos().to_degrees();
+        let air_mass = 1.0 / (to_sun.y + 0.50572 * (96.07995 - zenith_deg).powf(-1.6364));
+        let haze = self.turbidity / ProceduralSky::TURBIDITY;
+        let fade = (to_sun.y / ProceduralSky::TWILIGHT).min(1.0);
+        let extinction = |color: f32, beta: f32| color * (-beta * haze * (air_mass - 1.0)).exp() * fade;
+        Vector3f::new(
+            extinction(self.sun_color.x, ProceduralSky::EXTINCTION.x),
+            extinction(self.sun_color.y, ProceduralSky::EXTINCTION.y),
+            extinction(self.sun_color.z, ProceduralSky::EXTINCTION.z),
+        )
+    }
+
+    // points the light the way the sun shines during the day and the moon at night
+    // the two swap while the sun is on the horizon where both of them are dark
+    pub fn update_light(&self, light: &mut Light, wall_clock: &WallClock) {
+        let to_sun = self.to_sun(wall_clock);
+        if to_sun.y >= 0.0 {
+            light.color = self.sun_light_color(&to_sun);
+            light.set_direction(&-&to_sun);
+        } else {
+            let moon_height = (-to_sun.y / ProceduralSky::TWILIGHT).min(1.0);
+            light.color = ProceduralSky::MOON_COLOR * moon_height;
+            light.set_direction(&to_sun);
+        }
+    }
+}
+
+impl Default for ProceduralSky {
+    fn default() -> ProceduralSky {
+        ProceduralSky::new(ProceduralSky::LATITUDE)
+    }
+}
+
+// cie Yxy to linear srgb
+fn yxy_to_rgb(luminance: f32, x: f32, y: f32) -> Vector3f {
+    let y = y.max(1e-4);
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Vector3f::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(time_of_day: f32) -> WallClock {
+        WallClock { time_of_day, elapsed: time_of_day }
+    }
+
+    const NOON: f32 = WallClock::DAY_LENGTH * 0.375;
+    const SUNSET: f32 = WallClock::DAY_LENGTH * 0.625;
+    const MIDNIGHT: f32 = WallClock::DAY_LENGTH * 0.875;
+
+    #[test]
+    fn test_sun_follows_clock() {
+        let sky = ProceduralSky::default();
+        let noon = sky.to_sun(&clock(NOON));
+        // in the south and as high as the latitude allows
+        assert!((noon.y - 45f32.to_radians().cos()).abs() < 1e-4);
+        assert!(noon.z > 0.0 && noon.x.abs() < 1e-4);
+        let sunset = sky.to_sun(&clock(SUNSET));
+        assert!(sunset.y.abs() < 1e-4 && sunset.x < 0.0);
+        assert!(sky.to_sun(&clock(MIDNIGHT)).y < 0.0);
+        assert!((sky.daylight(&clock(SUNSET)) - 0.5).abs() < 1e-4);
+        assert_eq!(sky.daylight(&clock(NOON)), 1.0);
+        assert_eq!(sky.daylight(&clock(MIDNIGHT)), 0.0);
+    }
+
+    #[test]
+    fn test_noon_sky_is_blue_and_bright_around_the_sun() {
+        let sky = ProceduralSky::default();
+        let to_sun = sky.to_sun(&clock(NOON));
+        let zenith = sky.sky_color(&Vector3f::POS_Y_AXIS, &to_sun);
+        assert!(zenith.z > zenith.x && zenith.z > 0.0);
+        let around_sun = sky.sky_color(&Vector3f::new(to_sun.x, to_sun.y + 0.05, to_sun.z), &to_sun);
+        let away_from_sun = sky.sky_color(&Vector3f::new(0.0, 0.7, -0.7), &to_sun);
+        assert!(around_sun.y > away_from_sun.y);
+    }
+
+    #[test]
+    fn test_sunset_is_red() {
+        let sky = ProceduralSky::default();
+        let ratio = |c: Vector3f| c.x / c.z;
+        let late = clock(SUNSET - WallClock::DAY_LENGTH * 0.01);
+        let noon_sun = sky.sun_light_color(&sky.to_sun(&clock(NOON)));
+        let late_sun = sky.sun_light_color(&sky.to_sun(&late));
+        assert!(ratio(late_sun.clone()) > ratio(noon_sun.clone()) && late_sun.y < noon_sun.y);
+
+        let mut light = Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::zero());
+        sky.update_light(&mut light, &clock(NOON));
+        assert!(light.direction().expect("The sun is directional").y < 0.0);
+        assert!((light.color.x - noon_sun.x).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_night_is_dark_and_lit_by_the_moon() {
+        let sky = ProceduralSky::default();
+        let night = sky.horizon_color(&clock(MIDNIGHT));
+        assert!((night.z - ProceduralSky::NIGHT_COLOR.z).abs() < 1e-5);
+        let day = sky.horizon_color(&clock(NOON));
+        assert!(day.y > 10.0 * night.y);
+
+        let mut light = Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::zero());
+        sky.update_light(&mut light, &clock(MIDNIGHT));
+        // the moon is up and shines down
+        assert!(light.direction().expect("The moon is directional").y < 0.0);
+        assert!(light.color.z > light.color.x && light.color.z <= ProceduralSky::MOON_COLOR.z);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,193 @@
+use crate::display::Display;
+use crate::entities::Camera;
+use crate::math::{
+    Aabb,
+    Matrix4f,
+    Vector3f,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeUpdate {
+    // only when something asks for it with request_capture
+    OnDemand,
+    EveryFrames(usize),
+}
+
+// a point in the scene that renders the six faces of a cube map from its position
+// the env mapped entities close to it reflect that instead of the sky
+#[derive(Debug, Clone)]
+pub struct ReflectionProbe {
+    pub position: Vector3f,
+    // entities further away than this don't use the probe
+    pub radius: f32,
+    // the walls of the area the probe sees, the reflections get corrected as if they hit those instead of something infinitely far away
+    pub parallax_box: Option<Aabb>,
+    pub update: ProbeUpdate,
+    needs_capture: bool,
+    frames_since_capture: usize,
+}
+
+impl ReflectionProbe {
+    // the cube maps of all probes live in one cube map array
+    pub const MAX_REFLECTION_PROBES: usize = 4;
+    // size of every face of the probe cube maps
+    pub const CUBE_MAP_SIZE: usize = 256;
+    // camera pitch and yaw that look down every face in gl's face order
+    // gl stores the faces around the sides upside down so those get rendered rolled by 180 degrees
+    const FACES: [(f32, f32, bool); 6] = [
+        (0.0, -90.0, true),
+        (0.0, 90.0, true),
+        (-90.0, 0.0, false),
+        (90.0, 0.0, false),
+        (0.0, 180.0, true),
+        (0.0, 0.0, true),
+    ];
+
+    pub fn new(position: Vector3f, radius: f32) -> ReflectionProbe {
+        ReflectionProbe {
+            position,
+            radius,
+            parallax_box: None,
+            update: ProbeUpdate::OnDemand,
+            // the cube map has nothing in it until the first capture
+            needs_capture: true,
+            frames_since_capture: 0,
+        }
+    }
+
+    pub fn with_parallax_box(mut self, min: Vector3f, max: Vector3f) -> ReflectionProbe {
+        self.parallax_box = Some(Aabb::new(min, max));
+        self
+    }
+
+    pub fn updated_every(mut self, frames: usize) -> ReflectionProbe {
+        self.update = ProbeUpdate::EveryFrames(frames.max(1));
+        self
+    }
+
+    // captured again in the next frame
+    pub fn request_capture(&mut self) {
+        self.needs_capture = true;
+    }
+
+    // called once every frame, true when the probe should render its faces in this one
+    pub fn should_capture(&mut self) -> bool {
+        self.frames_since_capture += 1;
+        let is_due = match self.update {
+            ProbeUpdate::OnDemand => false,
+            ProbeUpdate::EveryFrames(frames) => self.frames_since_capture >= frames,
+        };
+        if self.needs_capture || is_due {
+            self.needs_capture = false;
+            self.frames_since_capture = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    // the closest probe that reaches the position, probes past the max don't get a cube map so they are never picked
+    pub fn closest(probes: &[ReflectionProbe], position: &Vector3f) -> Option<usize> {
+        probes.iter().take(ReflectionProbe::MAX_REFLECTION_PROBES).enumerate()
+            .map(|(idx, probe)| (idx, (&probe.position - position).length()))
+            .filter(|(idx, distance)| *distance <= probes[*idx].radius)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("Probe distances can't be NaN"))
+            .map(|(idx, _)| idx)
+    }
+
+    // a camera at the probe looking down the face
+    pub fn face_camera(&self, face: usize) -> Camera {
+        let (pitch, yaw, _) = ReflectionProbe::FACES[face];
+        let mut camera = Camera::new(pitch, 0.0);
+        camera.position = self.position.clone();
+        camera.yaw = yaw;
+        camera
+    }
+
+    // 90 degrees so the faces meet at the edges, the roll flips x and y which keeps the winding of the triangles
+    pub fn face_projection(face: usize) -> Matrix4f {
+        let mut projection = Matrix4f::create_projection_matrix(Display::NEAR, Display::FAR, 90.0, 1.0);
+        let (_, _, upside_down) = ReflectionProbe::FACES[face];
+        if upside_down {
+            for col in 0..4 {
+                projection[0][col] = -projection[0][col];
+                projection[1][col] = -projection[1][col];
+            }
+        }
+        projection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector4f;
+
+    #[test]
+    fn test_closest_probe_in_reach() {
+        let probes = vec![
+            ReflectionProbe::new(Vector3f::new(0.0, 0.0, 0.0), 10.0),
+            ReflectionProbe::new(Vector3f::new(8.0, 0.0, 0.0), 5.0),
+            ReflectionProbe::new(Vector3f::new(100.0, 0.0, 0.0), 20.0),
+        ];
+        assert_eq!(ReflectionProbe::closest(&probes, &Vector3f::new(1.0, 0.0, 0.0)), Some(0));
+        assert_eq!(ReflectionProbe::closest(&probes, &Vector3f::new(6.0, 0.0, 0.0)), Some(1));
+        // closer to the second one but out of its reach
+        assert_eq!(ReflectionProbe::closest(&probes, &Vector3f::new(8.0, 5.5, 0.0)), Some(0));
+        assert_eq!(ReflectionProbe::closest(&probes, &Vector3f::new(50.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_probes_past_the_max_are_never_picked() {
+        let mut probes: Vec<_> = (0..ReflectionProbe::MAX_REFLECTION_PROBES)
+            .map(|i| ReflectionProbe::new(Vector3f::new(100.0 * i as f32, 0.0, 0.0), 1.0))
+            .collect();
+        probes.push(ReflectionProbe::new(Vector3f::new(-50.0, 0.0, 0.0), 1.0));
+        assert_eq!(ReflectionProbe::closest(&probes, &Vector3f::new(-50.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_capture_schedule() {
+        let mut on_demand = ReflectionProbe::new(Vector3f::zero(), 1.0);
+        let captures: Vec<bool> = (0..3).map(|_| on_demand.should_capture()).collect();
+        assert_eq!(captures, vec![true, false, false]);
+        on_demand.request_capture();
+        assert!(on_demand.should_capture());
+        assert!(!on_demand.should_capture());
+
+        let mut periodic = ReflectionProbe::new(Vector3f::zero(), 1.0).updated_every(3);
+        let captures: Vec<bool> = (0..7).map(|_| periodic.should_capture()).collect();
+        assert_eq!(captures, vec![true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_faces_match_gl_cube_map_layout() {
+        // the direction every face looks at and the world directions of its s and t texture axes, from the gl spec
+        let faces = [
+            ((1.0, 0.0, 0.0), (0.0, 0.0, -1.0), (0.0, -1.0, 0.0)),
+            ((-1.0, 0.0, 0.0), (0.0, 0.0, 1.0), (0.0, -1.0, 0.0)),
+            ((0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0)),
+            ((0.0, -1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, -1.0)),
+            ((0.0, 0.0, 1.0), (1.0, 0.0, 0.0), (0.0, -1.0, 0.0)),
+            ((0.0, 0.0, -1.0), (-1.0, 0.0, 0.0), (0.0, -1.0, 0.0)),
+        ];
+        let probe = ReflectionProbe::new(Vector3f::new(3.0, 4.0, 5.0), 1.0);
+        let to_clip = |face: usize, (x, y, z): (f32, f32, f32)| {
+            let vp = &ReflectionProbe::face_projection(face) * &Matrix4f::create_view_matrix(&probe.face_camera(face));
+            let clip = vp.transform(&Vector4f::new(probe.position.x + x, probe.position.y + y, probe.position.z + z, 1.0));
+            (clip.x / clip.w, clip.y / clip.w, clip.w)
+        };
+        for (face, (forward, s, t)) in faces.iter().enumerate() {
+            let (x, y, w) = to_clip(face, (10.0 * forward.0, 10.0 * forward.1, 10.0 * forward.2));
+            assert!(w > 0.0, "face {} looks the wrong way", face);
+            assert_f32_eq!(x, 0.0, 1e-5);
+            assert_f32_eq!(y, 0.0, 1e-5);
+            let (x, y, _) = to_clip(face, (10.0 * forward.0 + s.0, 10.0 * forward.1 + s.1, 10.0 * forward.2 + s.2));
+            assert!(x > 0.0, "s of face {} is mirrored", face);
+            assert_f32_eq!(y, 0.0, 1e-5);
+            let (x, y, _) = to_clip(face, (10.0 * forward.0 + t.0, 10.0 * forward.1 + t.1, 10.0 * forward.2 + t.2));
+            assert_f32_eq!(x, 0.0, 1e-5);
+            assert!(y > 0.0, "t of face {} is mirrored", face);
+        }
+    }
+}
\ No newline at end of file
@@ -6,6 +6,7 @@ use crate::display::{
     Display,
     WallClock,
 };
+use crate::entities::ProceduralSky;
 
 const DAY_SEGMENTS: f32 = WallClock::DAY_LENGTH / 4.0;
 
@@ -15,7 +16,8 @@ pub struct Skybox {
     pub rotate_speed: f32,
     // this is a hack for scenes that don't want to show a skybox :(
     pub invisible: bool,
-    pub uses_fog: bool,
+    // drawn instead of the cube maps of the model, which the reflections and the sky lighting keep using
+    pub procedural: Option<ProceduralSky>,
 }
 
 impl Skybox {
@@ -24,11 +26,16 @@ impl Skybox {
             model,
             rotation_yaw_deg,
             invisible: false,
-            uses_fog: true,
             rotate_speed: 1.0,
+            procedural: None,
         }
     }
 
+    pub fn with_procedural_sky(mut self, procedural_sky: ProceduralSky) -> Skybox {
+        self.procedural = Some(procedural_sky);
+        self
+    }
+
     pub fn increase_rotation(&mut self, display: &Display) {
         self.rotation_yaw_deg += self.rotate_speed * display.frame_time_sec;
     }
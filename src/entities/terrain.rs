@@ -5,7 +5,12 @@ use crate::models::{
     ModelLoader,
 	TerrainModel,
 	TerrainGenerator,	
+	TerrainChunk,
+	TerrainLodMesh,
+	TextureParams,
 };
+use texture_lib::texture_loader::Texture2DRGBA;
+use crate::math::Vector3f;
 
 pub struct Terrain {
     pub x: f32,
@@ -32,24 +37,64 @@ impl Terrain {
 		self.x <= x && x < self.x + Terrain::SIZE && self.z <= z && z < self.z + Terrain::SIZE 
 	}
 	    
-    pub fn generate_terrain(loader: &mut ModelLoader, terrain_generator: &TerrainGenerator) -> TerrainModel {
+    pub fn grid_coords(&self) -> (i32, i32) {
+		((self.x / Terrain::SIZE).round() as i32, (self.z / Terrain::SIZE).round() as i32)
+	}
+
+	// grid cell which contains the world position x, z
+	pub fn grid_coords_at(x: f32, z: f32) -> (i32, i32) {
+		((x / Terrain::SIZE).floor() as i32, (z / Terrain::SIZE).floor() as i32)
+	}
+	    
+    pub fn generate_terrain(loader: &mut ModelLoader, terrain_generator: &dyn TerrainGenerator) -> TerrainModel {
+		let mesh = Terrain::generate_terrain_mesh(terrain_generator);
+		Terrain::load_terrain_mesh(loader, mesh)
+	}
+
+	pub fn load_terrain_mesh(loader: &mut ModelLoader, mesh: TerrainMeshData) -> TerrainModel {
+		TerrainModel {
+			raw_model: loader.load_to_vao(&mesh.vertices, &mesh.texture_coords, &mesh.indices, &mesh.normals),
+			height_map: Rc::new(mesh.heights),
+			chunks: Rc::new(mesh.chunks),
+			blend_map: mesh.blend_map.map(|blend_map| loader.load_terrain_texture_from_data(blend_map, TextureParams::mipmapped_texture(-0.4))),
+		}
+	}
+
+	// cpu only part of the terrain generation so that it can run on a background thread
+	pub fn generate_terrain_mesh(terrain_generator: &dyn TerrainGenerator) -> TerrainMeshData {
 		let vertex_count: usize = terrain_generator.width();	
 		let count: usize = vertex_count * vertex_count;
+		// sample the heights once with a 1 vertex border so the normals don't have to resample the generator
+		let bordered_count = vertex_count + 2;
+		let mut bordered_heights = vec![0.0f32; bordered_count * bordered_count];
+		for i in 0..bordered_count {
+			for j in 0..bordered_count {
+				bordered_heights[i * bordered_count + j] = terrain_generator.get_height(j as isize - 1, i as isize - 1);
+			}
+		}
+		let bordered_height = |x: usize, z: usize| bordered_heights[(z + 1) * bordered_count + x + 1];
 		let mut height_array = vec![vec![0.0f32; vertex_count]; vertex_count];
 
 		let mut vertices = vec![0.0f32; count * 3];
 		let mut normals = vec![0.0f32; count * 3];
 		let mut texture_coords = vec![0.0f32; count * 2];
-		let mut indices = vec![0u32; 6*(vertex_count-1)*(vertex_count-1)];
 		let mut vertex_pointer = 0;
 		for i in 0..vertex_count {
 			for j in 0..vertex_count {
-				let height_at_xz = terrain_generator.get_height(j as isize, i as isize);
+				let height_at_xz = bordered_height(j, i);
 				height_array[j][i] = height_at_xz;
 				vertices[vertex_pointer*3] = (j as f32/(vertex_count - 1) as f32) * Terrain::SIZE;				
 				vertices[vertex_pointer*3+1] = height_at_xz;
 				vertices[vertex_pointer*3+2] = (i as f32/(vertex_count - 1) as f32) * Terrain::SIZE;
-				let normal = terrain_generator.get_normal_at(j as isize, i as isize);
+				// same as TerrainGenerator::get_normal_at but using the cached heights (the border entries are at index -1 and vertex_count)
+				let lh = bordered_heights[(i + 1) * bordered_count + j];
+				let rh = bordered_heights[(i + 1) * bordered_count + j + 2];
+				let uh = bordered_heights[i * bordered_count + j + 1];
+				let dh = bordered_heights[(i + 2) * bordered_count + j + 1];
+				let x_dir_tangent = Vector3f::new(2.0, rh - lh, 0.0);
+				let z_dir_tangent = Vector3f::new(0.0, dh - uh, 2.0);
+				let mut normal = -x_dir_tangent.cross_prod(&z_dir_tangent);
+				normal.normalize();
 				normals[vertex_pointer*3] = normal.x;
 				normals[vertex_pointer*3+1] = normal.y;
 				normals[vertex_pointer*3+2] = normal.z;
@@ -58,30 +103,36 @@ impl Terrain {
 				vertex_pointer+=1;
 			}
 		}
-		let mut pointer = 0;
-		for gz in 0..vertex_count-1 {
-			for gx in 0..vertex_count-1 {
-				let top_left = (gz*vertex_count)+gx;
-				let top_right = top_left + 1;
-				let bottom_left = ((gz+1)*vertex_count)+gx;
-				let bottom_right = bottom_left + 1;
-				indices[pointer] = top_left as u32;
-                pointer+=1;
-				indices[pointer] = bottom_left as u32;
-                pointer+=1;
-				indices[pointer] = top_right as u32;
-                pointer+=1;
-				indices[pointer] = top_right as u32;
-                pointer+=1;
-				indices[pointer] = bottom_left as u32;
-                pointer+=1;
-				indices[pointer] = bottom_right as u32;
-                pointer+=1;
-			}
+
+		let lod_mesh = TerrainLodMesh::build(vertex_count, &height_array, Terrain::SIZE);
+		// the skirts have to reach below any crack between two levels which can't be deeper than the height range of the cell
+		let (min_height, max_height) = lod_mesh.chunks.iter().fold((f32::MAX, f32::MIN), |(min, max), chunk| (min.min(chunk.min.y), max.max(chunk.max.y)));
+		let skirt_depth = (max_height - min_height).max(1.0);
+		for source in lod_mesh.skirt_sources.iter() {
+			let source = *source as usize;
+			vertices.extend_from_slice(&[vertices[source*3], vertices[source*3+1] - skirt_depth, vertices[source*3+2]]);
+			normals.extend_from_slice(&[normals[source*3], normals[source*3+1], normals[source*3+2]]);
+			texture_coords.extend_from_slice(&[texture_coords[source*2], texture_coords[source*2+1]]);
 		}
-		TerrainModel {
-			raw_model: loader.load_to_vao(&vertices, &texture_coords, &indices, &normals),
-			height_map: Rc::new(height_array),
+		TerrainMeshData {
+			vertices,
+			normals,
+			texture_coords,
+			indices: lod_mesh.indices,
+			heights: height_array,
+			chunks: lod_mesh.chunks,
+			blend_map: None,
 		}
 	}
+}
+
+pub struct TerrainMeshData {
+	pub vertices: Vec<f32>,
+	pub normals: Vec<f32>,
+	pub texture_coords: Vec<f32>,
+	pub indices: Vec<u32>,
+	// indexed by [x][z]
+	pub heights: Vec<Vec<f32>>,
+	pub chunks: Vec<TerrainChunk>,
+	pub blend_map: Option<Texture2DRGBA>,
 }
\ No newline at end of file
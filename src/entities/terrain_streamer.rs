@@ -0,0 +1,181 @@
+use crate::entities::terrain::{
+    Terrain,
+    TerrainMeshData,
+};
+use crate::models::{
+    NoiseTerrainGenerator,
+    TerrainTexture,
+    TerrainTexturePack,
+    TerrainGenerator,
+    ErodedTerrain,
+    ErosionParams,
+};
+use crate::utils::murmur3;
+use std::collections::HashSet;
+use std::sync::mpsc;
+use threadpool::ThreadPool;
+
+type GridCoords = (i32, i32);
+
+// generates terrain cells on background threads in a ring around a center position
+// the gpu upload has to happen on the main thread so the finished meshes are handed back over a channel
+pub struct TerrainStreamer {
+    generator: NoiseTerrainGenerator,
+    pub texture_pack: TerrainTexturePack,
+    pub blend_texture: TerrainTexture,
+    // cells within this many cells (chebyshev distance) of the center are kept loaded
+    pub load_radius: i32,
+    // cells further than this get evicted. larger than load_radius so we don't thrash at cell boundaries
+    pub evict_radius: i32,
+    // when set the cells are eroded and get their own blend map
+    erosion: Option<ErosionParams>,
+    pending: HashSet<GridCoords>,
+    thread_pool: ThreadPool,
+    mesh_snd: mpsc::Sender<(GridCoords, TerrainMeshData)>,
+    mesh_rcv: mpsc::Receiver<(GridCoords, TerrainMeshData)>,
+}
+
+impl TerrainStreamer {
+    const THREAD_COUNT: usize = 4;
+    // uploading a cell is relatively expensive so spread them over multiple frames
+    pub const MAX_UPLOADS_PER_FRAME: usize = 2;
+
+    pub fn new(generator: NoiseTerrainGenerator, texture_pack: TerrainTexturePack, blend_texture: TerrainTexture, load_radius: i32, erosion: Option<ErosionParams>) -> Self {
+        let (mesh_snd, mesh_rcv) = mpsc::channel();
+        TerrainStreamer {
+            generator,
+            texture_pack,
+            blend_texture,
+            load_radius,
+            evict_radius: load_radius + 1,
+            erosion,
+            pending: HashSet::new(),
+            thread_pool: ThreadPool::new(TerrainStreamer::THREAD_COUNT),
+            mesh_snd,
+            mesh_rcv,
+        }
+    }
+
+    pub fn is_within_radius(center: GridCoords, cell: GridCoords, radius: i32) -> bool {
+        (cell.0 - center.0).abs() <= radius && (cell.1 - center.1).abs() <= radius
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // schedule generation of all cells in the load ring that are neither loaded nor being generated
+    pub fn request_cells_around<F: Fn(GridCoords) -> bool>(&mut self, center: GridCoords, is_loaded: F) {
+        for dz in -self.load_radius..=self.load_radius {
+            for dx in -self.load_radius..=self.load_radius {
+                let cell = (center.0 + dx, center.1 + dz);
+                if is_loaded(cell) || self.pending.contains(&cell) {
+                    continue;
+                }
+                self.pending.insert(cell);
+                let generator = self.generator.for_cell(cell.0 as isize, cell.1 as isize);
+                let sender = self.mesh_snd.clone();
+                let erosion = self.erosion;
+                self.thread_pool.execute(move || {
+                    let mesh = match erosion {
+                        Some(params) => TerrainStreamer::generate_eroded_mesh(&generator, params, cell),
+                        None => Terrain::generate_terrain_mesh(&generator),
+                    };
+                    // the receiver can be gone if the scene was dropped in the meantime
+                    let _ = sender.send((cell, mesh));
+                });
+            }
+        }
+    }
+
+    fn generate_eroded_mesh(generator: &NoiseTerrainGenerator, params: ErosionParams, cell: GridCoords) -> TerrainMeshData {
+        // different droplets in every cell
+        let params = ErosionParams {
+            seed: murmur3(cell.0 as u32, cell.1 as u32, params.seed),
+            ..params
+        };
+        let cell_size = Terrain::SIZE / (generator.width() - 1) as f32;
+        let eroded = ErodedTerrain::new(generator, &params, cell_size);
+        let mut mesh = Terrain::generate_terrain_mesh(&eroded);
+        mesh.blend_map = Some(eroded.maps.blend_map());
+        mesh
+    }
+
+    pub fn try_recv_mesh(&mut self) -> Option<(GridCoords, TerrainMeshData)> {
+        match self.mesh_rcv.try_recv() {
+            Ok((cell, mesh)) => {
+                self.pending.remove(&cell);
+                Some((cell, mesh))
+            },
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => panic!("The terrain generation side has disconnected. This shouldnt happen since we hold a sender"),
+        }
+    }
+
+    pub fn recv_mesh(&mut self) -> (GridCoords, TerrainMeshData) {
+        let (cell, mesh) = self.mesh_rcv.recv().expect("The terrain generation side has disconnected. This shouldnt happen since we hold a sender");
+        self.pending.remove(&cell);
+        (cell, mesh)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        NoiseTerrainParams,
+        TextureId,
+    };
+
+    fn small_streamer(load_radius: i32) -> TerrainStreamer {
+        let texture = TerrainTexture { tex_id: TextureId::Loaded(1) };
+        let texture_pack = TerrainTexturePack {
+            background_texture: texture.clone(),
+            r_texture: texture.clone(),
+            g_texture: texture.clone(),
+            b_texture: texture.clone(),
+        };
+        let generator = NoiseTerrainGenerator::from_params(&NoiseTerrainParams {
+            vertex_count: 9,
+            ..Default::default()
+        });
+        TerrainStreamer::new(generator, texture_pack, texture, load_radius, None)
+    }
+
+    fn recv_all(streamer: &mut TerrainStreamer) -> Vec<GridCoords> {
+        let mut cells = Vec::new();
+        while streamer.has_pending() {
+            let (cell, mesh) = streamer.recv_mesh();
+            assert_eq!(mesh.heights.len(), 9);
+            cells.push(cell);
+        }
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn test_is_within_radius() {
+        assert!(TerrainStreamer::is_within_radius((0, 0), (0, 0), 0));
+        assert!(TerrainStreamer::is_within_radius((0, 0), (1, -1), 1));
+        // chebyshev so the corners are in too
+        assert!(TerrainStreamer::is_within_radius((2, 3), (4, 5), 2));
+        assert!(!TerrainStreamer::is_within_radius((2, 3), (5, 3), 2));
+        assert!(!TerrainStreamer::is_within_radius((-1, -1), (-1, 1), 1));
+    }
+
+    #[test]
+    fn test_only_missing_cells_are_requested() {
+        let mut streamer = small_streamer(1);
+        assert_eq!(streamer.evict_radius, 2);
+        streamer.request_cells_around((0, 0), |cell| cell == (0, 0));
+        // asking again while they are generated doesn't schedule them twice
+        streamer.request_cells_around((0, 0), |cell| cell == (0, 0));
+        let ring = recv_all(&mut streamer);
+        assert_eq!(ring, vec![(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)]);
+
+        // moving one cell over only needs the new column
+        streamer.request_cells_around((1, 0), |cell| cell == (0, 0) || ring.contains(&cell));
+        assert_eq!(recv_all(&mut streamer), vec![(2, -1), (2, 0), (2, 1)]);
+    }
+}
@@ -1,30 +1,263 @@
 use crate::math::{
+    Aabb,
+    Frustum,
     Matrix4f,
+    Vector2f,
     Vector3f,
 };
 use crate::models::{
     WaterModel,
 };
+use super::water_waves::WaterWaves;
+
+// where the reflection on the water comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaterReflection {
+    // the scene rendered again from a mirrored camera, sees what is off screen but costs a whole pass per water height
+    Planar,
+    // ray marched through the depth buffer of the main camera, misses fall back to the skybox
+    ScreenSpace,
+    // the screen space reflection where its rays hit and the planar one everywhere else
+    Hybrid,
+}
+
+impl WaterReflection {
+    pub fn uses_planes(&self) -> bool {
+        *self != WaterReflection::ScreenSpace
+    }
+
+    pub fn uses_screen_space(&self) -> bool {
+        *self != WaterReflection::Planar
+    }
+}
+
+// how a body of water looks, every tile carries its own so a murky pond can sit next to a clear lake
+#[derive(Debug, Clone)]
+pub struct WaterMaterial {
+    // the color the water is mixed with, the deep water fades into it too
+    pub tint: Vector3f,
+    // how much of the tint is mixed into the surface color
+    pub tint_strength: f32,
+    // how quickly the bottom disappears in the tint, per unit of water depth (0 means clear water)
+    pub murkiness: f32,
+    // how far the dudv map scrolls per second, in repeats of the texture
+    pub wave_speed: f32,
+    // how far the waves move the reflection and refraction lookups
+    pub wave_strength: f32,
+    // the higher it is the more the water reflects and the less we see of the bottom
+    pub reflectivity: f32,
+    // without waves the tile is a flat quad, with them it is drawn with the grid mesh and moved in the vertex shader
+    pub waves: WaterWaves,
+    pub reflection: WaterReflection,
+}
+
+impl Default for WaterMaterial {
+    fn default() -> WaterMaterial {
+        WaterMaterial {
+            tint: Vector3f::new(0.0, 0.3, 0.5),
+            tint_strength: 0.2,
+            murkiness: 0.0,
+            wave_speed: 0.03,
+            wave_strength: 0.04,
+            reflectivity: 1.5,
+            waves: WaterWaves::default(),
+            reflection: WaterReflection::Planar,
+        }
+    }
+}
 
 pub struct WaterTile {
     pub position: Vector3f,
+    // the extent along x and z, the water model is a quad from -1 to 1
+    pub size: Vector2f,
     pub transform: Matrix4f,
     pub model: WaterModel,
+    pub material: WaterMaterial,
 }
 
 impl WaterTile {
-    const SIZE: f32 = 200.0;
+    // world units covered by one repeat of the dudv and normal maps, so bigger tiles don't get bigger waves
+    const TEXTURE_SIZE: f32 = 100.0;
+
+    pub fn new(position: Vector3f, size: Vector2f, model: WaterModel) -> Self {
+        WaterTile::with_material(position, size, model, WaterMaterial::default())
+    }
 
-    pub fn new(position: Vector3f, model: WaterModel) -> Self {
-        let transform = Matrix4f::create_transform_matrix(&position, &Vector3f::new(0.0, 0.0, 0.0), WaterTile::SIZE);
+    pub fn with_material(position: Vector3f, size: Vector2f, model: WaterModel, material: WaterMaterial) -> Self {
+        let scale = Vector3f::new(size.x / 2.0, 1.0, size.y / 2.0);
+        let transform = Matrix4f::create_transform_matrix_with_s(&position, &Vector3f::new(0.0, 0.0, 0.0), &scale);
         WaterTile {
             position,
+            size,
             transform,
             model,
+            material,
+        }
+    }
+
+    // how many times the dudv and normal maps repeat over the tile
+    pub fn texture_tiling(&self) -> Vector2f {
+        Vector2f::new(self.size.x / WaterTile::TEXTURE_SIZE, self.size.y / WaterTile::TEXTURE_SIZE)
+    }
+
+    // the waves move the surface by at most their amplitude in any direction
+    pub fn bounds(&self) -> Aabb {
+        let amplitude = self.material.waves.max_amplitude();
+        let half_x = self.size.x / 2.0 + amplitude;
+        let half_z = self.size.y / 2.0 + amplitude;
+        Aabb::new(
+            Vector3f::new(self.position.x - half_x, self.position.y - amplitude, self.position.z - half_z),
+            Vector3f::new(self.position.x + half_x, self.position.y + amplitude, self.position.z + half_z),
+        )
+    }
+
+    pub fn contains_xz(&self, x: f32, z: f32) -> bool {
+        (x - self.position.x).abs() <= self.size.x / 2.0 && (z - self.position.z).abs() <= self.size.y / 2.0
+    }
+
+    // the height of the moving surface, time is the elapsed time of the wall clock like the renderer uses
+    pub fn height_at(&self, x: f32, z: f32, time: f32) -> Option<f32> {
+        if !self.contains_xz(x, z) {
+            return None;
+        }
+        Some(self.position.y + self.material.waves.height_at(x, z, time))
+    }
+
+    // the highest water surface over x, z, for the things that float or swim
+    pub fn surface_height_at(water_tiles: &[WaterTile], x: f32, z: f32, time: f32) -> Option<f32> {
+        water_tiles.iter()
+            .filter_map(|tile| tile.height_at(x, z, time))
+            .fold(None, |highest: Option<f32>, height| Some(highest.map_or(height, |highest| highest.max(height))))
+    }
+}
+
+// the heights we render a reflection and refraction for this frame and which of them every tile uses
+// the tiles in the same plane share the textures, the planes past the budget borrow the ones of the closest height
+#[derive(Debug, Default, Clone)]
+pub struct WaterPlanes {
+    // sorted by the distance of their closest visible tile to the camera
+    pub heights: Vec<f32>,
+    // index into heights, None when the tile is outside of the view or only uses the screen space reflection
+    pub tile_planes: Vec<Option<usize>>,
+    pub visible_tiles: Vec<bool>,
+}
+
+impl WaterPlanes {
+    // tiles closer in height than this lie in the same plane
+    const HEIGHT_TOLERANCE: f32 = 0.01;
+
+    pub fn plan(water_tiles: &[WaterTile], camera_position: &Vector3f, frustum: &Frustum, max_planes: usize) -> WaterPlanes {
+        let bounds: Vec<Aabb> = water_tiles.iter().map(|tile| tile.bounds()).collect();
+        let uses_planes: Vec<bool> = water_tiles.iter().map(|tile| tile.material.reflection.uses_planes()).collect();
+        WaterPlanes::plan_bounds(&bounds, &uses_planes, camera_position, |aabb| frustum.intersects_bounding_box(aabb), max_planes)
+    }
+
+    // how the visible tile gets its reflection this frame, the ones left without a plane fall back to the screen space reflection
+    pub fn tile_reflection(&self, water_tiles: &[WaterTile], tile: usize) -> Option<WaterReflection> {
+        if !self.visible_tiles[tile] {
+            return None;
+        }
+        match self.tile_planes[tile] {
+            Some(_) => Some(water_tiles[tile].material.reflection),
+            None => Some(WaterReflection::ScreenSpace),
+        }
+    }
+
+    fn plan_bounds<F: Fn(&Aabb) -> bool>(bounds: &[Aabb], uses_planes: &[bool], camera_position: &Vector3f, is_visible: F, max_planes: usize) -> WaterPlanes {
+        // every plane with the distance to its closest visible tile
+        let mut planes: Vec<(f32, f32)> = Vec::new();
+        let visible_tiles: Vec<bool> = bounds.iter().map(is_visible).collect();
+        for ((aabb, is_tile_visible), uses_plane) in bounds.iter().zip(visible_tiles.iter()).zip(uses_planes.iter()) {
+            if !is_tile_visible || !uses_plane {
+                continue;
+            }
+            // the waves move the surface up and down around the middle of the box
+            let height = aabb.center().y;
+            let distance = WaterPlanes::distance_to_box(aabb, camera_position);
+            match planes.iter_mut().find(|(plane_height, _)| (plane_height - height).abs() < WaterPlanes::HEIGHT_TOLERANCE) {
+                Some(plane) => plane.1 = plane.1.min(distance),
+                None => planes.push((height, distance)),
+            }
+        }
+        planes.sort_by(|a, b| a.1.partial_cmp(&b.1).expect("Water distances can't be NaN"));
+        planes.truncate(max_planes);
+        let heights: Vec<f32> = planes.into_iter().map(|(height, _)| height).collect();
+
+        let tile_planes = bounds.iter().zip(visible_tiles.iter()).zip(uses_planes.iter()).map(|((aabb, is_tile_visible), uses_plane)| {
+            if !is_tile_visible || !uses_plane {
+                return None;
+            }
+            let height = aabb.center().y;
+            (0..heights.len()).min_by(|&a, &b| (heights[a] - height).abs().partial_cmp(&(heights[b] - height).abs()).expect("Water heights can't be NaN"))
+        }).collect();
+
+        WaterPlanes {
+            heights,
+            tile_planes,
+            visible_tiles,
         }
     }
 
-    pub fn get_water_height(water_tiles: &Vec<WaterTile>) -> f32 {
-        water_tiles[0].position.y
+    fn distance_to_box(aabb: &Aabb, point: &Vector3f) -> f32 {
+        let dx = (aabb.min.x - point.x).max(0.0).max(point.x - aabb.max.x);
+        let dy = (aabb.min.y - point.y).max(0.0).max(point.y - aabb.max.y);
+        let dz = (aabb.min.z - point.z).max(0.0).max(point.z - aabb.max.z);
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(x: f32, height: f32, z: f32, half_size: f32) -> Aabb {
+        Aabb::new(Vector3f::new(x - half_size, height, z - half_size), Vector3f::new(x + half_size, height, z + half_size))
+    }
+
+    #[test]
+    fn test_tiles_at_the_same_height_share_a_plane() {
+        let bounds = vec![tile(0.0, -0.2, 0.0, 10.0), tile(50.0, -0.205, 0.0, 10.0)];
+        let planes = WaterPlanes::plan_bounds(&bounds, &[true, true], &Vector3f::new(0.0, 10.0, 0.0), |_| true, 2);
+        assert_eq!(planes.heights, vec![-0.2]);
+        assert_eq!(planes.tile_planes, vec![Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn test_closest_planes_fill_the_budget() {
+        // a lake at the camera, a pond up the hill further away and a sea far away
+        let bounds = vec![tile(500.0, -5.0, 0.0, 100.0), tile(0.0, 0.0, 0.0, 10.0), tile(60.0, 12.0, 0.0, 5.0)];
+        let planes = WaterPlanes::plan_bounds(&bounds, &[true, true, true], &Vector3f::new(0.0, 2.0, 0.0), |_| true, 2);
+        assert_eq!(planes.heights, vec![0.0, 12.0]);
+        // the sea borrows the reflection of the lake which is closest in height
+        assert_eq!(planes.tile_planes, vec![Some(0), Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn test_culled_tiles_get_no_plane() {
+        let bounds = vec![tile(0.0, 0.0, 0.0, 10.0), tile(0.0, 8.0, -100.0, 10.0)];
+        let planes = WaterPlanes::plan_bounds(&bounds, &[true, true], &Vector3f::new(0.0, 2.0, 0.0), |aabb| aabb.min.z > -50.0, 2);
+        assert_eq!(planes.heights, vec![0.0]);
+        assert_eq!(planes.tile_planes, vec![Some(0), None]);
+        assert_eq!(planes.visible_tiles, vec![true, false]);
+    }
+
+    #[test]
+    fn test_no_budget_draws_no_water() {
+        let bounds = vec![tile(0.0, 0.0, 0.0, 10.0)];
+        let planes = WaterPlanes::plan_bounds(&bounds, &[true], &Vector3f::new(0.0, 2.0, 0.0), |_| true, 0);
+        assert!(planes.heights.is_empty());
+        assert_eq!(planes.tile_planes, vec![None]);
+        // still drawn, just with the screen space reflection
+        assert_eq!(planes.visible_tiles, vec![true]);
+    }
+
+    #[test]
+    fn test_screen_space_tiles_take_no_plane() {
+        // the close pond only uses the screen space reflection so the far lake gets the only plane
+        let bounds = vec![tile(0.0, 4.0, 0.0, 10.0), tile(300.0, 0.0, 0.0, 50.0)];
+        let planes = WaterPlanes::plan_bounds(&bounds, &[false, true], &Vector3f::new(0.0, 6.0, 0.0), |_| true, 1);
+        assert_eq!(planes.heights, vec![0.0]);
+        assert_eq!(planes.tile_planes, vec![None, Some(0)]);
+        assert_eq!(planes.visible_tiles, vec![true, true]);
     }
 }
\ No newline at end of file
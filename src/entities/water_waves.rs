@@ -0,0 +1,174 @@
+use crate::math::{
+    Vector2f,
+    Vector3f,
+};
+
+// one gerstner wave, the points of the surface move in circles so the crests get sharp and the troughs wide
+#[derive(Debug, Clone)]
+pub struct GerstnerWave {
+    pub amplitude: f32,
+    pub wavelength: f32,
+    // the direction the wave travels in on the xz plane, normalized by the constructor
+    pub direction: Vector2f,
+    // 0 is a sine wave, 1 is as sharp as the wave can get before the crests loop over
+    pub steepness: f32,
+}
+
+impl GerstnerWave {
+    pub fn new(amplitude: f32, wavelength: f32, direction: Vector2f, steepness: f32) -> GerstnerWave {
+        let length = direction.length();
+        assert!(length > 0.0 && length.is_finite(), "A wave needs a direction to travel in, got {:?}", direction);
+        GerstnerWave {
+            amplitude,
+            wavelength,
+            direction: Vector2f::new(direction.x / length, direction.y / length),
+            steepness: steepness.clamp(0.0, 1.0),
+        }
+    }
+}
+
+// the sum of waves that moves a water surface, the water vertex shader does the same math so it has to stay in sync with it
+#[derive(Debug, Default, Clone)]
+pub struct WaterWaves {
+    waves: Vec<GerstnerWave>,
+}
+
+impl WaterWaves {
+    // the size of the wave arrays in the water vertex shader
+    pub const MAX_WAVES: usize = 4;
+    const GRAVITY: f32 = 9.81;
+    // fixed point steps to find the undisplaced point under a world position, converges fast as long as the steepness is at most 1
+    const HEIGHT_ITERATIONS: usize = 6;
+
+    pub fn new(waves: Vec<GerstnerWave>) -> WaterWaves {
+        assert!(waves.len() <= WaterWaves::MAX_WAVES, "The water shader can't take more than {} waves", WaterWaves::MAX_WAVES);
+        WaterWaves {
+            waves,
+        }
+    }
+
+    pub fn waves(&self) -> &[GerstnerWave] {
+        &self.waves
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waves.is_empty()
+    }
+
+    // how far any point of the surface can move up or down
+    pub fn max_amplitude(&self) -> f32 {
+        self.waves.iter().map(|wave| wave.amplitude).sum()
+    }
+
+    // how far the point x, z of the flat surface moves at the given time
+    pub fn displacement(&self, x: f32, z: f32, time: f32) -> Vector3f {
+        let mut offset = Vector3f::zero();
+        for wave in self.waves.iter() {
+            let (_, q, phase) = self.wave_terms(wave, x, z, time);
+            let (sin, cos) = phase.sin_cos();
+            offset.x += q * wave.amplitude * wave.direction.x * cos;
+            offset.y += wave.amplitude * sin;
+            offset.z += q * wave.amplitude * wave.direction.y * cos;
+        }
+        offset
+    }
+
+    // the normal of the surface at the displaced position of the flat point x, z
+    pub fn normal(&self, x: f32, z: f32, time: f32) -> Vector3f {
+        let mut normal = Vector3f::new(0.0, 1.0, 0.0);
+        for wave in self.waves.iter() {
+            let (k, q, phase) = self.wave_terms(wave, x, z, time);
+            let (sin, cos) = phase.sin_cos();
+            let ka = k * wave.amplitude;
+            normal.x -= wave.direction.x * ka * cos;
+            normal.y -= q * ka * sin;
+            normal.z -= wave.direction.y * ka * cos;
+        }
+        normal.normalize();
+        normal
+    }
+
+    // the height of the surface above its rest height at the world position x, z
+    // the waves move the points sideways too so we look for the flat point that ends up at x, z first
+    pub fn height_at(&self, x: f32, z: f32, time: f32) -> f32 {
+        let (mut flat_x, mut flat_z) = (x, z);
+        for _ in 0..WaterWaves::HEIGHT_ITERATIONS {
+            let offset = self.displacement(flat_x, flat_z, time);
+            flat_x = x - offset.x;
+            flat_z = z - offset.z;
+        }
+        self.displacement(flat_x, flat_z, time).y
+    }
+
+    // wave number, the horizontal factor and the phase. deep water waves travel with sqrt(g / k)
+    fn wave_terms(&self, wave: &GerstnerWave, x: f32, z: f32, time: f32) -> (f32, f32, f32) {
+        let k = 2.0 * std::f32::consts::PI / wave.wavelength;
+        let speed = (WaterWaves::GRAVITY / k).sqrt();
+        // spread the steepness over the waves so their sum doesn't loop over either
+        let q = if wave.amplitude > 0.0 { wave.steepness / (k * wave.amplitude * self.waves.len() as f32) } else { 0.0 };
+        let phase = k * (wave.direction.x * x + wave.direction.y * z - speed * time);
+        (k, q, phase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_waves() -> WaterWaves {
+        WaterWaves::new(vec![
+            GerstnerWave::new(0.4, 18.0, Vector2f::new(1.0, 0.3), 0.6),
+            GerstnerWave::new(0.2, 9.0, Vector2f::new(-0.4, 1.0), 0.8),
+            GerstnerWave::new(0.1, 4.0, Vector2f::new(0.7, -0.7), 0.5),
+        ])
+    }
+
+    #[test]
+    fn test_no_waves_is_flat() {
+        let waves = WaterWaves::default();
+        let offset = waves.displacement(3.0, -7.0, 12.0);
+        assert_eq!((offset.x, offset.y, offset.z), (0.0, 0.0, 0.0));
+        assert_eq!(waves.height_at(3.0, -7.0, 12.0), 0.0);
+        let normal = waves.normal(3.0, -7.0, 12.0);
+        assert_eq!((normal.x, normal.y, normal.z), (0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_wave_without_direction_is_rejected() {
+        GerstnerWave::new(0.4, 18.0, Vector2f::new(0.0, 0.0), 0.6);
+    }
+
+    #[test]
+    fn test_height_at_finds_displaced_points() {
+        let waves = test_waves();
+        let time = 7.3;
+        for i in 0..50 {
+            let (x, z) = (i as f32 * 1.7 - 40.0, i as f32 * -0.9 + 12.0);
+            let offset = waves.displacement(x, z, time);
+            let height = waves.height_at(x + offset.x, z + offset.z, time);
+            assert!((height - offset.y).abs() < 1e-3, "{} {} {}", i, height, offset.y);
+            assert!(height.abs() <= waves.max_amplitude() + 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_normal_of_a_single_wave_matches_the_surface() {
+        let waves = WaterWaves::new(vec![GerstnerWave::new(0.5, 12.0, Vector2f::new(0.6, 0.8), 0.7)]);
+        let time = 2.0;
+        let eps = 1e-2;
+        let surface = |x: f32, z: f32| {
+            let offset = waves.displacement(x, z, time);
+            Vector3f::new(x + offset.x, offset.y, z + offset.z)
+        };
+        for i in 0..20 {
+            let (x, z) = (i as f32 * 0.83, i as f32 * -0.41);
+            let du = &surface(x + eps, z) - &surface(x - eps, z);
+            let dv = &surface(x, z + eps) - &surface(x, z - eps);
+            let mut expected = dv.cross_prod(&du);
+            expected.normalize();
+            let normal = waves.normal(x, z, time);
+            assert!(normal.dot_product(&expected) > 0.9999, "{} {:?} {:?}", i, normal, expected);
+        }
+    }
+}
\ No newline at end of file
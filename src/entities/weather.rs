@@ -0,0 +1,252 @@
+use crate::display::Display;
+use crate::math::Vector3f;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precipitation {
+    None,
+    Rain,
+    Snow,
+}
+
+// what the weather heads towards, the Weather blends between these over time
+#[derive(Debug, Clone)]
+pub struct WeatherState {
+    pub precipitation: Precipitation,
+    // 0 is nothing falling and 1 a downpour or a blizzard
+    pub intensity: f32,
+    // world units per second, only x and z are used
+    pub wind: Vector3f,
+}
+
+impl WeatherState {
+    pub fn clear() -> WeatherState {
+        WeatherState {
+            precipitation: Precipitation::None,
+            intensity: 0.0,
+            wind: Vector3f::zero(),
+        }
+    }
+
+    pub fn rain(intensity: f32) -> WeatherState {
+        WeatherState {
+            precipitation: Precipitation::Rain,
+            intensity: intensity.clamp(0.0, 1.0),
+            wind: Vector3f::zero(),
+        }
+    }
+
+    pub fn snow(intensity: f32) -> WeatherState {
+        WeatherState {
+            precipitation: Precipitation::Snow,
+            intensity: intensity.clamp(0.0, 1.0),
+            wind: Vector3f::zero(),
+        }
+    }
+
+    pub fn with_wind(mut self, wind: Vector3f) -> WeatherState {
+        self.wind = Vector3f::new(wind.x, 0.0, wind.z);
+        self
+    }
+
+    fn amount_of(&self, precipitation: Precipitation) -> f32 {
+        if self.precipitation == precipitation { self.intensity } else { 0.0 }
+    }
+}
+
+// runs through the states of its forecast one after the other and starts over after the last one
+// the rain and snow particles, the wet and snowy surfaces and the wind of the shaders and the particles all come from here
+#[derive(Debug, Clone)]
+pub struct Weather {
+    // every state with how long it lasts in seconds
+    forecast: Vec<(WeatherState, f32)>,
+    // how long the change to the next state takes, it happens at the end of the current one
+    pub transition_sec: f32,
+    current: usize,
+    time_in_state: f32,
+    // how hard it rains and snows right now
+    pub rain: f32,
+    pub snow: f32,
+    // including the gusts
+    pub wind: Vector3f,
+    // builds up while it rains and dries off after, the surfaces get darker and shinier with it
+    pub wetness: f32,
+    // settles on the ground while it snows and melts away after
+    pub snow_cover: f32,
+}
+
+impl Weather {
+    const WETTING_SPEED: f32 = 0.2;
+    const DRYING_SPEED: f32 = 0.02;
+    const SNOWING_SPEED: f32 = 0.05;
+    const MELTING_SPEED: f32 = 0.01;
+    // rain washes the snow away faster than it melts by itself
+    const RAIN_MELTING_SPEED: f32 = 0.1;
+    // the wind gets up to this much stronger or weaker in gusts
+    const GUSTINESS: f32 = 0.4;
+
+    pub fn new() -> Weather {
+        Weather {
+            forecast: Vec::new(),
+            transition_sec: 10.0,
+            current: 0,
+            time_in_state: 0.0,
+            rain: 0.0,
+            snow: 0.0,
+            wind: Vector3f::zero(),
+            wetness: 0.0,
+            snow_cover: 0.0,
+        }
+    }
+
+    // appends to the forecast, the first state added is what the scene starts with
+    pub fn with_state(mut self, state: WeatherState, duration_sec: f32) -> Weather {
+        if self.forecast.is_empty() {
+            self.rain = state.amount_of(Precipitation::Rain);
+            self.snow = state.amount_of(Precipitation::Snow);
+            self.wind = state.wind.clone();
+        }
+        self.forecast.push((state, duration_sec.max(0.001)));
+        self
+    }
+
+    pub fn with_transition(mut self, transition_sec: f32) -> Weather {
+        self.transition_sec = transition_sec;
+        self
+    }
+
+    // the surfaces start out like this, for scenes that should begin after the rain
+    pub fn with_surfaces(mut self, wetness: f32, snow_cover: f32) -> Weather {
+        self.wetness = wetness.clamp(0.0, 1.0);
+        self.snow_cover = snow_cover.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn state(&self) -> Option<&WeatherState> {
+        self.forecast.get(self.current).map(|(state, _)| state)
+    }
+
+    pub fn is_precipitating(&self) -> bool {
+        self.rain > 0.0 || self.snow > 0.0
+    }
+
+    pub fn update(&mut self, display: &Display) {
+        self.advance(display.frame_time_sec, display.wall_clock.elapsed);
+    }
+
+    fn advance(&mut self, frame_time_sec: f32, elapsed: f32) {
+        if self.forecast.is_empty() {
+            return;
+        }
+        self.time_in_state += frame_time_sec;
+        while self.time_in_state >= self.forecast[self.current].1 {
+            self.time_in_state -= self.forecast[self.current].1;
+            self.current = (self.current + 1) % self.forecast.len();
+        }
+
+        let (from, duration) = &self.forecast[self.current];
+        let to = &self.forecast[(self.current + 1) % self.forecast.len()].0;
+        let transition = self.transition_sec.min(*duration);
+        // 0 until the transition starts then up to 1 at the end of the state
+        let t = if transition > 0.0 { ((self.time_in_state - (duration - transition)) / transition).max(0.0) } else { 0.0 };
+        let blend = |a: f32, b: f32| a + (b - a) * t;
+        self.rain = blend(from.amount_of(Precipitation::Rain), to.amount_of(Precipitation::Rain));
+        self.snow = blend(from.amount_of(Precipitation::Snow), to.amount_of(Precipitation::Snow));
+        let gust = 1.0 + Weather::GUSTINESS * (elapsed * 0.7).sin() * (elapsed * 1.9).sin();
+        // Vector3f::lerp weights the first vector by t
+        self.wind = Vector3f::lerp(&to.wind, &from.wind, t) * gust;
+
+        let wetting = if self.rain > 0.0 { Weather::WETTING_SPEED * self.rain } else { -Weather::DRYING_SPEED };
+        self.wetness = (self.wetness + wetting * frame_time_sec).clamp(0.0, 1.0);
+        let snowing = if self.snow > 0.0 {
+            Weather::SNOWING_SPEED * self.snow
+        } else {
+            -Weather::MELTING_SPEED - Weather::RAIN_MELTING_SPEED * self.rain
+        };
+        self.snow_cover = (self.snow_cover + snowing * frame_time_sec).clamp(0.0, 1.0);
+    }
+}
+
+impl Default for Weather {
+    fn default() -> Weather {
+        Weather::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(weather: &mut Weather, seconds: f32) {
+        let steps = (seconds * 10.0) as usize;
+        for _ in 0..steps {
+            weather.advance(0.1, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_forecast_transitions() {
+        let mut weather = Weather::new()
+            .with_state(WeatherState::clear(), 20.0)
+            .with_state(WeatherState::rain(0.8).with_wind(Vector3f::new(4.0, 3.0, 0.0)), 20.0)
+            .with_transition(10.0);
+        assert_eq!(weather.rain, 0.0);
+        run(&mut weather, 15.05);
+        // halfway through the change to rain
+        assert!((weather.rain - 0.4).abs() < 0.02);
+        assert!(weather.wind.x > 0.0 && weather.wind.y == 0.0);
+        run(&mut weather, 10.0);
+        assert_eq!(weather.state().expect("There is a forecast").precipitation, Precipitation::Rain);
+        assert!((weather.rain - 0.8).abs() < 1e-5);
+        // back to the start after the last state
+        run(&mut weather, 20.0);
+        assert_eq!(weather.state().expect("There is a forecast").precipitation, Precipitation::None);
+    }
+
+    #[test]
+    fn test_surfaces_get_wet_and_snowy() {
+        let mut weather = Weather::new().with_state(WeatherState::rain(1.0), 100.0).with_state(WeatherState::snow(1.0), 100.0).with_transition(0.0);
+        run(&mut weather, 10.0);
+        assert!(weather.wetness > 0.9);
+        assert_eq!(weather.snow_cover, 0.0);
+        run(&mut weather, 120.0);
+        assert_eq!(weather.snow, 1.0);
+        assert!(weather.snow_cover > 0.9);
+        // the snow doesn't make anything wetter
+        assert!(weather.wetness < 0.5);
+
+        let mut dry = Weather::new().with_surfaces(1.0, 1.0);
+        run(&mut dry, 10.0);
+        // without a forecast nothing changes
+        assert_eq!(dry.wetness, 1.0);
+        assert!(!dry.is_precipitating());
+    }
+
+    #[test]
+    fn test_single_state_forecast_stays() {
+        let mut weather = Weather::new().with_state(WeatherState::snow(0.6).with_wind(Vector3f::new(2.0, 0.0, 1.0)), 30.0).with_transition(10.0);
+        // through the end of the state and its transition several times, it only ever changes into itself
+        for _ in 0..10 {
+            run(&mut weather, 13.0);
+            assert_eq!(weather.state().expect("There is a forecast").precipitation, Precipitation::Snow);
+            assert_eq!(weather.snow, 0.6);
+            assert_eq!(weather.rain, 0.0);
+            assert!((weather.wind.x - 2.0).abs() < 1e-5 && (weather.wind.z - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_wind_gusts() {
+        let mut weather = Weather::new().with_state(WeatherState::clear().with_wind(Vector3f::new(3.0, 0.0, 4.0)), 100.0);
+        let (mut weakest, mut strongest) = (f32::MAX, 0.0f32);
+        for i in 0..1000 {
+            weather.advance(0.01, i as f32 * 0.1);
+            let strength = weather.wind.length();
+            weakest = weakest.min(strength);
+            strongest = strongest.max(strength);
+            // the gusts only change how hard it blows, not where to
+            assert!((weather.wind.x * 4.0 - weather.wind.z * 3.0).abs() < 1e-4);
+        }
+        assert!(weakest >= 5.0 * (1.0 - Weather::GUSTINESS) - 1e-4 && strongest <= 5.0 * (1.0 + Weather::GUSTINESS) + 1e-4);
+        assert!(strongest - weakest > 5.0 * Weather::GUSTINESS, "the wind goes from {} to {}", weakest, strongest);
+    }
+}
\ No newline at end of file
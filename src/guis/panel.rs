@@ -8,6 +8,8 @@ pub struct GuiPanel {
     pub position: Vector2f,
     // scale relative to screen width/height
     pub scale: Vector2f,
+    // backgrounds cover the whole screen whatever its aspect ratio, the other panels keep their shape
+    pub fill_screen: bool,
 }
 
 impl GuiPanel {
@@ -16,6 +18,16 @@ impl GuiPanel {
             texture_id,
             position,
             scale,
+            fill_screen: false,
+        }
+    }
+
+    pub fn new_background(texture_id: TextureId) -> GuiPanel {
+        GuiPanel {
+            texture_id,
+            position: Vector2f::new(0.0, 0.0),
+            scale: Vector2f::new(1.0, 1.0),
+            fill_screen: true,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+use super::{
+    Matrix4f,
+    Vector3f,
+};
+
+// axis aligned box
+#[derive(Debug, Default, Clone)]
+pub struct Aabb {
+    pub min: Vector3f,
+    pub max: Vector3f,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3f, max: Vector3f) -> Aabb {
+        Aabb {
+            min,
+            max,
+        }
+    }
+
+    // box around flat xyz positions like the ones we put in the vbos, an empty mesh gets an empty box at the origin
+    pub fn from_positions(positions: &[f32]) -> Aabb {
+        if positions.len() < 3 {
+            return Aabb::default();
+        }
+        let mut min = Vector3f::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vector3f::new(f32::MIN, f32::MIN, f32::MIN);
+        for pos in positions.chunks_exact(3) {
+            min.x = min.x.min(pos[0]);
+            min.y = min.y.min(pos[1]);
+            min.z = min.z.min(pos[2]);
+            max.x = max.x.max(pos[0]);
+            max.y = max.y.max(pos[1]);
+            max.z = max.z.max(pos[2]);
+        }
+        Aabb::new(min, max)
+    }
+
+    pub fn center(&self) -> Vector3f {
+        Vector3f::lerp(&self.min, &self.max, 0.5)
+    }
+
+    // box around the transformed box (Arvo's method)
+    // every output axis is the translation plus the smallest/largest contribution of each input axis, so we dont have to transform all 8 corners
+    pub fn transform(&self, mat: &Matrix4f) -> Aabb {
+        let min_in = [self.min.x, self.min.y, self.min.z];
+        let max_in = [self.max.x, self.max.y, self.max.z];
+        let mut min_out = [mat[0][3], mat[1][3], mat[2][3]];
+        let mut max_out = min_out;
+        for i in 0..3 {
+            for j in 0..3 {
+                let a = mat[i][j] * min_in[j];
+                let b = mat[i][j] * max_in[j];
+                min_out[i] += a.min(b);
+                max_out[i] += a.max(b);
+            }
+        }
+        Aabb::new(Vector3f::new(min_out[0], min_out[1], min_out[2]), Vector3f::new(max_out[0], max_out[1], max_out[2]))
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BoundingSphere {
+    pub center: Vector3f,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vector3f, radius: f32) -> BoundingSphere {
+        BoundingSphere {
+            center,
+            radius,
+        }
+    }
+
+    pub fn intersects(&self, other: &BoundingSphere) -> bool {
+        let radius_sum = self.radius + other.radius;
+        (&self.center - &other.center).length_squared() <= radius_sum * radius_sum
+    }
+}
+
+// bounds of a mesh in model space
+#[derive(Debug, Default, Clone)]
+pub struct ModelBounds {
+    pub aabb: Aabb,
+    // distance of the furthest vertex from the model origin. a sphere around the origin doesn't move when the model rotates
+    // so the world space sphere is just the entity position and the radius times the scale
+    pub radius: f32,
+}
+
+impl ModelBounds {
+    pub fn new(positions: &[f32], furthest_point: f32) -> ModelBounds {
+        ModelBounds {
+            aabb: Aabb::from_positions(positions),
+            radius: furthest_point,
+        }
+    }
+
+    pub fn world_sphere(&self, position: &Vector3f, scale: f32) -> BoundingSphere {
+        BoundingSphere::new(position.clone(), self.radius * scale)
+    }
+
+    pub fn world_aabb(&self, transform_matrix: &Matrix4f) -> Aabb {
+        self.aabb.transform(transform_matrix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::*;
+    use crate::math::Vector4f;
+
+    #[test]
+    fn test_from_positions() {
+        let aabb = Aabb::from_positions(&[1.0, -2.0, 3.0, -1.0, 4.0, 0.5, 0.0, 0.0, -6.0]);
+        assert_f32_eq!(aabb.min.x, -1.0, test_constants::EPS_PRECISE);
+        assert_f32_eq!(aabb.min.y, -2.0, test_constants::EPS_PRECISE);
+        assert_f32_eq!(aabb.min.z, -6.0, test_constants::EPS_PRECISE);
+        assert_f32_eq!(aabb.max.x, 1.0, test_constants::EPS_PRECISE);
+        assert_f32_eq!(aabb.max.y, 4.0, test_constants::EPS_PRECISE);
+        assert_f32_eq!(aabb.max.z, 3.0, test_constants::EPS_PRECISE);
+    }
+
+    #[test]
+    fn test_transform_matches_corners() {
+        let aabb = Aabb::new(Vector3f::new(-1.0, 0.0, -2.0), Vector3f::new(3.0, 5.0, 1.0));
+        let mat = Matrix4f::create_transform_matrix(&Vector3f::new(10.0, -4.0, 2.0), &Vector3f::new(30.0, 45.0, -20.0), 2.0);
+        let transformed = aabb.transform(&mat);
+
+        // same as transforming all 8 corners and taking their box
+        let mut corners = Vec::new();
+        for &x in [aabb.min.x, aabb.max.x].iter() {
+            for &y in [aabb.min.y, aabb.max.y].iter() {
+                for &z in [aabb.min.z, aabb.max.z].iter() {
+                    let corner = mat.transform(&Vector4f::point(&Vector3f::new(x, y, z)));
+                    corners.extend_from_slice(&[corner.x, corner.y, corner.z]);
+                }
+            }
+        }
+        let expected = Aabb::from_positions(&corners);
+        assert_f32_eq!(transformed.min.x, expected.min.x, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(transformed.min.y, expected.min.y, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(transformed.min.z, expected.min.z, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(transformed.max.x, expected.max.x, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(transformed.max.y, expected.max.y, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(transformed.max.z, expected.max.z, test_constants::EPS_MEDIUM);
+    }
+
+    #[test]
+    fn test_sphere_intersects() {
+        let sphere = BoundingSphere::new(Vector3f::new(0.0, 0.0, 0.0), 2.0);
+        assert!(sphere.intersects(&BoundingSphere::new(Vector3f::new(3.0, 0.0, 0.0), 1.5)));
+        assert!(!sphere.intersects(&BoundingSphere::new(Vector3f::new(3.0, 3.0, 0.0), 1.5)));
+    }
+}
\ No newline at end of file
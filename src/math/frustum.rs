@@ -0,0 +1,154 @@
+use super::{
+    Aabb,
+    BoundingSphere,
+    Matrix4f,
+    Vector3f,
+    Vector4f,
+};
+
+// view frustum as planes (a, b, c, d) with the normals pointing inwards
+// a point p is inside a plane when a*p.x + b*p.y + c*p.z + d >= 0
+// works for perspective and ortho matrices alike as long as they map the visible volume to the usual gl clip cube,
+// which our projection matrices do when they get the near and far planes as negative z like Display::NEAR and Display::FAR
+#[derive(Debug, Clone)]
+pub struct Frustum {
+    planes: [Vector4f; 6],
+}
+
+impl Frustum {
+    // Gribb/Hartmann plane extraction from projection * view (or projection * view * model to get it in model space)
+    pub fn from_matrix(mat: &Matrix4f) -> Frustum {
+        let row = |i: usize| Vector4f::new(mat[i][0], mat[i][1], mat[i][2], mat[i][3]);
+        let plane = |a: Vector4f, b: Vector4f, sign: f32| {
+            let mut plane = Vector4f::new(a.x + sign * b.x, a.y + sign * b.y, a.z + sign * b.z, a.w + sign * b.w);
+            let len = (plane.x * plane.x + plane.y * plane.y + plane.z * plane.z).sqrt();
+            if len > 0.0 {
+                plane.x /= len;
+                plane.y /= len;
+                plane.z /= len;
+                plane.w /= len;
+            }
+            plane
+        };
+        Frustum {
+            planes: [
+                // left, right
+                plane(row(3), row(0), 1.0),
+                plane(row(3), row(0), -1.0),
+                // bottom, top
+                plane(row(3), row(1), 1.0),
+                plane(row(3), row(1), -1.0),
+                // near, far
+                plane(row(3), row(2), 1.0),
+                plane(row(3), row(2), -1.0),
+            ],
+        }
+    }
+
+    pub fn from_view_projection(projection_matrix: &Matrix4f, view_matrix: &Matrix4f) -> Frustum {
+        let mut vp_matrix = view_matrix.clone();
+        vp_matrix.pre_multiply_in_place(projection_matrix);
+        Frustum::from_matrix(&vp_matrix)
+    }
+
+    pub fn contains_point(&self, point: &Vector3f) -> bool {
+        self.planes.iter().all(|plane| plane.dot_product_v3(point) + plane.w >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, center: &Vector3f, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.dot_product_v3(center) + plane.w >= -radius)
+    }
+
+    pub fn intersects_bounding_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.intersects_sphere(&sphere.center, sphere.radius)
+    }
+
+    // conservative test, may report boxes near the frustum corners as intersecting
+    pub fn intersects_aabb(&self, min: &Vector3f, max: &Vector3f) -> bool {
+        self.planes.iter().all(|plane| {
+            // the box corner furthest along the plane normal
+            let positive_vertex = Vector3f::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.dot_product_v3(&positive_vertex) + plane.w >= 0.0
+        })
+    }
+
+    pub fn intersects_bounding_box(&self, aabb: &Aabb) -> bool {
+        self.intersects_aabb(&aabb.min, &aabb.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_frustum() -> Frustum {
+        // camera at the origin looking down -z, near and far given as z like Display::NEAR and Display::FAR
+        let projection = Matrix4f::create_projection_matrix(-0.1, -1000.0, 90.0, 1.0);
+        Frustum::from_matrix(&projection)
+    }
+
+    #[test]
+    fn test_contains_point() {
+        let frustum = test_frustum();
+        assert!(frustum.contains_point(&Vector3f::new(0.0, 0.0, -10.0)));
+        // behind the camera
+        assert!(!frustum.contains_point(&Vector3f::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(&Vector3f::new(1.0, 1.0, 0.5)));
+        // 90 degree fov so at distance 10 the frustum is 20 wide
+        assert!(frustum.contains_point(&Vector3f::new(9.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(&Vector3f::new(11.0, 0.0, -10.0)));
+        // in front of the near plane and past the far plane
+        assert!(!frustum.contains_point(&Vector3f::new(0.0, 0.0, -0.05)));
+        assert!(frustum.contains_point(&Vector3f::new(0.0, 0.0, -999.0)));
+        assert!(!frustum.contains_point(&Vector3f::new(0.0, 0.0, -1001.0)));
+    }
+
+    #[test]
+    fn test_intersects_sphere() {
+        let frustum = test_frustum();
+        assert!(frustum.intersects_sphere(&Vector3f::new(0.0, 0.0, 5.0), 6.0));
+        assert!(!frustum.intersects_sphere(&Vector3f::new(0.0, 0.0, 10.0), 4.0));
+        assert!(frustum.intersects_sphere(&Vector3f::new(12.0, 0.0, -10.0), 2.0));
+        assert!(frustum.intersects_sphere(&Vector3f::new(0.0, 0.0, -1003.0), 4.0));
+        assert!(!frustum.intersects_sphere(&Vector3f::new(0.0, 0.0, -1010.0), 4.0));
+    }
+
+    #[test]
+    fn test_intersects_aabb() {
+        let frustum = test_frustum();
+        assert!(frustum.intersects_aabb(&Vector3f::new(-1.0, -1.0, -11.0), &Vector3f::new(1.0, 1.0, -9.0)));
+        // straddles the left plane
+        assert!(frustum.intersects_aabb(&Vector3f::new(-15.0, -1.0, -11.0), &Vector3f::new(-8.0, 1.0, -9.0)));
+        // behind the camera
+        assert!(!frustum.intersects_aabb(&Vector3f::new(5.0, -1.0, 1.0), &Vector3f::new(6.0, 1.0, 3.0)));
+        assert!(!frustum.intersects_aabb(&Vector3f::new(20.0, -1.0, -11.0), &Vector3f::new(25.0, 1.0, -9.0)));
+        assert!(!frustum.intersects_aabb(&Vector3f::new(-1.0, -1.0, -1200.0), &Vector3f::new(1.0, 1.0, -1005.0)));
+    }
+
+    #[test]
+    fn test_terrain_chunks_are_culled_by_all_planes() {
+        // the camera of a scene looking along +x over terrain chunks of 100 x 100
+        let view = Matrix4f::create_fps_view_matrix(&Vector3f::new(0.0, 10.0, 0.0), 0.0, -90.0);
+        let frustum = Frustum::from_view_projection(&Matrix4f::create_projection_matrix(-0.1, -1000.0, 90.0, 16.0 / 9.0), &view);
+        let chunk = |x: f32, z: f32| frustum.intersects_aabb(&Vector3f::new(x, -5.0, z), &Vector3f::new(x + 100.0, 5.0, z + 100.0));
+        let visible: Vec<bool> = [(50.0, -50.0), (-200.0, -50.0), (1100.0, -50.0), (500.0, -50.0), (0.0, 800.0)].iter().map(|(x, z)| chunk(*x, *z)).collect();
+        // ahead, behind the camera, past the far plane, far ahead but in range, off to the side
+        assert_eq!(visible, vec![true, false, false, true, false]);
+    }
+
+    #[test]
+    fn test_ortho_frustum() {
+        let ortho = Matrix4f::create_ortho_projection_matrix(-10.0, 10.0, -5.0, 5.0, 1.0, 50.0);
+        let frustum = Frustum::from_matrix(&ortho);
+        assert!(frustum.contains_point(&Vector3f::new(9.0, 4.0, -2.0)));
+        assert!(frustum.contains_point(&Vector3f::new(-9.0, -4.0, -49.0)));
+        assert!(!frustum.contains_point(&Vector3f::new(0.0, 0.0, -0.5)));
+        assert!(!frustum.contains_point(&Vector3f::new(0.0, 0.0, -51.0)));
+        assert!(!frustum.contains_point(&Vector3f::new(0.0, 6.0, -10.0)));
+        assert!(frustum.intersects_sphere(&Vector3f::new(0.0, 6.0, -10.0), 1.5));
+    }
+}
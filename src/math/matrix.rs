@@ -121,6 +121,18 @@ impl Matrix4f {
         }
     }
 
+    // same as glOrtho, near and far are distances in front of the eye so the box spans z in [-far, -near] in eye space
+    pub fn create_ortho_projection_matrix(left: f32, right: f32, bottom: f32, top: f32, near_plane: f32, far_plane: f32) -> Matrix4f {
+        let mut ortho_mat = Matrix4f::identity();
+        ortho_mat[0][0] = 2.0 / (right - left);
+        ortho_mat[0][3] = -(right + left) / (right - left);
+        ortho_mat[1][1] = 2.0 / (top - bottom);
+        ortho_mat[1][3] = -(top + bottom) / (top - bottom);
+        ortho_mat[2][2] = -2.0 / (far_plane - near_plane);
+        ortho_mat[2][3] = -(far_plane + near_plane) / (far_plane - near_plane);
+        ortho_mat
+    }
+
     pub fn update_ortho_projection_matrix(ortho_mat: &mut Matrix4f, frustum_width: f32, frustum_height: f32, frustum_length: f32) {        
         ortho_mat[0][0] = 2.0 / frustum_width;
         ortho_mat[1][1] = 2.0 / frustum_height;
@@ -2,8 +2,13 @@ pub mod matrix;
 pub mod vector;
 pub mod quaternion;
 pub mod utils;
+pub mod noise;
+pub mod frustum;
+pub mod bounds;
 
 pub use self::matrix::*;
 pub use self::vector::*;
 pub use self::quaternion::*;
-pub use self::utils::*;
\ No newline at end of file
+pub use self::utils::*;
+pub use self::frustum::*;
+pub use self::bounds::*;
\ No newline at end of file
@@ -0,0 +1,418 @@
+use crate::utils::murmur3;
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
+
+// 2d coherent noise that can be composed into a graph of noise functions
+// everything is sampled in continuous coordinates so neighbouring terrain cells can just sample at their world position
+// Send + Sync so a noise graph can be shared with the threads that generate terrain
+pub trait Noise: Send + Sync {
+    // roughly in the [-1, 1] range
+    fn sample(&self, x: f32, z: f32) -> f32;
+}
+
+// unit length gradients used by both perlin and simplex noise
+const GRADIENTS_2D: [(f32, f32); 8] = [
+    (1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0),
+    (FRAC_1_SQRT_2, FRAC_1_SQRT_2), (-FRAC_1_SQRT_2, FRAC_1_SQRT_2), (FRAC_1_SQRT_2, -FRAC_1_SQRT_2), (-FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+];
+
+fn lattice_gradient(ix: i32, iz: i32, seed: u32) -> (f32, f32) {
+    let hash = murmur3(ix as u32, iz as u32, seed);
+    GRADIENTS_2D[(hash & 7) as usize]
+}
+
+// quintic smoothstep 6t^5 - 15t^4 + 10t^3 which has zero first and second derivatives at 0 and 1
+// that is what makes perlin noise continuous across lattice cells
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+pub struct Perlin {
+    seed: u32,
+}
+
+impl Perlin {
+    // max value of 2d perlin with unit gradients is sqrt(2)/2 so scale it to [-1, 1]
+    const SCALE: f32 = SQRT_2;
+
+    pub fn new(seed: u32) -> Self {
+        Perlin {
+            seed,
+        }
+    }
+
+    fn corner(&self, ix: i32, iz: i32, dx: f32, dz: f32) -> f32 {
+        let (gx, gz) = lattice_gradient(ix, iz, self.seed);
+        gx * dx + gz * dz
+    }
+}
+
+impl Noise for Perlin {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let x_floor = x.floor();
+        let z_floor = z.floor();
+        let (ix, iz) = (x_floor as i32, z_floor as i32);
+        let (fx, fz) = (x - x_floor, z - z_floor);
+
+        let n00 = self.corner(ix, iz, fx, fz);
+        let n10 = self.corner(ix + 1, iz, fx - 1.0, fz);
+        let n01 = self.corner(ix, iz + 1, fx, fz - 1.0);
+        let n11 = self.corner(ix + 1, iz + 1, fx - 1.0, fz - 1.0);
+
+        let u = fade(fx);
+        let v = fade(fz);
+        lerp(lerp(n00, n10, u), lerp(n01, n11, u), v) * Perlin::SCALE
+    }
+}
+
+pub struct Simplex {
+    seed: u32,
+}
+
+impl Simplex {
+    // skew and unskew factors to go between the square grid and the grid of equilateral triangles
+    const F2: f32 = 0.3660254; // (sqrt(3) - 1) / 2
+    const G2: f32 = 0.21132487; // (3 - sqrt(3)) / 6
+    const SCALE: f32 = 70.0;
+
+    pub fn new(seed: u32) -> Self {
+        Simplex {
+            seed,
+        }
+    }
+
+    fn corner(&self, ix: i32, iz: i32, dx: f32, dz: f32) -> f32 {
+        // radial falloff kernel which reaches 0 before the next corner so the sum is continuous
+        let t = 0.5 - dx * dx - dz * dz;
+        if t < 0.0 {
+            0.0
+        } else {
+            let (gx, gz) = lattice_gradient(ix, iz, self.seed);
+            let t2 = t * t;
+            t2 * t2 * (gx * dx + gz * dz)
+        }
+    }
+}
+
+impl Noise for Simplex {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        // find which triangle we are in by skewing into the square grid
+        let s = (x + z) * Simplex::F2;
+        let i = (x + s).floor();
+        let j = (z + s).floor();
+        let t = (i + j) * Simplex::G2;
+        let x0 = x - (i - t);
+        let z0 = z - (j - t);
+        // lower or upper triangle of the skewed square
+        let (i1, j1) = if x0 > z0 { (1, 0) } else { (0, 1) };
+        let x1 = x0 - i1 as f32 + Simplex::G2;
+        let z1 = z0 - j1 as f32 + Simplex::G2;
+        let x2 = x0 - 1.0 + 2.0 * Simplex::G2;
+        let z2 = z0 - 1.0 + 2.0 * Simplex::G2;
+
+        let (i, j) = (i as i32, j as i32);
+        let n0 = self.corner(i, j, x0, z0);
+        let n1 = self.corner(i + i1, j + j1, x1, z1);
+        let n2 = self.corner(i + 1, j + 1, x2, z2);
+        (n0 + n1 + n2) * Simplex::SCALE
+    }
+}
+
+// shared params for the fractal noises. each octave multiplies the frequency by lacunarity and the amplitude by gain
+#[derive(Clone, Copy, Debug)]
+pub struct FractalParams {
+    pub octaves: usize,
+    pub frequency: f32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl FractalParams {
+    // shift each octave so that the lattice points (where gradient noise is 0) of the octaves don't line up
+    const OCTAVE_OFFSET: f32 = 17.31;
+}
+
+impl Default for FractalParams {
+    fn default() -> Self {
+        FractalParams {
+            octaves: 5,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+// fractional brownian motion. sum of octaves of the source noise normalized back to [-1, 1]
+pub struct Fbm {
+    source: Box<dyn Noise>,
+    pub params: FractalParams,
+}
+
+impl Fbm {
+    pub fn new(source: Box<dyn Noise>, params: FractalParams) -> Self {
+        Fbm {
+            source,
+            params,
+        }
+    }
+}
+
+impl Noise for Fbm {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut frequency = self.params.frequency;
+        let mut amplitude = 1.0;
+        for octave in 0..self.params.octaves {
+            let offset = octave as f32 * FractalParams::OCTAVE_OFFSET;
+            total += amplitude * self.source.sample(x * frequency + offset, z * frequency + offset);
+            total_amplitude += amplitude;
+            frequency *= self.params.lacunarity;
+            amplitude *= self.params.gain;
+        }
+        if total_amplitude > 0.0 { total / total_amplitude } else { 0.0 }
+    }
+}
+
+// musgrave's ridged multifractal. 1 - |noise| creates sharp ridges where the noise crosses 0
+// and each octave is weighted by the previous one so that detail accumulates on the ridges and the valleys stay smooth
+pub struct RidgedMulti {
+    source: Box<dyn Noise>,
+    pub params: FractalParams,
+    pub offset: f32,
+    pub weight_gain: f32,
+}
+
+impl RidgedMulti {
+    pub fn new(source: Box<dyn Noise>, params: FractalParams) -> Self {
+        RidgedMulti {
+            source,
+            params,
+            offset: 1.0,
+            weight_gain: 2.0,
+        }
+    }
+}
+
+impl Noise for RidgedMulti {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut frequency = self.params.frequency;
+        let mut amplitude = 1.0;
+        let mut weight = 1.0;
+        for octave in 0..self.params.octaves {
+            let offset = octave as f32 * FractalParams::OCTAVE_OFFSET;
+            let mut signal = self.offset - self.source.sample(x * frequency + offset, z * frequency + offset).abs();
+            signal *= signal;
+            signal *= weight;
+            weight = (signal * self.weight_gain).clamp(0.0, 1.0);
+            total += signal * amplitude;
+            total_amplitude += amplitude;
+            frequency *= self.params.lacunarity;
+            amplitude *= self.params.gain;
+        }
+        // the signal is in [0, offset^2] so map it to [-1, 1]
+        let max_signal = self.offset * self.offset;
+        if total_amplitude > 0.0 && max_signal > 0.0 {
+            2.0 * total / (total_amplitude * max_signal) - 1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+// offsets the sample position of the source by two other noises which gives twisted/flowing features
+pub struct DomainWarp {
+    source: Box<dyn Noise>,
+    warp_x: Box<dyn Noise>,
+    warp_z: Box<dyn Noise>,
+    pub strength: f32,
+}
+
+impl DomainWarp {
+    pub fn new(source: Box<dyn Noise>, warp_x: Box<dyn Noise>, warp_z: Box<dyn Noise>, strength: f32) -> Self {
+        DomainWarp {
+            source,
+            warp_x,
+            warp_z,
+            strength,
+        }
+    }
+}
+
+impl Noise for DomainWarp {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        let dx = self.warp_x.sample(x, z) * self.strength;
+        let dz = self.warp_z.sample(x, z) * self.strength;
+        self.source.sample(x + dx, z + dz)
+    }
+}
+
+pub struct ScaleBias {
+    source: Box<dyn Noise>,
+    pub scale: f32,
+    pub bias: f32,
+}
+
+impl ScaleBias {
+    pub fn new(source: Box<dyn Noise>, scale: f32, bias: f32) -> Self {
+        ScaleBias {
+            source,
+            scale,
+            bias,
+        }
+    }
+}
+
+impl Noise for ScaleBias {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        self.source.sample(x, z) * self.scale + self.bias
+    }
+}
+
+pub struct Sum {
+    sources: Vec<Box<dyn Noise>>,
+}
+
+impl Sum {
+    pub fn new(sources: Vec<Box<dyn Noise>>) -> Self {
+        Sum {
+            sources,
+        }
+    }
+}
+
+impl Noise for Sum {
+    fn sample(&self, x: f32, z: f32) -> f32 {
+        self.sources.iter().map(|source| source.sample(x, z)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::*;
+
+    // max change between two samples STEP apart. used to check that the noises don't jump at cell boundaries
+    const STEP: f32 = 1e-3;
+    const MAX_JUMP: f32 = 0.05;
+
+    fn assert_continuous_along_x(noise: &dyn Noise, z: f32, from: f32, to: f32) {
+        let mut x = from;
+        let mut prev = noise.sample(x, z);
+        while x < to {
+            x += STEP;
+            let cur = noise.sample(x, z);
+            assert!((cur - prev).abs() < MAX_JUMP, "Noise jumped from {} to {} at ({}, {})", prev, cur, x, z);
+            prev = cur;
+        }
+    }
+
+    fn fractal_params() -> FractalParams {
+        FractalParams {
+            octaves: 4,
+            frequency: 0.25,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn perlin_zero_on_lattice() {
+        let perlin = Perlin::new(42);
+        for i in -3..3 {
+            for j in -3..3 {
+                assert_f32_eq!(perlin.sample(i as f32, j as f32), 0.0, test_constants::EPS_PRECISE);
+            }
+        }
+    }
+
+    #[test]
+    fn perlin_continuous_across_lattice_cells() {
+        let perlin = Perlin::new(42);
+        for i in -3..3 {
+            let boundary = i as f32;
+            for z in &[-2.3, 0.41, 1.77] {
+                let left = perlin.sample(boundary - STEP, *z);
+                let right = perlin.sample(boundary + STEP, *z);
+                assert!((left - right).abs() < MAX_JUMP, "Perlin discontinuous at x={} z={}: {} vs {}", boundary, z, left, right);
+            }
+        }
+        assert_continuous_along_x(&perlin, 0.37, -3.0, 3.0);
+    }
+
+    #[test]
+    fn simplex_continuous_across_triangle_cells() {
+        let simplex = Simplex::new(42);
+        // diagonal lines cross both the skewed square grid and the triangle split
+        assert_continuous_along_x(&simplex, 0.37, -3.0, 3.0);
+        assert_continuous_along_x(&simplex, -1.5, -3.0, 3.0);
+    }
+
+    #[test]
+    fn noises_stay_in_range() {
+        let noises: Vec<Box<dyn Noise>> = vec![
+            Box::new(Perlin::new(7)),
+            Box::new(Simplex::new(7)),
+            Box::new(Fbm::new(Box::new(Perlin::new(7)), fractal_params())),
+            Box::new(RidgedMulti::new(Box::new(Simplex::new(7)), fractal_params())),
+        ];
+        for noise in noises.iter() {
+            for i in 0..400 {
+                let x = i as f32 * 0.173 - 30.0;
+                let z = i as f32 * 0.311 - 50.0;
+                let value = noise.sample(x, z);
+                assert!((-1.0 - test_constants::EPS_BAD..=1.0 + test_constants::EPS_BAD).contains(&value), "Noise out of range: {}", value);
+            }
+        }
+    }
+
+    #[test]
+    fn deterministic_per_seed() {
+        let build = |seed: u32| {
+            let warp_x = Box::new(Fbm::new(Box::new(Simplex::new(seed + 1)), fractal_params()));
+            let warp_z = Box::new(Fbm::new(Box::new(Simplex::new(seed + 2)), fractal_params()));
+            let source = Box::new(RidgedMulti::new(Box::new(Perlin::new(seed)), fractal_params()));
+            DomainWarp::new(source, warp_x, warp_z, 2.0)
+        };
+        let a = build(3);
+        let b = build(3);
+        let c = build(4);
+        let mut differs = false;
+        for i in 0..100 {
+            let (x, z) = (i as f32 * 0.731, i as f32 * -0.377);
+            assert_eq!(a.sample(x, z), b.sample(x, z));
+            differs |= (a.sample(x, z) - c.sample(x, z)).abs() > test_constants::EPS_BAD;
+        }
+        assert!(differs, "Different seeds should give different noise");
+    }
+
+    #[test]
+    fn fractal_noises_continuous() {
+        let fbm = Fbm::new(Box::new(Perlin::new(11)), fractal_params());
+        assert_continuous_along_x(&fbm, 1.3, -8.0, 8.0);
+        let ridged = RidgedMulti::new(Box::new(Simplex::new(11)), fractal_params());
+        assert_continuous_along_x(&ridged, 1.3, -8.0, 8.0);
+        let warped = DomainWarp::new(
+            Box::new(Fbm::new(Box::new(Perlin::new(11)), fractal_params())),
+            Box::new(Simplex::new(12)),
+            Box::new(Simplex::new(13)),
+            0.5);
+        assert_continuous_along_x(&warped, 1.3, -8.0, 8.0);
+    }
+
+    #[test]
+    fn combinators() {
+        let perlin = Perlin::new(5);
+        let expected = perlin.sample(0.3, 0.6) * 2.0 + 1.0;
+        let scaled = ScaleBias::new(Box::new(Perlin::new(5)), 2.0, 1.0);
+        assert_f32_eq!(scaled.sample(0.3, 0.6), expected, test_constants::EPS_MEDIUM);
+
+        let sum = Sum::new(vec![Box::new(Perlin::new(5)), Box::new(Perlin::new(5))]);
+        assert_f32_eq!(sum.sample(0.3, 0.6), 2.0 * perlin.sample(0.3, 0.6), test_constants::EPS_MEDIUM);
+    }
+}
@@ -60,7 +60,7 @@ pub fn load_collada_animated_model(loader: &mut ModelLoader, path: &str, texture
     let animation = animations_from_collada(&collada_doc);
 
     let animated_raw_model = raw_model_from_obj_set(&collada_doc, loader, &correction_transform);
-    let texture_id = loader.load_texture_internal(texture_path, TextureParams::default(), ExtraInfo::default());
+    let texture_id = loader.load_texture_internal(texture_path, TextureParams::srgb_texture(), ExtraInfo::default());
     
     let root_joint = joints_from_collada(&collada_doc, &correction_transform);
     let joint_cnt = root_joint.children.len() + 1;
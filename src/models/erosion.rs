@@ -0,0 +1,574 @@
+use crate::utils::gen_murmur3_f32;
+use super::terrain_generator::TerrainGenerator;
+use texture_lib::texture_loader::{
+    Texture2DRGBA,
+    RGBA,
+};
+use std::thread;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ErosionParams {
+    pub seed: u32,
+    // hydraulic erosion, every droplet runs downhill picking up and dropping sediment
+    pub droplets: usize,
+    pub droplet_lifetime: usize,
+    // how much a droplet keeps its direction instead of following the gradient
+    pub inertia: f32,
+    pub sediment_capacity: f32,
+    pub min_sediment_capacity: f32,
+    pub erode_speed: f32,
+    pub deposit_speed: f32,
+    pub evaporate_speed: f32,
+    pub gravity: f32,
+    // in grid cells
+    pub erosion_radius: usize,
+    // thermal erosion, material slides down slopes steeper than the talus slope
+    pub thermal_iterations: usize,
+    // height difference over horizontal distance
+    pub talus_slope: f32,
+    pub thermal_rate: f32,
+    // the erosion fades out over this many cells towards the grid border so neighbouring terrain cells stay seamless
+    pub edge_falloff: usize,
+}
+
+impl Default for ErosionParams {
+    fn default() -> Self {
+        ErosionParams {
+            seed: 1234,
+            droplets: 20000,
+            droplet_lifetime: 30,
+            inertia: 0.05,
+            sediment_capacity: 4.0,
+            min_sediment_capacity: 0.01,
+            erode_speed: 0.3,
+            deposit_speed: 0.3,
+            evaporate_speed: 0.01,
+            gravity: 4.0,
+            erosion_radius: 3,
+            thermal_iterations: 20,
+            talus_slope: 0.9,
+            thermal_rate: 0.5,
+            edge_falloff: 8,
+        }
+    }
+}
+
+// square grid of heights indexed by [z * size + x]
+#[derive(Clone, Debug)]
+pub struct HeightGrid {
+    pub size: usize,
+    // world space distance between two neighbouring heights
+    pub cell_size: f32,
+    pub heights: Vec<f32>,
+}
+
+impl HeightGrid {
+    pub fn new(size: usize, cell_size: f32) -> Self {
+        HeightGrid {
+            size,
+            cell_size,
+            heights: vec![0.0; size * size],
+        }
+    }
+
+    pub fn get(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.size + x]
+    }
+
+    pub fn set(&mut self, x: usize, z: usize, height: f32) {
+        self.heights[z * self.size + x] = height;
+    }
+
+    // bilinearly interpolated height and its gradient (per cell) at a position inside the grid
+    fn height_and_gradient(heights: &[f32], size: usize, x: f32, z: f32) -> (f32, f32, f32) {
+        let cell_x = x as usize;
+        let cell_z = z as usize;
+        let u = x - cell_x as f32;
+        let v = z - cell_z as f32;
+        let idx = cell_z * size + cell_x;
+        let h00 = heights[idx];
+        let h10 = heights[idx + 1];
+        let h01 = heights[idx + size];
+        let h11 = heights[idx + size + 1];
+        let gradient_x = (h10 - h00) * (1.0 - v) + (h11 - h01) * v;
+        let gradient_z = (h01 - h00) * (1.0 - u) + (h11 - h10) * u;
+        let height = h00 * (1.0 - u) * (1.0 - v) + h10 * u * (1.0 - v) + h01 * (1.0 - u) * v + h11 * u * v;
+        (height, gradient_x, gradient_z)
+    }
+}
+
+// side outputs of the erosion, all values are in [0, 1] and indexed like the height grid
+pub struct ErosionMaps {
+    pub size: usize,
+    // how much water went through a cell
+    pub wetness: Vec<f32>,
+    // 0 is flat, 1 is vertical
+    pub slope: Vec<f32>,
+}
+
+impl ErosionMaps {
+    const ROCK_SLOPE_START: f32 = 0.15;
+    const ROCK_SLOPE_END: f32 = 0.45;
+
+    // drop a border of cells on each side
+    pub fn crop(&self, border: usize) -> ErosionMaps {
+        let size = self.size - 2 * border;
+        let crop_map = |map: &Vec<f32>| {
+            let mut cropped = Vec::with_capacity(size * size);
+            for z in border..border + size {
+                cropped.extend_from_slice(&map[z * self.size + border..z * self.size + border + size]);
+            }
+            cropped
+        };
+        ErosionMaps {
+            size,
+            wetness: crop_map(&self.wetness),
+            slope: crop_map(&self.slope),
+        }
+    }
+
+    // blend map for the terrain texture pack: mud (r channel) where the water flows and the path texture (b channel) on steep slopes
+    pub fn blend_map(&self) -> Texture2DRGBA {
+        let data = self.wetness.iter().zip(self.slope.iter()).map(|(wetness, slope)| {
+            let wet = *wetness;
+            let rock = smoothstep(ErosionMaps::ROCK_SLOPE_START, ErosionMaps::ROCK_SLOPE_END, *slope);
+            // the background texture gets whatever is left so the channels must not sum to more than 1
+            let total = wet + rock;
+            let (wet, rock) = if total > 1.0 { (wet / total, rock / total) } else { (wet, rock) };
+            RGBA {
+                r: (wet * 255.0) as u8,
+                g: 0,
+                b: (rock * 255.0) as u8,
+                a: 255,
+            }
+        }).collect();
+        Texture2DRGBA {
+            width: self.size,
+            height: self.size,
+            data,
+        }
+    }
+}
+
+// seeded and deterministic, the droplets are split into fixed batches which run on their own threads
+// so the result doesn't depend on scheduling
+pub struct Erosion {
+    params: ErosionParams,
+    // rings of vertices at the border which keep their original heights
+    fixed_border: usize,
+}
+
+impl Erosion {
+    const BATCHES: usize = 4;
+    // the batches see each other's changes only after each round
+    const ROUNDS: usize = 8;
+    const THREADS: usize = 4;
+    // water that went through a cell per droplet per cell of the grid which counts as fully wet
+    // a fixed scale instead of the wettest cell so the wetness of neighbouring terrain cells matches up
+    const FULL_FLOW: f32 = 100.0;
+
+    pub fn new(params: ErosionParams) -> Self {
+        Erosion {
+            params,
+            fixed_border: 1,
+        }
+    }
+
+    // a grid with padding around the vertices it shares with its neighbours has to keep those unchanged too
+    pub fn with_fixed_border(mut self, fixed_border: usize) -> Self {
+        self.fixed_border = fixed_border.max(1);
+        self
+    }
+
+    pub fn erode(&self, grid: &mut HeightGrid) -> ErosionMaps {
+        let original = grid.heights.clone();
+        let flow = self.hydraulic_erosion(grid);
+        self.thermal_erosion(grid);
+        self.fade_out_edges(grid, &original);
+        ErosionMaps {
+            size: grid.size,
+            wetness: self.normalize_flow(flow),
+            slope: Erosion::slope_map(grid),
+        }
+    }
+
+    // returns how much water passed through each cell
+    fn hydraulic_erosion(&self, grid: &mut HeightGrid) -> Vec<f32> {
+        let brush = self.erosion_brush();
+        let mut flow = vec![0.0f32; grid.heights.len()];
+        let droplets_per_batch = self.params.droplets / (Erosion::ROUNDS * Erosion::BATCHES);
+        for round in 0..Erosion::ROUNDS {
+            let snapshot = &*grid;
+            let results: Vec<(Vec<f32>, Vec<f32>)> = thread::scope(|scope| {
+                let handles: Vec<_> = (0..Erosion::BATCHES).map(|batch| {
+                    let brush = &brush;
+                    let batch_id = (round * Erosion::BATCHES + batch) as u32;
+                    scope.spawn(move || self.simulate_droplets(snapshot, batch_id, droplets_per_batch, brush))
+                }).collect();
+                handles.into_iter().map(|handle| handle.join().expect("Erosion thread panicked")).collect()
+            });
+            // merge in batch order to stay deterministic
+            for (delta, batch_flow) in results {
+                for (height, change) in grid.heights.iter_mut().zip(delta.iter()) {
+                    *height += change;
+                }
+                for (total, batch_flow) in flow.iter_mut().zip(batch_flow.iter()) {
+                    *total += batch_flow;
+                }
+            }
+        }
+        flow
+    }
+
+    // runs the droplets against a private copy of the heights and returns the height change and the water flow
+    fn simulate_droplets(&self, grid: &HeightGrid, batch_id: u32, droplet_count: usize, brush: &[(isize, isize, f32)]) -> (Vec<f32>, Vec<f32>) {
+        let params = &self.params;
+        let size = grid.size;
+        let max_pos = (size - 1) as f32;
+        let mut heights = grid.heights.clone();
+        let mut flow = vec![0.0f32; heights.len()];
+
+        for droplet in 0..droplet_count as u32 {
+            let mut pos_x = (gen_murmur3_f32(batch_id, 2 * droplet, params.seed) * max_pos).min(max_pos - 0.001);
+            let mut pos_z = (gen_murmur3_f32(batch_id, 2 * droplet + 1, params.seed) * max_pos).min(max_pos - 0.001);
+            let mut dir_x = 0.0f32;
+            let mut dir_z = 0.0f32;
+            let mut speed = 1.0f32;
+            let mut water = 1.0f32;
+            let mut sediment = 0.0f32;
+
+            for _ in 0..params.droplet_lifetime {
+                let cell_x = pos_x as usize;
+                let cell_z = pos_z as usize;
+                let cell_idx = cell_z * size + cell_x;
+                let u = pos_x - cell_x as f32;
+                let v = pos_z - cell_z as f32;
+                let (height, gradient_x, gradient_z) = HeightGrid::height_and_gradient(&heights, size, pos_x, pos_z);
+
+                dir_x = dir_x * params.inertia - gradient_x * (1.0 - params.inertia);
+                dir_z = dir_z * params.inertia - gradient_z * (1.0 - params.inertia);
+                let len = (dir_x * dir_x + dir_z * dir_z).sqrt();
+                // stuck in a perfectly flat spot
+                if len < 1e-6 {
+                    break;
+                }
+                dir_x /= len;
+                dir_z /= len;
+                pos_x += dir_x;
+                pos_z += dir_z;
+                if pos_x < 0.0 || pos_x >= max_pos || pos_z < 0.0 || pos_z >= max_pos {
+                    break;
+                }
+
+                let (new_height, _, _) = HeightGrid::height_and_gradient(&heights, size, pos_x, pos_z);
+                let delta_height = new_height - height;
+                let capacity = (-delta_height * speed * water * params.sediment_capacity).max(params.min_sediment_capacity);
+
+                if sediment > capacity || delta_height > 0.0 {
+                    // going uphill fills the pit behind us, otherwise drop what we can't carry
+                    let amount = if delta_height > 0.0 {
+                        delta_height.min(sediment)
+                    } else {
+                        (sediment - capacity) * params.deposit_speed
+                    };
+                    sediment -= amount;
+                    heights[cell_idx] += amount * (1.0 - u) * (1.0 - v);
+                    heights[cell_idx + 1] += amount * u * (1.0 - v);
+                    heights[cell_idx + size] += amount * (1.0 - u) * v;
+                    heights[cell_idx + size + 1] += amount * u * v;
+                } else {
+                    // never dig deeper than the height difference or we create holes
+                    let amount = ((capacity - sediment) * params.erode_speed).min(-delta_height);
+                    for (offset_x, offset_z, weight) in brush.iter() {
+                        let x = cell_x as isize + offset_x;
+                        let z = cell_z as isize + offset_z;
+                        if x < 0 || z < 0 || x >= size as isize || z >= size as isize {
+                            continue;
+                        }
+                        let eroded = amount * weight;
+                        heights[z as usize * size + x as usize] -= eroded;
+                        sediment += eroded;
+                    }
+                }
+
+                speed = (speed * speed - delta_height * params.gravity).max(0.0).sqrt();
+                water *= 1.0 - params.evaporate_speed;
+                flow[cell_idx] += water;
+            }
+        }
+
+        for (height, original) in heights.iter_mut().zip(grid.heights.iter()) {
+            *height -= original;
+        }
+        (heights, flow)
+    }
+
+    // cells around the droplet with weights falling off linearly with the distance
+    fn erosion_brush(&self) -> Vec<(isize, isize, f32)> {
+        let radius = self.params.erosion_radius as isize;
+        let mut brush = Vec::new();
+        for offset_z in -radius..=radius {
+            for offset_x in -radius..=radius {
+                let dist = ((offset_x * offset_x + offset_z * offset_z) as f32).sqrt();
+                if dist < radius as f32 {
+                    brush.push((offset_x, offset_z, radius as f32 - dist));
+                }
+            }
+        }
+        let total: f32 = brush.iter().map(|(_, _, weight)| weight).sum();
+        for (_, _, weight) in brush.iter_mut() {
+            *weight /= total;
+        }
+        brush
+    }
+
+    // every cell first computes what it sheds to its 4 neighbours and then gathers what its neighbours shed to it
+    // so the rows can be split over threads without write conflicts
+    fn thermal_erosion(&self, grid: &mut HeightGrid) {
+        const NEIGHBOURS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        // the neighbour in direction i receives from us through its opposite direction
+        const OPPOSITE: [usize; 4] = [1, 0, 3, 2];
+        let size = grid.size;
+        let talus = self.params.talus_slope * grid.cell_size;
+        let rate = self.params.thermal_rate;
+        let mut outflow = vec![[0.0f32; 4]; grid.heights.len()];
+        let mut next_heights = vec![0.0f32; grid.heights.len()];
+
+        let neighbour = |x: usize, z: usize, dir: usize| {
+            let (offset_x, offset_z) = NEIGHBOURS[dir];
+            let nx = x as isize + offset_x;
+            let nz = z as isize + offset_z;
+            if nx < 0 || nz < 0 || nx >= size as isize || nz >= size as isize {
+                None
+            } else {
+                Some(nz as usize * size + nx as usize)
+            }
+        };
+
+        for _ in 0..self.params.thermal_iterations {
+            let heights = &grid.heights;
+            Erosion::par_rows(&mut outflow, size, |first_row, rows| {
+                for (i, out) in rows.iter_mut().enumerate() {
+                    let (x, z) = (i % size, first_row + i / size);
+                    let height = heights[z * size + x];
+                    let mut excess = [0.0f32; 4];
+                    for (dir, excess) in excess.iter_mut().enumerate() {
+                        if let Some(n) = neighbour(x, z, dir) {
+                            *excess = (height - heights[n] - talus).max(0.0);
+                        }
+                    }
+                    let total: f32 = excess.iter().sum();
+                    let max_excess = excess.iter().cloned().fold(0.0f32, f32::max);
+                    // move half of the steepest excess so we don't overshoot and make the neighbour the higher one
+                    let moved = rate * max_excess * 0.5;
+                    for dir in 0..4 {
+                        out[dir] = if total > 0.0 { moved * excess[dir] / total } else { 0.0 };
+                    }
+                }
+            });
+
+            let outflow = &outflow;
+            Erosion::par_rows(&mut next_heights, size, |first_row, rows| {
+                for (i, next_height) in rows.iter_mut().enumerate() {
+                    let (x, z) = (i % size, first_row + i / size);
+                    let idx = z * size + x;
+                    let mut height = heights[idx] - outflow[idx].iter().sum::<f32>();
+                    for (dir, opposite) in OPPOSITE.iter().enumerate() {
+                        if let Some(n) = neighbour(x, z, dir) {
+                            height += outflow[n][*opposite];
+                        }
+                    }
+                    *next_height = height;
+                }
+            });
+            std::mem::swap(&mut grid.heights, &mut next_heights);
+        }
+    }
+
+    // splits the rows of a grid shaped buffer over the worker threads, f gets the index of the first row of its chunk
+    fn par_rows<T: Send, F: Fn(usize, &mut [T]) + Sync>(data: &mut [T], size: usize, f: F) {
+        let rows_per_thread = size.div_ceil(Erosion::THREADS);
+        thread::scope(|scope| {
+            for (chunk_idx, chunk) in data.chunks_mut(rows_per_thread * size).enumerate() {
+                let f = &f;
+                scope.spawn(move || f(chunk_idx * rows_per_thread, chunk));
+            }
+        });
+    }
+
+    fn fade_out_edges(&self, grid: &mut HeightGrid, original: &[f32]) {
+        let falloff = self.params.edge_falloff as f32;
+        if falloff == 0.0 {
+            return;
+        }
+        let size = grid.size;
+        for z in 0..size {
+            for x in 0..size {
+                // 0 on the fixed border so the vertices there come out exactly as they went in
+                let edge_dist = x.min(z).min(size - 1 - x).min(size - 1 - z) as f32 + 1.0 - self.fixed_border as f32;
+                let fade = smoothstep(0.0, falloff, edge_dist);
+                let idx = z * size + x;
+                grid.heights[idx] = original[idx] + (grid.heights[idx] - original[idx]) * fade;
+            }
+        }
+    }
+
+    // log scale since the rivers collect orders of magnitude more water than the slopes
+    fn normalize_flow(&self, flow: Vec<f32>) -> Vec<f32> {
+        let droplets_per_cell = self.params.droplets.max(1) as f32 / flow.len() as f32;
+        let norm = Erosion::FULL_FLOW.ln_1p();
+        flow.into_iter().map(|flow| ((flow / droplets_per_cell).ln_1p() / norm).min(1.0)).collect()
+    }
+
+    fn slope_map(grid: &HeightGrid) -> Vec<f32> {
+        let size = grid.size;
+        let mut slope = vec![0.0f32; size * size];
+        for z in 0..size {
+            for x in 0..size {
+                let (left, right) = (x.saturating_sub(1), (x + 1).min(size - 1));
+                let (up, down) = (z.saturating_sub(1), (z + 1).min(size - 1));
+                let gradient_x = (grid.get(right, z) - grid.get(left, z)) / ((right - left) as f32 * grid.cell_size);
+                let gradient_z = (grid.get(x, down) - grid.get(x, up)) / ((down - up) as f32 * grid.cell_size);
+                // 1 - the y component of the normal
+                slope[z * size + x] = 1.0 - 1.0 / (1.0 + gradient_x * gradient_x + gradient_z * gradient_z).sqrt();
+            }
+        }
+        slope
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// eroded copy of the heights of another terrain generator
+// the grid has a 1 vertex border like the one the mesh generation samples for the normals
+// the border and the edge vertices shared with the neighbouring cells stay uneroded so the cells meet without seams
+pub struct ErodedTerrain {
+    vertex_count: usize,
+    grid: HeightGrid,
+    pub maps: ErosionMaps,
+}
+
+impl ErodedTerrain {
+    pub fn new(generator: &dyn TerrainGenerator, params: &ErosionParams, cell_size: f32) -> Self {
+        let vertex_count = generator.width();
+        let mut grid = HeightGrid::new(vertex_count + 2, cell_size);
+        for z in 0..grid.size {
+            for x in 0..grid.size {
+                grid.set(x, z, generator.get_height(x as isize - 1, z as isize - 1));
+            }
+        }
+        let maps = Erosion::new(*params).with_fixed_border(2).erode(&mut grid).crop(1);
+        ErodedTerrain {
+            vertex_count,
+            grid,
+            maps,
+        }
+    }
+}
+
+impl TerrainGenerator for ErodedTerrain {
+    fn height(&self) -> usize {
+        self.vertex_count
+    }
+
+    fn width(&self) -> usize {
+        self.vertex_count
+    }
+
+    fn get_height(&self, x: isize, z: isize) -> f32 {
+        let max = self.grid.size as isize - 1;
+        let x = (x + 1).clamp(0, max) as usize;
+        let z = (z + 1).clamp(0, max) as usize;
+        self.grid.get(x, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::terrain_generator::{
+        NoiseTerrainGenerator,
+        NoiseTerrainParams,
+    };
+
+    fn cone_grid(size: usize) -> HeightGrid {
+        let mut grid = HeightGrid::new(size, 1.0);
+        let center = (size - 1) as f32 / 2.0;
+        for z in 0..size {
+            for x in 0..size {
+                let dist = ((x as f32 - center).powi(2) + (z as f32 - center).powi(2)).sqrt();
+                // a bit of hashed noise so the droplets don't all follow the same lines
+                let bumps = gen_murmur3_f32(x as u32, z as u32, 7);
+                grid.set(x, z, (center - dist).max(0.0) * 2.0 + bumps);
+            }
+        }
+        grid
+    }
+
+    fn test_params() -> ErosionParams {
+        ErosionParams {
+            droplets: 4000,
+            thermal_iterations: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_erosion_deterministic() {
+        let mut a = cone_grid(48);
+        let mut b = cone_grid(48);
+        Erosion::new(test_params()).erode(&mut a);
+        Erosion::new(test_params()).erode(&mut b);
+        assert_eq!(a.heights, b.heights);
+
+        let mut c = cone_grid(48);
+        Erosion::new(ErosionParams { seed: 4321, ..test_params() }).erode(&mut c);
+        assert_ne!(a.heights, c.heights);
+    }
+
+    #[test]
+    fn test_adjacent_cells_share_their_edge() {
+        let generator = NoiseTerrainGenerator::from_params(&NoiseTerrainParams { vertex_count: 48, ..Default::default() });
+        let (left_cell, right_cell) = (generator.for_cell(0, 0), generator.for_cell(1, 0));
+        // the terrain streamer seeds every cell differently
+        let left = ErodedTerrain::new(&left_cell, &ErosionParams { seed: 1, ..test_params() }, 1.0);
+        let right = ErodedTerrain::new(&right_cell, &ErosionParams { seed: 2, ..test_params() }, 1.0);
+        let last = left.width() as isize - 1;
+        for z in 0..left.height() as isize {
+            assert_eq!(left.get_height(last, z), right.get_height(0, z), "edge at {}", z);
+        }
+        let changed = |eroded: &ErodedTerrain, cell: &NoiseTerrainGenerator| (1..last).any(|i| eroded.get_height(i, i) != cell.get_height(i, i));
+        assert!(changed(&left, &left_cell) && changed(&right, &right_cell));
+    }
+
+    #[test]
+    fn test_thermal_erosion_conserves_material() {
+        let mut grid = cone_grid(32);
+        let total_before: f32 = grid.heights.iter().sum();
+        let max_slope_before = Erosion::slope_map(&grid).iter().cloned().fold(0.0f32, f32::max);
+        let params = ErosionParams { talus_slope: 0.5, thermal_iterations: 50, ..Default::default() };
+        Erosion::new(params).thermal_erosion(&mut grid);
+        let total_after: f32 = grid.heights.iter().sum();
+        assert!((total_before - total_after).abs() < 1e-2 * total_before, "{} vs {}", total_before, total_after);
+        let max_slope_after = Erosion::slope_map(&grid).iter().cloned().fold(0.0f32, f32::max);
+        assert!(max_slope_after < max_slope_before, "{} vs {}", max_slope_after, max_slope_before);
+    }
+
+    #[test]
+    fn test_maps_in_unit_range() {
+        let mut grid = cone_grid(48);
+        let maps = Erosion::new(test_params()).erode(&mut grid);
+        assert!(maps.wetness.iter().chain(maps.slope.iter()).all(|value| (0.0..=1.0).contains(value)));
+        assert!(maps.wetness.iter().any(|wetness| *wetness > 0.0));
+        let blend_map = maps.blend_map();
+        assert_eq!(blend_map.data.len(), 48 * 48);
+        assert!(blend_map.data.iter().all(|color| color.r as u32 + color.g as u32 + color.b as u32 <= 255));
+
+        let cropped = maps.crop(1);
+        assert_eq!(cropped.size, 46);
+        assert_eq!(cropped.slope[0], maps.slope[48 + 1]);
+    }
+}
@@ -0,0 +1,388 @@
+use std::fs;
+use std::io::{
+    Error,
+    ErrorKind,
+};
+use std::path::Path;
+
+// the metallic/roughness part of a material exported by our gltf pipeline, the meshes still come from the obj files
+// the base color and the normal map stay with the model like for every other model, this is just what goes into the PbrMaterial
+// missing fields get the defaults of the gltf spec which makes an untextured material fully metallic and fully rough
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    // paths of the images, relative to the gltf file
+    pub metallic_roughness_map: Option<String>,
+    pub occlusion_map: Option<String>,
+    pub emissive_map: Option<String>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive_strength: f32,
+}
+
+impl Default for GltfMaterial {
+    fn default() -> GltfMaterial {
+        GltfMaterial {
+            name: None,
+            metallic_roughness_map: None,
+            occlusion_map: None,
+            emissive_map: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            emissive_strength: 0.0,
+        }
+    }
+}
+
+impl GltfMaterial {
+    // the first material of the file and the image paths made relative to the working dir like the rest of our resources
+    pub fn load(file_name: &str) -> std::io::Result<GltfMaterial> {
+        let json = fs::read_to_string(file_name)?;
+        let mut material = GltfMaterial::parse(&json, 0).map_err(|err| Error::new(ErrorKind::InvalidData, format!("{}: {}", file_name, err)))?;
+        let dir = Path::new(file_name).parent().unwrap_or(Path::new(""));
+        for map in [&mut material.metallic_roughness_map, &mut material.occlusion_map, &mut material.emissive_map].iter_mut() {
+            if let Some(uri) = map.take() {
+                **map = Some(dir.join(uri).to_string_lossy().replace('\\', "/"));
+            }
+        }
+        Ok(material)
+    }
+
+    pub fn parse(json: &str, material_idx: usize) -> Result<GltfMaterial, String> {
+        let root = JsonParser::new(json).parse_document()?;
+        let material = root.get("materials").and_then(|materials| materials.at(material_idx))
+            .ok_or_else(|| format!("No material {}", material_idx))?;
+
+        let mut result = GltfMaterial {
+            name: material.get("name").and_then(Json::as_str).map(String::from),
+            ..GltfMaterial::default()
+        };
+        if let Some(pbr) = material.get("pbrMetallicRoughness") {
+            if let Some(metallic) = pbr.get("metallicFactor") {
+                result.metallic = factor(metallic, "metallicFactor")?;
+            }
+            if let Some(roughness) = pbr.get("roughnessFactor") {
+                result.roughness = factor(roughness, "roughnessFactor")?;
+            }
+            result.metallic_roughness_map = image_uri(&root, pbr.get("metallicRoughnessTexture"))?;
+        }
+        result.occlusion_map = image_uri(&root, material.get("occlusionTexture"))?;
+        result.emissive_map = image_uri(&root, material.get("emissiveTexture"))?;
+
+        // our emission is white scaled by one strength, so the brightest channel of the factor it is
+        let emissive_factor = match material.get("emissiveFactor") {
+            Some(Json::Array(channels)) if channels.len() == 3 => {
+                let mut brightest = 0.0f32;
+                for channel in channels.iter() {
+                    brightest = brightest.max(factor(channel, "emissiveFactor")?);
+                }
+                brightest
+            },
+            Some(_) => return Err("emissiveFactor must have three channels".to_string()),
+            None => 0.0,
+        };
+        // the strength extension lets the emission go past 1
+        let emissive_boost = material.get("extensions")
+            .and_then(|extensions| extensions.get("KHR_materials_emissive_strength"))
+            .and_then(|extension| extension.get("emissiveStrength"))
+            .and_then(Json::as_f32)
+            .unwrap_or(1.0);
+        result.emissive_strength = emissive_factor * emissive_boost;
+
+        Ok(result)
+    }
+}
+
+// the factors of a gltf material are all in [0, 1]
+fn factor(value: &Json, name: &str) -> Result<f32, String> {
+    match value.as_f32() {
+        Some(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(format!("{} must be a number between 0 and 1", name)),
+    }
+}
+
+// texture info -> texture -> image, we only read external images
+fn image_uri(root: &Json, texture_info: Option<&Json>) -> Result<Option<String>, String> {
+    let texture_info = match texture_info {
+        Some(texture_info) => texture_info,
+        None => return Ok(None),
+    };
+    if texture_info.get("texCoord").and_then(Json::as_f32).unwrap_or(0.0) != 0.0 {
+        return Err("Only the first set of texture coordinates is supported".to_string());
+    }
+    let texture_idx = texture_info.get("index").and_then(Json::as_index).ok_or("Texture info without an index")?;
+    let texture = root.get("textures").and_then(|textures| textures.at(texture_idx)).ok_or_else(|| format!("No texture {}", texture_idx))?;
+    let image_idx = texture.get("source").and_then(Json::as_index).ok_or_else(|| format!("Texture {} has no image", texture_idx))?;
+    let image = root.get("images").and_then(|images| images.at(image_idx)).ok_or_else(|| format!("No image {}", image_idx))?;
+    let uri = image.get("uri").and_then(Json::as_str).ok_or_else(|| format!("Image {} is not an external file", image_idx))?;
+    if uri.starts_with("data:") {
+        return Err(format!("Image {} is embedded, only external images are supported", image_idx));
+    }
+    Ok(Some(uri.to_string()))
+}
+
+// just enough json for the gltf files, objects keep the order of their keys
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn at(&self, idx: usize) -> Option<&Json> {
+        match self {
+            Json::Array(values) => values.get(idx),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            Json::Number(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    fn as_index(&self) -> Option<usize> {
+        match self {
+            Json::Number(value) if *value >= 0.0 && value.fract() == 0.0 => Some(*value as usize),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(json: &'a str) -> Self {
+        JsonParser {
+            chars: json.chars().peekable(),
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<Json, String> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        match self.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("Unexpected '{}' after the document", c)),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek().cloned() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('n') => self.parse_literal("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) => Err(format!("Unexpected '{}'", c)),
+            None => Err("Unexpected end of the document".to_string()),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.chars.next();
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err("Expected ',' or '}' in an object".to_string()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.chars.next();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err("Expected ',' or ']' in an array".to_string()),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escaped = match self.chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let code: String = self.chars.by_ref().take(4).collect();
+                            u32::from_str_radix(&code, 16).ok().and_then(std::char::from_u32).ok_or_else(|| format!("Bad escape \\u{}", code))?
+                        },
+                        _ => return Err("Bad escape in a string".to_string()),
+                    };
+                    value.push(escaped);
+                },
+                Some(c) => value.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let mut number = String::new();
+        while let Some(c) = self.chars.peek() {
+            if c.is_ascii_digit() || *c == '-' || *c == '+' || *c == '.' || *c == 'e' || *c == 'E' {
+                number.push(*c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        number.parse().map(Json::Number).map_err(|_| format!("Bad number {}", number))
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        for expected in literal.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("Expected {}", literal));
+            }
+        }
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected '{}' but got '{}'", expected, c)),
+            None => Err(format!("Expected '{}' but the document ended", expected)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BARREL: &str = r#"{
+        "asset": { "version": "2.0" },
+        "materials": [
+            {
+                "name": "barrel",
+                "pbrMetallicRoughness": {
+                    "metallicRoughnessTexture": { "index": 0 },
+                    "metallicFactor": 0.5,
+                    "roughnessFactor": 0.25
+                },
+                "occlusionTexture": { "index": 0, "strength": 1.0 },
+                "emissiveTexture": { "index": 1 },
+                "emissiveFactor": [0.2, 0.5, 0.1],
+                "extensions": { "KHR_materials_emissive_strength": { "emissiveStrength": 4.0 } }
+            },
+            {}
+        ],
+        "textures": [ { "source": 1 }, { "source": 0 } ],
+        "images": [ { "uri": "glow.png" }, { "uri": "orm.png" } ]
+    }"#;
+
+    #[test]
+    fn test_material_defaults() {
+        let material = GltfMaterial::parse(BARREL, 1).unwrap();
+        assert_eq!(material, GltfMaterial::default());
+        assert_eq!(material.metallic, 1.0);
+        assert_eq!(material.roughness, 1.0);
+        assert_eq!(material.emissive_strength, 0.0);
+        // no emission without a factor even with a map
+        let no_factor = BARREL.replace(r#""emissiveFactor": [0.2, 0.5, 0.1],"#, "");
+        assert_eq!(GltfMaterial::parse(&no_factor, 0).unwrap().emissive_strength, 0.0);
+    }
+
+    #[test]
+    fn test_material_factors_and_maps() {
+        let material = GltfMaterial::parse(BARREL, 0).unwrap();
+        assert_eq!(material.name.as_deref(), Some("barrel"));
+        assert_eq!(material.metallic, 0.5);
+        assert_eq!(material.roughness, 0.25);
+        assert_eq!(material.emissive_strength, 0.5 * 4.0);
+        // the textures point at the images, the orm map is shared like in the usual exports
+        assert_eq!(material.metallic_roughness_map.as_deref(), Some("orm.png"));
+        assert_eq!(material.occlusion_map.as_deref(), Some("orm.png"));
+        assert_eq!(material.emissive_map.as_deref(), Some("glow.png"));
+    }
+
+    #[test]
+    fn test_bad_materials_are_rejected() {
+        assert!(GltfMaterial::parse(BARREL, 2).is_err());
+        assert!(GltfMaterial::parse(&BARREL.replace("0.25", "1.5"), 0).is_err());
+        assert!(GltfMaterial::parse(&BARREL.replace(r#"{ "source": 0 }"#, "{}"), 0).is_err());
+        assert!(GltfMaterial::parse(&BARREL.replace("orm.png", "data:image/png;base64,AAAA"), 0).is_err());
+        assert!(GltfMaterial::parse(&BARREL.replace("[0.2, 0.5, 0.1]", "[0.2, 0.5]"), 0).is_err());
+        assert!(GltfMaterial::parse("{ \"materials\": [ {} ] } }", 0).is_err());
+    }
+
+    #[test]
+    fn test_barrel_material_file() {
+        let material = GltfMaterial::load("res/materials/barrel.gltf").unwrap();
+        assert_eq!(material.metallic_roughness_map.as_deref(), Some("res/materials/../textures/pbr/barrelOrm.png"));
+        assert_eq!(material.occlusion_map, material.metallic_roughness_map);
+        assert!(Path::new(material.occlusion_map.as_ref().unwrap()).exists());
+    }
+}
\ No newline at end of file
@@ -1,10 +1,14 @@
 use crate::gl;
 use texture_lib::texture_loader::{
     load_rgba_2d_texture,
+    load_hdr_2d_texture,
     Texture2DRGBA,
+    Texture2DRGBF,
 };
 use crate::math::utils::f32_min;
+use crate::math::ModelBounds;
 use super::texture_id::TextureId;
+use super::terrain_lod::TerrainChunk;
 
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
@@ -16,6 +20,9 @@ pub struct ModelLoader {
     vao_list: Vec<u32>,
     vbo_list: Vec<u32>,
     tex_list: Vec<u32>,
+    // the vbos that were created while a vao was bound so that they can be freed together with the vao
+    vao_vbos: HashMap<u32, Vec<u32>>,
+    bound_vao: u32,
     texture_loading_rcv: mpsc::Receiver<TextureResult>,
     loaded_texture_snd: mpsc::Sender<TextureResult>,
     pub texture_token_map: HashMap<u32, u32>,
@@ -28,7 +35,36 @@ pub struct ModelLoader {
 }
 
 // the fields are Texture, temp_tex_id, params, texture_order (used for cubemaps)
-type TextureResult = (Texture2DRGBA, u32, TextureParams, ExtraInfo);
+type TextureResult = (TextureData, u32, TextureParams, ExtraInfo);
+
+// png files are loaded as 8 bit per channel while radiance .hdr files are loaded as floats
+pub enum TextureData {
+    Ldr(Texture2DRGBA),
+    Hdr(Texture2DRGBF),
+}
+
+impl TextureData {
+    fn size(&self) -> (usize, usize) {
+        match self {
+            TextureData::Ldr(texture) => (texture.width, texture.height),
+            TextureData::Hdr(texture) => (texture.width, texture.height),
+        }
+    }
+}
+
+// color textures (albedo) are authored in srgb and need to be converted to linear when sampled so that lighting math is done in linear space
+// data textures (normal maps, extra info, dudv, blend maps) already hold linear values
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::Linear
+    }
+}
 
 #[derive(Default)]
 pub struct ExtraInfo {
@@ -37,12 +73,13 @@ pub struct ExtraInfo {
     cubemap_token: u32,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub struct TextureParams {
     reverse_texture_data: bool,
     use_mipmap: bool,
     mipmap_lod: f32,
     use_anisotropic_filtering: bool,
+    color_space: ColorSpace,
 }
 
 impl TextureParams {
@@ -63,6 +100,27 @@ impl TextureParams {
             ..Default::default()
         }
     }
+
+    pub fn srgb_texture() -> TextureParams {
+        TextureParams {
+            color_space: ColorSpace::Srgb,
+            ..Default::default()
+        }
+    }
+
+    // mark any of the above as holding srgb color data
+    pub fn in_srgb(mut self) -> TextureParams {
+        self.color_space = ColorSpace::Srgb;
+        self
+    }
+
+    fn internal_format(&self, texture_data: &TextureData) -> gl::types::GLenum {
+        match (texture_data, self.color_space) {
+            (TextureData::Hdr(_), _) => gl::RGB16F,
+            (TextureData::Ldr(_), ColorSpace::Srgb) => gl::SRGB8_ALPHA8,
+            (TextureData::Ldr(_), ColorSpace::Linear) => gl::RGBA8,
+        }
+    }
 }
 
 impl Default for ModelLoader {
@@ -73,6 +131,8 @@ impl Default for ModelLoader {
             vao_list: Vec::new(),
             vbo_list: Vec::new(),
             tex_list: Vec::new(),
+            vao_vbos: HashMap::new(),
+            bound_vao: 0,
             texture_loading_rcv: receiver,
             loaded_texture_snd: transmitter,
             texture_token_map: HashMap::new(),
@@ -189,12 +249,21 @@ impl ModelLoader {
     }
 
     pub fn load_cube_map(&mut self, cube_map_folder: &str) -> TextureId {
+        self.load_cube_map_faces(cube_map_folder, "png", TextureParams::srgb_texture())
+    }
+
+    // expects the faces as radiance files 1.hdr to 6.hdr. these are stored as half floats so the sky can be brighter than 1.0
+    pub fn load_hdr_cube_map(&mut self, cube_map_folder: &str) -> TextureId {
+        self.load_cube_map_faces(cube_map_folder, "hdr", TextureParams::default())
+    }
+
+    fn load_cube_map_faces(&mut self, cube_map_folder: &str, extension: &str, params: TextureParams) -> TextureId {
         self.cubemap_token_gen += 1;
         let cubemap_token = self.cubemap_token_gen;
         self.unprocessed_cubemap_textures.insert(cubemap_token, Vec::new());
         for i in 1..=6 {
-            let filename = format!("{}/{}.png", cube_map_folder, i);
-            self.load_texture_internal(&filename, TextureParams::default(), ExtraInfo { is_cubemap: true, order: i, cubemap_token});
+            let filename = format!("{}/{}.{}", cube_map_folder, i, extension);
+            self.load_texture_internal(&filename, params, ExtraInfo { is_cubemap: true, order: i, cubemap_token});
         }
         TextureId::Loading(cubemap_token)
     }
@@ -210,9 +279,7 @@ impl ModelLoader {
 
         for tex_result in textures_for_cubemap {
             let face = tex_result.3.order;
-            let width = tex_result.0.width;
-            let height = tex_result.0.height;
-            gl::tex_image_2d(gl::helper::CUBEMAP_FACES[face-1], 0, gl::RGBA, width, height, gl::UNSIGNED_BYTE, &tex_result.0.data);
+            Self::upload_texture_data(gl::helper::CUBEMAP_FACES[face-1], &tex_result.0, &tex_result.2);
 
             gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR);
             gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR);
@@ -237,14 +304,27 @@ impl ModelLoader {
         let sender = self.loaded_texture_snd.clone();
         self.thread_pool.execute(move || {
             // make sure to not panic on thread
-            let texture = load_rgba_2d_texture(&file_name_str, params.reverse_texture_data).expect(&format!("Failed to load texture: {}", file_name_str));
+            let texture = if file_name_str.ends_with(".hdr") {
+                TextureData::Hdr(load_hdr_2d_texture(&file_name_str, params.reverse_texture_data).expect(&format!("Failed to load hdr texture: {}", file_name_str)))
+            } else {
+                TextureData::Ldr(load_rgba_2d_texture(&file_name_str, params.reverse_texture_data).expect(&format!("Failed to load texture: {}", file_name_str)))
+            };
             sender.send((texture, texture_queue_id, params, extra_info)).expect("Failed to send");
         });
 
         TextureId::Loading(texture_queue_id)
     }
 
-    fn load_texture_into_graphics_lib(&mut self, texture: Texture2DRGBA, params: TextureParams) -> u32 {
+    fn upload_texture_data(target: gl::types::GLenum, texture_data: &TextureData, params: &TextureParams) {
+        let (width, height) = texture_data.size();
+        let internal_format = params.internal_format(texture_data);
+        match texture_data {
+            TextureData::Ldr(texture) => gl::tex_image_2d_with_internal_format(target, 0, gl::RGBA, internal_format, width, height, gl::UNSIGNED_BYTE, &texture.data),
+            TextureData::Hdr(texture) => gl::tex_image_2d_with_internal_format(target, 0, gl::RGB, internal_format, width, height, gl::FLOAT, &texture.data),
+        }
+    }
+
+    fn load_texture_into_graphics_lib(&mut self, texture: TextureData, params: TextureParams) -> u32 {
         let tex_id = gl::gen_texture();
         self.tex_list.push(tex_id);
         gl::active_texture(gl::TEXTURE0); // even though 0 is default i think, just to be explicit let's activate texture unit 0
@@ -253,7 +333,7 @@ impl ModelLoader {
         gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT);
         gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT);        
 
-        gl::tex_image_2d(gl::TEXTURE_2D, 0, gl::RGBA, texture.width, texture.height, gl::UNSIGNED_BYTE, &texture.data);
+        Self::upload_texture_data(gl::TEXTURE_2D, &texture, &params);
         if params.use_mipmap {
              // turn on mipmapping, has to be called after loading the texture data 
             gl::generate_mipmap(gl::TEXTURE_2D);
@@ -299,6 +379,18 @@ impl ModelLoader {
         }
     }
 
+    // for blend maps that are generated instead of loaded from a file
+    pub fn load_terrain_texture_from_data(&mut self, texture: Texture2DRGBA, params: TextureParams) -> TerrainTexture {
+        TerrainTexture {
+            tex_id: TextureId::Loaded(self.load_texture_into_graphics_lib(TextureData::Ldr(texture), params)),
+        }
+    }
+
+    pub fn unload_texture(&mut self, tex_id: u32) {
+        gl::delete_texture(tex_id);
+        self.tex_list.retain(|tex| *tex != tex_id);
+    }
+
     pub fn create_empty_float_vbo(&mut self, float_count: usize) -> u32 {
         let vbo_id = gl::gen_buffer();
         self.vbo_list.push(vbo_id);
@@ -310,7 +402,7 @@ impl ModelLoader {
 
     pub fn create_empty_float_vbo_for_attrib(&mut self, attribute_num: u32, item_count: usize, coord_size: u32) -> u32 {
         let vbo_id = gl::gen_buffer();
-        self.vbo_list.push(vbo_id);
+        self.track_vbo(vbo_id);
         gl::bind_buffer(gl::ARRAY_BUFFER, vbo_id);
         gl::buffer_data_unitialized::<f32>(gl::ARRAY_BUFFER, item_count * (coord_size as usize), gl::STREAM_DRAW);
         gl::vertex_attrib_pointer(attribute_num, coord_size, gl::FLOAT);
@@ -331,17 +423,36 @@ impl ModelLoader {
         let vao_id = gl::gen_vertex_array();
         self.vao_list.push(vao_id);
         gl::bind_vertex_array(vao_id);                
+        self.bound_vao = vao_id;
         vao_id
     }
     
-    fn unbind_vao(&self) {
+    fn unbind_vao(&mut self) {
         // binding to 0 unbinds
         gl::bind_vertex_array(0);
+        self.bound_vao = 0;
+    }
+
+    // frees a vao and the vbos that were created with it. used for models that get streamed in and out like terrain cells
+    pub fn unload_vao(&mut self, vao_id: u32) {
+        if let Some(vbos) = self.vao_vbos.remove(&vao_id) {
+            gl::delete_buffers(&vbos);
+            self.vbo_list.retain(|vbo| !vbos.contains(vbo));
+        }
+        gl::delete_vertex_arrays(&[vao_id]);
+        self.vao_list.retain(|vao| *vao != vao_id);
+    }
+
+    fn track_vbo(&mut self, vbo_id: u32) {
+        self.vbo_list.push(vbo_id);
+        if self.bound_vao != 0 {
+            self.vao_vbos.entry(self.bound_vao).or_insert_with(Vec::new).push(vbo_id);
+        }
     }
     
     fn store_data_in_attribute_list<T: AsGlType>(&mut self, attribute_num: u32, coord_size: u32, data: &[T]) {
         let vbo_id = gl::gen_buffer();
-        self.vbo_list.push(vbo_id);
+        self.track_vbo(vbo_id);
         gl::bind_buffer(gl::ARRAY_BUFFER, vbo_id);
         gl::buffer_data(gl::ARRAY_BUFFER, data, gl::STATIC_DRAW);
         if T::as_gl_type() == gl::INT {
@@ -354,7 +465,7 @@ impl ModelLoader {
 
     fn bind_indices_buffer(&mut self, indices: &[u32]) {
         let vbo_id = gl::gen_buffer();
-        self.vbo_list.push(vbo_id);
+        self.track_vbo(vbo_id);
         gl::bind_buffer(gl::ELEMENT_ARRAY_BUFFER, vbo_id);
         gl::buffer_data(gl::ELEMENT_ARRAY_BUFFER, indices, gl::STATIC_DRAW);
         // no unbind since we will bind data buffer next -> that means it HAS to be called after        
@@ -427,6 +538,8 @@ pub struct ModelTexture {
     pub reflectivity: f32,
     pub has_transparency: bool,
     pub uses_fake_lighting: bool,
+    // plants whose vertices the wind pushes around, the higher up the further
+    pub sways_in_wind: bool,
     // if this is 1 then the texture is not an atlas
     // also rows == columns since textures are power of two squares and so are textures
     pub number_of_rows_in_atlas: usize,
@@ -440,17 +553,50 @@ impl Default for ModelTexture {
             reflectivity: 0.0,
             has_transparency: false,
             uses_fake_lighting: false,
+            sways_in_wind: false,
             number_of_rows_in_atlas: 1,
         }
     }
 }
 
+// metallic/roughness material like the ones our gltf pipeline exports
+// the base color is the texture of the model and the normal map is the normal map of the model, the rest lives here
+// the maps use the gltf channels: occlusion in r, roughness in g and metallic in b. the factors multiply whatever the maps say
+#[derive(Clone)]
+pub struct PbrMaterial {
+    pub metallic_roughness_tex_id: Option<TextureId>,
+    pub occlusion_tex_id: Option<TextureId>,
+    pub emissive_tex_id: Option<TextureId>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive_strength: f32,
+}
+
 #[derive(Clone)]
 pub struct TexturedModel {
     pub raw_model: RawModel,
     pub texture: ModelTexture,
     pub normal_map_tex_id: Option<TextureId>,
     pub extra_info_tex_id: Option<TextureId>,
+    // models with a pbr material are drawn by the pbr renderer and ignore shine_damper, reflectivity and the extra info map
+    pub pbr_material: Option<PbrMaterial>,
+    // model space bounds for culling, the entities place them in the world
+    pub bounds: ModelBounds,
+    // per instance transforms and atlas offsets, hooked up to the vao with a divisor of 1 like the particle model
+    pub instance_vbo: u32,
+}
+
+impl TexturedModel {
+    // after the skinning attributes, a mat4 attribute takes one slot per column
+    pub const TRANSFORM_COLUMN1: u32 = 6;
+    pub const TRANSFORM_COLUMN2: u32 = 7;
+    pub const TRANSFORM_COLUMN3: u32 = 8;
+    pub const TRANSFORM_COLUMN4: u32 = 9;
+    pub const ATLAS_OFFSET: u32 = 10;
+
+    // 16 floats of the transform and 2 of the atlas offset
+    pub const INSTANCED_DATA_LENGTH: usize = 18;
+    pub const MAX_INSTANCES: usize = 1_000;
 }
 
 impl PartialEq for TexturedModel {
@@ -472,6 +618,9 @@ impl Hash for TexturedModel {
 pub struct TerrainModel {
     pub raw_model: RawModel,
     pub height_map: Rc<Vec<Vec<f32>>>,
+    pub chunks: Rc<Vec<TerrainChunk>>,
+    // blend map generated together with the mesh (from erosion), the terrain should use it over the one of the scene
+    pub blend_map: Option<TerrainTexture>,
 }
 
 #[derive(Clone)]
@@ -489,7 +638,10 @@ pub struct SkyboxModel {
 
 #[derive(Clone)]
 pub struct WaterModel {
+    // a quad drawn as a triangle strip for the flat water
     pub raw_model: RawModel,
+    // the same quad split into a grid of triangles so the waves have vertices to move
+    pub grid_raw_model: RawModel,
     pub dudv_tex_id: TextureId,
     pub normal_map_tex_id: TextureId,
 }
@@ -1,12 +1,18 @@
 pub mod loader;
 pub mod resource_manager;
 pub mod terrain_generator;
+pub mod terrain_lod;
+pub mod erosion;
 pub mod texture_id;
 pub mod collada_load_helper;
+pub mod gltf_material;
 pub mod correction_transform;
 
 pub use self::loader::*;
 pub use self::resource_manager::*;
 pub use self::terrain_generator::*;
+pub use self::terrain_lod::*;
+pub use self::erosion::*;
 pub use self::texture_id::*;
+pub use self::gltf_material::*;
 pub use self::correction_transform::*;
\ No newline at end of file
@@ -2,6 +2,7 @@ use super::{
     loader::{
         ModelLoader,
         TexturedModel,
+        PbrMaterial,
         TerrainTexture,  
         TerrainTexturePack,
         TextureParams,
@@ -17,12 +18,16 @@ use super::{
     terrain_generator::HeightsGenerator,
     texture_id::TextureId,
     collada_load_helper::load_collada_animated_model,
+    gltf_material::GltfMaterial,
     CorrectionTransform,
 };
 use crate::animations::{
     AnimatedModel,    
 };
-use crate::entities::Terrain;
+use crate::entities::{
+    Terrain,
+    terrain::TerrainMeshData,
+};
 use crate::obj_converter::{
     load_obj_model,
     load_simple_obj_model
@@ -35,6 +40,7 @@ use crate::guis::{
     text::text_mesh_creator::*,
 };
 use crate::math::{
+    ModelBounds,
     Vector2f,
 };
 
@@ -93,6 +99,7 @@ pub enum ModelType {
     Boulder,
     FloorTile,
     Lantern,
+    MetalBarrel,
     // demo entities
     Dragon,
     Tea,
@@ -106,6 +113,7 @@ pub struct AtlasProps(usize);
 pub struct ModelProps {
     pub has_transparency: bool,
     pub uses_fake_lighting: bool,
+    pub sways_in_wind: bool,
     pub uses_mipmaps: bool,
     pub uses_anisotropic_filtering: bool,
     pub shine_damper: f32,
@@ -113,20 +121,34 @@ pub struct ModelProps {
     pub atlas_props: AtlasProps,
     pub normal_map: Option<&'static str>,
     pub extra_info_map: Option<&'static str>,
+    pub pbr: Option<PbrProps>,
+    // the pbr material exported with the model, takes over from the pbr props
+    pub gltf_material: Option<&'static str>,
+}
+
+// maps are in the gltf channel layout, see PbrMaterial
+pub struct PbrProps {
+    pub metallic_roughness_map: Option<&'static str>,
+    pub occlusion_map: Option<&'static str>,
+    pub emissive_map: Option<&'static str>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive_strength: f32,
 }
 
 impl ModelProps {
+    // params for the albedo texture of the model which is always in srgb
     fn get_texture_params(&self) -> TextureParams {        
         if self.uses_mipmaps {
             if self.uses_anisotropic_filtering {
-                TextureParams::anisotropic_texture()
+                TextureParams::anisotropic_texture().in_srgb()
             } else if self.normal_map.is_some() {
-                TextureParams::mipmapped_texture(-2.4)
+                TextureParams::mipmapped_texture(-2.4).in_srgb()
             } else {
-                TextureParams::mipmapped_texture(-0.4)
+                TextureParams::mipmapped_texture(-0.4).in_srgb()
             }
         } else {
-            TextureParams::default()
+            TextureParams::srgb_texture()
         }        
     }
 }
@@ -139,6 +161,7 @@ impl Models {
     const DEFAULT_PROPS: ModelProps = ModelProps {
         has_transparency: false, 
         uses_fake_lighting: false, 
+        sways_in_wind: false,
         uses_mipmaps: false,
         uses_anisotropic_filtering: false,
         shine_damper: 1.0,
@@ -146,9 +169,8 @@ impl Models {
         atlas_props: AtlasProps(1),
         normal_map: None,
         extra_info_map: None,
-    };
-    const GUI_PROPS: ModelProps = ModelProps {        
-        ..Self::DEFAULT_PROPS
+        pbr: None,
+        gltf_material: None,
     };
     const COMMON_PROPS: ModelProps = ModelProps {        
         uses_mipmaps: true,
@@ -164,6 +186,7 @@ impl Models {
     };
     const FERN_PROPS: ModelProps = ModelProps { 
         has_transparency: true,         
+        sways_in_wind: true,
         uses_mipmaps: true,        
         atlas_props: AtlasProps(2),
         ..Self::DEFAULT_PROPS
@@ -171,6 +194,7 @@ impl Models {
     const GRASS_PROPS: ModelProps = ModelProps { 
         has_transparency: true, 
         uses_fake_lighting: true, 
+        sways_in_wind: true,
         uses_mipmaps: true,        
         ..Self::DEFAULT_PROPS
     };
@@ -207,6 +231,13 @@ impl Models {
         extra_info_map: Some("res/textures/extra_info_maps/lantern_spec_glow.png"),
         ..Self::DEFAULT_PROPS
     };
+    // same mesh and maps as the barrel but shaded as polished metal
+    const METAL_BARREL_PROPS: ModelProps = ModelProps {
+        uses_mipmaps: true,
+        normal_map: Some("res/textures/normal_maps/barrelNormal.png"),
+        gltf_material: Some("res/materials/barrel.gltf"),
+        ..Self::DEFAULT_PROPS
+    };
     const DEMO_PROPS: ModelProps = ModelProps {
         ..Self::DEFAULT_PROPS
     };
@@ -222,6 +253,7 @@ impl Models {
     pub const TOON_ROCKS: Model = Model(ModelType::ToonRocks, "res/models/toonRocks.obj", "res/textures/toonRocks.png", &Models::SHINY_PROPS);
     pub const BOBBLE_TREE: Model = Model(ModelType::BobbleTree, "res/models/bobbleTree.obj", "res/textures/bobbleTree.png", &Models::COMMON_PROPS);
     pub const BARREL: Model = Model(ModelType::Barrel, "res/models/barrel.obj", "res/textures/barrel.png", &Models::BARREL_PROPS);
+    pub const METAL_BARREL: Model = Model(ModelType::MetalBarrel, "res/models/barrel.obj", "res/textures/barrel.png", &Models::METAL_BARREL_PROPS);
     pub const BOULDER: Model = Model(ModelType::Boulder, "res/models/boulder.obj", "res/textures/boulder.png", &Models::BOULDER_PROPS);
     pub const FLOOR_TILE: Model = Model(ModelType::FloorTile, "res/models/flat.obj", "res/textures/box.png", &Models::FLOOR_PROPS);
     pub const LANTERN: Model = Model(ModelType::Lantern, "res/models/lantern.obj", "res/textures/lantern.png", &Models::LANTERN_PROPS);
@@ -239,6 +271,9 @@ impl ResourceManager {
         
     pub const COPPER_SDF_FONT_TYPE: &'static str = "res/fonts/copperDf";
 
+    // cells per side of the water grid, the waves need a few vertices per wavelength to look round
+    const WATER_GRID_CELLS: usize = 128;
+
     pub const PARTICLE_STAR: ParticleTextureProps = ("res/textures/particles/particleStar.png", 1);
     pub const PARTICLE_ATLAS: ParticleTextureProps = ("res/textures/particles/particleAtlas.png", 4);
     pub const SMOKE_ATLAS: ParticleTextureProps = ("res/textures/particles/smoke.png", 8);
@@ -276,6 +311,12 @@ impl ResourceManager {
                 model.1.extra_info_tex_id = model.1.extra_info_tex_id.map(|tex_id| {
                     self.loader.resolve(tex_id)
                 });
+                model.1.pbr_material = model.1.pbr_material.take().map(|mut material| {
+                    material.metallic_roughness_tex_id = material.metallic_roughness_tex_id.map(|tex_id| self.loader.resolve(tex_id));
+                    material.occlusion_tex_id = material.occlusion_tex_id.map(|tex_id| self.loader.resolve(tex_id));
+                    material.emissive_tex_id = material.emissive_tex_id.map(|tex_id| self.loader.resolve(tex_id));
+                    material
+                });
                 loaded_texture_models.insert(model.0, model.1);
             };
             self.models = loaded_texture_models;
@@ -333,15 +374,17 @@ impl ResourceManager {
             return;
         }
         
-        let (raw_model, normal_map) = if let Some(normal_map_texture) = model_props.normal_map {
+        let (raw_model, normal_map, bounds) = if let Some(normal_map_texture) = model_props.normal_map {
             let model_data = load_obj_model(obj_file, true).expect(&format!("Unable to load {}", obj_file));
             let normal_map = self.loader.load_texture(normal_map_texture, TextureParams::default());
             let raw_model = self.loader.load_to_vao_with_normal_map(&model_data.vertices, &model_data.texture_coords, &model_data.indices, &model_data.normals, &model_data.tangents);
-            (raw_model, Some(normal_map.tex_id))
+            let bounds = ModelBounds::new(&model_data.vertices, model_data.furthest_point);
+            (raw_model, Some(normal_map.tex_id), bounds)
         } else {            
             let model_data = load_simple_obj_model(obj_file).expect(&format!("Unable to load simple {}", obj_file));
             let raw_model = self.loader.load_to_vao(&model_data.vertices, &model_data.texture_coords, &model_data.indices, &model_data.normals);            
-            (raw_model, None)
+            let bounds = ModelBounds::new(&model_data.vertices, model_data.furthest_point);
+            (raw_model, None, bounds)
         };
 
         let extra_info_texture = if let Some(extra_info_tex_name) = model_props.extra_info_map {
@@ -350,28 +393,65 @@ impl ResourceManager {
         } else {
             None
         };
+
+        let pbr_material = if let Some(material_file) = model_props.gltf_material {
+            let material = GltfMaterial::load(material_file).expect(&format!("Unable to load {}", material_file));
+            Some(self.load_pbr_material(material.metallic_roughness_map.as_deref(), material.occlusion_map.as_deref(), material.emissive_map.as_deref(),
+                material.metallic, material.roughness, material.emissive_strength))
+        } else {
+            model_props.pbr.as_ref().map(|pbr| {
+                self.load_pbr_material(pbr.metallic_roughness_map, pbr.occlusion_map, pbr.emissive_map, pbr.metallic, pbr.roughness, pbr.emissive_strength)
+            })
+        };
         
         let mut texture = self.loader.load_texture(texture_file, model_props.get_texture_params());
         texture.has_transparency = model_props.has_transparency;
         texture.uses_fake_lighting = model_props.uses_fake_lighting;
+        texture.sways_in_wind = model_props.sways_in_wind;
         texture.shine_damper = model_props.shine_damper;
         texture.reflectivity = model_props.reflectivity;
         texture.number_of_rows_in_atlas = model_props.atlas_props.0;
-        let model = TexturedModel { raw_model, texture, normal_map_tex_id: normal_map, extra_info_tex_id: extra_info_texture };
+        let instance_vbo = self.loader.create_empty_float_vbo(TexturedModel::INSTANCED_DATA_LENGTH * TexturedModel::MAX_INSTANCES);
+        self.loader.add_instanced_attrib(raw_model.vao_id, instance_vbo, TexturedModel::TRANSFORM_COLUMN1, 4, TexturedModel::INSTANCED_DATA_LENGTH, 0);
+        self.loader.add_instanced_attrib(raw_model.vao_id, instance_vbo, TexturedModel::TRANSFORM_COLUMN2, 4, TexturedModel::INSTANCED_DATA_LENGTH, 4);
+        self.loader.add_instanced_attrib(raw_model.vao_id, instance_vbo, TexturedModel::TRANSFORM_COLUMN3, 4, TexturedModel::INSTANCED_DATA_LENGTH, 8);
+        self.loader.add_instanced_attrib(raw_model.vao_id, instance_vbo, TexturedModel::TRANSFORM_COLUMN4, 4, TexturedModel::INSTANCED_DATA_LENGTH, 12);
+        self.loader.add_instanced_attrib(raw_model.vao_id, instance_vbo, TexturedModel::ATLAS_OFFSET, 2, TexturedModel::INSTANCED_DATA_LENGTH, 16);
+        let model = TexturedModel { raw_model, texture, normal_map_tex_id: normal_map, extra_info_tex_id: extra_info_texture, pbr_material, bounds, instance_vbo };
 
         self.models.insert(model_type.clone(), model);
     }
 
+    // data maps are linear, only the emissive map is a color
+    fn load_pbr_material(&mut self, metallic_roughness_map: Option<&str>, occlusion_map: Option<&str>, emissive_map: Option<&str>,
+                metallic: f32, roughness: f32, emissive_strength: f32) -> PbrMaterial {
+        let metallic_roughness_tex_id = metallic_roughness_map.map(|map| self.loader.load_texture(map, TextureParams::mipmapped_texture(-0.4)).tex_id);
+        // exports usually pack the occlusion into the red channel of the metallic roughness map, no need to load it twice
+        let occlusion_tex_id = match occlusion_map {
+            Some(map) if Some(map) == metallic_roughness_map => metallic_roughness_tex_id,
+            Some(map) => Some(self.loader.load_texture(map, TextureParams::mipmapped_texture(-0.4)).tex_id),
+            None => None,
+        };
+        PbrMaterial {
+            metallic_roughness_tex_id,
+            occlusion_tex_id,
+            emissive_tex_id: emissive_map.map(|map| self.loader.load_texture(map, TextureParams::mipmapped_texture(-0.4).in_srgb()).tex_id),
+            metallic,
+            roughness,
+            emissive_strength,
+        }
+    }
+
     pub fn model(&self, model_type: ModelType) -> TexturedModel {
         self.models.get(&model_type).expect(&format!("Need to call init_model({:?}) before accessing the model", model_type)).clone()
     }
     
     pub fn init_terrain_textures(&mut self) {        
         if let None = self.texture_pack {
-            let background_texture = self.loader.load_terrain_texture("res/textures/terrain/grassy2.png", TextureParams::mipmapped_texture(-0.4));
-            let r_texture = self.loader.load_terrain_texture("res/textures/terrain/mud.png", TextureParams::mipmapped_texture(-0.4));
-            let g_texture = self.loader.load_terrain_texture("res/textures/terrain/grassFlowers.png", TextureParams::mipmapped_texture(-0.4));
-            let b_texture = self.loader.load_terrain_texture("res/textures/terrain/path.png", TextureParams::mipmapped_texture(-0.4));
+            let background_texture = self.loader.load_terrain_texture("res/textures/terrain/grassy2.png", TextureParams::mipmapped_texture(-0.4).in_srgb());
+            let r_texture = self.loader.load_terrain_texture("res/textures/terrain/mud.png", TextureParams::mipmapped_texture(-0.4).in_srgb());
+            let g_texture = self.loader.load_terrain_texture("res/textures/terrain/grassFlowers.png", TextureParams::mipmapped_texture(-0.4).in_srgb());
+            let b_texture = self.loader.load_terrain_texture("res/textures/terrain/path.png", TextureParams::mipmapped_texture(-0.4).in_srgb());
             self.texture_pack = Some(TerrainTexturePack { background_texture, r_texture, g_texture, b_texture, });
         }
         if let None = self.blend_texture {
@@ -398,20 +478,33 @@ impl ResourceManager {
         self.terrain_model.clone().expect("Need to call init_terrain_model before accessing the model")
     }
 
+    // used by streamed terrain where each cell has its own mesh generated off the main thread
+    pub fn load_terrain_mesh(&mut self, mesh: TerrainMeshData) -> TerrainModel {
+        Terrain::load_terrain_mesh(&mut self.loader, mesh)
+    }
+
+    pub fn unload_terrain_model(&mut self, terrain_model: &TerrainModel) {
+        self.loader.unload_vao(terrain_model.raw_model.vao_id);
+        if let Some(blend_map) = &terrain_model.blend_map {
+            self.loader.unload_texture(blend_map.tex_id.unwrap());
+        }
+    }
+
     pub fn init_gui_textures(&mut self) {        
-        let props = Models::GUI_PROPS;
+        // guis are drawn straight to the screen after the gamma correction pass so we sample them as is
+        let params = TextureParams::default();
         if !self.gui_textures.contains_key(ResourceManager::HEALTHBAR_TEXTURE) {
-            let texture_id = self.loader.load_gui_texture(ResourceManager::HEALTHBAR_TEXTURE, props.get_texture_params());
+            let texture_id = self.loader.load_gui_texture(ResourceManager::HEALTHBAR_TEXTURE, params);
             self.gui_textures.insert(ResourceManager::HEALTHBAR_TEXTURE, texture_id);
         }
 
         if !self.gui_textures.contains_key(ResourceManager::GUI_BACKGROUND_TEXTURE) {
-            let texture_id = self.loader.load_gui_texture(ResourceManager::GUI_BACKGROUND_TEXTURE, props.get_texture_params());
+            let texture_id = self.loader.load_gui_texture(ResourceManager::GUI_BACKGROUND_TEXTURE, params);
             self.gui_textures.insert(ResourceManager::GUI_BACKGROUND_TEXTURE, texture_id);
         }
 
         if !self.gui_textures.contains_key(ResourceManager::WHITE_TEXTURE) {
-            let texture_id = self.loader.load_gui_texture(ResourceManager::WHITE_TEXTURE, props.get_texture_params());
+            let texture_id = self.loader.load_gui_texture(ResourceManager::WHITE_TEXTURE, params);
             self.gui_textures.insert(ResourceManager::WHITE_TEXTURE, texture_id);
         }
     }
@@ -487,16 +580,36 @@ impl ResourceManager {
                 1.0, 0.0, -1.0, 
             ];
             let raw_model = self.loader.load_simple_model_to_vao(&positions, 3);
+            let grid_raw_model = self.loader.load_simple_model_to_vao(&ResourceManager::water_grid_positions(ResourceManager::WATER_GRID_CELLS), 3);
             let dudv_tex_id = self.loader.load_terrain_texture("res/textures/water/waterDUDV.png", TextureParams::default()).tex_id;
             let normal_map_tex_id = self.loader.load_terrain_texture("res/textures/water/normalMap.png", TextureParams::default()).tex_id;
             self.water_model = Some(WaterModel {
                 raw_model,
+                grid_raw_model,
                 dudv_tex_id,
                 normal_map_tex_id,
             });
         }
     }
 
+    // two triangles per cell over the same [-1, 1] square as the water quad
+    fn water_grid_positions(cells: usize) -> Vec<f32> {
+        let coord = |i: usize| -1.0 + 2.0 * i as f32 / cells as f32;
+        let mut positions = Vec::with_capacity(cells * cells * 6 * 3);
+        for row in 0..cells {
+            for col in 0..cells {
+                let (x0, x1, z0, z1) = (coord(col), coord(col + 1), coord(row), coord(row + 1));
+                // counter clockwise when looking down on the water
+                for &(x, z) in [(x0, z0), (x0, z1), (x1, z0), (x1, z0), (x0, z1), (x1, z1)].iter() {
+                    positions.push(x);
+                    positions.push(0.0);
+                    positions.push(z);
+                }
+            }
+        }
+        positions
+    }
+
     pub fn water_model(&self) -> WaterModel {
         self.water_model.clone().expect("Need to call init_water first")
     }
@@ -581,7 +694,7 @@ impl ResourceManager {
         for texture_prop in texture_props.iter() {
             if !self.particle_textures.contains_key(texture_prop) {
 
-                let mut particle_texture = self.loader.load_particle_texture(texture_prop.0, TextureParams::default());
+                let mut particle_texture = self.loader.load_particle_texture(texture_prop.0, TextureParams::srgb_texture());
                 particle_texture.number_of_rows_in_atlas = texture_prop.1;
                 
                 self.particle_textures.insert(texture_prop.clone(), particle_texture);
@@ -5,6 +5,18 @@ use texture_lib::texture_loader::{
 	Texture2DRGBA,
 };
 use crate::utils::gen_murmur3_f32;
+use crate::math::noise::{
+    Noise,
+    Perlin,
+    Simplex,
+    Fbm,
+    RidgedMulti,
+    DomainWarp,
+    ScaleBias,
+    Sum,
+    FractalParams,
+};
+use std::sync::Arc;
 
 pub trait TerrainGenerator {
     fn height(&self) -> usize;
@@ -174,4 +186,189 @@ impl TerrainGenerator for HeightsGenerator {
         }        
         total_noise
     }
-}
\ No newline at end of file
+}
+
+// all the knobs of the default noise graph used by NoiseTerrainGenerator
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseTerrainParams {
+    pub seed: u32,
+    // number of vertices along one side of a terrain cell
+    pub vertex_count: usize,
+    pub amplitude: f32,
+    // size of the largest features in vertices
+    pub feature_size: f32,
+    pub octaves: usize,
+    pub lacunarity: f32,
+    pub gain: f32,
+    // how much of the ridged multifractal gets added on top of the rolling fbm hills
+    pub ridge_weight: f32,
+    // how far (in units of feature_size) the domain warp can push a sample
+    pub warp_strength: f32,
+    // octaves of the fbm that pushes the samples around, more gives a more detailed warp
+    pub warp_octaves: usize,
+}
+
+impl Default for NoiseTerrainParams {
+    fn default() -> Self {
+        NoiseTerrainParams {
+            seed: 1234,
+            vertex_count: 128,
+            amplitude: 40.0,
+            feature_size: 96.0,
+            octaves: 6,
+            lacunarity: 2.0,
+            gain: 0.5,
+            ridge_weight: 0.4,
+            warp_strength: 0.35,
+            warp_octaves: 3,
+        }
+    }
+}
+
+// terrain generator which samples a noise graph at global vertex coordinates
+// so the cells of a terrain grid line up without having to flatten their edges
+#[derive(Clone)]
+pub struct NoiseTerrainGenerator {
+    noise: Arc<dyn Noise>,
+    vertex_count: usize,
+    amplitude: f32,
+    // offset of this cell in vertices
+    offset_x: isize,
+    offset_z: isize,
+}
+
+impl NoiseTerrainGenerator {
+    // use any noise graph, the noise is sampled with 1 unit per vertex
+    pub fn new(noise: Arc<dyn Noise>, vertex_count: usize, amplitude: f32) -> Self {
+        NoiseTerrainGenerator {
+            noise,
+            vertex_count,
+            amplitude,
+            offset_x: 0,
+            offset_z: 0,
+        }
+    }
+
+    // fbm hills plus ridged mountains, both domain warped
+    pub fn from_params(params: &NoiseTerrainParams) -> Self {
+        let seed = params.seed;
+        let fractal_params = FractalParams {
+            octaves: params.octaves,
+            frequency: 1.0 / params.feature_size,
+            lacunarity: params.lacunarity,
+            gain: params.gain,
+        };
+        let warp_params = FractalParams {
+            octaves: params.warp_octaves,
+            ..fractal_params
+        };
+        let hills = Box::new(Fbm::new(Box::new(Perlin::new(seed)), fractal_params));
+        let ridges = Box::new(RidgedMulti::new(Box::new(Simplex::new(seed.wrapping_add(1))), fractal_params));
+        let terrain = Box::new(Sum::new(vec![
+            hills, 
+            Box::new(ScaleBias::new(ridges, params.ridge_weight, 0.0)),
+        ]));
+        let warp_x = Box::new(Fbm::new(Box::new(Simplex::new(seed.wrapping_add(2))), warp_params));
+        let warp_z = Box::new(Fbm::new(Box::new(Simplex::new(seed.wrapping_add(3))), warp_params));
+        let warped = DomainWarp::new(terrain, warp_x, warp_z, params.warp_strength * params.feature_size);
+        // keep the final height within [-amplitude, amplitude]
+        let normalized = ScaleBias::new(Box::new(warped), 1.0 / (1.0 + params.ridge_weight), 0.0);
+
+        NoiseTerrainGenerator::new(Arc::new(normalized), params.vertex_count, params.amplitude)
+    }
+
+    // generator for the terrain cell at grid position (grid_x, grid_z) sharing the same noise graph
+    // neighbouring cells share their edge vertices so the offset is vertex_count - 1
+    pub fn for_cell(&self, grid_x: isize, grid_z: isize) -> Self {
+        let cell_stride = self.vertex_count as isize - 1;
+        NoiseTerrainGenerator {
+            noise: self.noise.clone(),
+            offset_x: grid_x * cell_stride,
+            offset_z: grid_z * cell_stride,
+            ..*self
+        }
+    }
+}
+
+impl Default for NoiseTerrainGenerator {
+    fn default() -> Self {
+        NoiseTerrainGenerator::from_params(&NoiseTerrainParams::default())
+    }
+}
+
+impl TerrainGenerator for NoiseTerrainGenerator {
+    fn height(&self) -> usize {
+        self.vertex_count
+    }
+
+    fn width(&self) -> usize {
+        self.vertex_count
+    }
+
+    fn get_height(&self, x: isize, z: isize) -> f32 {
+        let global_x = (self.offset_x + x) as f32;
+        let global_z = (self.offset_z + z) as f32;
+        self.noise.sample(global_x, global_z) * self.amplitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::*;
+
+    #[test]
+    fn noise_terrain_deterministic_per_seed() {
+        let params = NoiseTerrainParams { seed: 99, ..Default::default() };
+        let a = NoiseTerrainGenerator::from_params(&params);
+        let b = NoiseTerrainGenerator::from_params(&params);
+        for i in 0..50 {
+            assert_eq!(a.get_height(i, 3 * i), b.get_height(i, 3 * i));
+        }
+    }
+
+    #[test]
+    fn noise_terrain_continuous_across_cell_boundaries() {
+        let generator = NoiseTerrainGenerator::default();
+        let last = generator.width() as isize - 1;
+        let cell = generator.for_cell(0, 0);
+        let right = generator.for_cell(1, 0);
+        let below = generator.for_cell(0, 1);
+        let left = generator.for_cell(-1, 0);
+        for i in 0..generator.height() as isize {
+            // shared edge vertices have the same height
+            assert_f32_eq!(cell.get_height(last, i), right.get_height(0, i), test_constants::EPS_MEDIUM);
+            assert_f32_eq!(cell.get_height(i, last), below.get_height(i, 0), test_constants::EPS_MEDIUM);
+            assert_f32_eq!(cell.get_height(0, i), left.get_height(last, i), test_constants::EPS_MEDIUM);
+            // and the normals which look at the neighbouring vertices match as well
+            let n1 = cell.get_normal_at(last, i);
+            let n2 = right.get_normal_at(0, i);
+            assert_f32_eq!(n1.dot_product(&n2), 1.0, test_constants::EPS_MEDIUM);
+        }
+    }
+
+    #[test]
+    fn noise_terrain_warp_octaves() {
+        let heights = |params: &NoiseTerrainParams| {
+            let generator = NoiseTerrainGenerator::from_params(params);
+            (0..50).map(|i| generator.get_height(7 * i, 5 * i)).collect::<Vec<f32>>()
+        };
+        let default = NoiseTerrainParams::default();
+        let detailed = NoiseTerrainParams { warp_octaves: 6, ..Default::default() };
+        assert_ne!(heights(&default), heights(&detailed));
+        // without any warp the warp octaves don't matter
+        let unwarped = NoiseTerrainParams { warp_strength: 0.0, ..Default::default() };
+        let unwarped_detailed = NoiseTerrainParams { warp_strength: 0.0, warp_octaves: 6, ..Default::default() };
+        assert_eq!(heights(&unwarped), heights(&unwarped_detailed));
+    }
+
+    #[test]
+    fn noise_terrain_within_amplitude() {
+        let params = NoiseTerrainParams::default();
+        let generator = NoiseTerrainGenerator::from_params(&params);
+        for i in 0..params.vertex_count as isize {
+            let height = generator.get_height(i, params.vertex_count as isize - i);
+            assert!(height.abs() <= params.amplitude * 1.01, "Height {} exceeds amplitude", height);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::math::Vector3f;
+use std::cmp;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexRange {
+    // in indices, not bytes
+    pub offset: usize,
+    pub count: usize,
+}
+
+// square piece of a terrain cell (geomipmapping). every level of detail has its own range in the shared index buffer of the cell
+// and all levels reuse the same vertices, a level just skips every other vertex of the previous level
+#[derive(Debug, Clone)]
+pub struct TerrainChunk {
+    // bounds in the local space of the terrain cell
+    pub min: Vector3f,
+    pub max: Vector3f,
+    // lods[0] is the full resolution mesh
+    pub lods: Vec<IndexRange>,
+}
+
+impl TerrainChunk {
+    pub const CHUNK_QUADS: usize = 32;
+    // vertex steps 1, 2, 4, .., CHUNK_QUADS
+    pub const LOD_COUNT: usize = 6;
+    // full detail up to this distance then every doubling of the distance drops one level
+    pub const LOD_DISTANCE: f32 = 150.0;
+
+    // distance from a point in the local space of the terrain cell to the closest point of the chunk
+    pub fn distance_to(&self, point: &Vector3f) -> f32 {
+        let dx = (self.min.x - point.x).max(0.0).max(point.x - self.max.x);
+        let dy = (self.min.y - point.y).max(0.0).max(point.y - self.max.y);
+        let dz = (self.min.z - point.z).max(0.0).max(point.z - self.max.z);
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    pub fn lod_for_distance(&self, distance: f32) -> usize {
+        if distance <= TerrainChunk::LOD_DISTANCE {
+            return 0;
+        }
+        let lod = (distance / TerrainChunk::LOD_DISTANCE).log2().floor() as usize + 1;
+        cmp::min(lod, self.lods.len() - 1)
+    }
+
+    pub fn lod_range_at(&self, point: &Vector3f) -> IndexRange {
+        self.lods[self.lod_for_distance(self.distance_to(point))]
+    }
+}
+
+// indices of all chunks and levels of one terrain cell
+// neighbouring chunks can be drawn at different levels which leaves cracks along their shared edge,
+// so every chunk gets a skirt hanging down from its border which fills the gaps
+pub struct TerrainLodMesh {
+    pub indices: Vec<u32>,
+    pub chunks: Vec<TerrainChunk>,
+    // the skirt vertices are appended after the grid vertices, each one is a lowered copy of the grid vertex stored here
+    pub skirt_sources: Vec<u32>,
+}
+
+impl TerrainLodMesh {
+    // heights are indexed by [x][z], the grid vertex at (x, z) has index z * vertex_count + x
+    pub fn build(vertex_count: usize, heights: &[Vec<f32>], size: f32) -> TerrainLodMesh {
+        let quads = vertex_count - 1;
+        let grid_width = size / quads as f32;
+        let grid_vertex_count = vertex_count * vertex_count;
+        let mut lod_mesh = TerrainLodMesh {
+            indices: Vec::new(),
+            chunks: Vec::new(),
+            skirt_sources: Vec::new(),
+        };
+        let mut skirt_index = vec![u32::MAX; grid_vertex_count];
+
+        for chunk_z in (0..quads).step_by(TerrainChunk::CHUNK_QUADS) {
+            for chunk_x in (0..quads).step_by(TerrainChunk::CHUNK_QUADS) {
+                let end_x = cmp::min(chunk_x + TerrainChunk::CHUNK_QUADS, quads);
+                let end_z = cmp::min(chunk_z + TerrainChunk::CHUNK_QUADS, quads);
+
+                let mut min_height = f32::MAX;
+                let mut max_height = f32::MIN;
+                for column in heights.iter().take(end_x + 1).skip(chunk_x) {
+                    for height in column.iter().take(end_z + 1).skip(chunk_z) {
+                        min_height = min_height.min(*height);
+                        max_height = max_height.max(*height);
+                    }
+                }
+
+                let mut lods = Vec::with_capacity(TerrainChunk::LOD_COUNT);
+                for lod in 0..TerrainChunk::LOD_COUNT {
+                    let step = 1 << lod;
+                    let offset = lod_mesh.indices.len();
+                    let xs = TerrainLodMesh::samples(chunk_x, end_x, step);
+                    let zs = TerrainLodMesh::samples(chunk_z, end_z, step);
+                    lod_mesh.add_surface(&xs, &zs, vertex_count);
+                    lod_mesh.add_skirts(&xs, &zs, vertex_count, &mut skirt_index);
+                    lods.push(IndexRange { offset, count: lod_mesh.indices.len() - offset });
+                }
+
+                lod_mesh.chunks.push(TerrainChunk {
+                    min: Vector3f::new(chunk_x as f32 * grid_width, min_height, chunk_z as f32 * grid_width),
+                    max: Vector3f::new(end_x as f32 * grid_width, max_height, end_z as f32 * grid_width),
+                    lods,
+                });
+            }
+        }
+        lod_mesh
+    }
+
+    // every step-th grid line between start and end. the end is always included so the last step may be shorter
+    fn samples(start: usize, end: usize, step: usize) -> Vec<usize> {
+        let mut samples: Vec<usize> = (start..end).step_by(step).collect();
+        samples.push(end);
+        samples
+    }
+
+    fn add_surface(&mut self, xs: &[usize], zs: &[usize], vertex_count: usize) {
+        for z in zs.windows(2) {
+            for x in xs.windows(2) {
+                let top_left = (z[0] * vertex_count + x[0]) as u32;
+                let top_right = (z[0] * vertex_count + x[1]) as u32;
+                let bottom_left = (z[1] * vertex_count + x[0]) as u32;
+                let bottom_right = (z[1] * vertex_count + x[1]) as u32;
+                self.indices.extend_from_slice(&[top_left, bottom_left, top_right, top_right, bottom_left, bottom_right]);
+            }
+        }
+    }
+
+    fn add_skirts(&mut self, xs: &[usize], zs: &[usize], vertex_count: usize, skirt_index: &mut [u32]) {
+        let (first_x, last_x) = (xs[0], xs[xs.len() - 1]);
+        let (first_z, last_z) = (zs[0], zs[zs.len() - 1]);
+        // walk the border so that the chunk is always on the right, that way the skirts face outwards
+        let mut border = Vec::with_capacity(2 * (xs.len() + zs.len()));
+        border.extend(xs.iter().map(|x| first_z * vertex_count + x));
+        border.extend(zs.iter().skip(1).map(|z| z * vertex_count + last_x));
+        border.extend(xs.iter().rev().skip(1).map(|x| last_z * vertex_count + x));
+        border.extend(zs.iter().rev().skip(1).map(|z| z * vertex_count + first_x));
+
+        let grid_vertex_count = vertex_count * vertex_count;
+        for edge in border.windows(2) {
+            let a = edge[0] as u32;
+            let b = edge[1] as u32;
+            let skirt_a = self.skirt_vertex(edge[0], grid_vertex_count, skirt_index);
+            let skirt_b = self.skirt_vertex(edge[1], grid_vertex_count, skirt_index);
+            self.indices.extend_from_slice(&[a, b, skirt_a, b, skirt_b, skirt_a]);
+        }
+    }
+
+    // skirt vertices are shared between neighbouring chunks and between levels
+    fn skirt_vertex(&mut self, grid_vertex: usize, grid_vertex_count: usize, skirt_index: &mut [u32]) -> u32 {
+        if skirt_index[grid_vertex] == u32::MAX {
+            skirt_index[grid_vertex] = (grid_vertex_count + self.skirt_sources.len()) as u32;
+            self.skirt_sources.push(grid_vertex as u32);
+        }
+        skirt_index[grid_vertex]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_lod_mesh(vertex_count: usize) -> TerrainLodMesh {
+        let heights = vec![vec![0.0f32; vertex_count]; vertex_count];
+        TerrainLodMesh::build(vertex_count, &heights, 100.0)
+    }
+
+    #[test]
+    fn test_single_chunk_index_counts() {
+        let quads = TerrainChunk::CHUNK_QUADS;
+        let lod_mesh = flat_lod_mesh(quads + 1);
+        assert_eq!(lod_mesh.chunks.len(), 1);
+        let lods = &lod_mesh.chunks[0].lods;
+        assert_eq!(lods.len(), TerrainChunk::LOD_COUNT);
+        for (lod, range) in lods.iter().enumerate() {
+            let side = quads >> lod;
+            // two triangles per quad plus two per skirt segment along the 4 edges
+            assert_eq!(range.count, side * side * 6 + 4 * side * 6, "lod {}", lod);
+        }
+        // the coarsest level is a single quad
+        assert_eq!(lods[TerrainChunk::LOD_COUNT - 1].count, 6 + 4 * 6);
+        // the levels follow each other in the index buffer
+        for window in lods.windows(2) {
+            assert_eq!(window[0].offset + window[0].count, window[1].offset);
+        }
+    }
+
+    #[test]
+    fn test_uneven_chunks() {
+        // 127 quads per side, the last chunk in each direction is one quad short
+        let vertex_count = 128;
+        let lod_mesh = flat_lod_mesh(vertex_count);
+        let chunks_per_side = (vertex_count - 1).div_ceil(TerrainChunk::CHUNK_QUADS);
+        assert_eq!(lod_mesh.chunks.len(), chunks_per_side * chunks_per_side);
+        let last = lod_mesh.chunks.last().unwrap();
+        assert_eq!(last.max.x, 100.0);
+        assert_eq!(last.max.z, 100.0);
+        let skirt_vertex_count = lod_mesh.skirt_sources.len();
+        for index in lod_mesh.indices.iter() {
+            assert!((*index as usize) < vertex_count * vertex_count + skirt_vertex_count);
+        }
+        // skirts only go along the chunk borders
+        for source in lod_mesh.skirt_sources.iter() {
+            let x = *source as usize % vertex_count;
+            let z = *source as usize / vertex_count;
+            let on_border = |v: usize| v % TerrainChunk::CHUNK_QUADS == 0 || v == vertex_count - 1;
+            assert!(on_border(x) || on_border(z), "skirt at {} {}", x, z);
+        }
+    }
+
+    #[test]
+    fn test_full_resolution_covers_grid() {
+        let vertex_count = 2 * TerrainChunk::CHUNK_QUADS + 1;
+        let lod_mesh = flat_lod_mesh(vertex_count);
+        let grid_vertex_count = (vertex_count * vertex_count) as u32;
+        let mut used = vec![false; grid_vertex_count as usize];
+        for chunk in lod_mesh.chunks.iter() {
+            let range = chunk.lods[0];
+            for index in lod_mesh.indices[range.offset..range.offset + range.count].iter() {
+                if *index < grid_vertex_count {
+                    used[*index as usize] = true;
+                }
+            }
+        }
+        assert!(used.iter().all(|used| *used));
+    }
+
+    #[test]
+    fn test_lod_selection() {
+        let lod_mesh = flat_lod_mesh(TerrainChunk::CHUNK_QUADS + 1);
+        let chunk = &lod_mesh.chunks[0];
+        assert_eq!(chunk.distance_to(&Vector3f::new(50.0, 0.0, 50.0)), 0.0);
+        assert_eq!(chunk.distance_to(&Vector3f::new(50.0, 0.0, 130.0)), 30.0);
+        assert_eq!(chunk.lod_for_distance(0.0), 0);
+        assert_eq!(chunk.lod_for_distance(TerrainChunk::LOD_DISTANCE * 1.5), 1);
+        assert_eq!(chunk.lod_for_distance(TerrainChunk::LOD_DISTANCE * 2.5), 2);
+        assert_eq!(chunk.lod_for_distance(1e9), TerrainChunk::LOD_COUNT - 1);
+    }
+}
@@ -5,8 +5,12 @@ use crate::constants::GRAVITY;
 use crate::display::{
     Display,
 };
-use crate::entities::Camera;
+use crate::entities::{
+    Camera,
+    WaterTile,
+};
 use crate::math::{
+    Frustum,
     Matrix4f,
     Vector2f,
     Vector3f,
@@ -15,6 +19,7 @@ use crate::models::{
     ParticleTexturedModel,
 };
 use crate::renderers::{
+    culling::CullCounter,
     particle_renderer::ParticleRenderer,
     particle_renderer_gpu_instanced::ParticleRendererGpuInstanced,    
 };
@@ -29,6 +34,7 @@ pub struct Particle {
     pub position: Vector3f,
     pub velocity: Vector3f,
     pub gravity_effect: f32, // scale that says how much graity affects this particle
+    pub wind_effect: f32, // how quickly the particle picks up the speed of the wind
     pub rotation_deg_z: f32,
     pub scale: f32,
     pub lifetime: f32,
@@ -41,6 +47,8 @@ pub struct Particle {
 }
 
 impl Particle {
+    // how much of the speed into the water a particle keeps when it bounces off
+    const WATER_BOUNCE: f32 = 0.3;
 
     pub fn new(model: ParticleTexturedModel, position: Vector3f, velocity: Vector3f, gravity_effect: f32, rotation_deg_z: f32, scale: f32, lifetime: f32,) -> Self {
         Particle {
@@ -48,6 +56,7 @@ impl Particle {
             position,
             velocity,
             gravity_effect,
+            wind_effect: 0.0,
             rotation_deg_z,
             scale,
             lifetime,
@@ -59,15 +68,38 @@ impl Particle {
         }
     }
 
-    pub fn update(&mut self, display: &Display, camera: &Camera) {
+    pub fn with_wind_effect(mut self, wind_effect: f32) -> Self {
+        self.wind_effect = wind_effect;
+        self
+    }
+
+    pub fn update(&mut self, display: &Display, camera: &Camera, wind: &Vector3f, water_tiles: &[WaterTile]) {
         self.velocity.y += GRAVITY * display.frame_time_sec * self.gravity_effect;
+        // the wind drags the particle along sideways until it moves as fast as the wind
+        let drag = (self.wind_effect * display.frame_time_sec).min(1.0);
+        self.velocity.x += (wind.x - self.velocity.x) * drag;
+        self.velocity.z += (wind.z - self.velocity.z) * drag;
         let dpos_per_frame = self.velocity.clone() * display.frame_time_sec;
         self.position += &dpos_per_frame;
+        if let Some(water_height) = WaterTile::surface_height_at(water_tiles, self.position.x, self.position.z, display.wall_clock.elapsed) {
+            Particle::bounce_off_water(&mut self.position, &mut self.velocity, water_height);
+        }
         self.update_texture_atlas_data();
         self.update_dist(camera);
         self.elapsed_time += display.frame_time_sec;        
     }
 
+    // the particles float on the water instead of sinking through it, the ones falling onto it bounce back up a bit
+    fn bounce_off_water(position: &mut Vector3f, velocity: &mut Vector3f, water_height: f32) {
+        if position.y >= water_height {
+            return;
+        }
+        position.y = water_height;
+        if velocity.y < 0.0 {
+            velocity.y *= -Particle::WATER_BOUNCE;
+        }
+    }
+
     pub fn is_alive(&self) -> bool {
         self.elapsed_time < self.lifetime
     }
@@ -110,6 +142,9 @@ impl PartialOrd for Particle {
 pub struct ParticleMaster {
     particles: HashMap<ParticleTexturedModel, Vec<Particle>>,
     particle_renderer: Box<dyn ParticleRenderer>,
+    projection_matrix: Matrix4f,
+    // particles skipped by the last render
+    pub culling: CullCounter,
 }
 
 impl ParticleMaster {
@@ -117,18 +152,26 @@ impl ParticleMaster {
         ParticleMaster {
             particles: HashMap::new(),            
             particle_renderer: Box::new(ParticleRendererGpuInstanced::new(projection_matrix)),
+            projection_matrix: projection_matrix.clone(),
+            culling: CullCounter::default(),
         }
     }
 
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.particle_renderer.update_projection_matrix(projection_matrix);
+        self.projection_matrix = projection_matrix.clone();
+    }
+
     pub fn add_particle(&mut self, particle: Particle) {
         let entry = self.particles.entry(particle.model.clone()).or_insert(Vec::new());
         entry.push(particle);
     }
 
-    pub fn update(&mut self, display: &Display, camera: &Camera) {
+    // the wind is the one of the weather of the scene, the water tiles the particles land on
+    pub fn update(&mut self, display: &Display, camera: &Camera, wind: &Vector3f, water_tiles: &[WaterTile]) {
         for (_texture, particles) in self.particles.iter_mut() {
             for particle in particles.iter_mut() {
-                particle.update(display, camera);
+                particle.update(display, camera, wind, water_tiles);
             }
             insertion_sort(particles);
             particles.retain(|particle_ref| particle_ref.is_alive());
@@ -137,7 +180,20 @@ impl ParticleMaster {
     }
 
     pub fn render(&mut self, camera: &Camera) {
-        self.particle_renderer.render(&self.particles, camera);
+        self.culling.reset();
+        let frustum = Frustum::from_view_projection(&self.projection_matrix, &Matrix4f::create_view_matrix(camera));
+        let culling = &mut self.culling;
+        let mut visible = HashMap::new();
+        for (model, particles) in self.particles.iter() {
+            // the scale is the side of the quad so it is a bit more than the radius of any rotation of it
+            let in_frustum: Vec<&Particle> = particles.iter()
+                .filter(|particle| culling.count(frustum.intersects_sphere(&particle.position, particle.scale)))
+                .collect();
+            if !in_frustum.is_empty() {
+                visible.insert(model, in_frustum);
+            }
+        }
+        self.particle_renderer.render(&visible, camera);
     }
 
     pub fn emit_particles(&mut self, particle_systems: &Vec<(AdvancedParticleSystem, Vector3f)>, display: &Display) {
@@ -145,4 +201,29 @@ impl ParticleMaster {
             system.emit_particles(self, pos, display);
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_particles_bounce_off_the_water() {
+        // falling into the water puts the particle back on the surface going up
+        let mut position = Vector3f::new(1.0, -0.5, 2.0);
+        let mut velocity = Vector3f::new(0.5, -4.0, 0.0);
+        Particle::bounce_off_water(&mut position, &mut velocity, 0.2);
+        assert_eq!((position.x, position.y, position.z), (1.0, 0.2, 2.0));
+        assert_eq!((velocity.x, velocity.y, velocity.z), (0.5, 4.0 * Particle::WATER_BOUNCE, 0.0));
+
+        // a wave coming up under a rising particle only lifts it
+        let mut velocity = Vector3f::new(0.0, 1.0, 0.0);
+        Particle::bounce_off_water(&mut position, &mut velocity, 0.6);
+        assert_eq!((position.y, velocity.y), (0.6, 1.0));
+
+        // above the water nothing happens
+        let mut velocity = Vector3f::new(0.0, -1.0, 0.0);
+        Particle::bounce_off_water(&mut position, &mut velocity, 0.0);
+        assert_eq!((position.y, velocity.y), (0.6, -1.0));
+    }
 }
\ No newline at end of file
@@ -84,6 +84,8 @@ pub struct ParticleSystemProps {
     pub randomize_rotation: bool,
     pub direction: Option<(Vector3f, f32)>,
     pub additive_blending: bool,
+    // how quickly the wind of the weather carries the particles along, 0 for particles that ignore it
+    pub wind_effect: f32,
 }
 
 pub struct AdvancedParticleSystem {
@@ -99,6 +101,7 @@ pub struct AdvancedParticleSystem {
     randomize_rotation: bool,
     direction: Option<Vector3f>,
     direction_deviation: Option<f32>,
+    wind_effect: f32,
 }
 
 impl AdvancedParticleSystem {    
@@ -123,6 +126,7 @@ impl AdvancedParticleSystem {
             randomize_rotation: props.randomize_rotation,
             direction,
             direction_deviation,
+            wind_effect: props.wind_effect,
         }
     }
 
@@ -138,6 +142,7 @@ impl AdvancedParticleSystem {
         let particle_rotation = if self.randomize_rotation { rng.gen::<f32>() * 360.0 } else { 0.0 };
         let particle_life = AdvancedParticleSystem::generate_value_using_error(rng, self.life_length, self.life_error);
         Particle::new(self.particle_model.clone(), spawn_pos.clone(), velocity, self.gravity_effect, particle_rotation, particle_scale, particle_life)
+            .with_wind_effect(self.wind_effect)
     }
 
     fn generate_random_direction(rng: &mut ThreadRng) -> Vector3f {
@@ -0,0 +1,98 @@
+use crate::math::Vector3f;
+
+// 3d table of output colors indexed by the input color, the color grading effect uploads it as a 3d texture
+// rgb triplets with red changing fastest then green then blue
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLut {
+    pub size: usize,
+    pub data: Vec<f32>,
+}
+
+impl ColorLut {
+    // entries per channel, the texture filtering blends between them
+    pub const DEFAULT_SIZE: usize = 16;
+
+    pub fn from_fn<F: Fn(&Vector3f) -> Vector3f>(size: usize, grade: F) -> ColorLut {
+        assert!(size >= 2, "A color lut needs at least two entries per channel");
+        let step = 1.0 / (size - 1) as f32;
+        let mut data = Vec::with_capacity(3 * size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let color = grade(&Vector3f::new(r as f32 * step, g as f32 * step, b as f32 * step));
+                    data.extend_from_slice(&[color.x, color.y, color.z]);
+                }
+            }
+        }
+        ColorLut {
+            size,
+            data,
+        }
+    }
+
+    pub fn identity(size: usize) -> ColorLut {
+        ColorLut::from_fn(size, |color| color.clone())
+    }
+
+    // a simple grade: saturation around the luminance and then a tint multiplied in
+    pub fn graded(size: usize, saturation: f32, tint: &Vector3f) -> ColorLut {
+        ColorLut::from_fn(size, |color| {
+            let luminance = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+            let saturate = |channel: f32, tint: f32| ((luminance + (channel - luminance) * saturation) * tint).clamp(0.0, 1.0);
+            Vector3f::new(saturate(color.x, tint.x), saturate(color.y, tint.y), saturate(color.z, tint.z))
+        })
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> Vector3f {
+        let idx = 3 * ((b * self.size + g) * self.size + r);
+        Vector3f::new(self.data[idx], self.data[idx + 1], self.data[idx + 2])
+    }
+
+    // the same trilinear lookup the gpu does for the color grading shader
+    pub fn sample(&self, color: &Vector3f) -> Vector3f {
+        let max = (self.size - 1) as f32;
+        let coord = |channel: f32| {
+            let scaled = channel.clamp(0.0, 1.0) * max;
+            let lo = (scaled.floor() as usize).min(self.size - 2);
+            (lo, scaled - lo as f32)
+        };
+        let (r, fr) = coord(color.x);
+        let (g, fg) = coord(color.y);
+        let (b, fb) = coord(color.z);
+        // Vector3f::lerp weights the first vector by t
+        let lerp = |a: Vector3f, b: Vector3f, t: f32| Vector3f::lerp(&b, &a, t);
+        let along_r = |g: usize, b: usize| lerp(self.entry(r, g, b), self.entry(r + 1, g, b), fr);
+        let along_g = |b: usize| lerp(along_r(g, b), along_r(g + 1, b), fg);
+        lerp(along_g(b), along_g(b + 1), fb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &Vector3f, b: &Vector3f) {
+        assert!((a.x - b.x).abs() < 1e-5 && (a.y - b.y).abs() < 1e-5 && (a.z - b.z).abs() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_identity_lut_keeps_colors() {
+        let lut = ColorLut::identity(ColorLut::DEFAULT_SIZE);
+        assert_eq!(lut.data.len(), 3 * 16 * 16 * 16);
+        for color in [Vector3f::new(0.0, 0.0, 0.0), Vector3f::new(1.0, 1.0, 1.0), Vector3f::new(0.13, 0.58, 0.92)].iter() {
+            assert_close(&lut.sample(color), color);
+        }
+        // out of range colors get clamped like the texture lookup does
+        assert_close(&lut.sample(&Vector3f::new(1.5, -0.2, 0.5)), &Vector3f::new(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn test_graded_lut() {
+        let grey = ColorLut::graded(8, 0.0, &Vector3f::new(1.0, 1.0, 1.0));
+        let red = grey.entry(7, 0, 0);
+        assert_close(&red, &Vector3f::new(0.2126, 0.2126, 0.2126));
+
+        let warm = ColorLut::graded(8, 1.0, &Vector3f::new(1.0, 0.9, 0.8));
+        assert_close(&warm.entry(7, 7, 7), &Vector3f::new(1.0, 0.9, 0.8));
+    }
+}
\ No newline at end of file
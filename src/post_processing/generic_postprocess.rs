@@ -22,6 +22,12 @@ impl<ShaderType: Shader> GenericPostprocess<ShaderType> {
         }
     }
 
+    // changes the settings of the shader and loads them again
+    pub fn update_shader<F: FnOnce(&mut ShaderType)>(&mut self, f: F) {
+        f(&mut self.shader);
+        self.shader.init();
+    }
+
     pub fn render_with_one_input(&mut self, source_color_texture: u32, display: &Display) {
         gl::active_texture(gl::TEXTURE0);
         gl::bind_texture(gl::TEXTURE_2D, source_color_texture);
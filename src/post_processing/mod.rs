@@ -1,2 +1,5 @@
 pub mod post_processing;
-pub mod generic_postprocess;
\ No newline at end of file
+pub mod generic_postprocess;
+pub mod post_effect;
+pub mod color_lut;
+pub mod ssao;
\ No newline at end of file
@@ -0,0 +1,122 @@
+use super::color_lut::ColorLut;
+
+// how the tonemapping squeezes the hdr colors into [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TonemapOperator {
+    Reinhard,
+    // the filmic curve fitted to aces, keeps more contrast in the darks and rolls off the highlights
+    Aces,
+}
+
+// one step of the post processing stack with its settings, the scene lists them in the order they run
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostEffect {
+    // pushes the colors away from the middle grey
+    Contrast { amount: f32 },
+    // blurs what is away from the focus distance (in world units from the camera), fully blurred focus_range units further
+    // max_blur is the radius in pixels of the blurriest parts
+    DepthOfField { focus_distance: f32, focus_range: f32, max_blur: f32 },
+    // hdr in, gamma corrected [0, 1] out. every stack has exactly one
+    Tonemap { operator: TonemapOperator, exposure: f32, gamma: f32 },
+    // looks the tonemapped colors up in a 3d table and mixes that in by strength
+    ColorGrading { lut: ColorLut, strength: f32 },
+    // darkens towards the corners, radius and softness are distances from the center in uv units
+    Vignette { radius: f32, softness: f32, strength: f32 },
+    // pulls red and blue apart towards the edges of the screen, strength is the offset in the corners in uv units
+    ChromaticAberration { strength: f32 },
+    // noise that changes every frame
+    FilmGrain { strength: f32 },
+    // smooths the edges of the tonemapped image, with it in the stack the camera fbo isn't multisampled
+    Fxaa,
+}
+
+impl PostEffect {
+    // the render graph has a pass for every one of these which the stack enables from the front
+    pub const MAX_EFFECTS: usize = 8;
+    pub const EXPOSURE: f32 = 1.0;
+    pub const GAMMA: f32 = 2.2;
+
+    pub fn tonemap(operator: TonemapOperator) -> PostEffect {
+        PostEffect::Tonemap { operator, exposure: PostEffect::EXPOSURE, gamma: PostEffect::GAMMA }
+    }
+
+    // the contrast and tonemapping the engine always did
+    pub fn default_stack() -> Vec<PostEffect> {
+        vec![
+            PostEffect::Contrast { amount: 0.3 },
+            PostEffect::tonemap(TonemapOperator::Reinhard),
+        ]
+    }
+
+    // the scene in linear hdr colors with nothing done to it
+    pub fn minimal_stack() -> Vec<PostEffect> {
+        vec![PostEffect::tonemap(TonemapOperator::Reinhard)]
+    }
+
+    // these work on the linear hdr colors so they go before the tonemapping, the others after it
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, PostEffect::Contrast { .. } | PostEffect::DepthOfField { .. })
+    }
+
+    pub fn validate_stack(effects: &[PostEffect]) -> Result<(), String> {
+        if effects.len() > PostEffect::MAX_EFFECTS {
+            return Err(format!("The post processing stack can have at most {} effects, it has {}", PostEffect::MAX_EFFECTS, effects.len()));
+        }
+        let tonemaps: Vec<usize> = effects.iter().enumerate()
+            .filter(|(_, effect)| matches!(effect, PostEffect::Tonemap { .. }))
+            .map(|(idx, _)| idx)
+            .collect();
+        if tonemaps.len() != 1 {
+            return Err(format!("The post processing stack needs exactly one tonemapping, it has {}", tonemaps.len()));
+        }
+        let tonemap = tonemaps[0];
+        for (idx, effect) in effects.iter().enumerate() {
+            if idx < tonemap && !effect.is_hdr() {
+                return Err(format!("{:?} works on tonemapped colors so it has to come after the tonemapping", effect));
+            }
+            if idx > tonemap && effect.is_hdr() {
+                return Err(format!("{:?} works on hdr colors so it has to come before the tonemapping", effect));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn uses_fxaa(effects: &[PostEffect]) -> bool {
+        effects.contains(&PostEffect::Fxaa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_stack() {
+        assert!(PostEffect::validate_stack(&PostEffect::default_stack()).is_ok());
+        assert!(PostEffect::validate_stack(&PostEffect::minimal_stack()).is_ok());
+        let full = vec![
+            PostEffect::DepthOfField { focus_distance: 50.0, focus_range: 100.0, max_blur: 6.0 },
+            PostEffect::Contrast { amount: 0.2 },
+            PostEffect::tonemap(TonemapOperator::Aces),
+            PostEffect::ColorGrading { lut: ColorLut::identity(4), strength: 1.0 },
+            PostEffect::Vignette { radius: 0.6, softness: 0.4, strength: 0.5 },
+            PostEffect::ChromaticAberration { strength: 0.003 },
+            PostEffect::FilmGrain { strength: 0.04 },
+            PostEffect::Fxaa,
+        ];
+        assert!(PostEffect::validate_stack(&full).is_ok());
+        assert!(PostEffect::uses_fxaa(&full));
+        assert!(!PostEffect::uses_fxaa(&PostEffect::default_stack()));
+
+        // no tonemapping or two of them
+        assert!(PostEffect::validate_stack(&[PostEffect::Fxaa]).is_err());
+        assert!(PostEffect::validate_stack(&[PostEffect::tonemap(TonemapOperator::Reinhard), PostEffect::tonemap(TonemapOperator::Aces)]).is_err());
+        // on the wrong side of the tonemapping
+        assert!(PostEffect::validate_stack(&[PostEffect::tonemap(TonemapOperator::Aces), PostEffect::Contrast { amount: 0.3 }]).is_err());
+        assert!(PostEffect::validate_stack(&[PostEffect::Fxaa, PostEffect::tonemap(TonemapOperator::Aces)]).is_err());
+        // more than the graph has passes for
+        let mut too_many = full.clone();
+        too_many.push(PostEffect::FilmGrain { strength: 0.1 });
+        assert!(PostEffect::validate_stack(&too_many).is_err());
+    }
+}
\ No newline at end of file
@@ -1,81 +1,309 @@
 use super::generic_postprocess::GenericPostprocess;
-use crate::display::{
-    Display,
-    framebuffers::FboMap,
-    framebuffers::FramebufferObject,
-    framebuffers::FboFlags,
+use super::post_effect::PostEffect;
+use super::color_lut::ColorLut;
+use super::ssao::SsaoKernel;
+use crate::display::Display;
+use crate::entities::{
+    Atmosphere,
+    Camera,
+    Light,
+    LightShafts,
 };
 use crate::gl;
+use crate::math::{
+    Matrix4f,
+    Vector3f,
+};
 use crate::models::{
     RawModel,
     QuadModel,
 };
+use crate::renderers::render_graph::{
+    RenderGraph,
+    RenderPass,
+};
 use crate::shaders::post_processing::{
     HorizontalBlurShader,
     VerticalBlurShader,
     ContrastShader,
     CombineShader,
+    TonemapShader,
+    DepthOfFieldShader,
+    ColorGradingShader,
+    VignetteShader,
+    ChromaticAberrationShader,
+    FilmGrainShader,
+    FxaaShader,
+    SsaoShader,
+    SsaoApplyShader,
+    LightShaftsShader,
+    AtmosphereShader,
 };
 
+// an effect of the configured stack with the shader that renders it
+enum EffectStage {
+    Contrast(GenericPostprocess<ContrastShader>),
+    DepthOfField(GenericPostprocess<DepthOfFieldShader>),
+    Tonemap(GenericPostprocess<TonemapShader>),
+    // with the 3d texture of its lut
+    ColorGrading(GenericPostprocess<ColorGradingShader>, u32),
+    Vignette(GenericPostprocess<VignetteShader>),
+    ChromaticAberration(GenericPostprocess<ChromaticAberrationShader>),
+    FilmGrain(GenericPostprocess<FilmGrainShader>),
+    Fxaa(GenericPostprocess<FxaaShader>),
+}
+
 pub struct PostProcessing {
     quad_model: QuadModel,
-    contrast_changer: GenericPostprocess<ContrastShader>,
     horizontal_blur: GenericPostprocess<HorizontalBlurShader>,
     vertical_blur: GenericPostprocess<VerticalBlurShader>,
     //brightness_filter: GenericPostprocess<BrightnessFilterShader>,
     combine_shader: GenericPostprocess<CombineShader>,
-    pub post_processing_fbos: FboMap,
+    ssao: GenericPostprocess<SsaoShader>,
+    // the occlusion gets its own blurs since it is smaller than the bloom
+    ssao_horizontal_blur: GenericPostprocess<HorizontalBlurShader>,
+    ssao_vertical_blur: GenericPostprocess<VerticalBlurShader>,
+    ssao_apply: GenericPostprocess<SsaoApplyShader>,
+    ssao_noise_texture: u32,
+    light_shafts: GenericPostprocess<LightShaftsShader>,
+    atmosphere: GenericPostprocess<AtmosphereShader>,
+    // one per enabled post effect pass of the render graph
+    stages: Vec<EffectStage>,
 }
 
+// every step renders into whatever fbo is bound, the render graph binds the fbo it allocated for the step (or the screen)
 impl PostProcessing {
-    pub fn new(quad_model: QuadModel, display: &Display) -> Self {
-        let screen_size = display.get_size();
-        let width = screen_size.width;
-        let height = screen_size.height;
+    // the bloom is blurred at a fraction of the screen size
+    pub const BLOOM_DOWNSCALE: usize = 5;
+    const SSAO_SEED: u64 = 0x55a0;
 
-        let horizontal_blur = GenericPostprocess::new(HorizontalBlurShader::new(width / 5), 
-            Some(FramebufferObject::new(width / 5, height / 5, FboFlags::COLOR_TEX, 1)));
-        let vertical_blur = GenericPostprocess::new(VerticalBlurShader::new(height / 5), 
-            Some(FramebufferObject::new(width / 5, height / 5, FboFlags::COLOR_TEX, 1)));
+    pub fn new(quad_model: QuadModel, display: &Display) -> Self {
+        let render_size = display.get_render_size();
+        let width = render_size.width;
+        let height = render_size.height;
 
-        // this final step upscales this image back to screen size
-        let contrast_changer = GenericPostprocess::new(ContrastShader::new(), None);
+        let horizontal_blur = GenericPostprocess::new(HorizontalBlurShader::new(width / Self::BLOOM_DOWNSCALE), None);
+        let vertical_blur = GenericPostprocess::new(VerticalBlurShader::new(height / Self::BLOOM_DOWNSCALE), None);
 
         // shaders required for bloom effect
-        let combine_shader = GenericPostprocess::new(CombineShader::new(), Some(FramebufferObject::new(width, height, FboFlags::COLOR_TEX, 1)));
+        let combine_shader = GenericPostprocess::new(CombineShader::new(), None);
         // no need for brightness filter .. we are using mutliple render targets to get a brightness texture
         // let brightness_filter = GenericPostprocess::new(BrightnessFilterShader::new(), Some(FramebufferObject::new(width / 2, height / 2, FboFlags::COLOR_TEX, 1)));
 
-        let post_processing_fbos = FboMap::new_postprocessing_fbos(display);
+        let kernel = SsaoKernel::new(Self::SSAO_SEED);
+        let ssao_noise_texture = PostProcessing::create_noise_texture(&kernel);
+        let ssao = GenericPostprocess::new(SsaoShader::new(kernel, &display.projection_matrix), None);
+        let ssao_horizontal_blur = GenericPostprocess::new(HorizontalBlurShader::new(width / SsaoKernel::DOWNSCALE), None);
+        let ssao_vertical_blur = GenericPostprocess::new(VerticalBlurShader::new(height / SsaoKernel::DOWNSCALE), None);
+        let ssao_apply = GenericPostprocess::new(SsaoApplyShader::new(SsaoKernel::STRENGTH), None);
+
+        let light_shafts = GenericPostprocess::new(LightShaftsShader::new(&display.projection_matrix), None);
+        let atmosphere = GenericPostprocess::new(AtmosphereShader::new(), None);
 
         PostProcessing {
             quad_model,
-            contrast_changer,
             horizontal_blur,
             vertical_blur,
             //brightness_filter,
             combine_shader,
-            post_processing_fbos,
+            ssao,
+            ssao_horizontal_blur,
+            ssao_vertical_blur,
+            ssao_apply,
+            ssao_noise_texture,
+            light_shafts,
+            atmosphere,
+            stages: Vec::new(),
         }
     }
 
-    // here we setup our chain of post processing steps
-    pub fn do_post_processing(&mut self, display: &Display) {
-        let camera_texture_fbo = self.post_processing_fbos.fbos.get_mut(FboMap::CAMERA_TEXTURE_FBO).expect("A camera texture must be present for postprocessing");
-        let camera_texture = camera_texture_fbo.color_texture(0).expect("A camera texture must be present for postprocessing");
+    // the rotations are read with texelFetch so the filtering doesn't matter, they tile over the screen by the pixel position
+    fn create_noise_texture(kernel: &SsaoKernel) -> u32 {
+        let texture_id = gl::gen_texture();
+        gl::bind_texture(gl::TEXTURE_2D, texture_id);
+        gl::tex_image_2d_with_internal_format(gl::TEXTURE_2D, 0, gl::RGB, gl::RGB16F, SsaoKernel::NOISE_SIZE, SsaoKernel::NOISE_SIZE, gl::FLOAT, &kernel.noise_data());
+        gl::tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST);
+        gl::tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST);
+        gl::bind_texture(gl::TEXTURE_2D, 0);
+        texture_id
+    }
+
+    // builds the shaders of the effects and switches on a post effect pass for each of them
+    // the scene is always rendered in linear hdr so the stack must have a tonemapping even if it has nothing else
+    // an invalid stack leaves the current one in place
+    pub fn configure(&mut self, effects: &[PostEffect], render_graph: &mut RenderGraph) -> Result<(), String> {
+        PostEffect::validate_stack(effects)?;
+        for stage in self.stages.drain(..) {
+            if let EffectStage::ColorGrading(_, lut_texture) = stage {
+                gl::delete_texture(lut_texture);
+            }
+        }
+        self.stages = effects.iter().map(PostProcessing::create_stage).collect();
+
+        for slot in 0..PostEffect::MAX_EFFECTS {
+            render_graph.set_enabled(RenderPass::PostEffect(slot), slot < effects.len());
+        }
+        render_graph.set_multisampling(!PostEffect::uses_fxaa(effects));
+        Ok(())
+    }
+
+    fn create_stage(effect: &PostEffect) -> EffectStage {
+        match effect {
+            PostEffect::Contrast { amount } => EffectStage::Contrast(GenericPostprocess::new(ContrastShader::new(*amount), None)),
+            PostEffect::DepthOfField { focus_distance, focus_range, max_blur } => {
+                let shader = DepthOfFieldShader::new(Display::NEAR.abs(), Display::FAR.abs(), *focus_distance, *focus_range, *max_blur);
+                EffectStage::DepthOfField(GenericPostprocess::new(shader, None))
+            },
+            PostEffect::Tonemap { operator, exposure, gamma } => EffectStage::Tonemap(GenericPostprocess::new(TonemapShader::new(*operator, *exposure, *gamma), None)),
+            PostEffect::ColorGrading { lut, strength } => {
+                EffectStage::ColorGrading(GenericPostprocess::new(ColorGradingShader::new(*strength), None), PostProcessing::create_lut_texture(lut))
+            },
+            PostEffect::Vignette { radius, softness, strength } => EffectStage::Vignette(GenericPostprocess::new(VignetteShader::new(*radius, *softness, *strength), None)),
+            PostEffect::ChromaticAberration { strength } => EffectStage::ChromaticAberration(GenericPostprocess::new(ChromaticAberrationShader::new(*strength), None)),
+            PostEffect::FilmGrain { strength } => EffectStage::FilmGrain(GenericPostprocess::new(FilmGrainShader::new(*strength), None)),
+            PostEffect::Fxaa => EffectStage::Fxaa(GenericPostprocess::new(FxaaShader::new(), None)),
+        }
+    }
+
+    fn create_lut_texture(lut: &ColorLut) -> u32 {
+        let texture_id = gl::gen_texture();
+        gl::bind_texture(gl::TEXTURE_3D, texture_id);
+        gl::tex_image_3d(gl::TEXTURE_3D, 0, gl::RGB, gl::RGB16F, lut.size, lut.size, lut.size, gl::FLOAT, &lut.data);
+        // the filtering blends between the entries, the edges must not wrap around to the other end of the table
+        gl::tex_parameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+        gl::tex_parameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+        gl::tex_parameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        gl::tex_parameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        gl::tex_parameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE);
+        gl::bind_texture(gl::TEXTURE_3D, 0);
+        texture_id
+    }
+
+    // the blur steps are sized by the render size so they follow the window and the resolution scale
+    pub fn resize(&mut self, display: &Display) {
+        let render_size = display.get_render_size();
+        self.horizontal_blur.update_shader(|shader| shader.set_viewport_width(render_size.width / Self::BLOOM_DOWNSCALE));
+        self.vertical_blur.update_shader(|shader| shader.set_viewport_height(render_size.height / Self::BLOOM_DOWNSCALE));
+        self.ssao_horizontal_blur.update_shader(|shader| shader.set_viewport_width(render_size.width / SsaoKernel::DOWNSCALE));
+        self.ssao_vertical_blur.update_shader(|shader| shader.set_viewport_height(render_size.height / SsaoKernel::DOWNSCALE));
+        // the aspect ratio changed with the window
+        self.ssao.update_shader(|shader| shader.set_projection_matrix(&display.projection_matrix));
+        self.light_shafts.update_shader(|shader| shader.set_projection_matrix(&display.projection_matrix));
+    }
+
+    // the depth texture is the one of the resolved camera fbo
+    pub fn compute_ssao(&mut self, depth_texture: u32, display: &Display) {
+        self.start();
+        gl::active_texture(gl::TEXTURE1);
+        gl::bind_texture(gl::TEXTURE_2D, self.ssao_noise_texture);
+        self.ssao.render_with_one_input(depth_texture, display);
+        self.end();
+    }
+
+    pub fn blur_ssao_horizontally(&mut self, ssao_texture: u32, display: &Display) {
+        self.start();
+        self.ssao_horizontal_blur.render_with_one_input(ssao_texture, display);
+        self.end();
+    }
+
+    pub fn blur_ssao_vertically(&mut self, ssao_texture: u32, display: &Display) {
+        self.start();
+        self.ssao_vertical_blur.render_with_one_input(ssao_texture, display);
+        self.end();
+    }
+
+    pub fn apply_ssao(&mut self, scene_texture: u32, ssao_texture: u32, display: &Display) {
+        self.start();
+        self.ssao_apply.render_with_two_inputs(scene_texture, ssao_texture, display);
+        self.end();
+    }
 
-        let camera_brightness_fbo = self.post_processing_fbos.fbos.get_mut(FboMap::CAMERA_BRIGHTNESS_FBO).expect("A camera brightness texture must be present for bloom effect");
-        let camera_brightness = camera_brightness_fbo.color_texture(0).expect("A camera texture must be present for postprocessing");
-        
+    // the depth texture is the one of the resolved camera fbo, the shafts come from the directional light that casts the shadows
+    pub fn render_light_shafts(&mut self, depth_texture: u32, light_shafts: &LightShafts, camera: &Camera, lights: &[Light], display: &Display) {
+        let view_projection = &display.projection_matrix * &Matrix4f::create_view_matrix(camera);
+        let sun = PostProcessing::sun(lights).and_then(|(to_sun, _)| LightShafts::sun_on_screen(&view_projection, &to_sun));
+        let (sun_position, sun_visibility) = sun.unwrap_or_default();
         self.start();
-        
-        self.horizontal_blur.render_with_one_input(camera_brightness, display);
-        self.vertical_blur.render_with_one_input(self.horizontal_blur.get_output_texture().unwrap(), display);
-        self.combine_shader.render_with_two_inputs(camera_texture, self.vertical_blur.get_output_texture().unwrap(), display);
-        self.contrast_changer.render_with_one_input(self.combine_shader.get_output_texture().unwrap(), display);
+        self.light_shafts.update_shader(|shader| {
+            shader.set_light_shafts(light_shafts);
+            shader.set_sun(&sun_position, sun_visibility);
+        });
+        self.light_shafts.render_with_one_input(depth_texture, display);
+        self.end();
+    }
+
+    // fogs the scene and adds the light shafts, the shafts texture is only read when the atmosphere has them
+    // daylight goes from 0 at night to 1 during the day, see Atmosphere::daylight and ProceduralSky::daylight
+    pub fn apply_atmosphere(&mut self, scene_texture: u32, depth_texture: u32, light_shafts_texture: u32, atmosphere: &Atmosphere, fog_color: &Vector3f, daylight: f32, 
+                            camera: &Camera, lights: &[Light], display: &Display) {
+        let view_projection = &display.projection_matrix * &Matrix4f::create_view_matrix(camera);
+        // the sun only lights up the fog and makes shafts during the day
+        let (to_sun, sun_color) = match PostProcessing::sun(lights) {
+            Some((to_sun, color)) => (to_sun, color * daylight),
+            None => (Vector3f::POS_Y_AXIS, Vector3f::zero()),
+        };
+        self.start();
+        self.atmosphere.update_shader(|shader| {
+            shader.set_camera(&view_projection, &camera.position);
+            shader.set_fog(atmosphere.fog.as_ref(), fog_color);
+            shader.set_sun(&to_sun, &sun_color);
+            shader.set_light_shafts_strength(atmosphere.light_shafts.as_ref().map(|shafts| shafts.strength));
+        });
+        gl::active_texture(gl::TEXTURE2);
+        gl::bind_texture(gl::TEXTURE_2D, light_shafts_texture);
+        self.atmosphere.render_with_two_inputs(scene_texture, depth_texture, display);
+        self.end();
+    }
+
+    // the direction towards the directional light that casts the shadows (the opposite of where its light goes) and its color
+    fn sun(lights: &[Light]) -> Option<(Vector3f, &Vector3f)> {
+        let sun = &lights[Light::shadow_caster(lights)?];
+        let mut to_sun = -sun.direction()?;
+        to_sun.normalize();
+        Some((to_sun, &sun.color))
+    }
 
+    pub fn blur_horizontally(&mut self, source_texture: u32, display: &Display) {
+        self.start();
+        self.horizontal_blur.render_with_one_input(source_texture, display);
+        self.end();
+    }
+
+    pub fn blur_vertically(&mut self, source_texture: u32, display: &Display) {
+        self.start();
+        self.vertical_blur.render_with_one_input(source_texture, display);
+        self.end();
+    }
+
+    pub fn combine_bloom(&mut self, scene_texture: u32, bloom_texture: u32, display: &Display) {
+        self.start();
+        self.combine_shader.render_with_two_inputs(scene_texture, bloom_texture, display);
+        self.end();
+    }
+
+    // the depth texture is the one of the resolved camera fbo, only the depth of field reads it
+    pub fn render_effect(&mut self, slot: usize, source_texture: u32, depth_texture: u32, display: &Display) {
+        self.start();
+        let stage = self.stages.get_mut(slot).expect("Every enabled post effect pass must have a configured effect");
+        match stage {
+            EffectStage::Contrast(effect) => effect.render_with_one_input(source_texture, display),
+            EffectStage::DepthOfField(effect) => effect.render_with_two_inputs(source_texture, depth_texture, display),
+            EffectStage::Tonemap(effect) => effect.render_with_one_input(source_texture, display),
+            EffectStage::ColorGrading(effect, lut_texture) => {
+                gl::active_texture(gl::TEXTURE1);
+                gl::bind_texture(gl::TEXTURE_3D, *lut_texture);
+                effect.render_with_one_input(source_texture, display);
+            },
+            EffectStage::Vignette(effect) => effect.render_with_one_input(source_texture, display),
+            EffectStage::ChromaticAberration(effect) => effect.render_with_one_input(source_texture, display),
+            EffectStage::FilmGrain(effect) => {
+                let elapsed = display.wall_clock.elapsed;
+                effect.update_shader(|shader| shader.set_time(elapsed));
+                effect.render_with_one_input(source_texture, display);
+            },
+            EffectStage::Fxaa(effect) => effect.render_with_one_input(source_texture, display),
+        }
         self.end();
-        display.restore_default_framebuffer();
     }
 
     fn start(&mut self) {
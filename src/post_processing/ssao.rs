@@ -0,0 +1,79 @@
+use rand::prelude::*;
+use rand::SeedableRng;
+use crate::math::Vector3f;
+
+// the points the ssao shader samples around every pixel, they are in the tangent space of the surface with the normal along z
+// so they all sit in the hemisphere above it
+pub struct SsaoKernel {
+    pub samples: Vec<Vector3f>,
+    // random rotations of the kernel around the normal, tiled over the screen so neighbouring pixels don't sample the same spots
+    // the blur afterwards smears the pattern out
+    pub noise: Vec<Vector3f>,
+}
+
+impl SsaoKernel {
+    // has to match the size of the kernel array in the shader
+    pub const KERNEL_SIZE: usize = 32;
+    pub const NOISE_SIZE: usize = 4;
+    // view space radius of the hemisphere
+    pub const RADIUS: f32 = 1.5;
+    // keeps flat surfaces from occluding themselves because of the depth precision
+    pub const BIAS: f32 = 0.05;
+    // how dark the fully occluded parts get
+    pub const STRENGTH: f32 = 0.8;
+    // the occlusion is computed at a fraction of the screen size
+    pub const DOWNSCALE: usize = 2;
+
+    // seeded so the pattern is the same every run
+    pub fn new(seed: u64) -> SsaoKernel {
+        let mut rng: StdRng = SeedableRng::seed_from_u64(seed);
+        let samples = (0..SsaoKernel::KERNEL_SIZE).map(|i| {
+            let mut sample = Vector3f::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), rng.gen_range(0.0, 1.0));
+            sample.normalize();
+            // more samples close to the pixel where the occlusion matters most
+            let t = i as f32 / SsaoKernel::KERNEL_SIZE as f32;
+            sample * (rng.gen_range(0.0, 1.0) * (0.1 + 0.9 * t * t))
+        }).collect();
+        let noise = (0..SsaoKernel::NOISE_SIZE * SsaoKernel::NOISE_SIZE)
+            .map(|_| Vector3f::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), 0.0))
+            .collect();
+        SsaoKernel {
+            samples,
+            noise,
+        }
+    }
+
+    // rgb triplets for the noise texture
+    pub fn noise_data(&self) -> Vec<f32> {
+        self.noise.iter().flat_map(|rotation| vec![rotation.x, rotation.y, rotation.z]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernel_in_hemisphere() {
+        let kernel = SsaoKernel::new(7);
+        assert_eq!(kernel.samples.len(), SsaoKernel::KERNEL_SIZE);
+        for sample in kernel.samples.iter() {
+            assert!(sample.z >= 0.0, "{:?} is below the surface", sample);
+            assert!(sample.length() <= 1.0 + 1e-5, "{:?} is outside the hemisphere", sample);
+        }
+        // the first samples stay close to the pixel
+        assert!(kernel.samples[0].length() <= 0.1 + 1e-5);
+    }
+
+    #[test]
+    fn test_noise_rotates_around_normal() {
+        let kernel = SsaoKernel::new(7);
+        assert_eq!(kernel.noise.len(), 16);
+        assert!(kernel.noise.iter().all(|rotation| rotation.z == 0.0));
+        assert_eq!(kernel.noise_data().len(), 3 * 16);
+        // same seed same pattern
+        let again = SsaoKernel::new(7);
+        assert_eq!(kernel.samples[5].x, again.samples[5].x);
+        assert_eq!(kernel.noise[3].y, again.noise[3].y);
+    }
+}
\ No newline at end of file
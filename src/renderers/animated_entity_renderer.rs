@@ -5,6 +5,11 @@ use crate::entities::{
     Camera,    
 };
 use crate::shaders::AnimatedModelShader;
+use super::light_clusters::LightClusters;
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
+};
 use crate::math::{
     Matrix4f,
 };
@@ -23,7 +28,10 @@ pub struct AnimatedEntityRenderer {
 impl AnimatedEntityRenderer {
     
     pub fn new(projection_matrix: &Matrix4f) -> Self {     
-        let shader = AnimatedModelShader::new();
+        let mut shader = AnimatedModelShader::new();
+        shader.start();
+        shader.connect_texture_units();
+        shader.stop();
         let mut proj_matrix = Matrix4f::identity();
         proj_matrix.post_multiply_in_place(projection_matrix);
         let view_matrix = Matrix4f::identity();
@@ -36,19 +44,31 @@ impl AnimatedEntityRenderer {
             accumulator: AccumulatedJointTransforms::new(),
         }
     }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        let mut proj_matrix = Matrix4f::identity();
+        proj_matrix.post_multiply_in_place(projection_matrix);
+        self.proj_matrix = proj_matrix;
+    }
     
-    pub fn render_entities(&mut self, entities: &Vec<AnimatedEntity>, camera: &Camera) {
+    pub fn render_entities(&mut self, entities: &Vec<AnimatedEntity>, camera: &Camera, light_clusters: &LightClusters, shadow_params: &ShadowParams, point_shadows: &PointShadows) {
         for entity in entities {
-            self.render(entity, camera);
+            self.render(entity, camera, light_clusters, shadow_params, point_shadows);
         }
     }
 
-    pub fn render(&mut self, animated_entity: &AnimatedEntity, camera: &Camera) {
+    pub fn render(&mut self, animated_entity: &AnimatedEntity, camera: &Camera, light_clusters: &LightClusters, shadow_params: &ShadowParams, point_shadows: &PointShadows) {
         self.shader.start();
         self.view_matrix = Matrix4f::create_view_matrix(camera);
+        self.shader.load_view_matrix(&self.view_matrix);
+        self.shader.load_light_clusters(light_clusters);
+        self.shader.load_shadow_params(shadow_params);
+        self.shader.load_point_shadows(point_shadows);
 
         gl::active_texture(gl::TEXTURE0);
         gl::bind_texture(gl::TEXTURE_2D, animated_entity.model.tex_id.unwrap());
+        gl::active_texture(gl::TEXTURE1);
+        gl::bind_texture(gl::TEXTURE_2D_ARRAY, shadow_params.shadow_map_texture);
 
         gl::bind_vertex_array(animated_entity.model.raw_model.vao_id);
         gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
@@ -65,6 +85,7 @@ impl AnimatedEntityRenderer {
         self.mvp_matrix.pre_multiply_in_place(&self.view_matrix);        
         self.mvp_matrix.pre_multiply_in_place(&self.proj_matrix);
         self.shader.load_mvp_matrix(&self.mvp_matrix);
+        self.shader.load_transform_matrix(&transform);
 
         animated_entity.model.root_joint.collect_transforms(&mut self.accumulator);
         self.shader.load_joint_transforms(&self.accumulator);
@@ -0,0 +1,159 @@
+use std::f32::consts::PI;
+
+// the brdf of the pbr shaders and the image based lighting bake on the cpu, per color channel
+// keep in sync with pbr/pbrFrag.glsl and ibl/brdfLutFrag.glsl
+
+// the normal distribution, how many microfacets face the half vector
+pub fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * denominator * denominator)
+}
+
+// smith with the schlick approximation for one direction, k is the one for direct lights
+pub fn geometry_schlick_ggx(n_dot_x: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    n_dot_x / (n_dot_x * (1.0 - k) + k)
+}
+
+// same with the k that image based lighting uses
+pub fn geometry_schlick_ggx_ibl(n_dot_x: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    n_dot_x / (n_dot_x * (1.0 - k) + k)
+}
+
+pub fn fresnel_schlick(cos_theta: f32, f0: f32) -> f32 {
+    f0 + (1.0 - f0) * (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+// rough surfaces reflect less at grazing angles, used for the environment where there is no half vector
+pub fn fresnel_schlick_roughness(cos_theta: f32, f0: f32, roughness: f32) -> f32 {
+    f0 + ((1.0 - roughness).max(f0) - f0) * (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+// low discrepancy points on the unit square, the second coordinate is the bits of i mirrored behind the point
+pub fn hammersley(i: u32, n: u32) -> (f32, f32) {
+    (i as f32 / n as f32, i.reverse_bits() as f32 * 2.328_306_4e-10)
+}
+
+// a half vector around the normal (0, 0, 1), denser where the ggx distribution has more microfacets
+pub fn importance_sample_ggx(xi: (f32, f32), roughness: f32) -> (f32, f32, f32) {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.0;
+    let cos_theta = ((1.0 - xi.1) / (1.0 + (a * a - 1.0) * xi.1)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    (phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta)
+}
+
+// the second half of the split sum, what the brdf lut holds for a view angle and roughness
+// a scale and a bias of the fresnel reflectance at normal incidence
+pub fn integrate_brdf(n_dot_v: f32, roughness: f32, sample_count: u32) -> (f32, f32) {
+    let n_dot_v = n_dot_v.max(0.001);
+    let view = ((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+
+    let mut scale = 0.0;
+    let mut bias = 0.0;
+    for i in 0..sample_count {
+        let half_vector = importance_sample_ggx(hammersley(i, sample_count), roughness);
+        let v_dot_h = view.0 * half_vector.0 + view.1 * half_vector.1 + view.2 * half_vector.2;
+        // the view reflected around the half vector, only its height above the surface matters
+        let n_dot_l = (2.0 * v_dot_h * half_vector.2 - view.2).max(0.0);
+        let n_dot_h = half_vector.2.max(0.0);
+        let v_dot_h = v_dot_h.max(0.0);
+        if n_dot_l > 0.0 {
+            let geometry = geometry_schlick_ggx_ibl(n_dot_v, roughness) * geometry_schlick_ggx_ibl(n_dot_l, roughness);
+            let visibility = geometry * v_dot_h / (n_dot_h * n_dot_v);
+            let fresnel = (1.0 - v_dot_h).powi(5);
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+    (scale / sample_count as f32, bias / sample_count as f32)
+}
+
+// the roughness each mip level of the prefiltered map is baked for, the pbr shader picks the level back from the roughness
+pub fn prefiltered_roughness(level: usize, levels: usize) -> f32 {
+    level as f32 / (levels - 1) as f32
+}
+
+pub fn prefiltered_level(roughness: f32, levels: usize) -> f32 {
+    roughness * (levels - 1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hammersley() {
+        assert_eq!(hammersley(0, 4), (0.0, 0.0));
+        assert_eq!(hammersley(1, 4), (0.25, 0.5));
+        assert_eq!(hammersley(2, 4), (0.5, 0.25));
+        assert_eq!(hammersley(3, 4), (0.75, 0.75));
+    }
+
+    #[test]
+    fn test_fresnel() {
+        for f0 in [0.04, 0.5, 1.0].iter() {
+            assert!((fresnel_schlick(1.0, *f0) - f0).abs() < 1e-6);
+            assert!((fresnel_schlick(0.0, *f0) - 1.0).abs() < 1e-6);
+            // rough surfaces don't go all the way to white at the grazing angles
+            assert!((fresnel_schlick_roughness(0.0, *f0, 0.8) - f0.max(0.2)).abs() < 1e-6);
+            assert!((fresnel_schlick_roughness(1.0, *f0, 0.8) - f0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_distribution_is_normalized() {
+        // the projected area of the microfacets adds up to the area of the surface
+        let steps = 20_000;
+        for roughness in [0.3, 0.6, 1.0].iter() {
+            let d_theta = 0.5 * PI / steps as f32;
+            let mut area = 0.0;
+            for i in 0..steps {
+                let theta = (i as f32 + 0.5) * d_theta;
+                area += distribution_ggx(theta.cos(), *roughness) * theta.cos() * theta.sin() * d_theta * 2.0 * PI;
+            }
+            assert!((area - 1.0).abs() < 0.01, "roughness {} covers {}", roughness, area);
+        }
+    }
+
+    #[test]
+    fn test_geometry() {
+        for roughness in [0.0, 0.5, 1.0].iter() {
+            assert!((geometry_schlick_ggx(1.0, *roughness) - 1.0).abs() < 1e-6);
+            assert!((geometry_schlick_ggx_ibl(1.0, *roughness) - 1.0).abs() < 1e-6);
+            assert!(geometry_schlick_ggx(0.2, *roughness) <= 1.0);
+        }
+        // rougher surfaces shadow themselves more
+        assert!(geometry_schlick_ggx_ibl(0.3, 0.9) < geometry_schlick_ggx_ibl(0.3, 0.2));
+    }
+
+    #[test]
+    fn test_brdf_lut() {
+        // a mirror seen head on reflects exactly f0
+        let (scale, bias) = integrate_brdf(1.0, 0.0, 256);
+        assert!((scale - 1.0).abs() < 1e-3 && bias.abs() < 1e-3, "mirror gives {} {}", scale, bias);
+        for roughness in [0.1, 0.5, 1.0].iter() {
+            for n_dot_v in [0.1, 0.5, 1.0].iter() {
+                let (scale, bias) = integrate_brdf(*n_dot_v, *roughness, 512);
+                // no surface reflects more than what comes in
+                assert!(scale >= 0.0 && bias >= 0.0 && scale + bias <= 1.0 + 1e-3, "{} {} gives {} {}", n_dot_v, roughness, scale, bias);
+            }
+            // fresnel takes over at the grazing angles
+            assert!(integrate_brdf(0.1, *roughness, 512).1 > integrate_brdf(1.0, *roughness, 512).1);
+        }
+    }
+
+    #[test]
+    fn test_prefiltered_levels() {
+        let levels = 5;
+        assert_eq!(prefiltered_roughness(0, levels), 0.0);
+        assert_eq!(prefiltered_roughness(levels - 1, levels), 1.0);
+        for level in 0..levels {
+            assert!((prefiltered_level(prefiltered_roughness(level, levels), levels) - level as f32).abs() < 1e-6);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,82 @@
+use std::fmt;
+
+// how many objects a pass tested against its volume and how many of them it didn't draw
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CullCounter {
+    pub tested: usize,
+    pub culled: usize,
+}
+
+impl CullCounter {
+    // passes the test result through so it can sit inside a filter
+    pub fn count(&mut self, visible: bool) -> bool {
+        self.tested += 1;
+        if !visible {
+            self.culled += 1;
+        }
+        visible
+    }
+
+    pub fn reset(&mut self) {
+        self.tested = 0;
+        self.culled = 0;
+    }
+
+    pub fn add(&mut self, other: &CullCounter) {
+        self.tested += other.tested;
+        self.culled += other.culled;
+    }
+}
+
+impl fmt::Display for CullCounter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} culled", self.culled, self.tested)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PassCulling {
+    pub entities: CullCounter,
+    pub terrain_chunks: CullCounter,
+}
+
+impl PassCulling {
+    // for passes that run more than once per frame
+    pub fn add(&mut self, other: &PassCulling) {
+        self.entities.add(&other.entities);
+        self.terrain_chunks.add(&other.terrain_chunks);
+    }
+}
+
+impl fmt::Display for PassCulling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "entities {}, terrain chunks {}", self.entities, self.terrain_chunks)
+    }
+}
+
+// the counters of the last frame. the shadow pass sums up its cascades, the point shadows sum up their lights
+// and the water passes sum up their planes
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CullingStats {
+    pub main: PassCulling,
+    pub reflection: PassCulling,
+    pub refraction: PassCulling,
+    pub reflection_probes: PassCulling,
+    pub shadow: PassCulling,
+    pub point_shadow: CullCounter,
+    pub particles: CullCounter,
+    pub water_tiles: CullCounter,
+}
+
+impl fmt::Display for CullingStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "main: {}", self.main)?;
+        writeln!(f, "reflection: {}", self.reflection)?;
+        writeln!(f, "refraction: {}", self.refraction)?;
+        writeln!(f, "reflection probes: {}", self.reflection_probes)?;
+        writeln!(f, "shadow: {}", self.shadow)?;
+        writeln!(f, "point shadow: entities {}", self.point_shadow)?;
+        writeln!(f, "particles: {}", self.particles)?;
+        write!(f, "water tiles: {}", self.water_tiles)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::f32;
+use crate::gl;
+use crate::display::framebuffers::FramebufferObject;
+use crate::entities::{
+    Camera,
+    Entity,
+    Light,
+    Terrain,
+    Weather,
+};
+use crate::math::{
+    Matrix4f,
+    Vector4f,
+};
+use crate::models::{
+    RawModel,
+    TexturedModel,
+};
+use crate::shaders::DeferredLightingShader;
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
+};
+use super::entity_renderer::EntityRenderer;
+use super::normal_map_entity_renderer::NormalMapEntityRenderer;
+use super::terrain_renderer::TerrainRenderer;
+use super::light_clusters::LightClusters;
+use super::culling::CullCounter;
+
+// deferred shading of the opaque geometry of the main camera
+// the entities, normal mapped entities and terrain write their surface into the g-buffer and the lighting runs once per pixel afterwards:
+// one full screen pass for the lights that reach everything (plus the ambient light and glow) and a sphere for every other light that only touches the pixels in its range
+// water, particles, the pbr materials, the env mapped and the animated entities are still rendered forward on top of the result
+// the g-buffer attachments (all rgba16f):
+//   0 albedo
+//   1 world space normal, w is unused
+//   2 reflectivity, shine damper, glow flag, shadow bias
+//   3 emissive color
+//   + depth which we turn back into the world position
+pub struct DeferredRenderer {
+    entity_renderer: EntityRenderer,
+    normal_map_entity_renderer: NormalMapEntityRenderer,
+    terrain_renderer: TerrainRenderer,
+    fullscreen_shader: DeferredLightingShader,
+    light_volume_shader: DeferredLightingShader,
+    projection_matrix: Matrix4f,
+    // the full screen triangle is made from gl_VertexID but the core profile still wants a vao bound
+    empty_vao: u32,
+    light_volume_vao: u32,
+    // positions and indices
+    light_volume_buffers: [u32; 2],
+    light_volume_index_count: usize,
+}
+
+impl DeferredRenderer {
+    // the full screen lighting shader has an array of this size
+    pub const MAX_GLOBAL_LIGHTS: usize = 8;
+    const LIGHT_VOLUME_RINGS: usize = 8;
+    const LIGHT_VOLUME_SEGMENTS: usize = 12;
+
+    pub fn new(projection_matrix: &Matrix4f) -> DeferredRenderer {
+        let mut fullscreen_shader = DeferredLightingShader::new_fullscreen();
+        fullscreen_shader.start();
+        fullscreen_shader.connect_texture_units();
+        fullscreen_shader.stop();
+        let mut light_volume_shader = DeferredLightingShader::new_light_volume();
+        light_volume_shader.start();
+        light_volume_shader.connect_texture_units();
+        light_volume_shader.stop();
+
+        let empty_vao = gl::gen_vertex_array();
+
+        let (positions, indices) = DeferredRenderer::light_volume_mesh(DeferredRenderer::LIGHT_VOLUME_RINGS, DeferredRenderer::LIGHT_VOLUME_SEGMENTS);
+        let light_volume_vao = gl::gen_vertex_array();
+        gl::bind_vertex_array(light_volume_vao);
+        let position_buffer = gl::gen_buffer();
+        gl::bind_buffer(gl::ARRAY_BUFFER, position_buffer);
+        gl::buffer_data(gl::ARRAY_BUFFER, &positions, gl::STATIC_DRAW);
+        gl::vertex_attrib_pointer(RawModel::POS_ATTRIB, 3, gl::FLOAT);
+        let index_buffer = gl::gen_buffer();
+        gl::bind_buffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer);
+        gl::buffer_data(gl::ELEMENT_ARRAY_BUFFER, &indices, gl::STATIC_DRAW);
+        gl::bind_vertex_array(0);
+        gl::bind_buffer(gl::ARRAY_BUFFER, 0);
+
+        DeferredRenderer {
+            entity_renderer: EntityRenderer::new_gbuffer(projection_matrix),
+            normal_map_entity_renderer: NormalMapEntityRenderer::new_gbuffer(projection_matrix),
+            terrain_renderer: TerrainRenderer::new_gbuffer(projection_matrix),
+            fullscreen_shader,
+            light_volume_shader,
+            projection_matrix: projection_matrix.clone(),
+            empty_vao,
+            light_volume_vao,
+            light_volume_buffers: [position_buffer, index_buffer],
+            light_volume_index_count: indices.len(),
+        }
+    }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.entity_renderer.update_projection_matrix(projection_matrix);
+        self.normal_map_entity_renderer.update_projection_matrix(projection_matrix);
+        self.terrain_renderer.update_projection_matrix(projection_matrix);
+        self.projection_matrix = projection_matrix.clone();
+    }
+
+    pub fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.entity_renderer.update_weather(weather, time);
+        self.normal_map_entity_renderer.update_weather(weather, time);
+        self.terrain_renderer.update_weather(weather, time);
+    }
+
+    // the g-buffer has to be bound and cleared, the lighting params are only passed on to the renderers which need them for start_render
+    pub fn render_geometry(&mut self, camera: &Camera, entities: &HashMap<&TexturedModel, Vec<&Entity>>, normal_mapped_entities: &HashMap<&TexturedModel, Vec<&Entity>>,
+                terrains: &Vec<Terrain>, light_clusters: &LightClusters, shadow_params: &ShadowParams, point_shadows: &PointShadows) {
+
+        // only the main camera is deferred so nothing gets clipped
+        let no_clip_plane = Vector4f::new(0.0, -1.0, 0.0, 10_000.0);
+
+        self.entity_renderer.start_render(light_clusters, camera, shadow_params, point_shadows);
+        for (textured_model, entity_vec) in entities.iter() {
+            self.entity_renderer.prepare_textured_model(textured_model, &no_clip_plane);
+            self.entity_renderer.render(textured_model, entity_vec);
+            self.entity_renderer.unprepare_textured_model(textured_model);
+        }
+        self.entity_renderer.stop_render();
+
+        self.normal_map_entity_renderer.start_render(light_clusters, camera, shadow_params, point_shadows);
+        for (textured_model, entity_vec) in normal_mapped_entities.iter() {
+            self.normal_map_entity_renderer.prepare_textured_model(textured_model, &no_clip_plane);
+            self.normal_map_entity_renderer.render(textured_model, entity_vec);
+            self.normal_map_entity_renderer.unprepare_textured_model(textured_model);
+        }
+        self.normal_map_entity_renderer.stop_render();
+
+        self.terrain_renderer.start_render(light_clusters, camera, shadow_params, point_shadows);
+        for terrain in terrains.iter() {
+            self.terrain_renderer.prepare_terrain(terrain, &no_clip_plane);
+            self.terrain_renderer.render(terrain, camera);
+            self.terrain_renderer.unprepare_terrain();
+        }
+        self.terrain_renderer.stop_render();
+    }
+
+    // terrain chunks the last geometry pass skipped
+    pub fn terrain_chunk_culling(&self) -> &CullCounter {
+        &self.terrain_renderer.chunk_culling
+    }
+
+    // lights the g-buffer into the currently bound fbo and copies its depth there
+    // the light clusters and the point shadow maps have to be bound already
+    pub fn render_lighting(&mut self, gbuffer: &FramebufferObject, camera: &Camera, lights: &[Light], light_clusters: &LightClusters,
+                shadow_params: &ShadowParams, point_shadows: &PointShadows) {
+
+        let view_matrix = Matrix4f::create_view_matrix(camera);
+        let view_projection = &self.projection_matrix * &view_matrix;
+
+        // see DeferredLightingShader::connect_texture_units for the units
+        for attachment in 0..4 {
+            gl::active_texture(gl::TEXTURE0 + attachment as u32);
+            gl::bind_texture(gl::TEXTURE_2D, gbuffer.color_texture(attachment).expect("The g-buffer must have color textures"));
+        }
+        gl::active_texture(gl::TEXTURE4);
+        gl::bind_texture(gl::TEXTURE_2D, gbuffer.depth_texture.expect("The g-buffer must have a depth texture"));
+        gl::active_texture(gl::TEXTURE5);
+        gl::bind_texture(gl::TEXTURE_2D_ARRAY, shadow_params.shadow_map_texture);
+
+        let (global_lights, volume_lights): (Vec<usize>, Vec<usize>) = (0..lights.len()).partition(|light| !lights[*light].range().is_finite());
+
+        // the full screen pass writes the depth of the g-buffer so that everything forward rendered afterwards depth tests against it
+        gl::depth_func(gl::ALWAYS);
+        self.fullscreen_shader.start();
+        self.fullscreen_shader.load_camera(&view_matrix, &view_projection, &camera.position);
+        self.fullscreen_shader.load_global_lights(&global_lights);
+        self.fullscreen_shader.load_light_clusters(light_clusters);
+        self.fullscreen_shader.load_shadow_params(shadow_params);
+        gl::bind_vertex_array(self.empty_vao);
+        gl::draw_arrays(gl::TRIANGLES, 0, 3);
+        self.fullscreen_shader.stop();
+        gl::depth_func(gl::LESS);
+
+        if !volume_lights.is_empty() {
+            // every light adds on top, the shader checks the range per pixel so the volumes need no depth test
+            // we draw the back faces so the volume doesn't disappear when the camera is inside of it
+            gl::disable(gl::DEPTH_TEST);
+            gl::depth_mask(false);
+            gl::enable(gl::BLEND);
+            gl::blend_func(gl::ONE, gl::ONE);
+            gl::cull_face(gl::FRONT);
+
+            self.light_volume_shader.start();
+            self.light_volume_shader.load_camera(&view_matrix, &view_projection, &camera.position);
+            self.light_volume_shader.load_point_shadows(point_shadows);
+            gl::bind_vertex_array(self.light_volume_vao);
+            gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+            for light in volume_lights.iter() {
+                self.light_volume_shader.load_volume_light(*light);
+                gl::draw_elements(gl::TRIANGLES, self.light_volume_index_count, gl::UNSIGNED_INT);
+            }
+            gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+            self.light_volume_shader.stop();
+
+            gl::helper::enable_backface_culling();
+            gl::disable(gl::BLEND);
+            gl::depth_mask(true);
+            gl::enable(gl::DEPTH_TEST);
+        }
+        gl::bind_vertex_array(0);
+    }
+
+    // uv sphere around the origin for the light volumes, returns the positions and the indices of counter clockwise triangles
+    // the flat triangles cut into a sphere through their corners so the radius is made big enough for the mesh to contain the unit sphere
+    pub fn light_volume_mesh(rings: usize, segments: usize) -> (Vec<f32>, Vec<u32>) {
+        assert!(rings >= 3 && segments >= 4, "The light volume is too coarse to contain the sphere");
+        let ring_step = f32::consts::PI / rings as f32;
+        let segment_step = 2.0 * f32::consts::PI / segments as f32;
+        let radius = 1.0 / ((ring_step + segment_step) / 2.0).cos();
+
+        // the poles plus a circle of vertices for every ring in between
+        let mut positions = vec![0.0, radius, 0.0];
+        for ring in 1..rings {
+            let (ring_sin, ring_cos) = (ring as f32 * ring_step).sin_cos();
+            for segment in 0..segments {
+                let (segment_sin, segment_cos) = (segment as f32 * segment_step).sin_cos();
+                positions.extend_from_slice(&[radius * ring_sin * segment_cos, radius * ring_cos, radius * ring_sin * segment_sin]);
+            }
+        }
+        positions.extend_from_slice(&[0.0, -radius, 0.0]);
+
+        let bottom = (positions.len() / 3 - 1) as u32;
+        let vertex = |ring: usize, segment: usize| (1 + (ring - 1) * segments + segment % segments) as u32;
+        let mut indices = Vec::new();
+        for segment in 0..segments {
+            indices.extend_from_slice(&[0, vertex(1, segment + 1), vertex(1, segment)]);
+            for ring in 1..rings - 1 {
+                let (top_left, top_right) = (vertex(ring, segment), vertex(ring, segment + 1));
+                let (bottom_left, bottom_right) = (vertex(ring + 1, segment), vertex(ring + 1, segment + 1));
+                indices.extend_from_slice(&[top_left, top_right, bottom_left, top_right, bottom_right, bottom_left]);
+            }
+            indices.extend_from_slice(&[vertex(rings - 1, segment), vertex(rings - 1, segment + 1), bottom]);
+        }
+        (positions, indices)
+    }
+}
+
+impl Drop for DeferredRenderer {
+    fn drop(&mut self) {
+        gl::delete_vertex_arrays(&[self.empty_vao, self.light_volume_vao]);
+        gl::delete_buffers(&self.light_volume_buffers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector3f;
+
+    #[test]
+    fn test_light_volume_mesh() {
+        for (rings, segments) in [(3, 4), (4, 6), (8, 12), (12, 24)].iter() {
+            let (positions, indices) = DeferredRenderer::light_volume_mesh(*rings, *segments);
+            assert_eq!(indices.len(), 3 * 2 * segments * (rings - 1));
+            let vertex = |i: u32| Vector3f::new(positions[3 * i as usize], positions[3 * i as usize + 1], positions[3 * i as usize + 2]);
+            for triangle in indices.chunks(3) {
+                let (a, b, c) = (vertex(triangle[0]), vertex(triangle[1]), vertex(triangle[2]));
+                let normal = (&b - &a).cross_prod(&(&c - &a));
+                // the triangle faces away from the center and its plane doesn't cut into the unit sphere
+                let plane_distance = normal.dot_product(&a) / normal.length();
+                assert!(plane_distance >= 1.0, "triangle {:?} of the {}x{} sphere is {} from the center", triangle, rings, segments, plane_distance);
+            }
+        }
+    }
+}
\ No newline at end of file
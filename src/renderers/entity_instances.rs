@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use crate::entities::Entity;
+use crate::gl;
+use crate::math::Matrix4f;
+use crate::models::TexturedModel;
+use super::particle_renderer::update_vbo;
+
+// draws all the entities of one model with a single instanced draw (or one per MAX_INSTANCES of them)
+// the transforms and atlas offsets get streamed into the instance vbo of the model before every draw
+pub struct EntityInstances {
+    instance_data: Vec<f32>,
+}
+
+impl EntityInstances {
+    pub fn new() -> EntityInstances {
+        EntityInstances {
+            instance_data: Vec::with_capacity(TexturedModel::MAX_INSTANCES * TexturedModel::INSTANCED_DATA_LENGTH),
+        }
+    }
+
+    // the model's vao has to be bound
+    pub fn enable_attribs() {
+        gl::enable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN1);
+        gl::enable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN2);
+        gl::enable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN3);
+        gl::enable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN4);
+        gl::enable_vertex_attrib_array(TexturedModel::ATLAS_OFFSET);
+    }
+
+    pub fn disable_attribs() {
+        gl::disable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN1);
+        gl::disable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN2);
+        gl::disable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN3);
+        gl::disable_vertex_attrib_array(TexturedModel::TRANSFORM_COLUMN4);
+        gl::disable_vertex_attrib_array(TexturedModel::ATLAS_OFFSET);
+    }
+
+    // every group is one model, so one bound vao and one run of instanced draws
+    pub fn group_by_model<'b, I>(entities: I) -> HashMap<&'b TexturedModel, Vec<&'b Entity>> where I: IntoIterator<Item = &'b Entity> {
+        let mut groups = HashMap::new();
+        for entity in entities {
+            groups.entry(&entity.model).or_insert_with(Vec::new).push(entity);
+        }
+        groups
+    }
+
+    pub fn render(&mut self, textured_model: &TexturedModel, entities: &[&Entity]) {
+        for batch in entities.chunks(TexturedModel::MAX_INSTANCES) {
+            EntityInstances::fill_instance_data(&mut self.instance_data, batch);
+            update_vbo(textured_model.instance_vbo, &self.instance_data);
+            gl::draw_elements_instanced(gl::TRIANGLES, textured_model.raw_model.vertex_count, gl::UNSIGNED_INT, batch.len());
+        }
+    }
+
+    // INSTANCED_DATA_LENGTH floats per entity in the layout of the instanced attributes
+    fn fill_instance_data(instance_data: &mut Vec<f32>, batch: &[&Entity]) {
+        instance_data.clear();
+        for entity in batch {
+            let transform_mat = Matrix4f::create_transform_matrix(&entity.position, &entity.rotation_deg, entity.scale);
+            // store column wise
+            for col in 0..4 {
+                for row in 0..4 {
+                    instance_data.push(transform_mat[row][col]);
+                }
+            }
+            let atlas_offset = entity.get_atlas_offset();
+            instance_data.push(atlas_offset.x);
+            instance_data.push(atlas_offset.y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{ModelBounds, Vector3f};
+    use crate::models::{ModelTexture, RawModel, TextureId};
+
+    fn model(vao_id: u32, number_of_rows_in_atlas: usize) -> TexturedModel {
+        TexturedModel {
+            raw_model: RawModel::new(vao_id, 6),
+            texture: ModelTexture { tex_id: TextureId::Loaded(vao_id), number_of_rows_in_atlas, ..Default::default() },
+            normal_map_tex_id: None,
+            extra_info_tex_id: None,
+            pbr_material: None,
+            bounds: ModelBounds::default(),
+            instance_vbo: 0,
+        }
+    }
+
+    fn entity(model: &TexturedModel, x: f32) -> Entity {
+        Entity::new(model.clone(), Vector3f::new(x, 0.0, 0.0), Vector3f::new(0.0, 0.0, 0.0), 1.0)
+    }
+
+    #[test]
+    fn test_group_by_model() {
+        let (tree, fern) = (model(1, 1), model(2, 1));
+        let entities = vec![entity(&tree, 0.0), entity(&fern, 1.0), entity(&tree, 2.0), entity(&fern, 3.0), entity(&tree, 4.0)];
+        let groups = EntityInstances::group_by_model(entities.iter());
+        assert_eq!(groups.len(), 2);
+        // the entities keep their order inside a group
+        let xs = |model: &TexturedModel| groups[model].iter().map(|entity| entity.position.x).collect::<Vec<_>>();
+        assert_eq!(xs(&tree), vec![0.0, 2.0, 4.0]);
+        assert_eq!(xs(&fern), vec![1.0, 3.0]);
+    }
+
+    #[test]
+    fn test_instance_data_layout() {
+        let atlas = model(1, 2);
+        let entity = Entity::new_with_texture_atlas(atlas, Vector3f::new(1.0, 2.0, 3.0), Vector3f::new(0.0, 0.0, 0.0), 2.0, 3);
+        let mut data = Vec::new();
+        EntityInstances::fill_instance_data(&mut data, &[&entity]);
+        assert_eq!(data.len(), TexturedModel::INSTANCED_DATA_LENGTH);
+        // the scale on the diagonal of the column major transform, the translation in the last column
+        assert_eq!((data[0], data[5], data[10]), (2.0, 2.0, 2.0));
+        assert_eq!(&data[12..16], &[1.0, 2.0, 3.0, 1.0]);
+        // atlas index 3 of a 2x2 atlas is the bottom right quarter
+        assert_eq!(&data[16..18], &[0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_instance_data_per_batch() {
+        let tree = model(1, 1);
+        let entities: Vec<_> = (0..2 * TexturedModel::MAX_INSTANCES + 3).map(|i| entity(&tree, i as f32)).collect();
+        let refs: Vec<_> = entities.iter().collect();
+        let mut data = Vec::new();
+        let lengths: Vec<_> = refs.chunks(TexturedModel::MAX_INSTANCES).map(|batch| {
+            EntityInstances::fill_instance_data(&mut data, batch);
+            data.len()
+        }).collect();
+        let per_entity = TexturedModel::INSTANCED_DATA_LENGTH;
+        assert_eq!(lengths, vec![TexturedModel::MAX_INSTANCES * per_entity, TexturedModel::MAX_INSTANCES * per_entity, 3 * per_entity]);
+        // the last batch starts where the previous one stopped
+        assert_eq!(data[12], (2 * TexturedModel::MAX_INSTANCES) as f32);
+    }
+}
\ No newline at end of file
@@ -1,12 +1,13 @@
 use crate::gl;
+use super::light_clusters::LightClusters;
+use super::entity_instances::EntityInstances;
 use crate::entities::{
     Entity,
     Camera,
-    Light,
+    Weather,
 };
 use crate::math::{
     Matrix4f,
-    Vector3f,
     Vector4f,
 };
 use crate::models::{
@@ -14,36 +15,61 @@ use crate::models::{
     RawModel,
 };
 use crate::shaders::StaticShader;
-use crate::shadows::shadow_params::ShadowParams;
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
+};
 
 pub struct EntityRenderer {
     shader: StaticShader,
+    instances: EntityInstances,
 }
 
 impl EntityRenderer {    
     
     pub fn new(projection_matrix: &Matrix4f) -> EntityRenderer {     
-        let mut shader = StaticShader::new();
+        EntityRenderer::with_shader(StaticShader::new(), projection_matrix)
+    }
+
+    // fills the g-buffer of the deferred renderer, the lighting params given to start_render are ignored by that shader
+    pub fn new_gbuffer(projection_matrix: &Matrix4f) -> EntityRenderer {
+        EntityRenderer::with_shader(StaticShader::new_gbuffer(), projection_matrix)
+    }
+
+    fn with_shader(mut shader: StaticShader, projection_matrix: &Matrix4f) -> EntityRenderer {
         shader.start();
         shader.load_projection_matrix(projection_matrix);
         shader.connect_texture_units();
         shader.stop();
         EntityRenderer {
             shader,
+            instances: EntityInstances::new(),
         }
     }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.shader.start();
+        self.shader.load_projection_matrix(projection_matrix);
+        self.shader.stop();
+    }
+
+    // the weather changes every frame so the master renderer loads it once at the start of it
+    pub fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.shader.start();
+        self.shader.load_weather(weather, time);
+        self.shader.stop();
+    }
     
-    pub fn start_render(&mut self, lights: &Vec<Light>, camera: &Camera, sky_color: &Vector3f, to_shadow_space: &Matrix4f, shadow_params: &ShadowParams) {
+    pub fn start_render(&mut self, light_clusters: &LightClusters, camera: &Camera, shadow_params: &ShadowParams, point_shadows: &PointShadows) {
         self.shader.start();
-        self.shader.load_lights(lights);
+        self.shader.load_light_clusters(light_clusters);
         self.shader.load_view_matrix(camera);
-        self.shader.load_sky_color(sky_color);
         
-        self.shader.load_to_shadowmap_space(to_shadow_space);
         self.shader.load_shadow_params(shadow_params);
+        self.shader.load_point_shadows(point_shadows);
 
         gl::active_texture(gl::TEXTURE1);
-        gl::bind_texture(gl::TEXTURE_2D, shadow_params.shadow_map_texture);
+        gl::bind_texture(gl::TEXTURE_2D_ARRAY, shadow_params.shadow_map_texture);
     }
 
     pub fn stop_render(&mut self) {
@@ -59,9 +85,11 @@ impl EntityRenderer {
         gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
         gl::enable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
         gl::enable_vertex_attrib_array(RawModel::NORMAL_ATTRIB);
+        EntityInstances::enable_attribs();
 
         self.shader.load_shine_variables(textured_model.texture.shine_damper, textured_model.texture.reflectivity);
         self.shader.load_uses_fake_lighting(textured_model.texture.uses_fake_lighting);
+        self.shader.load_sways_in_wind(textured_model.texture.sways_in_wind);
         self.shader.load_atlas_number_of_rows(textured_model.texture.number_of_rows_in_atlas);
 
         // clip plane for water 
@@ -81,13 +109,9 @@ impl EntityRenderer {
         };
     }
 
-    pub fn render(&mut self, entity: &Entity) {
-        // load transform matrix into shader
-        let transform_mat = Matrix4f::create_transform_matrix(&entity.position, &entity.rotation_deg, entity.scale);
-        self.shader.load_transformation_matrix(&transform_mat);
-        self.shader.load_atlas_offset(&entity.get_atlas_offset());
-        
-        gl::draw_elements(gl::TRIANGLES, entity.model.raw_model.vertex_count, gl::UNSIGNED_INT);
+    // the entities all have the prepared model
+    pub fn render(&mut self, textured_model: &TexturedModel, entities: &[&Entity]) {
+        self.instances.render(textured_model, entities);
     }
 
     pub fn unprepare_textured_model(&self, textured_model: &TexturedModel) {
@@ -97,6 +121,7 @@ impl EntityRenderer {
         gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
         gl::disable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
         gl::disable_vertex_attrib_array(RawModel::NORMAL_ATTRIB);
+        EntityInstances::disable_attribs();
 
         gl::bind_vertex_array(0);
         gl::bind_texture(gl::TEXTURE_2D, 0);
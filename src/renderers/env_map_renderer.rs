@@ -2,9 +2,12 @@ use crate::gl;
 use crate::entities::{
     Entity,
     Camera,
+    Light,
+    ReflectionProbe,
 };
 use crate::math::{
     Matrix4f,
+    Vector3f,
 };
 use crate::models::{
     TexturedModel,
@@ -12,6 +15,7 @@ use crate::models::{
     TextureId,
 };
 use crate::shaders::EnvMapShader;
+use crate::shadows::shadow_params::ShadowParams;
 
 pub struct EnvMapRenderer {
     shader: EnvMapShader,
@@ -19,7 +23,9 @@ pub struct EnvMapRenderer {
 }
 
 impl EnvMapRenderer {    
-    
+    // the light direction we use when no light casts the sun shadows
+    const DEFAULT_LIGHT_DIRECTION: Vector3f = Vector3f { x: 0.3, y: -1.0, z: 0.7 };
+
     pub fn new(projection_matrix: &Matrix4f) -> Self {
         let mut shader = EnvMapShader::new();
         shader.start();
@@ -30,12 +36,41 @@ impl EnvMapRenderer {
             proj_mat: projection_matrix.clone(),
         }
     }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.proj_mat = projection_matrix.clone();
+    }
    
-    pub fn render(&mut self, entities: &Vec<Entity>, camera: &Camera, env_map_texture_id: &TextureId) {
+    // probe_maps is the cube map array of the probes, None when they weren't captured and everything reflects the sky
+    pub fn render(&mut self, entities: &Vec<Entity>, camera: &Camera, env_map_texture_id: &TextureId, probes: &[ReflectionProbe], probe_maps: Option<u32>,
+                lights: &Vec<Light>, shadow_params: &ShadowParams) {
+        self.shader.start();
+        let view_mat = Matrix4f::create_view_matrix(camera);
+        let vp = &self.proj_mat * &view_mat;
+        self.shader.load_vp_matrix(&vp);
+        self.shader.load_view_matrix(&view_mat);
+        self.shader.load_camera_position(&camera.position);
+
+        // lit by the sun so they get its shadows like everything else
+        let sun_direction = Light::shadow_caster(lights).and_then(|sun| lights[sun].direction());
+        let mut light_direction = sun_direction.cloned().unwrap_or(EnvMapRenderer::DEFAULT_LIGHT_DIRECTION);
+        light_direction.normalize();
+        self.shader.load_light_direction(&light_direction);
+        self.shader.load_shadow_params(sun_direction.map(|_| shadow_params));
+        gl::active_texture(gl::TEXTURE2);
+        gl::bind_texture(gl::TEXTURE_2D_ARRAY, shadow_params.shadow_map_texture);
+        if let Some(probe_maps) = probe_maps {
+            gl::active_texture(gl::TEXTURE3);
+            gl::bind_texture(gl::TEXTURE_CUBE_MAP_ARRAY, probe_maps);
+        }
+
         for entity in entities {
+            let probe = probe_maps.and_then(|_| ReflectionProbe::closest(probes, &entity.position));
+            self.shader.load_probe(probe.map(|slot| (slot, &probes[slot])));
             self.prepare_textured_model(&entity.model, env_map_texture_id);
-            self.render_entity(entity, camera);
+            self.render_entity(entity);
         }
+        self.shader.stop();
     }
 
     fn prepare_textured_model(&mut self, textured_model: &TexturedModel, env_map_texture_id: &TextureId) {
@@ -50,19 +85,12 @@ impl EnvMapRenderer {
         gl::bind_texture(gl::TEXTURE_CUBE_MAP, env_map_texture_id.unwrap());
     }
 
-    fn render_entity(&mut self, entity: &Entity, camera: &Camera) {
-        self.shader.start();
+    fn render_entity(&mut self, entity: &Entity) {
         // load transform matrix into shader
         let transform_mat = Matrix4f::create_transform_matrix(&entity.position, &entity.rotation_deg, entity.scale);
-        let view_mat = Matrix4f::create_view_matrix(camera);
-        let mvp = &self.proj_mat * view_mat;
-        self.shader.load_vp_matrix(&mvp);
         self.shader.load_model_matrix(&transform_mat);
-        self.shader.load_camera_position(&camera.position);
                 
         gl::draw_elements(gl::TRIANGLES, entity.model.raw_model.vertex_count, gl::UNSIGNED_INT);
-
-        self.shader.stop();
     }
 
     pub fn unprepare_textured_model(&self, textured_model: &TexturedModel) {
@@ -9,6 +9,7 @@ use crate::models::{
 };
 use crate::math::{
     Matrix4f,
+    Vector2f,
 };
 use crate::gl;
 use crate::shaders::{
@@ -20,16 +21,26 @@ use super::master_renderer::RenderGroup;
 pub struct GuiRenderer {
     gui_shader: GuiShader,
     text_shader: TextShader,
+    // squeezes the layout horizontally when the window is wider than the layout aspect ratio (and stretches it when narrower)
+    aspect_scale: Vector2f,
 }
 
 impl GuiRenderer {
+    // the gui positions and scales were picked for this aspect ratio
+    const LAYOUT_ASPECT_RATIO: f32 = 16.0 / 9.0;
+
     pub fn new() -> GuiRenderer {
         GuiRenderer {
             gui_shader: GuiShader::new(),
             text_shader: TextShader::new(),
+            aspect_scale: Vector2f::new(1.0, 1.0),
         }
     }
 
+    pub fn update_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_scale = Vector2f::new(GuiRenderer::LAYOUT_ASPECT_RATIO / aspect_ratio, 1.0);
+    }
+
     pub fn render(&mut self, guis: &Vec<GuiPanel>, gui_model: &RawModel, texts: &Vec<GuiText>) {
         gl::helper::push_debug_group(RenderGroup::DRAW_GUI.id, RenderGroup::DRAW_GUI.name);
         
@@ -48,7 +59,12 @@ impl GuiRenderer {
         for gui in guis.iter() {
             gl::active_texture(gl::TEXTURE0);
             gl::bind_texture(gl::TEXTURE_2D, gui.texture_id.unwrap());
-            let transform_mat = Matrix4f::create_gui_transform_matrix(&gui.position, &gui.scale);
+            let transform_mat = if gui.fill_screen {
+                Matrix4f::create_gui_transform_matrix(&gui.position, &gui.scale)
+            } else {
+                let scale = Vector2f::new(gui.scale.x * self.aspect_scale.x, gui.scale.y * self.aspect_scale.y);
+                Matrix4f::create_gui_transform_matrix(&gui.position, &scale)
+            };
             self.gui_shader.load_transformation_matrix(&transform_mat);
             gl::draw_arrays(gl::TRIANGLE_STRIP, 0, gui_model.vertex_count);
         }
@@ -60,6 +76,7 @@ impl GuiRenderer {
 
 
         self.text_shader.start();
+        self.text_shader.load_scale(&self.aspect_scale);
         let text_by_font = GuiRenderer::group_text_by_font(texts);
         for (font_type, text_vec) in text_by_font.iter() {
             gl::active_texture(gl::TEXTURE0);
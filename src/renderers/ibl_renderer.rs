@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use crate::display::WallClock;
+use crate::entities::Skybox;
+use crate::gl;
+use crate::models::TextureId;
+use crate::shaders::IblShader;
+use super::brdf;
+
+// image based lighting baked from one sky cube map
+// the irradiance map is the diffuse light for every normal and the prefiltered map has the specular light for every reflection direction,
+// blurrier with every mip level since each level is one roughness
+pub struct IblMaps {
+    pub irradiance_map: u32,
+    pub prefiltered_map: u32,
+    pub prefiltered_levels: usize,
+}
+
+impl Drop for IblMaps {
+    fn drop(&mut self) {
+        gl::delete_textures(&[self.irradiance_map, self.prefiltered_map]);
+    }
+}
+
+// what the pbr shader needs to light with the sky of this frame
+// the day night cycle blends two skies so we blend the lighting of both the same way, rotated like the skybox
+pub struct SkyLighting<'a> {
+    pub from: &'a IblMaps,
+    pub to: &'a IblMaps,
+    pub blend_factor: f32,
+    pub rotation_yaw_deg: f32,
+    pub brdf_lut: u32,
+}
+
+// bakes the maps the first time a sky cube map shows up loaded and keeps them around for as long as the renderer lives
+pub struct IblRenderer {
+    irradiance_shader: IblShader,
+    prefilter_shader: IblShader,
+    fbo: u32,
+    // the triangle comes from gl_VertexID but the core profile still wants a vao bound
+    empty_vao: u32,
+    // scale and bias of the fresnel term for every view angle and roughness, doesn't depend on the sky
+    brdf_lut: u32,
+    // by the gl id of the sky cube map
+    maps: HashMap<u32, IblMaps>,
+}
+
+impl IblRenderer {
+    const IRRADIANCE_SIZE: usize = 32;
+    const PREFILTERED_SIZE: usize = 128;
+    // 128 down to 8 pixels, roughness 0 to 1
+    const PREFILTERED_LEVELS: usize = 5;
+    const BRDF_LUT_SIZE: usize = 512;
+
+    pub fn new() -> IblRenderer {
+        let mut irradiance_shader = IblShader::new_irradiance();
+        irradiance_shader.start();
+        irradiance_shader.connect_texture_units();
+        irradiance_shader.stop();
+        let mut prefilter_shader = IblShader::new_prefilter();
+        prefilter_shader.start();
+        prefilter_shader.connect_texture_units();
+        prefilter_shader.stop();
+        // only needed for the lut below
+        let mut brdf_shader = IblShader::new_brdf_integration();
+
+        // the prefiltered maps read across the face edges at the high mips, without this the seams show up as lines on rough metal
+        gl::enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+
+        let fbo = gl::gen_framebuffer();
+        let empty_vao = gl::gen_vertex_array();
+
+        let brdf_lut = gl::gen_texture();
+        gl::bind_texture(gl::TEXTURE_2D, brdf_lut);
+        gl::tex_storage_2d(gl::TEXTURE_2D, 1, gl::RG16F, IblRenderer::BRDF_LUT_SIZE, IblRenderer::BRDF_LUT_SIZE);
+        gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+        gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+        gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        gl::tex_parameter_iv(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        gl::bind_texture(gl::TEXTURE_2D, 0);
+
+        gl::bind_framebuffer(gl::FRAMEBUFFER, fbo);
+        gl::framebuffer_texture_2d(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, brdf_lut, 0);
+        gl::check_framebuffer_status(gl::FRAMEBUFFER);
+        gl::viewport(0, 0, IblRenderer::BRDF_LUT_SIZE as i32, IblRenderer::BRDF_LUT_SIZE as i32);
+        gl::disable(gl::DEPTH_TEST);
+        gl::bind_vertex_array(empty_vao);
+        brdf_shader.start();
+        gl::draw_arrays(gl::TRIANGLES, 0, 3);
+        brdf_shader.stop();
+        gl::bind_vertex_array(0);
+        gl::bind_framebuffer(gl::FRAMEBUFFER, 0);
+
+        IblRenderer {
+            irradiance_shader,
+            prefilter_shader,
+            fbo,
+            empty_vao,
+            brdf_lut,
+            maps: HashMap::new(),
+        }
+    }
+
+    // bakes the skies of the skybox that finished loading since the last call, the fbo and viewport are left for the next pass to set
+    pub fn bake_sky(&mut self, skybox: &Skybox) {
+        for texture_id in [skybox.model.day_texture_id, skybox.model.night_texture_id].iter() {
+            if let TextureId::Loaded(cube_map) = texture_id {
+                if !self.maps.contains_key(cube_map) {
+                    let maps = self.bake(*cube_map);
+                    self.maps.insert(*cube_map, maps);
+                }
+            }
+        }
+    }
+
+    // none until the sky of the skybox is loaded and baked
+    pub fn sky_lighting(&self, skybox: &Skybox, wall_clock: &WallClock) -> Option<SkyLighting> {
+        let (from, to, blend_factor) = skybox.get_day_night_textures(wall_clock);
+        let from = self.baked_maps(&from);
+        let to = self.baked_maps(&to);
+        let (from, to, blend_factor) = match (from, to) {
+            (Some(from), Some(to)) => (from, to, blend_factor),
+            (Some(maps), None) => (maps, maps, 0.0),
+            (None, Some(maps)) => (maps, maps, 0.0),
+            (None, None) => return None,
+        };
+        Some(SkyLighting {
+            from,
+            to,
+            blend_factor,
+            rotation_yaw_deg: skybox.rotation_yaw_deg,
+            brdf_lut: self.brdf_lut,
+        })
+    }
+
+    fn baked_maps(&self, texture_id: &TextureId) -> Option<&IblMaps> {
+        match texture_id {
+            TextureId::Loaded(cube_map) => self.maps.get(cube_map),
+            _ => None,
+        }
+    }
+
+    fn bake(&mut self, cube_map: u32) -> IblMaps {
+        // the prefilter shader reads blurrier mips of the sky for the samples that stand for a big part of the sphere
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP, cube_map);
+        gl::generate_mipmap(gl::TEXTURE_CUBE_MAP);
+        gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR);
+
+        gl::bind_framebuffer(gl::FRAMEBUFFER, self.fbo);
+        gl::disable(gl::DEPTH_TEST);
+        gl::bind_vertex_array(self.empty_vao);
+
+        let irradiance_map = IblRenderer::create_cube_map(IblRenderer::IRRADIANCE_SIZE, 1);
+        self.irradiance_shader.start();
+        IblRenderer::render_faces(&mut self.irradiance_shader, irradiance_map, 0, IblRenderer::IRRADIANCE_SIZE);
+        self.irradiance_shader.stop();
+
+        let prefiltered_map = IblRenderer::create_cube_map(IblRenderer::PREFILTERED_SIZE, IblRenderer::PREFILTERED_LEVELS);
+        self.prefilter_shader.start();
+        for level in 0..IblRenderer::PREFILTERED_LEVELS {
+            let roughness = brdf::prefiltered_roughness(level, IblRenderer::PREFILTERED_LEVELS);
+            self.prefilter_shader.load_roughness(roughness);
+            IblRenderer::render_faces(&mut self.prefilter_shader, prefiltered_map, level, IblRenderer::PREFILTERED_SIZE >> level);
+        }
+        self.prefilter_shader.stop();
+
+        gl::bind_vertex_array(0);
+        gl::bind_framebuffer(gl::FRAMEBUFFER, 0);
+        gl::enable(gl::DEPTH_TEST);
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
+
+        IblMaps {
+            irradiance_map,
+            prefiltered_map,
+            prefiltered_levels: IblRenderer::PREFILTERED_LEVELS,
+        }
+    }
+
+    // one draw per face into the mip level of the target, the source sky stays bound to unit 0
+    fn render_faces(shader: &mut IblShader, target: u32, level: usize, size: usize) {
+        gl::viewport(0, 0, size as i32, size as i32);
+        for (face, face_target) in gl::helper::CUBEMAP_FACES.iter().enumerate() {
+            gl::framebuffer_texture_2d(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, *face_target, target, level as i32);
+            shader.load_face(face);
+            gl::draw_arrays(gl::TRIANGLES, 0, 3);
+        }
+    }
+
+    // half floats since the sky can end up brighter than 1 once it's summed up
+    fn create_cube_map(size: usize, levels: usize) -> u32 {
+        let cube_map = gl::gen_texture();
+        gl::active_texture(gl::TEXTURE1);
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP, cube_map);
+        gl::tex_storage_2d(gl::TEXTURE_CUBE_MAP, levels as i32, gl::RGBA16F, size, size);
+        let min_filter = if levels > 1 { gl::LINEAR_MIPMAP_LINEAR } else { gl::LINEAR };
+        gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, min_filter);
+        gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+        gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        gl::tex_parameter_iv(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE);
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
+        gl::active_texture(gl::TEXTURE0);
+        cube_map
+    }
+}
+
+impl Drop for IblRenderer {
+    fn drop(&mut self) {
+        gl::delete_textures(&[self.brdf_lut]);
+        gl::delete_vertex_arrays(&[self.empty_vao]);
+        gl::delete_framebuffer(self.fbo);
+    }
+}
\ No newline at end of file
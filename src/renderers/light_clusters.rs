@@ -0,0 +1,441 @@
+use crate::gl;
+use crate::entities::{
+    Camera,
+    Light,
+    LightKind,
+};
+use crate::math::{
+    Matrix4f,
+    Vector2f,
+    Vector3f,
+    Vector4f,
+};
+
+// clustered forward lighting
+// the view frustum is split into CLUSTER_X * CLUSTER_Y screen tiles and each tile into CLUSTER_Z depth slices,
+// every frame the lights are binned into the clusters they can reach and the shaders only loop over the lights of the fragment's cluster
+// the cluster counts have to match the constants in the shaders
+pub const CLUSTER_X: usize = 16;
+pub const CLUSTER_Y: usize = 9;
+pub const CLUSTER_Z: usize = 24;
+pub const CLUSTER_COUNT: usize = CLUSTER_X * CLUSTER_Y * CLUSTER_Z;
+// rgba32f texels per light in the light data buffer:
+// position + kind (0 directional, 1 point, 2 spot), color + range (0 means no falloff),
+// attenuation + cos of the outer spot angle, direction + cos of the inner spot angle
+pub const LIGHT_TEXELS: usize = 4;
+
+// the cpu side of the clustering, knows nothing about gl so it can be tested
+pub struct ClusterGrid {
+    // projection_matrix[0][0] and [1][1], view space x and y are scaled by these before the perspective divide
+    projection_scale: Vector2f,
+    // slice 0 goes from the eye to slice_near, after that the slices grow exponentially up to far
+    slice_near: f32,
+    far: f32,
+    // view space bounds of every cluster
+    bounds: Vec<(Vector3f, Vector3f)>,
+    cluster_lights: Vec<Vec<u32>>,
+    // flattened data that gets uploaded to the buffer textures
+    pub light_data: Vec<f32>,
+    // offset and count into light_indices for every cluster
+    pub cluster_ranges: Vec<u32>,
+    pub light_indices: Vec<u32>,
+    // index of the light that the sun shadow map belongs to
+    pub shadow_light: Option<usize>,
+}
+
+impl ClusterGrid {
+    pub const SLICE_NEAR: f32 = 1.0;
+
+    // far is the positive view distance
+    pub fn new(projection_matrix: &Matrix4f, far: f32) -> ClusterGrid {
+        let mut grid = ClusterGrid {
+            projection_scale: Vector2f::new(projection_matrix[0][0], projection_matrix[1][1]),
+            slice_near: ClusterGrid::SLICE_NEAR,
+            far,
+            bounds: Vec::with_capacity(CLUSTER_COUNT),
+            cluster_lights: vec![Vec::new(); CLUSTER_COUNT],
+            light_data: Vec::new(),
+            cluster_ranges: vec![0; 2 * CLUSTER_COUNT],
+            light_indices: Vec::new(),
+            shadow_light: None,
+        };
+        grid.compute_bounds();
+        grid
+    }
+
+    // the tile bounds depend on the aspect ratio so they are computed again after a resize
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.projection_scale = Vector2f::new(projection_matrix[0][0], projection_matrix[1][1]);
+        self.bounds.clear();
+        self.compute_bounds();
+    }
+
+    pub fn projection_scale(&self) -> Vector2f {
+        self.projection_scale.clone()
+    }
+
+    // what the shaders need to go from a depth to a slice
+    pub fn depth_params(&self) -> Vector2f {
+        Vector2f::new(self.slice_near, (self.far / self.slice_near).ln())
+    }
+
+    // depth at which a slice starts
+    pub fn slice_start(&self, slice: usize) -> f32 {
+        if slice == 0 {
+            0.0
+        } else {
+            let t = (slice - 1) as f32 / (CLUSTER_Z - 1) as f32;
+            self.slice_near * (self.far / self.slice_near).powf(t)
+        }
+    }
+
+    pub fn slice_of_depth(&self, depth: f32) -> usize {
+        if depth < self.slice_near {
+            return 0;
+        }
+        let t = (depth / self.slice_near).ln() / (self.far / self.slice_near).ln();
+        let slice = (t * (CLUSTER_Z - 1) as f32).floor() as usize + 1;
+        slice.min(CLUSTER_Z - 1)
+    }
+
+    pub fn cluster_index(tile_x: usize, tile_y: usize, slice: usize) -> usize {
+        (slice * CLUSTER_Y + tile_y) * CLUSTER_X + tile_x
+    }
+
+    // same lookup as the shaders do for a view space position
+    pub fn cluster_of_view_position(&self, position: &Vector3f) -> usize {
+        let depth = (-position.z).max(1e-4);
+        let tile = |coord: f32, scale: f32, count: usize| {
+            let ndc = scale * coord / depth;
+            let tile = ((ndc * 0.5 + 0.5) * count as f32).floor();
+            tile.clamp(0.0, (count - 1) as f32) as usize
+        };
+        let tile_x = tile(position.x, self.projection_scale.x, CLUSTER_X);
+        let tile_y = tile(position.y, self.projection_scale.y, CLUSTER_Y);
+        ClusterGrid::cluster_index(tile_x, tile_y, self.slice_of_depth(depth))
+    }
+
+    pub fn lights_in_cluster(&self, cluster: usize) -> &[u32] {
+        let offset = self.cluster_ranges[2 * cluster] as usize;
+        let count = self.cluster_ranges[2 * cluster + 1] as usize;
+        &self.light_indices[offset..offset + count]
+    }
+
+    fn compute_bounds(&mut self) {
+        for slice in 0..CLUSTER_Z {
+            let near = self.slice_start(slice);
+            let far = if slice + 1 == CLUSTER_Z { self.far } else { self.slice_start(slice + 1) };
+            for tile_y in 0..CLUSTER_Y {
+                for tile_x in 0..CLUSTER_X {
+                    // the tile edges in ndc, a view space point at depth d projects to ndc = scale * coord / d
+                    let ndc = |tile: usize, count: usize| 2.0 * tile as f32 / count as f32 - 1.0;
+                    let x_range = (ndc(tile_x, CLUSTER_X) / self.projection_scale.x, ndc(tile_x + 1, CLUSTER_X) / self.projection_scale.x);
+                    let y_range = (ndc(tile_y, CLUSTER_Y) / self.projection_scale.y, ndc(tile_y + 1, CLUSTER_Y) / self.projection_scale.y);
+                    // the reflection probes roll the projection by 180 degrees which flips the scales and with them the ranges
+                    let x_range = (x_range.0.min(x_range.1), x_range.0.max(x_range.1));
+                    let y_range = (y_range.0.min(y_range.1), y_range.0.max(y_range.1));
+                    // the tile is a pyramid section so the extremes are at either the near or the far depth
+                    let min = Vector3f::new(
+                        (x_range.0 * near).min(x_range.0 * far),
+                        (y_range.0 * near).min(y_range.0 * far),
+                        -far,
+                    );
+                    let max = Vector3f::new(
+                        (x_range.1 * near).max(x_range.1 * far),
+                        (y_range.1 * near).max(y_range.1 * far),
+                        -near,
+                    );
+                    self.bounds.push((min, max));
+                }
+            }
+        }
+    }
+
+    pub fn build(&mut self, lights: &[Light], view_matrix: &Matrix4f) {
+        self.light_data.clear();
+        self.light_indices.clear();
+        for cluster_lights in self.cluster_lights.iter_mut() {
+            cluster_lights.clear();
+        }
+
+        for (light_index, light) in lights.iter().enumerate() {
+            let range = light.range();
+            // point lights get a cone that covers everything
+            let (kind, position, direction, cos_inner, cos_outer) = match &light.kind {
+                LightKind::Directional { direction } => (0.0, Vector3f::zero(), direction.clone(), 1.0, -1.0),
+                LightKind::Point { position } => (1.0, position.clone(), Vector3f::zero(), -1.0, -2.0),
+                LightKind::Spot { position, direction, inner_angle, outer_angle } => {
+                    (2.0, position.clone(), direction.clone(), inner_angle.to_radians().cos(), outer_angle.to_radians().cos())
+                },
+            };
+            self.light_data.extend_from_slice(&[position.x, position.y, position.z, kind]);
+            self.light_data.extend_from_slice(&[light.color.x, light.color.y, light.color.z, if range.is_finite() { range } else { 0.0 }]);
+            self.light_data.extend_from_slice(&[light.attenuation.x, light.attenuation.y, light.attenuation.z, cos_outer]);
+            self.light_data.extend_from_slice(&[direction.x, direction.y, direction.z, cos_inner]);
+            // spot lights are binned by the sphere around them, the cone would only save a few clusters
+            self.bin_light(light_index as u32, &position, range, view_matrix);
+        }
+        self.shadow_light = Light::shadow_caster(lights);
+
+        for (cluster, cluster_lights) in self.cluster_lights.iter().enumerate() {
+            self.cluster_ranges[2 * cluster] = self.light_indices.len() as u32;
+            self.cluster_ranges[2 * cluster + 1] = cluster_lights.len() as u32;
+            self.light_indices.extend_from_slice(cluster_lights);
+        }
+    }
+
+    fn bin_light(&mut self, light_index: u32, position: &Vector3f, range: f32, view_matrix: &Matrix4f) {
+        if range <= 0.0 {
+            return;
+        }
+        if !range.is_finite() {
+            // the sun and other lights without falloff reach everything
+            for cluster_lights in self.cluster_lights.iter_mut() {
+                cluster_lights.push(light_index);
+            }
+            return;
+        }
+
+        let view_position = view_matrix.transform(&Vector4f::point(position)).xyz();
+        let depth = -view_position.z;
+        if depth + range < 0.0 || depth - range > self.far {
+            return;
+        }
+        let first_slice = self.slice_of_depth((depth - range).max(0.0));
+        let last_slice = self.slice_of_depth(depth + range);
+        for slice in first_slice..=last_slice {
+            for tile_y in 0..CLUSTER_Y {
+                for tile_x in 0..CLUSTER_X {
+                    let cluster = ClusterGrid::cluster_index(tile_x, tile_y, slice);
+                    let (min, max) = &self.bounds[cluster];
+                    if ClusterGrid::sphere_intersects_aabb(&view_position, range, min, max) {
+                        self.cluster_lights[cluster].push(light_index);
+                    }
+                }
+            }
+        }
+    }
+
+    fn sphere_intersects_aabb(center: &Vector3f, radius: f32, min: &Vector3f, max: &Vector3f) -> bool {
+        let dx = (min.x - center.x).max(0.0).max(center.x - max.x);
+        let dy = (min.y - center.y).max(0.0).max(center.y - max.y);
+        let dz = (min.z - center.z).max(0.0).max(center.z - max.z);
+        dx * dx + dy * dy + dz * dz <= radius * radius
+    }
+}
+
+// the gl side, the light lists live in buffer textures which the lit shaders read with texelFetch
+pub struct LightClusters {
+    pub grid: ClusterGrid,
+    // (buffer, texture) for the light data, cluster ranges and light indices
+    buffers: [(u32, u32); 3],
+}
+
+impl LightClusters {
+    pub const LIGHT_DATA_UNIT: i32 = 10;
+    pub const CLUSTER_RANGES_UNIT: i32 = 11;
+    pub const LIGHT_INDICES_UNIT: i32 = 12;
+
+    const FORMATS: [gl::types::GLenum; 3] = [gl::RGBA32F, gl::RG32UI, gl::R32UI];
+    const UNITS: [gl::types::GLenum; 3] = [gl::TEXTURE10, gl::TEXTURE11, gl::TEXTURE12];
+
+    pub fn new(projection_matrix: &Matrix4f, far: f32) -> LightClusters {
+        let mut buffers = [(0, 0); 3];
+        for (i, buffer) in buffers.iter_mut().enumerate() {
+            let buffer_id = gl::gen_buffer();
+            gl::bind_buffer(gl::TEXTURE_BUFFER, buffer_id);
+            gl::buffer_data_unitialized::<u32>(gl::TEXTURE_BUFFER, 4, gl::STREAM_DRAW);
+            let texture_id = gl::gen_texture();
+            gl::bind_texture(gl::TEXTURE_BUFFER, texture_id);
+            gl::tex_buffer(LightClusters::FORMATS[i], buffer_id);
+            *buffer = (buffer_id, texture_id);
+        }
+        gl::bind_texture(gl::TEXTURE_BUFFER, 0);
+        gl::bind_buffer(gl::TEXTURE_BUFFER, 0);
+
+        LightClusters {
+            grid: ClusterGrid::new(projection_matrix, far),
+            buffers,
+        }
+    }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.grid.update_projection_matrix(projection_matrix);
+    }
+
+    // has to run for every camera that renders lit geometry (the water reflection camera too)
+    pub fn update(&mut self, lights: &[Light], camera: &Camera) {
+        let view_matrix = Matrix4f::create_view_matrix(camera);
+        self.grid.build(lights, &view_matrix);
+
+        LightClusters::upload(self.buffers[0].0, &self.grid.light_data);
+        LightClusters::upload(self.buffers[1].0, &self.grid.cluster_ranges);
+        LightClusters::upload(self.buffers[2].0, &self.grid.light_indices);
+        gl::bind_buffer(gl::TEXTURE_BUFFER, 0);
+    }
+
+    fn upload<T>(buffer_id: u32, data: &[T]) {
+        gl::bind_buffer(gl::TEXTURE_BUFFER, buffer_id);
+        // orphan the old storage so we don't stall on draws that still read it
+        gl::buffer_data_unitialized::<T>(gl::TEXTURE_BUFFER, data.len().max(4), gl::STREAM_DRAW);
+        if !data.is_empty() {
+            gl::buffer_sub_data(gl::TEXTURE_BUFFER, 0, data);
+        }
+    }
+
+    pub fn bind(&self) {
+        for (i, (_, texture_id)) in self.buffers.iter().enumerate() {
+            gl::active_texture(LightClusters::UNITS[i]);
+            gl::bind_texture(gl::TEXTURE_BUFFER, *texture_id);
+        }
+    }
+}
+
+impl Drop for LightClusters {
+    fn drop(&mut self) {
+        let buffer_ids: Vec<u32> = self.buffers.iter().map(|(buffer_id, _)| *buffer_id).collect();
+        let texture_ids: Vec<u32> = self.buffers.iter().map(|(_, texture_id)| *texture_id).collect();
+        gl::delete_textures(&texture_ids);
+        gl::delete_buffers(&buffer_ids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_grid() -> ClusterGrid {
+        let projection = Matrix4f::create_projection_matrix(-0.1, -1000.0, 90.0, 16.0 / 9.0);
+        ClusterGrid::new(&projection, 1000.0)
+    }
+
+    fn lantern(position: Vector3f) -> Light {
+        Light::new_point(position, Vector3f::new(1.0, 0.8, 0.5), Vector3f::new(1.0, 0.1, 0.05))
+    }
+
+    #[test]
+    fn test_slices() {
+        let grid = test_grid();
+        assert_eq!(grid.slice_of_depth(0.5), 0);
+        assert_eq!(grid.slice_of_depth(1.0), 1);
+        assert_eq!(grid.slice_of_depth(999.0), CLUSTER_Z - 1);
+        assert_eq!(grid.slice_of_depth(5000.0), CLUSTER_Z - 1);
+        for slice in 1..CLUSTER_Z {
+            let start = grid.slice_start(slice);
+            assert_eq!(grid.slice_of_depth(start * 1.001), slice, "slice {}", slice);
+            assert!(grid.slice_start(slice - 1) < start);
+        }
+    }
+
+    #[test]
+    fn test_light_range() {
+        let light = lantern(Vector3f::new(0.0, 0.0, 0.0));
+        let range = light.range();
+        let attenuation = |d: f32| light.attenuation.x + light.attenuation.y * d + light.attenuation.z * d * d;
+        assert!((light.color.x / attenuation(range) - Light::MIN_BRIGHTNESS).abs() < 1e-4);
+        let sun = Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::new(1.0, 1.0, 1.0));
+        assert!(sun.range().is_infinite());
+        // lights without falloff reach everything
+        let flat = Light::new_point(Vector3f::new(0.0, 0.0, 0.0), Vector3f::new(1.0, 1.0, 1.0), Vector3f::new(1.0, 0.0, 0.0));
+        assert!(flat.range().is_infinite());
+    }
+
+    #[test]
+    fn test_binning() {
+        let mut grid = test_grid();
+        // camera at the origin looking down -z
+        let view = Matrix4f::identity();
+        let lights = vec![
+            Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::new(1.0, 1.0, 1.0)).with_shadows(),
+            lantern(Vector3f::new(0.0, 0.0, -20.0)),
+            lantern(Vector3f::new(30.0, 0.0, -200.0)),
+            // behind the camera
+            lantern(Vector3f::new(0.0, 0.0, 100.0)),
+        ];
+        grid.build(&lights, &view);
+        assert_eq!(grid.light_data.len(), lights.len() * LIGHT_TEXELS * 4);
+        assert_eq!(grid.shadow_light, Some(0));
+
+        let near_cluster = grid.cluster_of_view_position(&Vector3f::new(0.0, 0.0, -20.0));
+        assert_eq!(grid.lights_in_cluster(near_cluster), &[0, 1]);
+        let far_cluster = grid.cluster_of_view_position(&Vector3f::new(30.0, 0.0, -200.0));
+        assert_eq!(grid.lights_in_cluster(far_cluster), &[0, 2]);
+        let empty_cluster = grid.cluster_of_view_position(&Vector3f::new(-100.0, 0.0, -600.0));
+        assert_eq!(grid.lights_in_cluster(empty_cluster), &[0]);
+        assert!(grid.light_indices.iter().all(|light| *light != 3));
+    }
+
+    #[test]
+    fn test_light_data_layout() {
+        let mut grid = test_grid();
+        let lights = vec![
+            lantern(Vector3f::new(0.0, 0.0, -20.0)).with_shadows(),
+            Light::new_spot(Vector3f::new(1.0, 2.0, -3.0), Vector3f::new(0.0, -2.0, 0.0), Vector3f::new(1.0, 1.0, 1.0), Vector3f::new(1.0, 0.1, 0.05), 20.0, 30.0),
+        ];
+        grid.build(&lights, &Matrix4f::identity());
+        // only directional lights have a shadow map
+        assert_eq!(grid.shadow_light, None);
+        let spot = &grid.light_data[LIGHT_TEXELS * 4..];
+        assert_eq!(&spot[0..4], &[1.0, 2.0, -3.0, 2.0]);
+        assert_eq!(&spot[12..15], &[0.0, -1.0, 0.0]);
+        assert!((spot[11] - 30f32.to_radians().cos()).abs() < 1e-6);
+        assert!((spot[15] - 20f32.to_radians().cos()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_binning_is_conservative() {
+        let mut grid = test_grid();
+        let view = Matrix4f::identity();
+        let lights: Vec<Light> = (0..200).map(|i| {
+            let angle = i as f32 * 0.37;
+            lantern(Vector3f::new(angle.cos() * (i as f32), (i % 7) as f32, -5.0 - 2.0 * i as f32))
+        }).collect();
+        grid.build(&lights, &view);
+        // every point lit by a light has to find that light in its cluster
+        for (light_index, light) in lights.iter().enumerate() {
+            let range = light.range();
+            for step in 0..16 {
+                let angle = step as f32 * std::f32::consts::PI / 8.0;
+                let offset = Vector3f::new(angle.cos() * range * 0.9, angle.sin() * range * 0.5, angle.sin() * range * 0.3);
+                let position = light.position().unwrap();
+                let point = Vector3f::new(position.x + offset.x, position.y + offset.y, position.z + offset.z);
+                if point.z > -0.1 {
+                    continue;
+                }
+                let cluster = grid.cluster_of_view_position(&point);
+                assert!(grid.lights_in_cluster(cluster).contains(&(light_index as u32)), "light {} step {}", light_index, step);
+            }
+        }
+    }
+
+    #[test]
+    fn test_binning_with_rolled_projection() {
+        let mut projection = Matrix4f::create_projection_matrix(-0.1, -1000.0, 90.0, 1.0);
+        for col in 0..4 {
+            projection[0][col] = -projection[0][col];
+            projection[1][col] = -projection[1][col];
+        }
+        let mut grid = ClusterGrid::new(&projection, 1000.0);
+        // a dim light that only reaches the clusters around it
+        let lights = vec![Light::new_point(Vector3f::new(-15.0, 10.0, -40.0), Vector3f::new(0.2, 0.2, 0.2), Vector3f::new(1.0, 0.5, 0.5))];
+        grid.build(&lights, &Matrix4f::identity());
+        let cluster = grid.cluster_of_view_position(&Vector3f::new(-15.0, 10.0, -40.0));
+        assert_eq!(grid.lights_in_cluster(cluster), &[0]);
+        let mirrored_cluster = grid.cluster_of_view_position(&Vector3f::new(15.0, -10.0, -40.0));
+        assert!(grid.lights_in_cluster(mirrored_cluster).is_empty());
+    }
+
+    #[test]
+    fn test_update_projection_matrix() {
+        let mut grid = test_grid();
+        let projection = Matrix4f::create_projection_matrix(-0.1, -1000.0, 90.0, 4.0 / 3.0);
+        grid.update_projection_matrix(&projection);
+        let fresh = ClusterGrid::new(&projection, 1000.0);
+        assert_eq!(grid.bounds.len(), CLUSTER_COUNT);
+        for (updated, expected) in grid.bounds.iter().zip(fresh.bounds.iter()) {
+            for (a, b) in [(&updated.0, &expected.0), (&updated.1, &expected.1)].iter() {
+                assert!((a.x - b.x).abs() < 1e-6 && (a.y - b.y).abs() < 1e-6 && (a.z - b.z).abs() < 1e-6);
+            }
+        }
+    }
+}
@@ -2,13 +2,12 @@ use std::collections::HashMap;
 use crate::display::{
     Display,
     WallClock,
-    framebuffers::{
-        FboMap,
-    }
 };
 use crate::gl;
 use crate::entities::*;
 use crate::math::{
+    BoundingSphere,
+    Frustum,
     Matrix4f,
     Vector3f,
     Vector4f,
@@ -17,15 +16,41 @@ use crate::models::{
     TexturedModel,
 };
 use crate::particles::ParticleMaster;
+use crate::post_processing::post_processing::PostProcessing;
+use crate::post_processing::post_effect::PostEffect;
+use crate::scenes::scene::Scene;
+use crate::shadows::{
+    shadow_box::ShadowCascades,
+    shadow_casters::ShadowCasters,
+};
 use super::shadowmap_renderer::ShadowMapRenderer;
+use super::point_shadow_renderer::PointShadowRenderer;
 use super::entity_renderer::EntityRenderer;
 use super::normal_map_entity_renderer::NormalMapEntityRenderer;
 use super::terrain_renderer::TerrainRenderer;
 use super::skybox_renderer::SkyboxRenderer;
+use super::gui_renderer::GuiRenderer;
 use super::water_renderer::WaterRenderer;
 use super::debug_renderer::DebugRenderer;
 use super::env_map_renderer::EnvMapRenderer;
 use super::animated_entity_renderer::AnimatedEntityRenderer;
+use super::light_clusters::LightClusters;
+use super::deferred_renderer::DeferredRenderer;
+use super::ibl_renderer::IblRenderer;
+use super::pbr_renderer::PbrRenderer;
+use super::ssr_renderer::ScreenSpaceReflectionRenderer;
+use super::precipitation_renderer::PrecipitationRenderer;
+use super::entity_instances::EntityInstances;
+use super::culling::{
+    CullCounter,
+    CullingStats,
+    PassCulling,
+};
+use super::render_graph::{
+    RenderGraph,
+    RenderPass,
+    RenderResource,
+};
 
 pub struct RenderGroup {
     pub id: u32,
@@ -43,6 +68,13 @@ impl RenderGroup {
     pub const PARTICLE_EFFECTS_PASS: RenderGroup = RenderGroup {id: 7, name: "ParticleEffects"};
     pub const POST_PROCESSING: RenderGroup = RenderGroup {id: 8, name: "PostProcessing"};
     pub const DRAW_GUI: RenderGroup = RenderGroup {id: 9, name: "GuiOverlayDraw"};
+    pub const POINT_SHADOW_MAP_PASS: RenderGroup = RenderGroup {id: 10, name: "PointShadowMapPass"};
+    pub const DEFERRED_GEOMETRY_PASS: RenderGroup = RenderGroup {id: 11, name: "DeferredGeometryPass"};
+    pub const DEFERRED_LIGHTING_PASS: RenderGroup = RenderGroup {id: 12, name: "DeferredLightingPass"};
+    pub const DRAW_PBR_ENTITIES: RenderGroup = RenderGroup {id: 13, name: "PbrEntityDrawPass"};
+    pub const SCREEN_SPACE_REFLECTIONS: RenderGroup = RenderGroup {id: 14, name: "ScreenSpaceReflections"};
+    pub const REFLECTION_PROBES: RenderGroup = RenderGroup {id: 15, name: "ReflectionProbes"};
+    pub const PRECIPITATION: RenderGroup = RenderGroup {id: 16, name: "Precipitation"};
 }
 
 pub struct MasterRenderer {    
@@ -52,24 +84,44 @@ pub struct MasterRenderer {
     skybox_renderer: SkyboxRenderer,
     water_renderer: WaterRenderer,
     shadowmap_renderer: ShadowMapRenderer,
+    point_shadow_renderer: PointShadowRenderer,
     env_map_renderer: EnvMapRenderer,
     animated_entity_renderer: AnimatedEntityRenderer,
+    light_clusters: LightClusters,
+    deferred_renderer: DeferredRenderer,
+    ibl_renderer: IblRenderer,
+    pbr_renderer: PbrRenderer,
+    ssr_renderer: ScreenSpaceReflectionRenderer,
+    precipitation_renderer: PrecipitationRenderer,
+    projection_matrix: Matrix4f,
+    culling_stats: CullingStats,
+    // planned at the start of the frame, the water passes draw with it
+    water_planes: WaterPlanes,
+    // what the screen gets cleared to, follows the horizon of a procedural sky
+    sky_color: Vector3f,
 }
 
 impl MasterRenderer {
 
     const SKY_COLOR: Vector3f = Vector3f{ x: 0.5444, y: 0.62, z: 0.69 };
 
-    pub fn new(projection_matrix: &Matrix4f, aspect_ratio: f32) -> MasterRenderer {
+    pub fn new(projection_matrix: &Matrix4f, aspect_ratio: f32, shadow_cascades: &ShadowCascades) -> MasterRenderer {
         let entity_renderer = EntityRenderer::new(projection_matrix);
         let normal_map_entity_renderer = NormalMapEntityRenderer::new(projection_matrix);
         let terrain_renderer = TerrainRenderer::new(projection_matrix);
         let skybox_renderer = SkyboxRenderer::new(projection_matrix);
-        let water_renderer = WaterRenderer::new(projection_matrix, &MasterRenderer::SKY_COLOR);
-        let shadowmap_renderer = ShadowMapRenderer::new(aspect_ratio);
+        let water_renderer = WaterRenderer::new(projection_matrix);
+        let shadowmap_renderer = ShadowMapRenderer::new(aspect_ratio, shadow_cascades);
+        let point_shadow_renderer = PointShadowRenderer::new();
         let _debug_renderer = DebugRenderer::new(projection_matrix);
         let env_map_renderer = EnvMapRenderer::new(projection_matrix);
         let animated_entity_renderer = AnimatedEntityRenderer::new(projection_matrix);
+        let light_clusters = LightClusters::new(projection_matrix, -Display::FAR);
+        let deferred_renderer = DeferredRenderer::new(projection_matrix);
+        let ibl_renderer = IblRenderer::new();
+        let pbr_renderer = PbrRenderer::new(projection_matrix);
+        let ssr_renderer = ScreenSpaceReflectionRenderer::new(projection_matrix);
+        let precipitation_renderer = PrecipitationRenderer::new(projection_matrix);
 
         MasterRenderer {
             entity_renderer,
@@ -78,33 +130,157 @@ impl MasterRenderer {
             skybox_renderer,
             water_renderer,
             shadowmap_renderer,
+            point_shadow_renderer,
             env_map_renderer,
             animated_entity_renderer,
+            light_clusters,
+            deferred_renderer,
+            ibl_renderer,
+            pbr_renderer,
+            ssr_renderer,
+            precipitation_renderer,
+            projection_matrix: projection_matrix.clone(),
+            culling_stats: CullingStats::default(),
+            water_planes: WaterPlanes::default(),
+            sky_color: MasterRenderer::SKY_COLOR,
         }
     }
-    
-    pub fn render(&mut self, lights: &Vec<Light>, camera: &mut Camera, entities: &Vec<Entity>, normal_mapped_entities: &Vec<Entity>, terrains: &Vec<Terrain>, 
-                player: &Player, water_tiles: &Vec<WaterTile>, skybox: &Skybox, display: &Display, framebuffers: &mut FboMap, particle_master: &mut ParticleMaster, 
-                entities_with_env_map: &Vec<Entity>, _debug_entity: &mut DebugEntity) {
 
-        self.do_shadowmap_render_passes(camera, framebuffers, entities, normal_mapped_entities, player, lights, terrains);
+    // after the window changed its size every shader and frustum that baked in the old aspect ratio gets the new projection
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f, aspect_ratio: f32) {
+        self.shadowmap_renderer.update_aspect_ratio(aspect_ratio);
+        self.load_projection_matrix(projection_matrix);
+    }
 
-        self.do_water_render_passes(water_tiles, camera, framebuffers, entities, normal_mapped_entities, terrains, player, lights, skybox, display);
-        
-        let camera_tex_fbo = framebuffers.fbos.get_mut(FboMap::CAMERA_TEXTURE_FBO_MULTI).expect("Must have a camera output fbo to which to render the scene for post processing");
-        camera_tex_fbo.bind(); // we will unbind it later after particle effects are drawn
+    // the reflection probes swap in the projection of their faces and then put back the one of the camera
+    fn load_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.entity_renderer.update_projection_matrix(projection_matrix);
+        self.normal_map_entity_renderer.update_projection_matrix(projection_matrix);
+        self.terrain_renderer.update_projection_matrix(projection_matrix);
+        self.skybox_renderer.update_projection_matrix(projection_matrix);
+        self.water_renderer.update_projection_matrix(projection_matrix);
+        self.env_map_renderer.update_projection_matrix(projection_matrix);
+        self.animated_entity_renderer.update_projection_matrix(projection_matrix);
+        self.light_clusters.update_projection_matrix(projection_matrix);
+        self.deferred_renderer.update_projection_matrix(projection_matrix);
+        self.pbr_renderer.update_projection_matrix(projection_matrix);
+        self.ssr_renderer.update_projection_matrix(projection_matrix);
+        self.precipitation_renderer.update_projection_matrix(projection_matrix);
+        self.projection_matrix = projection_matrix.clone();
+    }
 
-        let above_infinity_plane = Vector4f::new(0.0, -1.0, 0.0, 10_000.0);
-        self.render_pass(lights, camera, entities, normal_mapped_entities, terrains, player, skybox, &display.wall_clock, &above_infinity_plane);
-        // render water
-        self.water_renderer.render(water_tiles, framebuffers, camera, display, lights);
+    // the wind, the wet surfaces and the snow cover are the same for every pass of the frame
+    fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.entity_renderer.update_weather(weather, time);
+        self.normal_map_entity_renderer.update_weather(weather, time);
+        self.terrain_renderer.update_weather(weather, time);
+        self.pbr_renderer.update_weather(weather, time);
+        self.water_renderer.update_weather(weather, time);
+        self.deferred_renderer.update_weather(weather, time);
+        self.shadowmap_renderer.update_weather(weather, time);
+        self.point_shadow_renderer.update_weather(weather, time);
+    }
 
-        // render entities which have an env map -> for the time being this happens outside of render pass but needs to be integrated at some point
-        self.env_map_renderer.render(entities_with_env_map, camera, &skybox.model.day_texture_id);
+    // what the passes of the last frame skipped
+    pub fn culling_stats(&self) -> &CullingStats {
+        &self.culling_stats
+    }
+    
+    // runs the enabled passes of the graph in the order it planned them
+    pub fn render(&mut self, render_graph: &mut RenderGraph, scene: &mut Scene, display: &Display, particle_master: &mut ParticleMaster, 
+                post_processing: &mut PostProcessing, gui_renderer: &mut GuiRenderer) {
+
+        // the sky lighting of the pbr materials is baked once the cube maps of the skybox are loaded
+        self.ibl_renderer.bake_sky(&scene.skybox);
+        self.sky_color = scene.skybox.procedural.as_ref().map(|sky| sky.horizon_color(&display.wall_clock)).unwrap_or(MasterRenderer::SKY_COLOR);
+        self.update_weather(&scene.weather, display.wall_clock.elapsed);
+
+        // the display picks how the scene is lit, only changes in the enabled passes make the graph compile again
+        let deferred = display.is_deferred_rendering_active();
+        render_graph.set_enabled(RenderPass::ForwardScene, !deferred);
+        render_graph.set_enabled(RenderPass::DeferredScene, deferred);
+        // the fog and the light shafts come from the scene
+        render_graph.set_enabled(RenderPass::LightShafts, scene.atmosphere.light_shafts.is_some());
+        render_graph.set_enabled(RenderPass::Atmosphere, scene.atmosphere.is_enabled());
+        render_graph.compile(display);
+
+        // the passes which are switched off leave their counters at zero
+        self.culling_stats = CullingStats::default();
+        let above_infinity_plane = Vector4f::new(0.0, -1.0, 0.0, 10_000.0);
+        self.plan_water(&scene.water, &scene.camera);
 
-        // render particles
-        particle_master.render(&camera);
-        display.restore_default_framebuffer();
+        for pass in render_graph.order().to_vec() {
+            match pass {
+                RenderPass::ShadowMap => {
+                    self.do_shadowmap_render_passes(&mut scene.camera, render_graph, &scene.entities, &scene.normal_mapped_entities, &scene.entities_with_env_map, 
+                        &scene.player, &scene.lights, &scene.ground.terrains);
+                },
+                RenderPass::PointShadowMap => {
+                    self.do_point_shadow_render_passes(&scene.camera, render_graph, &scene.entities, &scene.normal_mapped_entities, &scene.entities_with_env_map, 
+                        &scene.player, &scene.lights);
+                },
+                RenderPass::ReflectionProbes => {
+                    self.do_reflection_probe_pass(scene, render_graph, display);
+                },
+                RenderPass::WaterReflectRefract => {
+                    self.do_water_render_passes(&mut scene.camera, render_graph, &scene.entities, &scene.normal_mapped_entities, &scene.ground.terrains, 
+                        &scene.player, &scene.lights, &scene.skybox, display);
+                },
+                RenderPass::ForwardScene => {
+                    render_graph.fbo_mut(RenderResource::CameraMultisampled).bind();
+                    self.culling_stats.main = self.render_pass(&scene.lights, &scene.camera, &scene.entities, &scene.normal_mapped_entities, &scene.ground.terrains, 
+                        &scene.player, &scene.skybox, &display.wall_clock, &above_infinity_plane);
+                    MasterRenderer::copy_scene(render_graph, display);
+                },
+                RenderPass::DeferredScene => {
+                    self.culling_stats.main = self.render_deferred_pass(&scene.lights, &scene.camera, &scene.entities, &scene.normal_mapped_entities, &scene.ground.terrains, 
+                        &scene.player, &scene.skybox, &display.wall_clock, render_graph);
+                    MasterRenderer::copy_scene(render_graph, display);
+                },
+                RenderPass::ScreenSpaceReflection => {
+                    self.do_screen_space_reflection_pass(scene, render_graph, display);
+                },
+                RenderPass::WaterSurface => {
+                    render_graph.fbo_mut(RenderResource::CameraMultisampled).bind();
+                    // the light clusters are still the ones of the main camera from the scene pass
+                    let plane_textures: Vec<_> = (0..self.water_planes.heights.len())
+                        .map(|plane| (render_graph.fbo(RenderResource::Reflection(plane)), render_graph.fbo(RenderResource::Refraction(plane))))
+                        .collect();
+                    let ssr_texture = render_graph.color_texture(RenderResource::ScreenSpaceReflection);
+                    self.water_renderer.render(&scene.water, &self.water_planes, &plane_textures, render_graph.fbo(RenderResource::SceneCopy), ssr_texture, 
+                        &scene.camera, display, &self.light_clusters);
+                },
+                RenderPass::EnvMapEntities => {
+                    // render entities which have an env map -> for the time being this happens outside of render pass but needs to be integrated at some point
+                    // the probes have nothing captured when their pass is switched off
+                    let probe_maps = if render_graph.is_enabled(RenderPass::ReflectionProbes) {
+                        Some(render_graph.color_texture(RenderResource::ReflectionProbes))
+                    } else {
+                        None
+                    };
+                    render_graph.fbo_mut(RenderResource::CameraMultisampled).bind();
+                    self.env_map_renderer.render(&scene.entities_with_env_map, &scene.camera, &scene.skybox.model.day_texture_id, &scene.reflection_probes, probe_maps, 
+                        &scene.lights, &self.shadowmap_renderer.shadow_params);
+                },
+                RenderPass::Particles => {
+                    render_graph.fbo_mut(RenderResource::CameraMultisampled).bind();
+                    particle_master.render(&scene.camera);
+                    self.culling_stats.particles = particle_master.culling.clone();
+                    self.precipitation_renderer.render(&scene.weather, &scene.camera, &scene.quad_model.raw_model, display.wall_clock.elapsed);
+                    display.restore_default_framebuffer();
+                },
+                RenderPass::LightShafts | RenderPass::Atmosphere => {
+                    MasterRenderer::do_atmosphere_pass(pass, scene, render_graph, post_processing, display);
+                },
+                RenderPass::MsaaResolve | RenderPass::Ssao | RenderPass::SsaoBlurHorizontal | RenderPass::SsaoBlurVertical | RenderPass::SsaoApply
+                | RenderPass::BloomBlurHorizontal | RenderPass::BloomBlurVertical | RenderPass::BloomCombine | RenderPass::PostEffect(_) | RenderPass::Present => {
+                    MasterRenderer::do_post_processing_pass(pass, render_graph, post_processing, display);
+                },
+                RenderPass::Gui => {
+                    gui_renderer.render(&scene.guis, &scene.quad_model.raw_model, &scene.texts);
+                },
+            }
+        }
 
         //let obb_ref = &self.shadowmap_renderer.shadow_box.frustum_corners;
         //self.debug_renderer.render(debug_entity, camera, obb_ref); 
@@ -114,49 +290,280 @@ impl MasterRenderer {
         //self.debug_renderer.render_cube(debug_entity, camera);
     }
 
-    fn do_shadowmap_render_passes(&mut self, camera: &mut Camera, framebuffers: &mut FboMap, entities: &Vec<Entity>, 
-                normal_mapped_entities: &Vec<Entity>, player: &Player, lights: &Vec<Light>, terrains: &Vec<Terrain>) {
+    // like the post processing passes but they need the camera, the sun and the settings of the scene
+    fn do_atmosphere_pass(pass: RenderPass, scene: &Scene, render_graph: &mut RenderGraph, post_processing: &mut PostProcessing, display: &Display) {
+        gl::helper::push_debug_group(RenderGroup::POST_PROCESSING.id, RenderGroup::POST_PROCESSING.name);
+        let depth_texture = render_graph.fbo(RenderResource::CameraTexture).depth_texture.expect("The resolved camera fbo must have a depth texture");
+        match pass {
+            RenderPass::LightShafts => {
+                let light_shafts = scene.atmosphere.light_shafts.as_ref().expect("The light shafts pass only runs when the atmosphere has them");
+                render_graph.fbo_mut(RenderResource::LightShafts).bind();
+                post_processing.render_light_shafts(depth_texture, light_shafts, &scene.camera, &scene.lights, display);
+            },
+            RenderPass::Atmosphere => {
+                let scene_texture = render_graph.color_texture(RenderResource::AmbientOccluded);
+                let light_shafts_texture = render_graph.color_texture(RenderResource::LightShafts);
+                let wall_clock = &display.wall_clock;
+                // a procedural sky says when it is day by its sun and the fog takes on the color of its horizon
+                let (fog_color, daylight) = match &scene.skybox.procedural {
+                    Some(sky) => (sky.horizon_color(wall_clock), sky.daylight(wall_clock)),
+                    None => (scene.atmosphere.fog.as_ref().map(|fog| fog.color(wall_clock)).unwrap_or_default(), Atmosphere::daylight(wall_clock)),
+                };
+                render_graph.fbo_mut(RenderResource::Atmosphere).bind();
+                post_processing.apply_atmosphere(scene_texture, depth_texture, light_shafts_texture, &scene.atmosphere, &fog_color, daylight, &scene.camera, &scene.lights, display);
+            },
+            _ => panic!("{:?} is not an atmosphere pass", pass),
+        }
+        display.restore_default_framebuffer();
+        gl::helper::pop_debug_group();
+    }
+
+    // every step reads the textures of the steps before and renders into its own fbo, the present pass copies the last one to the screen
+    fn do_post_processing_pass(pass: RenderPass, render_graph: &mut RenderGraph, post_processing: &mut PostProcessing, display: &Display) {
+        gl::helper::push_debug_group(RenderGroup::POST_PROCESSING.id, RenderGroup::POST_PROCESSING.name);
+        match pass {
+            RenderPass::MsaaResolve => {
+                // the resolved textures are what the post processing shaders read from
+                let camera_multisampled_fbo = render_graph.fbo(RenderResource::CameraMultisampled);
+                camera_multisampled_fbo.resolve_to_fbo(gl::COLOR_ATTACHMENT0, render_graph.fbo(RenderResource::CameraTexture), display);
+                camera_multisampled_fbo.resolve_to_fbo(gl::COLOR_ATTACHMENT1, render_graph.fbo(RenderResource::CameraBrightness), display);
+            },
+            RenderPass::Ssao => {
+                let depth_texture = render_graph.fbo(RenderResource::CameraTexture).depth_texture.expect("The resolved camera fbo must have a depth texture");
+                render_graph.fbo_mut(RenderResource::Ssao).bind();
+                post_processing.compute_ssao(depth_texture, display);
+            },
+            RenderPass::SsaoBlurHorizontal => {
+                let occlusion = render_graph.color_texture(RenderResource::Ssao);
+                render_graph.fbo_mut(RenderResource::SsaoBlurHorizontal).bind();
+                post_processing.blur_ssao_horizontally(occlusion, display);
+            },
+            RenderPass::SsaoBlurVertical => {
+                let blurred = render_graph.color_texture(RenderResource::SsaoBlurHorizontal);
+                render_graph.fbo_mut(RenderResource::SsaoBlurVertical).bind();
+                post_processing.blur_ssao_vertically(blurred, display);
+            },
+            RenderPass::SsaoApply => {
+                let scene_texture = render_graph.color_texture(RenderResource::CameraTexture);
+                let occlusion = render_graph.color_texture(RenderResource::SsaoBlurVertical);
+                render_graph.fbo_mut(RenderResource::AmbientOccluded).bind();
+                post_processing.apply_ssao(scene_texture, occlusion, display);
+            },
+            RenderPass::BloomBlurHorizontal => {
+                let brightness = render_graph.color_texture(RenderResource::CameraBrightness);
+                render_graph.fbo_mut(RenderResource::BloomBlurHorizontal).bind();
+                post_processing.blur_horizontally(brightness, display);
+            },
+            RenderPass::BloomBlurVertical => {
+                let blurred = render_graph.color_texture(RenderResource::BloomBlurHorizontal);
+                render_graph.fbo_mut(RenderResource::BloomBlurVertical).bind();
+                post_processing.blur_vertically(blurred, display);
+            },
+            RenderPass::BloomCombine => {
+                let scene_texture = render_graph.color_texture(RenderResource::Atmosphere);
+                let bloom = render_graph.color_texture(RenderResource::BloomBlurVertical);
+                render_graph.fbo_mut(RenderResource::BloomCombined).bind();
+                post_processing.combine_bloom(scene_texture, bloom, display);
+            },
+            RenderPass::PostEffect(slot) => {
+                // the disabled passes before forward to whatever came last so this is the bloom or the camera texture for the first effect
+                let source = if slot == 0 { RenderResource::BloomCombined } else { RenderResource::PostEffect(slot - 1) };
+                let source_texture = render_graph.color_texture(source);
+                let depth_texture = render_graph.fbo(RenderResource::CameraTexture).depth_texture.expect("The resolved camera fbo must have a depth texture");
+                render_graph.fbo_mut(RenderResource::PostEffect(slot)).bind();
+                post_processing.render_effect(slot, source_texture, depth_texture, display);
+            },
+            RenderPass::Present => {
+                // the last slot forwards to the output of the last enabled effect
+                let last_effect = RenderResource::PostEffect(PostEffect::MAX_EFFECTS - 1);
+                render_graph.fbo_mut(last_effect).resolve_to_screen(display);
+            },
+            _ => panic!("{:?} is not a post processing pass", pass),
+        }
+        display.restore_default_framebuffer();
+        gl::helper::pop_debug_group();
+    }
+
+    fn do_shadowmap_render_passes(&mut self, camera: &mut Camera, render_graph: &mut RenderGraph, entities: &Vec<Entity>, normal_mapped_entities: &Vec<Entity>, 
+                entities_with_env_map: &Vec<Entity>, player: &Player, lights: &Vec<Light>, terrains: &Vec<Terrain>) {
         
+        self.culling_stats.shadow = PassCulling::default();
+        // the shaders only apply the shadow map to the caster so with no caster there is nothing to render
+        let sun = match Light::shadow_caster(lights) {
+            Some(caster) => &lights[caster],
+            None => return,
+        };
+
         gl::helper::push_debug_group(RenderGroup::SHADOW_MAP_PASS.id, RenderGroup::SHADOW_MAP_PASS.name);
 
-        let shadowmap_fbo = framebuffers.fbos.get_mut(FboMap::SHADOW_MAP_FBO).expect("Must have shadowmap fbo to render shadowmaps");
+        let shadowmap_fbo = render_graph.fbo_mut(RenderResource::ShadowMap);
         shadowmap_fbo.bind();
-        self.shadowmap_renderer.start_render(camera, &lights[0]);
+        self.shadowmap_renderer.start_render(camera, sun);
         self.shadowmap_renderer.shadow_params.shadow_map_texture = shadowmap_fbo.depth_texture.expect("A shadowmup must have a depth texture or crash");
 
-        // render into the shadowmap depth buffer all the entities that we want to cast shadows
         let entity_by_tex = MasterRenderer::group_entities_by_tex(entities);
-        for (tex_model, entity_group) in entity_by_tex {
-            self.shadowmap_renderer.prepare_textured_model(tex_model);
-            self.shadowmap_renderer.render(&entity_group);
-            self.shadowmap_renderer.cleanup_textured_model();
+        let norm_entity_by_tex = MasterRenderer::group_entities_by_tex(normal_mapped_entities);
+        let env_map_entity_by_tex = MasterRenderer::group_entities_by_tex(entities_with_env_map);
+
+        for cascade in 0..self.shadowmap_renderer.cascade_count() {
+            shadowmap_fbo.bind_depth_layer(cascade);
+            self.shadowmap_renderer.start_cascade(cascade);
+            let cascade_frustum = self.shadowmap_renderer.cascade_frustum().clone();
+
+            // render into the shadowmap depth buffer all the entities that we want to cast shadows
+            let casters = ShadowCasters::collect(&[&entity_by_tex, &norm_entity_by_tex, &env_map_entity_by_tex], player, 
+                &mut self.culling_stats.shadow.entities, |entity| entity.is_in_frustum(&cascade_frustum));
+            for (tex_model, entities) in casters.instanced.iter() {
+                self.shadowmap_renderer.prepare_textured_model(tex_model);
+                self.shadowmap_renderer.render(tex_model, entities);
+                self.shadowmap_renderer.cleanup_textured_model();
+            }
+            for entity in casters.animated.iter() {
+                self.shadowmap_renderer.render_animated_entity(entity);
+            }
+
+            self.shadowmap_renderer.render_terrain(terrains, camera);
         }
+        self.culling_stats.shadow.terrain_chunks = self.shadowmap_renderer.chunk_culling.clone();
 
-        let norm_entity_by_tex = MasterRenderer::group_entities_by_tex(normal_mapped_entities);
-        for (tex_model, entity_group) in norm_entity_by_tex {
-            self.shadowmap_renderer.prepare_textured_model(tex_model);
-            self.shadowmap_renderer.render(&entity_group);
-            self.shadowmap_renderer.cleanup_textured_model();
+        self.shadowmap_renderer.stop_render();
+
+        gl::helper::pop_debug_group();
+    }
+
+    fn do_point_shadow_render_passes(&mut self, camera: &Camera, render_graph: &mut RenderGraph, entities: &Vec<Entity>, normal_mapped_entities: &Vec<Entity>, 
+                entities_with_env_map: &Vec<Entity>, player: &Player, lights: &Vec<Light>) {
+
+        self.culling_stats.point_shadow = CullCounter::default();
+        // picked once per frame with the main camera so the water passes use the same cube maps
+        self.point_shadow_renderer.point_shadows.pick_lights(lights, &camera.position);
+        if self.point_shadow_renderer.point_shadows.lights.is_empty() {
+            return;
         }
 
-        if let player::PlayerEntityType::StaticModelEntity(entity) = &player.entity {
-            self.shadowmap_renderer.prepare_textured_model(&entity.model);
-            self.shadowmap_renderer.render_entity(entity);
-            self.shadowmap_renderer.cleanup_textured_model();
+        gl::helper::push_debug_group(RenderGroup::POINT_SHADOW_MAP_PASS.id, RenderGroup::POINT_SHADOW_MAP_PASS.name);
+
+        let point_shadow_fbo = render_graph.fbo_mut(RenderResource::PointShadowMap);
+        point_shadow_fbo.bind();
+        self.point_shadow_renderer.point_shadows.shadow_map_texture = point_shadow_fbo.depth_texture.expect("The point shadow fbo must have a depth texture");
+        self.point_shadow_renderer.start_render();
+
+        // the terrain is left out, the lights sit close above it so it hardly ever shadows anything from them
+        let entity_by_tex = MasterRenderer::group_entities_by_tex(entities);
+        let norm_entity_by_tex = MasterRenderer::group_entities_by_tex(normal_mapped_entities);
+        let env_map_entity_by_tex = MasterRenderer::group_entities_by_tex(entities_with_env_map);
+        let shadowed_lights = self.point_shadow_renderer.point_shadows.lights.clone();
+        for (slot, light) in shadowed_lights.iter().enumerate() {
+            let light = &lights[*light];
+            self.point_shadow_renderer.start_light(slot, light);
+            // the cube map covers the whole sphere the light reaches, nothing outside of it gets lit so nothing outside casts a shadow
+            let light_sphere = BoundingSphere::new(light.position().expect("A light with point shadows must have a position").clone(), light.range());
+
+            let casters = ShadowCasters::collect(&[&entity_by_tex, &norm_entity_by_tex, &env_map_entity_by_tex], player, 
+                &mut self.culling_stats.point_shadow, |entity| entity.bounding_sphere().intersects(&light_sphere));
+            for (tex_model, entities) in casters.instanced.iter() {
+                self.point_shadow_renderer.prepare_textured_model(tex_model);
+                self.point_shadow_renderer.render(tex_model, entities);
+                self.point_shadow_renderer.cleanup_textured_model();
+            }
+            for entity in casters.animated.iter() {
+                self.point_shadow_renderer.render_animated_entity(entity);
+            }
         }
 
-        self.shadowmap_renderer.render_terrain(terrains);
+        self.point_shadow_renderer.stop_render();
 
-        self.shadowmap_renderer.stop_render();
+        gl::helper::pop_debug_group();
+    }
 
+    // renders the faces of the probes that are due into their cubes of the probe cube map array
+    // the probes see the same lit scene as the camera minus the water and the env mapped entities which would have to reflect themselves
+    fn do_reflection_probe_pass(&mut self, scene: &mut Scene, render_graph: &mut RenderGraph, display: &Display) {
+        self.culling_stats.reflection_probes = PassCulling::default();
+        let due_probes: Vec<usize> = scene.reflection_probes.iter_mut().take(ReflectionProbe::MAX_REFLECTION_PROBES).enumerate()
+            .filter_map(|(slot, probe)| if probe.should_capture() { Some(slot) } else { None })
+            .collect();
+        if due_probes.is_empty() {
+            return;
+        }
+
+        gl::helper::push_debug_group(RenderGroup::REFLECTION_PROBES.id, RenderGroup::REFLECTION_PROBES.name);
+        let camera_projection = self.projection_matrix.clone();
+        let above_infinity_plane = Vector4f::new(0.0, -1.0, 0.0, 10_000.0);
+        let mut probe_culling = PassCulling::default();
+        // face by face so every projection is loaded once for all the probes
+        for face in 0..gl::helper::CUBEMAP_FACES.len() {
+            self.load_projection_matrix(&ReflectionProbe::face_projection(face));
+            for slot in due_probes.iter() {
+                let probe_camera = scene.reflection_probes[*slot].face_camera(face);
+                let probe_fbo = render_graph.fbo_mut(RenderResource::ReflectionProbes);
+                probe_fbo.bind();
+                probe_fbo.bind_color_layer(6 * slot + face);
+                let culling = self.render_pass(&scene.lights, &probe_camera, &scene.entities, &scene.normal_mapped_entities, &scene.ground.terrains, 
+                    &scene.player, &scene.skybox, &display.wall_clock, &above_infinity_plane);
+                probe_culling.add(&culling);
+            }
+        }
+        self.load_projection_matrix(&camera_projection);
+        self.culling_stats.reflection_probes = probe_culling;
+
+        display.restore_default_framebuffer();
         gl::helper::pop_debug_group();
     }
 
-    fn do_water_render_passes(&mut self, water_tiles: &Vec<WaterTile>, camera: &mut Camera, framebuffers: &mut FboMap,
+    // which water tiles are in view and which heights get a planar reflection this frame
+    fn plan_water(&mut self, water_tiles: &Vec<WaterTile>, camera: &Camera) {
+        self.culling_stats.water_tiles = CullCounter::default();
+        let frustum = Frustum::from_view_projection(&self.projection_matrix, &Matrix4f::create_view_matrix(camera));
+        self.water_planes = WaterPlanes::plan(water_tiles, &camera.position, &frustum, WaterRenderer::MAX_WATER_PLANES);
+        for is_visible in self.water_planes.visible_tiles.iter() {
+            self.culling_stats.water_tiles.count(*is_visible);
+        }
+    }
+
+    // the screen space reflections march through the opaque scene without the water and the transparent things
+    fn copy_scene(render_graph: &RenderGraph, display: &Display) {
+        let camera_multisampled_fbo = render_graph.fbo(RenderResource::CameraMultisampled);
+        camera_multisampled_fbo.resolve_to_fbo(gl::COLOR_ATTACHMENT0, render_graph.fbo(RenderResource::SceneCopy), display);
+    }
+
+    // the glossy entities get their reflections blended over the scene, the water ones stay in the fbo for the water surface
+    fn do_screen_space_reflection_pass(&mut self, scene: &Scene, render_graph: &mut RenderGraph, display: &Display) {
+        let frustum = Frustum::from_view_projection(&self.projection_matrix, &Matrix4f::create_view_matrix(&scene.camera));
+        let glossy_entities: Vec<&Entity> = scene.entities.iter().chain(scene.normal_mapped_entities.iter())
+            .filter(|entity| ScreenSpaceReflectionRenderer::reflection_weight(&entity.model).is_some() && entity.is_in_frustum(&frustum))
+            .collect();
+        let has_ssr_water = (0..scene.water.len())
+            .any(|tile| self.water_planes.tile_reflection(&scene.water, tile).is_some_and(|reflection| reflection.uses_screen_space()));
+
+        if !glossy_entities.is_empty() {
+            MasterRenderer::clear_ssr_fbo(render_graph);
+            self.ssr_renderer.render_entities(&glossy_entities, &scene.camera, render_graph.fbo(RenderResource::SceneCopy), &scene.skybox, &display.wall_clock);
+            let ssr_texture = render_graph.color_texture(RenderResource::ScreenSpaceReflection);
+            render_graph.fbo_mut(RenderResource::CameraMultisampled).bind();
+            self.ssr_renderer.composite(ssr_texture, &scene.quad_model);
+        }
+        if has_ssr_water {
+            MasterRenderer::clear_ssr_fbo(render_graph);
+            self.ssr_renderer.render_water(&scene.water, &self.water_planes, &scene.camera, render_graph.fbo(RenderResource::SceneCopy), &scene.skybox, &display.wall_clock);
+        }
+        display.restore_default_framebuffer();
+    }
+
+    fn clear_ssr_fbo(render_graph: &mut RenderGraph) {
+        render_graph.fbo_mut(RenderResource::ScreenSpaceReflection).bind();
+        // nothing reflected where nothing was drawn
+        gl::clear_color(0.0, 0.0, 0.0, 0.0);
+        gl::clear(gl::COLOR_BUFFER_BIT);
+    }
+
+    fn do_water_render_passes(&mut self, camera: &mut Camera, render_graph: &mut RenderGraph,
                 entities: &Vec<Entity>, normal_mapped_entities: &Vec<Entity>, terrains: &Vec<Terrain>, player: &Player, lights: &Vec<Light>,
                 skybox: &Skybox, display: &Display) {
 
-        if water_tiles.is_empty() {
+        self.culling_stats.reflection = PassCulling::default();
+        self.culling_stats.refraction = PassCulling::default();
+        if self.water_planes.heights.is_empty() {
             return;
         }
 
@@ -164,42 +571,57 @@ impl MasterRenderer {
         // enable clip plane                    
         gl::enable(gl::CLIP_DISTANCE0);
 
-        let water_height = WaterTile::get_water_height(water_tiles);
-        let tiny_overlap = 0.07; // to prevent glitches near the edge of the water
-        let above_water_clip_plane = Vector4f::new(0.0, -1.0, 0.0, water_height + tiny_overlap);
-        let below_water_clip_plane = Vector4f::new(0.0, 1.0, 0.0, -water_height + tiny_overlap);        
-        
-        camera.set_to_reflected_ray_camera_origin(water_height);
-        let reflection_fbo = framebuffers.fbos.get_mut(FboMap::REFLECTION_FBO).expect("Must have reflection fbo for water render");
-        reflection_fbo.bind();
-        self.render_pass(lights, camera, entities, normal_mapped_entities, terrains, player, skybox, &display.wall_clock, &below_water_clip_plane);
-        camera.set_to_reflected_ray_camera_origin(water_height);
-
-        // we should also move camera before refraction to account for refracted angle?
-        let refraction_fbo = framebuffers.fbos.get_mut(FboMap::REFRACTION_FBO).expect("Must have refraction fbo for water render");
-        refraction_fbo.bind();
-        self.render_pass(lights, camera, entities, normal_mapped_entities, terrains, player, skybox, &display.wall_clock, &above_water_clip_plane);
+        // the passes of all planes add up in the culling stats
+        let mut reflection_culling = PassCulling::default();
+        let mut refraction_culling = PassCulling::default();
+        let water_heights = self.water_planes.heights.clone();
+        for (plane, water_height) in water_heights.into_iter().enumerate() {
+            let tiny_overlap = 0.07; // to prevent glitches near the edge of the water
+            let above_water_clip_plane = Vector4f::new(0.0, -1.0, 0.0, water_height + tiny_overlap);
+            let below_water_clip_plane = Vector4f::new(0.0, 1.0, 0.0, -water_height + tiny_overlap);        
+            
+            camera.set_to_reflected_ray_camera_origin(water_height);
+            render_graph.fbo_mut(RenderResource::Reflection(plane)).bind();
+            let culling = self.render_pass(lights, camera, entities, normal_mapped_entities, terrains, player, skybox, &display.wall_clock, &below_water_clip_plane);
+            reflection_culling.add(&culling);
+            camera.set_to_reflected_ray_camera_origin(water_height);
+
+            // we should also move camera before refraction to account for refracted angle?
+            render_graph.fbo_mut(RenderResource::Refraction(plane)).bind();
+            let culling = self.render_pass(lights, camera, entities, normal_mapped_entities, terrains, player, skybox, &display.wall_clock, &above_water_clip_plane);
+            refraction_culling.add(&culling);
+        }
+        self.culling_stats.reflection = reflection_culling;
+        self.culling_stats.refraction = refraction_culling;
 
         gl::disable(gl::CLIP_DISTANCE0); // apparently this doesnt work on all drivers?   
 
         gl::helper::pop_debug_group();     
     }
 
+    // returns what the pass culled
     fn render_pass(&mut self, lights: &Vec<Light>, camera: &Camera, entities: &Vec<Entity>, normal_mapped_entities: &Vec<Entity>, terrains: &Vec<Terrain>, 
-                player: &Player, skybox: &Skybox, wall_clock: &WallClock, clip_plane: &Vector4f) {
+                player: &Player, skybox: &Skybox, wall_clock: &WallClock, clip_plane: &Vector4f) -> PassCulling {
+        let mut culling = PassCulling::default();
+        let frustum = Frustum::from_view_projection(&self.projection_matrix, &Matrix4f::create_view_matrix(camera));
+        let entity_by_tex = MasterRenderer::group_visible_entities_by_tex(entities, &frustum, &mut culling.entities);
+        let norm_entity_by_tex = MasterRenderer::group_visible_entities_by_tex(normal_mapped_entities, &frustum, &mut culling.entities);
 
         gl::helper::push_debug_group(RenderGroup::DRAW_ENTITIES.id, RenderGroup::DRAW_ENTITIES.name);
         self.prepare();
+        // the reflection pass has its own camera so the lights get binned for every pass
+        self.light_clusters.update(lights, camera);
+        self.light_clusters.bind();
+        self.point_shadow_renderer.bind_shadow_maps();
 
         // render entites
-        self.entity_renderer.start_render(lights, camera, &MasterRenderer::SKY_COLOR, &self.shadowmap_renderer.get_to_shadow(), &self.shadowmap_renderer.shadow_params);
-        let groups_by_tex = MasterRenderer::group_entities_by_tex(entities);
-        for (textured_model, entity_vec) in groups_by_tex.iter() {
-            self.entity_renderer.prepare_textured_model(textured_model, clip_plane);
-            for entity in entity_vec {
-                // load transform matrix into shader
-                self.entity_renderer.render(entity);
+        self.entity_renderer.start_render(&self.light_clusters, camera, &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows);
+        for (textured_model, entity_vec) in entity_by_tex.iter() {
+            if textured_model.pbr_material.is_some() {
+                continue;
             }
+            self.entity_renderer.prepare_textured_model(textured_model, clip_plane);
+            self.entity_renderer.render(textured_model, entity_vec);
             self.entity_renderer.unprepare_textured_model(textured_model);
         }        
         // render player
@@ -207,11 +629,11 @@ impl MasterRenderer {
             match &player.entity {
                 player::PlayerEntityType::StaticModelEntity(entity) => {
                     self.entity_renderer.prepare_textured_model(&entity.model, clip_plane); 
-                    self.entity_renderer.render(entity);
+                    self.entity_renderer.render(&entity.model, &[entity]);
                     self.entity_renderer.unprepare_textured_model(&entity.model);
                 },
                 player::PlayerEntityType::AnimatedModelEntity(entity) => {
-                    self.animated_entity_renderer.render(entity, camera);
+                    self.animated_entity_renderer.render(entity, camera, &self.light_clusters, &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows);
                 },
             }
         }
@@ -221,51 +643,126 @@ impl MasterRenderer {
 
         gl::helper::push_debug_group(RenderGroup::DRAW_NORMAL_MAP_ENTITIES.id, RenderGroup::DRAW_NORMAL_MAP_ENTITIES.name);
         // render normal mapped entites
-        self.normal_map_entity_renderer.start_render(lights, camera, &MasterRenderer::SKY_COLOR);
-        let groups_by_tex = MasterRenderer::group_entities_by_tex(normal_mapped_entities);
-        for (textured_model, entity_vec) in groups_by_tex.iter() {
-            self.normal_map_entity_renderer.prepare_textured_model(textured_model, clip_plane);
-            for entity in entity_vec {
-                // load transform matrix into shader
-                self.normal_map_entity_renderer.render(entity);
+        self.normal_map_entity_renderer.start_render(&self.light_clusters, camera, &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows);
+        for (textured_model, entity_vec) in norm_entity_by_tex.iter() {
+            if textured_model.pbr_material.is_some() {
+                continue;
             }
+            self.normal_map_entity_renderer.prepare_textured_model(textured_model, clip_plane);
+            self.normal_map_entity_renderer.render(textured_model, entity_vec);
             self.normal_map_entity_renderer.unprepare_textured_model(textured_model);
         }
         self.normal_map_entity_renderer.stop_render(); 
         gl::helper::pop_debug_group();
 
+        self.render_pbr_entities(camera, &[&entity_by_tex, &norm_entity_by_tex], skybox, wall_clock, clip_plane);
+
         // render terrain
         gl::helper::push_debug_group(RenderGroup::DRAW_TERRAIN.id, RenderGroup::DRAW_TERRAIN.name);
-        self.terrain_renderer.start_render(lights, camera, &MasterRenderer::SKY_COLOR, &self.shadowmap_renderer.get_to_shadow(), &self.shadowmap_renderer.shadow_params);
+        self.terrain_renderer.start_render(&self.light_clusters, camera, &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows);
         for terrain in terrains.iter() {
             self.terrain_renderer.prepare_terrain(terrain, clip_plane);
-            self.terrain_renderer.render(terrain);
+            self.terrain_renderer.render(terrain, camera);
             self.terrain_renderer.unprepare_terrain();
         }
         self.terrain_renderer.stop_render();
+        culling.terrain_chunks = self.terrain_renderer.chunk_culling.clone();
         gl::helper::pop_debug_group();
 
         gl::helper::push_debug_group(RenderGroup::DRAW_SKYBOX.id, RenderGroup::DRAW_SKYBOX.name);
-        self.skybox_renderer.render(camera, skybox, &MasterRenderer::SKY_COLOR, wall_clock, clip_plane);
+        self.skybox_renderer.render(camera, skybox, wall_clock, clip_plane);
         gl::helper::pop_debug_group();
+
+        culling
     }
     
+    // the opaque geometry of the main camera goes through the g-buffer and gets lit into the camera fbo
+    // the animated player and the skybox are then drawn forward on top like in render_pass
+    fn render_deferred_pass(&mut self, lights: &Vec<Light>, camera: &Camera, entities: &Vec<Entity>, normal_mapped_entities: &Vec<Entity>, terrains: &Vec<Terrain>, 
+                player: &Player, skybox: &Skybox, wall_clock: &WallClock, render_graph: &mut RenderGraph) -> PassCulling {
+        let mut culling = PassCulling::default();
+        let frustum = Frustum::from_view_projection(&self.projection_matrix, &Matrix4f::create_view_matrix(camera));
+
+        self.light_clusters.update(lights, camera);
+        self.light_clusters.bind();
+        self.point_shadow_renderer.bind_shadow_maps();
+
+        gl::helper::push_debug_group(RenderGroup::DEFERRED_GEOMETRY_PASS.id, RenderGroup::DEFERRED_GEOMETRY_PASS.name);
+        render_graph.fbo_mut(RenderResource::GBuffer).bind();
+        self.prepare();
+        // there is no room in the g-buffer for the pbr materials, they get drawn forward after the lighting
+        let (mut entity_by_tex, pbr_entity_by_tex): (HashMap<_, _>, HashMap<_, _>) = MasterRenderer::group_visible_entities_by_tex(entities, &frustum, &mut culling.entities)
+            .into_iter().partition(|(textured_model, _)| textured_model.pbr_material.is_none());
+        let (norm_entity_by_tex, pbr_norm_entity_by_tex): (HashMap<_, _>, HashMap<_, _>) = MasterRenderer::group_visible_entities_by_tex(normal_mapped_entities, &frustum, &mut culling.entities)
+            .into_iter().partition(|(textured_model, _)| textured_model.pbr_material.is_none());
+        // a static player is just one more entity, there is no g-buffer shader for the animated one
+        if !player.is_invisible_immovable {
+            if let player::PlayerEntityType::StaticModelEntity(entity) = &player.entity {
+                entity_by_tex.entry(&entity.model).or_insert(Vec::new()).push(entity);
+            }
+        }
+        self.deferred_renderer.render_geometry(camera, &entity_by_tex, &norm_entity_by_tex, terrains, &self.light_clusters, 
+            &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows);
+        culling.terrain_chunks = self.deferred_renderer.terrain_chunk_culling().clone();
+        gl::helper::pop_debug_group();
+
+        gl::helper::push_debug_group(RenderGroup::DEFERRED_LIGHTING_PASS.id, RenderGroup::DEFERRED_LIGHTING_PASS.name);
+        render_graph.fbo_mut(RenderResource::CameraMultisampled).bind();
+        self.prepare();
+        let gbuffer = render_graph.fbo(RenderResource::GBuffer);
+        self.deferred_renderer.render_lighting(gbuffer, camera, lights, &self.light_clusters, 
+            &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows);
+        gl::helper::pop_debug_group();
+
+        if !player.is_invisible_immovable {
+            if let player::PlayerEntityType::AnimatedModelEntity(entity) = &player.entity {
+                self.animated_entity_renderer.render(entity, camera, &self.light_clusters, &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows);
+            }
+        }
+
+        let above_infinity_plane = Vector4f::new(0.0, -1.0, 0.0, 10_000.0);
+        self.render_pbr_entities(camera, &[&pbr_entity_by_tex, &pbr_norm_entity_by_tex], skybox, wall_clock, &above_infinity_plane);
+
+        gl::helper::push_debug_group(RenderGroup::DRAW_SKYBOX.id, RenderGroup::DRAW_SKYBOX.name);
+        self.skybox_renderer.render(camera, skybox, wall_clock, &above_infinity_plane);
+        gl::helper::pop_debug_group();
+
+        culling
+    }
+    
+    // the models with a pbr material from the already culled groups of the pass, the other renderers skip them
+    fn render_pbr_entities(&mut self, camera: &Camera, groups: &[&HashMap<&TexturedModel, Vec<&Entity>>], skybox: &Skybox, wall_clock: &WallClock, clip_plane: &Vector4f) {
+        gl::helper::push_debug_group(RenderGroup::DRAW_PBR_ENTITIES.id, RenderGroup::DRAW_PBR_ENTITIES.name);
+        let sky_lighting = self.ibl_renderer.sky_lighting(skybox, wall_clock);
+        self.pbr_renderer.start_render(&self.light_clusters, camera, &self.shadowmap_renderer.shadow_params, &self.point_shadow_renderer.point_shadows, 
+            sky_lighting.as_ref());
+        for (textured_model, entity_vec) in groups.iter().flat_map(|group| group.iter()) {
+            if textured_model.pbr_material.is_none() {
+                continue;
+            }
+            self.pbr_renderer.prepare_textured_model(textured_model, clip_plane);
+            for entity in entity_vec {
+                self.pbr_renderer.render(entity);
+            }
+            self.pbr_renderer.unprepare_textured_model(textured_model);
+        }
+        self.pbr_renderer.stop_render();
+        gl::helper::pop_debug_group();
+    }
+
     fn prepare(&self) {
         gl::helper::enable_backface_culling();
         gl::enable(gl::DEPTH_TEST);
-        let (Vector3f{x : r, y : g, z : b}, a) = (MasterRenderer::SKY_COLOR, 1.0);
+        let (Vector3f{x : r, y : g, z : b}, a) = (self.sky_color.clone(), 1.0);
         gl::clear_color(r, g, b, a);
         gl::clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
     }
 
     fn group_entities_by_tex<'b>(entities: &'b Vec<Entity>) -> HashMap<&'b TexturedModel, Vec<&'b Entity>> {
-        let mut groups_by_tex = HashMap::new();
-
-        for entity in entities.iter() {
-            let group = groups_by_tex.entry(&entity.model).or_insert(Vec::new());
-            group.push(entity);
-        }
+        EntityInstances::group_by_model(entities.iter())
+    }
 
-        groups_by_tex
+    fn group_visible_entities_by_tex<'b>(entities: &'b Vec<Entity>, frustum: &Frustum, culling: &mut CullCounter) -> HashMap<&'b TexturedModel, Vec<&'b Entity>> {
+        EntityInstances::group_by_model(entities.iter().filter(|entity| culling.count(entity.is_in_frustum(frustum))))
     }
 }
\ No newline at end of file
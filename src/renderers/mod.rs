@@ -7,10 +7,21 @@ pub mod skybox_renderer;
 pub mod water_renderer;
 pub mod image_renderer;
 pub mod shadowmap_renderer;
+pub mod point_shadow_renderer;
 pub mod debug_renderer;
 pub mod env_map_renderer;
 pub mod animated_entity_renderer;
+pub mod light_clusters;
+pub mod deferred_renderer;
+pub mod brdf;
+pub mod ibl_renderer;
+pub mod pbr_renderer;
+pub mod ssr_renderer;
+pub mod culling;
+pub mod entity_instances;
+pub mod render_graph;
 
 pub mod particle_renderer;
 pub mod particle_renderer_gpu_instanced;
 pub mod particle_renderer_geometry_shdr;
+pub mod precipitation_renderer;
@@ -1,12 +1,16 @@
 use std::collections::HashMap;
 use crate::entities::Camera;
+use crate::math::Matrix4f;
 use crate::models::{
     ParticleTexturedModel,
 };
 use crate::particles::Particle;
 
 pub trait ParticleRenderer {
-    fn render(&mut self, particles: &HashMap<ParticleTexturedModel, Vec<Particle>>, camera: &Camera);
+    // only the particles that passed the culling, still sorted back to front
+    fn render(&mut self, particles: &HashMap<&ParticleTexturedModel, Vec<&Particle>>, camera: &Camera);
+
+    fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f);
 }
 
 pub fn update_vbo(vbo: u32, particle_data: &Vec<f32>) {        
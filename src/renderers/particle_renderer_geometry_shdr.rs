@@ -24,7 +24,7 @@ pub struct ParticleRendererGeometryShader {
 }
 
 impl ParticleRenderer for ParticleRendererGeometryShader {
-    fn render(&mut self, particles: &HashMap<ParticleTexturedModel, Vec<Particle>>, camera: &Camera) {
+    fn render(&mut self, particles: &HashMap<&ParticleTexturedModel, Vec<&Particle>>, camera: &Camera) {
         gl::helper::push_debug_group(RenderGroup::PARTICLE_EFFECTS_PASS.id, RenderGroup::PARTICLE_EFFECTS_PASS.name);
         self.prepare();
 
@@ -60,6 +60,10 @@ impl ParticleRenderer for ParticleRendererGeometryShader {
 
         gl::helper::pop_debug_group();
     }
+
+    fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.projection_matrix = projection_matrix.clone();
+    }
 }
 
 impl ParticleRendererGeometryShader {
@@ -24,7 +24,7 @@ pub struct ParticleRendererGpuInstanced {
 }
 
 impl ParticleRenderer for ParticleRendererGpuInstanced {
-    fn render(&mut self, particles: &HashMap<ParticleTexturedModel, Vec<Particle>>, camera: &Camera) {
+    fn render(&mut self, particles: &HashMap<&ParticleTexturedModel, Vec<&Particle>>, camera: &Camera) {
         gl::helper::push_debug_group(RenderGroup::PARTICLE_EFFECTS_PASS.id, RenderGroup::PARTICLE_EFFECTS_PASS.name);
         self.prepare();
 
@@ -67,6 +67,12 @@ impl ParticleRenderer for ParticleRendererGpuInstanced {
         self.finish_rendering();
         gl::helper::pop_debug_group();
     }
+
+    fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.shader.start();
+        self.shader.load_projection_matrix(projection_matrix);
+        self.shader.stop();
+    }
 }
 
 impl ParticleRendererGpuInstanced {
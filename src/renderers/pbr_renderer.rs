@@ -0,0 +1,135 @@
+use crate::gl;
+use super::ibl_renderer::SkyLighting;
+use super::light_clusters::LightClusters;
+use crate::entities::{
+    Entity,
+    Camera,
+    Weather,
+};
+use crate::shaders::PbrShader;
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
+};
+use crate::math::{
+    Matrix4f,
+    Vector4f,
+};
+use crate::models::{
+    TexturedModel,
+    RawModel,
+};
+
+// models with a pbr material, normal mapped or not. they are always drawn forward, also when the rest goes through the g-buffer
+pub struct PbrRenderer {
+    shader: PbrShader,
+}
+
+impl PbrRenderer {
+    pub fn new(projection_matrix: &Matrix4f) -> PbrRenderer {
+        let mut shader = PbrShader::new();
+        shader.start();
+        shader.load_projection_matrix(projection_matrix);
+        shader.connect_texture_units();
+        shader.stop();
+        PbrRenderer {
+            shader,
+        }
+    }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.shader.start();
+        self.shader.load_projection_matrix(projection_matrix);
+        self.shader.stop();
+    }
+
+    // the weather changes every frame so the master renderer loads it once at the start of it
+    pub fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.shader.start();
+        self.shader.load_weather(weather, time);
+        self.shader.stop();
+    }
+
+    pub fn start_render(&mut self, light_clusters: &LightClusters, camera: &Camera, shadow_params: &ShadowParams, point_shadows: &PointShadows,
+                sky_lighting: Option<&SkyLighting>) {
+        self.shader.start();
+        self.shader.load_light_clusters(light_clusters);
+        self.shader.load_view_matrix(&Matrix4f::create_view_matrix(camera));
+        self.shader.load_camera_position(&camera.position);
+        self.shader.load_shadow_params(shadow_params);
+        self.shader.load_point_shadows(point_shadows);
+        self.shader.load_sky_lighting(sky_lighting);
+
+        // texture units 0 to 4 are the material's maps
+        gl::active_texture(gl::TEXTURE5);
+        gl::bind_texture(gl::TEXTURE_2D_ARRAY, shadow_params.shadow_map_texture);
+        if let Some(sky_lighting) = sky_lighting {
+            gl::active_texture(gl::TEXTURE6);
+            gl::bind_texture(gl::TEXTURE_CUBE_MAP, sky_lighting.from.irradiance_map);
+            gl::active_texture(gl::TEXTURE7);
+            gl::bind_texture(gl::TEXTURE_CUBE_MAP, sky_lighting.from.prefiltered_map);
+            gl::active_texture(gl::TEXTURE8);
+            gl::bind_texture(gl::TEXTURE_CUBE_MAP, sky_lighting.to.irradiance_map);
+            gl::active_texture(gl::TEXTURE9);
+            gl::bind_texture(gl::TEXTURE_CUBE_MAP, sky_lighting.to.prefiltered_map);
+            gl::active_texture(gl::TEXTURE14);
+            gl::bind_texture(gl::TEXTURE_2D, sky_lighting.brdf_lut);
+        }
+    }
+
+    pub fn stop_render(&mut self) {
+        self.shader.stop();
+    }
+
+    pub fn prepare_textured_model(&mut self, textured_model: &TexturedModel, clip_plane: &Vector4f) {
+        let material = textured_model.pbr_material.as_ref().expect("The pbr renderer only draws models with a pbr material");
+        if textured_model.texture.has_transparency {
+            gl::helper::disable_culling();
+        }
+
+        gl::bind_vertex_array(textured_model.raw_model.vao_id);
+        gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::NORMAL_ATTRIB);
+        // only models with a normal map get loaded with tangents
+        if textured_model.normal_map_tex_id.is_some() {
+            gl::enable_vertex_attrib_array(RawModel::TANGENT_ATTRIB);
+        }
+
+        self.shader.load_material(material, textured_model.normal_map_tex_id.is_some());
+        self.shader.load_atlas_number_of_rows(textured_model.texture.number_of_rows_in_atlas);
+        self.shader.load_clip_plane(clip_plane);
+
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_2D, textured_model.texture.tex_id.unwrap());
+        let maps = [textured_model.normal_map_tex_id, material.metallic_roughness_tex_id, material.occlusion_tex_id, material.emissive_tex_id];
+        for (unit, map) in [gl::TEXTURE1, gl::TEXTURE2, gl::TEXTURE3, gl::TEXTURE4].iter().zip(maps.iter()) {
+            if let Some(tex_id) = map {
+                gl::active_texture(*unit);
+                gl::bind_texture(gl::TEXTURE_2D, tex_id.unwrap());
+            }
+        }
+    }
+
+    pub fn render(&mut self, entity: &Entity) {
+        let transform_mat = Matrix4f::create_transform_matrix(&entity.position, &entity.rotation_deg, entity.scale);
+        self.shader.load_transformation_matrix(&transform_mat);
+        self.shader.load_atlas_offset(&entity.get_atlas_offset());
+
+        gl::draw_elements(gl::TRIANGLES, entity.model.raw_model.vertex_count, gl::UNSIGNED_INT);
+    }
+
+    pub fn unprepare_textured_model(&self, textured_model: &TexturedModel) {
+        if textured_model.texture.has_transparency {
+            gl::helper::enable_backface_culling(); // restore backbace culling for next model
+        }
+        gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::NORMAL_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::TANGENT_ATTRIB);
+
+        gl::bind_vertex_array(0);
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_2D, 0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,132 @@
+use crate::animations::joint::AccumulatedJointTransforms;
+use crate::entities::{
+    AnimatedEntity,
+    Entity,
+    Light,
+    Weather,
+};
+use crate::gl;
+use crate::math::Matrix4f;
+use crate::models::{
+    RawModel,
+    TexturedModel,
+};
+use crate::shadows::point_shadows::PointShadows;
+use crate::shaders::PointShadowShader;
+use super::entity_instances::EntityInstances;
+
+// renders the cube depth maps of the point lights that PointShadows picked
+// every light is one instanced draw per model since the geometry shader sends each triangle to all 6 faces
+pub struct PointShadowRenderer {
+    shader: PointShadowShader,
+    animated_shader: PointShadowShader,
+    accumulator: AccumulatedJointTransforms,
+    instances: EntityInstances,
+    pub point_shadows: PointShadows,
+}
+
+impl PointShadowRenderer {
+    // the texture unit the lit shaders read the cube map array from, after the light cluster units
+    pub const SHADOW_MAP_UNIT: i32 = 13;
+    // size of every face of the point light cube maps
+    pub const SHADOW_MAP_SIZE: usize = 512;
+
+    pub fn new() -> Self {
+        PointShadowRenderer {
+            shader: PointShadowShader::new(),
+            animated_shader: PointShadowShader::new_animated(),
+            accumulator: AccumulatedJointTransforms::new(),
+            instances: EntityInstances::new(),
+            point_shadows: PointShadows::new(PointShadows::MAX_POINT_SHADOWS, PointShadowRenderer::SHADOW_MAP_SIZE),
+        }
+    }
+
+    // the plants sway in the shadows like they do in the entity renderer
+    pub fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.shader.start();
+        self.shader.load_weather(weather, time);
+        self.shader.stop();
+    }
+
+    pub fn start_render(&mut self) {
+        gl::enable(gl::DEPTH_TEST);
+        // clears the layers of all the cubes, whatever isn't rendered to stays at the max distance which means no shadow
+        gl::clear(gl::DEPTH_BUFFER_BIT);
+        self.shader.start();
+    }
+
+    // slot is the light's cube in the cube map array
+    pub fn start_light(&mut self, slot: usize, light: &Light) {
+        let position = light.position().expect("Only lights with a position can have a cube shadow map");
+        let range = light.range();
+        let face_matrices = PointShadows::face_matrices(position, range);
+        self.shader.load_light(slot, &face_matrices, position, range);
+        // the skinned models have their own shader that needs the same light
+        self.animated_shader.start();
+        self.animated_shader.load_light(slot, &face_matrices, position, range);
+        self.animated_shader.stop();
+        self.shader.start();
+    }
+
+    pub fn prepare_textured_model(&mut self, model: &TexturedModel) {
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_2D, model.texture.tex_id.unwrap());
+        gl::bind_vertex_array(model.raw_model.vao_id);
+        gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        EntityInstances::enable_attribs();
+        self.shader.load_sways_in_wind(model.texture.sways_in_wind);
+    }
+
+    // the entities all have the prepared model
+    pub fn render(&mut self, model: &TexturedModel, entities: &[&Entity]) {
+        self.instances.render(model, entities);
+    }
+
+    // skinned models need their own shader, we switch back to the normal one when done so the other renders can carry on
+    pub fn render_animated_entity(&mut self, animated_entity: &AnimatedEntity) {
+        self.shader.stop();
+        self.animated_shader.start();
+
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_2D, animated_entity.model.tex_id.unwrap());
+        gl::bind_vertex_array(animated_entity.model.raw_model.vao_id);
+        gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::JOINT_IDX_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::JOINT_WEIGHT_ATTRIB);
+
+        let transform_mat = Matrix4f::create_transform_matrix(&animated_entity.position, &animated_entity.rotation_deg, animated_entity.scale);
+        self.animated_shader.load_transform_matrix(&transform_mat);
+        animated_entity.model.root_joint.collect_transforms(&mut self.accumulator);
+        self.animated_shader.load_joint_transforms(&self.accumulator);
+
+        gl::draw_elements(gl::TRIANGLES, animated_entity.model.raw_model.vertex_count, gl::UNSIGNED_INT);
+
+        gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::JOINT_IDX_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::JOINT_WEIGHT_ATTRIB);
+        gl::bind_vertex_array(0);
+
+        self.animated_shader.stop();
+        self.shader.start();
+    }
+
+    pub fn cleanup_textured_model(&mut self) {
+        gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        EntityInstances::disable_attribs();
+        gl::bind_vertex_array(0);
+    }
+
+    pub fn stop_render(&mut self) {
+        self.shader.stop();
+    }
+
+    pub fn bind_shadow_maps(&self) {
+        // has to match SHADOW_MAP_UNIT
+        gl::active_texture(gl::TEXTURE13);
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP_ARRAY, self.point_shadows.shadow_map_texture);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::entities::{
+    Camera,
+    Weather,
+};
+use crate::gl;
+use crate::math::{
+    Matrix4f,
+    Vector2f,
+    Vector3f,
+    Vector4f,
+};
+use crate::models::RawModel;
+use crate::shaders::PrecipitationShader;
+use super::master_renderer::RenderGroup;
+
+// how one kind of precipitation falls and looks
+struct Drops {
+    // at full intensity, the weather scales it down
+    max_count: usize,
+    fall_speed: f32,
+    size: Vector2f,
+    flutter: f32,
+    color: Vector4f,
+}
+
+// draws the rain and the snow of the weather as instances of the quad in a box that moves with the camera
+// there is no per drop data, the shader works out where every drop is from its instance id and the time
+pub struct PrecipitationRenderer {
+    shader: PrecipitationShader,
+    // the wind changes with the gusts so how far it has blown the drops is summed up here instead of being wind * time
+    wind_drift: Vector3f,
+    last_time: f32,
+}
+
+impl PrecipitationRenderer {
+    const AREA: Vector3f = Vector3f{ x: 40.0, y: 30.0, z: 40.0 };
+    // the drops fall at this many different speeds per fall speed, keep in sync with precipitationVert.glsl
+    const SPEED_STEPS: f32 = 10.0;
+
+    const RAIN: Drops = Drops {
+        max_count: 20_000,
+        fall_speed: 25.0,
+        size: Vector2f{ x: 0.01, y: 0.3 },
+        flutter: 0.0,
+        color: Vector4f{ x: 0.7, y: 0.75, z: 0.8, w: 0.35 },
+    };
+
+    const SNOW: Drops = Drops {
+        max_count: 15_000,
+        fall_speed: 1.5,
+        size: Vector2f{ x: 0.04, y: 0.04 },
+        flutter: 0.5,
+        color: Vector4f{ x: 0.95, y: 0.95, z: 1.0, w: 0.8 },
+    };
+
+    pub fn new(projection_matrix: &Matrix4f) -> PrecipitationRenderer {
+        let mut shader = PrecipitationShader::new();
+        shader.start();
+        shader.load_projection_matrix(projection_matrix);
+        shader.stop();
+        PrecipitationRenderer {
+            shader,
+            wind_drift: Vector3f::new(0.0, 0.0, 0.0),
+            last_time: 0.0,
+        }
+    }
+
+    // in this long every drop speed of the shader falls through the box a whole number of times
+    // so the time can start over without the drops jumping, and the floats stay small however long the game runs
+    pub fn wrap_period(fall_speed: f32) -> f32 {
+        PrecipitationRenderer::SPEED_STEPS * PrecipitationRenderer::AREA.y / fall_speed
+    }
+
+    pub fn drift_with_wind(wind_drift: &Vector3f, wind: &Vector3f, dt: f32) -> Vector3f {
+        let area = &PrecipitationRenderer::AREA;
+        Vector3f::new((wind_drift.x + wind.x * dt).rem_euclid(area.x), 0.0, (wind_drift.z + wind.z * dt).rem_euclid(area.z))
+    }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.shader.start();
+        self.shader.load_projection_matrix(projection_matrix);
+        self.shader.stop();
+    }
+
+    pub fn render(&mut self, weather: &Weather, camera: &Camera, quad: &RawModel, time: f32) {
+        // the wall clock wraps around too so the time can go backwards once in a while
+        let dt = (time - self.last_time).max(0.0);
+        self.last_time = time;
+        self.wind_drift = PrecipitationRenderer::drift_with_wind(&self.wind_drift, &weather.wind, dt);
+        if !weather.is_precipitating() {
+            return;
+        }
+        gl::helper::push_debug_group(RenderGroup::PRECIPITATION.id, RenderGroup::PRECIPITATION.name);
+        self.shader.start();
+        self.shader.load_camera(camera);
+        // like the particles the drops get depth tested but don't write depth so they don't hide each other
+        gl::depth_mask(false);
+        gl::enable(gl::BLEND);
+        gl::blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl::bind_vertex_array(quad.vao_id);
+        gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+
+        self.draw_drops(&PrecipitationRenderer::RAIN, weather.rain, &weather.wind, quad, time);
+        self.draw_drops(&PrecipitationRenderer::SNOW, weather.snow, &weather.wind, quad, time);
+
+        gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::bind_vertex_array(0);
+        gl::depth_mask(true);
+        gl::disable(gl::BLEND);
+        self.shader.stop();
+        gl::helper::pop_debug_group();
+    }
+
+    fn draw_drops(&mut self, drops: &Drops, amount: f32, wind: &Vector3f, quad: &RawModel, time: f32) {
+        let count = (amount * drops.max_count as f32) as usize;
+        if count == 0 {
+            return;
+        }
+        let period = PrecipitationRenderer::wrap_period(drops.fall_speed);
+        self.shader.load_motion(time % period, period, drops.fall_speed, wind, &self.wind_drift, &PrecipitationRenderer::AREA);
+        self.shader.load_drop(&drops.size, drops.flutter, &drops.color);
+        gl::draw_arrays_instanced(gl::TRIANGLE_STRIP, 0, quad.vertex_count, count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drops_fall_whole_boxes_in_a_period() {
+        for drops in [PrecipitationRenderer::RAIN, PrecipitationRenderer::SNOW].iter() {
+            let period = PrecipitationRenderer::wrap_period(drops.fall_speed);
+            // the speeds the shader picks from
+            for step in 8..=12 {
+                let fall = drops.fall_speed * step as f32 / PrecipitationRenderer::SPEED_STEPS;
+                let boxes = fall * period / PrecipitationRenderer::AREA.y;
+                assert!((boxes - boxes.round()).abs() < 1e-4, "speed step {} falls {} boxes", step, boxes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wind_drift_stays_in_the_box() {
+        let wind = Vector3f::new(7.3, 0.0, -3.1);
+        let mut drift = Vector3f::new(0.0, 0.0, 0.0);
+        for _ in 0..10_000 {
+            drift = PrecipitationRenderer::drift_with_wind(&drift, &wind, 0.1);
+            assert!(drift.x >= 0.0 && drift.x <= PrecipitationRenderer::AREA.x);
+            assert!(drift.z >= 0.0 && drift.z <= PrecipitationRenderer::AREA.z);
+        }
+        // 7300 and -3100 meters blown around the box
+        assert!((drift.x - 7300.0f32.rem_euclid(PrecipitationRenderer::AREA.x)).abs() < 0.1);
+        assert!((drift.z - (-3100.0f32).rem_euclid(PrecipitationRenderer::AREA.z)).abs() < 0.1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,697 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::display::{
+    Display,
+    framebuffers::FramebufferObject,
+    framebuffers::FboFlags,
+};
+use crate::entities::ReflectionProbe;
+use crate::entities::atmosphere;
+use crate::post_processing::post_processing::PostProcessing;
+use crate::post_processing::post_effect;
+use crate::post_processing::ssao::SsaoKernel;
+use crate::shadows::{
+    shadow_box::ShadowBox,
+    point_shadows::PointShadows,
+};
+use super::shadowmap_renderer::ShadowMapRenderer;
+use super::point_shadow_renderer::PointShadowRenderer;
+use super::water_renderer::WaterRenderer;
+
+// everything that gets drawn in a frame, the graph decides in which order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderPass {
+    ShadowMap,
+    PointShadowMap,
+    ReflectionProbes,
+    WaterReflectRefract,
+    ForwardScene,
+    DeferredScene,
+    ScreenSpaceReflection,
+    WaterSurface,
+    EnvMapEntities,
+    Particles,
+    MsaaResolve,
+    Ssao,
+    SsaoBlurHorizontal,
+    SsaoBlurVertical,
+    SsaoApply,
+    LightShafts,
+    Atmosphere,
+    BloomBlurHorizontal,
+    BloomBlurVertical,
+    BloomCombine,
+    // one for every effect the post processing stack can have, the unused ones are disabled
+    PostEffect(usize),
+    // copies the output of the last effect to the window
+    Present,
+    Gui,
+}
+
+impl RenderPass {
+    // the optional bloom between the msaa resolve and the post effects
+    pub const POST_PROCESSING_EFFECTS: [RenderPass; 3] = [RenderPass::BloomBlurHorizontal, RenderPass::BloomBlurVertical, RenderPass::BloomCombine];
+    // the ambient occlusion between the msaa resolve and the bloom
+    pub const AMBIENT_OCCLUSION: [RenderPass; 4] = [RenderPass::Ssao, RenderPass::SsaoBlurHorizontal, RenderPass::SsaoBlurVertical, RenderPass::SsaoApply];
+    // the fog and the light shafts of the scene between the ambient occlusion and the bloom
+    pub const ATMOSPHERE: [RenderPass; 2] = [RenderPass::LightShafts, RenderPass::Atmosphere];
+}
+
+// the fbos the passes read from and write into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderResource {
+    ShadowMap,
+    PointShadowMap,
+    // the cube maps of the reflection probes, they only get rendered again when a probe asks for it
+    ReflectionProbes,
+    // one pair for every water plane that can be rendered in a frame
+    Reflection(usize),
+    Refraction(usize),
+    GBuffer,
+    CameraMultisampled,
+    // the opaque scene resolved with its depth, the screen space reflections march through it
+    SceneCopy,
+    ScreenSpaceReflection,
+    CameraTexture,
+    CameraBrightness,
+    Ssao,
+    SsaoBlurHorizontal,
+    SsaoBlurVertical,
+    // the camera texture darkened by the occlusion
+    AmbientOccluded,
+    LightShafts,
+    // the ambient occluded scene with the fog and the light shafts
+    Atmosphere,
+    BloomBlurHorizontal,
+    BloomBlurVertical,
+    BloomCombined,
+    PostEffect(usize),
+    // the window, nothing gets allocated for it
+    Backbuffer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FboDesc {
+    Sized { width: usize, height: usize, flags: FboFlags, color_attachments: usize },
+    // follows the render size of the display, the divisor shrinks it for the blur steps
+    ScreenSized { divisor: usize, flags: FboFlags, color_attachments: usize },
+    LayeredShadowDepth { size: usize, layers: usize },
+    CubeShadowDepth { size: usize, cubes: usize },
+    CubeColor { size: usize, cubes: usize },
+    Backbuffer,
+}
+
+impl FboDesc {
+    fn create(&self, display: &Display) -> Option<FramebufferObject> {
+        match *self {
+            FboDesc::Sized { width, height, flags, color_attachments } => Some(FramebufferObject::new(width, height, flags, color_attachments)),
+            FboDesc::ScreenSized { divisor, flags, color_attachments } => {
+                let size = display.get_render_size();
+                Some(FramebufferObject::new(size.width / divisor, size.height / divisor, flags, color_attachments))
+            },
+            FboDesc::LayeredShadowDepth { size, layers } => Some(FramebufferObject::new_layered_shadow_depth(size, size, layers)),
+            FboDesc::CubeShadowDepth { size, cubes } => Some(FramebufferObject::new_cube_shadow_depth(size, cubes)),
+            FboDesc::CubeColor { size, cubes } => Some(FramebufferObject::new_cube_color(size, cubes)),
+            FboDesc::Backbuffer => None,
+        }
+    }
+
+    fn follows_render_size(&self) -> bool {
+        matches!(self, FboDesc::ScreenSized { .. })
+    }
+}
+
+struct ResourceDecl {
+    resource: RenderResource,
+    desc: FboDesc,
+    // read outside of the frame (shadow maps shown in the gui) so they never share their fbo with anything else
+    persistent: bool,
+}
+
+pub struct PassDecl {
+    pass: RenderPass,
+    reads: Vec<RenderResource>,
+    writes: Vec<RenderResource>,
+    // when the pass is disabled its readers get the input instead of the output
+    forward: Option<(RenderResource, RenderResource)>,
+    enabled: bool,
+}
+
+impl PassDecl {
+    pub fn forwards_when_disabled(&mut self, from: RenderResource, to: RenderResource) -> &mut PassDecl {
+        self.forward = Some((from, to));
+        self
+    }
+}
+
+// the pass order and where every resource lives, only depends on the declarations so it can be tested without a gl context
+#[derive(Debug, Default)]
+pub struct GraphPlan {
+    pub order: Vec<RenderPass>,
+    // the passes the resource is alive for, as indices into the order
+    pub lifetimes: HashMap<RenderResource, (usize, usize)>,
+    // the fbo slot of each resource, aliased resources share a slot
+    pub slots: HashMap<RenderResource, usize>,
+    pub slot_descs: Vec<FboDesc>,
+    redirects: HashMap<RenderResource, RenderResource>,
+}
+
+impl GraphPlan {
+    pub fn resolve(&self, resource: RenderResource) -> RenderResource {
+        let mut resolved = resource;
+        // every redirect comes from a different disabled pass so following them can't take longer than that
+        for _ in 0..=self.redirects.len() {
+            match self.redirects.get(&resolved) {
+                Some(from) => resolved = *from,
+                None => return resolved,
+            }
+        }
+        panic!("The disabled passes forward {:?} in a loop", resource);
+    }
+
+    pub fn is_aliased(&self, resource: RenderResource) -> bool {
+        match self.slots.get(&resource) {
+            Some(slot) => self.slots.values().filter(|other| *other == slot).count() > 1,
+            None => false,
+        }
+    }
+}
+
+pub struct RenderGraph {
+    resources: Vec<ResourceDecl>,
+    passes: Vec<PassDecl>,
+    plan: GraphPlan,
+    fbos: Vec<FramebufferObject>,
+    dirty: bool,
+    // the render size of the display the screen sized fbos were made for
+    render_size: (usize, usize),
+    // bumped on every compile so we know when the dump changed
+    generation: usize,
+}
+
+impl RenderGraph {
+    pub fn new() -> RenderGraph {
+        RenderGraph {
+            resources: Vec::new(),
+            passes: Vec::new(),
+            plan: GraphPlan::default(),
+            fbos: Vec::new(),
+            dirty: true,
+            render_size: (0, 0),
+            generation: 0,
+        }
+    }
+
+    // the frame of the engine. the declaration order only breaks ties, the reads and writes decide the rest
+    pub fn new_frame_graph() -> RenderGraph {
+        use self::RenderResource::*;
+        let hdr_color = FboFlags::COLOR_TEX | FboFlags::FLOAT_COLOR;
+        let mut graph = RenderGraph::new();
+
+        // room for as many cascades as the shaders take, the scene picks how many of them it uses
+        graph.add_persistent_resource(ShadowMap, FboDesc::LayeredShadowDepth { size: ShadowMapRenderer::SHADOW_MAP_SIZE, layers: ShadowBox::MAX_CASCADES });
+        graph.add_persistent_resource(PointShadowMap, FboDesc::CubeShadowDepth { size: PointShadowRenderer::SHADOW_MAP_SIZE, cubes: PointShadows::MAX_POINT_SHADOWS });
+        // a probe that didn't capture this frame still shows what it captured before
+        graph.add_persistent_resource(ReflectionProbes, FboDesc::CubeColor { size: ReflectionProbe::CUBE_MAP_SIZE, cubes: ReflectionProbe::MAX_REFLECTION_PROBES });
+        // everything that ends up in the scene is kept in linear hdr until the tonemapping at the end of post processing
+        let mut water_textures = Vec::new();
+        for plane in 0..WaterRenderer::MAX_WATER_PLANES {
+            graph.add_resource(Reflection(plane), FboDesc::ScreenSized { divisor: 1, flags: hdr_color, color_attachments: 1 });
+            graph.add_resource(Refraction(plane), FboDesc::ScreenSized { divisor: 1, flags: hdr_color | FboFlags::DEPTH_TEX, color_attachments: 1 });
+            water_textures.push(Reflection(plane));
+            water_textures.push(Refraction(plane));
+        }
+        // not multisampled, the lighting reads it per pixel
+        graph.add_resource(GBuffer, FboDesc::ScreenSized { divisor: 1, flags: hdr_color | FboFlags::DEPTH_TEX, color_attachments: 4 });
+        // the second attachment holds the brightness for the bloom
+        graph.add_resource(CameraMultisampled, FboDesc::ScreenSized { divisor: 1, flags: FboFlags::MULTISAMPLED | FboFlags::COLOR_RENDERBUF | FboFlags::FLOAT_COLOR | FboFlags::DEPTH_RENDERBUF, color_attachments: 2 });
+        graph.add_resource(SceneCopy, FboDesc::ScreenSized { divisor: 1, flags: hdr_color | FboFlags::DEPTH_TEX, color_attachments: 1 });
+        // the alpha says how much the reflection is worth
+        graph.add_resource(ScreenSpaceReflection, FboDesc::ScreenSized { divisor: 1, flags: hdr_color, color_attachments: 1 });
+        graph.add_resource(CameraTexture, FboDesc::ScreenSized { divisor: 1, flags: hdr_color | FboFlags::DEPTH_TEX, color_attachments: 1 });
+        // all intermediate steps stay in floating point so the bloom doesn't clip
+        graph.add_resource(CameraBrightness, FboDesc::ScreenSized { divisor: 1, flags: hdr_color, color_attachments: 1 });
+        // the occlusion is a single value that doesn't need floats
+        graph.add_resource(Ssao, FboDesc::ScreenSized { divisor: SsaoKernel::DOWNSCALE, flags: FboFlags::COLOR_TEX, color_attachments: 1 });
+        graph.add_resource(SsaoBlurHorizontal, FboDesc::ScreenSized { divisor: SsaoKernel::DOWNSCALE, flags: FboFlags::COLOR_TEX, color_attachments: 1 });
+        graph.add_resource(SsaoBlurVertical, FboDesc::ScreenSized { divisor: SsaoKernel::DOWNSCALE, flags: FboFlags::COLOR_TEX, color_attachments: 1 });
+        graph.add_resource(AmbientOccluded, FboDesc::ScreenSized { divisor: 1, flags: hdr_color, color_attachments: 1 });
+        graph.add_resource(LightShafts, FboDesc::ScreenSized { divisor: atmosphere::LightShafts::DOWNSCALE, flags: hdr_color, color_attachments: 1 });
+        graph.add_resource(Atmosphere, FboDesc::ScreenSized { divisor: 1, flags: hdr_color, color_attachments: 1 });
+        graph.add_resource(BloomBlurHorizontal, FboDesc::ScreenSized { divisor: PostProcessing::BLOOM_DOWNSCALE, flags: hdr_color, color_attachments: 1 });
+        graph.add_resource(BloomBlurVertical, FboDesc::ScreenSized { divisor: PostProcessing::BLOOM_DOWNSCALE, flags: hdr_color, color_attachments: 1 });
+        graph.add_resource(BloomCombined, FboDesc::ScreenSized { divisor: 1, flags: hdr_color, color_attachments: 1 });
+        // the effects after the tonemapping don't need the float colors but the stack decides where that is
+        for slot in 0..post_effect::PostEffect::MAX_EFFECTS {
+            graph.add_resource(PostEffect(slot), FboDesc::ScreenSized { divisor: 1, flags: hdr_color, color_attachments: 1 });
+        }
+        graph.add_resource(Backbuffer, FboDesc::Backbuffer);
+
+        graph.add_pass(RenderPass::ShadowMap, &[], &[ShadowMap]);
+        graph.add_pass(RenderPass::PointShadowMap, &[], &[PointShadowMap]);
+        // the probes see the scene lit like the camera does
+        graph.add_pass(RenderPass::ReflectionProbes, &[ShadowMap, PointShadowMap], &[ReflectionProbes]);
+        graph.add_pass(RenderPass::WaterReflectRefract, &[ShadowMap, PointShadowMap], &water_textures);
+        // only one of the two runs, the display toggles between them
+        // both resolve what they drew into the scene copy at the end
+        graph.add_pass(RenderPass::ForwardScene, &[ShadowMap, PointShadowMap], &[CameraMultisampled, SceneCopy]);
+        graph.add_pass(RenderPass::DeferredScene, &[ShadowMap, PointShadowMap], &[GBuffer, CameraMultisampled, SceneCopy]);
+        // blends the reflections of the glossy entities over the scene and leaves the ones of the water for the water surface
+        graph.add_pass(RenderPass::ScreenSpaceReflection, &[SceneCopy], &[ScreenSpaceReflection, CameraMultisampled]);
+        // the screen space reflected water takes its refraction from the scene copy
+        let water_surface_reads: Vec<RenderResource> = water_textures.iter().cloned().chain([SceneCopy, ScreenSpaceReflection].iter().cloned()).collect();
+        graph.add_pass(RenderPass::WaterSurface, &water_surface_reads, &[CameraMultisampled]);
+        graph.add_pass(RenderPass::EnvMapEntities, &[ShadowMap, ReflectionProbes], &[CameraMultisampled]);
+        graph.add_pass(RenderPass::Particles, &[], &[CameraMultisampled]);
+        graph.add_pass(RenderPass::MsaaResolve, &[CameraMultisampled], &[CameraTexture, CameraBrightness]);
+        // reads the resolved depth, the blur reuses the blur shaders of the bloom
+        graph.add_pass(RenderPass::Ssao, &[CameraTexture], &[Ssao]);
+        graph.add_pass(RenderPass::SsaoBlurHorizontal, &[Ssao], &[SsaoBlurHorizontal]);
+        graph.add_pass(RenderPass::SsaoBlurVertical, &[SsaoBlurHorizontal], &[SsaoBlurVertical]);
+        graph.add_pass(RenderPass::SsaoApply, &[CameraTexture, SsaoBlurVertical], &[AmbientOccluded])
+            .forwards_when_disabled(CameraTexture, AmbientOccluded);
+        // the shafts only need the depth, the atmosphere reads it to find out how far away every pixel is
+        graph.add_pass(RenderPass::LightShafts, &[CameraTexture], &[LightShafts]);
+        graph.add_pass(RenderPass::Atmosphere, &[AmbientOccluded, CameraTexture, LightShafts], &[Atmosphere])
+            .forwards_when_disabled(AmbientOccluded, Atmosphere);
+        graph.add_pass(RenderPass::BloomBlurHorizontal, &[CameraBrightness], &[BloomBlurHorizontal]);
+        graph.add_pass(RenderPass::BloomBlurVertical, &[BloomBlurHorizontal], &[BloomBlurVertical]);
+        graph.add_pass(RenderPass::BloomCombine, &[Atmosphere, BloomBlurVertical], &[BloomCombined])
+            .forwards_when_disabled(Atmosphere, BloomCombined);
+        // every effect reads the one before, the depth of field also needs the depth of the camera texture
+        let mut previous = BloomCombined;
+        for slot in 0..post_effect::PostEffect::MAX_EFFECTS {
+            graph.add_pass(RenderPass::PostEffect(slot), &[previous, CameraTexture], &[PostEffect(slot)])
+                .forwards_when_disabled(previous, PostEffect(slot));
+            previous = PostEffect(slot);
+        }
+        graph.add_pass(RenderPass::Present, &[previous], &[Backbuffer]);
+        graph.add_pass(RenderPass::Gui, &[], &[Backbuffer]);
+        graph.set_enabled(RenderPass::DeferredScene, false);
+        // the scene switches on what its atmosphere has
+        for pass in RenderPass::ATMOSPHERE.iter() {
+            graph.set_enabled(*pass, false);
+        }
+        // the post processing stack enables as many as it has effects
+        for slot in 0..post_effect::PostEffect::MAX_EFFECTS {
+            graph.set_enabled(RenderPass::PostEffect(slot), false);
+        }
+
+        graph
+    }
+
+    pub fn add_resource(&mut self, resource: RenderResource, desc: FboDesc) {
+        self.declare_resource(resource, desc, false);
+    }
+
+    pub fn add_persistent_resource(&mut self, resource: RenderResource, desc: FboDesc) {
+        self.declare_resource(resource, desc, true);
+    }
+
+    fn declare_resource(&mut self, resource: RenderResource, desc: FboDesc, persistent: bool) {
+        assert!(self.resource_decl(resource).is_none(), "Resource {:?} is declared twice", resource);
+        self.resources.push(ResourceDecl { resource, desc, persistent });
+        self.dirty = true;
+    }
+
+    pub fn add_pass(&mut self, pass: RenderPass, reads: &[RenderResource], writes: &[RenderResource]) -> &mut PassDecl {
+        assert!(self.passes.iter().all(|decl| decl.pass != pass), "Pass {:?} is declared twice", pass);
+        self.dirty = true;
+        self.passes.push(PassDecl {
+            pass,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            forward: None,
+            enabled: true,
+        });
+        self.passes.last_mut().unwrap()
+    }
+
+    // takes effect with the next compile
+    pub fn set_enabled(&mut self, pass: RenderPass, enabled: bool) {
+        let decl = self.passes.iter_mut().find(|decl| decl.pass == pass).expect("Can only enable passes which are declared in the graph");
+        if decl.enabled != enabled {
+            decl.enabled = enabled;
+            self.dirty = true;
+        }
+    }
+
+    pub fn is_enabled(&self, pass: RenderPass) -> bool {
+        self.passes.iter().any(|decl| decl.pass == pass && decl.enabled)
+    }
+
+    // the scene is drawn into the camera fbo without msaa when fxaa smooths the edges instead, takes effect with the next compile
+    pub fn set_multisampling(&mut self, enabled: bool) {
+        let decl = self.resources.iter_mut().find(|decl| decl.resource == RenderResource::CameraMultisampled).expect("The camera fbo must be declared to change its samples");
+        if let FboDesc::ScreenSized { ref mut flags, .. } = decl.desc {
+            if flags.contains(FboFlags::MULTISAMPLED) != enabled {
+                flags.set(FboFlags::MULTISAMPLED, enabled);
+                self.dirty = true;
+            }
+        }
+    }
+
+    // plans the graph again and (re)allocates the fbos if any pass or resource changed since the last time
+    // a new render size only recreates the screen sized fbos
+    pub fn compile(&mut self, display: &Display) {
+        let render_size = display.get_render_size();
+        let render_size = (render_size.width, render_size.height);
+        let resized = render_size != self.render_size;
+        if !self.dirty && !resized {
+            return;
+        }
+        let plan = self.plan();
+
+        // keep the fbos which fit a slot of the new plan, this way the shadow maps the gui shows stay the same textures
+        let mut old_fbos: Vec<(FboDesc, FramebufferObject)> = self.plan.slot_descs.iter().cloned().zip(self.fbos.drain(..))
+            .filter(|(old_desc, _)| !resized || !old_desc.follows_render_size())
+            .collect();
+        for desc in plan.slot_descs.iter() {
+            let fbo = match old_fbos.iter().position(|(old_desc, _)| old_desc == desc) {
+                Some(idx) => old_fbos.swap_remove(idx).1,
+                None => desc.create(display).expect("Only resources with an fbo get a slot"),
+            };
+            self.fbos.push(fbo);
+        }
+        display.restore_default_framebuffer();
+
+        self.plan = plan;
+        self.render_size = render_size;
+        self.dirty = false;
+        self.generation += 1;
+    }
+
+    pub fn order(&self) -> &[RenderPass] {
+        &self.plan.order
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn fbo(&self, resource: RenderResource) -> &FramebufferObject {
+        let slot = self.slot(resource);
+        &self.fbos[slot]
+    }
+
+    pub fn fbo_mut(&mut self, resource: RenderResource) -> &mut FramebufferObject {
+        let slot = self.slot(resource);
+        &mut self.fbos[slot]
+    }
+
+    pub fn color_texture(&self, resource: RenderResource) -> u32 {
+        self.fbo(resource).color_texture(0).expect("The fbo must have a color texture to be read as one")
+    }
+
+    fn slot(&self, resource: RenderResource) -> usize {
+        let resolved = self.plan.resolve(resource);
+        *self.plan.slots.get(&resolved).expect("The fbo must be used by an enabled pass of a compiled graph")
+    }
+
+    fn resource_decl(&self, resource: RenderResource) -> Option<&ResourceDecl> {
+        self.resources.iter().find(|decl| decl.resource == resource)
+    }
+
+    pub fn plan(&self) -> GraphPlan {
+        let mut plan = GraphPlan::default();
+        for decl in self.passes.iter().filter(|decl| !decl.enabled) {
+            if let Some((from, to)) = decl.forward {
+                plan.redirects.insert(to, from);
+            }
+        }
+
+        // the inputs already point to what the disabled passes forwarded
+        let enabled: Vec<(RenderPass, Vec<RenderResource>, &Vec<RenderResource>)> = self.passes.iter()
+            .filter(|decl| decl.enabled)
+            .map(|decl| (decl.pass, decl.reads.iter().map(|read| plan.resolve(*read)).collect(), &decl.writes))
+            .collect();
+
+        // a pass runs after every pass which writes something it reads, passes writing the same thing keep their declaration order
+        let mut dependencies = vec![Vec::new(); enabled.len()];
+        for (i, (_, reads, writes)) in enabled.iter().enumerate() {
+            for (j, (_, _, other_writes)) in enabled.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let reads_other_output = reads.iter().any(|read| other_writes.contains(read));
+                let writes_after_other = j < i && writes.iter().any(|write| other_writes.contains(write));
+                if reads_other_output || writes_after_other {
+                    dependencies[i].push(j);
+                }
+            }
+        }
+
+        // topological sort which always picks the earliest declared pass that is ready
+        let mut done = vec![false; enabled.len()];
+        let mut order_idx = Vec::new();
+        while order_idx.len() < enabled.len() {
+            let next = (0..enabled.len()).find(|&i| !done[i] && dependencies[i].iter().all(|&dep| done[dep]));
+            let next = match next {
+                Some(next) => next,
+                None => panic!("The render graph has a cycle between the passes {:?}",
+                    (0..enabled.len()).filter(|&i| !done[i]).map(|i| enabled[i].0).collect::<Vec<_>>()),
+            };
+            done[next] = true;
+            order_idx.push(next);
+        }
+
+        for (pos, &idx) in order_idx.iter().enumerate() {
+            let (pass, reads, writes) = &enabled[idx];
+            plan.order.push(*pass);
+            for resource in reads.iter().chain(writes.iter()) {
+                let lifetime = plan.lifetimes.entry(*resource).or_insert((pos, pos));
+                lifetime.1 = pos;
+            }
+        }
+
+        // hand out the fbo slots in the order the resources come alive, a slot gets reused once the resource before is dead
+        let mut used: Vec<(&ResourceDecl, (usize, usize))> = self.resources.iter()
+            .filter_map(|decl| plan.lifetimes.get(&decl.resource).map(|lifetime| (decl, *lifetime)))
+            .collect();
+        for (resource, _) in plan.lifetimes.iter() {
+            assert!(self.resource_decl(*resource).is_some(), "Resource {:?} is used by a pass but never declared", resource);
+        }
+        used.sort_by_key(|(_, (first, _))| *first);
+        // the last pass of each slot or none if the slot can't be shared
+        let mut slot_last_use: Vec<Option<usize>> = Vec::new();
+        for (decl, (first, last)) in used {
+            if decl.desc == FboDesc::Backbuffer {
+                continue;
+            }
+            let free_slot = if decl.persistent {
+                None
+            } else {
+                (0..plan.slot_descs.len()).find(|&slot| plan.slot_descs[slot] == decl.desc && slot_last_use[slot].is_some_and(|slot_last| slot_last < first))
+            };
+            let slot = match free_slot {
+                Some(slot) => slot,
+                None => {
+                    plan.slot_descs.push(decl.desc);
+                    slot_last_use.push(None);
+                    plan.slot_descs.len() - 1
+                },
+            };
+            slot_last_use[slot] = if decl.persistent { None } else { Some(last) };
+            plan.slots.insert(decl.resource, slot);
+        }
+
+        plan
+    }
+}
+
+impl Default for RenderGraph {
+    fn default() -> RenderGraph {
+        RenderGraph::new()
+    }
+}
+
+impl fmt::Display for RenderGraph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "render graph (compile {}):", self.generation)?;
+        for (pos, pass) in self.plan.order.iter().enumerate() {
+            let decl = self.passes.iter().find(|decl| decl.pass == *pass).expect("Planned passes are declared");
+            let reads: Vec<RenderResource> = decl.reads.iter().map(|read| self.plan.resolve(*read)).collect();
+            writeln!(f, "  {:>2}. {:?} reads {:?} writes {:?}", pos, pass, reads, decl.writes)?;
+        }
+        let disabled: Vec<RenderPass> = self.passes.iter().filter(|decl| !decl.enabled).map(|decl| decl.pass).collect();
+        writeln!(f, "  disabled: {:?}", disabled)?;
+        writeln!(f, "resources:")?;
+        for decl in self.resources.iter() {
+            match (self.plan.slots.get(&decl.resource), self.plan.lifetimes.get(&decl.resource)) {
+                (Some(slot), Some((first, last))) => {
+                    let aliased = if self.plan.is_aliased(decl.resource) { " aliased" } else { "" };
+                    writeln!(f, "  {:?} -> fbo {}{} passes {}..{} {:?}", decl.resource, slot, aliased, first, last, decl.desc)?;
+                },
+                (None, Some((first, last))) => writeln!(f, "  {:?} passes {}..{}", decl.resource, first, last)?,
+                _ => writeln!(f, "  {:?} unused", decl.resource)?,
+            }
+        }
+        write!(f, "{} fbos allocated", self.fbos.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(plan: &GraphPlan, pass: RenderPass) -> usize {
+        plan.order.iter().position(|p| *p == pass).unwrap()
+    }
+
+    #[test]
+    fn test_frame_graph_order() {
+        let plan = RenderGraph::new_frame_graph().plan();
+        assert!(!plan.order.contains(&RenderPass::DeferredScene));
+        assert!(position(&plan, RenderPass::ShadowMap) < position(&plan, RenderPass::WaterReflectRefract));
+        assert!(position(&plan, RenderPass::PointShadowMap) < position(&plan, RenderPass::ReflectionProbes));
+        assert!(position(&plan, RenderPass::ReflectionProbes) < position(&plan, RenderPass::EnvMapEntities));
+        assert!(position(&plan, RenderPass::WaterReflectRefract) < position(&plan, RenderPass::ForwardScene));
+        assert!(position(&plan, RenderPass::ForwardScene) < position(&plan, RenderPass::ScreenSpaceReflection));
+        assert!(position(&plan, RenderPass::ScreenSpaceReflection) < position(&plan, RenderPass::WaterSurface));
+        assert!(position(&plan, RenderPass::Particles) < position(&plan, RenderPass::MsaaResolve));
+        assert!(position(&plan, RenderPass::Present) < position(&plan, RenderPass::Gui));
+        assert_eq!(*plan.order.last().unwrap(), RenderPass::Gui);
+    }
+
+    #[test]
+    fn test_producer_declared_late_runs_first() {
+        let desc = FboDesc::Sized { width: 4, height: 4, flags: FboFlags::COLOR_TEX, color_attachments: 1 };
+        let mut graph = RenderGraph::new();
+        graph.add_resource(RenderResource::Reflection(0), desc);
+        graph.add_pass(RenderPass::WaterSurface, &[RenderResource::Reflection(0)], &[]);
+        graph.add_pass(RenderPass::WaterReflectRefract, &[], &[RenderResource::Reflection(0)]);
+        let plan = graph.plan();
+        assert_eq!(plan.order, vec![RenderPass::WaterReflectRefract, RenderPass::WaterSurface]);
+    }
+
+    #[test]
+    fn test_dead_resources_share_fbos() {
+        let plan = RenderGraph::new_frame_graph().plan();
+        // the brightness is dead once it is blurred so the combined bloom can go into its fbo
+        assert_eq!(plan.slots[&RenderResource::CameraBrightness], plan.slots[&RenderResource::BloomCombined]);
+        assert!(plan.is_aliased(RenderResource::BloomCombined));
+        // both blur targets are alive in the vertical blur
+        assert_ne!(plan.slots[&RenderResource::BloomBlurHorizontal], plan.slots[&RenderResource::BloomBlurVertical]);
+        // and so are the textures of every water plane when the water surface is drawn
+        assert_ne!(plan.slots[&RenderResource::Reflection(0)], plan.slots[&RenderResource::Reflection(1)]);
+        assert_ne!(plan.slots[&RenderResource::Reflection(0)], plan.slots[&RenderResource::ScreenSpaceReflection]);
+        // the water surface reads the scene copy next to the refractions which look just like it
+        assert_ne!(plan.slots[&RenderResource::SceneCopy], plan.slots[&RenderResource::Refraction(0)]);
+        assert_ne!(plan.slots[&RenderResource::SceneCopy], plan.slots[&RenderResource::Refraction(1)]);
+        assert!(!plan.is_aliased(RenderResource::ShadowMap));
+        assert!(!plan.is_aliased(RenderResource::ReflectionProbes));
+        assert!(!plan.slots.contains_key(&RenderResource::Backbuffer));
+        // the g-buffer is only needed by the deferred scene
+        assert!(!plan.slots.contains_key(&RenderResource::GBuffer));
+    }
+
+    #[test]
+    fn test_persistent_resources_are_never_shared() {
+        let desc = FboDesc::Sized { width: 4, height: 4, flags: FboFlags::COLOR_TEX, color_attachments: 1 };
+        let mut graph = RenderGraph::new();
+        graph.add_persistent_resource(RenderResource::Reflection(0), desc);
+        graph.add_resource(RenderResource::Refraction(0), desc);
+        graph.add_pass(RenderPass::WaterReflectRefract, &[], &[RenderResource::Reflection(0)]);
+        graph.add_pass(RenderPass::WaterSurface, &[], &[RenderResource::Refraction(0)]);
+        let plan = graph.plan();
+        assert_ne!(plan.slots[&RenderResource::Reflection(0)], plan.slots[&RenderResource::Refraction(0)]);
+    }
+
+    #[test]
+    fn test_disabled_post_processing_forwards_camera_texture() {
+        let mut graph = RenderGraph::new_frame_graph();
+        for pass in RenderPass::POST_PROCESSING_EFFECTS.iter().chain(RenderPass::AMBIENT_OCCLUSION.iter()) {
+            graph.set_enabled(*pass, false);
+        }
+        let plan = graph.plan();
+        assert!(!plan.slots.contains_key(&RenderResource::Ssao));
+        let last_effect = RenderResource::PostEffect(post_effect::PostEffect::MAX_EFFECTS - 1);
+        assert_eq!(plan.resolve(last_effect), RenderResource::CameraTexture);
+        assert!(!plan.slots.contains_key(&RenderResource::BloomBlurHorizontal));
+        assert!(!plan.slots.contains_key(&RenderResource::PostEffect(0)));
+        assert_eq!(plan.lifetimes[&RenderResource::CameraTexture].1, position(&plan, RenderPass::Present));
+    }
+
+    #[test]
+    fn test_ambient_occlusion_before_bloom() {
+        let mut graph = RenderGraph::new_frame_graph();
+        for pass in RenderPass::POST_PROCESSING_EFFECTS.iter() {
+            graph.set_enabled(*pass, false);
+        }
+        let plan = graph.plan();
+        assert!(position(&plan, RenderPass::MsaaResolve) < position(&plan, RenderPass::Ssao));
+        assert!(position(&plan, RenderPass::SsaoBlurVertical) < position(&plan, RenderPass::SsaoApply));
+        // without the bloom the effects get the occluded scene
+        let last_effect = RenderResource::PostEffect(post_effect::PostEffect::MAX_EFFECTS - 1);
+        assert_eq!(plan.resolve(last_effect), RenderResource::AmbientOccluded);
+        // the raw occlusion is dead once it is blurred
+        assert_eq!(plan.slots[&RenderResource::Ssao], plan.slots[&RenderResource::SsaoBlurVertical]);
+    }
+
+    #[test]
+    fn test_atmosphere_between_occlusion_and_bloom() {
+        let mut graph = RenderGraph::new_frame_graph();
+        assert!(!graph.plan().order.contains(&RenderPass::Atmosphere));
+        for pass in RenderPass::ATMOSPHERE.iter() {
+            graph.set_enabled(*pass, true);
+        }
+        let plan = graph.plan();
+        assert!(position(&plan, RenderPass::SsaoApply) < position(&plan, RenderPass::Atmosphere));
+        assert!(position(&plan, RenderPass::LightShafts) < position(&plan, RenderPass::Atmosphere));
+        assert!(position(&plan, RenderPass::Atmosphere) < position(&plan, RenderPass::BloomCombine));
+        assert_eq!(plan.lifetimes[&RenderResource::LightShafts].1, position(&plan, RenderPass::Atmosphere));
+
+        // without the bloom the effects get the fogged scene
+        for pass in RenderPass::POST_PROCESSING_EFFECTS.iter() {
+            graph.set_enabled(*pass, false);
+        }
+        let last_effect = RenderResource::PostEffect(post_effect::PostEffect::MAX_EFFECTS - 1);
+        assert_eq!(graph.plan().resolve(last_effect), RenderResource::Atmosphere);
+    }
+
+    #[test]
+    fn test_post_effect_chain() {
+        let mut graph = RenderGraph::new_frame_graph();
+        for slot in 0..3 {
+            graph.set_enabled(RenderPass::PostEffect(slot), true);
+        }
+        let plan = graph.plan();
+        assert!(position(&plan, RenderPass::BloomCombine) < position(&plan, RenderPass::PostEffect(0)));
+        assert!(position(&plan, RenderPass::PostEffect(0)) < position(&plan, RenderPass::PostEffect(1)));
+        assert!(position(&plan, RenderPass::PostEffect(2)) < position(&plan, RenderPass::Present));
+        assert!(!plan.order.contains(&RenderPass::PostEffect(3)));
+        // the present pass gets the output of the last enabled effect
+        let last_effect = RenderResource::PostEffect(post_effect::PostEffect::MAX_EFFECTS - 1);
+        assert_eq!(plan.resolve(last_effect), RenderResource::PostEffect(2));
+        // an effect reads the one right before so every other one can share an fbo
+        assert_ne!(plan.slots[&RenderResource::PostEffect(0)], plan.slots[&RenderResource::PostEffect(1)]);
+        assert_eq!(plan.slots[&RenderResource::PostEffect(0)], plan.slots[&RenderResource::PostEffect(2)]);
+        // the depth of field can sit anywhere in the stack
+        assert_eq!(plan.lifetimes[&RenderResource::CameraTexture].1, position(&plan, RenderPass::PostEffect(2)));
+    }
+
+    #[test]
+    fn test_multisampling_toggle() {
+        let mut graph = RenderGraph::new_frame_graph();
+        let camera_desc = |plan: &GraphPlan| plan.slot_descs[plan.slots[&RenderResource::CameraMultisampled]];
+        let is_multisampled = |desc: FboDesc| match desc {
+            FboDesc::ScreenSized { flags, .. } => flags.contains(FboFlags::MULTISAMPLED),
+            _ => panic!("The camera fbo follows the render size"),
+        };
+        assert!(is_multisampled(camera_desc(&graph.plan())));
+        graph.set_multisampling(false);
+        assert!(graph.dirty);
+        assert!(!is_multisampled(camera_desc(&graph.plan())));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cycle_panics() {
+        let mut graph = RenderGraph::new();
+        graph.add_resource(RenderResource::Reflection(0), FboDesc::Backbuffer);
+        graph.add_resource(RenderResource::Refraction(0), FboDesc::Backbuffer);
+        graph.add_pass(RenderPass::WaterReflectRefract, &[RenderResource::Refraction(0)], &[RenderResource::Reflection(0)]);
+        graph.add_pass(RenderPass::WaterSurface, &[RenderResource::Reflection(0)], &[RenderResource::Refraction(0)]);
+        graph.plan();
+    }
+}
\ No newline at end of file
@@ -1,15 +1,16 @@
-use crate::display::{
-    Display,
-    framebuffers::FboMap,
-};
+use crate::display::Display;
+use crate::animations::joint::AccumulatedJointTransforms;
 use crate::entities::{
+    AnimatedEntity,
     Camera,
     Entity,
     Light,
     Terrain,
+    Weather,
 };
 use crate::gl;
 use crate::math::{
+    Frustum,
     Matrix4f,
     Vector3f,
 };
@@ -19,61 +20,123 @@ use crate::models::{
 };
 use crate::shadows::{
     shadow_params::ShadowParams,
-    shadow_box::ShadowBox,
+    shadow_box::{
+        ShadowBox,
+        ShadowCascades,
+    },
 };
 use crate::shaders::ShadowShader;
+use super::culling::CullCounter;
+use super::entity_instances::EntityInstances;
+use std::mem;
 
 
 pub struct ShadowMapRenderer {
     shadow_shader: ShadowShader,
+    animated_shadow_shader: ShadowShader,
+    accumulator: AccumulatedJointTransforms,
+    instances: EntityInstances,
     pub shadow_box: ShadowBox,
     pub shadow_params: ShadowParams,
+    // kept for rebuilding the shadow box when the window changes
+    cascades: ShadowCascades,
     world_to_lightspace: Matrix4f,    
     bias: Matrix4f,
     vp_matrix: Matrix4f,
     mvp_matrix: Matrix4f,    
+    // the ortho box of the current cascade, everything outside can't cast a shadow into it
+    cascade_frustum: Frustum,
+    // terrain chunks skipped over all cascades since the last start_render
+    pub chunk_culling: CullCounter,
 }
 
 impl ShadowMapRenderer {
+    // size of every cascade's layer
+    pub const SHADOW_MAP_SIZE: usize = 2048;
 
-    pub fn new(aspect_ratio: f32) -> Self {
-        let shadow_box = ShadowBox::new(aspect_ratio, Display::FOV_HORIZONTAL, Display::NEAR, -ShadowBox::SHADOW_DISTANCE);
+    pub fn new(aspect_ratio: f32, cascades: &ShadowCascades) -> Self {
+        let shadow_box = ShadowMapRenderer::create_shadow_box(aspect_ratio, cascades);
         let world_to_lightspace = Matrix4f::identity();        
         let bias = ShadowMapRenderer::create_bias_matrix();
         let shadow_shader = ShadowShader::new();
+        let animated_shadow_shader = ShadowShader::new_animated();
         let vp_matrix = Matrix4f::identity();
         let mvp_matrix = Matrix4f::identity();
 
         let shadow_params = ShadowParams {
             shadow_map_texture: 0,
-            shadow_distance: ShadowBox::SHADOW_DISTANCE,
-            shadow_map_size: FboMap::SHADOW_MAP_SIZE,
+            shadow_map_size: ShadowMapRenderer::SHADOW_MAP_SIZE,
+            cascade_count: shadow_box.cascades.len(),
+            cascade_splits: Default::default(),
+            to_shadowmap_space: Default::default(),
         };
 
         ShadowMapRenderer {
             shadow_shader,
+            animated_shadow_shader,
+            accumulator: AccumulatedJointTransforms::new(),
+            instances: EntityInstances::new(),
             shadow_box,
             world_to_lightspace,            
             bias,
             vp_matrix,
+            cascade_frustum: Frustum::from_matrix(&Matrix4f::identity()),
+            chunk_culling: CullCounter::default(),
             mvp_matrix,
             shadow_params,
+            cascades: cascades.clone(),
         }
     }
 
+    fn create_shadow_box(aspect_ratio: f32, cascades: &ShadowCascades) -> ShadowBox {
+        ShadowBox::new(aspect_ratio, Display::FOV_HORIZONTAL, Display::NEAR, -ShadowBox::SHADOW_DISTANCE, 
+            cascades.count, cascades.split_lambda, ShadowMapRenderer::SHADOW_MAP_SIZE)
+    }
+
+    // the cascades have to cover the whole view frustum which gets wider or narrower with the window
+    pub fn update_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.shadow_box = ShadowMapRenderer::create_shadow_box(aspect_ratio, &self.cascades);
+    }
+
+    // the plants sway in the shadows like they do in the entity renderer
+    pub fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.shadow_shader.start();
+        self.shadow_shader.load_weather(weather, time);
+        self.shadow_shader.stop();
+    }
+
     pub fn start_render(&mut self, camera: &Camera, sun: &Light) {                
-        let (pitch, yaw) = Self::calc_light_pitch_yaw_dg(&sun.position);
-        let world_to_lightspace_non_moving = Matrix4f::create_fps_view_matrix(&Vector3f::ZERO, pitch, yaw);
-        self.shadow_box.update(camera, &world_to_lightspace_non_moving);        
-        self.update_world_to_lightspace(pitch, yaw);
+        let sun_direction = sun.direction().expect("The shadow map needs a light with a direction");
+        let to_sun = Vector3f::new(-sun_direction.x, -sun_direction.y, -sun_direction.z);
+        let (pitch, yaw) = Self::calc_light_pitch_yaw_dg(&to_sun);
+        // the light space only rotates, every cascade's ortho projection moves its box to where the camera is
+        // that way the snapping of the boxes to texels happens in a space that doesn't move with the camera
+        self.world_to_lightspace = Matrix4f::create_fps_view_matrix(&Vector3f::ZERO, pitch, yaw);
+        self.shadow_box.update(camera, &self.world_to_lightspace);
+        self.update_shadow_params();
+        self.chunk_culling.reset();
         
         gl::enable(gl::DEPTH_TEST);
-        gl::clear(gl::DEPTH_BUFFER_BIT);
         self.shadow_shader.start();
+    }
 
+    // every cascade is rendered into its own layer of the shadow map, the layer has to be bound before this
+    pub fn start_cascade(&mut self, cascade: usize) {
+        gl::clear(gl::DEPTH_BUFFER_BIT);
         self.vp_matrix.make_identity();
         self.vp_matrix.pre_multiply_in_place(&self.world_to_lightspace);
-        self.vp_matrix.pre_multiply_in_place(&self.shadow_box.ortho_proj_mat);
+        self.vp_matrix.pre_multiply_in_place(&self.shadow_box.cascades[cascade].ortho_proj_mat);
+        // the boxes already reach back towards the light to catch the casters in front of them so the near plane can cull too
+        self.cascade_frustum = Frustum::from_matrix(&self.vp_matrix);
+        self.shadow_shader.load_vp_matrix(&self.vp_matrix);
+    }
+
+    pub fn cascade_frustum(&self) -> &Frustum {
+        &self.cascade_frustum
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.shadow_box.cascades.len()
     }
 
     pub fn prepare_textured_model(&mut self, model: &TexturedModel) {
@@ -82,31 +145,56 @@ impl ShadowMapRenderer {
         gl::bind_vertex_array(model.raw_model.vao_id);
         gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
         gl::enable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);        
+        EntityInstances::enable_attribs();
+        self.shadow_shader.load_sways_in_wind(model.texture.sways_in_wind);
     }
 
-    pub fn render(&mut self, entities: &Vec<&Entity>) {        
-        for entity in entities.iter() {      
-            self.render_entity(entity);
-        }
+    // the entities all have the prepared model
+    pub fn render(&mut self, model: &TexturedModel, entities: &[&Entity]) {        
+        self.instances.render(model, entities);
     }
 
-    pub fn render_entity(&mut self, entity: &Entity) {
+    pub fn cleanup_textured_model(&mut self) {        
+        gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        EntityInstances::disable_attribs();
+        gl::bind_vertex_array(0);
+    }
+
+    // skinned models need their own shader, we switch back to the normal one when done so the other renders can carry on
+    pub fn render_animated_entity(&mut self, animated_entity: &AnimatedEntity) {
+        self.shadow_shader.stop();
+        self.animated_shadow_shader.start();
+
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_2D, animated_entity.model.tex_id.unwrap());
+        gl::bind_vertex_array(animated_entity.model.raw_model.vao_id);
+        gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::JOINT_IDX_ATTRIB);
+        gl::enable_vertex_attrib_array(RawModel::JOINT_WEIGHT_ATTRIB);
+
         self.mvp_matrix.make_identity();
         self.mvp_matrix.post_multiply_in_place(&self.vp_matrix);
-        let transform_mat = Matrix4f::create_transform_matrix(&entity.position, &entity.rotation_deg, entity.scale);
+        let transform_mat = Matrix4f::create_transform_matrix(&animated_entity.position, &animated_entity.rotation_deg, animated_entity.scale);
         self.mvp_matrix.post_multiply_in_place(&transform_mat);
-        self.shadow_shader.load_mvp_matrix(&self.mvp_matrix);
+        self.animated_shadow_shader.load_mvp_matrix(&self.mvp_matrix);
+        animated_entity.model.root_joint.collect_transforms(&mut self.accumulator);
+        self.animated_shadow_shader.load_joint_transforms(&self.accumulator);
 
-        gl::draw_elements(gl::TRIANGLES, entity.model.raw_model.vertex_count, gl::UNSIGNED_INT);            
-    }
+        gl::draw_elements(gl::TRIANGLES, animated_entity.model.raw_model.vertex_count, gl::UNSIGNED_INT);
 
-    pub fn cleanup_textured_model(&mut self) {        
         gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
         gl::disable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::JOINT_IDX_ATTRIB);
+        gl::disable_vertex_attrib_array(RawModel::JOINT_WEIGHT_ATTRIB);
         gl::bind_vertex_array(0);
+
+        self.animated_shadow_shader.stop();
+        self.shadow_shader.start();
     }
 
-    pub fn render_terrain(&mut self, terrains: &Vec<Terrain>) {
+    pub fn render_terrain(&mut self, terrains: &Vec<Terrain>, camera: &Camera) {
         for terrain in terrains.iter() {
             gl::bind_vertex_array(terrain.model.raw_model.vao_id);
             gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);            
@@ -114,13 +202,21 @@ impl ShadowMapRenderer {
             let terrain_pos = Vector3f::new(terrain.x as f32, 0.0, terrain.z as f32);
             let terrain_rot = Vector3f::new(0.0, 0.0, 0.0);
             let transform_mat = Matrix4f::create_transform_matrix(&terrain_pos, &terrain_rot, 1.0);
-
-            self.mvp_matrix.make_identity();
-            self.mvp_matrix.pre_multiply_in_place(&transform_mat);
-            self.mvp_matrix.pre_multiply_in_place(&self.vp_matrix);
-
-            self.shadow_shader.load_mvp_matrix(&self.mvp_matrix);
-            gl::draw_elements(gl::TRIANGLES, terrain.model.raw_model.vertex_count, gl::UNSIGNED_INT);
+            // the terrain vao has no instance data, with the arrays disabled the shader reads the transform from the constant attribute values
+            for (col, attrib) in [TexturedModel::TRANSFORM_COLUMN1, TexturedModel::TRANSFORM_COLUMN2, TexturedModel::TRANSFORM_COLUMN3, TexturedModel::TRANSFORM_COLUMN4].iter().enumerate() {
+                gl::vertex_attrib_4f(*attrib, transform_mat[0][col], transform_mat[1][col], transform_mat[2][col], transform_mat[3][col]);
+            }
+            // use the same levels as the camera pass so the terrain doesn't shadow itself where the meshes differ
+            let camera_pos = &camera.position - &terrain_pos;
+            for chunk in terrain.model.chunks.iter() {
+                let min = &chunk.min + &terrain_pos;
+                let max = &chunk.max + &terrain_pos;
+                if !self.chunk_culling.count(self.cascade_frustum.intersects_aabb(&min, &max)) {
+                    continue;
+                }
+                let lod = chunk.lod_range_at(&camera_pos);
+                gl::draw_elements_with_offset(gl::TRIANGLES, lod.count, gl::UNSIGNED_INT, lod.offset * mem::size_of::<u32>());
+            }
 
             gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);            
         }
@@ -131,12 +227,15 @@ impl ShadowMapRenderer {
         self.shadow_shader.stop();
     }
 
-    pub fn get_to_shadow(&self) -> Matrix4f {
-        let mut res = Matrix4f::identity();
-        res.pre_multiply_in_place(&self.world_to_lightspace);
-        res.pre_multiply_in_place(&self.shadow_box.ortho_proj_mat);
-        res.pre_multiply_in_place(&self.bias);
-        res
+    fn update_shadow_params(&mut self) {
+        for (i, cascade) in self.shadow_box.cascades.iter().enumerate() {
+            let to_shadow = &mut self.shadow_params.to_shadowmap_space[i];
+            to_shadow.make_identity();
+            to_shadow.pre_multiply_in_place(&self.world_to_lightspace);
+            to_shadow.pre_multiply_in_place(&cascade.ortho_proj_mat);
+            to_shadow.pre_multiply_in_place(&self.bias);
+            self.shadow_params.cascade_splits[i] = cascade.far;
+        }
     }
 
     fn calc_light_pitch_yaw_dg(to_light_direction: &Vector3f) -> (f32, f32) {
@@ -145,14 +244,6 @@ impl ShadowMapRenderer {
         (pitch.to_degrees(), yaw.to_degrees())
     }
 
-    fn update_world_to_lightspace(&mut self, pitch: f32, yaw: f32) {
-        // this is a different to_lightspace matrix -> namely this one is moving whilst the one we compute in start_render is static
-        // the reason this is important is that this allows us to remove the circular dependency between computing the center of the shadowbox
-        // and using the center to compute the to_lightspace matrix
-        // if the dependency is there then the frustum bounding box (shadow box) jumps around too much which seems to causes it to not correctly center on the player        
-        let center = &self.shadow_box.world_space_center;
-        self.world_to_lightspace = Matrix4f::create_fps_view_matrix(center, pitch, yaw);
-    }
     // we want to use the lightspace transform in a shader to sample from the depth map
     // the projection to lightspace ndc coords will leave us in the unit cube [-1,1]
     // but a texture has coords in range [0,1] so we use the bias matrix to apply the conversion directly to the matrix
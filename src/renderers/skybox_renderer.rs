@@ -8,16 +8,19 @@ use crate::entities::{
 use crate::gl;
 use crate::math::{
     Matrix4f,
-    Vector3f,
     Vector4f,
 };
 use crate::models::{
     RawModel,
 };
-use crate::shaders::SkyboxShader;
+use crate::shaders::{
+    SkyboxShader,
+    ProceduralSkyShader,
+};
 
 pub struct SkyboxRenderer {
     shader: SkyboxShader,    
+    procedural_shader: ProceduralSkyShader,
 }
 
 impl SkyboxRenderer {
@@ -26,32 +29,58 @@ impl SkyboxRenderer {
         skybox_shader.start();
         skybox_shader.load_projection_matrix(proj_matrix);
         skybox_shader.stop();
+        let mut procedural_shader = ProceduralSkyShader::new();
+        procedural_shader.start();
+        procedural_shader.load_projection_matrix(proj_matrix);
+        procedural_shader.stop();
         SkyboxRenderer {
             shader: skybox_shader,
+            procedural_shader,
         }
     }
 
-    pub fn render(&mut self, camera: &Camera, skybox: &Skybox, sky_color: &Vector3f, wall_clock: &WallClock, clip_plane: &Vector4f) {
+    pub fn update_projection_matrix(&mut self, proj_matrix: &Matrix4f) {
+        self.shader.start();
+        self.shader.load_projection_matrix(proj_matrix);
+        self.shader.stop();
+        self.procedural_shader.start();
+        self.procedural_shader.load_projection_matrix(proj_matrix);
+        self.procedural_shader.stop();
+    }
+
+    pub fn render(&mut self, camera: &Camera, skybox: &Skybox, wall_clock: &WallClock, clip_plane: &Vector4f) {
         if skybox.invisible {
             return;
         }
+        if let Some(procedural_sky) = &skybox.procedural {
+            self.procedural_shader.start();
+            self.procedural_shader.load_view_matrix(camera);
+            self.procedural_shader.load_clip_plane(clip_plane);
+            self.procedural_shader.load_sky(procedural_sky, wall_clock);
+            SkyboxRenderer::draw_cube(skybox);
+            self.procedural_shader.stop();
+            return;
+        }
         self.shader.start();        
         self.shader.load_view_matrix(camera, skybox.rotation_yaw_deg);
-        self.shader.load_sky_color(sky_color, skybox.uses_fog); // due to day night this color needs to be set every frame
         // water stuff (every frame?)
         self.shader.load_clip_plane(clip_plane);
         
         self.bind_textures(skybox, wall_clock);        
 
+        SkyboxRenderer::draw_cube(skybox);
+
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
+
+        self.shader.stop();
+    }
+
+    fn draw_cube(skybox: &Skybox) {
         gl::bind_vertex_array(skybox.model.raw_model.vao_id);
         gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
         gl::draw_arrays(gl::TRIANGLES, 0, skybox.model.raw_model.vertex_count);
         gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
         gl::bind_vertex_array(0);
-
-        gl::bind_texture(gl::TEXTURE_CUBE_MAP, 0);
-
-        self.shader.stop();
     }
 
     fn bind_textures(&mut self, skybox: &Skybox, wall_clock: &WallClock) {
@@ -0,0 +1,210 @@
+use crate::display::{
+    WallClock,
+    framebuffers::FramebufferObject,
+};
+use crate::entities::{
+    Camera,
+    Entity,
+    Skybox,
+    WaterPlanes,
+    WaterTile,
+};
+use crate::gl;
+use crate::math::Matrix4f;
+use crate::models::{
+    QuadModel,
+    RawModel,
+    TexturedModel,
+};
+use crate::shaders::{
+    ScreenSpaceReflectionShader,
+    SsrCompositeShader,
+};
+use super::master_renderer::RenderGroup;
+
+// reflections ray marched through the resolved opaque scene of the main camera
+// the glossy entities get theirs blended over the scene, the water reads them in the water surface pass
+pub struct ScreenSpaceReflectionRenderer {
+    water_shader: ScreenSpaceReflectionShader,
+    entity_shader: ScreenSpaceReflectionShader,
+    composite_shader: SsrCompositeShader,
+}
+
+impl ScreenSpaceReflectionRenderer {
+    // rougher pbr surfaces would blur the reflection away, they keep just the sky lighting
+    pub const MAX_ROUGHNESS: f32 = 0.5;
+
+    pub fn new(projection_matrix: &Matrix4f) -> Self {
+        let mut water_shader = ScreenSpaceReflectionShader::new_for_water();
+        water_shader.start();
+        water_shader.load_projection_matrix(projection_matrix);
+        water_shader.load_reflection_weight(1.0);
+        // the water reads the alpha as the confidence of the hit and uses its planar reflection for the rest
+        water_shader.load_sky_fallback(false);
+        water_shader.connect_texture_units();
+        water_shader.stop();
+        let mut entity_shader = ScreenSpaceReflectionShader::new_for_entities();
+        entity_shader.start();
+        entity_shader.load_projection_matrix(projection_matrix);
+        entity_shader.load_sky_fallback(true);
+        entity_shader.connect_texture_units();
+        entity_shader.stop();
+        let mut composite_shader = SsrCompositeShader::new();
+        composite_shader.start();
+        composite_shader.connect_texture_units();
+        composite_shader.stop();
+        ScreenSpaceReflectionRenderer {
+            water_shader,
+            entity_shader,
+            composite_shader,
+        }
+    }
+
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        for shader in [&mut self.water_shader, &mut self.entity_shader].iter_mut() {
+            shader.start();
+            shader.load_projection_matrix(projection_matrix);
+            shader.stop();
+        }
+    }
+
+    // how much of the lit color the reflection replaces, None for the models that don't get one
+    pub fn reflection_weight(textured_model: &TexturedModel) -> Option<f32> {
+        let material = textured_model.pbr_material.as_ref()?;
+        if material.roughness >= ScreenSpaceReflectionRenderer::MAX_ROUGHNESS {
+            return None;
+        }
+        Some(1.0 - material.roughness / ScreenSpaceReflectionRenderer::MAX_ROUGHNESS)
+    }
+
+    // alpha the ssr shader writes, the glossy entities have nothing else to reflect so their missed rays show the sky
+    pub fn reflection_alpha(confidence: f32, reflection_weight: f32, sky_fallback: bool) -> f32 {
+        let fallback = if sky_fallback { 1.0 } else { 0.0 };
+        (confidence + (1.0 - confidence) * fallback) * reflection_weight
+    }
+
+    // draws the reflections of the glossy entities into the bound fbo, the fragments hidden in the scene get discarded
+    pub fn render_entities(&mut self, entities: &[&Entity], camera: &Camera, scene_copy: &FramebufferObject, skybox: &Skybox, wall_clock: &WallClock) {
+        gl::helper::push_debug_group(RenderGroup::SCREEN_SPACE_REFLECTIONS.id, RenderGroup::SCREEN_SPACE_REFLECTIONS.name);
+        self.entity_shader.start();
+        ScreenSpaceReflectionRenderer::load_camera_and_sky(&mut self.entity_shader, camera, skybox, wall_clock);
+        ScreenSpaceReflectionRenderer::bind_scene_and_sky(scene_copy, skybox, wall_clock);
+
+        for entity in entities.iter() {
+            let weight = match ScreenSpaceReflectionRenderer::reflection_weight(&entity.model) {
+                Some(weight) => weight,
+                None => continue,
+            };
+            self.entity_shader.load_reflection_weight(weight);
+            self.entity_shader.load_transform_matrix(&Matrix4f::create_transform_matrix(&entity.position, &entity.rotation_deg, entity.scale));
+
+            gl::bind_vertex_array(entity.model.raw_model.vao_id);
+            gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+            gl::enable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+            gl::enable_vertex_attrib_array(RawModel::NORMAL_ATTRIB);
+            gl::draw_elements(gl::TRIANGLES, entity.model.raw_model.vertex_count, gl::UNSIGNED_INT);
+            gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+            gl::disable_vertex_attrib_array(RawModel::TEX_COORD_ATTRIB);
+            gl::disable_vertex_attrib_array(RawModel::NORMAL_ATTRIB);
+        }
+        gl::bind_vertex_array(0);
+
+        self.entity_shader.stop();
+        gl::helper::pop_debug_group();
+    }
+
+    // blends the reflections of the entities over the bound fbo
+    pub fn composite(&mut self, ssr_texture: u32, quad_model: &QuadModel) {
+        gl::helper::push_debug_group(RenderGroup::SCREEN_SPACE_REFLECTIONS.id, RenderGroup::SCREEN_SPACE_REFLECTIONS.name);
+        self.composite_shader.start();
+        gl::disable(gl::DEPTH_TEST);
+        gl::enable(gl::BLEND);
+        gl::blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_2D, ssr_texture);
+        gl::bind_vertex_array(quad_model.raw_model.vao_id);
+        gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::draw_arrays(gl::TRIANGLE_STRIP, 0, quad_model.raw_model.vertex_count);
+        gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        gl::bind_vertex_array(0);
+
+        gl::disable(gl::BLEND);
+        gl::enable(gl::DEPTH_TEST);
+        self.composite_shader.stop();
+        gl::helper::pop_debug_group();
+    }
+
+    // draws the reflections of the visible water tiles which use them into the bound fbo
+    pub fn render_water(&mut self, water_tiles: &[WaterTile], water_planes: &WaterPlanes, camera: &Camera, scene_copy: &FramebufferObject,
+                skybox: &Skybox, wall_clock: &WallClock) {
+        gl::helper::push_debug_group(RenderGroup::SCREEN_SPACE_REFLECTIONS.id, RenderGroup::SCREEN_SPACE_REFLECTIONS.name);
+        self.water_shader.start();
+        ScreenSpaceReflectionRenderer::load_camera_and_sky(&mut self.water_shader, camera, skybox, wall_clock);
+        ScreenSpaceReflectionRenderer::bind_scene_and_sky(scene_copy, skybox, wall_clock);
+
+        for (idx, water_tile) in water_tiles.iter().enumerate() {
+            match water_planes.tile_reflection(water_tiles, idx) {
+                Some(reflection) if reflection.uses_screen_space() => {},
+                _ => continue,
+            }
+            self.water_shader.load_transform_matrix(&water_tile.transform);
+            self.water_shader.load_tiling(&water_tile.texture_tiling());
+            self.water_shader.load_waves(&water_tile.material.waves, wall_clock.elapsed);
+
+            // same meshes as the water renderer so the surfaces line up
+            let (raw_model, primitive) = if water_tile.material.waves.is_empty() {
+                (&water_tile.model.raw_model, gl::TRIANGLE_STRIP)
+            } else {
+                (&water_tile.model.grid_raw_model, gl::TRIANGLES)
+            };
+            gl::bind_vertex_array(raw_model.vao_id);
+            gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+            gl::draw_arrays(primitive, 0, raw_model.vertex_count);
+            gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+        }
+        gl::bind_vertex_array(0);
+
+        self.water_shader.stop();
+        gl::helper::pop_debug_group();
+    }
+
+    fn load_camera_and_sky(shader: &mut ScreenSpaceReflectionShader, camera: &Camera, skybox: &Skybox, wall_clock: &WallClock) {
+        shader.load_view_matrix(&Matrix4f::create_view_matrix(camera), &camera.position);
+        // the skybox rotates on its own, this takes a world direction to where the skybox shows it
+        let mut sky_view = Matrix4f::create_skybox_view_matrix(camera, skybox.rotation_yaw_deg);
+        sky_view.transpose_ip();
+        let sky_rotation = &sky_view * &Matrix4f::create_skybox_view_matrix(camera, 0.0);
+        let (_, _, blend_factor) = skybox.get_day_night_textures(wall_clock);
+        shader.load_sky(&sky_rotation, blend_factor);
+    }
+
+    fn bind_scene_and_sky(scene_copy: &FramebufferObject, skybox: &Skybox, wall_clock: &WallClock) {
+        gl::active_texture(gl::TEXTURE0);
+        gl::bind_texture(gl::TEXTURE_2D, scene_copy.color_texture(0).expect("The scene copy must have a color attachment"));
+        gl::active_texture(gl::TEXTURE1);
+        gl::bind_texture(gl::TEXTURE_2D, scene_copy.depth_texture.expect("The scene copy must have a depth texture"));
+        let (day_tex, night_tex, _) = skybox.get_day_night_textures(wall_clock);
+        gl::active_texture(gl::TEXTURE2);
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP, day_tex.unwrap());
+        gl::active_texture(gl::TEXTURE3);
+        gl::bind_texture(gl::TEXTURE_CUBE_MAP, night_tex.unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missed_rays_fall_back_to_the_sky() {
+        for weight in [0.25, 0.5, 1.0].iter() {
+            // a miss still covers the glossy entity with the sky at its full weight
+            assert_eq!(ScreenSpaceReflectionRenderer::reflection_alpha(0.0, *weight, true), *weight);
+            assert_eq!(ScreenSpaceReflectionRenderer::reflection_alpha(1.0, *weight, true), *weight);
+            // the water only takes the hits and leaves the misses to the planar reflection
+            assert_eq!(ScreenSpaceReflectionRenderer::reflection_alpha(0.0, *weight, false), 0.0);
+            assert_eq!(ScreenSpaceReflectionRenderer::reflection_alpha(0.5, *weight, false), 0.5 * weight);
+        }
+    }
+}
\ No newline at end of file
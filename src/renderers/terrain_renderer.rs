@@ -1,12 +1,18 @@
 use crate::gl;
+use super::light_clusters::LightClusters;
+use super::culling::CullCounter;
 use crate::entities::{
     Camera,
-    Light,
     Terrain,
+    Weather,
 };
 use crate::shaders::TerrainShader;
-use crate::shadows::shadow_params::ShadowParams;
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
+};
 use crate::math::{
+    Frustum,
     Matrix4f,
     Vector3f,
     Vector4f,    
@@ -14,37 +20,69 @@ use crate::math::{
 use crate::models::{
     RawModel,
 };
+use std::mem;
 
 pub struct TerrainRenderer {
     shader: TerrainShader,
+    projection_matrix: Matrix4f,
+    // frustum of the camera of the current pass, used to skip terrain chunks
+    frustum: Frustum,
+    // chunks skipped since the last start_render
+    pub chunk_culling: CullCounter,
 }
 
 impl TerrainRenderer {    
     
     pub fn new(projection_matrix: &Matrix4f) -> TerrainRenderer {     
-        let mut shader = TerrainShader::new();
+        TerrainRenderer::with_shader(TerrainShader::new(), projection_matrix)
+    }
+
+    // fills the g-buffer of the deferred renderer, the lighting params given to start_render are ignored by that shader
+    pub fn new_gbuffer(projection_matrix: &Matrix4f) -> TerrainRenderer {
+        TerrainRenderer::with_shader(TerrainShader::new_gbuffer(), projection_matrix)
+    }
+
+    fn with_shader(mut shader: TerrainShader, projection_matrix: &Matrix4f) -> TerrainRenderer {
         shader.start();
         shader.load_projection_matrix(projection_matrix);
         shader.connect_texture_units();
         shader.stop();
         TerrainRenderer {
             shader,
+            projection_matrix: projection_matrix.clone(),
+            frustum: Frustum::from_matrix(projection_matrix),
+            chunk_culling: CullCounter::default(),
         }
     }
 
-    pub fn start_render(&mut self, lights: &Vec<Light>, camera: &Camera, sky_color: &Vector3f, to_shadow_space: &Matrix4f, shadow_params: &ShadowParams) {
+    pub fn update_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.shader.start();
+        self.shader.load_projection_matrix(projection_matrix);
+        self.shader.stop();
+        self.projection_matrix = projection_matrix.clone();
+    }
+
+    // the weather changes every frame so the master renderer loads it once at the start of it
+    pub fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.shader.start();
+        self.shader.load_weather(weather, time);
+        self.shader.stop();
+    }
+
+    pub fn start_render(&mut self, light_clusters: &LightClusters, camera: &Camera, shadow_params: &ShadowParams, point_shadows: &PointShadows) {
         self.shader.start();
         // we do this more than once because we may want to change the light, view, sky color
         // but we do them once per model type, because the type has one shader
-        self.shader.load_lights(lights);
+        self.shader.load_light_clusters(light_clusters);
         self.shader.load_view_matrix(camera);  
-        self.shader.load_sky_color(sky_color);
+        self.frustum = Frustum::from_view_projection(&self.projection_matrix, &Matrix4f::create_view_matrix(camera));
+        self.chunk_culling.reset();
         
-        self.shader.load_to_shadowmap_space(to_shadow_space);
         self.shader.load_shadow_params(shadow_params);
+        self.shader.load_point_shadows(point_shadows);
 
         gl::active_texture(gl::TEXTURE5);
-        gl::bind_texture(gl::TEXTURE_2D, shadow_params.shadow_map_texture);
+        gl::bind_texture(gl::TEXTURE_2D_ARRAY, shadow_params.shadow_map_texture);
     }
 
     pub fn stop_render(&mut self) {          
@@ -73,14 +111,24 @@ impl TerrainRenderer {
         gl::bind_texture(gl::TEXTURE_2D, terrain.blend_texture.tex_id.unwrap());
     }
 
-    pub fn render(&mut self, terrain: &Terrain) {        
+    pub fn render(&mut self, terrain: &Terrain, camera: &Camera) {        
         // load transform matrix into shader
         let terrain_pos = Vector3f::new(terrain.x as f32, 0.0, terrain.z as f32);
         let terrain_rot = Vector3f::new(0.0, 0.0, 0.0);
         let transform_mat = Matrix4f::create_transform_matrix(&terrain_pos, &terrain_rot, 1.0);
         self.shader.load_transformation_matrix(&transform_mat);
         
-        gl::draw_elements(gl::TRIANGLES, terrain.model.raw_model.vertex_count, gl::UNSIGNED_INT);
+        // the chunk bounds and lod distances are in the local space of the terrain cell
+        let camera_pos = &camera.position - &terrain_pos;
+        for chunk in terrain.model.chunks.iter() {
+            let min = &chunk.min + &terrain_pos;
+            let max = &chunk.max + &terrain_pos;
+            if !self.chunk_culling.count(self.frustum.intersects_aabb(&min, &max)) {
+                continue;
+            }
+            let lod = chunk.lod_range_at(&camera_pos);
+            gl::draw_elements_with_offset(gl::TRIANGLES, lod.count, gl::UNSIGNED_INT, lod.offset * mem::size_of::<u32>());
+        }
     }
 
     pub fn unprepare_terrain(&self) {
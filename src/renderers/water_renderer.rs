@@ -1,11 +1,12 @@
 use crate::display::{
     Display,
-    framebuffers::FboMap,
+    framebuffers::FramebufferObject,
 };
 use crate::entities::{
     Camera,
-    Light,
+    WaterPlanes,
     WaterTile,
+    Weather,
 };
 use crate::gl;
 use crate::models::{
@@ -13,74 +14,105 @@ use crate::models::{
 };
 use crate::math::{
     Matrix4f,
-    Vector3f,
 };
 use crate::shaders::WaterShader;
 use super::master_renderer::RenderGroup;
+use super::light_clusters::LightClusters;
 
 pub struct WaterRenderer {
     shader: WaterShader,
-    wave_factor: f32,
 }
 
 impl WaterRenderer {
-    const WATER_SPEED: f32 = 0.03;
+    // the number of planes which get their own reflection and refraction in a frame
+    pub const MAX_WATER_PLANES: usize = 2;
 
-    pub fn new(projection_mat: &Matrix4f, sky_color: &Vector3f) -> Self {
+    pub fn new(projection_mat: &Matrix4f) -> Self {
         let mut shader = WaterShader::new();
         shader.start();
         shader.load_projection_matrix(projection_mat);
-        shader.load_sky_color(sky_color);
         shader.connect_texture_units();
         shader.stop();        
         WaterRenderer {
             shader,
-            wave_factor: 0.0,
         }
     }
 
-    pub fn render(&mut self, water_tiles: &Vec<WaterTile>, framebuffers: &FboMap, camera: &Camera, display: &Display, lights: &Vec<Light>) {
+    pub fn update_projection_matrix(&mut self, projection_mat: &Matrix4f) {
+        self.shader.start();
+        self.shader.load_projection_matrix(projection_mat);
+        self.shader.stop();
+    }
+
+    // the weather changes every frame so the master renderer loads it once at the start of it
+    pub fn update_weather(&mut self, weather: &Weather, time: f32) {
+        self.shader.start();
+        self.shader.load_weather(weather, time);
+        self.shader.stop();
+    }
+
+    // plane_textures holds the reflection and refraction fbos of every height in water_planes
+    // the tiles without a plane refract the scene copy and reflect with the screen space reflection only
+    pub fn render(&mut self, water_tiles: &Vec<WaterTile>, water_planes: &WaterPlanes, plane_textures: &[(&FramebufferObject, &FramebufferObject)], 
+                scene_copy: &FramebufferObject, ssr_texture: u32, camera: &Camera, display: &Display, light_clusters: &LightClusters) {
         gl::helper::push_debug_group(RenderGroup::DRAW_WATER.id, RenderGroup::DRAW_WATER.name);
 
         self.shader.start();
         self.shader.load_camera(camera);
         
-        self.update_wave_factor(display);
-        self.shader.load_wave_factor(self.wave_factor);
+        // the same clock the cpu side uses for the water height so floating things stay on the waves
+        let wave_time = display.wall_clock.elapsed;
 
-        self.shader.load_lights(lights);
-
-        let reflection_fbo = framebuffers.fbos.get(FboMap::REFLECTION_FBO).expect("Must have reflection fbo for water render");
-        let refraction_fbo = framebuffers.fbos.get(FboMap::REFRACTION_FBO).expect("Must have refraction fbo for water render");
-
-        gl::active_texture(gl::TEXTURE0);
-        gl::bind_texture(gl::TEXTURE_2D, reflection_fbo.color_texture(0).expect("ReflectionFbo must have a color attachment"));
-        gl::active_texture(gl::TEXTURE1);
-        gl::bind_texture(gl::TEXTURE_2D, refraction_fbo.color_texture(0).expect("RefractionFbo must have a color attachment")); 
-        gl::active_texture(gl::TEXTURE4);
-        gl::bind_texture(gl::TEXTURE_2D, refraction_fbo.depth_texture.expect("RefractionFbo must have a depth attach"));
+        self.shader.load_light_clusters(light_clusters);
 
         // turn on alpha blending for softer edges (linear blending)
         // this is ok because water rendering happens after terrain/entity rendering so we blend with them
         gl::enable(gl::BLEND);
         gl::blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
 
-        for water_tile in water_tiles {
-            let transform_matrix = &water_tile.transform;
-            self.shader.load_transform_matrix(transform_matrix);
-
-            gl::bind_vertex_array(water_tile.model.raw_model.vao_id);
-            gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
-            
-            gl::active_texture(gl::TEXTURE2);
-            gl::bind_texture(gl::TEXTURE_2D, water_tile.model.dudv_tex_id.unwrap());
-            gl::active_texture(gl::TEXTURE3);
-            gl::bind_texture(gl::TEXTURE_2D, water_tile.model.normal_map_tex_id.unwrap());
-
-            gl::draw_arrays(gl::TRIANGLE_STRIP, 0, water_tile.model.raw_model.vertex_count);
-
-            gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
-            gl::bind_vertex_array(0);
+        gl::active_texture(gl::TEXTURE5);
+        gl::bind_texture(gl::TEXTURE_2D, ssr_texture);
+
+        let planes = plane_textures.iter().enumerate().map(|(plane, textures)| (Some(plane), *textures));
+        for (plane, (reflection_fbo, refraction_fbo)) in planes.chain(std::iter::once((None, (scene_copy, scene_copy)))) {
+            gl::active_texture(gl::TEXTURE0);
+            gl::bind_texture(gl::TEXTURE_2D, reflection_fbo.color_texture(0).expect("ReflectionFbo must have a color attachment"));
+            gl::active_texture(gl::TEXTURE1);
+            gl::bind_texture(gl::TEXTURE_2D, refraction_fbo.color_texture(0).expect("RefractionFbo must have a color attachment")); 
+            gl::active_texture(gl::TEXTURE4);
+            gl::bind_texture(gl::TEXTURE_2D, refraction_fbo.depth_texture.expect("RefractionFbo must have a depth attach"));
+
+            let plane_tiles = water_tiles.iter().enumerate()
+                .filter(|(idx, _)| water_planes.visible_tiles[*idx] && water_planes.tile_planes[*idx] == plane);
+            for (idx, water_tile) in plane_tiles {
+                let reflection = water_planes.tile_reflection(water_tiles, idx).expect("Only visible tiles get drawn");
+                self.shader.load_reflection(reflection);
+                self.shader.load_transform_matrix(&water_tile.transform);
+                self.shader.load_tiling(&water_tile.texture_tiling());
+                self.shader.load_material(&water_tile.material);
+                self.shader.load_wave_factor((wave_time * water_tile.material.wave_speed) % 1.0);
+                self.shader.load_waves(&water_tile.material.waves, wave_time);
+
+                // flat water is fine with the quad, the waves need the vertices of the grid
+                let (raw_model, primitive) = if water_tile.material.waves.is_empty() {
+                    (&water_tile.model.raw_model, gl::TRIANGLE_STRIP)
+                } else {
+                    (&water_tile.model.grid_raw_model, gl::TRIANGLES)
+                };
+
+                gl::bind_vertex_array(raw_model.vao_id);
+                gl::enable_vertex_attrib_array(RawModel::POS_ATTRIB);
+                
+                gl::active_texture(gl::TEXTURE2);
+                gl::bind_texture(gl::TEXTURE_2D, water_tile.model.dudv_tex_id.unwrap());
+                gl::active_texture(gl::TEXTURE3);
+                gl::bind_texture(gl::TEXTURE_2D, water_tile.model.normal_map_tex_id.unwrap());
+
+                gl::draw_arrays(primitive, 0, raw_model.vertex_count);
+
+                gl::disable_vertex_attrib_array(RawModel::POS_ATTRIB);
+                gl::bind_vertex_array(0);
+            }
         }
 
         gl::disable(gl::BLEND);
@@ -88,9 +120,4 @@ impl WaterRenderer {
 
         gl::helper::pop_debug_group();
     }
-
-    fn update_wave_factor(&mut self, display: &Display) {
-        self.wave_factor += WaterRenderer::WATER_SPEED * display.frame_time_sec;
-        self.wave_factor %= 1.0;
-    }
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
 use super::scene::Scene;
+use crate::shadows::shadow_box::ShadowCascades;
 
 extern crate rand;
 use rand::prelude::*;
 use rand::{Rng, SeedableRng};
 
-use crate::display::framebuffers::FboMap;
+use crate::renderers::render_graph::RenderGraph;
 use crate::entities::{
     AnimatedEntity,
     Entity,
@@ -13,9 +14,18 @@ use crate::entities::{
     Player,
     Ground,
     Skybox,
-    Terrain,
     DebugEntity,
     WaterTile,
+    WaterMaterial,
+    WaterReflection,
+    WaterWaves,
+    GerstnerWave,
+    Atmosphere,
+    HeightFog,
+    LightShafts,
+    ProceduralSky,
+    Weather,
+    WeatherState,
 };
 use crate::guis::{
     GuiPanel,
@@ -27,11 +37,20 @@ use crate::models::{
     Models,
     ModelType,
     CorrectionTransform,
+    NoiseTerrainGenerator,
+    ErosionParams,
 };
 use crate::particles::{
     AdvancedParticleSystem,
     ParticleSystemProps,
 }; 
+use crate::post_processing::{
+    color_lut::ColorLut,
+    post_effect::{
+        PostEffect,
+        TonemapOperator,
+    },
+};
 
 pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     //resource_manager.init(&Models::TREE);
@@ -44,6 +63,7 @@ pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     resource_manager.init(&Models::CRATE);
     resource_manager.init(&Models::LAMP);
     resource_manager.init(&Models::BARREL);
+    resource_manager.init(&Models::METAL_BARREL);
     resource_manager.init(&Models::BOULDER);
     resource_manager.init(&Models::LANTERN);
 
@@ -51,7 +71,6 @@ pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     resource_manager.init_player(CorrectionTransform::create_coord_correction(Matrix4f::get_rotation(-90.0, 0.0, 0.0)));
 
     resource_manager.init_terrain_textures();
-    resource_manager.init_terrain_model();
 
     resource_manager.init_skybox();
     resource_manager.init_water();
@@ -66,20 +85,15 @@ pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     resource_manager.init_debug_cuboid_model();
 }
 
-pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboMap) -> Scene {
+pub fn create_scene(resource_manager: &mut ResourceManager, _render_graph: &RenderGraph) -> Scene {
     let mut entities = Vec::new();    
     let mut rng: StdRng = SeedableRng::seed_from_u64(0xb000u64);
     const X_WIDTH: f32 = 1000.0;
     const Z_WIDTH: f32 = -1000.0;
     
-    let mut terrains = Vec::new();    
-    for i in -2..2 {
-        for j in -2..2 {            
-            let terrain = Terrain::new(i, j, resource_manager.terrain_pack(), resource_manager.blend_texture(), resource_manager.terrain_model());
-            terrains.push(terrain);
-        }
-    }
-    let ground = Ground { terrains };
+    // terrain is streamed in around the player so load the first ring where the player starts
+    let player_start = Vector3f::new(150.0, 0.0, -250.0);
+    let ground = Ground::new_streamed(resource_manager, NoiseTerrainGenerator::default(), Some(ErosionParams::default()), 2, &player_start);
 
     for _ in 0..100 {
         // let r_pos = ground.create_pos_on_terrain(rng.gen::<f32>() * X_WIDTH - X_WIDTH/2.0, rng.gen::<f32>() * Z_WIDTH);
@@ -114,7 +128,7 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
     }    
 
     ///////////// animated player but without all the fancy shaders ///////////////////////
-    let player_entity = AnimatedEntity::new(resource_manager.player_model(), ground.create_pos_on_terrain(150.0, -250.0), Vector3f::new(0.0, 0.0, 0.0), 0.3);
+    let player_entity = AnimatedEntity::new(resource_manager.player_model(), ground.create_pos_on_terrain(player_start.x, player_start.z), Vector3f::new(0.0, 0.0, 0.0), 0.3);
     let player = Player::new_animated(player_entity);
 
     //////////// non animated player with all shaders ///////////////////////////
@@ -126,21 +140,42 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
     let box_entity = Entity::new(resource_manager.model(ModelType::Crate), box_pos, Vector3f::new(0.0, 0.0, 0.0), 5.0);
     entities.push(box_entity);
 
+    // a few gentle swells, the wavelengths stay well above the cell size of the water grid
+    let water_material = WaterMaterial {
+        waves: WaterWaves::new(vec![
+            GerstnerWave::new(0.6, 40.0, Vector2f::new(1.0, 0.4), 0.5),
+            GerstnerWave::new(0.3, 22.0, Vector2f::new(-0.3, 1.0), 0.6),
+            GerstnerWave::new(0.15, 12.0, Vector2f::new(0.6, -0.8), 0.4),
+        ]),
+        // the close by details come from the screen space reflection, the planar one fills in what is off screen
+        reflection: WaterReflection::Hybrid,
+        ..WaterMaterial::default()
+    };
     let water_tiles = vec![
         // put the water slightly below 0 to reduce z-fighting since a lot of terrain is at 0
-        WaterTile::new(Vector3f::new(150.0, -0.2, -250.0), resource_manager.water_model()),
+        WaterTile::with_material(Vector3f::new(150.0, -0.2, -250.0), Vector2f::new(400.0, 400.0), resource_manager.water_model(), water_material),
     ];
 
+    // crates bobbing on the lake, main moves them with the water surface every frame
+    let mut floating_entities = Vec::new();
+    for &(x, z) in [(170.0, -230.0), (200.0, -280.0)].iter() {
+        floating_entities.push(entities.len());
+        entities.push(Entity::new(resource_manager.model(ModelType::Crate), Vector3f::new(x, -0.2, z), Vector3f::new(0.0, 30.0, 0.0), 2.0));
+    }
+
     let mut normal_mapped_entities = Vec::new();   
     normal_mapped_entities.push(Entity::new(resource_manager.model(ModelType::Barrel), ground.create_pos_above_terrain(150.0, 10.0, -255.0), Vector3f::zero(), 0.5));
     normal_mapped_entities.push(Entity::new(resource_manager.model(ModelType::Boulder), ground.create_pos_above_terrain(140.0, 10.0, -255.0), Vector3f::zero(), 0.5));
+    // same barrel with a pbr material so the two can be compared
+    normal_mapped_entities.push(Entity::new(resource_manager.model(ModelType::MetalBarrel), ground.create_pos_above_terrain(160.0, 10.0, -255.0), Vector3f::zero(), 0.5));
 
     let debug_entity = DebugEntity::new(resource_manager.debug_cuboid_model());
 
     let mut camera = Camera::default();
     camera.position = Vector3f::new(0.0, 80.0, 5.0);
 
-    let skybox = Skybox::new(resource_manager.skybox(), 0.0);
+    // the sun of the sky drives the sunlight, see main.rs
+    let skybox = Skybox::new(resource_manager.skybox(), 0.0).with_procedural_sky(ProceduralSky::default());
 
     let texts = vec![
         resource_manager.create_gui_text("hello\nworld", 
@@ -176,13 +211,25 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
     // add lantern which isnt a light source but has extra_info_map
     entities.push(Entity::new(resource_manager.model(ModelType::Lantern), ground.create_pos_on_terrain(130.0, -190.0), Vector3f::new(0.0, 0.0, 0.0), 1.0));
 
-    let lights = vec!{        
-        Light::new_infinite(Vector3f::new(5000.0, 10000.0, 5000.0), Vector3f::new(1.0, 1.0, 1.0)), // sunlight, no attenuation
-        Light::new_point(ground.create_pos_above_terrain(185.0,12.5,-293.0), Vector3f::new(2.0, 0.0, 0.0), Vector3f::new(1.0, 0.01, 0.002)),
-        Light::new_point(ground.create_pos_above_terrain(370.0,14.0,-300.0), Vector3f::new(0.0, 2.0, 2.0), Vector3f::new(1.0, 0.01, 0.002)),
-        Light::new_point(ground.create_pos_above_terrain(120.0,14.0,-240.0), Vector3f::new(2.0, 2.0, 0.0), Vector3f::new(1.0, 0.01, 0.002)),        
+    let mut lights = vec!{        
+        Light::new_directional(Vector3f::new(-0.5, -1.0, -0.5), Vector3f::new(1.0, 1.0, 1.0)).with_shadows(), // sunlight, no attenuation
+        Light::new_point(ground.create_pos_above_terrain(185.0,12.5,-293.0), Vector3f::new(2.0, 0.0, 0.0), Vector3f::new(1.0, 0.01, 0.002)).with_shadows(),
+        Light::new_point(ground.create_pos_above_terrain(370.0,14.0,-300.0), Vector3f::new(0.0, 2.0, 2.0), Vector3f::new(1.0, 0.01, 0.002)).with_shadows(),
+        Light::new_point(ground.create_pos_above_terrain(120.0,14.0,-240.0), Vector3f::new(2.0, 2.0, 0.0), Vector3f::new(1.0, 0.01, 0.002)).with_shadows(),        
     };
-    // add lamps 
+    // a village worth of lanterns around the player start, the light clusters keep the per fragment cost down to the few that reach it
+    for i in 0..200 {
+        let x = player_start.x + rng.gen::<f32>() * 200.0 - 100.0;
+        let z = player_start.z + rng.gen::<f32>() * 200.0 - 100.0;
+        entities.push(Entity::new(resource_manager.model(ModelType::Lantern), ground.create_pos_on_terrain(x, z), Vector3f::new(0.0, rng.gen::<f32>() * 180.0, 0.0), 1.0));
+        let color = Vector3f::new(1.0, 0.6 + rng.gen::<f32>() * 0.2, 0.3);
+        let light = Light::new_point(ground.create_pos_above_terrain(x, 3.0, z), color, Vector3f::new(1.0, 0.05, 0.02));
+        // only a handful of hero lanterns cast shadows, the closest of those get the cube shadow maps, see PointShadows
+        lights.push(if i % 50 == 0 { light.with_shadows() } else { light });
+    }
+    // spot light over the crate
+    lights.push(Light::new_spot(ground.create_pos_above_terrain(0.0, 30.0, -150.0), Vector3f::new(0.0, -1.0, 0.0), Vector3f::new(2.0, 2.0, 2.0), Vector3f::new(1.0, 0.01, 0.002), 15.0, 25.0));
+    // add lamps, the mouse picker moves the last one around together with lights[3]
     entities.push(Entity::new(resource_manager.model(ModelType::Lamp), ground.create_pos_on_terrain(185.0, -293.0), Vector3f::new(0.0, 0.0, 0.0), 1.0));
     entities.push(Entity::new(resource_manager.model(ModelType::Lamp), ground.create_pos_on_terrain(370.0, -300.0), Vector3f::new(0.0, 0.0, 0.0), 1.0));
     entities.push(Entity::new(resource_manager.model(ModelType::Lamp), ground.create_pos_on_terrain(120.0, -240.0), Vector3f::new(0.0, 0.0, 0.0), 1.0));
@@ -198,6 +245,7 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
             speed_error: 0.3, life_error: 0.3, scale_error: 0.3, 
             randomize_rotation: true, direction: Some((Vector3f::new(0.0, 1.0, 0.0), 45.0)),
             additive_blending: false,
+            wind_effect: 0.5,
         }
     );
     let mut particle_spawn_point_fire = player.position().clone();
@@ -210,6 +258,7 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
             speed_error: 0.3, life_error: 0.7, scale_error: 0.5, 
             randomize_rotation: true, direction: Some((Vector3f::new(0.0, 1.0, 0.0), 65.0)),
             additive_blending: true,
+            wind_effect: 0.3,
         }
     );
     let mut particle_spawn_point_smoke = player.position().clone();
@@ -222,6 +271,7 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
             speed_error: 0.3, life_error: 0.3, scale_error: 0.1, 
             randomize_rotation: true, direction: Some((Vector3f::new(0.0, 1.0, 0.0), 50.0)),
             additive_blending: false,
+            wind_effect: 1.0,
         }
     );
     let particle_systems = vec![
@@ -230,6 +280,30 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
         (particle_system_smoke, particle_spawn_point_smoke),
     ];
 
+    // the camera follows the player from about this far away so keep them in focus
+    let post_effects = vec![
+        PostEffect::DepthOfField { focus_distance: 50.0, focus_range: 250.0, max_blur: 6.0 },
+        PostEffect::Contrast { amount: 0.3 },
+        PostEffect::tonemap(TonemapOperator::Aces),
+        PostEffect::ColorGrading { lut: ColorLut::graded(ColorLut::DEFAULT_SIZE, 1.15, &Vector3f::new(1.0, 0.96, 0.9)), strength: 0.8 },
+        PostEffect::Vignette { radius: 0.45, softness: 0.35, strength: 0.6 },
+        PostEffect::ChromaticAberration { strength: 0.004 },
+        PostEffect::FilmGrain { strength: 0.03 },
+    ];
+
+    // god rays when the sun is on the screen
+    let atmosphere = Atmosphere::new()
+        .with_fog(HeightFog::default())
+        .with_light_shafts(LightShafts::new(0.6));
+
+    // a rainy spell and a snowy one, each with some wind
+    let weather = Weather::new()
+        .with_state(WeatherState::clear(), 60.0)
+        .with_state(WeatherState::rain(0.8).with_wind(Vector3f::new(3.0, 0.0, 1.5)), 45.0)
+        .with_state(WeatherState::clear().with_wind(Vector3f::new(1.0, 0.0, 0.5)), 30.0)
+        .with_state(WeatherState::snow(0.6).with_wind(Vector3f::new(-2.0, 0.0, 1.0)), 45.0)
+        .with_transition(10.0);
+
     Scene {
         entities, 
         normal_mapped_entities, 
@@ -244,7 +318,13 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
         guis,
         lights,
         particle_systems,
-        uses_post_processing: true,
+        disabled_passes: Vec::new(),
+        post_effects,
+        atmosphere,
+        weather,
+        shadow_cascades: ShadowCascades::default(),
         entities_with_env_map: Vec::new(),
+        reflection_probes: Vec::new(),
+        floating_entities,
     }
 }
\ No newline at end of file
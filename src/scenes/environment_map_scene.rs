@@ -1,6 +1,10 @@
 use super::scene::Scene;
+use crate::shadows::shadow_box::ShadowCascades;
 
-use crate::display::framebuffers::FboMap;
+use crate::renderers::render_graph::{
+    RenderGraph,
+    RenderPass,
+};
 use crate::entities::{
     Entity,
     Camera,
@@ -9,6 +13,9 @@ use crate::entities::{
     Ground,
     Skybox,
     DebugEntity,
+    ReflectionProbe,
+    Atmosphere,
+    Weather,
 };
 use crate::math::Vector3f;
 use crate::models::{
@@ -16,12 +23,19 @@ use crate::models::{
     Models,
     ModelType,
 };
+use crate::post_processing::post_effect::{
+    PostEffect,
+    TonemapOperator,
+};
 
 pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     resource_manager.init(&Models::PLAYER);
     resource_manager.init(&Models::DRAGON);
     resource_manager.init(&Models::META);
     resource_manager.init(&Models::TEA);
+    // something other than the sky for the probes to see
+    resource_manager.init(&Models::FLOOR_TILE);
+    resource_manager.init(&Models::CRATE);
     
     resource_manager.init_terrain_textures();
     resource_manager.init_terrain_model();
@@ -34,11 +48,22 @@ pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     resource_manager.init_debug_cuboid_model();
 }
 
-pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboMap) -> Scene {
-    let entities = Vec::new();
+pub fn create_scene(resource_manager: &mut ResourceManager, _render_graph: &RenderGraph) -> Scene {
+    let mut entities = Vec::new();
+    let tile_size = 10.0;
+    for x in -4..=4 {
+        for z in -2..=2 {
+            entities.push(Entity::new(resource_manager.model(ModelType::FloorTile), 
+                Vector3f::new(x as f32 * 2.0 * tile_size, 5.0, -50.0 + z as f32 * 2.0 * tile_size), Vector3f::zero(), tile_size));
+        }
+    }
+    // a few crates between the env mapped models so they show up in each other's reflections
+    for &(x, z) in [(-25.0, -50.0), (25.0, -50.0), (-50.0, -30.0), (0.0, -70.0), (50.0, -30.0)].iter() {
+        entities.push(Entity::new(resource_manager.model(ModelType::Crate), Vector3f::new(x, 9.0, z), Vector3f::new(0.0, 20.0, 0.0), 4.0));
+    }
     
     let terrains = Vec::new();   
-    let ground = Ground { terrains };
+    let ground = Ground::new(terrains);
 
     let player_entity = Entity::new(resource_manager.model(ModelType::Player), Vector3f::new(0.0, 20.0, -50.0), Vector3f::new(0.0, 180.0, 0.0), 1.0);
     let mut player = Player::new(player_entity);
@@ -54,14 +79,13 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
     camera.position = Vector3f::new(0.0, 0.0, 0.0);
 
     let mut skybox = Skybox::new(resource_manager.cathedral_skybox(), 0.0);
-    skybox.uses_fog = false;
     skybox.rotate_speed = 0.0;
 
     let texts = Vec::new();
     
     let lights = vec!{
-        //Light::new_infinite(Vector3f::new(0.0, 10000.0, 0.0), Vector3f::new(0.8, 0.8, 0.8)), // sunlight, no attenuation
-        Light::new_infinite(Vector3f::new(5000.0, 10000.0, -5000.0), Vector3f::new(0.8, 0.8, 0.8)), // sunlight, no attenuation
+        //Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::new(0.8, 0.8, 0.8)).with_shadows(), // sunlight, no attenuation
+        Light::new_directional(Vector3f::new(-0.5, -1.0, 0.5), Vector3f::new(0.8, 0.8, 0.8)).with_shadows(), // sunlight, no attenuation
     };
 
     let particle_systems = Vec::new();
@@ -74,6 +98,14 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
         Entity::new(resource_manager.model(ModelType::Meta), Vector3f::new(50.0, 20.0, -50.0), Vector3f::new(0.0, 0.0, 0.0), 3.0),
     ];
 
+    // one probe in the middle of every model, the teapot sits in a box made of the crates around it
+    let reflection_probes = vec![
+        ReflectionProbe::new(Vector3f::new(-50.0, 20.0, -50.0), 30.0),
+        ReflectionProbe::new(Vector3f::new(0.0, 20.0, -50.0), 30.0)
+            .with_parallax_box(Vector3f::new(-25.0, 5.0, -70.0), Vector3f::new(25.0, 60.0, -30.0)),
+        ReflectionProbe::new(Vector3f::new(50.0, 20.0, -50.0), 30.0),
+    ];
+
     Scene {
         entities, 
         normal_mapped_entities, 
@@ -88,7 +120,18 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
         guis,
         lights,
         particle_systems,
-        uses_post_processing: false,
+        disabled_passes: RenderPass::POST_PROCESSING_EFFECTS.to_vec(),
+        // the reflections have lots of thin bright edges, fxaa instead of msaa smooths those too
+        post_effects: vec![
+            PostEffect::tonemap(TonemapOperator::Reinhard),
+            PostEffect::Fxaa,
+        ],
+        // the cathedral is close by and its sky shouldn't get foggy
+        atmosphere: Atmosphere::new(),
+        weather: Weather::new(),
+        shadow_cascades: ShadowCascades::default(),
         entities_with_env_map,
+        reflection_probes,
+        floating_entities: Vec::new(),
     }
 }
\ No newline at end of file
@@ -1,6 +1,10 @@
 use super::scene::Scene;
+use crate::shadows::shadow_box::ShadowCascades;
 
-use crate::display::framebuffers::FboMap;
+use crate::renderers::render_graph::{
+    RenderGraph,
+    RenderPass,
+};
 use crate::entities::{
     Entity,
     Camera,
@@ -9,6 +13,9 @@ use crate::entities::{
     Ground,
     Skybox,
     DebugEntity,
+    Atmosphere,
+    Weather,
+    HeightFog,
 };
 use crate::math::{Vector3f};
 use crate::models::{
@@ -19,6 +26,7 @@ use crate::models::{
     RawModel,
     TextureId,
 };
+use crate::post_processing::post_effect::PostEffect;
 use crate::particles::{
     AdvancedParticleSystem,
     ParticleSystemProps,
@@ -39,11 +47,11 @@ pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
 
 // to use the particles in this example you need to use the correct renderer in the ParticleMaster
 // at the moment no switching through parameters to simplify things since gpu instancing is more efficient anyway
-pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboMap) -> Scene {    
+pub fn create_scene(resource_manager: &mut ResourceManager, _render_graph: &RenderGraph) -> Scene {    
     let entities = Vec::new();
     
     let terrains = Vec::new();
-    let ground = Ground { terrains };
+    let ground = Ground::new(terrains);
 
     //let player_entity = Entity::new(resource_manager.model(ModelType::Player), ground.create_pos_on_terrain(150.0, -250.0), Vector3f::new(0.0, 180.0, 0.0), 0.3);
     let player_entity = Entity::new(resource_manager.model(ModelType::Player), Vector3f::new(0.0, 0.0, 0.0), Vector3f::new(0.0, 180.0, 0.0), 0.3);
@@ -64,8 +72,8 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
     let texts = Vec::new();
     
     let lights = vec!{
-        //Light::new_infinite(Vector3f::new(0.0, 10000.0, 0.0), Vector3f::new(0.8, 0.8, 0.8)), // sunlight, no attenuation
-        Light::new_infinite(Vector3f::new(5000.0, 10000.0, -5000.0), Vector3f::new(0.8, 0.8, 0.8)), // sunlight, no attenuation
+        //Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::new(0.8, 0.8, 0.8)).with_shadows(), // sunlight, no attenuation
+        Light::new_directional(Vector3f::new(-0.5, -1.0, 0.5), Vector3f::new(0.8, 0.8, 0.8)).with_shadows(), // sunlight, no attenuation
     };
 
     let particle_spawn = player.position().clone();    
@@ -76,6 +84,7 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
             speed_error: 0.3, life_error: 0.3, scale_error: 0.1, 
             randomize_rotation: true, direction: Some((Vector3f::new(0.0, 1.0, 0.0), 150.0)),
             additive_blending: false,
+            wind_effect: 0.0,
         }
     );
 
@@ -99,7 +108,13 @@ pub fn create_scene(resource_manager: &mut ResourceManager, _framebuffers: &FboM
         guis,
         lights,
         particle_systems,
-        uses_post_processing: false,
+        disabled_passes: RenderPass::POST_PROCESSING_EFFECTS.to_vec(),
+        post_effects: PostEffect::minimal_stack(),
+        atmosphere: Atmosphere::new().with_fog(HeightFog::default()),
+        weather: Weather::new(),
+        shadow_cascades: ShadowCascades::default(),
         entities_with_env_map: Vec::new(),
+        reflection_probes: Vec::new(),
+        floating_entities: Vec::new(),
     }
 }
\ No newline at end of file
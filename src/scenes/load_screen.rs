@@ -29,7 +29,7 @@ pub fn create_load_screen(resource_manager: &mut ResourceManager) -> LoadScreen
     let gui_background = resource_manager.get_gui_texture(ResourceManager::WHITE_TEXTURE);
     //let shadow_map = framebuffers.shadowmap_fbo.depth_texture;
     let guis = vec!{
-        GuiPanel::new(gui_background, Vector2f::new(0.0, 0.0), Vector2f::new(1.0, 1.0)),
+        GuiPanel::new_background(gui_background),
     };
 
     let texts = vec![
@@ -5,8 +5,11 @@ use crate::entities::{
     Player,
     Ground,
     Skybox,
+    ReflectionProbe,
     WaterTile,
     DebugEntity,
+    Atmosphere,
+    Weather,
 };
 use crate::math::Vector3f;
 use crate::models::QuadModel;
@@ -15,6 +18,9 @@ use crate::guis::{
     GuiText,
 };
 use crate::particles::AdvancedParticleSystem;
+use crate::post_processing::post_effect::PostEffect;
+use crate::renderers::render_graph::RenderPass;
+use crate::shadows::shadow_box::ShadowCascades;
 
 pub struct Scene {
     pub entities: Vec<Entity>, 
@@ -31,6 +37,19 @@ pub struct Scene {
     pub guis: Vec<GuiPanel>,
     pub lights: Vec<Light>,
     pub particle_systems: Vec<(AdvancedParticleSystem, Vector3f)>,
-    pub uses_post_processing: bool,
+    // passes of the render graph this scene switches off
+    pub disabled_passes: Vec<RenderPass>,
+    // runs in this order after the bloom
+    pub post_effects: Vec<PostEffect>,
+    // the fog and the light shafts, applied before the bloom
+    pub atmosphere: Atmosphere,
+    // rain, snow and wind, see entities/weather.rs
+    pub weather: Weather,
+    // how the sun shadows split the view into cascades
+    pub shadow_cascades: ShadowCascades,
     pub entities_with_env_map: Vec<Entity>,
+    // the env mapped entities reflect the closest one that reaches them
+    pub reflection_probes: Vec<ReflectionProbe>,
+    // indices into entities of the ones that bob on the water
+    pub floating_entities: Vec<usize>,
 }
\ No newline at end of file
@@ -1,6 +1,11 @@
 use super::scene::Scene;
+use crate::shadows::shadow_box::ShadowCascades;
 
-use crate::display::framebuffers::FboMap;
+use crate::renderers::render_graph::{
+    RenderGraph,
+    RenderPass,
+    RenderResource,
+};
 use crate::entities::{
     AnimatedEntity,    
     Camera,
@@ -10,6 +15,9 @@ use crate::entities::{
     Skybox,
     Terrain,
     DebugEntity,
+    Atmosphere,
+    Weather,
+    HeightFog,
 };
 use crate::guis::GuiPanel;
 use crate::math::{Matrix4f, Vector3f, Vector2f};
@@ -19,6 +27,7 @@ use crate::models::{
     Models,    
     TextureId,
 };
+use crate::post_processing::post_effect::PostEffect;
 
 pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     resource_manager.init(&Models::PLAYER);
@@ -35,7 +44,7 @@ pub fn init_scene_resources(resource_manager: &mut ResourceManager) {
     resource_manager.init_debug_cuboid_model();
 }
 
-pub fn create_scene(resource_manager: &mut ResourceManager, framebuffers: &FboMap) -> Scene {
+pub fn create_scene(resource_manager: &mut ResourceManager, render_graph: &RenderGraph) -> Scene {
 
     let entities = Vec::new();
     
@@ -46,7 +55,7 @@ pub fn create_scene(resource_manager: &mut ResourceManager, framebuffers: &FboMa
             terrains.push(terrain);
         }
     }
-    let ground = Ground { terrains };
+    let ground = Ground::new(terrains);
 
     //let player_entity = Entity::new(resource_manager.model(ModelType::Player), ground.create_pos_on_terrain(150.0, -250.0), Vector3f::new(0.0, 180.0, 0.0), 0.3);
     //let player_entity = Entity::new(resource_manager.model(ModelType::Player), ground.create_pos_on_terrain(0.0, 0.0), Vector3f::new(0.0, 180.0, 0.0), 0.3);
@@ -69,16 +78,17 @@ pub fn create_scene(resource_manager: &mut ResourceManager, framebuffers: &FboMa
     let texts = Vec::new();
     
     let lights = vec!{
-        //Light::new_infinite(Vector3f::new(0.0, 10000.0, 0.0), Vector3f::new(0.8, 0.8, 0.8)), // sunlight, no attenuation
-        Light::new_infinite(Vector3f::new(5000.0, 10000.0, -5000.0), Vector3f::new(0.8, 0.8, 0.8)), // sunlight, no attenuation
+        //Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::new(0.8, 0.8, 0.8)).with_shadows(), // sunlight, no attenuation
+        Light::new_directional(Vector3f::new(-0.5, -1.0, 0.5), Vector3f::new(0.8, 0.8, 0.8)).with_shadows(), // sunlight, no attenuation
     };
 
     let particle_systems = Vec::new();
 
-    let shadow_map = framebuffers.fbos[FboMap::SHADOW_MAP_FBO].depth_texture.expect("Must have shadowmaps to show it in gui");
-    let guis = vec!{
-        GuiPanel::new(TextureId::FboTexture(shadow_map), Vector2f::new(0.6, 0.6), Vector2f::new(0.4, 0.4)),
-    };
+    // one panel per cascade along the top of the screen, the closest cascade on the right
+    let shadow_maps = render_graph.fbo(RenderResource::ShadowMap).depth_layer_views.as_ref().expect("Must have shadowmap cascades to show them in gui");
+    let guis = shadow_maps.iter().enumerate().map(|(i, shadow_map)| {
+        GuiPanel::new(TextureId::FboTexture(*shadow_map), Vector2f::new(0.8 - 0.4 * i as f32, 0.8), Vector2f::new(0.2, 0.2))
+    }).collect();
 
     Scene {
         entities, 
@@ -94,7 +104,13 @@ pub fn create_scene(resource_manager: &mut ResourceManager, framebuffers: &FboMa
         guis,
         lights,
         particle_systems,
-        uses_post_processing: false,
+        disabled_passes: RenderPass::POST_PROCESSING_EFFECTS.to_vec(),
+        post_effects: PostEffect::minimal_stack(),
+        atmosphere: Atmosphere::new().with_fog(HeightFog::default()),
+        weather: Weather::new(),
+        shadow_cascades: ShadowCascades::default(),
         entities_with_env_map: Vec::new(),
+        reflection_probes: Vec::new(),
+        floating_entities: Vec::new(),
     }
 }
\ No newline at end of file
@@ -1,18 +1,27 @@
 use super::shader_program::ShaderProgram;
+use super::light_cluster_uniforms::LightClusterUniforms;
+use super::joint_transform_uniforms::JointTransformUniforms;
+use super::shadow_uniforms::ShadowUniforms;
+use super::point_shadow_uniforms::PointShadowUniforms;
 use crate::animations::joint::AccumulatedJointTransforms;
 use crate::models::RawModel;
-use crate::math::{
-    Matrix4f,
-    Vector3f,
+use crate::math::Matrix4f;
+use crate::renderers::light_clusters::LightClusters;
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
 };
-use crate::constants::MAX_JOINTS;
 
 pub struct AnimatedModelShader {
     shader_program: ShaderProgram,
     location_mvp_matrix: i32,
-    location_light_direction: i32,
+    location_transform_matrix: i32,
+    location_view_matrix: i32,
+    location_light_clusters: LightClusterUniforms,
+    location_shadows: ShadowUniforms,
+    location_point_shadows: PointShadowUniforms,
     location_diffuse_map: i32,
-    location_joint_transforms: [i32; MAX_JOINTS],
+    location_joint_transforms: JointTransformUniforms,
 }
 
 impl AnimatedModelShader {
@@ -20,10 +29,14 @@ impl AnimatedModelShader {
     pub fn new() -> Self {
         let (
             mut location_mvp_matrix,
-            mut location_light_direction,
-            mut location_diffuse_map,            
+            mut location_transform_matrix,
+            mut location_view_matrix,
+            mut location_light_clusters,
+            mut location_shadows,
+            mut location_point_shadows,
+            mut location_diffuse_map,
+            mut location_joint_transforms,
         ) = Default::default();
-        let mut location_joint_transforms = [0i32; MAX_JOINTS];
         
         let shader_program = ShaderProgram::new(
             "res/shaders/animations/animModelVert.glsl",
@@ -39,17 +52,22 @@ impl AnimatedModelShader {
             |shader_prog| {                
                 location_diffuse_map = shader_prog.get_uniform_location("diffuse_map");
                 location_mvp_matrix = shader_prog.get_uniform_location("projection_view_model");
-                location_light_direction = shader_prog.get_uniform_location("light_direction");
-                // diffuse lighting                
-                for i in 0..MAX_JOINTS {
-                    // TODO: maybe we should optimize these string allocations that we keep doing
-                    location_joint_transforms[i] = shader_prog.get_uniform_location(&format!("joint_transforms[{}]", i));                    
-                }                
+                location_transform_matrix = shader_prog.get_uniform_location("transform");
+                location_view_matrix = shader_prog.get_uniform_location("view_matrix");
+                // diffuse lighting
+                location_light_clusters = LightClusterUniforms::new(shader_prog);
+                location_shadows = ShadowUniforms::new(shader_prog);
+                location_point_shadows = PointShadowUniforms::new(shader_prog);
+                location_joint_transforms = JointTransformUniforms::new(shader_prog);
         });
         AnimatedModelShader {
             shader_program,
             location_mvp_matrix,
-            location_light_direction,
+            location_transform_matrix,
+            location_view_matrix,
+            location_light_clusters,
+            location_shadows,
+            location_point_shadows,
             location_diffuse_map,
             location_joint_transforms,
         }
@@ -64,22 +82,37 @@ impl AnimatedModelShader {
     }
 
     pub fn load_joint_transforms(&mut self, joint_transforms: &AccumulatedJointTransforms) {
-        let mut cnt = 0;
-        for trans in joint_transforms.transforms.iter() {
-            ShaderProgram::load_matrix(self.location_joint_transforms[cnt], trans);
-            cnt += 1;
-        }
+        self.location_joint_transforms.load(joint_transforms);
     }
 
     pub fn load_mvp_matrix(&mut self, mvp: &Matrix4f) {
         ShaderProgram::load_matrix(self.location_mvp_matrix, mvp);
     }
 
-    pub fn load_light_direction(&mut self, light_position: &Vector3f) {
-        ShaderProgram::load_vector3d(self.location_light_direction, light_position);
+    pub fn load_transform_matrix(&mut self, transform_matrix: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_transform_matrix, transform_matrix);
+    }
+
+    pub fn load_view_matrix(&mut self, view_matrix: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_view_matrix, view_matrix);
+    }
+
+    pub fn load_light_clusters(&mut self, light_clusters: &LightClusters) {
+        self.location_light_clusters.load(light_clusters);
+    }
+
+    pub fn load_shadow_params(&mut self, shadow_params: &ShadowParams) {
+        self.location_shadows.load(shadow_params);
+    }
+
+    pub fn load_point_shadows(&mut self, point_shadows: &PointShadows) {
+        self.location_point_shadows.load(point_shadows);
     }
 
     pub fn connect_texture_units(&mut self) {
         ShaderProgram::load_int(self.location_diffuse_map, 0);
+        self.location_shadows.connect_texture_units(1);
+        self.location_light_clusters.connect_texture_units();
+        self.location_point_shadows.connect_texture_units();
     }
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+use super::shader_program::ShaderProgram;
+use super::light_cluster_uniforms::LightClusterUniforms;
+use super::point_shadow_uniforms::PointShadowUniforms;
+use super::shadow_uniforms::ShadowUniforms;
+use crate::renderers::{
+    deferred_renderer::DeferredRenderer,
+    light_clusters::LightClusters,
+};
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
+};
+use crate::models::RawModel;
+use crate::math::{
+    Matrix4f,
+    Vector3f,
+};
+
+// lights the g-buffer, either for the whole screen or inside the volume of one light
+pub struct DeferredLightingShader {
+    program: ShaderProgram,
+    location_albedo_map: i32,
+    location_normal_map: i32,
+    location_specular_map: i32,
+    location_emissive_map: i32,
+    location_depth_map: i32,
+    location_inverse_view_projection: i32,
+    location_view_projection: i32,
+    location_view_matrix: i32,
+    location_camera_position: i32,
+    location_global_lights: [i32; DeferredRenderer::MAX_GLOBAL_LIGHTS],
+    location_global_light_count: i32,
+    location_volume_light: i32,
+    location_light_clusters: LightClusterUniforms,
+    location_point_shadows: PointShadowUniforms,
+    location_shadows: ShadowUniforms,
+}
+
+impl DeferredLightingShader {
+    // the lights without a range, the ambient light and the glow for every pixel
+    pub fn new_fullscreen() -> DeferredLightingShader {
+        DeferredLightingShader::create("res/shaders/deferred/fullscreenVert.glsl", "res/shaders/deferred/deferredLightingFrag.glsl")
+    }
+
+    // one light drawn as a sphere of its range that adds its light on top of the full screen pass
+    pub fn new_light_volume() -> DeferredLightingShader {
+        DeferredLightingShader::create("res/shaders/deferred/lightVolumeVert.glsl", "res/shaders/deferred/lightVolumeFrag.glsl")
+    }
+
+    fn create(vertex_file: &str, fragment_file: &str) -> DeferredLightingShader {
+        let (
+            mut location_albedo_map,
+            mut location_normal_map,
+            mut location_specular_map,
+            mut location_emissive_map,
+            mut location_depth_map,
+            mut location_inverse_view_projection,
+            mut location_view_projection,
+            mut location_view_matrix,
+            mut location_camera_position,
+        ) = Default::default();
+
+        let (
+            mut location_global_lights,
+            mut location_global_light_count,
+            mut location_volume_light,
+            mut location_light_clusters,
+            mut location_point_shadows,
+            mut location_shadows,
+        ) = Default::default();
+
+        let shader_program = ShaderProgram::new(
+            vertex_file,
+            None,
+            fragment_file,
+            |shader_prog| {
+                // only the light volumes have vertices, the full screen triangle comes from gl_VertexID
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "pos");
+            },
+            |shader_prog| {
+                // g-buffer samplers
+                location_albedo_map = shader_prog.get_uniform_location("albedo_map");
+                location_normal_map = shader_prog.get_uniform_location("normal_map");
+                location_specular_map = shader_prog.get_uniform_location("specular_map");
+                location_emissive_map = shader_prog.get_uniform_location("emissive_map");
+                location_depth_map = shader_prog.get_uniform_location("depth_map");
+                // reconstructing the world position from the depth
+                location_inverse_view_projection = shader_prog.get_uniform_location("inverse_view_projection");
+                location_view_projection = shader_prog.get_uniform_location("view_projection");
+                location_view_matrix = shader_prog.get_uniform_location("view_matrix");
+                location_camera_position = shader_prog.get_uniform_location("camera_position");
+
+                let mut global_lights = [0; DeferredRenderer::MAX_GLOBAL_LIGHTS];
+                for (i, location) in global_lights.iter_mut().enumerate() {
+                    *location = shader_prog.get_uniform_location(&format!("global_lights[{}]", i));
+                }
+                location_global_lights = global_lights;
+                location_global_light_count = shader_prog.get_uniform_location("global_light_count");
+                location_volume_light = shader_prog.get_uniform_location("volume_light");
+
+                location_light_clusters = LightClusterUniforms::new(shader_prog);
+                location_point_shadows = PointShadowUniforms::new(shader_prog);
+                location_shadows = ShadowUniforms::new(shader_prog);
+        });
+
+        DeferredLightingShader {
+            program: shader_program,
+            location_albedo_map,
+            location_normal_map,
+            location_specular_map,
+            location_emissive_map,
+            location_depth_map,
+            location_inverse_view_projection,
+            location_view_projection,
+            location_view_matrix,
+            location_camera_position,
+            location_global_lights,
+            location_global_light_count,
+            location_volume_light,
+            location_light_clusters,
+            location_point_shadows,
+            location_shadows,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.program.stop();
+    }
+
+    pub fn connect_texture_units(&mut self) {
+        ShaderProgram::load_int(self.location_albedo_map, 0);
+        ShaderProgram::load_int(self.location_normal_map, 1);
+        ShaderProgram::load_int(self.location_specular_map, 2);
+        ShaderProgram::load_int(self.location_emissive_map, 3);
+        ShaderProgram::load_int(self.location_depth_map, 4);
+        self.location_shadows.connect_texture_units(5);
+        self.location_light_clusters.connect_texture_units();
+        self.location_point_shadows.connect_texture_units();
+    }
+
+    pub fn load_camera(&mut self, view_matrix: &Matrix4f, view_projection: &Matrix4f, camera_position: &Vector3f) {
+        ShaderProgram::load_matrix(self.location_view_matrix, view_matrix);
+        ShaderProgram::load_matrix(self.location_view_projection, view_projection);
+        ShaderProgram::load_matrix(self.location_inverse_view_projection, &view_projection.inverse());
+        ShaderProgram::load_vector3d(self.location_camera_position, camera_position);
+    }
+
+    // the lights are indices into the lights array like in the light data
+    pub fn load_global_lights(&mut self, lights: &[usize]) {
+        let count = lights.len().min(DeferredRenderer::MAX_GLOBAL_LIGHTS);
+        for (location, light) in self.location_global_lights.iter().zip(lights.iter()) {
+            ShaderProgram::load_int(*location, *light as i32);
+        }
+        ShaderProgram::load_int(self.location_global_light_count, count as i32);
+    }
+
+    pub fn load_volume_light(&mut self, light: usize) {
+        ShaderProgram::load_int(self.location_volume_light, light as i32);
+    }
+
+    pub fn load_light_clusters(&mut self, light_clusters: &LightClusters) {
+        self.location_light_clusters.load(light_clusters);
+    }
+
+    pub fn load_point_shadows(&mut self, point_shadows: &PointShadows) {
+        self.location_point_shadows.load(point_shadows);
+    }
+
+    pub fn load_shadow_params(&mut self, shadow_params: &ShadowParams) {
+        self.location_shadows.load(shadow_params);
+    }
+}
\ No newline at end of file
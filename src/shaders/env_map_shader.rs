@@ -1,5 +1,8 @@
 use super::shader_program::ShaderProgram;
+use super::shadow_uniforms::ShadowUniforms;
+use crate::entities::ReflectionProbe;
 use crate::models::RawModel;
+use crate::shadows::shadow_params::ShadowParams;
 use crate::math::{
     Vector3f,
     Matrix4f,
@@ -8,10 +11,20 @@ use crate::math::{
 pub struct EnvMapShader {
     shader_program: ShaderProgram,
     location_vp_matrix: i32,
+    location_view_matrix: i32,
     location_model_matrix: i32,
     location_camera_position: i32,
     location_in_texture: i32,
     location_env_map: i32,
+    location_light_direction: i32,
+    location_has_shadow_map: i32,
+    location_shadows: ShadowUniforms,
+    location_probe_maps: i32,
+    location_probe_layer: i32,
+    location_probe_position: i32,
+    location_has_parallax_box: i32,
+    location_parallax_box_min: i32,
+    location_parallax_box_max: i32,
 }
 
 impl EnvMapShader {
@@ -19,10 +32,23 @@ impl EnvMapShader {
     pub fn new() -> Self {
         let (
             mut location_vp_matrix,
+            mut location_view_matrix,
             mut location_camera_position,
             mut location_model_matrix,
             mut location_in_texture,
             mut location_env_map,
+            mut location_light_direction,
+            mut location_has_shadow_map,
+            mut location_shadows,
+        ) = Default::default();
+        // the reflection probe the entity reflects, Default only goes up to tuples of 12
+        let (
+            mut location_probe_maps,
+            mut location_probe_layer,
+            mut location_probe_position,
+            mut location_has_parallax_box,
+            mut location_parallax_box_min,
+            mut location_parallax_box_max,
         ) = Default::default();
 
         let shader_program = ShaderProgram::new(
@@ -36,19 +62,39 @@ impl EnvMapShader {
             },
             |shader_prog| {
                 location_vp_matrix = shader_prog.get_uniform_location("vp_matrix");
+                location_view_matrix = shader_prog.get_uniform_location("view_matrix");
                 location_model_matrix = shader_prog.get_uniform_location("model_matrix");
                 location_camera_position = shader_prog.get_uniform_location("camera_position");
                 location_in_texture = shader_prog.get_uniform_location("in_texture");
                 location_env_map = shader_prog.get_uniform_location("env_map");
+                location_light_direction = shader_prog.get_uniform_location("light_direction");
+                location_has_shadow_map = shader_prog.get_uniform_location("has_shadow_map");
+                location_shadows = ShadowUniforms::new(shader_prog);
+                location_probe_maps = shader_prog.get_uniform_location("probe_maps");
+                location_probe_layer = shader_prog.get_uniform_location("probe_layer");
+                location_probe_position = shader_prog.get_uniform_location("probe_position");
+                location_has_parallax_box = shader_prog.get_uniform_location("has_parallax_box");
+                location_parallax_box_min = shader_prog.get_uniform_location("parallax_box_min");
+                location_parallax_box_max = shader_prog.get_uniform_location("parallax_box_max");
             }
         );
         Self {
             shader_program,
             location_vp_matrix,
+            location_view_matrix,
             location_model_matrix,
             location_camera_position,
             location_in_texture,
             location_env_map,
+            location_light_direction,
+            location_has_shadow_map,
+            location_shadows,
+            location_probe_maps,
+            location_probe_layer,
+            location_probe_position,
+            location_has_parallax_box,
+            location_parallax_box_min,
+            location_parallax_box_max,
         }
     }
 
@@ -64,6 +110,10 @@ impl EnvMapShader {
         ShaderProgram::load_matrix(self.location_vp_matrix, vp);
     }
 
+    pub fn load_view_matrix(&mut self, view: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_view_matrix, view);
+    }
+
     pub fn load_model_matrix(&mut self, model: &Matrix4f) {
         ShaderProgram::load_matrix(self.location_model_matrix, model);
     }
@@ -72,8 +122,38 @@ impl EnvMapShader {
         ShaderProgram::load_vector3d(self.location_camera_position, camera_pos);
     }
 
+    pub fn load_light_direction(&mut self, direction: &Vector3f) {
+        ShaderProgram::load_vector3d(self.location_light_direction, direction);
+    }
+
+    // None when no light casts the sun shadows
+    pub fn load_shadow_params(&mut self, shadow_params: Option<&ShadowParams>) {
+        ShaderProgram::load_bool(self.location_has_shadow_map, shadow_params.is_some());
+        if let Some(shadow_params) = shadow_params {
+            self.location_shadows.load(shadow_params);
+        }
+    }
+
+    // the probe and its cube in the probe cube map array, None reflects the sky
+    pub fn load_probe(&mut self, probe: Option<(usize, &ReflectionProbe)>) {
+        match probe {
+            Some((slot, probe)) => {
+                ShaderProgram::load_int(self.location_probe_layer, slot as i32);
+                ShaderProgram::load_vector3d(self.location_probe_position, &probe.position);
+                ShaderProgram::load_bool(self.location_has_parallax_box, probe.parallax_box.is_some());
+                if let Some(parallax_box) = &probe.parallax_box {
+                    ShaderProgram::load_vector3d(self.location_parallax_box_min, &parallax_box.min);
+                    ShaderProgram::load_vector3d(self.location_parallax_box_max, &parallax_box.max);
+                }
+            },
+            None => ShaderProgram::load_int(self.location_probe_layer, -1),
+        }
+    }
+
     pub fn connect_texture_units(&mut self) {
         ShaderProgram::load_int(self.location_in_texture, 0);
         ShaderProgram::load_int(self.location_env_map, 1);
+        self.location_shadows.connect_texture_units(2);
+        ShaderProgram::load_int(self.location_probe_maps, 3);
     }
 }
\ No newline at end of file
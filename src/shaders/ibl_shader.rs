@@ -0,0 +1,74 @@
+use super::shader_program::ShaderProgram;
+
+// bakes the image based lighting maps of a sky cube map, every draw is a full screen triangle on one cube face (or on the brdf lut)
+pub struct IblShader {
+    program: ShaderProgram,
+    location_environment_map: i32,
+    location_face: i32,
+    location_roughness: i32,
+}
+
+impl IblShader {
+    // diffuse light for every normal
+    pub fn new_irradiance() -> IblShader {
+        IblShader::create("res/shaders/ibl/irradianceFrag.glsl")
+    }
+
+    // specular light for every reflection direction, one mip level per roughness
+    pub fn new_prefilter() -> IblShader {
+        IblShader::create("res/shaders/ibl/prefilterFrag.glsl")
+    }
+
+    // doesn't depend on the sky so it only needs to be rendered once
+    pub fn new_brdf_integration() -> IblShader {
+        IblShader::create("res/shaders/ibl/brdfLutFrag.glsl")
+    }
+
+    fn create(fragment_file: &str) -> IblShader {
+        let (
+            mut location_environment_map,
+            mut location_face,
+            mut location_roughness,
+        ) = Default::default();
+
+        let shader_program = ShaderProgram::new(
+            "res/shaders/ibl/cubeFaceVert.glsl",
+            None,
+            fragment_file,
+            |_| {
+                // the triangle comes from gl_VertexID
+            },
+            |shader_prog| {
+                location_environment_map = shader_prog.get_uniform_location("environment_map");
+                location_face = shader_prog.get_uniform_location("face");
+                location_roughness = shader_prog.get_uniform_location("roughness");
+        });
+
+        IblShader {
+            program: shader_program,
+            location_environment_map,
+            location_face,
+            location_roughness,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.program.stop();
+    }
+
+    pub fn connect_texture_units(&mut self) {
+        ShaderProgram::load_int(self.location_environment_map, 0);
+    }
+
+    pub fn load_face(&mut self, face: usize) {
+        ShaderProgram::load_int(self.location_face, face as i32);
+    }
+
+    pub fn load_roughness(&mut self, roughness: f32) {
+        ShaderProgram::load_float(self.location_roughness, roughness);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,28 @@
+use super::shader_program::ShaderProgram;
+use crate::animations::joint::AccumulatedJointTransforms;
+use crate::constants::MAX_JOINTS;
+
+// the joint_transforms array of the skinning vertex shaders, shared by the animated model shader and its shadow shaders
+#[derive(Default)]
+pub struct JointTransformUniforms {
+    location_joint_transforms: [i32; MAX_JOINTS],
+}
+
+impl JointTransformUniforms {
+    pub fn new(shader_prog: &ShaderProgram) -> JointTransformUniforms {
+        let mut location_joint_transforms = [0; MAX_JOINTS];
+        for (i, location) in location_joint_transforms.iter_mut().enumerate() {
+            // TODO: maybe we should optimize these string allocations that we keep doing
+            *location = shader_prog.get_uniform_location(&format!("joint_transforms[{}]", i));
+        }
+        JointTransformUniforms {
+            location_joint_transforms,
+        }
+    }
+
+    pub fn load(&self, joint_transforms: &AccumulatedJointTransforms) {
+        for (location, transform) in self.location_joint_transforms.iter().zip(joint_transforms.transforms.iter()) {
+            ShaderProgram::load_matrix(*location, transform);
+        }
+    }
+}
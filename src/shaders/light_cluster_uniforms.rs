@@ -0,0 +1,39 @@
+use super::shader_program::ShaderProgram;
+use crate::renderers::light_clusters::LightClusters;
+
+// uniforms of the clustered light lookup, shared by all the shaders that do lighting
+#[derive(Default)]
+pub struct LightClusterUniforms {
+    location_light_data: i32,
+    location_cluster_ranges: i32,
+    location_light_indices: i32,
+    location_cluster_projection: i32,
+    location_cluster_depth_params: i32,
+    location_shadow_light: i32,
+}
+
+impl LightClusterUniforms {
+    pub fn new(shader_prog: &ShaderProgram) -> LightClusterUniforms {
+        LightClusterUniforms {
+            location_light_data: shader_prog.get_uniform_location("light_data"),
+            location_cluster_ranges: shader_prog.get_uniform_location("cluster_ranges"),
+            location_light_indices: shader_prog.get_uniform_location("light_indices"),
+            location_cluster_projection: shader_prog.get_uniform_location("cluster_projection"),
+            location_cluster_depth_params: shader_prog.get_uniform_location("cluster_depth_params"),
+            location_shadow_light: shader_prog.get_uniform_location("shadow_light"),
+        }
+    }
+
+    pub fn connect_texture_units(&self) {
+        ShaderProgram::load_int(self.location_light_data, LightClusters::LIGHT_DATA_UNIT);
+        ShaderProgram::load_int(self.location_cluster_ranges, LightClusters::CLUSTER_RANGES_UNIT);
+        ShaderProgram::load_int(self.location_light_indices, LightClusters::LIGHT_INDICES_UNIT);
+    }
+
+    pub fn load(&self, light_clusters: &LightClusters) {
+        ShaderProgram::load_vector2d(self.location_cluster_projection, &light_clusters.grid.projection_scale());
+        ShaderProgram::load_vector2d(self.location_cluster_depth_params, &light_clusters.grid.depth_params());
+        // -1 when nothing casts a shadow
+        ShaderProgram::load_int(self.location_shadow_light, light_clusters.grid.shadow_light.map_or(-1, |light| light as i32));
+    }
+}
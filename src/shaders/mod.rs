@@ -9,11 +9,25 @@ pub mod normal_map_static_shader;
 pub mod text_shader;
 pub mod particle_shader;
 pub mod shadow_shader;
+pub mod point_shadow_shader;
 pub mod debug_shader;
 pub mod post_processing;
 pub mod particle_using_geometry_shader;
 pub mod env_map_shader;
 pub mod animated_model_shader;
+pub mod light_cluster_uniforms;
+pub mod shadow_uniforms;
+pub mod point_shadow_uniforms;
+pub mod joint_transform_uniforms;
+pub mod wave_uniforms;
+pub mod weather_uniforms;
+pub mod deferred_lighting_shader;
+pub mod ibl_shader;
+pub mod pbr_shader;
+pub mod ssr_shader;
+pub mod ssr_composite_shader;
+pub mod procedural_sky_shader;
+pub mod precipitation_shader;
 
 pub use self::static_shader::StaticShader;
 pub use self::normal_map_static_shader::NormalMapStaticShader;
@@ -24,6 +38,14 @@ pub use self::water_shader::WaterShader;
 pub use self::text_shader::TextShader;
 pub use self::particle_shader::ParticleShader;
 pub use self::shadow_shader::ShadowShader;
+pub use self::point_shadow_shader::PointShadowShader;
 pub use self::debug_shader::DebugShader;
 pub use self::env_map_shader::EnvMapShader;
 pub use self::animated_model_shader::AnimatedModelShader;
+pub use self::deferred_lighting_shader::DeferredLightingShader;
+pub use self::ibl_shader::IblShader;
+pub use self::pbr_shader::PbrShader;
+pub use self::ssr_shader::ScreenSpaceReflectionShader;
+pub use self::ssr_composite_shader::SsrCompositeShader;
+pub use self::procedural_sky_shader::ProceduralSkyShader;
+pub use self::precipitation_shader::PrecipitationShader;
@@ -0,0 +1,283 @@
+use super::shader_program::ShaderProgram;
+use super::light_cluster_uniforms::LightClusterUniforms;
+use super::point_shadow_uniforms::PointShadowUniforms;
+use super::shadow_uniforms::ShadowUniforms;
+use super::weather_uniforms::WeatherUniforms;
+use crate::entities::Weather;
+use crate::renderers::{
+    ibl_renderer::SkyLighting,
+    light_clusters::LightClusters,
+};
+use crate::shadows::{
+    shadow_params::ShadowParams,
+    point_shadows::PointShadows,
+};
+use crate::models::{
+    PbrMaterial,
+    RawModel,
+};
+use crate::math::{
+    Matrix4f,
+    Vector2f,
+    Vector3f,
+    Vector4f,
+};
+
+// metallic/roughness materials lit with a cook-torrance brdf by the clustered lights and by the sky
+pub struct PbrShader {
+    program: ShaderProgram,
+    location_transformation_matrix: i32,
+    location_projection_matrix: i32,
+    location_view_matrix: i32,
+    location_camera_position: i32,
+    location_light_clusters: LightClusterUniforms,
+    location_point_shadows: PointShadowUniforms,
+    location_shadows: ShadowUniforms,
+    location_number_of_rows: i32,
+    location_texture_offset: i32,
+    location_clip_plane: i32,
+    location_base_color_map: i32,
+    location_normal_map: i32,
+    location_metallic_roughness_map: i32,
+    location_occlusion_map: i32,
+    location_emissive_map: i32,
+    location_has_normal_map: i32,
+    location_has_metallic_roughness_map: i32,
+    location_has_occlusion_map: i32,
+    location_has_emissive_map: i32,
+    location_metallic_factor: i32,
+    location_roughness_factor: i32,
+    location_emissive_strength: i32,
+    location_has_ibl: i32,
+    location_irradiance_map: i32,
+    location_prefiltered_map: i32,
+    location_next_irradiance_map: i32,
+    location_next_prefiltered_map: i32,
+    location_brdf_lut: i32,
+    location_sky_blend_factor: i32,
+    location_sky_rotation: i32,
+    location_prefiltered_max_level: i32,
+    location_weather: WeatherUniforms,
+}
+
+impl PbrShader {
+    pub fn new() -> PbrShader {
+        let (
+            mut location_transformation_matrix,
+            mut location_projection_matrix,
+            mut location_view_matrix,
+            mut location_camera_position,
+            mut location_light_clusters,
+            mut location_point_shadows,
+            mut location_shadows,
+            mut location_number_of_rows,
+            mut location_texture_offset,
+            mut location_clip_plane,
+        ) = Default::default();
+
+        let (
+            mut location_base_color_map,
+            mut location_normal_map,
+            mut location_metallic_roughness_map,
+            mut location_occlusion_map,
+            mut location_emissive_map,
+            mut location_has_normal_map,
+            mut location_has_metallic_roughness_map,
+            mut location_has_occlusion_map,
+            mut location_has_emissive_map,
+            mut location_metallic_factor,
+            mut location_roughness_factor,
+            mut location_emissive_strength,
+        ) = Default::default();
+
+        let (
+            mut location_has_ibl,
+            mut location_irradiance_map,
+            mut location_prefiltered_map,
+            mut location_next_irradiance_map,
+            mut location_next_prefiltered_map,
+            mut location_brdf_lut,
+            mut location_sky_blend_factor,
+            mut location_sky_rotation,
+            mut location_prefiltered_max_level,
+        ) = Default::default();
+
+        let mut location_weather = WeatherUniforms::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/pbr/pbrVert.glsl",
+            None,
+            "res/shaders/pbr/pbrFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "pos");
+                shader_prog.bind_attribute(RawModel::TEX_COORD_ATTRIB, "tex_coord");
+                shader_prog.bind_attribute(RawModel::NORMAL_ATTRIB, "normal");
+                shader_prog.bind_attribute(RawModel::TANGENT_ATTRIB, "tangents");
+            },
+            |shader_prog| {
+                location_transformation_matrix = shader_prog.get_uniform_location("transform");
+                location_projection_matrix = shader_prog.get_uniform_location("projection_matrix");
+                location_view_matrix = shader_prog.get_uniform_location("view_matrix");
+                location_camera_position = shader_prog.get_uniform_location("camera_position");
+                // direct lighting
+                location_light_clusters = LightClusterUniforms::new(shader_prog);
+                location_point_shadows = PointShadowUniforms::new(shader_prog);
+                location_shadows = ShadowUniforms::new(shader_prog);
+                location_weather = WeatherUniforms::new(shader_prog);
+                // atlas uniforms
+                location_number_of_rows = shader_prog.get_uniform_location("number_of_rows");
+                location_texture_offset = shader_prog.get_uniform_location("texture_offset");
+                location_clip_plane = shader_prog.get_uniform_location("clip_plane");
+                // material
+                location_base_color_map = shader_prog.get_uniform_location("base_color_map");
+                location_normal_map = shader_prog.get_uniform_location("normal_map");
+                location_metallic_roughness_map = shader_prog.get_uniform_location("metallic_roughness_map");
+                location_occlusion_map = shader_prog.get_uniform_location("occlusion_map");
+                location_emissive_map = shader_prog.get_uniform_location("emissive_map");
+                location_has_normal_map = shader_prog.get_uniform_location("has_normal_map");
+                location_has_metallic_roughness_map = shader_prog.get_uniform_location("has_metallic_roughness_map");
+                location_has_occlusion_map = shader_prog.get_uniform_location("has_occlusion_map");
+                location_has_emissive_map = shader_prog.get_uniform_location("has_emissive_map");
+                location_metallic_factor = shader_prog.get_uniform_location("metallic_factor");
+                location_roughness_factor = shader_prog.get_uniform_location("roughness_factor");
+                location_emissive_strength = shader_prog.get_uniform_location("emissive_strength");
+                // image based lighting
+                location_has_ibl = shader_prog.get_uniform_location("has_ibl");
+                location_irradiance_map = shader_prog.get_uniform_location("irradiance_map");
+                location_prefiltered_map = shader_prog.get_uniform_location("prefiltered_map");
+                location_next_irradiance_map = shader_prog.get_uniform_location("next_irradiance_map");
+                location_next_prefiltered_map = shader_prog.get_uniform_location("next_prefiltered_map");
+                location_brdf_lut = shader_prog.get_uniform_location("brdf_lut");
+                location_sky_blend_factor = shader_prog.get_uniform_location("sky_blend_factor");
+                location_sky_rotation = shader_prog.get_uniform_location("sky_rotation");
+                location_prefiltered_max_level = shader_prog.get_uniform_location("prefiltered_max_level");
+        });
+
+        PbrShader {
+            program: shader_program,
+            location_transformation_matrix,
+            location_projection_matrix,
+            location_view_matrix,
+            location_camera_position,
+            location_light_clusters,
+            location_point_shadows,
+            location_shadows,
+            location_weather,
+            location_number_of_rows,
+            location_texture_offset,
+            location_clip_plane,
+            location_base_color_map,
+            location_normal_map,
+            location_metallic_roughness_map,
+            location_occlusion_map,
+            location_emissive_map,
+            location_has_normal_map,
+            location_has_metallic_roughness_map,
+            location_has_occlusion_map,
+            location_has_emissive_map,
+            location_metallic_factor,
+            location_roughness_factor,
+            location_emissive_strength,
+            location_has_ibl,
+            location_irradiance_map,
+            location_prefiltered_map,
+            location_next_irradiance_map,
+            location_next_prefiltered_map,
+            location_brdf_lut,
+            location_sky_blend_factor,
+            location_sky_rotation,
+            location_prefiltered_max_level,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.program.stop();
+    }
+
+    pub fn load_atlas_number_of_rows(&mut self, number_of_rows: usize) {
+        ShaderProgram::load_float(self.location_number_of_rows, number_of_rows as f32);
+    }
+
+    pub fn load_atlas_offset(&mut self, offset: &Vector2f) {
+        ShaderProgram::load_vector2d(self.location_texture_offset, offset);
+    }
+
+    // which of the maps the material has, the rest falls back to the factors
+    pub fn load_material(&mut self, material: &PbrMaterial, has_normal_map: bool) {
+        ShaderProgram::load_bool(self.location_has_normal_map, has_normal_map);
+        ShaderProgram::load_bool(self.location_has_metallic_roughness_map, material.metallic_roughness_tex_id.is_some());
+        ShaderProgram::load_bool(self.location_has_occlusion_map, material.occlusion_tex_id.is_some());
+        ShaderProgram::load_bool(self.location_has_emissive_map, material.emissive_tex_id.is_some());
+        ShaderProgram::load_float(self.location_metallic_factor, material.metallic);
+        ShaderProgram::load_float(self.location_roughness_factor, material.roughness);
+        ShaderProgram::load_float(self.location_emissive_strength, material.emissive_strength);
+    }
+
+    // without sky lighting the shader falls back to the flat ambient light of the phong shaders
+    pub fn load_sky_lighting(&mut self, sky_lighting: Option<&SkyLighting>) {
+        ShaderProgram::load_bool(self.location_has_ibl, sky_lighting.is_some());
+        if let Some(sky_lighting) = sky_lighting {
+            ShaderProgram::load_float(self.location_sky_blend_factor, sky_lighting.blend_factor);
+            // the skybox view matrix turns the sky by its yaw so we look up the sky by turning the world directions the other way
+            let sky_rotation = Matrix4f::get_rotation(0.0, sky_lighting.rotation_yaw_deg, 0.0);
+            ShaderProgram::load_matrix(self.location_sky_rotation, &sky_rotation);
+            ShaderProgram::load_float(self.location_prefiltered_max_level, (sky_lighting.from.prefiltered_levels - 1) as f32);
+        }
+    }
+
+    pub fn load_light_clusters(&mut self, light_clusters: &LightClusters) {
+        self.location_light_clusters.load(light_clusters);
+    }
+
+    pub fn load_point_shadows(&mut self, point_shadows: &PointShadows) {
+        self.location_point_shadows.load(point_shadows);
+    }
+
+    pub fn load_shadow_params(&mut self, shadow_params: &ShadowParams) {
+        self.location_shadows.load(shadow_params);
+    }
+
+    pub fn load_transformation_matrix(&mut self, transform_matrix: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_transformation_matrix, transform_matrix);
+    }
+
+    pub fn load_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_projection_matrix, projection_matrix);
+    }
+
+    pub fn load_view_matrix(&mut self, view_matrix: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_view_matrix, view_matrix);
+    }
+
+    pub fn load_camera_position(&mut self, camera_position: &Vector3f) {
+        ShaderProgram::load_vector3d(self.location_camera_position, camera_position);
+    }
+
+    pub fn load_clip_plane(&mut self, clip_plane: &Vector4f) {
+        ShaderProgram::load_vector4d(self.location_clip_plane, clip_plane);
+    }
+
+    pub fn connect_texture_units(&mut self) {
+        ShaderProgram::load_int(self.location_base_color_map, 0);
+        ShaderProgram::load_int(self.location_normal_map, 1);
+        ShaderProgram::load_int(self.location_metallic_roughness_map, 2);
+        ShaderProgram::load_int(self.location_occlusion_map, 3);
+        ShaderProgram::load_int(self.location_emissive_map, 4);
+        self.location_shadows.connect_texture_units(5);
+        ShaderProgram::load_int(self.location_irradiance_map, 6);
+        ShaderProgram::load_int(self.location_prefiltered_map, 7);
+        ShaderProgram::load_int(self.location_next_irradiance_map, 8);
+        ShaderProgram::load_int(self.location_next_prefiltered_map, 9);
+        // 10 to 13 are the light clusters and the point shadows
+        ShaderProgram::load_int(self.location_brdf_lut, 14);
+        self.location_light_clusters.connect_texture_units();
+        self.location_point_shadows.connect_texture_units();
+    }
+
+    pub fn load_weather(&mut self, weather: &Weather, time: f32) {
+        self.location_weather.load(weather, time);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,124 @@
+use crate::shaders::shader_program::ShaderProgram;
+use super::joint_transform_uniforms::JointTransformUniforms;
+use super::weather_uniforms::WeatherUniforms;
+use crate::animations::joint::AccumulatedJointTransforms;
+use crate::entities::Weather;
+use crate::math::{
+    Matrix4f,
+    Vector3f,
+};
+use crate::models::{
+    RawModel,
+    TexturedModel,
+};
+
+pub struct PointShadowShader {
+    shader_program: ShaderProgram,
+    // only the animated shader has the transform as a uniform, the static one takes it per instance
+    location_transform: i32,
+    location_face_matrices: [i32; 6],
+    location_layer_offset: i32,
+    location_light_position: i32,
+    location_far_plane: i32,
+    // only the animated point shadow shader has them
+    location_joint_transforms: JointTransformUniforms,
+    // only the static one sways the plants like the entity shader does
+    location_sways_in_wind: i32,
+    location_weather: WeatherUniforms,
+}
+
+impl PointShadowShader {
+    pub fn new() -> Self {
+        PointShadowShader::create("res/shaders/shadows/pointShadowVertexShader.glsl")
+    }
+
+    // skins the vertices with the joint transforms before the geometry shader sends them to the faces
+    pub fn new_animated() -> Self {
+        PointShadowShader::create("res/shaders/shadows/animPointShadowVertexShader.glsl")
+    }
+
+    fn create(vertex_file: &str) -> Self {
+        let (
+            mut location_transform,
+            mut location_face_matrices,
+            mut location_layer_offset,
+            mut location_light_position,
+            mut location_far_plane,
+            mut location_joint_transforms,
+            mut location_sways_in_wind,
+            mut location_weather,
+        ) = Default::default();
+
+        let shader_program = ShaderProgram::new(
+            vertex_file,
+            Some("res/shaders/shadows/pointShadowGeometryShader.glsl"),
+            "res/shaders/shadows/pointShadowFragmentShader.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "pos");
+                shader_prog.bind_attribute(RawModel::TEX_COORD_ATTRIB, "in_texture_coords");
+                shader_prog.bind_attribute(RawModel::JOINT_IDX_ATTRIB, "in_joint_indicies");
+                shader_prog.bind_attribute(RawModel::JOINT_WEIGHT_ATTRIB, "in_joint_weights");
+                shader_prog.bind_attribute(TexturedModel::TRANSFORM_COLUMN1, "transform");
+            },
+            |shader_prog| {
+                location_transform = shader_prog.get_uniform_location("transform");
+                let mut face_matrices = [0; 6];
+                for (i, location) in face_matrices.iter_mut().enumerate() {
+                    *location = shader_prog.get_uniform_location(&format!("face_matrices[{}]", i));
+                }
+                location_face_matrices = face_matrices;
+                location_layer_offset = shader_prog.get_uniform_location("layer_offset");
+                location_light_position = shader_prog.get_uniform_location("light_position");
+                location_far_plane = shader_prog.get_uniform_location("far_plane");
+                location_joint_transforms = JointTransformUniforms::new(shader_prog);
+                location_sways_in_wind = shader_prog.get_uniform_location("sways_in_wind");
+                location_weather = WeatherUniforms::new(shader_prog);
+            }
+        );
+        PointShadowShader {
+            shader_program,
+            location_transform,
+            location_face_matrices,
+            location_layer_offset,
+            location_light_position,
+            location_far_plane,
+            location_joint_transforms,
+            location_sways_in_wind,
+            location_weather,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    pub fn load_transform_matrix(&mut self, transform: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_transform, transform);
+    }
+
+    pub fn load_joint_transforms(&mut self, joint_transforms: &AccumulatedJointTransforms) {
+        self.location_joint_transforms.load(joint_transforms);
+    }
+
+    pub fn load_sways_in_wind(&mut self, sways_in_wind: bool) {
+        ShaderProgram::load_bool(self.location_sways_in_wind, sways_in_wind);
+    }
+
+    pub fn load_weather(&mut self, weather: &Weather, time: f32) {
+        self.location_weather.load(weather, time);
+    }
+
+    // the cube of the light is the 6 layers starting at slot * 6
+    pub fn load_light(&mut self, slot: usize, face_matrices: &[Matrix4f; 6], light_position: &Vector3f, far_plane: f32) {
+        for (location, matrix) in self.location_face_matrices.iter().zip(face_matrices.iter()) {
+            ShaderProgram::load_matrix(*location, matrix);
+        }
+        ShaderProgram::load_int(self.location_layer_offset, 6 * slot as i32);
+        ShaderProgram::load_vector3d(self.location_light_position, light_position);
+        ShaderProgram::load_float(self.location_far_plane, far_plane);
+    }
+}
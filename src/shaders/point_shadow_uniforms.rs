@@ -0,0 +1,37 @@
+use super::shader_program::ShaderProgram;
+use crate::renderers::point_shadow_renderer::PointShadowRenderer;
+use crate::shadows::point_shadows::PointShadows;
+
+// uniforms of the point light cube shadow lookup, shared by all the shaders that do lighting
+#[derive(Default)]
+pub struct PointShadowUniforms {
+    location_point_shadow_maps: i32,
+    location_point_shadow_map_size: i32,
+    location_point_shadow_lights: [i32; PointShadows::MAX_POINT_SHADOWS],
+}
+
+impl PointShadowUniforms {
+    pub fn new(shader_prog: &ShaderProgram) -> PointShadowUniforms {
+        let mut location_point_shadow_lights = [0; PointShadows::MAX_POINT_SHADOWS];
+        for (i, location) in location_point_shadow_lights.iter_mut().enumerate() {
+            *location = shader_prog.get_uniform_location(&format!("point_shadow_lights[{}]", i));
+        }
+        PointShadowUniforms {
+            location_point_shadow_maps: shader_prog.get_uniform_location("point_shadow_maps"),
+            location_point_shadow_map_size: shader_prog.get_uniform_location("point_shadow_map_size"),
+            location_point_shadow_lights,
+        }
+    }
+
+    pub fn connect_texture_units(&self) {
+        ShaderProgram::load_int(self.location_point_shadow_maps, PointShadowRenderer::SHADOW_MAP_UNIT);
+    }
+
+    pub fn load(&self, point_shadows: &PointShadows) {
+        ShaderProgram::load_float(self.location_point_shadow_map_size, point_shadows.shadow_map_size as f32);
+        // -1 for the slots without a light
+        for (location, light) in self.location_point_shadow_lights.iter().zip(point_shadows.shader_slots().iter()) {
+            ShaderProgram::load_int(*location, *light);
+        }
+    }
+}
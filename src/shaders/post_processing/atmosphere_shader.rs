@@ -0,0 +1,167 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::entities::HeightFog;
+use crate::math::{
+    Matrix4f,
+    Vector3f,
+};
+use crate::models::RawModel;
+
+// fogs the resolved scene by the distance and the height of what every pixel shows and adds the light shafts on top
+pub struct AtmosphereShader {
+    shader_program: ShaderProgram,
+    inverse_view_projection: Matrix4f,
+    camera_position: Vector3f,
+    fog: Option<HeightFog>,
+    fog_color: Vector3f,
+    sun_direction: Vector3f,
+    sun_color: Vector3f,
+    light_shafts_strength: Option<f32>,
+    location_scene_texture: i32,
+    location_depth_texture: i32,
+    location_light_shafts_texture: i32,
+    location_inverse_view_projection: i32,
+    location_camera_position: i32,
+    location_has_fog: i32,
+    location_fog_density: i32,
+    location_fog_falloff: i32,
+    location_fog_base_height: i32,
+    location_fog_color: i32,
+    location_sun_direction: i32,
+    location_sun_color: i32,
+    location_sun_scattering: i32,
+    location_has_light_shafts: i32,
+    location_light_shafts_strength: i32,
+}
+
+
+impl AtmosphereShader {
+    pub fn new() -> Self {
+        let (
+            mut location_scene_texture,
+            mut location_depth_texture,
+            mut location_light_shafts_texture,
+            mut location_inverse_view_projection,
+            mut location_camera_position,
+            mut location_has_fog,
+            mut location_fog_density,
+            mut location_fog_falloff,
+        ) = Default::default();
+        let (
+            mut location_fog_base_height,
+            mut location_fog_color,
+            mut location_sun_direction,
+            mut location_sun_color,
+            mut location_sun_scattering,
+            mut location_has_light_shafts,
+            mut location_light_shafts_strength,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/atmosphereFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            },
+            |shader_prog| {
+                location_scene_texture = shader_prog.get_uniform_location("scene_texture");
+                location_depth_texture = shader_prog.get_uniform_location("depth_texture");
+                location_light_shafts_texture = shader_prog.get_uniform_location("light_shafts_texture");
+                location_inverse_view_projection = shader_prog.get_uniform_location("inverse_view_projection");
+                location_camera_position = shader_prog.get_uniform_location("camera_position");
+                location_has_fog = shader_prog.get_uniform_location("has_fog");
+                location_fog_density = shader_prog.get_uniform_location("fog_density");
+                location_fog_falloff = shader_prog.get_uniform_location("fog_falloff");
+                location_fog_base_height = shader_prog.get_uniform_location("fog_base_height");
+                location_fog_color = shader_prog.get_uniform_location("fog_color");
+                location_sun_direction = shader_prog.get_uniform_location("sun_direction");
+                location_sun_color = shader_prog.get_uniform_location("sun_color");
+                location_sun_scattering = shader_prog.get_uniform_location("sun_scattering");
+                location_has_light_shafts = shader_prog.get_uniform_location("has_light_shafts");
+                location_light_shafts_strength = shader_prog.get_uniform_location("light_shafts_strength");
+            });
+        AtmosphereShader {
+            shader_program,
+            inverse_view_projection: Matrix4f::identity(),
+            camera_position: Vector3f::zero(),
+            fog: None,
+            fog_color: Vector3f::zero(),
+            sun_direction: Vector3f::POS_Y_AXIS,
+            sun_color: Vector3f::zero(),
+            light_shafts_strength: None,
+            location_scene_texture,
+            location_depth_texture,
+            location_light_shafts_texture,
+            location_inverse_view_projection,
+            location_camera_position,
+            location_has_fog,
+            location_fog_density,
+            location_fog_falloff,
+            location_fog_base_height,
+            location_fog_color,
+            location_sun_direction,
+            location_sun_color,
+            location_sun_scattering,
+            location_has_light_shafts,
+            location_light_shafts_strength,
+        }
+    }
+
+    // the settings below take effect with the next init
+    pub fn set_camera(&mut self, view_projection: &Matrix4f, camera_position: &Vector3f) {
+        self.inverse_view_projection = view_projection.inverse();
+        self.camera_position = camera_position.clone();
+    }
+
+    // the color is the one of the fog at the current time of day
+    pub fn set_fog(&mut self, fog: Option<&HeightFog>, color: &Vector3f) {
+        self.fog = fog.cloned();
+        self.fog_color = color.clone();
+    }
+
+    // towards the sun, the color is zero when there is no sun
+    pub fn set_sun(&mut self, direction: &Vector3f, color: &Vector3f) {
+        self.sun_direction = direction.clone();
+        self.sun_color = color.clone();
+    }
+
+    pub fn set_light_shafts_strength(&mut self, strength: Option<f32>) {
+        self.light_shafts_strength = strength;
+    }
+}
+
+impl Shader for AtmosphereShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_scene_texture, 0);
+        ShaderProgram::load_int(self.location_depth_texture, 1);
+        ShaderProgram::load_int(self.location_light_shafts_texture, 2);
+        ShaderProgram::load_matrix(self.location_inverse_view_projection, &self.inverse_view_projection);
+        ShaderProgram::load_vector3d(self.location_camera_position, &self.camera_position);
+        ShaderProgram::load_bool(self.location_has_fog, self.fog.is_some());
+        if let Some(fog) = &self.fog {
+            ShaderProgram::load_float(self.location_fog_density, fog.density);
+            ShaderProgram::load_float(self.location_fog_falloff, fog.falloff);
+            ShaderProgram::load_float(self.location_fog_base_height, fog.base_height);
+        }
+        ShaderProgram::load_vector3d(self.location_fog_color, &self.fog_color);
+        ShaderProgram::load_vector3d(self.location_sun_direction, &self.sun_direction);
+        ShaderProgram::load_vector3d(self.location_sun_color, &self.sun_color);
+        ShaderProgram::load_float(self.location_sun_scattering, HeightFog::SUN_SCATTERING);
+        ShaderProgram::load_bool(self.location_has_light_shafts, self.light_shafts_strength.is_some());
+        ShaderProgram::load_float(self.location_light_shafts_strength, self.light_shafts_strength.unwrap_or(0.0));
+        self.stop();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,58 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::models::RawModel;
+
+pub struct ChromaticAberrationShader {
+    shader_program: ShaderProgram,
+    strength: f32,
+    location_strength: i32,
+    location_color_texture: i32,
+}
+
+
+impl ChromaticAberrationShader {
+    pub fn new(strength: f32) -> Self {
+        let (
+            mut location_strength,
+            mut location_color_texture,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/chromaticAberrationFrag.glsl",
+            |shader_prog| { 
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            }, 
+            |shader_prog| {
+                location_strength = shader_prog.get_uniform_location("strength");
+                location_color_texture = shader_prog.get_uniform_location("color_texture");
+            });
+        ChromaticAberrationShader {
+            shader_program,
+            strength,
+            location_strength,
+            location_color_texture,
+        }
+    }
+}
+
+impl Shader for ChromaticAberrationShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_color_texture, 0);
+        ShaderProgram::load_float(self.location_strength, self.strength);
+        self.stop();
+    }
+}
\ No newline at end of file
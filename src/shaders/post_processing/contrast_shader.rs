@@ -7,11 +7,18 @@ use crate::models::RawModel;
 
 pub struct ContrastShader {
     shader_program: ShaderProgram,
+    contrast: f32,
+    location_contrast: i32,
+    location_color_texture: i32,
 }
 
 
 impl ContrastShader {
-    pub fn new() -> Self {
+    pub fn new(contrast: f32) -> Self {
+        let (
+            mut location_contrast,
+            mut location_color_texture,
+        ) = Default::default();
         let shader_program = ShaderProgram::new(
             "res/shaders/post_processing/defaultVert.glsl",
             None,
@@ -19,11 +26,15 @@ impl ContrastShader {
             |shader_prog| { 
                 shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
             }, 
-            |_| {
-                
+            |shader_prog| {
+                location_contrast = shader_prog.get_uniform_location("contrast");
+                location_color_texture = shader_prog.get_uniform_location("color_texture");
             });
         ContrastShader {
             shader_program,
+            contrast,
+            location_contrast,
+            location_color_texture,
         }
     }
 }
@@ -38,5 +49,10 @@ impl Shader for ContrastShader {
     }
 
     fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_color_texture, 0);
+        ShaderProgram::load_float(self.location_contrast, self.contrast);
+        self.stop();
     }
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::models::RawModel;
+
+// reads the depth of the resolved camera fbo to know how far away every pixel is
+pub struct DepthOfFieldShader {
+    shader_program: ShaderProgram,
+    near: f32,
+    far: f32,
+    focus_distance: f32,
+    focus_range: f32,
+    max_blur: f32,
+    location_near: i32,
+    location_far: i32,
+    location_focus_distance: i32,
+    location_focus_range: i32,
+    location_max_blur: i32,
+    location_color_texture: i32,
+    location_depth_texture: i32,
+}
+
+
+impl DepthOfFieldShader {
+    pub fn new(near: f32, far: f32, focus_distance: f32, focus_range: f32, max_blur: f32) -> Self {
+        let (
+            mut location_near,
+            mut location_far,
+            mut location_focus_distance,
+            mut location_focus_range,
+            mut location_max_blur,
+            mut location_color_texture,
+            mut location_depth_texture,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/depthOfFieldFrag.glsl",
+            |shader_prog| { 
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            }, 
+            |shader_prog| {
+                location_near = shader_prog.get_uniform_location("near");
+                location_far = shader_prog.get_uniform_location("far");
+                location_focus_distance = shader_prog.get_uniform_location("focus_distance");
+                location_focus_range = shader_prog.get_uniform_location("focus_range");
+                location_max_blur = shader_prog.get_uniform_location("max_blur");
+                location_color_texture = shader_prog.get_uniform_location("color_texture");
+                location_depth_texture = shader_prog.get_uniform_location("depth_texture");
+            });
+        DepthOfFieldShader {
+            shader_program,
+            near,
+            far,
+            focus_distance,
+            focus_range,
+            max_blur,
+            location_near,
+            location_far,
+            location_focus_distance,
+            location_focus_range,
+            location_max_blur,
+            location_color_texture,
+            location_depth_texture,
+        }
+    }
+}
+
+impl Shader for DepthOfFieldShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_color_texture, 0);
+        ShaderProgram::load_int(self.location_depth_texture, 1);
+        ShaderProgram::load_float(self.location_near, self.near);
+        ShaderProgram::load_float(self.location_far, self.far);
+        ShaderProgram::load_float(self.location_focus_distance, self.focus_distance);
+        ShaderProgram::load_float(self.location_focus_range, self.focus_range);
+        ShaderProgram::load_float(self.location_max_blur, self.max_blur);
+        self.stop();
+    }
+}
\ No newline at end of file
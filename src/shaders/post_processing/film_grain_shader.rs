@@ -0,0 +1,70 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::models::RawModel;
+
+pub struct FilmGrainShader {
+    shader_program: ShaderProgram,
+    strength: f32,
+    time: f32,
+    location_strength: i32,
+    location_time: i32,
+    location_color_texture: i32,
+}
+
+
+impl FilmGrainShader {
+    pub fn new(strength: f32) -> Self {
+        let (
+            mut location_strength,
+            mut location_time,
+            mut location_color_texture,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/filmGrainFrag.glsl",
+            |shader_prog| { 
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            }, 
+            |shader_prog| {
+                location_strength = shader_prog.get_uniform_location("strength");
+                location_time = shader_prog.get_uniform_location("time");
+                location_color_texture = shader_prog.get_uniform_location("color_texture");
+            });
+        FilmGrainShader {
+            shader_program,
+            strength,
+            time: 0.0,
+            location_strength,
+            location_time,
+            location_color_texture,
+        }
+    }
+
+    // seconds since the start, the noise changes with it
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+}
+
+impl Shader for FilmGrainShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_color_texture, 0);
+        ShaderProgram::load_float(self.location_strength, self.strength);
+        ShaderProgram::load_float(self.location_time, self.time);
+        self.stop();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,49 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::models::RawModel;
+
+pub struct FxaaShader {
+    shader_program: ShaderProgram,
+    location_color_texture: i32,
+}
+
+
+impl FxaaShader {
+    pub fn new() -> Self {
+        let mut location_color_texture = 0;
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/fxaaFrag.glsl",
+            |shader_prog| { 
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            }, 
+            |shader_prog| {
+                location_color_texture = shader_prog.get_uniform_location("color_texture");
+            });
+        FxaaShader {
+            shader_program,
+            location_color_texture,
+        }
+    }
+}
+
+impl Shader for FxaaShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_color_texture, 0);
+        self.stop();
+    }
+}
\ No newline at end of file
@@ -32,6 +32,11 @@ impl HorizontalBlurShader {
         }
     }
 
+    // takes effect with the next init
+    pub fn set_viewport_width(&mut self, viewport_width: usize) {
+        self.viewport_width = viewport_width;
+    }
+
     fn load_viewport_dimension(&mut self, viewport_width: usize) {
         ShaderProgram::load_float(self.location_size, viewport_width as f32);
     }
@@ -0,0 +1,109 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::entities::LightShafts;
+use crate::math::{
+    Matrix4f,
+    Vector2f,
+};
+use crate::models::RawModel;
+
+// blurs the sky around the sun radially away from it, the depth of the resolved camera fbo says where the sky is
+pub struct LightShaftsShader {
+    shader_program: ShaderProgram,
+    inverse_projection: Matrix4f,
+    decay: f32,
+    density: f32,
+    sun_position: Vector2f,
+    sun_visibility: f32,
+    location_depth_texture: i32,
+    location_inverse_projection: i32,
+    location_decay: i32,
+    location_density: i32,
+    location_sun_position: i32,
+    location_sun_visibility: i32,
+}
+
+
+impl LightShaftsShader {
+    pub fn new(projection_matrix: &Matrix4f) -> Self {
+        let (
+            mut location_depth_texture,
+            mut location_inverse_projection,
+            mut location_decay,
+            mut location_density,
+            mut location_sun_position,
+            mut location_sun_visibility,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/lightShaftsFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            },
+            |shader_prog| {
+                location_depth_texture = shader_prog.get_uniform_location("depth_texture");
+                location_inverse_projection = shader_prog.get_uniform_location("inverse_projection");
+                location_decay = shader_prog.get_uniform_location("decay");
+                location_density = shader_prog.get_uniform_location("density");
+                location_sun_position = shader_prog.get_uniform_location("sun_position");
+                location_sun_visibility = shader_prog.get_uniform_location("sun_visibility");
+            });
+        let shafts = LightShafts::new(1.0);
+        LightShaftsShader {
+            shader_program,
+            inverse_projection: projection_matrix.inverse(),
+            decay: shafts.decay,
+            density: shafts.density,
+            sun_position: Vector2f::new(0.5, 0.5),
+            sun_visibility: 0.0,
+            location_depth_texture,
+            location_inverse_projection,
+            location_decay,
+            location_density,
+            location_sun_position,
+            location_sun_visibility,
+        }
+    }
+
+    // the settings below take effect with the next init
+    pub fn set_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.inverse_projection = projection_matrix.inverse();
+    }
+
+    pub fn set_light_shafts(&mut self, light_shafts: &LightShafts) {
+        self.decay = light_shafts.decay;
+        self.density = light_shafts.density;
+    }
+
+    // see LightShafts::sun_on_screen
+    pub fn set_sun(&mut self, position: &Vector2f, visibility: f32) {
+        self.sun_position = position.clone();
+        self.sun_visibility = visibility;
+    }
+}
+
+impl Shader for LightShaftsShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_depth_texture, 0);
+        ShaderProgram::load_matrix(self.location_inverse_projection, &self.inverse_projection);
+        ShaderProgram::load_float(self.location_decay, self.decay);
+        ShaderProgram::load_float(self.location_density, self.density);
+        ShaderProgram::load_vector2d(self.location_sun_position, &self.sun_position);
+        ShaderProgram::load_float(self.location_sun_visibility, self.sun_visibility);
+        self.stop();
+    }
+}
\ No newline at end of file
@@ -3,9 +3,31 @@ pub mod horizontal_blur_shader;
 pub mod vertical_blur_shader;
 pub mod brighness_filter_shader;
 pub mod combine_shader;
+pub mod tonemap_shader;
+pub mod depth_of_field_shader;
+pub mod color_grading_shader;
+pub mod vignette_shader;
+pub mod chromatic_aberration_shader;
+pub mod film_grain_shader;
+pub mod fxaa_shader;
+pub mod ssao_shader;
+pub mod ssao_apply_shader;
+pub mod light_shafts_shader;
+pub mod atmosphere_shader;
 
 pub use self::contrast_shader::ContrastShader;
 pub use self::vertical_blur_shader::VerticalBlurShader;
 pub use self::horizontal_blur_shader::HorizontalBlurShader;
 pub use self::brighness_filter_shader::BrightnessFilterShader;
-pub use self::combine_shader::CombineShader;
\ No newline at end of file
+pub use self::combine_shader::CombineShader;
+pub use self::tonemap_shader::TonemapShader;
+pub use self::depth_of_field_shader::DepthOfFieldShader;
+pub use self::color_grading_shader::ColorGradingShader;
+pub use self::vignette_shader::VignetteShader;
+pub use self::chromatic_aberration_shader::ChromaticAberrationShader;
+pub use self::film_grain_shader::FilmGrainShader;
+pub use self::fxaa_shader::FxaaShader;
+pub use self::ssao_shader::SsaoShader;
+pub use self::ssao_apply_shader::SsaoApplyShader;
+pub use self::light_shafts_shader::LightShaftsShader;
+pub use self::atmosphere_shader::AtmosphereShader;
\ No newline at end of file
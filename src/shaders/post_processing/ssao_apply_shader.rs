@@ -0,0 +1,64 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::models::RawModel;
+
+// darkens the scene by the blurred occlusion
+pub struct SsaoApplyShader {
+    shader_program: ShaderProgram,
+    strength: f32,
+    location_strength: i32,
+    location_scene_texture: i32,
+    location_ambient_occlusion: i32,
+}
+
+
+impl SsaoApplyShader {
+    pub fn new(strength: f32) -> Self {
+        let (
+            mut location_strength,
+            mut location_scene_texture,
+            mut location_ambient_occlusion,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/ssaoApplyFrag.glsl",
+            |shader_prog| { 
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            }, 
+            |shader_prog| {
+                location_strength = shader_prog.get_uniform_location("strength");
+                location_scene_texture = shader_prog.get_uniform_location("scene_texture");
+                location_ambient_occlusion = shader_prog.get_uniform_location("ambient_occlusion");
+            });
+        SsaoApplyShader {
+            shader_program,
+            strength,
+            location_strength,
+            location_scene_texture,
+            location_ambient_occlusion,
+        }
+    }
+}
+
+impl Shader for SsaoApplyShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_scene_texture, 0);
+        ShaderProgram::load_int(self.location_ambient_occlusion, 1);
+        ShaderProgram::load_float(self.location_strength, self.strength);
+        self.stop();
+    }
+}
\ No newline at end of file
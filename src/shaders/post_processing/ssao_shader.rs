@@ -0,0 +1,92 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::math::Matrix4f;
+use crate::models::RawModel;
+use crate::post_processing::ssao::SsaoKernel;
+
+// reads the depth of the resolved camera fbo and reconstructs the view space positions from it
+pub struct SsaoShader {
+    shader_program: ShaderProgram,
+    kernel: SsaoKernel,
+    projection_matrix: Matrix4f,
+    location_depth_texture: i32,
+    location_noise_texture: i32,
+    location_projection_matrix: i32,
+    location_kernel: [i32; SsaoKernel::KERNEL_SIZE],
+    location_radius: i32,
+    location_bias: i32,
+}
+
+
+impl SsaoShader {
+    pub fn new(kernel: SsaoKernel, projection_matrix: &Matrix4f) -> Self {
+        let (
+            mut location_depth_texture,
+            mut location_noise_texture,
+            mut location_projection_matrix,
+            mut location_radius,
+            mut location_bias,
+        ) = Default::default();
+        let mut location_kernel = [0; SsaoKernel::KERNEL_SIZE];
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/ssaoFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            },
+            |shader_prog| {
+                location_depth_texture = shader_prog.get_uniform_location("depth_texture");
+                location_noise_texture = shader_prog.get_uniform_location("noise_texture");
+                location_projection_matrix = shader_prog.get_uniform_location("projection_matrix");
+                for i in 0..SsaoKernel::KERNEL_SIZE {
+                    location_kernel[i] = shader_prog.get_uniform_location(&format!("kernel[{}]", i));
+                }
+                location_radius = shader_prog.get_uniform_location("radius");
+                location_bias = shader_prog.get_uniform_location("bias");
+            });
+        SsaoShader {
+            shader_program,
+            kernel,
+            projection_matrix: projection_matrix.clone(),
+            location_depth_texture,
+            location_noise_texture,
+            location_projection_matrix,
+            location_kernel,
+            location_radius,
+            location_bias,
+        }
+    }
+
+    // takes effect with the next init
+    pub fn set_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        self.projection_matrix = projection_matrix.clone();
+    }
+}
+
+impl Shader for SsaoShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_depth_texture, 0);
+        ShaderProgram::load_int(self.location_noise_texture, 1);
+        ShaderProgram::load_matrix(self.location_projection_matrix, &self.projection_matrix);
+        for (location, sample) in self.location_kernel.iter().zip(self.kernel.samples.iter()) {
+            ShaderProgram::load_vector3d(*location, sample);
+        }
+        ShaderProgram::load_float(self.location_radius, SsaoKernel::RADIUS);
+        ShaderProgram::load_float(self.location_bias, SsaoKernel::BIAS);
+        self.stop();
+    }
+}
\ No newline at end of file
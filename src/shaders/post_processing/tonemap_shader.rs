@@ -0,0 +1,76 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::models::RawModel;
+use crate::post_processing::post_effect::TonemapOperator;
+
+pub struct TonemapShader {
+    shader_program: ShaderProgram,
+    operator: TonemapOperator,
+    exposure: f32,
+    gamma: f32,
+    location_tonemap_operator: i32,
+    location_exposure: i32,
+    location_gamma: i32,
+}
+
+
+impl TonemapShader {
+    pub fn new(operator: TonemapOperator, exposure: f32, gamma: f32) -> Self {
+        let (
+            mut location_tonemap_operator,
+            mut location_exposure,
+            mut location_gamma,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/tonemapFrag.glsl",
+            |shader_prog| { 
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            }, 
+            |shader_prog| {
+                location_tonemap_operator = shader_prog.get_uniform_location("tonemap_operator");
+                location_exposure = shader_prog.get_uniform_location("exposure");
+                location_gamma = shader_prog.get_uniform_location("gamma");
+            });
+        TonemapShader {
+            shader_program,
+            operator,
+            exposure,
+            gamma,
+            location_tonemap_operator,
+            location_exposure,
+            location_gamma,
+        }
+    }
+
+    fn load_tonemap_params(&mut self) {
+        // has to match the constants in the shader
+        let operator = match self.operator {
+            TonemapOperator::Reinhard => 0,
+            TonemapOperator::Aces => 1,
+        };
+        ShaderProgram::load_int(self.location_tonemap_operator, operator);
+        ShaderProgram::load_float(self.location_exposure, self.exposure);
+        ShaderProgram::load_float(self.location_gamma, self.gamma);
+    }
+}
+
+impl Shader for TonemapShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        self.load_tonemap_params();
+        self.stop();
+    }
+}
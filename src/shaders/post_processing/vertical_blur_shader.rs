@@ -32,6 +32,11 @@ impl VerticalBlurShader {
         }
     }
 
+    // takes effect with the next init
+    pub fn set_viewport_height(&mut self, viewport_height: usize) {
+        self.viewport_height = viewport_height;
+    }
+
     pub fn load_viewport_dimension(&mut self, viewport_height: usize) {
         ShaderProgram::load_float(self.location_size, viewport_height as f32);
     }
@@ -0,0 +1,72 @@
+use super::super::{
+    shader::Shader,
+    shader_program::ShaderProgram,
+};
+
+use crate::models::RawModel;
+
+pub struct VignetteShader {
+    shader_program: ShaderProgram,
+    radius: f32,
+    softness: f32,
+    strength: f32,
+    location_radius: i32,
+    location_softness: i32,
+    location_strength: i32,
+    location_color_texture: i32,
+}
+
+
+impl VignetteShader {
+    pub fn new(radius: f32, softness: f32, strength: f32) -> Self {
+        let (
+            mut location_radius,
+            mut location_softness,
+            mut location_strength,
+            mut location_color_texture,
+        ) = Default::default();
+        let shader_program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/post_processing/vignetteFrag.glsl",
+            |shader_prog| { 
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            }, 
+            |shader_prog| {
+                location_radius = shader_prog.get_uniform_location("radius");
+                location_softness = shader_prog.get_uniform_location("softness");
+                location_strength = shader_prog.get_uniform_location("strength");
+                location_color_texture = shader_prog.get_uniform_location("color_texture");
+            });
+        VignetteShader {
+            shader_program,
+            radius,
+            softness,
+            strength,
+            location_radius,
+            location_softness,
+            location_strength,
+            location_color_texture,
+        }
+    }
+}
+
+impl Shader for VignetteShader {
+    fn start(&mut self) {
+        self.shader_program.start();
+    }
+
+    fn stop(&mut self) {
+        self.shader_program.stop();
+    }
+
+    fn init(&mut self) {
+        self.start();
+        // connect sampler uniforms to texture units
+        ShaderProgram::load_int(self.location_color_texture, 0);
+        ShaderProgram::load_float(self.location_radius, self.radius);
+        ShaderProgram::load_float(self.location_softness, self.softness);
+        ShaderProgram::load_float(self.location_strength, self.strength);
+        self.stop();
+    }
+}
\ No newline at end of file
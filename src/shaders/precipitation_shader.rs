@@ -0,0 +1,120 @@
+use super::shader_program::ShaderProgram;
+use crate::entities::Camera;
+use crate::math::{
+    Matrix4f,
+    Vector2f,
+    Vector3f,
+    Vector4f,
+};
+use crate::models::RawModel;
+
+// rain streaks and snow flakes, the vertex shader places every instance of the quad from its instance id alone
+pub struct PrecipitationShader {
+    program: ShaderProgram,
+    location_projection_matrix: i32,
+    location_view_matrix: i32,
+    location_camera_position: i32,
+    location_time: i32,
+    location_period: i32,
+    location_fall_speed: i32,
+    location_wind: i32,
+    location_wind_drift: i32,
+    location_drop_size: i32,
+    location_area: i32,
+    location_flutter: i32,
+    location_color: i32,
+}
+
+impl PrecipitationShader {
+    pub fn new() -> PrecipitationShader {
+        let (
+            mut location_projection_matrix,
+            mut location_view_matrix,
+            mut location_camera_position,
+            mut location_time,
+            mut location_period,
+            mut location_fall_speed,
+            mut location_wind,
+            mut location_wind_drift,
+            mut location_drop_size,
+            mut location_area,
+            mut location_flutter,
+            mut location_color,
+        ) = Default::default();
+
+        let program = ShaderProgram::new(
+            "res/shaders/particles/precipitationVert.glsl",
+            None,
+            "res/shaders/particles/precipitationFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            },
+            |shader_prog| {
+                location_projection_matrix = shader_prog.get_uniform_location("projection_matrix");
+                location_view_matrix = shader_prog.get_uniform_location("view_matrix");
+                location_camera_position = shader_prog.get_uniform_location("camera_position");
+                location_time = shader_prog.get_uniform_location("time");
+                location_period = shader_prog.get_uniform_location("period");
+                location_fall_speed = shader_prog.get_uniform_location("fall_speed");
+                location_wind = shader_prog.get_uniform_location("wind");
+                location_wind_drift = shader_prog.get_uniform_location("wind_drift");
+                location_drop_size = shader_prog.get_uniform_location("drop_size");
+                location_area = shader_prog.get_uniform_location("area");
+                location_flutter = shader_prog.get_uniform_location("flutter");
+                location_color = shader_prog.get_uniform_location("color");
+            }
+        );
+
+        PrecipitationShader {
+            program,
+            location_projection_matrix,
+            location_view_matrix,
+            location_camera_position,
+            location_time,
+            location_period,
+            location_fall_speed,
+            location_wind,
+            location_wind_drift,
+            location_drop_size,
+            location_area,
+            location_flutter,
+            location_color,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.program.stop();
+    }
+
+    pub fn load_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_projection_matrix, projection_matrix);
+    }
+
+    pub fn load_camera(&mut self, camera: &Camera) {
+        let view_matrix = Matrix4f::create_view_matrix(camera);
+        ShaderProgram::load_matrix(self.location_view_matrix, &view_matrix);
+        ShaderProgram::load_vector3d(self.location_camera_position, &camera.position);
+    }
+
+    // the drops move through the box around the camera with the wind and wrap around at its sides
+    // time has to be wrapped to the period already, the wind drift to the area
+    pub fn load_motion(&mut self, time: f32, period: f32, fall_speed: f32, wind: &Vector3f, wind_drift: &Vector3f, area: &Vector3f) {
+        ShaderProgram::load_float(self.location_time, time);
+        ShaderProgram::load_float(self.location_period, period);
+        ShaderProgram::load_float(self.location_fall_speed, fall_speed);
+        ShaderProgram::load_vector3d(self.location_wind, wind);
+        ShaderProgram::load_vector3d(self.location_wind_drift, wind_drift);
+        ShaderProgram::load_vector3d(self.location_area, area);
+    }
+
+    // width and length of a drop, flutter is how far it swings from side to side while falling
+    pub fn load_drop(&mut self, drop_size: &Vector2f, flutter: f32, color: &Vector4f) {
+        ShaderProgram::load_vector2d(self.location_drop_size, drop_size);
+        ShaderProgram::load_float(self.location_flutter, flutter);
+        ShaderProgram::load_vector4d(self.location_color, color);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,151 @@
+use super::shader_program::ShaderProgram;
+use crate::display::WallClock;
+use crate::models::{
+    RawModel,
+};
+use crate::entities::{
+    Camera,
+    ProceduralSky,
+};
+use crate::math::{
+    Matrix4f,
+    Vector4f,
+};
+
+// draws the preetham sky with the sun and at night the moon and the stars on the skybox cube
+pub struct ProceduralSkyShader {
+    program: ShaderProgram,
+    location_proj_matrix: i32,
+    location_view_matrix: i32,
+    location_clip_plane: i32,
+    location_perez_a: i32,
+    location_perez_b: i32,
+    location_perez_c: i32,
+    location_perez_d: i32,
+    location_perez_e: i32,
+    location_zenith: i32,
+    location_sky_exposure: i32,
+    location_to_sun: i32,
+    location_sun_color: i32,
+    location_daylight: i32,
+    location_night_color: i32,
+    location_moon_color: i32,
+    location_celestial_pole: i32,
+    location_hour_angle: i32,
+}
+
+impl ProceduralSkyShader {
+    pub fn new() -> ProceduralSkyShader {
+        let (
+            mut location_proj_matrix,
+            mut location_view_matrix,
+            mut location_clip_plane,
+            mut location_perez_a,
+            mut location_perez_b,
+            mut location_perez_c,
+            mut location_perez_d,
+            mut location_perez_e,
+            mut location_zenith,
+        ) = Default::default();
+        let (
+            mut location_sky_exposure,
+            mut location_to_sun,
+            mut location_sun_color,
+            mut location_daylight,
+            mut location_night_color,
+            mut location_moon_color,
+            mut location_celestial_pole,
+            mut location_hour_angle,
+        ) = Default::default();
+
+        let program = ShaderProgram::new(
+            "res/shaders/skyboxVertexShader.glsl",
+            None,
+            "res/shaders/proceduralSkyFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            },
+            |shader_prog| {
+                location_proj_matrix = shader_prog.get_uniform_location("projection_matrix");
+                location_view_matrix = shader_prog.get_uniform_location("view_matrix");
+                location_clip_plane = shader_prog.get_uniform_location("clip_plane");
+                location_perez_a = shader_prog.get_uniform_location("perez_a");
+                location_perez_b = shader_prog.get_uniform_location("perez_b");
+                location_perez_c = shader_prog.get_uniform_location("perez_c");
+                location_perez_d = shader_prog.get_uniform_location("perez_d");
+                location_perez_e = shader_prog.get_uniform_location("perez_e");
+                location_zenith = shader_prog.get_uniform_location("zenith");
+                location_sky_exposure = shader_prog.get_uniform_location("sky_exposure");
+                location_to_sun = shader_prog.get_uniform_location("to_sun");
+                location_sun_color = shader_prog.get_uniform_location("sun_color");
+                location_daylight = shader_prog.get_uniform_location("daylight");
+                location_night_color = shader_prog.get_uniform_location("night_color");
+                location_moon_color = shader_prog.get_uniform_location("moon_color");
+                location_celestial_pole = shader_prog.get_uniform_location("celestial_pole");
+                location_hour_angle = shader_prog.get_uniform_location("hour_angle");
+            }
+        );
+
+        ProceduralSkyShader {
+            program,
+            location_proj_matrix,
+            location_view_matrix,
+            location_clip_plane,
+            location_perez_a,
+            location_perez_b,
+            location_perez_c,
+            location_perez_d,
+            location_perez_e,
+            location_zenith,
+            location_sky_exposure,
+            location_to_sun,
+            location_sun_color,
+            location_daylight,
+            location_night_color,
+            location_moon_color,
+            location_celestial_pole,
+            location_hour_angle,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.program.stop();
+    }
+
+    pub fn load_projection_matrix(&mut self, projection_matrix: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_proj_matrix, projection_matrix);
+    }
+
+    // the procedural sky doesn't turn with the skybox rotation, its sun and stars move with the clock instead
+    pub fn load_view_matrix(&mut self, camera: &Camera) {
+        let view_matrix = Matrix4f::create_skybox_view_matrix(camera, 0.0);
+        ShaderProgram::load_matrix(self.location_view_matrix, &view_matrix);
+    }
+
+    pub fn load_clip_plane(&mut self, clip_plane: &Vector4f) {
+        ShaderProgram::load_vector4d(self.location_clip_plane, clip_plane);
+    }
+
+    pub fn load_sky(&mut self, sky: &ProceduralSky, wall_clock: &WallClock) {
+        let to_sun = sky.to_sun(wall_clock);
+        let coefficients = sky.coefficients(&to_sun);
+        ShaderProgram::load_vector3d(self.location_perez_a, &coefficients.a);
+        ShaderProgram::load_vector3d(self.location_perez_b, &coefficients.b);
+        ShaderProgram::load_vector3d(self.location_perez_c, &coefficients.c);
+        ShaderProgram::load_vector3d(self.location_perez_d, &coefficients.d);
+        ShaderProgram::load_vector3d(self.location_perez_e, &coefficients.e);
+        ShaderProgram::load_vector3d(self.location_zenith, &coefficients.zenith);
+        ShaderProgram::load_float(self.location_sky_exposure, ProceduralSky::SKY_EXPOSURE);
+        ShaderProgram::load_vector3d(self.location_to_sun, &to_sun);
+        ShaderProgram::load_vector3d(self.location_sun_color, &sky.sun_light_color(&to_sun));
+        ShaderProgram::load_float(self.location_daylight, sky.daylight(wall_clock));
+        ShaderProgram::load_vector3d(self.location_night_color, &ProceduralSky::NIGHT_COLOR);
+        ShaderProgram::load_vector3d(self.location_moon_color, &ProceduralSky::MOON_COLOR);
+        ShaderProgram::load_vector3d(self.location_celestial_pole, &sky.celestial_pole());
+        ShaderProgram::load_float(self.location_hour_angle, ProceduralSky::hour_angle(wall_clock));
+    }
+}
\ No newline at end of file
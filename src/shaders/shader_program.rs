@@ -5,7 +5,6 @@ use std::io::{
     prelude::*,
     BufReader,
     Error,
-    ErrorKind,
 }; 
 use super::super::gl;
 use super::super::math::{
@@ -23,10 +22,12 @@ pub struct ShaderProgram {
 }
 
 impl ShaderProgram {
+    // shared glsl like the light clusters and the shadow sampling, shaders pull it in with a line like #include "lightData.glsl"
+    const INCLUDE_DIR: &'static str = "res/shaders/include";
 
     pub fn new<F1, F2>(vertex_file: &str, geometry_file: Option<&str>, fragment_file: &str, attrib_binder_fn: F1, uniform_loader: F2) -> ShaderProgram 
-        where F1: FnOnce(&ShaderProgram) -> (), 
-              F2: FnOnce(&ShaderProgram) -> () {
+        where F1: FnOnce(&ShaderProgram), 
+              F2: FnOnce(&ShaderProgram) {
         let vertex_shader_id = ShaderProgram::load_shader(vertex_file, gl::VERTEX_SHADER)
             .expect("Failed to create vertex shader");
         let geometry_shader_id = geometry_file.map(|geo_file| { ShaderProgram::load_shader(geo_file, gl::GEOMETRY_SHADER).expect("Failed to create geometry shader") });
@@ -72,23 +73,66 @@ impl ShaderProgram {
     }
 
     pub fn load_shader(filename: &str, type_: u32) -> std::io::Result<u32> {
-        let shader_file = File::open(filename)?;
-        let mut buf_reader = BufReader::new(shader_file);
-        let mut contents = String::new();
-        buf_reader.read_to_string(&mut contents)?;
+        let source = ShaderProgram::read_source(filename)?;
+        let mut includes = Vec::new();
+        let contents = ShaderProgram::resolve_includes(&source, 0, &mut includes, &|name| {
+            ShaderProgram::read_source(&format!("{}/{}", ShaderProgram::INCLUDE_DIR, name))
+        })?;
         let shader_id = gl::create_shader(type_);
         gl::shader_source(shader_id, &contents)?;
         gl::compile_shader(shader_id);
         if gl::get_shader(shader_id, gl::COMPILE_STATUS) == gl::FALSE as i32 {
             let compile_log = gl::get_shader_info_log(shader_id)?;
-            println!("Could not compile shader. Log: {}", compile_log);
-            Err(Error::new(ErrorKind::Other, "Failed to compile shader"))
+            // the log says file(line) with the number of the source, 0 is the shader itself
+            let sources: Vec<String> = includes.iter().enumerate().map(|(idx, name)| format!("{} {}", idx + 1, name)).collect();
+            println!("Could not compile shader {} (includes: {}). Log: {}", filename, sources.join(", "), compile_log);
+            Err(Error::other("Failed to compile shader"))
         }
         else {
             Ok(shader_id)
         }
     }
 
+    fn read_source(filename: &str) -> std::io::Result<String> {
+        let shader_file = File::open(filename)?;
+        let mut buf_reader = BufReader::new(shader_file);
+        let mut contents = String::new();
+        buf_reader.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    // replaces every #include line with the file it names, a file gets included only once even if several includes ask for it
+    // the #line directives keep the line numbers of compile errors right, includes[i] is source number i + 1
+    fn resolve_includes<F>(source: &str, source_number: usize, includes: &mut Vec<String>, read_include: &F) -> std::io::Result<String>
+        where F: Fn(&str) -> std::io::Result<String> {
+        let mut resolved = String::with_capacity(source.len());
+        for (line_idx, line) in source.lines().enumerate() {
+            let name = match ShaderProgram::include_name(line) {
+                Some(name) => name,
+                None => {
+                    resolved.push_str(line);
+                    resolved.push('\n');
+                    continue;
+                },
+            };
+            if !includes.iter().any(|included| included == name) {
+                includes.push(name.to_string());
+                let include_number = includes.len();
+                let include_source = read_include(name).map_err(|err| Error::new(err.kind(), format!("Failed to include {}: {}", name, err)))?;
+                resolved.push_str(&format!("#line 1 {}\n", include_number));
+                resolved.push_str(&ShaderProgram::resolve_includes(&include_source, include_number, includes, read_include)?);
+            }
+            // continue with the line after the include
+            resolved.push_str(&format!("#line {} {}\n", line_idx + 2, source_number));
+        }
+        Ok(resolved)
+    }
+
+    fn include_name(line: &str) -> Option<&str> {
+        let name = line.trim().strip_prefix("#include")?.trim();
+        name.strip_prefix('"')?.strip_suffix('"')
+    }
+
     pub fn bind_attribute(&self, attribute: u32, variable_name: &str) {
         gl::bind_attrib_location(self.program_id, attribute, variable_name).expect("Variable name invalid");
     }
@@ -144,3 +188,52 @@ impl Drop for ShaderProgram {
         gl::delete_program(self.program_id);
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    fn read_test_include(name: &str) -> std::io::Result<String> {
+        match name {
+            "a.glsl" => Ok("float a() { return 1.0; }".to_string()),
+            "b.glsl" => Ok("#include \"a.glsl\"\nfloat b() { return a(); }\n".to_string()),
+            _ => Err(Error::new(ErrorKind::NotFound, "No such include")),
+        }
+    }
+
+    #[test]
+    fn test_includes_are_inlined_once() {
+        let source = "#version 400 core\r\n#include \"b.glsl\"\r\n  #include \"a.glsl\"\r\nvoid main() {}\r\n";
+        let mut includes = Vec::new();
+        let resolved = ShaderProgram::resolve_includes(source, 0, &mut includes, &read_test_include).expect("The includes exist");
+        assert_eq!(includes, vec!["b.glsl".to_string(), "a.glsl".to_string()]);
+        let expected = [
+            "#version 400 core",
+            "#line 1 1",
+            "#line 1 2",
+            "float a() { return 1.0; }",
+            // back in b.glsl after its include
+            "#line 2 1",
+            "float b() { return a(); }",
+            "#line 3 0",
+            // a.glsl is already there
+            "#line 4 0",
+            "void main() {}",
+        ];
+        assert_eq!(resolved.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_missing_include_fails() {
+        let mut includes = Vec::new();
+        let result = ShaderProgram::resolve_includes("#include \"missing.glsl\"\n", 0, &mut includes, &read_test_include);
+        let err = result.expect_err("The include doesn't exist");
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("missing.glsl"));
+        // not an include and left for the glsl compiler to complain about
+        assert_eq!(ShaderProgram::include_name("#include missing.glsl"), None);
+        assert_eq!(ShaderProgram::include_name("#include \"missing.glsl\""), Some("missing.glsl"));
+    }
+}
\ No newline at end of file
@@ -1,35 +1,73 @@
 use crate::shaders::shader_program::ShaderProgram;
+use super::joint_transform_uniforms::JointTransformUniforms;
+use super::weather_uniforms::WeatherUniforms;
+use crate::animations::joint::AccumulatedJointTransforms;
+use crate::entities::Weather;
 use crate::math::{
     Matrix4f,
 };
-use crate::models::RawModel;
+use crate::models::{
+    RawModel,
+    TexturedModel,
+};
 
 pub struct ShadowShader {
     shader_program: ShaderProgram,
+    // the static shader takes the model transforms per instance, the animated one draws one model at a time
+    location_vp_matrix: i32,
     location_mvp_matrix: i32,
+    // only the animated shadow shader has them
+    location_joint_transforms: JointTransformUniforms,
+    // only the static one sways the plants like the entity shader does
+    location_sways_in_wind: i32,
+    location_weather: WeatherUniforms,
 }
 
 impl ShadowShader {
     pub fn new() -> Self {
+        ShadowShader::create("res/shaders/shadows/shadowVertexShader.glsl")
+    }
+
+    // skins the vertices with the joint transforms before projecting them like the animated model shader does
+    pub fn new_animated() -> Self {
+        ShadowShader::create("res/shaders/shadows/animShadowVertexShader.glsl")
+    }
+
+    fn create(vertex_file: &str) -> Self {
         let (
+            mut location_vp_matrix,
             mut location_mvp_matrix,
+            mut location_joint_transforms,
+            mut location_sways_in_wind,
+            mut location_weather,
         ) = Default::default();
 
         let shader_program = ShaderProgram::new(
-            "res/shaders/shadows/shadowVertexShader.glsl",
+            vertex_file,
             None,
             "res/shaders/shadows/shadowFragmentShader.glsl",
             |shader_prog| {
                 shader_prog.bind_attribute(RawModel::POS_ATTRIB, "pos");
                 shader_prog.bind_attribute(RawModel::TEX_COORD_ATTRIB, "in_texture_coords");
+                shader_prog.bind_attribute(RawModel::JOINT_IDX_ATTRIB, "in_joint_indicies");
+                shader_prog.bind_attribute(RawModel::JOINT_WEIGHT_ATTRIB, "in_joint_weights");
+                shader_prog.bind_attribute(TexturedModel::TRANSFORM_COLUMN1, "transform");
             },
             |shader_prog| {
+                location_vp_matrix = shader_prog.get_uniform_location("vp_matrix");
                 location_mvp_matrix = shader_prog.get_uniform_location("mvp_matrix");
+                location_joint_transforms = JointTransformUniforms::new(shader_prog);
+                location_sways_in_wind = shader_prog.get_uniform_location("sways_in_wind");
+                location_weather = WeatherUniforms::new(shader_prog);
             }
         );
         ShadowShader {
             shader_program,
+            location_vp_matrix,
             location_mvp_matrix,
+            location_joint_transforms,
+            location_sways_in_wind,
+            location_weather,
         }
     }
 
@@ -41,7 +79,23 @@ impl ShadowShader {
         self.shader_program.stop();
     }
 
+    pub fn load_vp_matrix(&mut self, vp: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_vp_matrix, vp);
+    }
+
     pub fn load_mvp_matrix(&mut self, mvp: &Matrix4f) {
         ShaderProgram::load_matrix(self.location_mvp_matrix, mvp);
     }
-}
\ No newline at end of file
+
+    pub fn load_joint_transforms(&mut self, joint_transforms: &AccumulatedJointTransforms) {
+        self.location_joint_transforms.load(joint_transforms);
+    }
+
+    pub fn load_sways_in_wind(&mut self, sways_in_wind: bool) {
+        ShaderProgram::load_bool(self.location_sways_in_wind, sways_in_wind);
+    }
+
+    pub fn load_weather(&mut self, weather: &Weather, time: f32) {
+        self.location_weather.load(weather, time);
+    }
+}
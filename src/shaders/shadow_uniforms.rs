@@ -0,0 +1,46 @@
+use super::shader_program::ShaderProgram;
+use crate::shadows::{
+    shadow_box::ShadowBox,
+    shadow_params::ShadowParams,
+};
+
+// uniforms of the cascaded shadow map lookup, shared by all the shaders that receive the sun shadows
+#[derive(Default)]
+pub struct ShadowUniforms {
+    location_shadow_map: i32,
+    location_shadow_map_size: i32,
+    location_cascade_count: i32,
+    location_cascade_splits: [i32; ShadowBox::MAX_CASCADES],
+    location_to_shadowmap_space: [i32; ShadowBox::MAX_CASCADES],
+}
+
+impl ShadowUniforms {
+    pub fn new(shader_prog: &ShaderProgram) -> ShadowUniforms {
+        let mut location_cascade_splits = [0; ShadowBox::MAX_CASCADES];
+        let mut location_to_shadowmap_space = [0; ShadowBox::MAX_CASCADES];
+        for i in 0..ShadowBox::MAX_CASCADES {
+            location_cascade_splits[i] = shader_prog.get_uniform_location(&format!("cascade_splits[{}]", i));
+            location_to_shadowmap_space[i] = shader_prog.get_uniform_location(&format!("to_shadowmap_space[{}]", i));
+        }
+        ShadowUniforms {
+            location_shadow_map: shader_prog.get_uniform_location("shadow_map"),
+            location_shadow_map_size: shader_prog.get_uniform_location("shadow_map_size"),
+            location_cascade_count: shader_prog.get_uniform_location("cascade_count"),
+            location_cascade_splits,
+            location_to_shadowmap_space,
+        }
+    }
+
+    pub fn connect_texture_units(&self, shadow_map_unit: i32) {
+        ShaderProgram::load_int(self.location_shadow_map, shadow_map_unit);
+    }
+
+    pub fn load(&self, shadow_params: &ShadowParams) {
+        ShaderProgram::load_float(self.location_shadow_map_size, shadow_params.shadow_map_size as f32);
+        ShaderProgram::load_int(self.location_cascade_count, shadow_params.cascade_count as i32);
+        for i in 0..shadow_params.cascade_count {
+            ShaderProgram::load_float(self.location_cascade_splits[i], shadow_params.cascade_splits[i]);
+            ShaderProgram::load_matrix(self.location_to_shadowmap_space[i], &shadow_params.to_shadowmap_space[i]);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use super::shader_program::ShaderProgram;
+use crate::models::RawModel;
+
+// blends the reflections of the glossy entities over the lit scene
+pub struct SsrCompositeShader {
+    program: ShaderProgram,
+    location_ssr_unit: i32,
+}
+
+impl SsrCompositeShader {
+    pub fn new() -> Self {
+        let mut location_ssr_unit = 0;
+        let program = ShaderProgram::new(
+            "res/shaders/post_processing/defaultVert.glsl",
+            None,
+            "res/shaders/ssr/ssrCompositeFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+            },
+            |shader_prog| {
+                location_ssr_unit = shader_prog.get_uniform_location("ssr_tex");
+            },
+        );
+        SsrCompositeShader {
+            program,
+            location_ssr_unit,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.program.stop();
+    }
+
+    pub fn connect_texture_units(&mut self) {
+        ShaderProgram::load_int(self.location_ssr_unit, 0);
+    }
+}
\ No newline at end of file
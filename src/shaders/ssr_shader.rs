@@ -0,0 +1,164 @@
+use super::shader_program::ShaderProgram;
+use super::wave_uniforms::WaveUniforms;
+use crate::entities::WaterWaves;
+use crate::models::RawModel;
+use crate::math::{
+    Matrix4f,
+    Vector2f,
+    Vector3f,
+};
+
+// ray marches the reflection of a surface through the depth of the main camera
+// the water draws its tiles with the water vertex shader so the waves move the same way, the entities with a plain one
+pub struct ScreenSpaceReflectionShader {
+    program: ShaderProgram,
+    location_proj_mat: i32,
+    location_view_mat: i32,
+    location_transform_mat: i32,
+    location_camera: i32,
+    location_depth_a: i32,
+    location_depth_b: i32,
+    location_scene_color_unit: i32,
+    location_scene_depth_unit: i32,
+    location_sky_day_unit: i32,
+    location_sky_night_unit: i32,
+    location_sky_blend_factor: i32,
+    location_sky_rotation: i32,
+    location_reflection_weight: i32,
+    location_sky_fallback: i32,
+    // only used by the water vertex shader
+    location_tiling: i32,
+    location_waves: WaveUniforms,
+}
+
+impl ScreenSpaceReflectionShader {
+    pub fn new_for_water() -> Self {
+        ScreenSpaceReflectionShader::new("res/shaders/waterVertexShader.glsl")
+    }
+
+    pub fn new_for_entities() -> Self {
+        ScreenSpaceReflectionShader::new("res/shaders/ssr/ssrEntityVert.glsl")
+    }
+
+    fn new(vertex_shader: &str) -> Self {
+        let (
+            mut location_proj_mat,
+            mut location_view_mat,
+            mut location_transform_mat,
+            mut location_camera,
+            mut location_depth_a,
+            mut location_depth_b,
+            mut location_scene_color_unit,
+            mut location_scene_depth_unit,
+        ) = Default::default();
+        let (
+            mut location_sky_day_unit,
+            mut location_sky_night_unit,
+            mut location_sky_blend_factor,
+            mut location_sky_rotation,
+            mut location_reflection_weight,
+            mut location_sky_fallback,
+            mut location_tiling,
+            mut location_waves,
+        ) = Default::default();
+
+        let program = ShaderProgram::new(
+            vertex_shader,
+            None,
+            "res/shaders/ssr/ssrFrag.glsl",
+            |shader_prog| {
+                shader_prog.bind_attribute(RawModel::POS_ATTRIB, "position");
+                shader_prog.bind_attribute(RawModel::TEX_COORD_ATTRIB, "tex_coord");
+                shader_prog.bind_attribute(RawModel::NORMAL_ATTRIB, "normal");
+            },
+            |shader_prog| {
+                location_proj_mat = shader_prog.get_uniform_location("projection_matrix");
+                location_view_mat = shader_prog.get_uniform_location("view_matrix");
+                location_transform_mat = shader_prog.get_uniform_location("transform_matrix");
+                location_camera = shader_prog.get_uniform_location("camera_world_pos");
+                location_depth_a = shader_prog.get_uniform_location("depth_calc_A");
+                location_depth_b = shader_prog.get_uniform_location("depth_calc_B");
+                location_scene_color_unit = shader_prog.get_uniform_location("scene_color");
+                location_scene_depth_unit = shader_prog.get_uniform_location("scene_depth");
+                location_sky_day_unit = shader_prog.get_uniform_location("sky_day_map");
+                location_sky_night_unit = shader_prog.get_uniform_location("sky_night_map");
+                location_sky_blend_factor = shader_prog.get_uniform_location("sky_blend_factor");
+                location_sky_rotation = shader_prog.get_uniform_location("sky_rotation");
+                location_reflection_weight = shader_prog.get_uniform_location("reflection_weight");
+                location_sky_fallback = shader_prog.get_uniform_location("sky_fallback");
+                location_tiling = shader_prog.get_uniform_location("tiling");
+                location_waves = WaveUniforms::new(shader_prog);
+            },
+        );
+        ScreenSpaceReflectionShader {
+            program,
+            location_proj_mat,
+            location_view_mat,
+            location_transform_mat,
+            location_camera,
+            location_depth_a,
+            location_depth_b,
+            location_scene_color_unit,
+            location_scene_depth_unit,
+            location_sky_day_unit,
+            location_sky_night_unit,
+            location_sky_blend_factor,
+            location_sky_rotation,
+            location_reflection_weight,
+            location_sky_fallback,
+            location_tiling,
+            location_waves,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.program.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.program.stop();
+    }
+
+    pub fn load_projection_matrix(&mut self, proj_mat: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_proj_mat, proj_mat);
+        ShaderProgram::load_float(self.location_depth_a, proj_mat[2][2]);
+        ShaderProgram::load_float(self.location_depth_b, proj_mat[2][3]);
+    }
+
+    pub fn load_view_matrix(&mut self, view_mat: &Matrix4f, camera_pos: &Vector3f) {
+        ShaderProgram::load_matrix(self.location_view_mat, view_mat);
+        ShaderProgram::load_vector3d(self.location_camera, camera_pos);
+    }
+
+    pub fn load_transform_matrix(&mut self, transform_mat: &Matrix4f) {
+        ShaderProgram::load_matrix(self.location_transform_mat, transform_mat);
+    }
+
+    pub fn load_sky(&mut self, sky_rotation: &Matrix4f, blend_factor: f32) {
+        ShaderProgram::load_matrix(self.location_sky_rotation, sky_rotation);
+        ShaderProgram::load_float(self.location_sky_blend_factor, blend_factor);
+    }
+
+    pub fn load_reflection_weight(&mut self, weight: f32) {
+        ShaderProgram::load_float(self.location_reflection_weight, weight);
+    }
+
+    pub fn load_sky_fallback(&mut self, sky_fallback: bool) {
+        ShaderProgram::load_bool(self.location_sky_fallback, sky_fallback);
+    }
+
+    pub fn load_tiling(&mut self, tiling: &Vector2f) {
+        ShaderProgram::load_vector2d(self.location_tiling, tiling);
+    }
+
+    pub fn load_waves(&mut self, waves: &WaterWaves, time: f32) {
+        self.location_waves.load(waves, time);
+    }
+
+    pub fn connect_texture_units(&mut self) {
+        ShaderProgram::load_int(self.location_scene_color_unit, 0);
+        ShaderProgram::load_int(self.location_scene_depth_unit, 1);
+        ShaderProgram::load_int(self.location_sky_day_unit, 2);
+        ShaderProgram::load_int(self.location_sky_night_unit, 3);
+    }
+}
\ No newline at end of file
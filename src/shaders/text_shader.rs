@@ -12,6 +12,7 @@ use crate::models::{
 pub struct TextShader {
     shader_program: ShaderProgram,
     location_position: i32,
+    location_scale: i32,
     location_color: i32,
     location_width: i32,
     location_edge: i32,
@@ -25,6 +26,7 @@ impl TextShader {
     pub fn new() -> TextShader {
         let (
             mut location_position,
+            mut location_scale,
             mut location_color,
             mut location_width,
             mut location_edge,
@@ -44,6 +46,7 @@ impl TextShader {
             },
             |shader_prog| {
                 location_position = shader_prog.get_uniform_location("transform");
+                location_scale = shader_prog.get_uniform_location("scale");
                 location_color = shader_prog.get_uniform_location("color");
                 location_width = shader_prog.get_uniform_location("width");
                 location_edge = shader_prog.get_uniform_location("edge");
@@ -57,6 +60,7 @@ impl TextShader {
         TextShader {
             shader_program: program,
             location_position,
+            location_scale,
             location_color,
             location_width,
             location_edge,
@@ -79,6 +83,10 @@ impl TextShader {
         ShaderProgram::load_vector2d(self.location_position, position);
     }
 
+    pub fn load_scale(&mut self, scale: &Vector2f) {
+        ShaderProgram::load_vector2d(self.location_scale, scale);
+    }
+
     pub fn load_text_material(&mut self, material: &TextMaterial) {
         ShaderProgram::load_vector3d(self.location_color, &material.color);
         ShaderProgram::load_float(self.location_width, material.width);
@@ -1,17 +1,22 @@
 use crate::entities::{
     Camera,
-    Light,
+    WaterMaterial,
+    WaterReflection,
+    WaterWaves,
+    Weather,
 };
 use crate::models::{
     RawModel,
 };
 use crate::math::{
     Matrix4f,
-    Vector3f,
+    Vector2f,
 };
+use crate::renderers::light_clusters::LightClusters;
 use crate::shaders::shader_program::ShaderProgram;
-
-const LIGHT_NUM: usize = 4;
+use crate::shaders::light_cluster_uniforms::LightClusterUniforms;
+use crate::shaders::wave_uniforms::WaveUniforms;
+use crate::shaders::weather_uniforms::WeatherUniforms;
 
 pub struct WaterShader {
     program: ShaderProgram,
@@ -24,13 +29,20 @@ pub struct WaterShader {
     location_wave_factor: i32,
     location_camera: i32,
     location_normal_map_unit: i32,    
-    location_light_color: [i32; LIGHT_NUM],
-    location_light_pos: [i32; LIGHT_NUM],
-    location_attenuation: [i32; LIGHT_NUM],
+    location_light_clusters: LightClusterUniforms,
     location_depth_map_unit: i32,
     location_depth_a: i32,
     location_depth_b: i32,
-    location_sky_color: i32,
+    location_tiling: i32,
+    location_wave_strength: i32,
+    location_reflectivity: i32,
+    location_tint: i32,
+    location_tint_strength: i32,
+    location_murkiness: i32,
+    location_waves: WaveUniforms,
+    location_reflection_mode: i32,
+    location_ssr_unit: i32,
+    location_weather: WeatherUniforms,
 }
 
 impl WaterShader {
@@ -48,14 +60,25 @@ impl WaterShader {
             mut location_depth_map_unit,
         ) = Default::default();
         let (
-            mut location_light_color,
-            mut location_light_pos,
-            mut location_attenuation,
+            mut location_light_clusters,
             mut location_depth_a,
             mut location_depth_b,
-            mut location_sky_color,
+        ) = Default::default();
+        let (
+            mut location_tiling,
+            mut location_wave_strength,
+            mut location_reflectivity,
+            mut location_tint,
+            mut location_tint_strength,
+            mut location_murkiness,
+        ) = Default::default();
+        let (
+            mut location_waves,
+            mut location_reflection_mode,
+            mut location_ssr_unit,
         ) = Default::default();
 
+        let mut location_weather = WeatherUniforms::default();
         let program = ShaderProgram::new(
             "res/shaders/waterVertexShader.glsl",
             None,
@@ -75,18 +98,22 @@ impl WaterShader {
                 location_normal_map_unit = shader_prog.get_uniform_location("normal_map");
                 location_depth_map_unit = shader_prog.get_uniform_location("depth_map");
 
-                location_light_color = [0i32; LIGHT_NUM];
-                location_light_pos = [0i32; LIGHT_NUM];
-                location_attenuation = [0i32; LIGHT_NUM];
-                for i in 0..LIGHT_NUM {
-                    location_light_color[i] = shader_prog.get_uniform_location(&format!("light_color[{}]", i));
-                    location_light_pos[i] = shader_prog.get_uniform_location(&format!("light_pos[{}]", i));
-                    location_attenuation[i] = shader_prog.get_uniform_location(&format!("attenuation[{}]", i));                
-                }
+                location_light_clusters = LightClusterUniforms::new(shader_prog);
 
                 location_depth_a = shader_prog.get_uniform_location("depth_calc_A");
                 location_depth_b = shader_prog.get_uniform_location("depth_calc_B");
-                location_sky_color = shader_prog.get_uniform_location("sky_color");
+
+                location_tiling = shader_prog.get_uniform_location("tiling");
+                location_wave_strength = shader_prog.get_uniform_location("wave_strength");
+                location_reflectivity = shader_prog.get_uniform_location("water_reflectivity");
+                location_tint = shader_prog.get_uniform_location("tint");
+                location_tint_strength = shader_prog.get_uniform_location("tint_strength");
+                location_murkiness = shader_prog.get_uniform_location("murkiness");
+
+                location_waves = WaveUniforms::new(shader_prog);
+                location_weather = WeatherUniforms::new(shader_prog);
+                location_reflection_mode = shader_prog.get_uniform_location("reflection_mode");
+                location_ssr_unit = shader_prog.get_uniform_location("ssr_tex");
             },
         );
         WaterShader {
@@ -100,13 +127,20 @@ impl WaterShader {
             location_wave_factor,
             location_camera,
             location_normal_map_unit,
-            location_light_color,
-            location_light_pos,
-            location_attenuation,
+            location_light_clusters,
             location_depth_map_unit,
             location_depth_a,
             location_depth_b,
-            location_sky_color,
+            location_tiling,
+            location_wave_strength,
+            location_reflectivity,
+            location_tint,
+            location_tint_strength,
+            location_murkiness,
+            location_waves,
+            location_weather,
+            location_reflection_mode,
+            location_ssr_unit,
         }
     }
 
@@ -118,10 +152,6 @@ impl WaterShader {
         self.program.stop();
     }
 
-    pub fn load_sky_color(&mut self, color: &Vector3f) {
-        ShaderProgram::load_vector3d(self.location_sky_color, color);
-    }
-
     pub fn load_projection_matrix(&mut self, proj_mat: &Matrix4f) {
         ShaderProgram::load_matrix(self.location_proj_mat, proj_mat);
         ShaderProgram::load_float(self.location_depth_a, proj_mat[2][2]);
@@ -144,23 +174,44 @@ impl WaterShader {
         ShaderProgram::load_int(self.location_dudv_unit, 2);
         ShaderProgram::load_int(self.location_normal_map_unit, 3);
         ShaderProgram::load_int(self.location_depth_map_unit, 4);
+        ShaderProgram::load_int(self.location_ssr_unit, 5);
+        self.location_light_clusters.connect_texture_units();
     }
 
     pub fn load_wave_factor(&mut self, wave_factor: f32) {
         ShaderProgram::load_float(self.location_wave_factor, wave_factor);
     }
 
-    pub fn load_lights(&mut self, lights: &Vec<Light>) {
-        for li in 0..LIGHT_NUM {
-            if li < lights.len() {
-                ShaderProgram::load_vector3d(self.location_light_color[li], &lights[li].color);
-                ShaderProgram::load_vector3d(self.location_light_pos[li], &lights[li].position);
-                ShaderProgram::load_vector3d(self.location_attenuation[li], &lights[li].attenuation);
-            } else {
-                ShaderProgram::load_vector3d(self.location_light_color[li], &Vector3f::ZERO);
-                ShaderProgram::load_vector3d(self.location_light_pos[li], &Vector3f::ZERO);
-                ShaderProgram::load_vector3d(self.location_attenuation[li], &Vector3f::POS_X_AXIS);
-            }
-        }
+    pub fn load_tiling(&mut self, tiling: &Vector2f) {
+        ShaderProgram::load_vector2d(self.location_tiling, tiling);
+    }
+
+    pub fn load_material(&mut self, material: &WaterMaterial) {
+        ShaderProgram::load_float(self.location_wave_strength, material.wave_strength);
+        ShaderProgram::load_float(self.location_reflectivity, material.reflectivity);
+        ShaderProgram::load_vector3d(self.location_tint, &material.tint);
+        ShaderProgram::load_float(self.location_tint_strength, material.tint_strength);
+        ShaderProgram::load_float(self.location_murkiness, material.murkiness);
+    }
+
+    pub fn load_waves(&mut self, waves: &WaterWaves, time: f32) {
+        self.location_waves.load(waves, time);
+    }
+
+    pub fn load_reflection(&mut self, reflection: WaterReflection) {
+        let mode = match reflection {
+            WaterReflection::Planar => 0,
+            WaterReflection::ScreenSpace => 1,
+            WaterReflection::Hybrid => 2,
+        };
+        ShaderProgram::load_int(self.location_reflection_mode, mode);
+    }
+
+    pub fn load_light_clusters(&mut self, light_clusters: &LightClusters) {
+        self.location_light_clusters.load(light_clusters);
+    }
+
+    pub fn load_weather(&mut self, weather: &Weather, time: f32) {
+        self.location_weather.load(weather, time);
     }
 }
\ No newline at end of file
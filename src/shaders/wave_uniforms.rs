@@ -0,0 +1,40 @@
+use super::shader_program::ShaderProgram;
+use crate::entities::WaterWaves;
+use crate::math::Vector4f;
+
+// the gerstner waves of the water vertex shader, shared by the water shader and the screen space reflection of the water
+#[derive(Default)]
+pub struct WaveUniforms {
+    location_wave_count: i32,
+    location_wave_time: i32,
+    // direction, amplitude and wavelength of every wave
+    location_waves: [i32; WaterWaves::MAX_WAVES],
+    location_wave_steepness: [i32; WaterWaves::MAX_WAVES],
+}
+
+impl WaveUniforms {
+    pub fn new(shader_prog: &ShaderProgram) -> WaveUniforms {
+        let mut location_waves = [0; WaterWaves::MAX_WAVES];
+        let mut location_wave_steepness = [0; WaterWaves::MAX_WAVES];
+        for i in 0..WaterWaves::MAX_WAVES {
+            location_waves[i] = shader_prog.get_uniform_location(&format!("waves[{}]", i));
+            location_wave_steepness[i] = shader_prog.get_uniform_location(&format!("wave_steepness[{}]", i));
+        }
+        WaveUniforms {
+            location_wave_count: shader_prog.get_uniform_location("wave_count"),
+            location_wave_time: shader_prog.get_uniform_location("wave_time"),
+            location_waves,
+            location_wave_steepness,
+        }
+    }
+
+    // the vertex shader only moves the vertices when the count is above 0
+    pub fn load(&self, waves: &WaterWaves, time: f32) {
+        ShaderProgram::load_int(self.location_wave_count, waves.waves().len() as i32);
+        ShaderProgram::load_float(self.location_wave_time, time);
+        for (i, wave) in waves.waves().iter().enumerate() {
+            ShaderProgram::load_vector4d(self.location_waves[i], &Vector4f::new(wave.direction.x, wave.direction.y, wave.amplitude, wave.wavelength));
+            ShaderProgram::load_float(self.location_wave_steepness[i], wave.steepness);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,32 @@
+use super::shader_program::ShaderProgram;
+use crate::entities::Weather;
+
+// the wind, the rain and what it leaves on the surfaces, every shader that has one of these picks the uniforms it uses
+#[derive(Default)]
+pub struct WeatherUniforms {
+    location_wind: i32,
+    location_wind_time: i32,
+    location_rain: i32,
+    location_wetness: i32,
+    location_snow_cover: i32,
+}
+
+impl WeatherUniforms {
+    pub fn new(shader_prog: &ShaderProgram) -> WeatherUniforms {
+        WeatherUniforms {
+            location_wind: shader_prog.get_uniform_location("wind"),
+            location_wind_time: shader_prog.get_uniform_location("wind_time"),
+            location_rain: shader_prog.get_uniform_location("rain"),
+            location_wetness: shader_prog.get_uniform_location("wetness"),
+            location_snow_cover: shader_prog.get_uniform_location("snow_cover"),
+        }
+    }
+
+    pub fn load(&self, weather: &Weather, time: f32) {
+        ShaderProgram::load_vector3d(self.location_wind, &weather.wind);
+        ShaderProgram::load_float(self.location_wind_time, time);
+        ShaderProgram::load_float(self.location_rain, weather.rain);
+        ShaderProgram::load_float(self.location_wetness, weather.wetness);
+        ShaderProgram::load_float(self.location_snow_cover, weather.snow_cover);
+    }
+}
\ No newline at end of file
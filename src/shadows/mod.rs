@@ -1,2 +1,4 @@
 pub mod shadow_box;
 pub mod shadow_params;
+pub mod point_shadows;
+pub mod shadow_casters;
\ No newline at end of file
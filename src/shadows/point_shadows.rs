@@ -0,0 +1,149 @@
+use crate::entities::{
+    Light,
+    LightKind,
+};
+use crate::math::{
+    Matrix4f,
+    Vector3f,
+};
+
+// cube shadow maps for point lights
+// every frame we pick up to MAX_POINT_SHADOWS of the point lights that cast shadows and render a cube depth map around each of them
+// the maps are the layers of one cube map array and the lit shaders look up which layer belongs to a light in the lights array
+pub struct PointShadows {
+    budget: usize,
+    // indices of the lights that got a cube map this frame, the position in this vec is the cube map layer
+    pub lights: Vec<usize>,
+    pub shadow_map_texture: u32,
+    pub shadow_map_size: usize,
+}
+
+impl PointShadows {
+    // the shaders have arrays of this size
+    pub const MAX_POINT_SHADOWS: usize = 4;
+    // lights whose range ends further than this from the camera can't light anything close enough to need a shadow
+    pub const SHADOW_DISTANCE: f32 = 100.0;
+    pub const NEAR: f32 = 0.1;
+
+    // the target and up vectors of the cube faces in the order gl expects them (+x, -x, +y, -y, +z, -z)
+    const FACES: [(Vector3f, Vector3f); 6] = [
+        (Vector3f { x: 1.0, y: 0.0, z: 0.0 }, Vector3f { x: 0.0, y: -1.0, z: 0.0 }),
+        (Vector3f { x: -1.0, y: 0.0, z: 0.0 }, Vector3f { x: 0.0, y: -1.0, z: 0.0 }),
+        (Vector3f { x: 0.0, y: 1.0, z: 0.0 }, Vector3f { x: 0.0, y: 0.0, z: 1.0 }),
+        (Vector3f { x: 0.0, y: -1.0, z: 0.0 }, Vector3f { x: 0.0, y: 0.0, z: -1.0 }),
+        (Vector3f { x: 0.0, y: 0.0, z: 1.0 }, Vector3f { x: 0.0, y: -1.0, z: 0.0 }),
+        (Vector3f { x: 0.0, y: 0.0, z: -1.0 }, Vector3f { x: 0.0, y: -1.0, z: 0.0 }),
+    ];
+
+    pub fn new(budget: usize, shadow_map_size: usize) -> PointShadows {
+        assert!(budget <= PointShadows::MAX_POINT_SHADOWS, "The shaders support at most {} shadowed point lights", PointShadows::MAX_POINT_SHADOWS);
+        PointShadows {
+            budget,
+            lights: Vec::with_capacity(budget),
+            shadow_map_texture: 0,
+            shadow_map_size,
+        }
+    }
+
+    // picks the point lights that matter the most for what the camera sees, that is the bright ones (with a big range) that are close
+    pub fn pick_lights(&mut self, lights: &[Light], eye_position: &Vector3f) {
+        let mut candidates: Vec<(usize, f32)> = lights.iter().enumerate().filter_map(|(i, light)| {
+            let position = match &light.kind {
+                LightKind::Point { position } if light.casts_shadows => position,
+                _ => return None,
+            };
+            let range = light.range();
+            let distance = (position - eye_position).length();
+            if !range.is_finite() || distance > range + PointShadows::SHADOW_DISTANCE {
+                return None;
+            }
+            Some((i, range / distance.max(1.0)))
+        }).collect();
+        // highest score first, ties go to the light that comes first
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).expect("Light scores can't be nan").then(a.0.cmp(&b.0)));
+
+        self.lights.clear();
+        self.lights.extend(candidates.iter().take(self.budget).map(|(i, _)| *i));
+    }
+
+    // cube map layer of the light or -1 for every slot of the shaders' array
+    pub fn shader_slots(&self) -> [i32; PointShadows::MAX_POINT_SHADOWS] {
+        let mut slots = [-1; PointShadows::MAX_POINT_SHADOWS];
+        for (slot, light) in slots.iter_mut().zip(self.lights.iter()) {
+            *slot = *light as i32;
+        }
+        slots
+    }
+
+    // projection * view for the 6 faces of the cube around the light, far is the range of the light
+    pub fn face_matrices(position: &Vector3f, far: f32) -> [Matrix4f; 6] {
+        // our projection takes the planes as negative z values
+        let projection = Matrix4f::create_projection_matrix(-PointShadows::NEAR, -far, 90.0, 1.0);
+        let mut matrices: [Matrix4f; 6] = Default::default();
+        for (matrix, (target, up)) in matrices.iter_mut().zip(PointShadows::FACES.iter()) {
+            let center = position + target;
+            *matrix = &projection * Matrix4f::look_at(position, &center, up);
+        }
+        matrices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector4f;
+    use crate::utils::test_utils::*;
+
+    fn lantern(position: Vector3f) -> Light {
+        Light::new_point(position, Vector3f::new(1.0, 0.7, 0.3), Vector3f::new(1.0, 0.05, 0.02)).with_shadows()
+    }
+
+    #[test]
+    fn test_pick_lights() {
+        let lights = vec![
+            Light::new_directional(Vector3f::new(0.0, -1.0, 0.0), Vector3f::new(1.0, 1.0, 1.0)).with_shadows(),
+            lantern(Vector3f::new(30.0, 0.0, 0.0)),
+            // doesn't cast shadows
+            Light::new_point(Vector3f::new(1.0, 0.0, 0.0), Vector3f::new(1.0, 1.0, 1.0), Vector3f::new(1.0, 0.05, 0.02)),
+            lantern(Vector3f::new(5.0, 0.0, 0.0)),
+            // too far to matter
+            lantern(Vector3f::new(1000.0, 0.0, 0.0)),
+            lantern(Vector3f::new(0.0, 0.0, 10.0)),
+            // same distance as the one above but a lot brighter
+            Light::new_point(Vector3f::new(0.0, 0.0, -30.0), Vector3f::new(2.0, 2.0, 2.0), Vector3f::new(1.0, 0.01, 0.002)).with_shadows(),
+        ];
+        let mut point_shadows = PointShadows::new(3, 512);
+        point_shadows.pick_lights(&lights, &Vector3f::zero());
+        assert_eq!(point_shadows.lights, vec![3, 6, 5]);
+        assert_eq!(point_shadows.shader_slots(), [3, 6, 5, -1]);
+
+        // moving makes other lights more important
+        point_shadows.pick_lights(&lights, &Vector3f::new(31.0, 0.0, 0.0));
+        assert_eq!(point_shadows.lights[0], 1);
+
+        point_shadows.pick_lights(&lights, &Vector3f::new(5000.0, 0.0, 0.0));
+        assert!(point_shadows.lights.is_empty());
+    }
+
+    #[test]
+    fn test_face_matrices() {
+        let position = Vector3f::new(3.0, -2.0, 7.0);
+        let matrices = PointShadows::face_matrices(&position, 50.0);
+        for (matrix, (target, _)) in matrices.iter().zip(PointShadows::FACES.iter()) {
+            // a point straight along the face direction ends up in the middle of that face
+            let point = &position + &(10.0 * target);
+            let clip = matrix.transform(&Vector4f::point(&point));
+            assert_f32_eq!(clip.x / clip.w, 0.0, test_constants::EPS_MEDIUM);
+            assert_f32_eq!(clip.y / clip.w, 0.0, test_constants::EPS_MEDIUM);
+            assert!((clip.z / clip.w).abs() < 1.0);
+            // and a point in the opposite direction is behind it
+            let behind = &position - &(10.0 * target);
+            assert!(matrix.transform(&Vector4f::point(&behind)).w < 0.0);
+        }
+
+        // the +x face sees +y at the bottom of the image like the cube map lookups expect
+        let up = &(&position + &Vector3f::new(10.0, 0.0, 0.0)) + &Vector3f::new(0.0, 5.0, 0.0);
+        let clip = matrices[0].transform(&Vector4f::point(&up));
+        assert!(clip.y / clip.w < 0.0);
+    }
+}
@@ -2,253 +2,275 @@ use crate::entities::{
     Camera,
 };
 use crate::math::{
-    Matrix3f,
     Matrix4f,
     Vector3f,
     Vector4f,
-    distance,  
-    f32_min,  
-    f32_max,  
-};
-use std::{    
-    f32
 };
 
-// the cuboid that we use to find what to draw into the shadow map
-// we update the size every frame and we attempt to keep the cuboid as small as possible
-// everything in the cuboid will be rendered into the shadow map in the shadow render pass
+// one slice of the camera frustum that gets its own layer in the shadow map texture array
+#[derive(Debug, Clone)]
+pub struct Cascade {
+    // distances from the camera where the slice starts and ends
+    pub near: f32,
+    pub far: f32,
+    // radius of the sphere around the slice. it only depends on the slice and not on where the camera looks
+    // so the shadow map covers the same area every frame and the shadows don't change size when we turn
+    pub radius: f32,
+    // center of the sphere in light space, snapped to whole shadow map texels so the shadows don't shimmer when we move
+    pub lightspace_center: Vector3f,
+    pub ortho_proj_mat: Matrix4f,
+}
+
+// how a scene wants the sun shadows split up, see ShadowBox::split_distances
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowCascades {
+    pub count: usize,
+    pub split_lambda: f32,
+}
+
+impl Default for ShadowCascades {
+    fn default() -> ShadowCascades {
+        ShadowCascades {
+            count: ShadowBox::CASCADE_COUNT,
+            split_lambda: ShadowBox::SPLIT_LAMBDA,
+        }
+    }
+}
+
+// the cuboids that we use to find what to draw into the shadow map
+// the part of the camera frustum up to far is split into cascades, the close ones are small so they get a lot of texels per unit
+// and the far ones are big so the shadows reach further. everything in a cascade's box is rendered into its layer of the shadow map
 pub struct ShadowBox {
-    farplane_width: f32,
-    farplane_height: f32,
     nearplane_width: f32,
     nearplane_height: f32,
     near_plane: f32,
     far_plane: f32,
-    pub width: f32,
-    pub height: f32,
-    pub length: f32,
-    pub world_space_center: Vector3f,
-    pub obb_corners: [Vector3f; 8],
+    shadow_map_size: usize,
+    pub cascades: Vec<Cascade>,
     pub frustum_corners: [Vector3f; 8],
-    pub ortho_proj_mat: Matrix4f,
 }
 
 impl ShadowBox {
-    pub const OFFSET: f32 = 10.0;
-    pub const SHADOW_DISTANCE: f32 = 100.0;
+    // how much further towards the light than the cascade we still look for things that cast shadows into it
+    pub const OFFSET: f32 = 100.0;
+    pub const SHADOW_DISTANCE: f32 = 400.0;
+    // the shaders have arrays of this size
+    pub const MAX_CASCADES: usize = 4;
+    pub const CASCADE_COUNT: usize = 4;
+    // 0 splits the distance into equal parts, 1 splits it logarithmically so every cascade is the same times bigger than the previous
+    pub const SPLIT_LAMBDA: f32 = 0.8;
 
-    pub fn new(aspect_ratio: f32, fov_deg: f32, near: f32, far: f32) -> Self {
-       let (farplane_width, farplane_height, nearplane_width, nearplane_height) = ShadowBox::compute_frustum_sizes(aspect_ratio, fov_deg, near.abs(), far.abs());
+    pub fn new(aspect_ratio: f32, fov_deg: f32, near: f32, far: f32, cascade_count: usize, split_lambda: f32, shadow_map_size: usize) -> Self {
+        assert!(cascade_count > 0 && cascade_count <= ShadowBox::MAX_CASCADES, "The shaders support 1 to {} cascades", ShadowBox::MAX_CASCADES);
+        let tan_fov_half = (fov_deg / 2.0).to_radians().tan();
+        // frustum size at distance 1, multiply by the distance to get the size of a slice of the frustum
+        let nearplane_width = 2.0 * tan_fov_half;
+        let nearplane_height = nearplane_width / aspect_ratio;
+        let near_plane = near.abs();
+        let far_plane = far.abs();
+
+        let splits = ShadowBox::split_distances(near_plane, far_plane, cascade_count, split_lambda);
+        let cascades = splits.windows(2).map(|split| {
+            let (_, sphere_radius) = ShadowBox::bounding_sphere(split[0], split[1], nearplane_width, nearplane_height);
+            Cascade {
+                near: split[0],
+                far: split[1],
+                // leave a texel on every side since snapping can move the box up to a texel away from the sphere
+                radius: sphere_radius * shadow_map_size as f32 / (shadow_map_size - 2) as f32,
+                lightspace_center: Vector3f::zero(),
+                ortho_proj_mat: Matrix4f::identity(),
+            }
+        }).collect();
 
         ShadowBox {
-            farplane_width,
-            farplane_height,
             nearplane_width,
             nearplane_height,
-            near_plane: near,
-            far_plane: far,
-            width: 0.0,
-            height: 0.0,
-            length: 0.0,
-            world_space_center: Vector3f::zero(),
-            obb_corners: Default::default(),
+            near_plane,
+            far_plane,
+            shadow_map_size,
+            cascades,
             frustum_corners: Default::default(),
-            ortho_proj_mat: Matrix4f::identity(),
         }
     }
 
-    // does it make sense to transform to light space if all we care about is the world space center? -- yes -> 
-    // at best in world space you could get the diagonal of the frustum and just create a shadowbox of that size (works btw) but otherwise the shadowbox size changes
-    // based on the frustum angle    
-    pub fn update(&mut self, camera: &Camera, world_to_lightspace: &Matrix4f) {        
-        let frustum_corners_ws = self.get_frustum_corners_ws(camera);
-        self.frustum_corners = frustum_corners_ws.clone();
+    // cascade_count + 1 distances where the cascades start and end, the first is near and the last is far
+    // this is the practical split scheme which blends between uniform and logarithmic splits by lambda
+    pub fn split_distances(near: f32, far: f32, cascade_count: usize, lambda: f32) -> Vec<f32> {
+        (0..=cascade_count).map(|i| {
+            let t = i as f32 / cascade_count as f32;
+            let log_split = near * (far / near).powf(t);
+            let uniform_split = near + (far - near) * t;
+            lambda * log_split + (1.0 - lambda) * uniform_split
+        }).collect()
+    }
 
-        self.update_shadow_box_size(world_to_lightspace);        
-        //self.update_shadow_box_dot_with_planes_algo(world_to_lightspace);
+    // sphere around the slice of the frustum between near and far, the center is on the view axis at the given distance
+    // we pick the center so the near and far corners are equally far from it unless that would put it behind the far plane
+    fn bounding_sphere(near: f32, far: f32, width_at_one: f32, height_at_one: f32) -> (f32, f32) {
+        let diagonal_sq_at_one = 0.25 * (width_at_one * width_at_one + height_at_one * height_at_one);
+        let near_diag_sq = near * near * diagonal_sq_at_one;
+        let far_diag_sq = far * far * diagonal_sq_at_one;
+        let center = ((far * far - near * near + far_diag_sq - near_diag_sq) / (2.0 * (far - near))).min(far);
+        let radius = ((center - near) * (center - near) + near_diag_sq).sqrt().max(far_diag_sq.sqrt());
+        (center, radius)
+    }
 
-        self.ortho_proj_mat[0][0] = 2.0 / self.width;
-        self.ortho_proj_mat[1][1] = 2.0 / self.height;
-        self.ortho_proj_mat[2][2] = -2.0 / self.length;
+    pub fn update(&mut self, camera: &Camera, world_to_lightspace: &Matrix4f) {
+        let view_to_world = Matrix4f::create_view_matrix(camera).inverse();
+        self.update_from_view(&view_to_world, world_to_lightspace);
     }
 
-    fn compute_frustum_sizes(aspect_ratio: f32, fov_deg: f32, near_dist: f32, far_dist: f32) -> (f32, f32, f32, f32)  {
-        let tan_fov_half = (fov_deg / 2.0).to_radians().tan();
-        let near_width = 2.0 * near_dist * tan_fov_half;
-        let far_width = 2.0 * far_dist * tan_fov_half;
-        let near_height = near_width / aspect_ratio;
-        let far_height = far_width / aspect_ratio;
-        (far_width, far_height, near_width, near_height)
+    // world_to_lightspace should only rotate, the cascades bring in the translation themselves
+    pub fn update_from_view(&mut self, view_to_world: &Matrix4f, world_to_lightspace: &Matrix4f) {
+        self.frustum_corners = ShadowBox::slice_corners_ws(self.near_plane, self.far_plane, self.nearplane_width, self.nearplane_height, view_to_world);
+
+        for cascade in self.cascades.iter_mut() {
+            let (center_distance, _) = ShadowBox::bounding_sphere(cascade.near, cascade.far, self.nearplane_width, self.nearplane_height);
+            let center_ws = view_to_world.transform(&Vector4f::new(0.0, 0.0, -center_distance, 1.0));
+            let mut center_ls = world_to_lightspace.transform(&center_ws).xyz();
+
+            // only ever move the box by whole texels so a texel always covers the same part of the world
+            let texel_size = 2.0 * cascade.radius / self.shadow_map_size as f32;
+            center_ls.x = (center_ls.x / texel_size).floor() * texel_size;
+            center_ls.y = (center_ls.y / texel_size).floor() * texel_size;
+
+            let r = cascade.radius;
+            // in light space we look down -z so the things closer to the light have a bigger z
+            cascade.ortho_proj_mat = Matrix4f::create_ortho_projection_matrix(
+                center_ls.x - r, center_ls.x + r, center_ls.y - r, center_ls.y + r,
+                -(center_ls.z + r + ShadowBox::OFFSET), -(center_ls.z - r));
+            cascade.lightspace_center = center_ls;
+        }
     }
 
-    fn get_frustum_corners_ws(&self, camera: &Camera) -> [Vector3f; 8] {
+    // corners of the part of the camera frustum between the near and far distance in world space
+    fn slice_corners_ws(near: f32, far: f32, width_at_one: f32, height_at_one: f32, view_to_world: &Matrix4f) -> [Vector3f; 8] {
         let mut corners: [Vector3f; 8] = Default::default();
-
-        // near top right
-        corners[0].x = self.nearplane_width / 2.0;
-        corners[0].y = self.nearplane_height / 2.0;
-        corners[0].z = self.near_plane;
-        // near bottom right
-        corners[1].x = self.nearplane_width / 2.0;
-        corners[1].y = -self.nearplane_height / 2.0;
-        corners[1].z = self.near_plane;
-        // near bottom left
-        corners[2].x = -self.nearplane_width / 2.0;
-        corners[2].y = -self.nearplane_height / 2.0;
-        corners[2].z = self.near_plane;
-        // near top left
-        corners[3].x = -self.nearplane_width / 2.0;
-        corners[3].y = self.nearplane_height / 2.0;
-        corners[3].z = self.near_plane;
-        // far top right
-        corners[4].x = self.farplane_width / 2.0;
-        corners[4].y = self.farplane_height / 2.0;
-        corners[4].z = self.far_plane;
-        // far bottom right
-        corners[5].x = self.farplane_width / 2.0;
-        corners[5].y = -self.farplane_height / 2.0;
-        corners[5].z = self.far_plane;
-        // far bottom left
-        corners[6].x = -self.farplane_width / 2.0;
-        corners[6].y = -self.farplane_height / 2.0;
-        corners[6].z = self.far_plane;
-        // far top left
-        corners[7].x = -self.farplane_width / 2.0;
-        corners[7].y = self.farplane_height / 2.0;
-        corners[7].z = self.far_plane;
-        
-        let ws_to_vs = Matrix4f::create_view_matrix(camera);
-        let vs_to_ws = ws_to_vs.inverse();
-
-        let mut temp_v4 = Vector4f::new(0.0, 0.0, 0.0, 0.0);
-        for i in 0..corners.len() {
-            temp_v4.set_from(&corners[i]);
-            temp_v4.w = 1.0; // transform points
-            let res = vs_to_ws.transform(&temp_v4);
-            corners[i].set_from(&res);
+        // near top right, near bottom right, near bottom left, near top left and then the same for far
+        let signs = [(1.0, 1.0), (1.0, -1.0), (-1.0, -1.0), (-1.0, 1.0)];
+        for (i, distance) in [near, far].iter().enumerate() {
+            for (j, (sx, sy)) in signs.iter().enumerate() {
+                let corner_vs = Vector4f::new(sx * 0.5 * width_at_one * distance, sy * 0.5 * height_at_one * distance, -distance, 1.0);
+                corners[4 * i + j] = view_to_world.transform(&corner_vs).xyz();
+            }
         }
         corners
     }
-        
-    ////////////////////////////////////////////////////////////////////////////////////////////////////
-    /// this is my implementation of the idea of calculating the view cuboid by expressing frustum in view reference frame
-    /// and then since there the cuboid is axis-aligned to calculate it we find the mins and maxes     
-    ////////////////////////////////////////////////////////////////////////////////////////////////////
-    pub fn update_shadow_box_size(&mut self, light_basis_mat: &Matrix4f) {
-        // we want to project things to the light space which should act as a view space (eye space)
-        // therefore our rotation uses the negative angles since we want things to move in the opposite direction         
-        let light_orient_inv = light_basis_mat.inverse();
-        
-        // we express every frustum in lightspace
-        // in lightspace the cuboid is axis alighned so to figure out the corners of the cuboid we just need to figure out the min,max values
-        let mut temp_v4 = Vector4f::new(0.0, 0.0, 0.0, 0.0);
-        let mut min_v = Vector3f::new(f32::MAX, f32::MAX, f32::MAX);
-        let mut max_v = Vector3f::new(f32::MIN, f32::MIN, f32::MIN);
-        let mut avg = Vector3f::zero();
-        for i in 0..self.frustum_corners.len() {
-            temp_v4.set_from(&self.frustum_corners[i]);
-            temp_v4.w = 1.0; // transform points
-            avg += &self.frustum_corners[i];
-            let res = light_basis_mat.transform(&temp_v4);            
-            min_v.x = f32_min(min_v.x, res.x);
-            min_v.y = f32_min(min_v.y, res.y);
-            min_v.z = f32_min(min_v.z, res.z);
-            max_v.x = f32_max(max_v.x, res.x);
-            max_v.y = f32_max(max_v.y, res.y);
-            max_v.z = f32_max(max_v.z, res.z);
-        }
+}
 
-        // let mm = f32_max(max_v.x - min_v.x, max_v.y - min_v.y);
-        // let mm = f32_max(mm, max_v.z - min_v.z);
-
-        self.width = max_v.x - min_v.x;
-        self.height = max_v.y - min_v.y;
-        self.length = max_v.z - min_v.z;
-        // self.width = mm;
-        // self.height = mm;
-        // self.length = mm;
-
-        let world_space_v4 = Vector4f::new(0.5 * (max_v.x + min_v.x), 0.5 * (max_v.y + min_v.y), 0.5 * (max_v.z + min_v.z), 1.0);
-        
-        //camera.looking_at.clone();//
-        self.world_space_center = light_orient_inv.transform(&world_space_v4).xyz();
-        //self.world_space_center = (1.0 / corners.len() as f32) * avg;
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::*;
 
-    ////////////////////////////////////////////////////////////////////////////////////////////////////////////
-    /// This algo attempts to avoid having to project to the lightspace since i was worried about the
-    /// cicrular dependency even though it should be ok. The idea of the algo is that in the lightspace matrix we have the normal vectors
-    /// of the planes of the bounding cuboid (since the vectors are the axis which are normal to the cuboid if the cuboid is on origin)
-    /// then we compute the dot product of each plane normal with world space frustum corners
-    /// that gives us distances to the planes and we can use combinations of these distances to find the corners of the bounding cuboid
-    //////////////////////////////////////////////////////////////////////////////////////////////////////////// 
-    pub fn update_shadow_box_dot_with_planes_algo(&mut self, world_to_lightspace: &Matrix4f) {
+    const MAP_SIZE: usize = 2048;
 
-        self.obb_corners = ShadowBox::calc_bounding_cuboid_corners_ws(&self.frustum_corners, world_to_lightspace);
+    fn test_box() -> ShadowBox {
+        ShadowBox::new(16.0 / 9.0, 70.0, -0.1, -ShadowBox::SHADOW_DISTANCE, ShadowBox::CASCADE_COUNT, ShadowBox::SPLIT_LAMBDA, MAP_SIZE)
+    }
 
-        self.width = distance(&self.obb_corners[0], &self.obb_corners[1]);
-        self.height = distance(&self.obb_corners[0], &self.obb_corners[4]);
-        self.length = distance(&self.obb_corners[0], &self.obb_corners[3]);
+    fn light_rotation() -> Matrix4f {
+        Matrix4f::create_fps_view_matrix(&Vector3f::zero(), 50.0, 30.0)
+    }
 
-        self.world_space_center = 0.5 * (&self.obb_corners[0] + &self.obb_corners[6]);        
+    fn camera_to_world(position: &Vector3f, pitch: f32, yaw: f32) -> Matrix4f {
+        Matrix4f::create_fps_view_matrix(position, pitch, yaw).inverse()
     }
 
-    fn calc_bounding_cuboid_corners_ws(corners: &[Vector3f; 8], light_basis_mat: &Matrix4f) -> [Vector3f; 8] {
-        let mut result: [Vector3f; 8] = Default::default();
-        let mut cuboid_faces: [Vector4f; 6] = Default::default();
-        for i in 0..3 {            
-            cuboid_faces[2*i].x = light_basis_mat[i][0];
-            cuboid_faces[2*i].y = light_basis_mat[i][1];
-            cuboid_faces[2*i].z = light_basis_mat[i][2];
-            cuboid_faces[2*i].w = 0.0;
-            cuboid_faces[2*i].normalize();
-            cuboid_faces[2*i].w = f32::MAX;
-
-            cuboid_faces[2*i + 1].x = light_basis_mat[i][0];
-            cuboid_faces[2*i + 1].y = light_basis_mat[i][1];
-            cuboid_faces[2*i + 1].z = light_basis_mat[i][2];
-            cuboid_faces[2*i + 1].w = 0.0;
-            cuboid_faces[2*i + 1].normalize();
-            cuboid_faces[2*i + 1].w = f32::MIN;
+    #[test]
+    fn test_split_distances() {
+        let uniform = ShadowBox::split_distances(1.0, 101.0, 4, 0.0);
+        for (i, split) in uniform.iter().enumerate() {
+            assert_f32_eq!(*split, 1.0 + 25.0 * i as f32, test_constants::EPS_MEDIUM);
+        }
+
+        let log = ShadowBox::split_distances(1.0, 1000.0, 3, 1.0);
+        for (i, split) in log.iter().enumerate() {
+            assert_f32_eq!(*split, 10.0f32.powi(i as i32), test_constants::EPS_BAD);
+        }
+
+        let practical = ShadowBox::split_distances(0.1, 400.0, 4, 0.8);
+        assert_eq!(practical.len(), 5);
+        assert_f32_eq!(practical[0], 0.1, test_constants::EPS_MEDIUM);
+        assert_f32_eq!(practical[4], 400.0, test_constants::EPS_MEDIUM);
+        for split in practical.windows(2) {
+            assert!(split[0] < split[1]);
         }
-        
-        // compute the projection of the frustum corners in ws coords onto the cuboid face normals
-        // the min projection value should give us one corner point, the max the other corner point
-        // we just need to repeat this for all three face normals
-        for j in 0..cuboid_faces.len() {
-            for i in 0..corners.len() {
-                let dprod = cuboid_faces[j].dot_product_v3(&corners[i]);
-                if j % 2 == 0 {                    
-                    if dprod < cuboid_faces[j].w {
-                        cuboid_faces[j].w = dprod;
-                    }
-                } else {
-                    if dprod > cuboid_faces[j].w {
-                        cuboid_faces[j].w = dprod;
-                    }
-                }
+    }
+
+    #[test]
+    fn test_scene_picks_the_cascades() {
+        let cascades = ShadowCascades { count: 2, split_lambda: 0.5 };
+        let shadow_box = ShadowBox::new(16.0 / 9.0, 70.0, -0.1, -ShadowBox::SHADOW_DISTANCE, cascades.count, cascades.split_lambda, MAP_SIZE);
+        assert_eq!(shadow_box.cascades.len(), 2);
+        let splits = ShadowBox::split_distances(0.1, ShadowBox::SHADOW_DISTANCE, 2, 0.5);
+        assert_f32_eq!(shadow_box.cascades[0].far, splits[1], test_constants::EPS_MEDIUM);
+        assert_f32_eq!(shadow_box.cascades[1].far, ShadowBox::SHADOW_DISTANCE, test_constants::EPS_MEDIUM);
+        assert_eq!(ShadowCascades::default().count, ShadowBox::CASCADE_COUNT);
+    }
+
+    #[test]
+    fn test_cascades_cover_their_slice() {
+        let mut shadow_box = test_box();
+        let light = light_rotation();
+        shadow_box.update_from_view(&camera_to_world(&Vector3f::new(12.0, 5.0, -30.0), 20.0, 135.0), &light);
+        let view_to_world = camera_to_world(&Vector3f::new(12.0, 5.0, -30.0), 20.0, 135.0);
+
+        for cascade in shadow_box.cascades.iter() {
+            let corners = ShadowBox::slice_corners_ws(cascade.near, cascade.far, shadow_box.nearplane_width, shadow_box.nearplane_height, &view_to_world);
+            for corner in corners.iter() {
+                // every corner of the slice ends up inside the cascade's clip box
+                let clip = cascade.ortho_proj_mat.transform(&light.transform(&Vector4f::point(corner)));
+                assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0 && clip.z.abs() <= 1.0, "{:?} is outside of the cascade {:?}", clip, cascade);
             }
         }
+    }
 
-        result[0] = ShadowBox::compute_corner(&cuboid_faces, 0, 2, 5);
-        result[1] = ShadowBox::compute_corner(&cuboid_faces, 1, 2, 5);
-        result[2] = ShadowBox::compute_corner(&cuboid_faces, 1, 2, 4);
-        result[3] = ShadowBox::compute_corner(&cuboid_faces, 0, 2, 4);
-        
-        result[4] = ShadowBox::compute_corner(&cuboid_faces, 0, 3, 5);
-        result[5] = ShadowBox::compute_corner(&cuboid_faces, 1, 3, 5);
-        result[6] = ShadowBox::compute_corner(&cuboid_faces, 1, 3, 4);
-        result[7] = ShadowBox::compute_corner(&cuboid_faces, 0, 3, 4);
-
-        result
+    #[test]
+    fn test_cascade_size_is_stable() {
+        let mut shadow_box = test_box();
+        let light = light_rotation();
+        shadow_box.update_from_view(&camera_to_world(&Vector3f::zero(), 0.0, 0.0), &light);
+        let scales: Vec<f32> = shadow_box.cascades.iter().map(|cascade| cascade.ortho_proj_mat[0][0]).collect();
+
+        shadow_box.update_from_view(&camera_to_world(&Vector3f::new(100.0, 20.0, 50.0), -35.0, 250.0), &light);
+        for (cascade, scale) in shadow_box.cascades.iter().zip(scales.iter()) {
+            assert_f32_eq!(cascade.ortho_proj_mat[0][0], *scale, test_constants::EPS_PRECISE);
+            assert_f32_eq!(cascade.ortho_proj_mat[1][1], *scale, test_constants::EPS_PRECISE);
+        }
+        // cascades get bigger further away
+        for cascade in shadow_box.cascades.windows(2) {
+            assert!(cascade[0].radius < cascade[1].radius);
+        }
     }
 
-    fn compute_corner(cuboid_faces: &[Vector4f; 6], a: usize, b: usize, c: usize) -> Vector3f {
-        let mat = Matrix3f::new([[cuboid_faces[a].x, cuboid_faces[a].y, cuboid_faces[a].z], 
-            [cuboid_faces[b].x, cuboid_faces[b].y, cuboid_faces[b].z], [cuboid_faces[c].x, cuboid_faces[c].y, cuboid_faces[c].z]]);
-        let imat = mat.inverse();
-        let d_vec = Vector3f::new(cuboid_faces[a].w, cuboid_faces[b].w, cuboid_faces[c].w);
-        let result = imat.transform(&d_vec);
-        result
+    #[test]
+    fn test_texel_snapping() {
+        let mut shadow_box = test_box();
+        let light = light_rotation();
+        let light_to_world = light.inverse();
+
+        shadow_box.update_from_view(&camera_to_world(&Vector3f::new(3.0, 1.0, 7.0), 10.0, 45.0), &light);
+        let before = shadow_box.cascades.clone();
+        for cascade in before.iter() {
+            let texel_size = 2.0 * cascade.radius / MAP_SIZE as f32;
+            let texels_x = cascade.lightspace_center.x / texel_size;
+            let texels_y = cascade.lightspace_center.y / texel_size;
+            assert_f32_eq!(texels_x, texels_x.round(), test_constants::EPS_MEDIUM);
+            assert_f32_eq!(texels_y, texels_y.round(), test_constants::EPS_MEDIUM);
+        }
+
+        // moving the camera a small bit sideways in light space moves the boxes by whole texels or not at all
+        let nudge = light_to_world.transform(&Vector4f::new(0.0013, 0.0007, 0.0, 0.0)).xyz();
+        let moved = &Vector3f::new(3.0, 1.0, 7.0) + &nudge;
+        shadow_box.update_from_view(&camera_to_world(&moved, 10.0, 45.0), &light);
+        for (cascade, old) in shadow_box.cascades.iter().zip(before.iter()) {
+            let texel_size = 2.0 * cascade.radius / MAP_SIZE as f32;
+            let shift_x = (cascade.lightspace_center.x - old.lightspace_center.x) / texel_size;
+            let shift_y = (cascade.lightspace_center.y - old.lightspace_center.y) / texel_size;
+            assert_f32_eq!(shift_x, shift_x.round(), test_constants::EPS_MEDIUM);
+            assert_f32_eq!(shift_y, shift_y.round(), test_constants::EPS_MEDIUM);
+            assert!(shift_x.abs() <= 1.0 && shift_y.abs() <= 1.0);
+        }
     }
-}
\ No newline at end of file
+}
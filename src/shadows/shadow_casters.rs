@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use crate::entities::{
+    AnimatedEntity,
+    Entity,
+    Player,
+    player::PlayerEntityType,
+};
+use crate::models::TexturedModel;
+use crate::renderers::culling::CullCounter;
+
+// what one shadow map (a cascade of the sun or the cube of a point light) gets drawn from
+// every kind of entity casts: the plain, the normal mapped and the env mapped ones go in grouped by model for the instanced draws
+// and the skinned player goes in on its own for the animated shadow shader
+pub struct ShadowCasters<'a> {
+    pub instanced: Vec<(&'a TexturedModel, Vec<&'a Entity>)>,
+    pub animated: Vec<&'a AnimatedEntity>,
+}
+
+impl<'a> ShadowCasters<'a> {
+    // the entities outside of what the map covers are counted as culled, the player is always drawn
+    pub fn collect<F>(entity_groups: &[&HashMap<&'a TexturedModel, Vec<&'a Entity>>], player: &'a Player, culling: &mut CullCounter, is_caster: F) -> ShadowCasters<'a>
+            where F: Fn(&Entity) -> bool {
+        let mut instanced = Vec::new();
+        for (model, entities) in entity_groups.iter().flat_map(|groups| groups.iter()) {
+            let casters: Vec<&Entity> = entities.iter().filter(|entity| culling.count(is_caster(entity))).cloned().collect();
+            if !casters.is_empty() {
+                instanced.push((*model, casters));
+            }
+        }
+
+        let mut animated = Vec::new();
+        match &player.entity {
+            PlayerEntityType::StaticModelEntity(entity) => instanced.push((&entity.model, vec![entity])),
+            PlayerEntityType::AnimatedModelEntity(entity) => animated.push(entity),
+        }
+        ShadowCasters {
+            instanced,
+            animated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animations::{
+        AnimatedModel,
+        Animation,
+        joint::Joint,
+    };
+    use crate::math::{
+        Matrix4f,
+        ModelBounds,
+        Vector3f,
+    };
+    use crate::models::{
+        ModelTexture,
+        RawModel,
+        TextureId,
+    };
+    use crate::renderers::entity_instances::EntityInstances;
+
+    fn model(vao_id: u32) -> TexturedModel {
+        TexturedModel {
+            raw_model: RawModel::new(vao_id, 6),
+            texture: ModelTexture { tex_id: TextureId::Loaded(vao_id), ..Default::default() },
+            normal_map_tex_id: None,
+            extra_info_tex_id: None,
+            pbr_material: None,
+            bounds: ModelBounds::default(),
+            instance_vbo: 0,
+        }
+    }
+
+    fn entity(model: &TexturedModel, x: f32) -> Entity {
+        Entity::new(model.clone(), Vector3f::new(x, 0.0, 0.0), Vector3f::new(0.0, 0.0, 0.0), 1.0)
+    }
+
+    fn animated_player() -> Player {
+        let model = AnimatedModel {
+            raw_model: RawModel::new(10, 6),
+            tex_id: TextureId::Loaded(10),
+            root_joint: Joint::new(0, "root".to_string(), Matrix4f::identity(), None),
+            joint_cnt: 1,
+            animation: Animation::default(),
+        };
+        Player::new_animated(AnimatedEntity::new(model, Vector3f::new(0.0, 0.0, 0.0), Vector3f::new(0.0, 0.0, 0.0), 1.0))
+    }
+
+    #[test]
+    fn test_every_kind_of_entity_casts() {
+        let (tree, barrel, mirror) = (model(1), model(2), model(3));
+        let entities = vec![entity(&tree, 0.0), entity(&tree, 50.0)];
+        let normal_mapped_entities = vec![entity(&barrel, 1.0), entity(&barrel, 60.0)];
+        let entities_with_env_map = vec![entity(&mirror, 2.0)];
+        let groups = [
+            EntityInstances::group_by_model(entities.iter()),
+            EntityInstances::group_by_model(normal_mapped_entities.iter()),
+            EntityInstances::group_by_model(entities_with_env_map.iter()),
+        ];
+        let player = animated_player();
+
+        let mut culling = CullCounter::default();
+        let casters = ShadowCasters::collect(&[&groups[0], &groups[1], &groups[2]], &player, &mut culling, |entity| entity.position.x < 10.0);
+
+        let mut instanced: Vec<_> = casters.instanced.iter().map(|(model, entities)| (model.raw_model.vao_id, entities.len())).collect();
+        instanced.sort();
+        assert_eq!(instanced, vec![(1, 1), (2, 1), (3, 1)]);
+        assert_eq!(casters.animated.len(), 1);
+        assert_eq!(culling, CullCounter { tested: 5, culled: 2 });
+    }
+
+    #[test]
+    fn test_static_player_casts_with_the_entities() {
+        let tree = model(1);
+        let player = Player::new(entity(&model(4), 0.0));
+        let entities = vec![entity(&tree, 100.0)];
+        let groups = EntityInstances::group_by_model(entities.iter());
+
+        let mut culling = CullCounter::default();
+        // the player is drawn even when nothing else is
+        let casters = ShadowCasters::collect(&[&groups], &player, &mut culling, |_| false);
+        assert_eq!(casters.instanced.len(), 1);
+        assert_eq!(casters.instanced[0].0.raw_model.vao_id, 4);
+        assert!(casters.animated.is_empty());
+        assert_eq!(culling, CullCounter { tested: 1, culled: 1 });
+    }
+}
\ No newline at end of file
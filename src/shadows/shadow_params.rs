@@ -1,6 +1,13 @@
+use crate::math::Matrix4f;
+use super::shadow_box::ShadowBox;
+
 #[derive(Debug, Clone)]
 pub struct ShadowParams {
     pub shadow_map_texture: u32,
-    pub shadow_distance: f32,
-    pub shadow_map_size: usize,    
-}
\ No newline at end of file
+    pub shadow_map_size: usize,
+    pub cascade_count: usize,
+    // distance from the camera where every cascade ends, the last one is where the shadows stop
+    pub cascade_splits: [f32; ShadowBox::MAX_CASCADES],
+    // world space to the shadow map texture coords and depth of every cascade
+    pub to_shadowmap_space: [Matrix4f; ShadowBox::MAX_CASCADES],
+}
@@ -69,7 +69,7 @@ pub mod test_utils {
         ($left:expr, $right:expr, absolute=$eps_abs:expr, relative=$eps_rel:expr) => (assert_f32_eq!($left, $right, $eps_abs, $eps_rel, "Floats not equal"););
         ($left:expr, $right:expr, $eps_abs:expr, $eps_rel:expr, $msg:expr) => ({
             let absolute_error = ($left - $right).abs();
-            assert!(absolute_error < $eps_abs, format!("{}. Left: {}, Right: {}. Absolute error: {} >= {}", $msg, $left, $right, absolute_error, $eps_abs));
+            assert!(absolute_error < $eps_abs, "{}. Left: {}, Right: {}. Absolute error: {} >= {}", $msg, $left, $right, absolute_error, $eps_abs);
             let (maxi, mini) = if f32::abs($left) < f32::abs($right) { 
                 (f32::abs($right), f32::abs($left))
             } else { 
@@ -77,7 +77,7 @@ pub mod test_utils {
             };
             if mini != 0.0 {        
                 let relative_error = absolute_error / maxi;
-                assert!(relative_error < $eps_rel, format!("{}. Left: {}, Right: {}. Relative error: {} >= {}", $msg, $left, $right, relative_error, $eps_rel));
+                assert!(relative_error < $eps_rel, "{}. Left: {}, Right: {}. Relative error: {} >= {}", $msg, $left, $right, relative_error, $eps_rel);
             }
         });
         ($left:expr, $right:expr, $eps:expr, $msg:expr) => (assert_f32_eq!($left, $right, $eps, $eps, $msg););
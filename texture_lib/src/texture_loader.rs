@@ -1,6 +1,12 @@
 use std::fmt;
 use std::fs::File;
-use std::io::Error;
+use std::io::{
+    BufRead,
+    BufReader,
+    Error,
+    ErrorKind,
+    Read,
+};
 
 pub struct Texture<PixelType> {
     pub width: usize,
@@ -27,7 +33,16 @@ pub struct RGBA<T> {
     pub a: T,
 }
 
+// repr(C) since the float data is handed straight to the graphics lib as packed rgb triplets
+#[repr(C)]
+pub struct RGB<T> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+}
+
 pub type Texture2DRGBA = Texture<RGBA<u8>>;
+pub type Texture2DRGBF = Texture<RGB<f32>>;
 
 fn create_color_type(buf: &[u8], color_type: &png::ColorType, i: usize) -> RGBA<u8> {
     if *color_type == png::ColorType::RGBA {
@@ -76,4 +91,226 @@ pub fn load_rgba_2d_texture(file_name: &str, reverse: bool) -> Result<Texture2DR
         height: info.height as usize,
         data: result,
     })
-}
\ No newline at end of file
+}
+
+// loads a radiance .hdr (rgbe) image into linear floating point rgb values
+// supports both the flat and the "new" run length encoded scanlines (the old rle format is not supported)
+pub fn load_hdr_2d_texture(file_name: &str, reverse: bool) -> Result<Texture2DRGBF, Error> {
+    let mut reader = BufReader::new(File::open(file_name)?);
+    let (width, height) = read_hdr_header(&mut reader)?;
+
+    let mut result = Vec::with_capacity(width * height);
+    let mut scanline = vec![0u8; 4 * width];
+    for _ in 0..height {
+        read_hdr_scanline(&mut reader, &mut scanline)?;
+        for i in 0..width {
+            result.push(rgbe_to_rgb(&scanline[4*i..4*i+4]));
+        }
+    }
+
+    println!("filename: {}. width: {}. height: {}. format: rgbe", file_name, width, height);
+
+    if reverse {
+        // the standard -Y +X orientation stores rows from top to bottom just like png
+        result.reverse();
+    }
+    Ok(Texture{
+        width,
+        height,
+        data: result,
+    })
+}
+
+fn invalid_hdr(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn read_hdr_header<R: BufRead>(reader: &mut R) -> Result<(usize, usize), Error> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_hdr("Missing radiance signature"));
+    }
+    // header is a list of variables terminated by an empty line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_hdr("Unexpected end of radiance header"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_hdr("Only the 32-bit_rle_rgbe radiance format is supported"));
+        }
+    }
+    // resolution string which we only support in the standard orientation: -Y height +X width
+    line.clear();
+    reader.read_line(&mut line)?;
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+        return Err(invalid_hdr("Unsupported radiance resolution string"));
+    }
+    let height = tokens[1].parse::<usize>().map_err(|_| invalid_hdr("Bad radiance image height"))?;
+    let width = tokens[3].parse::<usize>().map_err(|_| invalid_hdr("Bad radiance image width"))?;
+    Ok((width, height))
+}
+
+fn read_hdr_scanline<R: Read>(reader: &mut R, scanline: &mut [u8]) -> Result<(), Error> {
+    let width = scanline.len() / 4;
+    let mut start = [0u8; 4];
+    reader.read_exact(&mut start)?;
+    let is_rle = (8..32768).contains(&width) && start[0] == 2 && start[1] == 2 && (start[2] & 0x80) == 0;
+    if !is_rle {
+        // flat scanline so the first 4 bytes are already the first pixel
+        scanline[..4].copy_from_slice(&start);
+        return reader.read_exact(&mut scanline[4..]);
+    }
+    if ((start[2] as usize) << 8 | start[3] as usize) != width {
+        return Err(invalid_hdr("Radiance scanline width mismatch"));
+    }
+    // rle scanlines store each of the r,g,b,e channels separately
+    let mut channel = vec![0u8; width];
+    for c in 0..4 {
+        let mut i = 0;
+        while i < width {
+            let mut count = [0u8; 1];
+            reader.read_exact(&mut count)?;
+            let count = count[0] as usize;
+            if count > 128 {
+                let run = count - 128;
+                if run == 0 || i + run > width {
+                    return Err(invalid_hdr("Bad radiance run length"));
+                }
+                let mut value = [0u8; 1];
+                reader.read_exact(&mut value)?;
+                for x in &mut channel[i..i+run] {
+                    *x = value[0];
+                }
+                i += run;
+            } else {
+                if count == 0 || i + count > width {
+                    return Err(invalid_hdr("Bad radiance literal length"));
+                }
+                reader.read_exact(&mut channel[i..i+count])?;
+                i += count;
+            }
+        }
+        for x in 0..width {
+            scanline[4*x + c] = channel[x];
+        }
+    }
+    Ok(())
+}
+
+fn rgbe_to_rgb(rgbe: &[u8]) -> RGB<f32> {
+    if rgbe[3] == 0 {
+        return RGB { r: 0.0, g: 0.0, b: 0.0 };
+    }
+    // shared exponent with a bias of 128 and 8 bits of mantissa
+    let factor = 2f32.powi(rgbe[3] as i32 - (128 + 8));
+    RGB {
+        r: (rgbe[0] as f32 + 0.5) * factor,
+        g: (rgbe[1] as f32 + 0.5) * factor,
+        b: (rgbe[2] as f32 + 0.5) * factor,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn rgb(color: &RGB<f32>) -> (f32, f32, f32) {
+        (color.r, color.g, color.b)
+    }
+
+    #[test]
+    fn test_rgbe_to_rgb() {
+        // exponent 129 scales the mantissas (plus half a step) by 2^(129 - 128 - 8)
+        assert_eq!(rgb(&rgbe_to_rgb(&[128, 64, 0, 129])), (128.5 / 128.0, 64.5 / 128.0, 0.5 / 128.0));
+        assert_eq!(rgb(&rgbe_to_rgb(&[255, 255, 255, 128])), (255.5 / 256.0, 255.5 / 256.0, 255.5 / 256.0));
+        // a zero exponent is black whatever the mantissas say
+        assert_eq!(rgb(&rgbe_to_rgb(&[0, 0, 0, 0])), (0.0, 0.0, 0.0));
+        assert_eq!(rgb(&rgbe_to_rgb(&[200, 100, 50, 0])), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_hdr_header() {
+        let header = "#?RADIANCE\n# made by hand\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n-Y 3 +X 5\n";
+        let mut reader = Cursor::new(header.as_bytes());
+        assert_eq!(read_hdr_header(&mut reader).unwrap(), (5, 3));
+        // the pixels start right after the resolution string
+        assert_eq!(reader.position() as usize, header.len());
+    }
+
+    #[test]
+    fn test_bad_hdr_headers() {
+        let bad_headers = [
+            // not a radiance file at all
+            "P6\n3 5\n255\n",
+            "#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 3 +X 5\n",
+            // the header never ends
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n",
+            // only the standard orientation is supported
+            "#?RADIANCE\n\n+Y 3 +X 5\n",
+            "#?RADIANCE\n\n-Y 3 -X 5\n",
+            "#?RADIANCE\n\n-Y three +X 5\n",
+            "#?RADIANCE\n\n-Y 3\n",
+        ];
+        for header in bad_headers.iter() {
+            let err = read_hdr_header(&mut Cursor::new(header.as_bytes())).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn test_rle_scanline() {
+        let data: Vec<u8> = vec![
+            2, 2, 0, 8,
+            // r is a single run, g all literals, b a run and then literals, e a run
+            128 + 8, 10,
+            8, 1, 2, 3, 4, 5, 6, 7, 8,
+            128 + 4, 0, 4, 9, 8, 7, 6,
+            128 + 8, 129,
+        ];
+        let mut scanline = vec![0u8; 4 * 8];
+        read_hdr_scanline(&mut Cursor::new(data), &mut scanline).unwrap();
+        let pixels: Vec<&[u8]> = scanline.chunks(4).collect();
+        assert_eq!(pixels[0], &[10, 1, 0, 129]);
+        assert_eq!(pixels[3], &[10, 4, 0, 129]);
+        assert_eq!(pixels[4], &[10, 5, 9, 129]);
+        assert_eq!(pixels[7], &[10, 8, 6, 129]);
+    }
+
+    #[test]
+    fn test_flat_scanline() {
+        // scanlines narrower than 8 pixels can't be run length encoded
+        let data: Vec<u8> = (0..12).collect();
+        let mut scanline = vec![0u8; 4 * 3];
+        read_hdr_scanline(&mut Cursor::new(data.clone()), &mut scanline).unwrap();
+        assert_eq!(scanline, data);
+    }
+
+    #[test]
+    fn test_bad_rle_scanlines() {
+        let bad_scanlines: [Vec<u8>; 3] = [
+            // says it's 9 pixels wide
+            vec![2, 2, 0, 9, 128 + 9, 0],
+            // a run past the end of the scanline
+            vec![2, 2, 0, 8, 128 + 9, 0],
+            // a literal with no bytes
+            vec![2, 2, 0, 8, 0],
+        ];
+        for data in bad_scanlines.iter() {
+            let mut scanline = vec![0u8; 4 * 8];
+            let err = read_hdr_scanline(&mut Cursor::new(data.clone()), &mut scanline).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData, "{:?}", data);
+        }
+        // and one that ends too soon
+        let err = read_hdr_scanline(&mut Cursor::new(vec![2, 2, 0, 8, 128 + 8]), &mut [0u8; 4 * 8]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}